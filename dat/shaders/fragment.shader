@@ -1,42 +1,52 @@
 # version 330 core
 
 in vec2 TexCoord;
+in vec4 VertexColor;
 
 out vec4 FragColor;
 
 // LEARN: Read up on uniforms again...
 uniform sampler2D ourTexture;
 uniform bool isText;
-uniform vec3 textColor;
 
-uniform vec4 bounding_box;
+// Must match BlendMode::get_shader_value
+const int blendAlpha = 0;
+const int blendAdditive = 1;
+const int blendMultiply = 2;
+const int blendPremultipliedAlpha = 3;
+uniform int blendMode;
 
 const float width = 0.49;
 const float edge = 0.041;
 
 void main()
 {
+    vec4 color;
+
     if (!isText) {
-        vec2 textureDimensions = textureSize(ourTexture, 0);
+        vec4 tint = VertexColor;
 
-        float x_scale = (bounding_box.x / textureDimensions.x) + (TexCoord.x * (bounding_box.z / textureDimensions.x));
-        float y_scale = (bounding_box.y / textureDimensions.y) + TexCoord.y * (bounding_box.w / textureDimensions.y);
+        // The texture is already premultiplied, so the tint has to be as well
+        if (blendMode == blendPremultipliedAlpha) {
+            tint.rgb *= tint.a;
+        }
 
-        vec2 new_scale = vec2(x_scale, y_scale);
+        color = texture(ourTexture, TexCoord) * tint;
 
-        FragColor = texture(ourTexture, new_scale);
+        if (blendMode == blendMultiply) {
+            color.rgb *= color.a;
+        }
     } else {
-        vec2 textureDimensions = textureSize(ourTexture, 0);
-
-        float x_scale = (bounding_box.x / textureDimensions.x) + (TexCoord.x * (bounding_box.z / textureDimensions.x));
-        float y_scale = (bounding_box.y / textureDimensions.y) + TexCoord.y * (bounding_box.w / textureDimensions.y);
-
-        vec2 new_scale = vec2(x_scale, y_scale);
-
-        float distance = 1.0 - texture(ourTexture, new_scale).a;
+        float distance = 1.0 - texture(ourTexture, TexCoord).a;
 
         float alpha_v = 1.0 - smoothstep(width, width + edge, distance);
 
-        FragColor = vec4(0.0, 0.0, 0.0, alpha_v);
+        color = vec4(VertexColor.rgb, VertexColor.a * alpha_v);
+
+        if (blendMode == blendMultiply || blendMode == blendPremultipliedAlpha) {
+            color.rgb *= color.a;
+        }
     }
+
+    FragColor = color;
 }
\ No newline at end of file
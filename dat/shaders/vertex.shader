@@ -2,14 +2,17 @@
 
 layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec4 aColor;
 
 out vec2 TexCoord;
+out vec4 VertexColor;
 
-uniform mat4 transform;
+// Vertices arrive already transformed into world space by the batch
 uniform mat4 projection;
 
 void main() {
     // LEARN: Read up on vector swizzling
-    gl_Position = projection * transform * vec4(aPos.xyz, 1.0);
+    gl_Position = projection * vec4(aPos.xyz, 1.0);
     TexCoord = aTexCoord;
-}
\ No newline at end of file
+    VertexColor = aColor;
+}
@@ -0,0 +1,116 @@
+use rusty_beagle2d_engine::core::collision::shapes::Aabb;
+use rusty_beagle2d_engine::core::collision::broadphase::Broadphase;
+use rusty_beagle2d_engine::core::collision::grid::SpatialGrid;
+use rusty_beagle2d_engine::core::collision::aabb_tree::AabbTree;
+
+use linear_beaglebra::vector2::Vector2;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const WORLD_SIZE: f32 = 4096.0;
+const COUNTS: [usize; 3] = [100, 1000, 5000];
+
+// Same numbers every run, so runs can be compared
+struct XorShift(u32);
+
+impl XorShift {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
+
+// Objects between 8 and 48 pixels, like sprites in a level
+fn make_boxes(count: usize) -> Vec<Aabb> {
+    let mut random = XorShift(0x2545f491);
+
+    (0..count).map(|_| {
+        let position = Vector2::new(random.range(0.0, WORLD_SIZE), random.range(0.0, WORLD_SIZE));
+        let size = Vector2::new(random.range(8.0, 48.0), random.range(8.0, 48.0));
+        Aabb::new(position, Vector2::new(position.x + size.x, position.y + size.y))
+    }).collect()
+}
+
+fn fill<B: Broadphase<usize>>(mut broadphase: B, boxes: &[Aabb]) -> B {
+    for (index, aabb) in boxes.iter().enumerate() {
+        broadphase.insert(*aabb, index);
+    }
+
+    broadphase
+}
+
+fn bench_broadphase<B: Broadphase<usize>, F: Fn() -> B>(c: &mut Criterion, name: &str, make: F) {
+    let mut group = c.benchmark_group(name);
+
+    for &count in COUNTS.iter() {
+        let boxes = make_boxes(count);
+
+        group.bench_with_input(BenchmarkId::new("insert", count), &boxes, |b, boxes| {
+            b.iter(|| fill(make(), boxes));
+        });
+
+        let mut broadphase = fill(make(), &boxes);
+        let ids: Vec<_> = broadphase.query_region(&Aabb::new(Vector2::new(-1.0, -1.0), Vector2::new(WORLD_SIZE * 2.0, WORLD_SIZE * 2.0)));
+
+        // Everything moves a few pixels, like a frame of a game
+        group.bench_function(BenchmarkId::new("move", count), |b| {
+            let mut frame = 0;
+
+            b.iter(|| {
+                frame += 1;
+                let offset = if frame % 2 == 0 { 2.0 } else { -2.0 };
+
+                for &id in ids.iter() {
+                    let aabb = broadphase.get_aabb(id).unwrap();
+                    broadphase.move_proxy(id, aabb.translated(Vector2::new(offset, offset * 0.5)));
+                }
+            });
+        });
+
+        group.bench_function(BenchmarkId::new("pairs", count), |b| {
+            b.iter(|| {
+                let mut pairs = 0;
+                broadphase.for_each_pair(|_, _| pairs += 1);
+                pairs
+            });
+        });
+
+        let region = Aabb::new(Vector2::new(1000.0, 1000.0), Vector2::new(1640.0, 1360.0));
+
+        group.bench_function(BenchmarkId::new("region", count), |b| {
+            b.iter(|| {
+                let mut found = 0;
+                broadphase.for_each_in_region(&region, |_| found += 1);
+                found
+            });
+        });
+
+        group.bench_function(BenchmarkId::new("raycast", count), |b| {
+            b.iter(|| {
+                broadphase.raycast(Vector2::new(0.0, 10.0), Vector2::new(1.0, 0.7), WORLD_SIZE * 2.0, |id, _| {
+                    let aabb = broadphase.get_aabb(id).unwrap();
+                    Some(aabb.min.x)
+                })
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn grid(c: &mut Criterion) {
+    bench_broadphase(c, "grid", || SpatialGrid::new(64.0));
+}
+
+fn tree(c: &mut Criterion) {
+    bench_broadphase(c, "tree", || AabbTree::new(4.0));
+}
+
+criterion_group!(benches, grid, tree);
+criterion_main!(benches);
@@ -0,0 +1,436 @@
+use crate::core::sprite::Sprite;
+use crate::core::texture_region::TextureRegion;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, Deserialize, Serialize)]
+pub enum PlaybackMode {
+    // First to last frame, then starts over
+    #[default]
+    Loop,
+    // First to last frame, then stays on the last frame
+    Once,
+    // First to last frame and back again, forever
+    PingPong,
+    // Last to first frame and back again, forever
+    PingPongReverse,
+    // Last to first frame, then starts over
+    Reverse,
+    // Last to first frame, then stays on the first frame
+    ReverseOnce
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct AnimationFrame {
+    pub region: TextureRegion,
+    // Seconds the frame stays on screen
+    pub duration: f32,
+    // Names of the events fired when the frame is shown, such as "footstep"
+    #[serde(default)]
+    pub events: Vec<String>
+}
+
+// A named sequence of frames, a "clip".
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct Animation {
+    pub name: String,
+    pub frames: Vec<AnimationFrame>,
+    #[serde(default)]
+    pub playback: PlaybackMode
+}
+
+impl Animation {
+    pub fn get_total_duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    // Clips need frames, and frames need time on screen, or a player would loop forever trying to move past them.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.frames.is_empty() {
+            return Err(format!("Animation '{}' has no frames", self.name));
+        }
+
+        if let Some(index) = self.frames.iter().position(|frame| frame.duration.is_nan() || frame.duration <= 0.0) {
+            return Err(format!("Frame {} of animation '{}' must have a duration above zero", index, self.name));
+        }
+
+        Ok(())
+    }
+}
+
+// Fired by an AnimationPlayer when it shows a frame that has events attached.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AnimationEvent {
+    pub animation: String,
+    pub name: String,
+    pub frame: usize
+}
+
+// The clips loaded from an animation data file, looked up by name.
+//
+// The file is JSON of the form:
+// { "animations": [ { "name": "walk", "playback": "Loop", "frames": [
+//     { "region": { "x": 0, "y": 0, "width": 32, "height": 32 }, "duration": 0.1, "events": ["footstep"] }, ...
+// ] } ] }
+pub struct AnimationLibrary {
+    animations: HashMap<String, Rc<Animation>>
+}
+
+#[derive(Deserialize, Serialize)]
+struct AnimationFile {
+    animations: Vec<Animation>
+}
+
+impl AnimationLibrary {
+    pub fn new() -> AnimationLibrary {
+        AnimationLibrary { animations: HashMap::new() }
+    }
+
+    pub fn load(path: &Path) -> Result<AnimationLibrary, String> {
+        let file_content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read animation file {}: {}", path.display(), error))?;
+
+        AnimationLibrary::from_json(&file_content)
+            .map_err(|error| format!("Failed to load animation file {}: {}", path.display(), error))
+    }
+
+    pub fn from_json(json: &str) -> Result<AnimationLibrary, String> {
+        let animation_file: AnimationFile = serde_json::from_str(json).map_err(|error| error.to_string())?;
+
+        let mut library = AnimationLibrary::new();
+        for animation in animation_file.animations {
+            library.add(animation)?;
+        }
+
+        Ok(library)
+    }
+
+    pub fn add(&mut self, animation: Animation) -> Result<(), String> {
+        animation.validate()?;
+        self.animations.insert(animation.name.clone(), Rc::new(animation));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<Animation>> {
+        self.animations.get(name).cloned()
+    }
+}
+
+// Steps through the frames of an Animation.
+// Advance it with the fixed timestep "dt" of the game loop, so playback speed does not depend on the frame rate.
+pub struct AnimationPlayer {
+    animation: Rc<Animation>,
+    frame_index: usize,
+    // Seconds spent on the current frame
+    frame_time: f32,
+    // +1 or -1, the direction ping-pong playback currently moves in
+    direction: i32,
+    speed: f32,
+    is_playing: bool,
+    is_finished: bool,
+    events: Vec<AnimationEvent>
+}
+
+impl AnimationPlayer {
+    // Fails for clips Animation::validate rejects. Clips from an AnimationLibrary were checked when they were added.
+    pub fn new(animation: Rc<Animation>) -> Result<AnimationPlayer, String> {
+        animation.validate()?;
+
+        let mut player = AnimationPlayer {
+            animation,
+            frame_index: 0,
+            frame_time: 0.0,
+            direction: 1,
+            speed: 1.0,
+            is_playing: true,
+            is_finished: false,
+            events: Vec::new()
+        };
+
+        player.restart();
+        Ok(player)
+    }
+
+    // Switches to another clip. Playing the clip that is already playing does not restart it.
+    // Keeps playing the current clip if the new one fails Animation::validate.
+    pub fn play(&mut self, animation: Rc<Animation>) -> Result<(), String> {
+        if Rc::ptr_eq(&self.animation, &animation) {
+            self.is_playing = true;
+            return Ok(());
+        }
+
+        animation.validate()?;
+
+        self.animation = animation;
+        self.restart();
+        Ok(())
+    }
+
+    pub fn restart(&mut self) {
+        let is_reversed = matches!(self.animation.playback, PlaybackMode::Reverse | PlaybackMode::ReverseOnce | PlaybackMode::PingPongReverse);
+
+        self.frame_index = if is_reversed { self.animation.frames.len().saturating_sub(1) } else { 0 };
+        self.frame_time = 0.0;
+        self.direction = if is_reversed { -1 } else { 1 };
+        self.is_playing = true;
+        self.is_finished = false;
+
+        self.queue_frame_events();
+    }
+
+    pub fn pause(&mut self) {
+        self.is_playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.is_playing = true;
+    }
+
+    // Multiplier on the frame durations. 2.0 plays twice as fast, 0.5 half as fast.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if !self.is_playing || self.is_finished || self.animation.frames.is_empty() {
+            return;
+        }
+
+        self.frame_time += dt * self.speed;
+
+        // A long dt can skip several frames. Each one still fires its events.
+        while !self.is_finished && self.frame_time >= self.get_current_frame().duration {
+            self.frame_time -= self.get_current_frame().duration;
+            self.advance_frame();
+        }
+    }
+
+    fn advance_frame(&mut self) {
+        let frame_count = self.animation.frames.len();
+        let last_frame = frame_count - 1;
+
+        let next_frame = match self.animation.playback {
+            PlaybackMode::Loop => Some((self.frame_index + 1) % frame_count),
+            PlaybackMode::Reverse => Some(if self.frame_index == 0 { last_frame } else { self.frame_index - 1 }),
+            PlaybackMode::Once => if self.frame_index < last_frame { Some(self.frame_index + 1) } else { None },
+            PlaybackMode::ReverseOnce => if self.frame_index > 0 { Some(self.frame_index - 1) } else { None },
+            PlaybackMode::PingPong | PlaybackMode::PingPongReverse => {
+                if frame_count == 1 {
+                    Some(0)
+                } else {
+                    if (self.direction > 0 && self.frame_index == last_frame) || (self.direction < 0 && self.frame_index == 0) {
+                        self.direction = -self.direction;
+                    }
+
+                    Some((self.frame_index as i32 + self.direction) as usize)
+                }
+            }
+        };
+
+        match next_frame {
+            Some(frame_index) => {
+                self.frame_index = frame_index;
+                self.queue_frame_events();
+            },
+            None => {
+                self.is_finished = true;
+                self.frame_time = 0.0;
+            }
+        }
+    }
+
+    fn queue_frame_events(&mut self) {
+        let animation = &self.animation;
+        let frame_index = self.frame_index;
+
+        if let Some(frame) = animation.frames.get(frame_index) {
+            self.events.extend(frame.events.iter().map(|event_name| AnimationEvent {
+                animation: animation.name.clone(),
+                name: event_name.clone(),
+                frame: frame_index
+            }));
+        }
+    }
+
+    // Hands over the events fired since the last call.
+    // LEARN: Drain removes the elements from the vector while iterating, but keeps its allocation.
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, AnimationEvent> {
+        self.events.drain(..)
+    }
+
+    pub fn get_current_frame(&self) -> &AnimationFrame {
+        &self.animation.frames[self.frame_index]
+    }
+
+    pub fn get_frame_index(&self) -> usize {
+        self.frame_index
+    }
+
+    pub fn get_animation(&self) -> &Rc<Animation> {
+        &self.animation
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing && !self.is_finished
+    }
+
+    // Only Once and ReverseOnce animations ever finish.
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    pub fn apply_to(&self, sprite: &mut Sprite) {
+        self.get_current_frame().region.apply_to(sprite);
+    }
+}
+
+// A sprite driven by an AnimationPlayer.
+pub struct AnimatedSprite {
+    pub sprite: Sprite,
+    pub player: AnimationPlayer
+}
+
+impl AnimatedSprite {
+    pub fn new(sprite: Sprite, animation: Rc<Animation>) -> Result<AnimatedSprite, String> {
+        let mut animated_sprite = AnimatedSprite { sprite, player: AnimationPlayer::new(animation)? };
+        animated_sprite.player.apply_to(&mut animated_sprite.sprite);
+        Ok(animated_sprite)
+    }
+
+    pub fn play(&mut self, animation: Rc<Animation>) -> Result<(), String> {
+        self.player.play(animation)?;
+        self.player.apply_to(&mut self.sprite);
+        Ok(())
+    }
+
+    // Advances the animation and updates the sprite's render view to the current frame.
+    pub fn update(&mut self, dt: f32) {
+        self.player.update(dt);
+        self.player.apply_to(&mut self.sprite);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: f32, duration: f32, events: &[&str]) -> AnimationFrame {
+        AnimationFrame {
+            region: TextureRegion::new(x, 0.0, 16.0, 16.0),
+            duration,
+            events: events.iter().map(|event| event.to_string()).collect()
+        }
+    }
+
+    fn clip(name: &str, playback: PlaybackMode, frame_count: usize) -> Rc<Animation> {
+        Rc::new(Animation {
+            name: name.to_string(),
+            frames: (0..frame_count).map(|index| frame(index as f32 * 16.0, 0.1, &[])).collect(),
+            playback
+        })
+    }
+
+    // Frame indices seen after each of "steps" updates of 0.1 seconds.
+    fn play_frames(player: &mut AnimationPlayer, steps: usize) -> Vec<usize> {
+        (0..steps).map(|_| { player.update(0.1001); player.get_frame_index() }).collect()
+    }
+
+    #[test]
+    fn empty_clip_is_rejected() {
+        let empty = Rc::new(Animation { name: "empty".to_string(), frames: Vec::new(), playback: PlaybackMode::Loop });
+
+        assert!(AnimationPlayer::new(empty.clone()).is_err());
+        assert!(AnimationLibrary::new().add((*empty).clone()).is_err());
+    }
+
+    #[test]
+    fn zero_and_negative_durations_are_rejected() {
+        for duration in [0.0, -0.1, f32::NAN] {
+            let animation = Rc::new(Animation {
+                name: "broken".to_string(),
+                frames: vec![frame(0.0, 0.1, &[]), frame(16.0, duration, &[])],
+                playback: PlaybackMode::Loop
+            });
+
+            assert!(AnimationPlayer::new(animation).is_err(), "Duration {} was accepted", duration);
+        }
+    }
+
+    #[test]
+    fn play_keeps_the_current_clip_when_the_new_one_is_invalid() {
+        let mut player = AnimationPlayer::new(clip("walk", PlaybackMode::Loop, 3)).unwrap();
+        player.update(0.15);
+
+        let broken = Rc::new(Animation { name: "broken".to_string(), frames: vec![frame(0.0, 0.0, &[])], playback: PlaybackMode::Loop });
+        assert!(player.play(broken).is_err());
+        assert_eq!(player.get_animation().name, "walk");
+        assert_eq!(player.get_frame_index(), 1);
+    }
+
+    #[test]
+    fn playback_modes() {
+        let mut player = AnimationPlayer::new(clip("loop", PlaybackMode::Loop, 3)).unwrap();
+        assert_eq!(play_frames(&mut player, 5), vec![1, 2, 0, 1, 2]);
+
+        let mut player = AnimationPlayer::new(clip("once", PlaybackMode::Once, 3)).unwrap();
+        assert_eq!(play_frames(&mut player, 4), vec![1, 2, 2, 2]);
+        assert!(player.is_finished());
+
+        let mut player = AnimationPlayer::new(clip("reverse", PlaybackMode::Reverse, 3)).unwrap();
+        assert_eq!(player.get_frame_index(), 2);
+        assert_eq!(play_frames(&mut player, 4), vec![1, 0, 2, 1]);
+
+        let mut player = AnimationPlayer::new(clip("reverse_once", PlaybackMode::ReverseOnce, 3)).unwrap();
+        assert_eq!(play_frames(&mut player, 3), vec![1, 0, 0]);
+        assert!(player.is_finished());
+
+        let mut player = AnimationPlayer::new(clip("ping_pong", PlaybackMode::PingPong, 3)).unwrap();
+        assert_eq!(play_frames(&mut player, 6), vec![1, 2, 1, 0, 1, 2]);
+
+        let mut player = AnimationPlayer::new(clip("ping_pong_reverse", PlaybackMode::PingPongReverse, 3)).unwrap();
+        assert_eq!(player.get_frame_index(), 2);
+        assert_eq!(play_frames(&mut player, 6), vec![1, 0, 1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn long_updates_skip_frames_but_fire_their_events() {
+        let animation = Rc::new(Animation {
+            name: "run".to_string(),
+            frames: vec![frame(0.0, 0.1, &["step_left"]), frame(16.0, 0.1, &[]), frame(32.0, 0.1, &["step_right"])],
+            playback: PlaybackMode::Loop
+        });
+
+        let mut player = AnimationPlayer::new(animation).unwrap();
+        let events: Vec<String> = player.drain_events().map(|event| event.name).collect();
+        assert_eq!(events, vec!["step_left"]);
+
+        player.update(0.3001);
+        assert_eq!(player.get_frame_index(), 0);
+        let events: Vec<(String, usize)> = player.drain_events().map(|event| (event.name, event.frame)).collect();
+        assert_eq!(events, vec![("step_right".to_string(), 2), ("step_left".to_string(), 0)]);
+    }
+
+    #[test]
+    fn speed_and_pause() {
+        let mut player = AnimationPlayer::new(clip("walk", PlaybackMode::Loop, 4)).unwrap();
+        player.set_speed(2.0);
+        player.update(0.1001);
+        assert_eq!(player.get_frame_index(), 2);
+
+        player.pause();
+        player.update(1.0);
+        assert_eq!(player.get_frame_index(), 2);
+
+        player.resume();
+        player.set_speed(-1.0);
+        assert_eq!(player.get_speed(), 0.0);
+    }
+}
@@ -0,0 +1,125 @@
+use rusty_beagle2d_glfw::glfw;
+use rusty_beagle2d_glfw::ogl;
+
+use crate::core::renderer2d::Renderer2d;
+use crate::core::game_loop::{Game, GameLoop};
+use crate::core::time::{Clock, SystemClock};
+use crate::core::color::Color;
+use crate::core::input::{self, WindowEvent};
+
+use std::time::Duration;
+
+// Owns the window and the renderer, and runs a Game in a fixed timestep game loop until the window is closed.
+//
+// let mut app = App::new("My game", 1024, 768);
+// let mut game = MyGame::new(app.get_renderer());
+// app.run(&mut game);
+pub struct App {
+    window: *mut glfw::GLFWwindow,
+    renderer: Renderer2d,
+    game_loop: GameLoop,
+    clear_color: Color,
+    // Reused every frame to hand the window's events to the game
+    events: Vec<WindowEvent>
+}
+
+impl App {
+    // Opens the window. The OpenGL context is current afterwards, so textures can be loaded before calling run.
+    pub fn new(title: &str, width: i32, height: i32) -> App {
+        glfw::init().expect("Failed to initialize GLFW!");
+
+        glfw::window_hint(glfw::WindowHint::Resizable as u32, glfw::GlfwBoolean::False as u32);
+        glfw::window_hint(glfw::WindowHint::OpenGlProfile as u32, glfw::WindowHintValue::OpenGlCoreProfile as u32);
+        glfw::window_hint(glfw::WindowHint::ContextVersionMajor as u32, 3);
+        glfw::window_hint(glfw::WindowHint::ContextVersionMinor as u32, 3);
+        glfw::window_hint(glfw::WindowHint::OpenGlDebugContext as u32, glfw::GlfwBoolean::True as u32);
+
+        let window = glfw::create_window(width, height, String::from(title), None, None).expect("Failed to create main window!");
+
+        glfw::make_context_current(window);
+
+        // Disable v-sync
+        glfw::swap_interval(0);
+
+        input::install_callbacks(window);
+
+        App {
+            window,
+            renderer: Renderer2d::new(),
+            game_loop: GameLoop::new(Box::new(SystemClock::new())),
+            clear_color: Color::new(100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0, 1.0),
+            events: Vec::new()
+        }
+    }
+
+    // Number of fixed updates per second. 60 by default.
+    pub fn with_tick_rate(mut self, ticks_per_second: u32) -> App {
+        self.game_loop = self.game_loop.with_tick_rate(ticks_per_second);
+        self
+    }
+
+    // The longest time a single frame is counted as. 250 milliseconds by default.
+    pub fn with_max_frame_time(mut self, max_frame_time: Duration) -> App {
+        self.game_loop = self.game_loop.with_max_frame_time(max_frame_time);
+        self
+    }
+
+    // Replaces where the game loop gets the time from, for example with a ManualClock.
+    pub fn with_clock(mut self, clock: Box<dyn Clock>) -> App {
+        self.game_loop = self.game_loop.with_clock(clock);
+        self
+    }
+
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    // For calling GLFW directly. Input already arrives through Game::handle_event, see input::install_callbacks.
+    pub fn get_window(&self) -> *mut glfw::GLFWwindow {
+        self.window
+    }
+
+    pub fn get_renderer(&mut self) -> &mut Renderer2d {
+        &mut self.renderer
+    }
+
+    pub fn get_game_loop(&self) -> &GameLoop {
+        &self.game_loop
+    }
+
+    pub fn get_game_loop_mut(&mut self) -> &mut GameLoop {
+        &mut self.game_loop
+    }
+
+    // Runs the game until the window is closed. Each frame:
+    // 1. Read I/O, and hand the window events to the game
+    // 2. Run the fixed updates that are due, then the frame update
+    // 3. Render
+    pub fn run<G: Game>(mut self, game: &mut G) {
+        while !glfw::window_should_close(self.window).expect("Failed to get window should close status.") {
+            // For continuous rendering, poll_events is the best way to process pending events.
+            // This is a non-blocking event processing call.
+            glfw::poll_events();
+
+            input::take_events(&mut self.events);
+            for event in self.events.drain(..) {
+                game.handle_event(&event);
+            }
+
+            let alpha = self.game_loop.run_frame(game);
+
+            ogl::clear_color(self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a);
+            ogl::clear(ogl::ClearMask::ColorBufferBit);
+
+            game.render(&mut self.renderer, alpha);
+            self.renderer.flush();
+            self.renderer.end_frame();
+
+            glfw::swap_buffers(self.window).expect("Failed to swap buffers for window!");
+        }
+
+        // The renderer's OpenGL objects have to go before the context does
+        drop(self.renderer);
+        glfw::terminate();
+    }
+}
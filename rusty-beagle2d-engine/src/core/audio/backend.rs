@@ -0,0 +1,285 @@
+use crate::core::audio::mixer::Mixer;
+use crate::core::audio::wav;
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// Where the mixed audio goes. The mixer is shared with the backend, which pulls frames from it as it needs them.
+// See DeviceBackend for speakers, and NullBackend and WavFileBackend for running without an audio device.
+pub trait AudioBackend {
+    // The sample rate the backend wants the mixer to output.
+    fn get_sample_rate(&self) -> u32;
+
+    // Starts pulling audio from the mixer.
+    fn start(&mut self, mixer: Arc<Mutex<Mixer>>) -> Result<(), String>;
+
+    // Called once per frame with the frame's time in seconds.
+    // Backends without a thread of their own mix "dt" seconds of audio here.
+    fn update(&mut self, _dt: f32) -> Result<(), String> {
+        Ok(())
+    }
+
+    // Errors that happened while playing on a thread of the backend's own, since the last call.
+    // For example the audio device being unplugged.
+    fn take_errors(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+// Mixes from the game thread as time passes, one update at a time.
+// Keeps track of the fraction of a frame left over, so no audio time is lost between updates.
+struct FramePuller {
+    sample_rate: u32,
+    mixer: Option<Arc<Mutex<Mixer>>>,
+    buffer: Vec<f32>,
+    leftover: f64
+}
+
+impl FramePuller {
+    fn new(sample_rate: u32) -> FramePuller {
+        if sample_rate == 0 {
+            panic!("The sample rate must be more than 0");
+        }
+
+        FramePuller {
+            sample_rate,
+            mixer: None,
+            buffer: Vec::new(),
+            leftover: 0.0
+        }
+    }
+
+    // The stereo samples of "dt" seconds of audio.
+    fn pull_seconds(&mut self, dt: f32) -> &[f32] {
+        let frames = dt.max(0.0) as f64 * self.sample_rate as f64 + self.leftover;
+        self.leftover = frames.fract();
+
+        self.pull_frames(frames as usize)
+    }
+
+    fn pull_frames(&mut self, frame_count: usize) -> &[f32] {
+        self.buffer.resize(frame_count * 2, 0.0);
+
+        match &self.mixer {
+            Some(mixer) => mixer.lock().expect("The audio mixer was poisoned").mix(&mut self.buffer),
+            None => {
+                for sample in self.buffer.iter_mut() {
+                    *sample = 0.0;
+                }
+            }
+        }
+
+        &self.buffer
+    }
+}
+
+// Mixes and throws the audio away. Voices still play, end and loop as they would on a device,
+// Which is handy for headless builds and servers.
+pub struct NullBackend {
+    puller: FramePuller
+}
+
+impl NullBackend {
+    pub fn new(sample_rate: u32) -> NullBackend {
+        NullBackend {
+            puller: FramePuller::new(sample_rate)
+        }
+    }
+
+    // Mixes exactly "frame_count" frames, independent of time. Returns the stereo samples.
+    pub fn mix_frames(&mut self, frame_count: usize) -> &[f32] {
+        self.puller.pull_frames(frame_count)
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn get_sample_rate(&self) -> u32 {
+        self.puller.sample_rate
+    }
+
+    fn start(&mut self, mixer: Arc<Mutex<Mixer>>) -> Result<(), String> {
+        self.puller.mixer = Some(mixer);
+        Ok(())
+    }
+
+    fn update(&mut self, dt: f32) -> Result<(), String> {
+        self.puller.pull_seconds(dt);
+        Ok(())
+    }
+}
+
+// Writes everything that is mixed to a stereo 32 bit float WAV file.
+// As mixing only depends on the update times, the same updates always write the same file.
+pub struct WavFileBackend {
+    path: PathBuf,
+    puller: FramePuller,
+    writer: Option<BufWriter<File>>,
+    sample_count: usize
+}
+
+impl WavFileBackend {
+    // Creates (or replaces) the file.
+    pub fn new(path: &Path, sample_rate: u32) -> Result<WavFileBackend, String> {
+        let file = File::create(path)
+            .map_err(|error| format!("Failed to create WAV file {}: {}", path.display(), error))?;
+
+        let mut backend = WavFileBackend {
+            path: path.to_path_buf(),
+            puller: FramePuller::new(sample_rate),
+            writer: Some(BufWriter::new(file)),
+            sample_count: 0
+        };
+
+        // The sizes in the header are filled in by finish
+        backend.write_header()?;
+
+        Ok(backend)
+    }
+
+    // Mixes exactly "frame_count" frames into the file, independent of time.
+    pub fn mix_frames(&mut self, frame_count: usize) -> Result<(), String> {
+        self.puller.pull_frames(frame_count);
+        self.write_buffer()
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    // Number of frames written so far.
+    pub fn get_frame_count(&self) -> usize {
+        self.sample_count / 2
+    }
+
+    // Writes the final sizes into the header and closes the file. Also done when the backend is dropped,
+    // But errors are lost then.
+    pub fn finish(&mut self) -> Result<(), String> {
+        if self.writer.is_none() {
+            return Ok(());
+        }
+
+        self.write_header()?;
+
+        let writer = self.writer.take().unwrap();
+        writer.into_inner()
+            .map_err(|error| error.to_string())
+            .and_then(|file| file.sync_all().map_err(|error| error.to_string()))
+            .map_err(|error| format!("Failed to finish WAV file {}: {}", self.path.display(), error))
+    }
+
+    fn write_header(&mut self) -> Result<(), String> {
+        let mut header = Vec::with_capacity(44);
+        wav::write_wav_header(&mut header, self.puller.sample_rate, 2, self.sample_count);
+
+        let writer = self.writer.as_mut().ok_or("The WAV file is already finished")?;
+
+        writer.seek(SeekFrom::Start(0))
+            .and_then(|_| writer.write_all(&header))
+            .and_then(|_| writer.seek(SeekFrom::End(0)))
+            .map(|_| ())
+            .map_err(|error| format!("Failed to write WAV file {}: {}", self.path.display(), error))
+    }
+
+    fn write_buffer(&mut self) -> Result<(), String> {
+        let path = &self.path;
+        let writer = self.writer.as_mut().ok_or("The WAV file is already finished")?;
+
+        for sample in self.puller.buffer.iter() {
+            writer.write_all(&sample.to_le_bytes())
+                .map_err(|error| format!("Failed to write WAV file {}: {}", path.display(), error))?;
+        }
+
+        self.sample_count += self.puller.buffer.len();
+        Ok(())
+    }
+}
+
+impl AudioBackend for WavFileBackend {
+    fn get_sample_rate(&self) -> u32 {
+        self.puller.sample_rate
+    }
+
+    fn start(&mut self, mixer: Arc<Mutex<Mixer>>) -> Result<(), String> {
+        self.puller.mixer = Some(mixer);
+        Ok(())
+    }
+
+    fn update(&mut self, dt: f32) -> Result<(), String> {
+        self.puller.pull_seconds(dt);
+        self.write_buffer()
+    }
+}
+
+impl Drop for WavFileBackend {
+    fn drop(&mut self) {
+        // Nowhere to report errors from here, call finish to find out if the file was written
+        let _ = self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::audio::mixer::PlaybackSettings;
+    use crate::core::audio::sound::Sound;
+
+    const SAMPLE_RATE: u32 = 100;
+
+    fn create_mixer() -> Arc<Mutex<Mixer>> {
+        let samples: Vec<f32> = (0..50).map(|index| (index as f32 * 0.3).sin()).collect();
+        let sound = Arc::new(Sound::new(SAMPLE_RATE, 1, samples));
+
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        mixer.play(&sound, PlaybackSettings::new().with_pitch(0.8).with_pan(0.25).looping());
+        Arc::new(Mutex::new(mixer))
+    }
+
+    // Frame times that don't add up to whole audio frames
+    const FRAME_TIMES: [f32; 5] = [0.016, 0.017, 0.0333, 0.25, 0.0];
+
+    fn write_wav(name: &str) -> Vec<u8> {
+        let path = std::env::temp_dir().join(name);
+        let mut backend = WavFileBackend::new(&path, SAMPLE_RATE).unwrap();
+        backend.start(create_mixer()).unwrap();
+
+        for &dt in FRAME_TIMES.iter() {
+            backend.update(dt).unwrap();
+        }
+
+        backend.mix_frames(3).unwrap();
+        backend.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn wav_files_are_deterministic() {
+        let bytes = write_wav("rusty_beagle2d_backend_a.wav");
+        assert_eq!(bytes, write_wav("rusty_beagle2d_backend_b.wav"));
+
+        // No audio time is lost between updates
+        let total_time: f32 = FRAME_TIMES.iter().sum();
+        let frame_count = (total_time * SAMPLE_RATE as f32) as usize + 3;
+
+        let sound = wav::decode_wav(&bytes).unwrap();
+        assert_eq!(sound.get_channels(), 2);
+        assert_eq!(sound.get_sample_rate(), SAMPLE_RATE);
+        assert_eq!(sound.get_frame_count(), frame_count);
+
+        // And the file holds the same audio as mixing all of it at once
+        let mut null_backend = NullBackend::new(SAMPLE_RATE);
+        null_backend.start(create_mixer()).unwrap();
+        assert_eq!(sound.get_samples(), null_backend.mix_frames(frame_count));
+    }
+
+    #[test]
+    fn backends_without_a_mixer_output_silence() {
+        let mut backend = NullBackend::new(SAMPLE_RATE);
+        assert_eq!(backend.mix_frames(2), &[0.0; 4]);
+        assert!(backend.update(1.0).is_ok());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::core::audio::mixer::{self, Mixer};
+use crate::core::audio::backend::AudioBackend;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+
+use std::sync::{Arc, Mutex};
+
+// Plays the mix on the system's default output device, through cpal.
+// The device asks for audio on its own thread, so mixing happens there and update has nothing to do.
+pub struct DeviceBackend {
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    // Playback stops when the stream is dropped
+    stream: Option<cpal::Stream>,
+    // Filled by the stream's error callback on the audio thread
+    errors: Arc<Mutex<Vec<String>>>
+}
+
+impl DeviceBackend {
+    pub fn new() -> Result<DeviceBackend, String> {
+        let host = cpal::default_host();
+
+        let device = host.default_output_device()
+            .ok_or("No audio output device found")?;
+
+        let config = device.default_output_config()
+            .map_err(|error| format!("Failed to get the audio output format: {}", error))?;
+
+        Ok(DeviceBackend {
+            device,
+            config,
+            stream: None,
+            errors: Arc::new(Mutex::new(Vec::new()))
+        })
+    }
+}
+
+impl AudioBackend for DeviceBackend {
+    fn get_sample_rate(&self) -> u32 {
+        self.config.sample_rate().0
+    }
+
+    fn start(&mut self, mixer: Arc<Mutex<Mixer>>) -> Result<(), String> {
+        let config = self.config.config();
+
+        let stream = match self.config.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&self.device, &config, mixer, Arc::clone(&self.errors))?,
+            SampleFormat::I16 => build_stream::<i16>(&self.device, &config, mixer, Arc::clone(&self.errors))?,
+            SampleFormat::U16 => build_stream::<u16>(&self.device, &config, mixer, Arc::clone(&self.errors))?,
+            format => return Err(format!("Unsupported audio output format {}", format))
+        };
+
+        stream.play()
+            .map_err(|error| format!("Failed to start audio playback: {}", error))?;
+
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn take_errors(&mut self) -> Vec<String> {
+        match self.errors.lock() {
+            Ok(mut errors) => std::mem::take(&mut *errors),
+            Err(_) => Vec::new()
+        }
+    }
+}
+
+fn build_stream<T: SizedSample + FromSample<f32>>(device: &cpal::Device, config: &cpal::StreamConfig, mixer: Arc<Mutex<Mixer>>, errors: Arc<Mutex<Vec<String>>>) -> Result<cpal::Stream, String> {
+    let channels = config.channels as usize;
+    let mut buffer: Vec<f32> = Vec::new();
+
+    let data_callback = move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+        let frame_count = data.len() / channels;
+        buffer.resize(frame_count * 2, 0.0);
+
+        // If the game thread panicked while holding the mixer, play silence
+        match mixer.lock() {
+            Ok(mut mixer) => mixer.mix(&mut buffer),
+            Err(_) => {
+                for sample in buffer.iter_mut() {
+                    *sample = 0.0;
+                }
+            }
+        }
+
+        // The mix is stereo. Mono devices get both sides together, and extra channels (like surround) stay silent.
+        for (frame, stereo) in data.chunks_exact_mut(channels).zip(buffer.chunks_exact(2)) {
+            if channels == 1 {
+                frame[0] = T::from_sample((stereo[0] + stereo[1]) * 0.5);
+                continue;
+            }
+
+            frame[0] = T::from_sample(stereo[0]);
+            frame[1] = T::from_sample(stereo[1]);
+
+            for sample in frame[2..].iter_mut() {
+                *sample = T::EQUILIBRIUM;
+            }
+        }
+    };
+
+    let error_callback = move |error| {
+        if let Ok(mut errors) = errors.lock() {
+            if errors.len() < mixer::MAX_ERRORS {
+                errors.push(format!("Audio output error: {}", error));
+            }
+        }
+    };
+
+    device.build_output_stream(config, data_callback, error_callback, None)
+        .map_err(|error| format!("Failed to open the audio output stream: {}", error))
+}
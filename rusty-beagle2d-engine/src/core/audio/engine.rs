@@ -0,0 +1,180 @@
+use crate::core::audio::mixer::{Mixer, Bus, PlaybackSettings, VoiceId};
+use crate::core::audio::backend::AudioBackend;
+use crate::core::audio::sound::{self, Sound, AudioStream};
+use crate::core::audio::spatial::SpatialSettings;
+use crate::core::renderer2d::Renderer2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Ties a mixer to a backend. The sound side of the engine:
+//
+// let mut audio = AudioEngine::new(Box::new(DeviceBackend::new()?))?;
+// let jump = load_sound(Path::new("resources/sounds/jump.wav"))?;
+// audio.play(&jump, PlaybackSettings::new().with_volume(0.8));
+// audio.play_music(Path::new("resources/music/level1.ogg"), PlaybackSettings::new().looping())?;
+//
+// Call update once per frame, and update_listener after the camera has moved.
+// NOTE: DeviceBackend is only there with the "audio-device" feature.
+pub struct AudioEngine {
+    mixer: Arc<Mutex<Mixer>>,
+    backend: Box<dyn AudioBackend>,
+    // Whether update_listener moves the listener to the camera
+    follow_camera: bool
+}
+
+impl AudioEngine {
+    pub fn new(mut backend: Box<dyn AudioBackend>) -> Result<AudioEngine, String> {
+        let mixer = Arc::new(Mutex::new(Mixer::new(backend.get_sample_rate())));
+        backend.start(Arc::clone(&mixer))?;
+
+        Ok(AudioEngine {
+            mixer,
+            backend,
+            follow_camera: true
+        })
+    }
+
+    // Lets the backend mix, for backends that run on the game thread.
+    pub fn update(&mut self, dt: f32) -> Result<(), String> {
+        self.backend.update(dt)
+    }
+
+    // What went wrong while playing since the last call: voices stopped because they failed to play,
+    // For example a corrupt Ogg file, and errors of the backend. See Mixer::take_errors for which voice failed.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        let mut errors: Vec<String> = self.lock_mixer().take_errors().into_iter()
+            .map(|(_, error)| format!("Stopped a voice that failed to play: {}", error))
+            .collect();
+
+        errors.extend(self.backend.take_errors());
+        errors
+    }
+
+    // Moves the listener to the middle of the renderer's view, unless it was placed with set_listener_position.
+    // The view follows Renderer2d::set_camera_position, and with that the Camera entity (ecs::systems::apply_camera)
+    // Or the camera node of a drawn scene (Renderer2d::draw_scene).
+    pub fn update_listener(&mut self, renderer: &Renderer2d) {
+        if self.follow_camera {
+            self.lock_mixer().set_listener_position(renderer.get_view_center());
+        }
+    }
+
+    // Places the listener by hand. It stays there until follow_camera is called.
+    pub fn set_listener_position(&mut self, position: Vector2) {
+        self.follow_camera = false;
+        self.lock_mixer().set_listener_position(position);
+    }
+
+    pub fn get_listener_position(&self) -> Vector2 {
+        self.lock_mixer().get_listener_position()
+    }
+
+    pub fn follow_camera(&mut self) {
+        self.follow_camera = true;
+    }
+
+    pub fn is_following_camera(&self) -> bool {
+        self.follow_camera
+    }
+
+    pub fn get_backend(&self) -> &dyn AudioBackend {
+        self.backend.as_ref()
+    }
+
+    // For everything the engine doesn't forward. Keep the lock short, the audio device waits on it.
+    pub fn lock_mixer(&self) -> MutexGuard<'_, Mixer> {
+        self.mixer.lock().expect("The audio mixer was poisoned")
+    }
+
+    pub fn play(&mut self, sound: &Arc<Sound>, settings: PlaybackSettings) -> VoiceId {
+        self.lock_mixer().play(sound, settings)
+    }
+
+    // Plays the sound at a position in the world. Uses the settings' SpatialSettings if it has them, or the defaults.
+    pub fn play_at(&mut self, sound: &Arc<Sound>, position: Vector2, settings: PlaybackSettings) -> VoiceId {
+        let mut spatial = settings.spatial.unwrap_or_else(|| SpatialSettings::new(position));
+        spatial.position = position;
+
+        self.play(sound, settings.with_spatial(spatial))
+    }
+
+    pub fn play_stream(&mut self, stream: Box<dyn AudioStream>, settings: PlaybackSettings) -> VoiceId {
+        self.lock_mixer().play_stream(stream, settings)
+    }
+
+    // Streams a music file on the music bus.
+    pub fn play_music(&mut self, path: &Path, settings: PlaybackSettings) -> Result<VoiceId, String> {
+        let stream = sound::open_stream(path)?;
+        Ok(self.play_stream(stream, settings.with_bus(Bus::Music)))
+    }
+
+    pub fn stop(&mut self, id: VoiceId) -> bool {
+        self.lock_mixer().stop(id)
+    }
+
+    pub fn stop_all(&mut self) {
+        self.lock_mixer().stop_all();
+    }
+
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.lock_mixer().is_playing(id)
+    }
+
+    pub fn pause(&mut self, id: VoiceId) {
+        self.lock_mixer().pause(id);
+    }
+
+    pub fn resume(&mut self, id: VoiceId) {
+        self.lock_mixer().resume(id);
+    }
+
+    pub fn set_volume(&mut self, id: VoiceId, volume: f32) {
+        self.lock_mixer().set_volume(id, volume);
+    }
+
+    pub fn set_pitch(&mut self, id: VoiceId, pitch: f32) {
+        self.lock_mixer().set_pitch(id, pitch);
+    }
+
+    pub fn set_pan(&mut self, id: VoiceId, pan: f32) {
+        self.lock_mixer().set_pan(id, pan);
+    }
+
+    // Moves a voice played with play_at (or with SpatialSettings).
+    pub fn set_position(&mut self, id: VoiceId, position: Vector2) {
+        self.lock_mixer().set_position(id, position);
+    }
+
+    pub fn set_priority(&mut self, id: VoiceId, priority: i32) {
+        self.lock_mixer().set_priority(id, priority);
+    }
+
+    // False if the voice was culled in the last mix, because it was out of range or too many voices played.
+    pub fn is_audible(&self, id: VoiceId) -> bool {
+        self.lock_mixer().is_audible(id)
+    }
+
+    // How many voices are heard at once. 32 by default.
+    pub fn set_max_voices(&mut self, max_voices: usize) {
+        self.lock_mixer().set_max_voices(max_voices);
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.lock_mixer().set_bus_volume(bus, volume);
+    }
+
+    pub fn get_bus_volume(&self, bus: Bus) -> f32 {
+        self.lock_mixer().get_bus_volume(bus)
+    }
+
+    pub fn set_bus_paused(&mut self, bus: Bus, paused: bool) {
+        self.lock_mixer().set_bus_paused(bus, paused);
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.lock_mixer().set_master_volume(volume);
+    }
+}
@@ -0,0 +1,1101 @@
+use crate::core::audio::sound::{Sound, AudioStream};
+use crate::core::audio::spatial::SpatialSettings;
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+// Groups of voices whose volume can be set together, like in an options menu.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Ui
+}
+
+impl Bus {
+    pub const ALL: [Bus; 3] = [Bus::Music, Bus::Sfx, Bus::Ui];
+
+    fn get_index(self) -> usize {
+        match self {
+            Bus::Music => 0,
+            Bus::Sfx => 1,
+            Bus::Ui => 2
+        }
+    }
+}
+
+// How a sound is played. Start from PlaybackSettings::new() and change what's needed:
+// PlaybackSettings::new().with_volume(0.5).looping()
+#[derive(Copy, Clone, Debug)]
+pub struct PlaybackSettings {
+    // 1.0 plays the sound as loud as it was recorded
+    pub volume: f32,
+    // Speed, which also shifts the pitch. 2.0 plays twice as fast, an octave higher.
+    pub pitch: f32,
+    // -1.0 is fully left, 0.0 the center and 1.0 fully right
+    pub pan: f32,
+    pub looping: bool,
+    pub bus: Bus,
+    // When more voices play than the mixer's max voices, the ones with the lowest priority are culled first
+    pub priority: i32,
+    // Plays the sound at a position in the world, see SpatialSettings. None plays it as is.
+    pub spatial: Option<SpatialSettings>
+}
+
+impl PlaybackSettings {
+    pub fn new() -> PlaybackSettings {
+        PlaybackSettings {
+            volume: 1.0,
+            pitch: 1.0,
+            pan: 0.0,
+            looping: false,
+            bus: Bus::Sfx,
+            priority: 0,
+            spatial: None
+        }
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> PlaybackSettings {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> PlaybackSettings {
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn with_pan(mut self, pan: f32) -> PlaybackSettings {
+        self.pan = pan;
+        self
+    }
+
+    pub fn with_bus(mut self, bus: Bus) -> PlaybackSettings {
+        self.bus = bus;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> PlaybackSettings {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_spatial(mut self, spatial: SpatialSettings) -> PlaybackSettings {
+        self.spatial = Some(spatial);
+        self
+    }
+
+    pub fn looping(mut self) -> PlaybackSettings {
+        self.looping = true;
+        self
+    }
+}
+
+// Refers to a playing sound. Stays valid after the sound ends, it just no longer does anything.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct VoiceId {
+    index: u32,
+    generation: u32
+}
+
+// Frames read from a stream at a time
+const STREAM_CHUNK_FRAMES: usize = 4096;
+
+struct StreamSource {
+    stream: Box<dyn AudioStream>,
+    channels: usize,
+    // Decoded frames, starting at "first_frame" counted from the start of the stream
+    frames: Vec<f32>,
+    first_frame: usize,
+    ended: bool
+}
+
+enum Source {
+    Buffer(Arc<Sound>),
+    Stream(StreamSource)
+}
+
+impl Source {
+    fn get_sample_rate(&self) -> u32 {
+        match self {
+            Source::Buffer(sound) => sound.get_sample_rate(),
+            Source::Stream(stream) => stream.stream.get_sample_rate()
+        }
+    }
+
+    // The frame as left and right samples, or None past the end.
+    // Mono plays on both sides, and only the first two channels of sounds with more are used.
+    fn get_frame(&mut self, index: usize) -> Result<Option<(f32, f32)>, String> {
+        let (samples, channels, frame_index) = match self {
+            Source::Buffer(sound) => (sound.get_samples(), sound.get_channels() as usize, index),
+            Source::Stream(stream) => {
+                stream.fill_to(index)?;
+                (&stream.frames[..], stream.channels, index - stream.first_frame)
+            }
+        };
+
+        let start = frame_index * channels;
+
+        if start + channels > samples.len() {
+            return Ok(None);
+        }
+
+        if channels == 1 {
+            Ok(Some((samples[start], samples[start])))
+        } else {
+            Ok(Some((samples[start], samples[start + 1])))
+        }
+    }
+
+    // Number of frames in the source. Streams only know once they were read to the end.
+    fn get_frame_count(&self) -> Option<usize> {
+        match self {
+            Source::Buffer(sound) => Some(sound.get_frame_count()),
+            Source::Stream(stream) if stream.ended => Some(stream.first_frame + stream.frames.len() / stream.channels),
+            Source::Stream(_) => None
+        }
+    }
+
+    // Goes back to the first frame.
+    fn restart(&mut self) -> Result<(), String> {
+        if let Source::Stream(stream) = self {
+            stream.stream.rewind()?;
+            stream.frames.clear();
+            stream.first_frame = 0;
+            stream.ended = false;
+        }
+
+        Ok(())
+    }
+
+    // Lets streams drop the frames before "index", which won't be played again.
+    fn release_before(&mut self, index: usize) {
+        if let Source::Stream(stream) = self {
+            let frame_count = stream.frames.len() / stream.channels;
+            let released = index.saturating_sub(stream.first_frame).min(frame_count);
+
+            stream.frames.drain(..released * stream.channels);
+            stream.first_frame += released;
+        }
+    }
+}
+
+impl StreamSource {
+    // Decodes until the frame at "index" is in memory, or the stream ends.
+    fn fill_to(&mut self, index: usize) -> Result<(), String> {
+        while !self.ended && index >= self.first_frame + self.frames.len() / self.channels {
+            let old_length = self.frames.len();
+            self.frames.resize(old_length + STREAM_CHUNK_FRAMES * self.channels, 0.0);
+
+            let read = self.stream.read(&mut self.frames[old_length..])?;
+
+            // Only keep whole frames
+            self.frames.truncate(old_length + read - read % self.channels);
+
+            if read == 0 {
+                self.ended = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Voice {
+    generation: u32,
+    source: Source,
+    settings: PlaybackSettings,
+    // In frames of the source, with a fraction for playing at other speeds and sample rates
+    position: f64,
+    paused: bool,
+    // Gains of the last mix, None before the first
+    gains: Option<(f32, f32)>,
+    // Left and right gains, cutoff and loudness for the coming mix. See update_target.
+    target: (f32, f32),
+    cutoff: Option<f32>,
+    loudness: f32,
+    // Last output of the low pass filter
+    filter: (f32, f32),
+    // Whether it was heard in the last mix, or culled
+    audible: bool
+}
+
+impl Voice {
+    // Works out how the voice should sound, with "gain" as the bus and master volume.
+    fn update_target(&mut self, gain: f32, listener_position: Vector2) {
+        let mut gain = self.settings.volume * gain;
+        let mut pan = self.settings.pan;
+        self.cutoff = None;
+
+        if let Some(spatial) = &self.settings.spatial {
+            gain *= spatial.get_gain(listener_position);
+            pan += spatial.get_pan(listener_position);
+
+            if let Some(low_pass) = &spatial.low_pass {
+                self.cutoff = Some(low_pass.get_cutoff(spatial.get_distance_fraction(listener_position)));
+            }
+        }
+
+        // LEARN: Balance panning
+        // Panning turns down the side it moves away from, and leaves the other as it is.
+        // So a centered sound plays at full volume on both sides.
+        let pan = pan.clamp(-1.0, 1.0);
+        self.target = (gain * (1.0 - pan).min(1.0), gain * (1.0 + pan).min(1.0));
+        self.loudness = gain.abs();
+    }
+
+    // How far to move through the source for each output frame.
+    fn get_step(&self, output_sample_rate: u32) -> f64 {
+        self.settings.pitch.max(0.0) as f64 * self.source.get_sample_rate() as f64 / output_sample_rate as f64
+    }
+}
+
+// LEARN: Software mixing
+// Sound hardware wants one stream of samples. The mixer adds up all playing sounds ("voices") into that stream,
+// Each one scaled by its volume and panned. Voices with another sample rate or pitch are stepped through
+// Faster or slower than one frame per output frame, blending between the two nearest frames (linear interpolation).
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Option<Voice>>,
+    // Bumped when a voice slot is reused, so old VoiceIds don't affect the new voice
+    generations: Vec<u32>,
+    bus_volumes: [f32; 3],
+    bus_paused: [bool; 3],
+    master_volume: f32,
+    // Where the spatial voices are heard from
+    listener_position: Vector2,
+    max_voices: usize,
+    // Reused by mix to sort the voices by importance
+    mix_order: Vec<usize>,
+    // Voices that failed to play, for example from a corrupt Ogg file, and why. Kept until take_errors.
+    errors: Vec<(VoiceId, String)>
+}
+
+// At most this many errors are kept, so a game that never takes them doesn't use more and more memory
+pub(crate) const MAX_ERRORS: usize = 32;
+
+impl Mixer {
+    // "sample_rate" is the rate of the output, usually the audio device's.
+    pub fn new(sample_rate: u32) -> Mixer {
+        if sample_rate == 0 {
+            panic!("The mixer's sample rate must be more than 0");
+        }
+
+        Mixer {
+            sample_rate,
+            voices: Vec::new(),
+            generations: Vec::new(),
+            bus_volumes: [1.0; 3],
+            bus_paused: [false; 3],
+            master_volume: 1.0,
+            listener_position: Vector2::new(0.0, 0.0),
+            max_voices: 32,
+            mix_order: Vec::new(),
+            errors: Vec::new()
+        }
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // The voices that were stopped because they failed to play since the last call, with the reason.
+    // A voice that ended early shows up here, one that was just quiet doesn't.
+    pub fn take_errors(&mut self) -> Vec<(VoiceId, String)> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn play(&mut self, sound: &Arc<Sound>, settings: PlaybackSettings) -> VoiceId {
+        self.add_voice(Source::Buffer(Arc::clone(sound)), settings)
+    }
+
+    pub fn play_stream(&mut self, stream: Box<dyn AudioStream>, settings: PlaybackSettings) -> VoiceId {
+        let channels = stream.get_channels() as usize;
+
+        if channels == 0 || stream.get_sample_rate() == 0 {
+            panic!("A stream needs a sample rate and at least one channel");
+        }
+
+        self.add_voice(Source::Stream(StreamSource {
+            stream,
+            channels,
+            frames: Vec::new(),
+            first_frame: 0,
+            ended: false
+        }), settings)
+    }
+
+    // Returns false if the voice had already ended.
+    pub fn stop(&mut self, id: VoiceId) -> bool {
+        if self.get_voice(id).is_none() {
+            return false;
+        }
+
+        self.voices[id.index as usize] = None;
+        true
+    }
+
+    pub fn stop_all(&mut self) {
+        for voice in self.voices.iter_mut() {
+            *voice = None;
+        }
+    }
+
+    pub fn stop_bus(&mut self, bus: Bus) {
+        for voice in self.voices.iter_mut() {
+            if voice.as_ref().is_some_and(|voice| voice.settings.bus == bus) {
+                *voice = None;
+            }
+        }
+    }
+
+    // True until the voice ends or is stopped. Paused voices are still playing.
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.get_voice(id).is_some()
+    }
+
+    pub fn pause(&mut self, id: VoiceId) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.paused = true;
+        }
+    }
+
+    pub fn resume(&mut self, id: VoiceId) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.paused = false;
+        }
+    }
+
+    pub fn is_paused(&self, id: VoiceId) -> bool {
+        self.get_voice(id).is_some_and(|voice| voice.paused)
+    }
+
+    pub fn set_volume(&mut self, id: VoiceId, volume: f32) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.settings.volume = volume;
+        }
+    }
+
+    pub fn set_pitch(&mut self, id: VoiceId, pitch: f32) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.settings.pitch = pitch;
+        }
+    }
+
+    pub fn set_pan(&mut self, id: VoiceId, pan: f32) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.settings.pan = pan;
+        }
+    }
+
+    pub fn set_looping(&mut self, id: VoiceId, looping: bool) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.settings.looping = looping;
+        }
+    }
+
+    pub fn set_priority(&mut self, id: VoiceId, priority: i32) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.settings.priority = priority;
+        }
+    }
+
+    // Moves a spatial voice. Does nothing to voices played without SpatialSettings.
+    pub fn set_position(&mut self, id: VoiceId, position: Vector2) {
+        if let Some(spatial) = self.get_voice_mut(id).and_then(|voice| voice.settings.spatial.as_mut()) {
+            spatial.position = position;
+        }
+    }
+
+    // Turns a voice into a spatial one, or back with None.
+    pub fn set_spatial(&mut self, id: VoiceId, spatial: Option<SpatialSettings>) {
+        if let Some(voice) = self.get_voice_mut(id) {
+            voice.settings.spatial = spatial;
+        }
+    }
+
+    // False if the voice was culled (or out of range) in the last mix, or ended.
+    pub fn is_audible(&self, id: VoiceId) -> bool {
+        self.get_voice(id).is_some_and(|voice| voice.audible)
+    }
+
+    // The settings the voice plays with now, or None if it ended.
+    pub fn get_settings(&self, id: VoiceId) -> Option<PlaybackSettings> {
+        self.get_voice(id).map(|voice| voice.settings)
+    }
+
+    // Where the voice is, in seconds from the start of the sound.
+    pub fn get_playback_position(&self, id: VoiceId) -> Option<f32> {
+        self.get_voice(id).map(|voice| (voice.position / voice.source.get_sample_rate() as f64) as f32)
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.bus_volumes[bus.get_index()] = volume;
+    }
+
+    pub fn get_bus_volume(&self, bus: Bus) -> f32 {
+        self.bus_volumes[bus.get_index()]
+    }
+
+    // Pauses or resumes every voice on the bus, for example all sound effects while the game is paused.
+    pub fn set_bus_paused(&mut self, bus: Bus, paused: bool) {
+        self.bus_paused[bus.get_index()] = paused;
+    }
+
+    pub fn is_bus_paused(&self, bus: Bus) -> bool {
+        self.bus_paused[bus.get_index()]
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume;
+    }
+
+    pub fn get_master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    // Where spatial voices are heard from, usually the middle of the view. See AudioEngine::update_listener.
+    pub fn set_listener_position(&mut self, position: Vector2) {
+        self.listener_position = position;
+    }
+
+    pub fn get_listener_position(&self) -> Vector2 {
+        self.listener_position
+    }
+
+    // How many voices are heard at once. 32 by default.
+    pub fn set_max_voices(&mut self, max_voices: usize) {
+        self.max_voices = max_voices;
+    }
+
+    pub fn get_max_voices(&self) -> usize {
+        self.max_voices
+    }
+
+    // Number of voices playing, paused ones included.
+    pub fn get_voice_count(&self) -> usize {
+        self.voices.iter().filter(|voice| voice.is_some()).count()
+    }
+
+    // Fills "output" with the next stereo frames (left, right, left, right, ...) of all playing voices.
+    pub fn mix(&mut self, output: &mut [f32]) {
+        if !output.len().is_multiple_of(2) {
+            panic!("The mixer outputs stereo, so the output needs an even number of samples, got {}", output.len());
+        }
+
+        for sample in output.iter_mut() {
+            *sample = 0.0;
+        }
+
+        // Work out how loud each voice should be
+        self.mix_order.clear();
+
+        for (index, slot) in self.voices.iter_mut().enumerate() {
+            let voice = match slot {
+                Some(voice) => voice,
+                None => continue
+            };
+
+            let bus_index = voice.settings.bus.get_index();
+
+            if voice.paused || self.bus_paused[bus_index] {
+                continue;
+            }
+
+            voice.update_target(self.bus_volumes[bus_index] * self.master_volume, self.listener_position);
+            self.mix_order.push(index);
+        }
+
+        // LEARN: Voice culling
+        // Many sounds at once mostly make noise (and cost time to mix), so only the most important are heard.
+        // Higher priority goes first, then the loudest. The rest keep playing silently ("virtual voices"),
+        // So they are at the right place in the sound if they become important again.
+        let voices = &self.voices;
+        self.mix_order.sort_by(|&a, &b| {
+            let a = voices[a].as_ref().unwrap();
+            let b = voices[b].as_ref().unwrap();
+
+            b.settings.priority.cmp(&a.settings.priority)
+                .then(b.loudness.partial_cmp(&a.loudness).unwrap_or(Ordering::Equal))
+        });
+
+        // Voices that are silent anyway (muted or out of range) don't take up a place
+        let mut audible_count = 0;
+
+        for &index in self.mix_order.iter() {
+            let slot = &mut self.voices[index];
+            let voice = slot.as_mut().unwrap();
+            let id = VoiceId { index: index as u32, generation: voice.generation };
+
+            voice.audible = audible_count < self.max_voices && voice.loudness > 0.0;
+
+            if voice.audible {
+                audible_count += 1;
+            }
+
+            let result = if voice.audible {
+                let target = voice.target;
+                Mixer::mix_voice(voice, target, self.sample_rate, output)
+            } else if voice.gains.is_some_and(|gains| gains != (0.0, 0.0)) {
+                // Fade out over this mix instead of cutting off with a click
+                Mixer::mix_voice(voice, (0.0, 0.0), self.sample_rate, output)
+            } else {
+                Mixer::skip_voice(voice, self.sample_rate, output)
+            };
+
+            match result {
+                Ok(true) => {},
+                Ok(false) => *slot = None,
+                Err(error) => {
+                    if self.errors.len() < MAX_ERRORS {
+                        self.errors.push((id, error));
+                    }
+
+                    *slot = None;
+                }
+            }
+        }
+
+        for sample in output.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+
+    // Adds the voice into "output", moving its left and right gains to "target" over the course of the mix.
+    // Returns false once the voice has ended.
+    fn mix_voice(voice: &mut Voice, target: (f32, f32), output_sample_rate: u32, output: &mut [f32]) -> Result<bool, String> {
+        let step = voice.get_step(output_sample_rate);
+
+        // Jumping straight to new gains would click, so they are ramped. New voices start at their gains right away.
+        let start = voice.gains.unwrap_or(target);
+        let frame_count = (output.len() / 2) as f32;
+        let left_step = (target.0 - start.0) / frame_count;
+        let right_step = (target.1 - start.1) / frame_count;
+
+        // LEARN: One pole low pass filter
+        // Each output moves only part of the way towards the input, which smooths out fast (high frequency) changes.
+        // The lower the cutoff, the smaller the part.
+        let filter_coefficient = voice.cutoff
+            .map(|cutoff| 1.0 - (-2.0 * std::f32::consts::PI * cutoff / output_sample_rate as f32).exp());
+
+        for (frame_index, output_frame) in output.chunks_exact_mut(2).enumerate() {
+            let index = voice.position.floor() as usize;
+
+            let current = match voice.source.get_frame(index)? {
+                Some(frame) => frame,
+                None => {
+                    // At pitches above 1.0 the position can be several frames past the end
+                    let length = voice.source.get_frame_count().unwrap_or(index);
+
+                    if !voice.settings.looping || length == 0 {
+                        return Ok(false);
+                    }
+
+                    voice.source.restart()?;
+                    voice.position %= length as f64;
+
+                    match voice.source.get_frame(voice.position.floor() as usize)? {
+                        Some(frame) => frame,
+                        None => return Ok(false)
+                    }
+                }
+            };
+
+            let index = voice.position.floor() as usize;
+            let fraction = (voice.position - index as f64) as f32;
+
+            // At the very end, blend towards the last frame itself
+            let next = voice.source.get_frame(index + 1)?.unwrap_or(current);
+
+            let mut left = current.0 + (next.0 - current.0) * fraction;
+            let mut right = current.1 + (next.1 - current.1) * fraction;
+
+            match filter_coefficient {
+                Some(coefficient) => {
+                    voice.filter.0 += (left - voice.filter.0) * coefficient;
+                    voice.filter.1 += (right - voice.filter.1) * coefficient;
+                    left = voice.filter.0;
+                    right = voice.filter.1;
+                },
+                // Follow along, so turning the filter on doesn't start from an old sample
+                None => voice.filter = (left, right)
+            }
+
+            let ramp = (frame_index + 1) as f32;
+            output_frame[0] += left * (start.0 + left_step * ramp);
+            output_frame[1] += right * (start.1 + right_step * ramp);
+
+            voice.position += step;
+        }
+
+        voice.gains = Some(target);
+        voice.source.release_before(voice.position.floor() as usize);
+
+        Ok(true)
+    }
+
+    // Moves a culled voice forward as if it was mixed.
+    fn skip_voice(voice: &mut Voice, output_sample_rate: u32, output: &mut [f32]) -> Result<bool, String> {
+        let length = match &voice.source {
+            Source::Buffer(sound) => sound.get_frame_count() as f64,
+            // NOTE: Streams have to be decoded to move forward, so they are mixed at no volume instead
+            Source::Stream(_) => return Mixer::mix_voice(voice, (0.0, 0.0), output_sample_rate, output)
+        };
+
+        voice.position += voice.get_step(output_sample_rate) * (output.len() / 2) as f64;
+
+        if voice.position >= length {
+            if !voice.settings.looping || length == 0.0 {
+                return Ok(false);
+            }
+
+            voice.position %= length;
+        }
+
+        // Fade in if it becomes audible again
+        voice.gains = Some((0.0, 0.0));
+
+        Ok(true)
+    }
+
+    fn add_voice(&mut self, source: Source, settings: PlaybackSettings) -> VoiceId {
+        let index = match self.voices.iter().position(|voice| voice.is_none()) {
+            Some(index) => index,
+            None => {
+                self.voices.push(None);
+                self.generations.push(0);
+                self.voices.len() - 1
+            }
+        };
+
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        let generation = self.generations[index];
+
+        self.voices[index] = Some(Voice {
+            generation,
+            source,
+            settings,
+            position: 0.0,
+            paused: false,
+            gains: None,
+            target: (0.0, 0.0),
+            cutoff: None,
+            loudness: 0.0,
+            filter: (0.0, 0.0),
+            audible: false
+        });
+
+        VoiceId { index: index as u32, generation }
+    }
+
+    fn get_voice(&self, id: VoiceId) -> Option<&Voice> {
+        self.voices.get(id.index as usize)?.as_ref().filter(|voice| voice.generation == id.generation)
+    }
+
+    fn get_voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        self.voices.get_mut(id.index as usize)?.as_mut().filter(|voice| voice.generation == id.generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::audio::backend::{AudioBackend, NullBackend};
+    use crate::core::audio::spatial::SpatialSettings;
+    use crate::core::audio::engine::AudioEngine;
+
+    use std::sync::Mutex;
+
+    const SAMPLE_RATE: u32 = 8;
+
+    // Plays a stored sound in chunks of at most "max_read" samples, like a decoder would
+    struct TestStream {
+        sound: Sound,
+        position: usize,
+        max_read: usize
+    }
+
+    impl AudioStream for TestStream {
+        fn get_sample_rate(&self) -> u32 {
+            self.sound.get_sample_rate()
+        }
+
+        fn get_channels(&self) -> u16 {
+            self.sound.get_channels()
+        }
+
+        fn read(&mut self, samples: &mut [f32]) -> Result<usize, String> {
+            let remaining = &self.sound.get_samples()[self.position..];
+            let count = remaining.len().min(samples.len()).min(self.max_read);
+            samples[..count].copy_from_slice(&remaining[..count]);
+            self.position += count;
+            Ok(count)
+        }
+
+        fn rewind(&mut self) -> Result<(), String> {
+            self.position = 0;
+            Ok(())
+        }
+    }
+
+    // A mono sound going 0.0, 0.1, 0.2, ...
+    fn ramp(frame_count: usize) -> Arc<Sound> {
+        Arc::new(Sound::new(SAMPLE_RATE, 1, (0..frame_count).map(|index| index as f32 * 0.1).collect()))
+    }
+
+    fn constant(value: f32, frame_count: usize) -> Arc<Sound> {
+        Arc::new(Sound::new(SAMPLE_RATE, 1, vec![value; frame_count]))
+    }
+
+    fn create_backend() -> (NullBackend, Arc<Mutex<Mixer>>) {
+        let mixer = Arc::new(Mutex::new(Mixer::new(SAMPLE_RATE)));
+        let mut backend = NullBackend::new(SAMPLE_RATE);
+        backend.start(Arc::clone(&mixer)).unwrap();
+        (backend, mixer)
+    }
+
+    fn left(samples: &[f32]) -> Vec<f32> {
+        samples.chunks(2).map(|frame| frame[0]).collect()
+    }
+
+    fn right(samples: &[f32]) -> Vec<f32> {
+        samples.chunks(2).map(|frame| frame[1]).collect()
+    }
+
+    fn assert_samples(samples: &[f32], expected: &[f32]) {
+        assert_eq!(samples.len(), expected.len(), "{:?} isn't {:?}", samples, expected);
+        for (sample, expected_sample) in samples.iter().zip(expected.iter()) {
+            assert!((sample - expected_sample).abs() < 1e-5, "{:?} isn't {:?}", samples, expected);
+        }
+    }
+
+    #[test]
+    fn plays_a_sound_once() {
+        let (mut backend, mixer) = create_backend();
+        let voice = mixer.lock().unwrap().play(&ramp(4), PlaybackSettings::new());
+
+        let samples = backend.mix_frames(6);
+        assert_samples(&left(samples), &[0.0, 0.1, 0.2, 0.3, 0.0, 0.0]);
+        assert_samples(&right(samples), &[0.0, 0.1, 0.2, 0.3, 0.0, 0.0]);
+
+        let mixer = mixer.lock().unwrap();
+        assert!(!mixer.is_playing(voice));
+        assert_eq!(mixer.get_voice_count(), 0);
+    }
+
+    #[test]
+    fn volume_and_buses() {
+        let (mut backend, mixer) = create_backend();
+        let sound = constant(0.5, 100);
+
+        let music = mixer.lock().unwrap().play(&sound, PlaybackSettings::new().with_volume(0.5).with_bus(Bus::Music));
+        assert_samples(backend.mix_frames(1), &[0.25, 0.25]);
+
+        mixer.lock().unwrap().set_bus_volume(Bus::Music, 0.5);
+        mixer.lock().unwrap().set_master_volume(0.5);
+        // The new gain is ramped to over the mix, to avoid clicks
+        assert_samples(&left(backend.mix_frames(4)), &[0.203125, 0.15625, 0.109375, 0.0625]);
+        assert_samples(&left(backend.mix_frames(2)), &[0.0625, 0.0625]);
+
+        // Pausing a bus only pauses its voices
+        let sfx = mixer.lock().unwrap().play(&sound, PlaybackSettings::new());
+        mixer.lock().unwrap().set_bus_paused(Bus::Music, true);
+        assert_samples(&left(backend.mix_frames(2)), &[0.25, 0.25]);
+        assert!(mixer.lock().unwrap().is_playing(music));
+
+        mixer.lock().unwrap().stop_bus(Bus::Sfx);
+        mixer.lock().unwrap().set_bus_paused(Bus::Music, false);
+        assert_samples(&left(backend.mix_frames(2)), &[0.0625, 0.0625]);
+        assert!(!mixer.lock().unwrap().is_playing(sfx));
+        assert!(mixer.lock().unwrap().is_playing(music));
+    }
+
+    #[test]
+    fn pan() {
+        let (mut backend, mixer) = create_backend();
+        let sound = constant(0.5, 100);
+
+        let voice = mixer.lock().unwrap().play(&sound, PlaybackSettings::new().with_pan(1.0));
+        let samples = backend.mix_frames(2);
+        assert_samples(&left(samples), &[0.0, 0.0]);
+        assert_samples(&right(samples), &[0.5, 0.5]);
+
+        // Half left turns the right side down to half, and leaves the left at full volume
+        mixer.lock().unwrap().set_pan(voice, -0.5);
+        backend.mix_frames(2);
+        let samples = backend.mix_frames(2);
+        assert_samples(&left(samples), &[0.5, 0.5]);
+        assert_samples(&right(samples), &[0.25, 0.25]);
+
+        // Out of range pans are clamped
+        mixer.lock().unwrap().set_pan(voice, -3.0);
+        backend.mix_frames(2);
+        assert_samples(backend.mix_frames(1), &[0.5, 0.0]);
+    }
+
+    #[test]
+    fn stereo_sounds_keep_their_sides() {
+        let (mut backend, mixer) = create_backend();
+        let sound = Arc::new(Sound::new(SAMPLE_RATE, 2, vec![0.1, -0.1, 0.2, -0.2]));
+
+        mixer.lock().unwrap().play(&sound, PlaybackSettings::new());
+        assert_samples(backend.mix_frames(3), &[0.1, -0.1, 0.2, -0.2, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn pitch_and_sample_rate() {
+        let (mut backend, mixer) = create_backend();
+
+        // Half speed blends between the frames
+        mixer.lock().unwrap().play(&ramp(3), PlaybackSettings::new().with_pitch(0.5));
+        assert_samples(&left(backend.mix_frames(6)), &[0.0, 0.05, 0.1, 0.15, 0.2, 0.2]);
+
+        // Double speed skips every other frame
+        mixer.lock().unwrap().play(&ramp(6), PlaybackSettings::new().with_pitch(2.0));
+        assert_samples(&left(backend.mix_frames(4)), &[0.0, 0.2, 0.4, 0.0]);
+
+        // A sound at half the mixer's sample rate plays like half speed
+        let low_rate = Arc::new(Sound::new(SAMPLE_RATE / 2, 1, vec![0.0, 0.1, 0.2]));
+        let voice = mixer.lock().unwrap().play(&low_rate, PlaybackSettings::new());
+        assert_samples(&left(backend.mix_frames(3)), &[0.0, 0.05, 0.1]);
+        assert_eq!(mixer.lock().unwrap().get_playback_position(voice), Some(0.375));
+    }
+
+    #[test]
+    fn looping() {
+        let (mut backend, mixer) = create_backend();
+
+        let voice = mixer.lock().unwrap().play(&ramp(3), PlaybackSettings::new().looping());
+        assert_samples(&left(backend.mix_frames(7)), &[0.0, 0.1, 0.2, 0.0, 0.1, 0.2, 0.0]);
+
+        // Stops at the end once it no longer loops
+        mixer.lock().unwrap().set_looping(voice, false);
+        assert_samples(&left(backend.mix_frames(3)), &[0.1, 0.2, 0.0]);
+        assert!(!mixer.lock().unwrap().is_playing(voice));
+    }
+
+    #[test]
+    fn looping_faster_than_one_frame_per_frame() {
+        let (mut backend, mixer) = create_backend();
+
+        // Steps of 2 through 5 frames: 0, 2, 4, then 6 wraps around to 1 instead of starting over at 0
+        mixer.lock().unwrap().play(&ramp(5), PlaybackSettings::new().with_pitch(2.0).looping());
+        assert_samples(&left(backend.mix_frames(8)), &[0.0, 0.2, 0.4, 0.1, 0.3, 0.0, 0.2, 0.4]);
+
+        // Steps longer than the whole sound wrap too
+        let voice = mixer.lock().unwrap().play(&ramp(2), PlaybackSettings::new().with_pitch(3.0).with_bus(Bus::Ui).looping());
+        mixer.lock().unwrap().set_bus_paused(Bus::Sfx, true);
+        assert_samples(&left(backend.mix_frames(3)), &[0.0, 0.1, 0.0]);
+        assert!(mixer.lock().unwrap().is_playing(voice));
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let (mut backend, mixer) = create_backend();
+        let voice = mixer.lock().unwrap().play(&ramp(4), PlaybackSettings::new());
+
+        backend.mix_frames(2);
+        mixer.lock().unwrap().pause(voice);
+        assert!(mixer.lock().unwrap().is_paused(voice));
+        assert_samples(&left(backend.mix_frames(2)), &[0.0, 0.0]);
+
+        mixer.lock().unwrap().resume(voice);
+        assert_samples(&left(backend.mix_frames(3)), &[0.2, 0.3, 0.0]);
+    }
+
+    #[test]
+    fn output_is_clamped() {
+        let (mut backend, mixer) = create_backend();
+        let sound = constant(0.75, 10);
+
+        mixer.lock().unwrap().play(&sound, PlaybackSettings::new());
+        mixer.lock().unwrap().play(&sound, PlaybackSettings::new());
+        mixer.lock().unwrap().play(&constant(-0.75, 10), PlaybackSettings::new().with_pan(1.0).with_volume(4.0));
+        assert_samples(backend.mix_frames(1), &[1.0, -1.0]);
+    }
+
+    #[test]
+    fn old_voice_ids_dont_affect_new_voices() {
+        let (_backend, mixer) = create_backend();
+        let mut mixer = mixer.lock().unwrap();
+        let sound = constant(0.5, 10);
+
+        let old = mixer.play(&sound, PlaybackSettings::new());
+        assert!(mixer.stop(old));
+        assert!(!mixer.stop(old));
+
+        let new = mixer.play(&sound, PlaybackSettings::new());
+        mixer.set_volume(old, 0.0);
+        assert_eq!(mixer.get_settings(new).unwrap().volume, 1.0);
+        assert!(!mixer.is_playing(old));
+    }
+
+    #[test]
+    fn streams_play_like_sounds() {
+        let stereo: Vec<f32> = (0..40).map(|index| (index as f32 * 0.7).sin() * 0.5).collect();
+        let sound = Arc::new(Sound::new(SAMPLE_RATE, 2, stereo.clone()));
+
+        for settings in [PlaybackSettings::new(), PlaybackSettings::new().with_pitch(0.75), PlaybackSettings::new().with_pitch(2.0).looping()] {
+            let (mut backend, mixer) = create_backend();
+            mixer.lock().unwrap().play(&sound, settings);
+            let expected = backend.mix_frames(50).to_vec();
+
+            let (mut backend, mixer) = create_backend();
+            let stream = TestStream { sound: Sound::new(SAMPLE_RATE, 2, stereo.clone()), position: 0, max_read: 6 };
+            let voice = mixer.lock().unwrap().play_stream(Box::new(stream), settings);
+
+            // Mixed a few frames at a time, so the stream is read and released in pieces
+            let mut samples = Vec::new();
+            for _ in 0..10 {
+                samples.extend_from_slice(backend.mix_frames(5));
+            }
+
+            assert_samples(&samples, &expected);
+            assert_eq!(mixer.lock().unwrap().is_playing(voice), settings.looping);
+        }
+    }
+
+    // Plays two frames of silence per read for a few reads, then fails like a decoder running into a corrupt file
+    struct BrokenStream {
+        reads_left: usize
+    }
+
+    impl AudioStream for BrokenStream {
+        fn get_sample_rate(&self) -> u32 {
+            SAMPLE_RATE
+        }
+
+        fn get_channels(&self) -> u16 {
+            1
+        }
+
+        fn read(&mut self, samples: &mut [f32]) -> Result<usize, String> {
+            if self.reads_left == 0 {
+                return Err(String::from("Corrupt page"));
+            }
+
+            self.reads_left -= 1;
+            let count = samples.len().min(2);
+            samples[..count].fill(0.0);
+            Ok(count)
+        }
+
+        fn rewind(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failing_voices_are_stopped_and_kept_as_errors() {
+        let (mut backend, mixer) = create_backend();
+        let broken = mixer.lock().unwrap().play_stream(Box::new(BrokenStream { reads_left: 3 }), PlaybackSettings::new());
+        let sound = mixer.lock().unwrap().play(&constant(0.5, 100), PlaybackSettings::new());
+
+        backend.mix_frames(4);
+        assert!(mixer.lock().unwrap().take_errors().is_empty());
+
+        // The other voice keeps playing
+        for _ in 0..10 {
+            assert_samples(backend.mix_frames(1), &[0.5, 0.5]);
+        }
+
+        let mut mixer = mixer.lock().unwrap();
+        assert!(!mixer.is_playing(broken));
+        assert!(mixer.is_playing(sound));
+        assert_eq!(mixer.take_errors(), vec![(broken, String::from("Corrupt page"))]);
+        assert!(mixer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn errors_are_capped() {
+        let (mut backend, mixer) = create_backend();
+
+        for _ in 0..MAX_ERRORS + 5 {
+            mixer.lock().unwrap().play_stream(Box::new(BrokenStream { reads_left: 0 }), PlaybackSettings::new().with_priority(1));
+        }
+
+        backend.mix_frames(1);
+        assert_eq!(mixer.lock().unwrap().take_errors().len(), MAX_ERRORS);
+    }
+
+    #[test]
+    fn the_audio_engine_reports_failed_voices() {
+        let mut audio = AudioEngine::new(Box::new(NullBackend::new(SAMPLE_RATE))).unwrap();
+        audio.play_stream(Box::new(BrokenStream { reads_left: 0 }), PlaybackSettings::new());
+        audio.update(1.0).unwrap();
+
+        assert_eq!(audio.take_errors(), vec![String::from("Stopped a voice that failed to play: Corrupt page")]);
+        assert!(audio.take_errors().is_empty());
+    }
+
+    #[test]
+    fn culls_the_least_important_voices() {
+        let (mut backend, mixer) = create_backend();
+        mixer.lock().unwrap().set_max_voices(2);
+
+        let important = mixer.lock().unwrap().play(&constant(0.1, 100), PlaybackSettings::new().with_priority(5));
+        let loud = mixer.lock().unwrap().play(&constant(0.4, 100), PlaybackSettings::new());
+        let quiet = mixer.lock().unwrap().play(&constant(0.4, 100), PlaybackSettings::new().with_volume(0.5));
+
+        // Priority first, then loudness
+        assert_samples(&left(backend.mix_frames(4)), &[0.5, 0.5, 0.5, 0.5]);
+        {
+            let mixer = mixer.lock().unwrap();
+            assert!(mixer.is_audible(important));
+            assert!(mixer.is_audible(loud));
+            assert!(!mixer.is_audible(quiet));
+
+            // Culled voices keep their place in the sound
+            assert!(mixer.is_playing(quiet));
+            assert_eq!(mixer.get_playback_position(quiet), Some(0.5));
+        }
+
+        // The voice that drops out fades out, the one that comes back fades in
+        mixer.lock().unwrap().set_priority(quiet, 10);
+        assert_samples(&left(backend.mix_frames(4)), &[0.45, 0.4, 0.35, 0.3]);
+        assert_samples(&left(backend.mix_frames(2)), &[0.3, 0.3]);
+        assert!(!mixer.lock().unwrap().is_audible(loud));
+
+        // Stopping a voice frees its place
+        mixer.lock().unwrap().stop(quiet);
+        assert_samples(&left(backend.mix_frames(2)), &[0.3, 0.5]);
+        assert!(mixer.lock().unwrap().is_audible(loud));
+    }
+
+    #[test]
+    fn silent_voices_dont_take_a_place() {
+        let (mut backend, mixer) = create_backend();
+        mixer.lock().unwrap().set_max_voices(1);
+
+        let far_away = SpatialSettings::new(Vector2::new(5000.0, 0.0));
+        let silent = mixer.lock().unwrap().play(&constant(0.5, 100), PlaybackSettings::new().with_priority(100).with_spatial(far_away));
+        let muted = mixer.lock().unwrap().play(&constant(0.5, 100), PlaybackSettings::new().with_priority(50).with_volume(0.0));
+        let heard = mixer.lock().unwrap().play(&constant(0.25, 100), PlaybackSettings::new());
+
+        assert_samples(&left(backend.mix_frames(2)), &[0.25, 0.25]);
+
+        let mixer = mixer.lock().unwrap();
+        assert!(!mixer.is_audible(silent));
+        assert!(!mixer.is_audible(muted));
+        assert!(mixer.is_audible(heard));
+    }
+
+    #[test]
+    fn culled_voices_still_end() {
+        let (mut backend, mixer) = create_backend();
+        mixer.lock().unwrap().set_max_voices(0);
+
+        let short = mixer.lock().unwrap().play(&constant(0.5, 3), PlaybackSettings::new());
+        let looping = mixer.lock().unwrap().play(&ramp(3), PlaybackSettings::new().looping());
+
+        assert_samples(backend.mix_frames(4), &[0.0; 8]);
+        assert!(!mixer.lock().unwrap().is_playing(short));
+        assert!(mixer.lock().unwrap().is_playing(looping));
+
+        // The looping voice wrapped around while it was culled: 4 frames into a 3 frame sound
+        mixer.lock().unwrap().set_max_voices(1);
+        assert_samples(&left(backend.mix_frames(2)), &[0.05, 0.2]);
+    }
+}
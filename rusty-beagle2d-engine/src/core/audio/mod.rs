@@ -0,0 +1,9 @@
+pub mod sound;
+pub mod wav;
+pub mod ogg;
+pub mod mixer;
+pub mod spatial;
+pub mod backend;
+#[cfg(feature = "audio-device")]
+pub mod device;
+pub mod engine;
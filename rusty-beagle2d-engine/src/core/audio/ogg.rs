@@ -0,0 +1,119 @@
+use crate::core::audio::sound::{Sound, AudioStream};
+
+use lewton::inside_ogg::OggStreamReader;
+
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+pub fn load_ogg(path: &Path) -> Result<Sound, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|error| format!("Failed to read Ogg file {}: {}", path.display(), error))?;
+
+    decode_ogg(&bytes)
+        .map_err(|error| format!("Failed to load Ogg file {}: {}", path.display(), error))
+}
+
+// Decodes a whole Ogg Vorbis file.
+pub fn decode_ogg(bytes: &[u8]) -> Result<Sound, String> {
+    let mut reader = OggStreamReader::new(Cursor::new(bytes)).map_err(|error| error.to_string())?;
+    let (sample_rate, channels) = get_format(&reader)?;
+
+    let mut samples: Vec<f32> = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl().map_err(|error| error.to_string())? {
+        samples.extend(packet.iter().map(|&sample| sample as f32 / 32768.0));
+    }
+
+    Ok(Sound::new(sample_rate, channels, samples))
+}
+
+// Decodes an Ogg Vorbis file as it plays. For music, which would take a lot of memory decoded all at once.
+pub struct OggStream {
+    path: PathBuf,
+    reader: OggStreamReader<BufReader<File>>,
+    sample_rate: u32,
+    channels: u16,
+    // Samples of the last decoded packet that haven't been read yet
+    pending: Vec<f32>,
+    pending_offset: usize
+}
+
+impl OggStream {
+    pub fn open(path: &Path) -> Result<OggStream, String> {
+        let reader = open_reader(path)?;
+        let (sample_rate, channels) = get_format(&reader)
+            .map_err(|error| format!("Failed to open Ogg file {}: {}", path.display(), error))?;
+
+        Ok(OggStream {
+            path: path.to_path_buf(),
+            reader,
+            sample_rate,
+            channels,
+            pending: Vec::new(),
+            pending_offset: 0
+        })
+    }
+}
+
+impl AudioStream for OggStream {
+    fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn get_channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn read(&mut self, samples: &mut [f32]) -> Result<usize, String> {
+        let mut written = 0;
+
+        while written < samples.len() {
+            if self.pending_offset == self.pending.len() {
+                // Vorbis packets vary in size, so decode one at a time and hand it out over as many reads as it takes
+                match self.reader.read_dec_packet_itl().map_err(|error| error.to_string())? {
+                    Some(packet) => {
+                        self.pending.clear();
+                        self.pending.extend(packet.iter().map(|&sample| sample as f32 / 32768.0));
+                        self.pending_offset = 0;
+                    },
+                    None => break
+                }
+            }
+
+            let count = (samples.len() - written).min(self.pending.len() - self.pending_offset);
+            samples[written..written + count].copy_from_slice(&self.pending[self.pending_offset..self.pending_offset + count]);
+
+            written += count;
+            self.pending_offset += count;
+        }
+
+        Ok(written)
+    }
+
+    // NOTE: Opens the file again. Seeking with lewton works on pages, which is more than starting over needs.
+    fn rewind(&mut self) -> Result<(), String> {
+        self.reader = open_reader(&self.path)?;
+        self.pending.clear();
+        self.pending_offset = 0;
+        Ok(())
+    }
+}
+
+fn open_reader(path: &Path) -> Result<OggStreamReader<BufReader<File>>, String> {
+    let file = File::open(path)
+        .map_err(|error| format!("Failed to open Ogg file {}: {}", path.display(), error))?;
+
+    OggStreamReader::new(BufReader::new(file))
+        .map_err(|error| format!("Failed to open Ogg file {}: {}", path.display(), error))
+}
+
+fn get_format<T: Read + Seek>(reader: &OggStreamReader<T>) -> Result<(u32, u16), String> {
+    let sample_rate = reader.ident_hdr.audio_sample_rate;
+    let channels = reader.ident_hdr.audio_channels as u16;
+
+    if sample_rate == 0 || channels == 0 {
+        return Err(String::from("Invalid channel count or sample rate"));
+    }
+
+    Ok((sample_rate, channels))
+}
@@ -0,0 +1,95 @@
+use crate::core::audio::{wav, ogg};
+
+use std::path::Path;
+use std::sync::Arc;
+
+// Decoded audio, held in memory. Fine for sound effects. Long music is better streamed, see open_stream.
+// Samples are interleaved: for stereo, left and right of the first frame, then left and right of the second, and so on.
+pub struct Sound {
+    sample_rate: u32,
+    channels: u16,
+    samples: Vec<f32>
+}
+
+impl Sound {
+    // Samples in the range -1.0 to 1.0.
+    pub fn new(sample_rate: u32, channels: u16, samples: Vec<f32>) -> Sound {
+        if sample_rate == 0 || channels == 0 {
+            panic!("A sound needs a sample rate and at least one channel");
+        }
+
+        if !samples.len().is_multiple_of(channels as usize) {
+            panic!("{} samples can't be split evenly into {} channels", samples.len(), channels);
+        }
+
+        Sound {
+            sample_rate,
+            channels,
+            samples
+        }
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn get_channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn get_samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    // A frame is one sample per channel.
+    pub fn get_frame_count(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    // In seconds.
+    pub fn get_duration(&self) -> f32 {
+        self.get_frame_count() as f32 / self.sample_rate as f32
+    }
+}
+
+// Audio that is decoded bit by bit while it plays, instead of all at once.
+// Must be Send, as the mixer may run on the audio device's own thread.
+pub trait AudioStream: Send {
+    fn get_sample_rate(&self) -> u32;
+
+    fn get_channels(&self) -> u16;
+
+    // Fills "samples" with interleaved samples from where the stream is. Returns how many were written.
+    // Fewer than asked for (usually 0) means the stream ended.
+    fn read(&mut self, samples: &mut [f32]) -> Result<usize, String>;
+
+    // Starts over from the beginning. Used for looping.
+    fn rewind(&mut self) -> Result<(), String>;
+}
+
+// Loads a WAV or Ogg Vorbis file, going by its extension. Shared, so many voices can play it at the same time.
+pub fn load_sound(path: &Path) -> Result<Arc<Sound>, String> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+
+    let sound = match extension.as_deref() {
+        Some("wav") => wav::load_wav(path)?,
+        Some("ogg") => ogg::load_ogg(path)?,
+        _ => return Err(format!("Unsupported sound file {}, expected .wav or .ogg", path.display()))
+    };
+
+    Ok(Arc::new(sound))
+}
+
+// Opens a file for streaming. Only Ogg Vorbis files can be streamed, load WAV files with load_sound.
+pub fn open_stream(path: &Path) -> Result<Box<dyn AudioStream>, String> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+
+    match extension.as_deref() {
+        Some("ogg") => Ok(Box::new(ogg::OggStream::open(path)?)),
+        _ => Err(format!("Can't stream {}, only .ogg files can be streamed", path.display()))
+    }
+}
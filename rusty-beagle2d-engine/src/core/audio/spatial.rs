@@ -0,0 +1,258 @@
+use crate::core::audio::mixer::VoiceId;
+
+use linear_beaglebra::vector2::Vector2;
+
+// How a sound gets quieter between its min and max distance from the listener.
+// The curves are scaled so they always end at silence at the max distance, so sounds never cut out abruptly.
+#[derive(Copy, Clone, Debug)]
+pub enum Falloff {
+    // Same volume everywhere in range
+    None,
+    // Fades evenly to silence
+    Linear,
+    // Drops quickly close to the sound and slowly further away, like sound in the real world.
+    // Higher rolloff drops faster.
+    Inverse { rolloff: f32 },
+    Exponential { rolloff: f32 },
+    // Gets how far the sound is between min and max distance (0.0 - 1.0), returns the volume (0.0 - 1.0)
+    Custom(fn(f32) -> f32)
+}
+
+impl Falloff {
+    // Volume at "t", where 0.0 is the min distance and 1.0 the max distance.
+    pub fn get_gain(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        let gain = match *self {
+            Falloff::None => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Inverse { rolloff } => {
+                let end = 1.0 / (1.0 + rolloff);
+                (1.0 / (1.0 + rolloff * t) - end) / (1.0 - end)
+            },
+            Falloff::Exponential { rolloff } => {
+                let end = (-rolloff).exp();
+                ((-rolloff * t).exp() - end) / (1.0 - end)
+            },
+            Falloff::Custom(curve) => curve(t)
+        };
+
+        // A rolloff of 0 divides 0 by 0, which is no falloff at all
+        if gain.is_nan() {
+            1.0
+        } else {
+            gain.clamp(0.0, 1.0)
+        }
+    }
+}
+
+// Muffles a sound more the further away it is, by cutting high frequencies above a cutoff frequency.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LowPassFilter {
+    // Cutoff in Hz at the min distance
+    pub near_cutoff: f32,
+    // Cutoff in Hz at the max distance
+    pub far_cutoff: f32
+}
+
+impl LowPassFilter {
+    pub fn new(near_cutoff: f32, far_cutoff: f32) -> LowPassFilter {
+        if near_cutoff <= 0.0 || far_cutoff <= 0.0 {
+            panic!("Low pass cutoff frequencies must be more than 0 Hz");
+        }
+
+        LowPassFilter {
+            near_cutoff,
+            far_cutoff
+        }
+    }
+
+    // Cutoff in Hz at "t", where 0.0 is the min distance and 1.0 the max distance.
+    // LEARN: We hear pitch logarithmically, so the cutoff moves by the same ratio for each step, not the same amount of Hz.
+    pub fn get_cutoff(&self, t: f32) -> f32 {
+        self.near_cutoff * (self.far_cutoff / self.near_cutoff).powf(t.clamp(0.0, 1.0))
+    }
+}
+
+// Plays a sound at a position in the world, relative to the mixer's listener.
+// SpatialSettings::new(position).with_distances(100.0, 800.0).with_falloff(Falloff::Inverse { rolloff: 4.0 })
+#[derive(Copy, Clone, Debug)]
+pub struct SpatialSettings {
+    pub position: Vector2,
+    // Full volume up to this distance
+    pub min_distance: f32,
+    // Silent from this distance
+    pub max_distance: f32,
+    pub falloff: Falloff,
+    // How far to the side a sound has to be to play on one side only
+    pub pan_distance: f32,
+    pub low_pass: Option<LowPassFilter>
+}
+
+impl SpatialSettings {
+    // Full volume within 64 pixels, fading linearly to silence at 1024 pixels.
+    // Panned fully to one side at 512 pixels, about the edge of the view.
+    pub fn new(position: Vector2) -> SpatialSettings {
+        SpatialSettings {
+            position,
+            min_distance: 64.0,
+            max_distance: 1024.0,
+            falloff: Falloff::Linear,
+            pan_distance: 512.0,
+            low_pass: None
+        }
+    }
+
+    pub fn with_distances(mut self, min_distance: f32, max_distance: f32) -> SpatialSettings {
+        if min_distance < 0.0 || max_distance < min_distance {
+            panic!("Invalid distances, min {} and max {}", min_distance, max_distance);
+        }
+
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> SpatialSettings {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn with_pan_distance(mut self, pan_distance: f32) -> SpatialSettings {
+        self.pan_distance = pan_distance;
+        self
+    }
+
+    pub fn with_low_pass(mut self, low_pass: LowPassFilter) -> SpatialSettings {
+        self.low_pass = Some(low_pass);
+        self
+    }
+
+    // How far the sound is between its min and max distance from the listener (0.0 - 1.0).
+    pub fn get_distance_fraction(&self, listener: Vector2) -> f32 {
+        let dx = self.position.x - listener.x;
+        let dy = self.position.y - listener.y;
+        let distance = (dx * dx + dy * dy).sqrt();
+
+        if distance <= self.min_distance {
+            0.0
+        } else if distance >= self.max_distance {
+            1.0
+        } else {
+            (distance - self.min_distance) / (self.max_distance - self.min_distance)
+        }
+    }
+
+    // Silent from the max distance on, whatever the falloff.
+    pub fn get_gain(&self, listener: Vector2) -> f32 {
+        let t = self.get_distance_fraction(listener);
+
+        if t >= 1.0 {
+            0.0
+        } else {
+            self.falloff.get_gain(t)
+        }
+    }
+
+    // -1.0 when the sound is fully to the left of the listener, 1.0 fully to the right.
+    pub fn get_pan(&self, listener: Vector2) -> f32 {
+        if self.pan_distance <= 0.0 {
+            return 0.0;
+        }
+
+        ((self.position.x - listener.x) / self.pan_distance).clamp(-1.0, 1.0)
+    }
+}
+
+// Makes a playing voice follow an entity. The voice is moved to the entity's Transform by
+// ecs::systems::update_audio_emitters, which needs the AudioEngine as a world resource.
+#[derive(Copy, Clone, Debug)]
+pub struct AudioEmitter {
+    pub voice: VoiceId
+}
+
+impl AudioEmitter {
+    pub fn new(voice: VoiceId) -> AudioEmitter {
+        AudioEmitter {
+            voice
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-4, "{} isn't {}", value, expected);
+    }
+
+    #[test]
+    fn falloff_curves_go_from_full_to_silent() {
+        let curves = [
+            Falloff::Linear,
+            Falloff::Inverse { rolloff: 4.0 },
+            Falloff::Exponential { rolloff: 3.0 },
+            Falloff::Custom(|t| 1.0 - t * t)
+        ];
+
+        for falloff in curves.iter() {
+            assert_close(falloff.get_gain(0.0), 1.0);
+            assert_close(falloff.get_gain(1.0), 0.0);
+            assert!(falloff.get_gain(0.25) > falloff.get_gain(0.5));
+            assert!(falloff.get_gain(0.5) > falloff.get_gain(0.75));
+
+            // Outside of the range counts as the ends
+            assert_close(falloff.get_gain(-1.0), 1.0);
+            assert_close(falloff.get_gain(2.0), 0.0);
+        }
+
+        assert_close(Falloff::Linear.get_gain(0.25), 0.75);
+        assert_close(Falloff::None.get_gain(0.9), 1.0);
+        assert_close(Falloff::Inverse { rolloff: 0.0 }.get_gain(0.5), 1.0);
+        // Inverse drops faster than linear close by
+        assert!(Falloff::Inverse { rolloff: 4.0 }.get_gain(0.25) < 0.75);
+    }
+
+    #[test]
+    fn gain_by_distance() {
+        let spatial = SpatialSettings::new(Vector2::new(0.0, 0.0)).with_distances(100.0, 300.0);
+
+        assert_close(spatial.get_gain(Vector2::new(0.0, 0.0)), 1.0);
+        assert_close(spatial.get_gain(Vector2::new(60.0, 80.0)), 1.0);
+        assert_close(spatial.get_gain(Vector2::new(0.0, 150.0)), 0.75);
+        assert_close(spatial.get_distance_fraction(Vector2::new(-200.0, 0.0)), 0.5);
+        assert_close(spatial.get_gain(Vector2::new(300.0, 0.0)), 0.0);
+
+        // Silent past the max distance, even without falloff
+        let no_falloff = spatial.with_falloff(Falloff::None);
+        assert_close(no_falloff.get_gain(Vector2::new(299.0, 0.0)), 1.0);
+        assert_close(no_falloff.get_gain(Vector2::new(301.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn pan_by_side() {
+        let spatial = SpatialSettings::new(Vector2::new(100.0, 0.0)).with_pan_distance(200.0);
+
+        assert_close(spatial.get_pan(Vector2::new(100.0, 500.0)), 0.0);
+        assert_close(spatial.get_pan(Vector2::new(0.0, 0.0)), 0.5);
+        assert_close(spatial.get_pan(Vector2::new(400.0, 0.0)), -1.0);
+        assert_close(spatial.with_pan_distance(0.0).get_pan(Vector2::new(0.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn low_pass_cutoff_moves_by_ratio() {
+        let low_pass = LowPassFilter::new(16000.0, 1000.0);
+
+        assert_close(low_pass.get_cutoff(0.0), 16000.0);
+        assert_close(low_pass.get_cutoff(0.5), 4000.0);
+        assert_close(low_pass.get_cutoff(1.0), 1000.0);
+        assert_close(low_pass.get_cutoff(3.0), 1000.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn max_distance_below_min_distance_panics() {
+        SpatialSettings::new(Vector2::new(0.0, 0.0)).with_distances(100.0, 50.0);
+    }
+}
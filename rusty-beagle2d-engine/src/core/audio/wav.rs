@@ -0,0 +1,161 @@
+use crate::core::audio::sound::Sound;
+
+use std::convert::TryInto;
+use std::path::Path;
+
+// LEARN: The WAV format
+// A WAV file is a RIFF file: a "RIFF" header followed by chunks, each with a 4 letter id and a size.
+// The "fmt " chunk says how the samples are stored, and the "data" chunk holds them, interleaved and little endian.
+// Other chunks (like "LIST" with metadata) can be skipped.
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+// The real format is then in the first two bytes of the chunk's sub format GUID
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+struct WavFormat {
+    format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16
+}
+
+pub fn load_wav(path: &Path) -> Result<Sound, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|error| format!("Failed to read WAV file {}: {}", path.display(), error))?;
+
+    decode_wav(&bytes)
+        .map_err(|error| format!("Failed to load WAV file {}: {}", path.display(), error))
+}
+
+// Decodes 8, 16, 24 and 32 bit integer, and 32 and 64 bit float WAV data.
+pub fn decode_wav(bytes: &[u8]) -> Result<Sound, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(String::from("Not a WAV file"));
+    }
+
+    let mut format: Option<WavFormat> = None;
+    let mut data: Option<&[u8]> = None;
+
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = read_u32(bytes, offset + 4) as usize;
+        let body_start = offset + 8;
+
+        // Some writers put a wrong size on the data chunk, so it's cut to what the file really holds
+        let body_end = (body_start + size).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match id {
+            b"fmt " => format = Some(parse_format(body)?),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        offset = body_start + size + (size & 1);
+    }
+
+    let format = format.ok_or("Missing \"fmt \" chunk")?;
+    let data = data.ok_or("Missing \"data\" chunk")?;
+
+    if format.channels == 0 || format.sample_rate == 0 {
+        return Err(String::from("Invalid channel count or sample rate"));
+    }
+
+    let bytes_per_sample = (format.bits_per_sample as usize).div_ceil(8);
+    let frame_size = bytes_per_sample * format.channels as usize;
+
+    // Ignore a trailing partial frame
+    let data = &data[..data.len() - data.len() % frame_size];
+
+    let samples: Vec<f32> = match (format.format, format.bits_per_sample) {
+        // 8 bit samples are unsigned, centered on 128
+        (FORMAT_PCM, 8) => data.iter().map(|&byte| (byte as f32 - 128.0) / 128.0).collect(),
+        (FORMAT_PCM, 16) => data.chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as f32 / 32768.0)
+            .collect(),
+        // Put the 3 bytes in the top of an i32, so the sign comes along
+        (FORMAT_PCM, 24) => data.chunks_exact(3)
+            .map(|sample| (i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) >> 8) as f32 / 8_388_608.0)
+            .collect(),
+        (FORMAT_PCM, 32) => data.chunks_exact(4)
+            .map(|sample| i32::from_le_bytes(sample.try_into().unwrap()) as f32 / 2_147_483_648.0)
+            .collect(),
+        (FORMAT_IEEE_FLOAT, 32) => data.chunks_exact(4)
+            .map(|sample| f32::from_le_bytes(sample.try_into().unwrap()))
+            .collect(),
+        (FORMAT_IEEE_FLOAT, 64) => data.chunks_exact(8)
+            .map(|sample| f64::from_le_bytes(sample.try_into().unwrap()) as f32)
+            .collect(),
+        (format_tag, bits) => return Err(format!("Unsupported sample format {} with {} bits per sample", format_tag, bits))
+    };
+
+    Ok(Sound::new(format.sample_rate, format.channels, samples))
+}
+
+// Encodes interleaved samples as a 32 bit float WAV file.
+pub fn encode_wav(sample_rate: u32, channels: u16, samples: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(44 + samples.len() * 4);
+    write_wav_header(&mut bytes, sample_rate, channels, samples.len());
+
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    bytes
+}
+
+// The 44 byte header of a 32 bit float WAV file holding "sample_count" samples.
+pub(crate) fn write_wav_header(bytes: &mut Vec<u8>, sample_rate: u32, channels: u16, sample_count: usize) {
+    let data_size = (sample_count * 4) as u32;
+    let block_align = channels * 4;
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&FORMAT_IEEE_FLOAT.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&32u16.to_le_bytes());
+
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+}
+
+fn parse_format(body: &[u8]) -> Result<WavFormat, String> {
+    if body.len() < 16 {
+        return Err(String::from("\"fmt \" chunk is too short"));
+    }
+
+    let mut format = WavFormat {
+        format: read_u16(body, 0),
+        channels: read_u16(body, 2),
+        sample_rate: read_u32(body, 4),
+        bits_per_sample: read_u16(body, 14)
+    };
+
+    if format.format == FORMAT_EXTENSIBLE {
+        if body.len() < 26 {
+            return Err(String::from("Extensible \"fmt \" chunk is too short"));
+        }
+
+        format.format = read_u16(body, 24);
+    }
+
+    Ok(format)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
@@ -0,0 +1,182 @@
+use rusty_beagle2d_glfw::ogl;
+use crate::core::blend_mode::BlendMode;
+use crate::core::vertex_layout::{Vertex, VertexLayout};
+use crate::core::vertex_buffer::VertexBuffer;
+use crate::core::index_buffer::IndexBuffer;
+use crate::core::vertex_array::VertexArray;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct BatchVertex {
+    pub position: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub color: [f32; 4]
+}
+
+impl Vertex for BatchVertex {
+    fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .with_floats(3) // Position
+            .with_floats(2) // Texture Coords
+            .with_floats(4) // Color
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BatchMode {
+    Textured,
+    // Signed distance field glyphs from the font atlas
+    Text
+}
+
+// Everything that forces a separate draw call when it changes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DrawState {
+    pub texture: ogl::TextureId,
+    pub mode: BatchMode,
+    pub blend_mode: BlendMode
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DrawCommand {
+    pub state: DrawState,
+    pub layer: i32,
+    pub first_index: usize,
+    pub index_count: usize
+}
+
+// Collects the geometry of every draw made during a frame into one vertex and index buffer.
+// Consecutive draws sharing a DrawState and layer are merged into a single DrawCommand,
+// So a frame of sprites from the same texture ends up as one draw call.
+// Before uploading, commands are sorted by layer (keeping the draw order within a layer).
+pub struct Batch {
+    vertices: Vec<BatchVertex>,
+    indices: Vec<u32>,
+    commands: Vec<DrawCommand>,
+    // Scratch space for reordering indices by layer, kept to avoid allocating every frame
+    sorted_indices: Vec<u32>,
+    sorted_commands: Vec<DrawCommand>,
+    vertex_buffer: VertexBuffer<BatchVertex>,
+    index_buffer: IndexBuffer,
+    vertex_array: VertexArray
+}
+
+impl Batch {
+    pub fn new(initial_vertex_capacity: usize) -> Batch {
+        let initial_index_capacity = initial_vertex_capacity / 4 * 6;
+
+        let vertex_buffer = VertexBuffer::new(initial_vertex_capacity, ogl::Usage::StreamDraw);
+        let index_buffer = IndexBuffer::new(initial_index_capacity, ogl::Usage::StreamDraw);
+
+        let mut vertex_array = VertexArray::new();
+        vertex_array.add_vertex_buffer(&vertex_buffer);
+        vertex_array.set_index_buffer(&index_buffer);
+
+        Batch {
+            vertices: Vec::with_capacity(initial_vertex_capacity),
+            indices: Vec::with_capacity(initial_index_capacity),
+            commands: Vec::new(),
+            sorted_indices: Vec::with_capacity(initial_index_capacity),
+            sorted_commands: Vec::new(),
+            vertex_buffer,
+            index_buffer,
+            vertex_array
+        }
+    }
+
+    // Adds a shape to the batch.
+    // "indices" are relative to the first of the given vertices.
+    pub fn push<V, I>(&mut self, state: DrawState, layer: i32, vertices: V, indices: I)
+        where V: IntoIterator<Item = BatchVertex>, I: IntoIterator<Item = u32>
+    {
+        let base_vertex = self.vertices.len() as u32;
+        let first_index = self.indices.len();
+
+        self.vertices.extend(vertices);
+        self.indices.extend(indices.into_iter().map(|index| base_vertex + index));
+
+        let index_count = self.indices.len() - first_index;
+
+        if index_count == 0 {
+            return;
+        }
+
+        match self.commands.last_mut() {
+            Some(last_command) if last_command.state == state && last_command.layer == layer => {
+                last_command.index_count += index_count;
+            },
+            _ => {
+                self.commands.push(DrawCommand { state, layer, first_index, index_count });
+            }
+        }
+    }
+
+    // Two triangles, with the corners given in clockwise or counter-clockwise order.
+    pub fn push_quad(&mut self, state: DrawState, layer: i32, corners: [BatchVertex; 4]) {
+        self.push(state, layer, corners.iter().cloned(), [0, 1, 2, 0, 2, 3].iter().cloned());
+    }
+
+    // Sorts the commands by layer and rewrites the index list to match,
+    // Merging commands that end up next to each other with the same state.
+    pub fn sort_by_layer(&mut self) {
+        let is_sorted = self.commands.windows(2).all(|pair| pair[0].layer <= pair[1].layer);
+
+        if is_sorted {
+            return;
+        }
+
+        // LEARN: sort_by_key is a stable sort, so commands on the same layer keep their draw order
+        self.commands.sort_by_key(|command| command.layer);
+
+        self.sorted_indices.clear();
+        self.sorted_commands.clear();
+
+        for command in &self.commands {
+            let first_index = self.sorted_indices.len();
+            self.sorted_indices.extend_from_slice(&self.indices[command.first_index..command.first_index + command.index_count]);
+
+            match self.sorted_commands.last_mut() {
+                Some(last_command) if last_command.state == command.state && last_command.layer == command.layer => {
+                    last_command.index_count += command.index_count;
+                },
+                _ => {
+                    self.sorted_commands.push(DrawCommand { first_index, ..*command });
+                }
+            }
+        }
+
+        std::mem::swap(&mut self.indices, &mut self.sorted_indices);
+        std::mem::swap(&mut self.commands, &mut self.sorted_commands);
+    }
+
+    // Sorts the geometry by layer, sends it to the GPU and leaves the batch's vertex array bound,
+    // Ready for draw_elements calls for each of the commands.
+    pub fn upload(&mut self) {
+        self.sort_by_layer();
+
+        self.vertex_array.bind();
+
+        self.vertex_buffer.orphan();
+        self.vertex_buffer.set_data(&self.vertices);
+
+        self.index_buffer.orphan();
+        self.index_buffer.set_data(&self.indices);
+    }
+
+    pub fn get_commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // Clears the collected geometry while keeping the allocations around for the next frame.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.sorted_indices.clear();
+        self.sorted_commands.clear();
+        self.indices.clear();
+        self.commands.clear();
+    }
+}
@@ -0,0 +1,39 @@
+use rusty_beagle2d_glfw::ogl;
+
+use serde::{Deserialize, Serialize};
+
+// How a draw is combined with what is already in the framebuffer.
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub enum BlendMode {
+    // Regular transparency, for textures with straight (non-premultiplied) alpha
+    Alpha,
+    // Adds to the destination. Useful for glows, fire and light effects
+    Additive,
+    // Darkens the destination by the source color, respecting the source alpha
+    Multiply,
+    // Transparency for textures whose color channels are already multiplied by alpha
+    PremultipliedAlpha
+}
+
+impl BlendMode {
+    pub fn apply(&self) {
+        match self {
+            BlendMode::Alpha => ogl::blend_func(ogl::BlendFactor::SrcAlpha, ogl::BlendFactor::OneMinusSrcAlpha),
+            BlendMode::Additive => ogl::blend_func(ogl::BlendFactor::SrcAlpha, ogl::BlendFactor::One),
+            // The fragment shader premultiplies its output in this mode, giving dst * (src * a + 1 - a)
+            BlendMode::Multiply => ogl::blend_func(ogl::BlendFactor::DstColor, ogl::BlendFactor::OneMinusSrcAlpha),
+            BlendMode::PremultipliedAlpha => ogl::blend_func(ogl::BlendFactor::One, ogl::BlendFactor::OneMinusSrcAlpha)
+        }
+    }
+
+    // The value of the "blendMode" uniform in the fragment shader.
+    // NOTE: Must match the constants in dat/shaders/fragment.shader.
+    pub fn get_shader_value(&self) -> i32 {
+        match self {
+            BlendMode::Alpha => 0,
+            BlendMode::Additive => 1,
+            BlendMode::Multiply => 2,
+            BlendMode::PremultipliedAlpha => 3
+        }
+    }
+}
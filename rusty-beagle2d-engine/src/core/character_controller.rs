@@ -0,0 +1,913 @@
+use crate::core::collision::shapes::{Aabb, Shape};
+use crate::core::collision::contact;
+use crate::core::collision::raycast::{self, ShapeCastHit};
+use crate::core::collision::tiles::TileCollisionGrid;
+use crate::core::physics::world::PhysicsWorld;
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+// Something the character can't move through.
+#[derive(Clone, Debug)]
+pub struct Collider {
+    pub shape: Shape,
+    // Only blocks the character from above, so it can jump up through it and stand on it
+    pub one_way: bool
+}
+
+// Where a CharacterController finds what to collide with.
+pub trait CollisionSource {
+    // Adds every collider touching "region" to "colliders".
+    fn get_colliders(&self, region: &Aabb, colliders: &mut Vec<Collider>);
+}
+
+impl CollisionSource for TileCollisionGrid {
+    fn get_colliders(&self, region: &Aabb, colliders: &mut Vec<Collider>) {
+        self.for_each_shape_in_region(region, |shape, one_way| colliders.push(Collider { shape, one_way }));
+    }
+}
+
+impl CollisionSource for Vec<Collider> {
+    fn get_colliders(&self, region: &Aabb, colliders: &mut Vec<Collider>) {
+        let nearby = self.iter().filter(|collider| collider.shape.get_aabb().overlaps(region));
+        colliders.extend(nearby.cloned());
+    }
+}
+
+// Every body that isn't a sensor. The character stands on them and is blocked by them, but doesn't push them.
+impl CollisionSource for PhysicsWorld {
+    fn get_colliders(&self, region: &Aabb, colliders: &mut Vec<Collider>) {
+        for id in self.query_region(region) {
+            let body = self.get_body(id).unwrap();
+
+            if !body.is_sensor() {
+                colliders.push(Collider { shape: body.get_world_shape(), one_way: false });
+            }
+        }
+    }
+}
+
+// What the player wants the character to do this update. Fill it from the keyboard, a gamepad, a replay or a test script.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct ControllerInput {
+    // -1.0 (left) to 1.0 (right)
+    pub move_x: f32,
+    // Held down. Pressing starts a jump, and letting go early makes it lower.
+    pub jump: bool,
+    // Drops down through the one way platform the character stands on
+    pub drop_down: bool
+}
+
+// Pixels and seconds.
+#[derive(Copy, Clone, Debug)]
+pub struct ControllerSettings {
+    pub run_speed: f32,
+    // How fast the character gets up to speed, and slows down when there is no input
+    pub ground_acceleration: f32,
+    pub ground_deceleration: f32,
+    pub air_acceleration: f32,
+    pub gravity: f32,
+    pub max_fall_speed: f32,
+    pub jump_speed: f32,
+    // The upwards speed is multiplied by this when jump is let go early
+    pub jump_cut: f32,
+    // Degrees. Steeper slopes are walls. Kept below 90, or walls would be walkable.
+    pub max_slope_angle: f32,
+    // Ledges up to this high are stepped onto without jumping
+    pub step_height: f32,
+    // How far the character is pulled down to stay on the ground when walking down slopes and steps
+    pub ground_snap_distance: f32,
+    // LEARN: Coyote time
+    // Jumping still works for a moment after walking off a ledge, like a cartoon coyote running off a cliff.
+    // Players press jump a little late all the time, and this makes the game feel fair.
+    pub coyote_time: f32,
+    // LEARN: Jump buffering
+    // Pressing jump a moment before landing still jumps when the character lands, instead of being ignored.
+    pub jump_buffer_time: f32
+}
+
+impl ControllerSettings {
+    pub fn new() -> ControllerSettings {
+        ControllerSettings {
+            run_speed: 200.0,
+            ground_acceleration: 2000.0,
+            ground_deceleration: 2500.0,
+            air_acceleration: 1200.0,
+            gravity: 1800.0,
+            max_fall_speed: 800.0,
+            jump_speed: 600.0,
+            jump_cut: 0.5,
+            max_slope_angle: 50.0,
+            step_height: 8.0,
+            ground_snap_distance: 8.0,
+            coyote_time: 0.1,
+            jump_buffer_time: 0.1
+        }
+    }
+}
+
+// How long one way platforms are ignored after dropping through one
+const DROP_THROUGH_TIME: f32 = 0.25;
+// How far below the character the ground is looked for while in the air, to notice landing while touching
+const GROUND_PROBE_DISTANCE: f32 = 0.05;
+// Moves are split at most this many times when sliding along things
+const MAX_SLIDES: usize = 4;
+// The steepest max_slope_angle works as. At 90 degrees walls would be ground.
+const MAX_WALKABLE_ANGLE: f32 = 89.0;
+
+#[derive(Copy, Clone, Debug)]
+struct MoveResult {
+    position: Vector2,
+    // Normal of a surface too steep to walk on that stopped the move
+    wall_normal: Option<Vector2>
+}
+
+// Moves a box through a level without going through walls, for the player characters of platformers.
+// It isn't a physics body: it goes exactly where the input says, and slides along what it runs into.
+// Call update from Game::fixed_update, with the same inputs for the same result.
+//
+// let mut player = CharacterController::new(Vector2::new(100.0, 100.0), Vector2::new(8.0, 14.0));
+// player.update(dt, &input, &[&level_grid]);
+pub struct CharacterController {
+    settings: ControllerSettings,
+    // The center of the box
+    position: Vector2,
+    previous_position: Vector2,
+    half_extents: Vector2,
+    velocity: Vector2,
+
+    grounded: bool,
+    // Points from the character into the ground
+    ground_normal: Vector2,
+    on_one_way: bool,
+    touching_wall: Option<f32>,
+    touching_ceiling: bool,
+    jumped: bool,
+    landed: bool,
+
+    jumping: bool,
+    jump_was_held: bool,
+    coyote_available: bool,
+    time_since_grounded: f32,
+    // Time left before a jump press is forgotten
+    jump_buffer: f32,
+    drop_through_timer: f32,
+
+    // Kept between updates so gathering the colliders doesn't allocate every time
+    colliders: Vec<Collider>
+}
+
+impl CharacterController {
+    // "position" is the center of the character's box.
+    pub fn new(position: Vector2, half_extents: Vector2) -> CharacterController {
+        if half_extents.x <= 0.0 || half_extents.y <= 0.0 {
+            panic!("The character needs a size, got half extents ({}, {})", half_extents.x, half_extents.y);
+        }
+
+        CharacterController {
+            settings: ControllerSettings::new(),
+            position,
+            previous_position: position,
+            half_extents,
+            velocity: Vector2::new(0.0, 0.0),
+            grounded: false,
+            ground_normal: Vector2::new(0.0, 1.0),
+            on_one_way: false,
+            touching_wall: None,
+            touching_ceiling: false,
+            jumped: false,
+            landed: false,
+            jumping: false,
+            jump_was_held: false,
+            coyote_available: false,
+            time_since_grounded: 0.0,
+            jump_buffer: 0.0,
+            drop_through_timer: 0.0,
+            colliders: Vec::new()
+        }
+    }
+
+    pub fn with_settings(mut self, settings: ControllerSettings) -> CharacterController {
+        self.settings = settings;
+        self
+    }
+
+    pub fn get_settings(&self) -> &ControllerSettings {
+        &self.settings
+    }
+
+    pub fn get_settings_mut(&mut self) -> &mut ControllerSettings {
+        &mut self.settings
+    }
+
+    pub fn get_position(&self) -> Vector2 {
+        self.position
+    }
+
+    // Moves the character straight there, like a respawn.
+    pub fn set_position(&mut self, position: Vector2) {
+        self.position = position;
+        self.previous_position = position;
+    }
+
+    // Where the character is between the last two updates, with "alpha" from the game loop. See Game::render.
+    pub fn get_interpolated_position(&self, alpha: f32) -> Vector2 {
+        math2d::add(self.previous_position, math2d::scale(math2d::sub(self.position, self.previous_position), alpha))
+    }
+
+    pub fn get_half_extents(&self) -> Vector2 {
+        self.half_extents
+    }
+
+    pub fn get_aabb(&self) -> Aabb {
+        Aabb::from_center(self.position, self.half_extents)
+    }
+
+    pub fn get_velocity(&self) -> Vector2 {
+        self.velocity
+    }
+
+    // For knockback, springs and the like. Negative y launches the character off the ground.
+    pub fn set_velocity(&mut self, velocity: Vector2) {
+        self.velocity = velocity;
+
+        if velocity.y < 0.0 {
+            self.grounded = false;
+            self.coyote_available = false;
+        }
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    // Points from the character into the ground. (0, 1) on flat ground.
+    pub fn get_ground_normal(&self) -> Vector2 {
+        self.ground_normal
+    }
+
+    // -1.0 if a wall stopped the character on the left in the last update, 1.0 on the right.
+    pub fn get_wall_direction(&self) -> Option<f32> {
+        self.touching_wall
+    }
+
+    pub fn is_touching_ceiling(&self) -> bool {
+        self.touching_ceiling
+    }
+
+    // Whether the character jumped in the last update.
+    pub fn has_jumped(&self) -> bool {
+        self.jumped
+    }
+
+    // Whether the character landed in the last update.
+    pub fn has_landed(&self) -> bool {
+        self.landed
+    }
+
+    pub fn update(&mut self, dt: f32, input: &ControllerInput, sources: &[&dyn CollisionSource]) {
+        self.previous_position = self.position;
+        self.jumped = false;
+        self.landed = false;
+        self.touching_wall = None;
+        self.touching_ceiling = false;
+
+        if dt <= 0.0 {
+            return;
+        }
+
+        self.update_timers(dt, input);
+        self.update_velocity(dt, input);
+
+        let motion = if self.grounded {
+            // Follow the ground, so walking up and down slopes goes as fast as on flat ground
+            let tangent = Vector2::new(self.ground_normal.y, -self.ground_normal.x);
+            math2d::scale(tangent, self.velocity.x * dt / tangent.x)
+        } else {
+            math2d::scale(self.velocity, dt)
+        };
+
+        self.gather_colliders(motion, sources);
+        self.resolve_overlaps();
+
+        let was_grounded = self.grounded;
+        let start = self.position;
+        let velocity = self.velocity;
+        let mut result = self.move_and_slide(start, motion);
+
+        // Blocked by a low ledge while walking, so try stepping onto it
+        if was_grounded && result.wall_normal.is_some() && self.settings.step_height > 0.0 {
+            if let Some(stepped) = self.try_step_up(start, motion) {
+                // The ledge didn't stop the character after all, so it keeps its speed
+                if (stepped.x - start.x).abs() > (result.position.x - start.x).abs() + 0.01 {
+                    result = MoveResult { position: stepped, wall_normal: None };
+                    self.velocity = velocity;
+                }
+            }
+        }
+
+        self.position = result.position;
+
+        if let Some(normal) = result.wall_normal {
+            if normal.x.abs() > 0.5 {
+                self.touching_wall = Some(normal.x.signum());
+            }
+        }
+
+        self.update_ground(was_grounded);
+    }
+
+    fn update_timers(&mut self, dt: f32, input: &ControllerInput) {
+        let jump_pressed = input.jump && !self.jump_was_held;
+        self.jump_was_held = input.jump;
+
+        if jump_pressed {
+            self.jump_buffer = self.settings.jump_buffer_time.max(dt);
+        } else {
+            self.jump_buffer = (self.jump_buffer - dt).max(0.0);
+        }
+
+        if self.grounded {
+            self.time_since_grounded = 0.0;
+        } else {
+            self.time_since_grounded += dt;
+        }
+
+        self.drop_through_timer = (self.drop_through_timer - dt).max(0.0);
+
+        if input.drop_down && self.grounded && self.on_one_way {
+            self.drop_through_timer = DROP_THROUGH_TIME;
+            self.grounded = false;
+            self.coyote_available = false;
+        }
+    }
+
+    fn update_velocity(&mut self, dt: f32, input: &ControllerInput) {
+        let settings = self.settings;
+
+        let target_speed = input.move_x.clamp(-1.0, 1.0) * settings.run_speed;
+        let acceleration = match (self.grounded, target_speed != 0.0) {
+            (true, true) => settings.ground_acceleration,
+            (true, false) => settings.ground_deceleration,
+            (false, _) => settings.air_acceleration
+        };
+
+        self.velocity.x = move_towards(self.velocity.x, target_speed, acceleration * dt);
+
+        let can_jump = self.grounded || (self.coyote_available && self.time_since_grounded <= settings.coyote_time);
+
+        if self.jump_buffer > 0.0 && can_jump {
+            self.velocity.y = -settings.jump_speed;
+            self.jump_buffer = 0.0;
+            self.grounded = false;
+            self.coyote_available = false;
+            self.jumping = true;
+            self.jumped = true;
+        }
+
+        // Letting go of jump early makes a lower jump
+        if self.jumping && !input.jump && self.velocity.y < 0.0 {
+            self.velocity.y *= settings.jump_cut;
+            self.jumping = false;
+        }
+
+        if self.velocity.y >= 0.0 {
+            self.jumping = false;
+        }
+
+        if self.grounded {
+            self.velocity.y = 0.0;
+        } else {
+            self.velocity.y = (self.velocity.y + settings.gravity * dt).min(settings.max_fall_speed);
+        }
+    }
+
+    fn gather_colliders(&mut self, motion: Vector2, sources: &[&dyn CollisionSource]) {
+        let reach = math2d::length(motion) + self.settings.step_height + self.settings.ground_snap_distance + 1.0;
+        let region = self.get_aabb().expanded(reach);
+
+        self.colliders.clear();
+
+        for source in sources {
+            source.get_colliders(&region, &mut self.colliders);
+        }
+    }
+
+    // Pushes the character out of anything it ended up inside, like a moving platform or a tile placed on it
+    fn resolve_overlaps(&mut self) {
+        for _ in 0..MAX_SLIDES {
+            let shape = self.get_shape_at(self.position);
+
+            let deepest = self.colliders.iter()
+                .filter(|collider| !collider.one_way)
+                .filter_map(|collider| contact::collide(&shape, &collider.shape))
+                .filter(|manifold| manifold.depth > 0.01)
+                .max_by(|a, b| a.depth.partial_cmp(&b.depth).unwrap_or(std::cmp::Ordering::Equal));
+
+            match deepest {
+                Some(manifold) => self.position = math2d::sub(self.position, math2d::scale(manifold.normal, manifold.depth)),
+                None => break
+            }
+        }
+    }
+
+    // LEARN: Move and slide
+    // The box is swept along the motion until it hits something. What is left of the motion is turned to go along
+    // The surface that was hit, and swept again. So running into a wall at an angle slides along it.
+    fn move_and_slide(&mut self, start: Vector2, motion: Vector2) -> MoveResult {
+        let mut position = start;
+        let mut remaining = motion;
+        let mut wall_normal = None;
+
+        for _ in 0..MAX_SLIDES {
+            if math2d::length_squared(remaining) <= std::f32::EPSILON {
+                break;
+            }
+
+            let hit = match self.cast(position, remaining) {
+                Some(hit) => hit,
+                None => {
+                    position = math2d::add(position, remaining);
+                    break;
+                }
+            };
+
+            position = math2d::add(position, math2d::scale(remaining, hit.time));
+            remaining = math2d::scale(remaining, 1.0 - hit.time);
+
+            // Slopes too steep to walk up are walls while on the ground, otherwise the slide would push up them
+            let walkable = self.is_walkable(hit.normal);
+            let slide_normal = if self.grounded && !walkable && hit.normal.y > 0.0 {
+                Vector2::new(hit.normal.x.signum(), 0.0)
+            } else {
+                hit.normal
+            };
+
+            // Take out the part going into the surface
+            remaining = math2d::sub(remaining, math2d::scale(slide_normal, math2d::dot(remaining, slide_normal)));
+
+            if walkable {
+                // Landing keeps the speed along the ground, rather than sliding down the slope
+                self.velocity.y = self.velocity.y.min(0.0);
+            } else {
+                let into_surface = math2d::dot(self.velocity, slide_normal);
+
+                if into_surface > 0.0 {
+                    self.velocity = math2d::sub(self.velocity, math2d::scale(slide_normal, into_surface));
+                }
+
+                if hit.normal.y < -0.5 {
+                    self.touching_ceiling = true;
+                    self.jumping = false;
+                } else {
+                    wall_normal = Some(hit.normal);
+                }
+            }
+        }
+
+        MoveResult {
+            position,
+            wall_normal
+        }
+    }
+
+    // Up by the step height, across, and back down onto the ledge. None if there's no ground to stand on there.
+    fn try_step_up(&self, start: Vector2, motion: Vector2) -> Option<Vector2> {
+        let up = Vector2::new(0.0, -self.settings.step_height);
+        let raised = match self.cast(start, up) {
+            Some(hit) => math2d::add(start, math2d::scale(up, hit.time)),
+            None => math2d::add(start, up)
+        };
+
+        let across = Vector2::new(motion.x, 0.0);
+        let moved = match self.cast(raised, across) {
+            Some(hit) => math2d::add(raised, math2d::scale(across, hit.time)),
+            None => math2d::add(raised, across)
+        };
+
+        let down = Vector2::new(0.0, start.y - raised.y + self.settings.ground_snap_distance);
+        let hit = self.cast(moved, down)?;
+
+        if !self.is_walkable(hit.normal) {
+            return None;
+        }
+
+        Some(math2d::add(moved, math2d::scale(down, hit.time)))
+    }
+
+    fn update_ground(&mut self, was_grounded: bool) {
+        self.on_one_way = false;
+
+        // Going up, so not on the ground
+        if self.velocity.y < 0.0 {
+            self.grounded = false;
+            return;
+        }
+
+        // Stick to the ground when walking down slopes and off small steps, but not after jumping or falling
+        let probe_distance = if was_grounded { self.settings.ground_snap_distance } else { GROUND_PROBE_DISTANCE };
+        let probe = Vector2::new(0.0, probe_distance);
+
+        let ground = self.cast(self.position, probe).filter(|hit| self.is_walkable(hit.normal));
+
+        match ground {
+            Some(hit) => {
+                self.position = math2d::add(self.position, math2d::scale(probe, hit.time));
+                self.ground_normal = hit.normal;
+                self.velocity.y = 0.0;
+                self.coyote_available = true;
+
+                self.on_one_way = self.get_touching_one_way(self.position);
+
+                if !was_grounded {
+                    self.landed = true;
+                }
+
+                self.grounded = true;
+            },
+            None => {
+                self.grounded = false;
+                self.ground_normal = Vector2::new(0.0, 1.0);
+            }
+        }
+    }
+
+    // The first thing the box hits moving from "position" by "motion"
+    fn cast(&self, position: Vector2, motion: Vector2) -> Option<ShapeCastHit> {
+        let shape = self.get_shape_at(position);
+        let bottom = position.y + self.half_extents.y;
+
+        self.colliders.iter()
+            .filter(|collider| !collider.one_way || self.blocks_one_way(collider, bottom, motion))
+            .filter_map(|collider| raycast::shape_cast(&shape, motion, &collider.shape))
+            // Touching something while moving along it or away from it isn't a hit
+            .filter(|hit| hit.time > 0.0 || math2d::dot(hit.normal, motion) > 0.0001 * math2d::length(motion))
+            .min_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    // One way platforms only block falling onto them from above
+    fn blocks_one_way(&self, collider: &Collider, bottom: f32, motion: Vector2) -> bool {
+        self.drop_through_timer <= 0.0 && motion.y > 0.0 && bottom <= collider.shape.get_aabb().min.y + 0.05
+    }
+
+    fn get_touching_one_way(&self, position: Vector2) -> bool {
+        let shape = self.get_shape_at(position);
+        let probe = Vector2::new(0.0, GROUND_PROBE_DISTANCE);
+
+        self.colliders.iter()
+            .filter(|collider| collider.one_way)
+            .filter_map(|collider| raycast::shape_cast(&shape, probe, &collider.shape))
+            .any(|hit| self.is_walkable(hit.normal))
+    }
+
+    // Up to the max slope angle
+    fn is_walkable(&self, normal: Vector2) -> bool {
+        let max_slope_angle = self.settings.max_slope_angle.clamp(0.0, MAX_WALKABLE_ANGLE);
+        normal.y >= math2d::degrees_to_radians(max_slope_angle).cos() - 0.0001
+    }
+
+    fn get_shape_at(&self, position: Vector2) -> Shape {
+        Shape::Aabb(Aabb::from_center(position, self.half_extents))
+    }
+}
+
+fn move_towards(current: f32, target: f32, max_change: f32) -> f32 {
+    if (target - current).abs() <= max_change {
+        target
+    } else {
+        current + (target - current).signum() * max_change
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::collision::shapes::Polygon;
+
+    const DT: f32 = 1.0 / 60.0;
+    const HALF_EXTENTS: Vector2 = Vector2 { x: 8.0, y: 14.0 };
+
+    fn v(x: f32, y: f32) -> Vector2 {
+        Vector2::new(x, y)
+    }
+
+    // Solid ground from min_x to max_x, with its top at "top"
+    fn ground(min_x: f32, max_x: f32, top: f32) -> Collider {
+        Collider { shape: Shape::Aabb(Aabb::new(v(min_x, top), v(max_x, top + 40.0))), one_way: false }
+    }
+
+    fn one_way(min_x: f32, max_x: f32, top: f32) -> Collider {
+        Collider { shape: Shape::Aabb(Aabb::new(v(min_x, top), v(max_x, top + 4.0))), one_way: true }
+    }
+
+    // Flat ground up to x = 0, then a slope going up to the right at "angle" degrees until x = 100, then flat again
+    fn slope_level(angle: f32) -> Vec<Collider> {
+        let height = 100.0 * math2d::degrees_to_radians(angle).tan();
+
+        vec![
+            ground(-300.0, 0.0, 0.0),
+            Collider { shape: Shape::Polygon(Polygon::new(&[v(0.0, 0.0), v(100.0, -height), v(100.0, 0.0)])), one_way: false },
+            ground(100.0, 400.0, -height)
+        ]
+    }
+
+    fn input(move_x: f32, jump: bool, drop_down: bool) -> ControllerInput {
+        ControllerInput { move_x, jump, drop_down }
+    }
+
+    // Standing on ground whose top is at "top"
+    fn standing_at(x: f32, top: f32, level: &Vec<Collider>) -> CharacterController {
+        let mut player = CharacterController::new(v(x, top - HALF_EXTENTS.y), HALF_EXTENTS);
+        player.update(DT, &ControllerInput::default(), &[level]);
+
+        assert!(player.is_grounded());
+        player
+    }
+
+    fn run(player: &mut CharacterController, level: &Vec<Collider>, input: ControllerInput, frames: u32) {
+        for _ in 0..frames {
+            player.update(DT, &input, &[level]);
+        }
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 0.01, "{} isn't {}", value, expected);
+    }
+
+    #[test]
+    fn falls_and_lands() {
+        let level = vec![ground(-100.0, 100.0, 0.0)];
+        let mut player = CharacterController::new(v(0.0, -100.0), HALF_EXTENTS);
+        let mut landings = 0;
+
+        for _ in 0..60 {
+            player.update(DT, &ControllerInput::default(), &[&level]);
+            landings += player.has_landed() as u32;
+        }
+
+        assert_eq!(landings, 1);
+        assert!(player.is_grounded());
+        assert_close(player.get_position().y, -HALF_EXTENTS.y);
+        assert_eq!(player.get_velocity().y, 0.0);
+        assert_eq!((player.get_ground_normal().x, player.get_ground_normal().y), (0.0, 1.0));
+    }
+
+    #[test]
+    fn runs_up_to_speed_and_stops() {
+        let level = vec![ground(-1000.0, 1000.0, 0.0)];
+        let mut player = standing_at(0.0, 0.0, &level);
+
+        // 2000 pixels per second squared gets to 200 in a tenth of a second
+        run(&mut player, &level, input(1.0, false, false), 6);
+        assert_close(player.get_velocity().x, 200.0);
+
+        run(&mut player, &level, input(0.0, false, false), 5);
+        assert_eq!(player.get_velocity().x, 0.0);
+        assert_close(player.get_position().y, -HALF_EXTENTS.y);
+    }
+
+    #[test]
+    fn walks_up_slopes_up_to_the_max_angle() {
+        for &angle in [20.0, 40.0, 50.0].iter() {
+            let level = slope_level(angle);
+            let mut player = standing_at(-50.0, 0.0, &level);
+
+            // Keeps running at full speed onto and up the slope
+            run(&mut player, &level, input(1.0, false, false), 10);
+
+            for _ in 0..20 {
+                player.update(DT, &input(1.0, false, false), &[&level]);
+                assert_eq!(player.get_velocity().x, 200.0, "Slowed down on a {} degree slope", angle);
+            }
+
+            let position = player.get_position();
+            // Standing on the slope with the bottom right corner of the box
+            let slope_top = -(position.x + HALF_EXTENTS.x).min(100.0) * math2d::degrees_to_radians(angle).tan();
+
+            assert!(player.is_grounded(), "Fell off a {} degree slope", angle);
+            assert!(position.x > 20.0, "Stopped at {} on a {} degree slope", position.x, angle);
+            assert!((position.y + HALF_EXTENTS.y - slope_top).abs() < 0.1, "At {:?} on a {} degree slope", position, angle);
+            assert_eq!(player.get_wall_direction(), None);
+        }
+    }
+
+    #[test]
+    fn steeper_slopes_are_walls() {
+        for &angle in [55.0, 70.0].iter() {
+            let level = slope_level(angle);
+            let mut player = standing_at(-50.0, 0.0, &level);
+
+            run(&mut player, &level, input(1.0, false, false), 30);
+
+            // Stopped at the foot of the slope, without climbing it
+            assert!(player.is_grounded());
+            assert!(player.get_position().x <= -HALF_EXTENTS.x + 0.1, "Got to {} on a {} degree slope", player.get_position().x, angle);
+            assert_close(player.get_position().y, -HALF_EXTENTS.y);
+            assert_eq!(player.get_wall_direction(), Some(1.0));
+        }
+    }
+
+    #[test]
+    fn max_slope_angle_stays_below_90_degrees() {
+        for &max_slope_angle in [90.0, 120.0].iter() {
+            let mut settings = ControllerSettings::new();
+            settings.max_slope_angle = max_slope_angle;
+
+            let player = CharacterController::new(v(0.0, 0.0), HALF_EXTENTS).with_settings(settings);
+            assert!(!player.is_walkable(v(1.0, 0.0)));
+            assert!(!player.is_walkable(v(-1.0, 0.0)));
+            assert!(player.is_walkable(v(0.99, 0.1411)));
+
+            // Falling along a wall, pushing into it, doesn't stand on it
+            let level = vec![ground(10.0, 50.0, -500.0), ground(-100.0, 100.0, 0.0)];
+            let mut falling = CharacterController::new(v(2.0, -300.0), HALF_EXTENTS).with_settings(settings);
+            falling.get_settings_mut().air_acceleration = 10000.0;
+
+            run(&mut falling, &level, input(1.0, false, false), 10);
+            assert!(!falling.is_grounded());
+            assert_close(falling.get_velocity().y, 10.0 * DT * settings.gravity);
+        }
+    }
+
+    #[test]
+    fn steps_up_low_ledges() {
+        let level = vec![ground(-200.0, 0.0, 0.0), ground(0.0, 200.0, -6.0)];
+        let mut player = standing_at(-50.0, 0.0, &level);
+
+        run(&mut player, &level, input(1.0, false, false), 10);
+
+        for _ in 0..20 {
+            player.update(DT, &input(1.0, false, false), &[&level]);
+            assert_eq!(player.get_velocity().x, 200.0);
+        }
+
+        assert!(player.get_position().x > 20.0);
+        assert_close(player.get_position().y, -6.0 - HALF_EXTENTS.y);
+        assert!(player.is_grounded());
+    }
+
+    #[test]
+    fn high_ledges_block() {
+        let level = vec![ground(-200.0, 0.0, 0.0), ground(0.0, 200.0, -12.0)];
+        let mut player = standing_at(-50.0, 0.0, &level);
+
+        run(&mut player, &level, input(1.0, false, false), 30);
+
+        assert_close(player.get_position().x, -HALF_EXTENTS.x);
+        assert_close(player.get_position().y, -HALF_EXTENTS.y);
+        assert_eq!(player.get_wall_direction(), Some(1.0));
+    }
+
+    #[test]
+    fn jumps_up_through_one_way_platforms_and_drops_down() {
+        let level = vec![ground(-200.0, 200.0, 0.0), one_way(-50.0, 50.0, -60.0)];
+        let mut player = standing_at(0.0, 0.0, &level);
+
+        // A full jump goes 100 pixels up, past the platform
+        player.update(DT, &input(0.0, true, false), &[&level]);
+        assert!(player.has_jumped());
+
+        let mut landed_on_platform = false;
+
+        for _ in 0..90 {
+            player.update(DT, &input(0.0, true, false), &[&level]);
+            landed_on_platform |= player.has_landed();
+        }
+
+        assert!(landed_on_platform);
+        assert!(player.is_grounded());
+        assert_close(player.get_position().y, -60.0 - HALF_EXTENTS.y);
+
+        // Down drops through, and it doesn't catch the character again on the way down
+        let mut frames = 0;
+        player.update(DT, &input(0.0, false, true), &[&level]);
+
+        while !player.is_grounded() {
+            player.update(DT, &input(0.0, false, false), &[&level]);
+            frames += 1;
+            assert!(frames < 60);
+        }
+
+        assert_close(player.get_position().y, -HALF_EXTENTS.y);
+
+        // Down on solid ground does nothing
+        player.update(DT, &input(0.0, false, true), &[&level]);
+        assert!(player.is_grounded());
+        assert_close(player.get_position().y, -HALF_EXTENTS.y);
+    }
+
+    #[test]
+    fn walks_through_one_way_platforms_from_the_side() {
+        let level = vec![ground(-200.0, 200.0, 0.0), one_way(0.0, 100.0, -20.0)];
+        let mut player = standing_at(-50.0, 0.0, &level);
+
+        run(&mut player, &level, input(1.0, false, false), 30);
+
+        assert!(player.get_position().x > 40.0);
+        assert_close(player.get_position().y, -HALF_EXTENTS.y);
+    }
+
+    // Walks right off a ledge at x = 0, then presses jump "frames_late" frames after leaving the ground
+    fn jump_after_leaving_ground(frames_late: u32) -> bool {
+        let level = vec![ground(-200.0, 0.0, 0.0), ground(-200.0, 400.0, 500.0)];
+        let mut player = standing_at(-20.0, 0.0, &level);
+
+        while player.is_grounded() {
+            player.update(DT, &input(1.0, false, false), &[&level]);
+        }
+
+        for _ in 0..frames_late {
+            player.update(DT, &input(1.0, false, false), &[&level]);
+        }
+
+        player.update(DT, &input(1.0, true, false), &[&level]);
+        player.has_jumped()
+    }
+
+    #[test]
+    fn coyote_time() {
+        // 0.1 seconds is 6 frames, counting the first frame in the air
+        for frames_late in 0..5 {
+            assert!(jump_after_leaving_ground(frames_late), "No jump {} frames late", frames_late);
+        }
+
+        assert!(!jump_after_leaving_ground(6));
+        assert!(!jump_after_leaving_ground(20));
+    }
+
+    #[test]
+    fn coyote_time_is_once_per_ledge() {
+        let level = vec![ground(-200.0, 0.0, 0.0)];
+        let mut player = standing_at(-20.0, 0.0, &level);
+
+        player.update(DT, &input(0.0, true, false), &[&level]);
+        assert!(player.has_jumped());
+
+        // Pressing again in the air right after jumping doesn't jump again
+        player.update(DT, &input(0.0, false, false), &[&level]);
+        player.update(DT, &input(0.0, true, false), &[&level]);
+        assert!(!player.has_jumped());
+    }
+
+    // Drops from high up and presses jump "frames_early" frames before the frame it lands on
+    fn jump_before_landing(frames_early: u32) -> bool {
+        let level = vec![ground(-200.0, 200.0, 0.0)];
+        let start = v(0.0, -200.0);
+
+        // A dry run finds the landing frame
+        let mut player = CharacterController::new(start, HALF_EXTENTS);
+        let mut landing_frame = 0;
+
+        while !player.has_landed() {
+            player.update(DT, &ControllerInput::default(), &[&level]);
+            landing_frame += 1;
+        }
+
+        let mut player = CharacterController::new(start, HALF_EXTENTS);
+
+        for frame in 1..=landing_frame + 10 {
+            player.update(DT, &input(0.0, frame + frames_early >= landing_frame, false), &[&level]);
+
+            if player.has_jumped() {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    #[test]
+    fn jump_buffer() {
+        for frames_early in 0..5 {
+            assert!(jump_before_landing(frames_early), "No jump {} frames early", frames_early);
+        }
+
+        // Holding jump from long before landing doesn't count as pressing it
+        assert!(!jump_before_landing(6));
+        assert!(!jump_before_landing(30));
+    }
+
+    #[test]
+    fn letting_go_of_jump_early_jumps_lower() {
+        let level = vec![ground(-200.0, 200.0, 0.0)];
+
+        let get_jump_height = |held_frames: u32| {
+            let mut player = standing_at(0.0, 0.0, &level);
+            let mut highest = 0.0f32;
+
+            for frame in 0..90 {
+                player.update(DT, &input(0.0, frame < held_frames, false), &[&level]);
+                highest = highest.min(player.get_position().y + HALF_EXTENTS.y);
+            }
+
+            assert!(player.is_grounded());
+            -highest
+        };
+
+        // jump_speed² / (2 * gravity) is 100 pixels
+        let full = get_jump_height(90);
+        assert!((full - 100.0).abs() < 6.0, "Jumped {}", full);
+        let short = get_jump_height(2);
+        assert!(short > 10.0 && short < full * 0.5, "Jumped {}", short);
+    }
+}
@@ -0,0 +1,540 @@
+use crate::core::collision::shapes::Aabb;
+use crate::core::collision::broadphase::{Broadphase, ProxyId, ProxySlots, Ray};
+
+use linear_beaglebra::vector2::Vector2;
+
+const NULL_NODE: u32 = u32::MAX;
+
+#[derive(Copy, Clone, Debug)]
+struct TreeNode {
+    // For leaves, the object's box grown by the tree's margin
+    aabb: Aabb,
+    parent: u32,
+    child1: u32,
+    child2: u32,
+    // 0 for leaves
+    height: i32,
+    // The proxy slot of a leaf
+    proxy: u32
+}
+
+impl TreeNode {
+    fn is_leaf(&self) -> bool {
+        self.child1 == NULL_NODE
+    }
+}
+
+struct TreeProxy<T> {
+    aabb: Aabb,
+    leaf: u32,
+    data: T
+}
+
+// LEARN: Dynamic AABB tree (as in Box2D's b2DynamicTree)
+// A binary tree where each node's box holds both of its children's boxes, and the objects are the leaves.
+// Searching skips every branch whose box misses what's searched for. New leaves go where they grow the boxes
+// The least, and the tree is rebalanced with rotations like an AVL tree, so it stays about log(n) deep.
+//
+// Leaves get a bit of margin ("fat" boxes), so objects moving a little don't change the tree at all.
+pub struct AabbTree<T> {
+    nodes: Vec<TreeNode>,
+    free_nodes: Vec<u32>,
+    root: u32,
+    proxies: ProxySlots<TreeProxy<T>>,
+    margin: f32
+}
+
+impl<T> AabbTree<T> {
+    // "margin" is how far objects can move before the tree needs updating. A few pixels is usually good.
+    pub fn new(margin: f32) -> AabbTree<T> {
+        if margin.is_nan() || margin < 0.0 {
+            panic!("The margin can't be negative, got {}", margin);
+        }
+
+        AabbTree {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            root: NULL_NODE,
+            proxies: ProxySlots::new(),
+            margin
+        }
+    }
+
+    // Number of levels, 0 for an empty tree.
+    pub fn get_height(&self) -> i32 {
+        if self.root == NULL_NODE {
+            0
+        } else {
+            self.nodes[self.root as usize].height + 1
+        }
+    }
+
+    fn allocate_node(&mut self, aabb: Aabb, proxy: u32) -> u32 {
+        let node = TreeNode {
+            aabb,
+            parent: NULL_NODE,
+            child1: NULL_NODE,
+            child2: NULL_NODE,
+            height: 0,
+            proxy
+        };
+
+        match self.free_nodes.pop() {
+            Some(index) => {
+                self.nodes[index as usize] = node;
+                index
+            },
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: u32) {
+        if self.root == NULL_NODE {
+            self.root = leaf;
+            self.nodes[leaf as usize].parent = NULL_NODE;
+            return;
+        }
+
+        // Go down the tree to the sibling that makes the boxes grow the least.
+        // NOTE: In 2D the perimeter stands in for the surface area used in 3D.
+        let leaf_aabb = self.nodes[leaf as usize].aabb;
+        let mut index = self.root;
+
+        while !self.nodes[index as usize].is_leaf() {
+            let node = self.nodes[index as usize];
+
+            let area = node.aabb.get_perimeter();
+            let combined_area = node.aabb.merge(&leaf_aabb).get_perimeter();
+
+            // Making a new parent for this node and the leaf
+            let cost = 2.0 * combined_area;
+
+            // Going further down grows this node's box for sure
+            let inheritance_cost = 2.0 * (combined_area - area);
+
+            let child_cost = |child: u32| {
+                let child = &self.nodes[child as usize];
+                let grown = leaf_aabb.merge(&child.aabb).get_perimeter();
+
+                if child.is_leaf() {
+                    grown + inheritance_cost
+                } else {
+                    grown - child.aabb.get_perimeter() + inheritance_cost
+                }
+            };
+
+            let cost1 = child_cost(node.child1);
+            let cost2 = child_cost(node.child2);
+
+            if cost < cost1 && cost < cost2 {
+                break;
+            }
+
+            index = if cost1 < cost2 { node.child1 } else { node.child2 };
+        }
+
+        let sibling = index;
+
+        // Put a new parent in the sibling's place, with the sibling and the leaf under it
+        let old_parent = self.nodes[sibling as usize].parent;
+        let new_parent = self.allocate_node(leaf_aabb.merge(&self.nodes[sibling as usize].aabb), 0);
+
+        self.nodes[new_parent as usize].parent = old_parent;
+        self.nodes[new_parent as usize].height = self.nodes[sibling as usize].height + 1;
+        self.nodes[new_parent as usize].child1 = sibling;
+        self.nodes[new_parent as usize].child2 = leaf;
+        self.nodes[sibling as usize].parent = new_parent;
+        self.nodes[leaf as usize].parent = new_parent;
+
+        if old_parent == NULL_NODE {
+            self.root = new_parent;
+        } else {
+            self.replace_child(old_parent, sibling, new_parent);
+        }
+
+        self.refit_from(self.nodes[leaf as usize].parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: u32) {
+        if leaf == self.root {
+            self.root = NULL_NODE;
+            return;
+        }
+
+        let parent = self.nodes[leaf as usize].parent;
+        let grandparent = self.nodes[parent as usize].parent;
+        let sibling = if self.nodes[parent as usize].child1 == leaf {
+            self.nodes[parent as usize].child2
+        } else {
+            self.nodes[parent as usize].child1
+        };
+
+        // The sibling takes the parent's place
+        self.nodes[sibling as usize].parent = grandparent;
+        self.free_nodes.push(parent);
+
+        if grandparent == NULL_NODE {
+            self.root = sibling;
+        } else {
+            self.replace_child(grandparent, parent, sibling);
+            self.refit_from(grandparent);
+        }
+    }
+
+    fn replace_child(&mut self, parent: u32, old_child: u32, new_child: u32) {
+        let parent = &mut self.nodes[parent as usize];
+
+        if parent.child1 == old_child {
+            parent.child1 = new_child;
+        } else {
+            parent.child2 = new_child;
+        }
+    }
+
+    // Fixes the boxes and heights from "index" up to the root, rebalancing on the way.
+    fn refit_from(&mut self, mut index: u32) {
+        while index != NULL_NODE {
+            index = self.balance(index);
+
+            let node = self.nodes[index as usize];
+            let child1 = self.nodes[node.child1 as usize];
+            let child2 = self.nodes[node.child2 as usize];
+
+            self.nodes[index as usize].height = 1 + child1.height.max(child2.height);
+            self.nodes[index as usize].aabb = child1.aabb.merge(&child2.aabb);
+
+            index = node.parent;
+        }
+    }
+
+    // If one child of "a" is more than one level deeper than the other, rotates the deeper child up into a's place.
+    // Returns the node now in a's place.
+    fn balance(&mut self, a: u32) -> u32 {
+        let node_a = self.nodes[a as usize];
+
+        if node_a.is_leaf() || node_a.height < 2 {
+            return a;
+        }
+
+        let b = node_a.child1;
+        let c = node_a.child2;
+        let balance = self.nodes[c as usize].height - self.nodes[b as usize].height;
+
+        if balance > 1 {
+            self.rotate_up(a, c, b, false)
+        } else if balance < -1 {
+            self.rotate_up(a, b, c, true)
+        } else {
+            a
+        }
+    }
+
+    // Moves "up" (a child of "a") into a's place, with "a" as its first child.
+    // "other" is a's other child. The deeper child of "up" stays with it, the other goes to "a".
+    fn rotate_up(&mut self, a: u32, up: u32, other: u32, up_was_child1: bool) -> u32 {
+        let node_up = self.nodes[up as usize];
+        let f = node_up.child1;
+        let g = node_up.child2;
+
+        // "up" takes a's place
+        self.nodes[up as usize].child1 = a;
+        self.nodes[up as usize].parent = self.nodes[a as usize].parent;
+        self.nodes[a as usize].parent = up;
+
+        let up_parent = self.nodes[up as usize].parent;
+        if up_parent == NULL_NODE {
+            self.root = up;
+        } else {
+            self.replace_child(up_parent, a, up);
+        }
+
+        let (keep, give) = if self.nodes[f as usize].height > self.nodes[g as usize].height { (f, g) } else { (g, f) };
+
+        self.nodes[up as usize].child2 = keep;
+
+        if up_was_child1 {
+            self.nodes[a as usize].child1 = give;
+        } else {
+            self.nodes[a as usize].child2 = give;
+        }
+
+        self.nodes[give as usize].parent = a;
+
+        let other_node = self.nodes[other as usize];
+        let give_node = self.nodes[give as usize];
+        let keep_node = self.nodes[keep as usize];
+
+        self.nodes[a as usize].aabb = other_node.aabb.merge(&give_node.aabb);
+        self.nodes[a as usize].height = 1 + other_node.height.max(give_node.height);
+
+        let node_a = self.nodes[a as usize];
+        self.nodes[up as usize].aabb = node_a.aabb.merge(&keep_node.aabb);
+        self.nodes[up as usize].height = 1 + node_a.height.max(keep_node.height);
+
+        up
+    }
+
+    // Calls "f" with each leaf whose fat box overlaps "region".
+    fn for_each_leaf_in<F: FnMut(u32)>(&self, region: &Aabb, mut f: F) {
+        if self.root == NULL_NODE {
+            return;
+        }
+
+        let mut stack = vec![self.root];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index as usize];
+
+            if !node.aabb.overlaps(region) {
+                continue;
+            }
+
+            if node.is_leaf() {
+                f(node.proxy);
+            } else {
+                stack.push(node.child1);
+                stack.push(node.child2);
+            }
+        }
+    }
+}
+
+impl<T> Broadphase<T> for AabbTree<T> {
+    fn insert(&mut self, aabb: Aabb, data: T) -> ProxyId {
+        let id = self.proxies.insert(TreeProxy { aabb, leaf: NULL_NODE, data });
+
+        let leaf = self.allocate_node(aabb.expanded(self.margin), id.index);
+        self.proxies.get_mut(id).unwrap().leaf = leaf;
+        self.insert_leaf(leaf);
+
+        id
+    }
+
+    fn remove(&mut self, id: ProxyId) -> Option<T> {
+        let proxy = self.proxies.remove(id)?;
+
+        self.remove_leaf(proxy.leaf);
+        self.free_nodes.push(proxy.leaf);
+
+        Some(proxy.data)
+    }
+
+    fn move_proxy(&mut self, id: ProxyId, aabb: Aabb) {
+        let margin = self.margin;
+        let proxy = self.proxies.get_mut(id).expect("Can't move a proxy that was removed");
+        proxy.aabb = aabb;
+
+        let leaf = proxy.leaf;
+
+        // Still inside its fat box, so the tree doesn't change
+        if self.nodes[leaf as usize].aabb.contains(&aabb) {
+            return;
+        }
+
+        self.remove_leaf(leaf);
+        self.nodes[leaf as usize].aabb = aabb.expanded(margin);
+        self.insert_leaf(leaf);
+    }
+
+    fn contains(&self, id: ProxyId) -> bool {
+        self.proxies.get(id).is_some()
+    }
+
+    fn get_aabb(&self, id: ProxyId) -> Option<Aabb> {
+        self.proxies.get(id).map(|proxy| proxy.aabb)
+    }
+
+    fn get_data(&self, id: ProxyId) -> Option<&T> {
+        self.proxies.get(id).map(|proxy| &proxy.data)
+    }
+
+    fn get_data_mut(&mut self, id: ProxyId) -> Option<&mut T> {
+        self.proxies.get_mut(id).map(|proxy| &mut proxy.data)
+    }
+
+    fn get_proxy_count(&self) -> usize {
+        self.proxies.get_count()
+    }
+
+    fn for_each_in_region<F: FnMut(ProxyId)>(&self, region: &Aabb, mut f: F) {
+        self.for_each_leaf_in(region, |proxy| {
+            // The fat box overlapping doesn't mean the object does
+            if self.proxies.get_by_index(proxy).aabb.overlaps(region) {
+                f(self.proxies.get_id(proxy));
+            }
+        });
+    }
+
+    fn for_each_pair<F: FnMut(ProxyId, ProxyId)>(&self, mut f: F) {
+        let mut stack = vec![self.root];
+
+        // Search the tree with every leaf
+        while let Some(index) = stack.pop() {
+            if index == NULL_NODE {
+                continue;
+            }
+
+            let node = self.nodes[index as usize];
+
+            if !node.is_leaf() {
+                stack.push(node.child1);
+                stack.push(node.child2);
+                continue;
+            }
+
+            let id = self.proxies.get_id(node.proxy);
+            let aabb = self.proxies.get_by_index(node.proxy).aabb;
+
+            self.for_each_in_region(&aabb, |other| {
+                // Each pair is found from both sides, so keep one
+                if id < other {
+                    f(id, other);
+                }
+            });
+        }
+    }
+
+    fn raycast<F: FnMut(ProxyId, f32) -> Option<f32>>(&self, origin: Vector2, direction: Vector2, max_distance: f32, mut hit_test: F) -> Option<(ProxyId, f32)> {
+        let ray = Ray::new(origin, direction)?;
+
+        if self.root == NULL_NODE {
+            return None;
+        }
+
+        let mut closest: Option<(ProxyId, f32)> = None;
+        let mut stack = vec![self.root];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index as usize];
+
+            // Branches further away than the closest hit so far are skipped
+            let limit = closest.map_or(max_distance, |(_, distance)| distance);
+
+            if ray.intersect_aabb(&node.aabb, limit).is_none() {
+                continue;
+            }
+
+            if !node.is_leaf() {
+                stack.push(node.child1);
+                stack.push(node.child2);
+                continue;
+            }
+
+            if ray.intersect_aabb(&self.proxies.get_by_index(node.proxy).aabb, limit).is_none() {
+                continue;
+            }
+
+            let id = self.proxies.get_id(node.proxy);
+
+            if let Some(distance) = hit_test(id, limit) {
+                if distance <= limit {
+                    closest = Some((id, distance));
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> Aabb {
+        Aabb::new(Vector2::new(x, y), Vector2::new(x + size, y + size))
+    }
+
+    // Every node's box holds its children's, and the heights add up
+    fn check_tree<T>(tree: &AabbTree<T>, index: u32) -> i32 {
+        let node = tree.nodes[index as usize];
+
+        if node.is_leaf() {
+            assert!(node.aabb.contains(&tree.proxies.get_by_index(node.proxy).aabb));
+            return 0;
+        }
+
+        assert_eq!(tree.nodes[node.child1 as usize].parent, index);
+        assert_eq!(tree.nodes[node.child2 as usize].parent, index);
+        assert!(node.aabb.contains(&tree.nodes[node.child1 as usize].aabb));
+        assert!(node.aabb.contains(&tree.nodes[node.child2 as usize].aabb));
+
+        let height1 = check_tree(tree, node.child1);
+        let height2 = check_tree(tree, node.child2);
+        assert!((height1 - height2).abs() <= 1, "Unbalanced at node {}", index);
+        assert_eq!(node.height, 1 + height1.max(height2));
+
+        node.height
+    }
+
+    #[test]
+    fn stays_balanced_with_objects_in_a_row() {
+        let mut tree = AabbTree::new(1.0);
+        let mut ids = Vec::new();
+
+        // Inserting in order is the worst case for an unbalanced tree
+        for index in 0..256 {
+            ids.push(tree.insert(square(index as f32 * 10.0, 0.0, 5.0), index));
+        }
+
+        check_tree(&tree, tree.root);
+        assert!(tree.get_height() <= 12, "Height {}", tree.get_height());
+
+        for id in ids.iter().step_by(2) {
+            tree.remove(*id);
+        }
+
+        check_tree(&tree, tree.root);
+        assert!(tree.get_height() <= 11, "Height {}", tree.get_height());
+    }
+
+    #[test]
+    fn removed_nodes_are_reused() {
+        let mut tree = AabbTree::new(1.0);
+
+        for index in 0..8 {
+            let id = tree.insert(square(index as f32 * 10.0, 0.0, 5.0), index);
+            tree.remove(id);
+        }
+
+        assert_eq!(tree.get_height(), 0);
+        assert_eq!(tree.nodes.len(), 1);
+
+        for index in 0..8 {
+            tree.insert(square(index as f32 * 10.0, 0.0, 5.0), index);
+        }
+
+        // 8 leaves and 7 parents
+        assert_eq!(tree.nodes.len(), 15);
+    }
+
+    #[test]
+    fn small_moves_keep_the_fat_box() {
+        let mut tree = AabbTree::new(2.0);
+        let id = tree.insert(square(0.0, 0.0, 5.0), 0);
+        tree.insert(square(20.0, 0.0, 5.0), 1);
+
+        let leaf = tree.proxies.get(id).unwrap().leaf;
+        let fat = tree.nodes[leaf as usize].aabb;
+
+        tree.move_proxy(id, square(1.5, -1.5, 5.0));
+        assert_eq!(tree.nodes[leaf as usize].aabb.min.x, fat.min.x);
+
+        // The object's own box is used for queries, not the fat one
+        assert!(tree.query_region(&square(-1.0, 4.0, 0.0)).is_empty());
+        assert_eq!(tree.query_region(&square(1.5, 3.0, 0.0)), vec![id]);
+
+        tree.move_proxy(id, square(3.0, 0.0, 5.0));
+        assert_eq!(tree.nodes[leaf as usize].aabb.min.x, 1.0);
+        check_tree(&tree, tree.root);
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_margins_panic() {
+        AabbTree::<u32>::new(-1.0);
+    }
+}
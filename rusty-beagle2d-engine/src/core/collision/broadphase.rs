@@ -0,0 +1,422 @@
+use crate::core::collision::shapes::Aabb;
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+// LEARN: Broadphase
+// Testing every shape against every other shape takes n * n tests. A broadphase sorts shapes by where they are,
+// So only shapes whose bounding boxes are close get tested. The exact tests (collision::contact) run on what it finds.
+//
+// There are two: SpatialGrid, which is simple and fast for many objects of about the same size,
+// And AabbTree, which handles any mix of sizes and large empty spaces.
+
+// Refers to an object stored in a broadphase. Only valid for the broadphase that made it.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct ProxyId {
+    pub(crate) index: u32,
+    pub(crate) generation: u32
+}
+
+pub trait Broadphase<T> {
+    // Adds an object with bounding box "aabb". "data" is whatever the object should be found as, like an Entity.
+    fn insert(&mut self, aabb: Aabb, data: T) -> ProxyId;
+
+    // Returns the object's data, or None if it was already removed.
+    fn remove(&mut self, id: ProxyId) -> Option<T>;
+
+    // Call when the object moved or changed size. Panics if it was removed.
+    fn move_proxy(&mut self, id: ProxyId, aabb: Aabb);
+
+    fn contains(&self, id: ProxyId) -> bool;
+
+    // The bounding box the object was last inserted or moved with.
+    fn get_aabb(&self, id: ProxyId) -> Option<Aabb>;
+
+    fn get_data(&self, id: ProxyId) -> Option<&T>;
+
+    fn get_data_mut(&mut self, id: ProxyId) -> Option<&mut T>;
+
+    fn get_proxy_count(&self) -> usize;
+
+    // Calls "f" for every object whose bounding box overlaps "region", each once.
+    fn for_each_in_region<F: FnMut(ProxyId)>(&self, region: &Aabb, f: F);
+
+    // Calls "f" once for every pair of objects whose bounding boxes overlap. The lower ProxyId comes first.
+    fn for_each_pair<F: FnMut(ProxyId, ProxyId)>(&self, f: F);
+
+    // Finds the closest object along a ray. "hit_test" is called with objects whose bounding box the ray goes through,
+    // And the distance of the closest hit so far. It returns the distance where the ray really hits the object, if it does.
+    // Returns the object that was hit closest, and the distance.
+    fn raycast<F: FnMut(ProxyId, f32) -> Option<f32>>(&self, origin: Vector2, direction: Vector2, max_distance: f32, hit_test: F) -> Option<(ProxyId, f32)>;
+
+    fn query_region(&self, region: &Aabb) -> Vec<ProxyId> {
+        let mut ids = Vec::new();
+        self.for_each_in_region(region, |id| ids.push(id));
+        ids
+    }
+
+    // Every object whose bounding box the ray goes through, in no particular order.
+    fn query_ray(&self, origin: Vector2, direction: Vector2, max_distance: f32) -> Vec<ProxyId> {
+        let mut ids = Vec::new();
+
+        self.raycast(origin, direction, max_distance, |id, _| {
+            ids.push(id);
+            None
+        });
+
+        ids
+    }
+
+    fn query_pairs(&self) -> Vec<(ProxyId, ProxyId)> {
+        let mut pairs = Vec::new();
+        self.for_each_pair(|a, b| pairs.push((a, b)));
+        pairs
+    }
+}
+
+// A ray with its direction made unit length, ready for many box tests.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Ray {
+    pub origin: Vector2,
+    pub direction: Vector2,
+    // 1 / direction, for the slab test
+    pub inverse_direction: Vector2
+}
+
+impl Ray {
+    // None if the direction has no length.
+    pub fn new(origin: Vector2, direction: Vector2) -> Option<Ray> {
+        let direction = math2d::normalize(direction);
+
+        if math2d::length_squared(direction) == 0.0 {
+            return None;
+        }
+
+        Some(Ray {
+            origin,
+            direction,
+            inverse_direction: Vector2::new(1.0 / direction.x, 1.0 / direction.y)
+        })
+    }
+
+    // LEARN: Slab test
+    // A box is where the x slab (between min.x and max.x) and the y slab cross. The ray is inside the box
+    // From when it's inside both slabs, until it leaves either one.
+    // Returns the distance where the ray enters the box (0 if it starts inside), if within "max_distance".
+    pub fn intersect_aabb(&self, aabb: &Aabb, max_distance: f32) -> Option<f32> {
+        let mut enter = 0.0f32;
+        let mut exit = max_distance;
+
+        for (origin, inverse, min, max) in [
+            (self.origin.x, self.inverse_direction.x, aabb.min.x, aabb.max.x),
+            (self.origin.y, self.inverse_direction.y, aabb.min.y, aabb.max.y)
+        ] {
+            if inverse.is_infinite() {
+                // Parallel to the slab, so it has to start inside it
+                if origin < min || origin > max {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let t1 = (min - origin) * inverse;
+            let t2 = (max - origin) * inverse;
+
+            enter = enter.max(t1.min(t2));
+            exit = exit.min(t1.max(t2));
+
+            if enter > exit {
+                return None;
+            }
+        }
+
+        Some(enter)
+    }
+}
+
+// Slots for broadphase objects, reused after removal. The generation tells an old ProxyId from the slot's new object.
+pub(crate) struct ProxySlots<P> {
+    slots: Vec<Option<P>>,
+    generations: Vec<u32>,
+    free: Vec<u32>,
+    count: usize
+}
+
+impl<P> ProxySlots<P> {
+    pub fn new() -> ProxySlots<P> {
+        ProxySlots {
+            slots: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+            count: 0
+        }
+    }
+
+    pub fn insert(&mut self, proxy: P) -> ProxyId {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(None);
+                self.generations.push(0);
+                (self.slots.len() - 1) as u32
+            }
+        };
+
+        self.slots[index as usize] = Some(proxy);
+        self.count += 1;
+
+        ProxyId {
+            index,
+            generation: self.generations[index as usize]
+        }
+    }
+
+    pub fn remove(&mut self, id: ProxyId) -> Option<P> {
+        self.get(id)?;
+
+        let proxy = self.slots[id.index as usize].take();
+        self.generations[id.index as usize] = self.generations[id.index as usize].wrapping_add(1);
+        self.free.push(id.index);
+        self.count -= 1;
+
+        proxy
+    }
+
+    pub fn get(&self, id: ProxyId) -> Option<&P> {
+        if self.generations.get(id.index as usize) != Some(&id.generation) {
+            return None;
+        }
+
+        self.slots[id.index as usize].as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: ProxyId) -> Option<&mut P> {
+        if self.generations.get(id.index as usize) != Some(&id.generation) {
+            return None;
+        }
+
+        self.slots[id.index as usize].as_mut()
+    }
+
+    // The id of whatever is in the slot now. Panics if it's empty.
+    pub fn get_id(&self, index: u32) -> ProxyId {
+        if self.slots[index as usize].is_none() {
+            panic!("Proxy slot {} is empty", index);
+        }
+
+        ProxyId {
+            index,
+            generation: self.generations[index as usize]
+        }
+    }
+
+    pub fn get_by_index(&self, index: u32) -> &P {
+        self.slots[index as usize].as_ref().expect("Proxy slot is empty")
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::collision::grid::SpatialGrid;
+    use crate::core::collision::aabb_tree::AabbTree;
+
+    // Same numbers every run, so failures can be repeated
+    struct XorShift(u32);
+
+    impl XorShift {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 >> 8) as f32 / (1 << 24) as f32
+        }
+
+        fn range(&mut self, min: f32, max: f32) -> f32 {
+            min + (max - min) * self.next()
+        }
+
+        fn index(&mut self, count: usize) -> usize {
+            ((self.next() * count as f32) as usize).min(count - 1)
+        }
+    }
+
+    // Mostly small boxes, some covering many grid cells, around the origin so cells go negative too
+    fn random_aabb(random: &mut XorShift) -> Aabb {
+        let max_size = if random.next() < 0.1 { 200.0 } else { 40.0 };
+        let position = Vector2::new(random.range(-250.0, 250.0), random.range(-250.0, 250.0));
+        let size = Vector2::new(random.range(0.0, max_size), random.range(0.0, max_size));
+
+        Aabb::new(position, Vector2::new(position.x + size.x, position.y + size.y))
+    }
+
+    // The O(n²) answers, from every object still in the broadphase
+    struct Reference {
+        objects: Vec<(ProxyId, Aabb)>
+    }
+
+    impl Reference {
+        fn query_region(&self, region: &Aabb) -> Vec<ProxyId> {
+            let mut ids: Vec<_> = self.objects.iter().filter(|(_, aabb)| aabb.overlaps(region)).map(|&(id, _)| id).collect();
+            ids.sort();
+            ids
+        }
+
+        fn query_pairs(&self) -> Vec<(ProxyId, ProxyId)> {
+            let mut pairs = Vec::new();
+
+            for (index, &(id_a, aabb_a)) in self.objects.iter().enumerate() {
+                for &(id_b, aabb_b) in &self.objects[index + 1..] {
+                    if aabb_a.overlaps(&aabb_b) {
+                        pairs.push((id_a.min(id_b), id_a.max(id_b)));
+                    }
+                }
+            }
+
+            pairs.sort();
+            pairs
+        }
+
+        fn query_ray(&self, ray: &Ray, max_distance: f32) -> Vec<ProxyId> {
+            let mut ids: Vec<_> = self.objects.iter().filter(|(_, aabb)| ray.intersect_aabb(aabb, max_distance).is_some()).map(|&(id, _)| id).collect();
+            ids.sort();
+            ids
+        }
+
+        fn get_closest_hit(&self, ray: &Ray, max_distance: f32) -> Option<f32> {
+            self.objects.iter().filter_map(|(_, aabb)| ray.intersect_aabb(aabb, max_distance)).fold(None, |closest: Option<f32>, distance| {
+                Some(closest.map_or(distance, |closest| closest.min(distance)))
+            })
+        }
+    }
+
+    fn check_queries<B: Broadphase<u32>>(broadphase: &B, reference: &Reference, random: &mut XorShift) {
+        assert_eq!(broadphase.get_proxy_count(), reference.objects.len());
+
+        for &(id, aabb) in reference.objects.iter() {
+            let stored = broadphase.get_aabb(id).unwrap();
+            assert!(stored.min == aabb.min && stored.max == aabb.max);
+        }
+
+        for _ in 0..10 {
+            let region = random_aabb(random);
+            let mut ids = broadphase.query_region(&region);
+            ids.sort();
+            assert_eq!(ids, reference.query_region(&region), "Region {:?}", region);
+        }
+
+        let mut pairs = broadphase.query_pairs();
+        pairs.sort();
+        assert_eq!(pairs, reference.query_pairs());
+
+        for _ in 0..10 {
+            // Rays from inside and outside of the objects, some going far past all of them
+            let origin = Vector2::new(random.range(-400.0, 400.0), random.range(-400.0, 400.0));
+            let angle = random.range(0.0, std::f32::consts::TAU);
+            let direction = Vector2::new(angle.cos(), angle.sin());
+            let max_distance = random.range(10.0, 1000.0);
+            let ray = Ray::new(origin, direction).unwrap();
+
+            let mut ids = broadphase.query_ray(origin, direction, max_distance);
+            ids.sort();
+            assert_eq!(ids, reference.query_ray(&ray, max_distance), "Ray from {:?} towards {:?}", origin, direction);
+
+            // Treating the boxes as the shapes, the closest box has to be found
+            let hit = broadphase.raycast(origin, direction, max_distance, |id, limit| {
+                ray.intersect_aabb(&broadphase.get_aabb(id).unwrap(), limit)
+            });
+
+            assert_eq!(hit.map(|(_, distance)| distance), reference.get_closest_hit(&ray, max_distance));
+
+            if let Some((id, distance)) = hit {
+                assert_eq!(ray.intersect_aabb(&broadphase.get_aabb(id).unwrap(), max_distance), Some(distance));
+            }
+        }
+    }
+
+    // Inserts, moves and removes objects at random, checking every query against the reference on the way
+    fn check_against_reference<B: Broadphase<u32>>(mut broadphase: B, seed: u32) {
+        let mut random = XorShift(seed);
+        let mut reference = Reference { objects: Vec::new() };
+
+        for step in 0..400u32 {
+            let action = random.next();
+
+            if action < 0.45 || reference.objects.is_empty() {
+                let aabb = random_aabb(&mut random);
+                let id = broadphase.insert(aabb, step);
+                reference.objects.push((id, aabb));
+            } else if action < 0.8 {
+                let index = random.index(reference.objects.len());
+                let (id, aabb) = reference.objects[index];
+
+                // Small moves stay in the same cells or fat box, others teleport
+                let moved = if random.next() < 0.5 {
+                    aabb.translated(Vector2::new(random.range(-3.0, 3.0), random.range(-3.0, 3.0)))
+                } else {
+                    random_aabb(&mut random)
+                };
+
+                broadphase.move_proxy(id, moved);
+                reference.objects[index].1 = moved;
+            } else {
+                let (id, _) = reference.objects.swap_remove(random.index(reference.objects.len()));
+                assert!(broadphase.remove(id).is_some());
+                assert!(!broadphase.contains(id));
+                assert!(broadphase.remove(id).is_none());
+            }
+
+            if step.is_multiple_of(20) {
+                check_queries(&broadphase, &reference, &mut random);
+            }
+        }
+
+        // Emptied out again
+        while let Some((id, _)) = reference.objects.pop() {
+            broadphase.remove(id);
+
+            if reference.objects.len().is_multiple_of(10) {
+                check_queries(&broadphase, &reference, &mut random);
+            }
+        }
+
+        assert!(broadphase.raycast(Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), 1000.0, |_, _| Some(0.0)).is_none());
+    }
+
+    #[test]
+    fn grid_matches_reference() {
+        for seed in 1..=5 {
+            check_against_reference(SpatialGrid::new(32.0), seed * 7919);
+        }
+    }
+
+    #[test]
+    fn grid_with_big_cells_matches_reference() {
+        check_against_reference(SpatialGrid::new(300.0), 17);
+    }
+
+    #[test]
+    fn tree_matches_reference() {
+        for seed in 1..=5 {
+            check_against_reference(AabbTree::new(4.0), seed * 7919);
+        }
+    }
+
+    #[test]
+    fn tree_without_margin_matches_reference() {
+        check_against_reference(AabbTree::new(0.0), 17);
+    }
+
+    #[test]
+    fn zero_length_rays_find_nothing() {
+        let mut grid = SpatialGrid::new(32.0);
+        grid.insert(Aabb::new(Vector2::new(-10.0, -10.0), Vector2::new(10.0, 10.0)), 0);
+
+        assert!(grid.query_ray(Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0), 100.0).is_empty());
+    }
+}
@@ -0,0 +1,532 @@
+use crate::core::collision::shapes::Shape;
+use crate::core::collision::convex::{self, Convex};
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ContactPoint {
+    // Halfway between the surfaces of the two shapes
+    pub point: Vector2,
+    // How far the shapes overlap at this point
+    pub depth: f32
+}
+
+// How two overlapping shapes touch. Moving the second shape by normal * depth separates them.
+#[derive(Copy, Clone, Debug)]
+pub struct Manifold {
+    // Unit length, pointing from the first shape towards the second
+    pub normal: Vector2,
+    // The deepest of the points
+    pub depth: f32,
+    points: [ContactPoint; 2],
+    point_count: usize
+}
+
+impl Manifold {
+    fn new(normal: Vector2) -> Manifold {
+        let empty = ContactPoint { point: Vector2::new(0.0, 0.0), depth: 0.0 };
+
+        Manifold {
+            normal,
+            depth: 0.0,
+            points: [empty; 2],
+            point_count: 0
+        }
+    }
+
+    fn add_point(&mut self, point: Vector2, depth: f32) {
+        if self.point_count == 0 || depth > self.depth {
+            self.depth = depth;
+        }
+
+        self.points[self.point_count] = ContactPoint { point, depth };
+        self.point_count += 1;
+    }
+
+    // One point, or two when edges lie against each other (like a box resting on the ground).
+    pub fn get_points(&self) -> &[ContactPoint] {
+        &self.points[..self.point_count]
+    }
+
+    // The same contact, seen from the second shape.
+    pub fn flipped(&self) -> Manifold {
+        let mut manifold = *self;
+        manifold.normal = math2d::scale(self.normal, -1.0);
+        manifold
+    }
+}
+
+// Tests whether two shapes overlap, and how. Touching shapes count as overlapping, with a depth of 0.
+pub fn collide(a: &Shape, b: &Shape) -> Option<Manifold> {
+    collide_convex(&Convex::from_shape(a), &Convex::from_shape(b))
+}
+
+pub fn intersects(a: &Shape, b: &Shape) -> bool {
+    collide(a, b).is_some()
+}
+
+// Like collide, but also finds points up to "margin" apart, with a negative depth.
+// The physics solver uses these so bodies about to touch don't fall in and out of contact, which makes them jitter.
+pub(crate) fn collide_speculative(a: &Shape, b: &Shape, margin: f32) -> Option<Manifold> {
+    let mut grown = Convex::from_shape(a);
+    grown.radius += margin;
+
+    let mut manifold = collide_convex(&grown, &Convex::from_shape(b))?;
+
+    // Back to the real surface of the first shape
+    let shift = math2d::scale(manifold.normal, -margin * 0.5);
+    for point in manifold.points[..manifold.point_count].iter_mut() {
+        point.point = math2d::add(point.point, shift);
+        point.depth -= margin;
+    }
+
+    manifold.depth -= margin;
+    Some(manifold)
+}
+
+pub(crate) fn collide_convex(a: &Convex, b: &Convex) -> Option<Manifold> {
+    let total_radius = a.radius + b.radius;
+
+    // Prefer the first shape's edges when both are about as good, so the result doesn't flicker between them
+    let face = match (convex::find_max_separation(a, b), convex::find_max_separation(b, a)) {
+        (Some(face_a), Some(face_b)) if face_b.0 > face_a.0 + 0.001 => Some((face_b, true)),
+        (Some(face_a), _) => Some((face_a, false)),
+        (None, Some(face_b)) => Some((face_b, true)),
+        (None, None) => None
+    };
+
+    if let Some(((separation, edge), flip)) = face {
+        if separation > total_radius {
+            return None;
+        }
+
+        // The cores overlap, so push out along the edge they overlap the least
+        if separation <= 0.0 {
+            let manifold = if flip { clip(b, a, edge, true) } else { clip(a, b, edge, false) };
+            return manifold.or_else(|| collide_closest_points(a, b));
+        }
+    }
+
+    // The cores are apart, so the shapes only touch through their radius
+    let (distance, point_a, point_b, normal) = convex::get_closest_points(a, b);
+
+    if distance > total_radius {
+        return None;
+    }
+
+    // Facing an edge rather than a corner, where clipping finds both points of two edges lying against each other
+    if let Some(((separation, edge), flip)) = face {
+        if separation >= distance - 0.001 * (1.0 + distance) {
+            let manifold = if flip { clip(b, a, edge, true) } else { clip(a, b, edge, false) };
+
+            if manifold.is_some() {
+                return manifold;
+            }
+        }
+    }
+
+    Some(make_point_contact(a.radius, b.radius, distance, point_a, point_b, normal))
+}
+
+fn collide_closest_points(a: &Convex, b: &Convex) -> Option<Manifold> {
+    let (distance, point_a, point_b, normal) = convex::get_closest_points(a, b);
+
+    if distance > a.radius + b.radius {
+        return None;
+    }
+
+    Some(make_point_contact(a.radius, b.radius, distance, point_a, point_b, normal))
+}
+
+fn make_point_contact(radius_a: f32, radius_b: f32, distance: f32, point_a: Vector2, point_b: Vector2, normal: Vector2) -> Manifold {
+    // NOTE: Shapes on top of each other have no direction to push apart in, so they're pushed along y
+    let normal = if distance > std::f32::EPSILON {
+        normal
+    } else {
+        Vector2::new(0.0, 1.0)
+    };
+
+    let surface_a = math2d::add(point_a, math2d::scale(normal, radius_a));
+    let surface_b = math2d::sub(point_b, math2d::scale(normal, radius_b));
+
+    let mut manifold = Manifold::new(normal);
+    manifold.add_point(math2d::scale(math2d::add(surface_a, surface_b), 0.5), radius_a + radius_b - distance);
+    manifold
+}
+
+// LEARN: Contact clipping
+// The "reference" edge is the one the shapes are pushed apart along. The "incident" edge is the edge of the other shape
+// Facing it the most. The incident edge is cut off where it goes past the ends of the reference edge,
+// And what is left of it that reaches the reference edge gives the contact points.
+fn clip(reference: &Convex, incident: &Convex, edge: usize, flip: bool) -> Option<Manifold> {
+    let total_radius = reference.radius + incident.radius;
+
+    let (start, end) = reference.get_edge(edge);
+    let normal = reference.get_edge_normal(edge);
+    let tangent = math2d::normalize(math2d::sub(end, start));
+    let edge_length = math2d::dot(tangent, math2d::sub(end, start));
+
+    let (incident_start, incident_end) = if incident.get_edge_count() == 0 {
+        (incident.get_vertices()[0], incident.get_vertices()[0])
+    } else {
+        let incident_edge = (0..incident.get_edge_count())
+            .min_by(|&x, &y| {
+                let x = math2d::dot(incident.get_edge_normal(x), normal);
+                let y = math2d::dot(incident.get_edge_normal(y), normal);
+                x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+
+        incident.get_edge(incident_edge)
+    };
+
+    let (clipped_start, clipped_end) = clip_segment(incident_start, incident_end, start, tangent, edge_length)?;
+
+    let mut manifold = Manifold::new(if flip { math2d::scale(normal, -1.0) } else { normal });
+    let single_point = math2d::length_squared(math2d::sub(clipped_end, clipped_start)) <= std::f32::EPSILON;
+
+    for &point in [clipped_start, clipped_end].iter().take(if single_point { 1 } else { 2 }) {
+        let separation = math2d::dot(normal, math2d::sub(point, start));
+
+        if separation > total_radius {
+            continue;
+        }
+
+        let surface_reference = math2d::add(point, math2d::scale(normal, reference.radius - separation));
+        let surface_incident = math2d::sub(point, math2d::scale(normal, incident.radius));
+
+        manifold.add_point(math2d::scale(math2d::add(surface_reference, surface_incident), 0.5), total_radius - separation);
+    }
+
+    if manifold.point_count == 0 {
+        None
+    } else {
+        Some(manifold)
+    }
+}
+
+// Cuts the segment from "a" to "b" down to the part between the two lines through "origin" and "origin + tangent * length"
+// Perpendicular to "tangent". None if nothing is left.
+fn clip_segment(a: Vector2, b: Vector2, origin: Vector2, tangent: Vector2, length: f32) -> Option<(Vector2, Vector2)> {
+    let mut a_t = math2d::dot(tangent, math2d::sub(a, origin));
+    let mut b_t = math2d::dot(tangent, math2d::sub(b, origin));
+    let (mut a, mut b) = (a, b);
+
+    // Make "a" the one closer to the start of the reference edge
+    if a_t > b_t {
+        std::mem::swap(&mut a, &mut b);
+        std::mem::swap(&mut a_t, &mut b_t);
+    }
+
+    if b_t < 0.0 || a_t > length {
+        return None;
+    }
+
+    let along = |t: f32| {
+        if b_t - a_t <= std::f32::EPSILON {
+            a
+        } else {
+            math2d::add(a, math2d::scale(math2d::sub(b, a), (t - a_t) / (b_t - a_t)))
+        }
+    };
+
+    let clipped_a = if a_t < 0.0 { along(0.0) } else { a };
+    let clipped_b = if b_t > length { along(length) } else { b };
+
+    Some((clipped_a, clipped_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::collision::shapes::{Aabb, Circle, Obb, Polygon, Capsule};
+
+    fn v(x: f32, y: f32) -> Vector2 {
+        Vector2::new(x, y)
+    }
+
+    fn aabb(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Shape {
+        Shape::Aabb(Aabb::new(v(min_x, min_y), v(max_x, max_y)))
+    }
+
+    fn circle(x: f32, y: f32, radius: f32) -> Shape {
+        Shape::Circle(Circle::new(v(x, y), radius))
+    }
+
+    fn obb(x: f32, y: f32, half_width: f32, half_height: f32, rotation: f32) -> Shape {
+        Shape::Obb(Obb::new(v(x, y), v(half_width, half_height), rotation))
+    }
+
+    fn polygon(points: &[(f32, f32)]) -> Shape {
+        let vertices: Vec<Vector2> = points.iter().map(|&(x, y)| v(x, y)).collect();
+        Shape::Polygon(Polygon::new(&vertices))
+    }
+
+    fn capsule(start_x: f32, start_y: f32, end_x: f32, end_y: f32, radius: f32) -> Shape {
+        Shape::Capsule(Capsule::new(v(start_x, start_y), v(end_x, end_y), radius))
+    }
+
+    fn triangle() -> Shape {
+        polygon(&[(0.0, 0.0), (2.0, 0.0), (1.0, 2.0)])
+    }
+
+    fn is_close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    fn is_close_point(a: Vector2, b: Vector2) -> bool {
+        is_close(a.x, b.x) && is_close(a.y, b.y)
+    }
+
+    // Checks the manifold of "a" against "b", and that the flipped test and moving "b" out agree with it.
+    // The points are (x, y, depth), in any order.
+    fn check(a: &Shape, b: &Shape, normal: (f32, f32), depth: f32, points: &[(f32, f32, f32)]) {
+        let manifold = collide(a, b).unwrap_or_else(|| panic!("{:?} and {:?} don't collide", a, b));
+        let message = format!("{:?} and {:?} gave {:?}", a, b, manifold);
+
+        assert!(is_close_point(manifold.normal, v(normal.0, normal.1)), "{}", message);
+        assert!(is_close(manifold.depth, depth), "{}", message);
+        assert_eq!(manifold.get_points().len(), points.len(), "{}", message);
+
+        for &(x, y, point_depth) in points.iter() {
+            assert!(manifold.get_points().iter().any(|point| is_close_point(point.point, v(x, y)) && is_close(point.depth, point_depth)),
+                "No contact point at ({}, {}) with depth {}: {}", x, y, point_depth, message);
+        }
+
+        // The same contact from the other side
+        let flipped = collide(b, a).unwrap();
+        assert!(is_close_point(flipped.normal, v(-normal.0, -normal.1)), "Flipped: {:?}", flipped);
+        assert!(is_close(flipped.depth, depth), "Flipped: {:?}", flipped);
+
+        // Moving "b" by the normal times the depth separates them
+        let moved = b.translated(math2d::scale(manifold.normal, depth + 0.01));
+        assert!(collide(a, &moved).is_none(), "Still colliding after moving out: {}", message);
+
+        if depth > 0.02 {
+            let almost_moved = b.translated(math2d::scale(manifold.normal, depth - 0.01));
+            assert!(collide(a, &almost_moved).is_some(), "Not colliding before moving all the way out: {}", message);
+        }
+    }
+
+    fn check_apart(a: &Shape, b: &Shape) {
+        assert!(collide(a, b).is_none(), "{:?} and {:?} collide", a, b);
+        assert!(collide(b, a).is_none(), "{:?} and {:?} collide", b, a);
+        assert!(!intersects(a, b));
+    }
+
+    #[test]
+    fn aabb_aabb() {
+        let a = aabb(0.0, 0.0, 2.0, 2.0);
+
+        check(&a, &aabb(1.5, 0.5, 3.5, 1.5), (1.0, 0.0), 0.5, &[(1.75, 0.5, 0.5), (1.75, 1.5, 0.5)]);
+        // Touching
+        check(&a, &aabb(2.0, 0.5, 4.0, 1.5), (1.0, 0.0), 0.0, &[(2.0, 0.5, 0.0), (2.0, 1.5, 0.0)]);
+        check_apart(&a, &aabb(2.1, 0.5, 4.0, 1.5));
+        // Corners overlapping only give the part of the edges that overlaps
+        check(&a, &aabb(1.0, 1.75, 3.0, 3.75), (0.0, 1.0), 0.25, &[(1.0, 1.875, 0.25), (2.0, 1.875, 0.25)]);
+
+        // Contained, pushed out through the closest side
+        let big = aabb(0.0, 0.0, 4.0, 4.0);
+        check(&big, &aabb(0.5, 1.5, 1.5, 2.5), (-1.0, 0.0), 1.5, &[(0.75, 1.5, 1.5), (0.75, 2.5, 1.5)]);
+    }
+
+    #[test]
+    fn circle_circle() {
+        let a = circle(0.0, 0.0, 1.0);
+
+        check(&a, &circle(1.5, 0.0, 1.0), (1.0, 0.0), 0.5, &[(0.75, 0.0, 0.5)]);
+        check(&a, &circle(0.0, -1.2, 0.5), (0.0, -1.0), 0.3, &[(0.0, -0.85, 0.3)]);
+        check(&a, &circle(2.0, 0.0, 1.0), (1.0, 0.0), 0.0, &[(1.0, 0.0, 0.0)]);
+        check_apart(&a, &circle(2.1, 0.0, 1.0));
+        check(&a, &circle(0.5, 0.0, 0.25), (1.0, 0.0), 0.75, &[(0.625, 0.0, 0.75)]);
+    }
+
+    #[test]
+    fn coincident_centers_push_along_y() {
+        let a = circle(0.0, 0.0, 1.0);
+        let b = circle(0.0, 0.0, 0.5);
+
+        let manifold = collide(&a, &b).unwrap();
+        assert!(is_close_point(manifold.normal, v(0.0, 1.0)));
+        assert!(is_close(manifold.depth, 1.5));
+        assert!(is_close_point(manifold.get_points()[0].point, v(0.0, 0.25)));
+
+        // Seen from the other circle it is pushed the same way, as there is no better direction either
+        assert!(is_close_point(collide(&b, &a).unwrap().normal, v(0.0, 1.0)));
+
+        // A capsule with no length is a circle
+        let dot = capsule(0.0, 0.0, 0.0, 0.0, 0.5);
+        let manifold = collide(&a, &dot).unwrap();
+        assert!(is_close_point(manifold.normal, v(0.0, 1.0)));
+        assert!(is_close(manifold.depth, 1.5));
+
+        // Moving out along y separates them
+        assert!(collide(&a, &b.translated(v(0.0, 1.51))).is_none());
+    }
+
+    #[test]
+    fn aabb_circle() {
+        let a = aabb(0.0, 0.0, 2.0, 2.0);
+
+        // Against a side
+        check(&a, &circle(2.5, 1.0, 1.0), (1.0, 0.0), 0.5, &[(1.75, 1.0, 0.5)]);
+        // Against a corner
+        check(&a, &circle(2.6, 2.8, 1.5), (0.6, 0.8), 0.5, &[(1.85, 1.8, 0.5)]);
+        check(&a, &circle(3.0, 1.0, 1.0), (1.0, 0.0), 0.0, &[(2.0, 1.0, 0.0)]);
+        check_apart(&a, &circle(3.1, 1.0, 1.0));
+        check_apart(&a, &circle(2.6, 2.8, 0.9));
+
+        // The center inside the box
+        check(&a, &circle(0.5, 1.0, 0.25), (-1.0, 0.0), 0.75, &[(0.375, 1.0, 0.75)]);
+    }
+
+    #[test]
+    fn aabb_obb() {
+        // A box turned 45 degrees pokes a corner into the other box's side
+        let diamond = obb(0.0, 0.0, 1.0, 1.0, 45.0);
+        let depth = std::f32::consts::SQRT_2 - 1.2;
+
+        check(&diamond, &aabb(1.2, -1.0, 3.2, 1.0), (1.0, 0.0), depth, &[(1.2 + depth * 0.5, 0.0, depth)]);
+        check_apart(&diamond, &aabb(1.5, -1.0, 3.5, 1.0));
+
+        // A box turned 90 degrees is a box again
+        check(&aabb(0.5, -1.0, 2.5, 1.0), &obb(0.0, 0.0, 2.0, 1.0, 90.0), (-1.0, 0.0), 0.5, &[(0.75, -1.0, 0.5), (0.75, 1.0, 0.5)]);
+    }
+
+    #[test]
+    fn aabb_polygon() {
+        check(&triangle(), &aabb(0.5, 1.5, 1.5, 3.0), (0.0, 1.0), 0.5, &[(1.0, 1.75, 0.5)]);
+        check_apart(&triangle(), &aabb(0.5, 2.1, 1.5, 3.0));
+
+        // The triangle completely inside the box is pushed out through the closest side.
+        // The points are the ends of the triangle's edge facing that side, however deep they are.
+        check(&triangle(), &aabb(-1.0, -0.5, 3.0, 4.0), (0.0, 1.0), 2.5, &[(1.0, 0.75, 2.5), (2.0, -0.25, 0.5)]);
+    }
+
+    #[test]
+    fn aabb_capsule() {
+        let ground = aabb(0.0, 0.0, 4.0, 2.0);
+
+        // Standing on its end
+        check(&ground, &capsule(2.0, 2.5, 2.0, 4.0, 1.0), (0.0, 1.0), 0.5, &[(2.0, 1.75, 0.5)]);
+        // Lying on its side
+        check(&ground, &capsule(1.0, 2.5, 3.0, 2.5, 1.0), (0.0, 1.0), 0.5, &[(1.0, 1.75, 0.5), (3.0, 1.75, 0.5)]);
+        check_apart(&ground, &capsule(1.0, 3.1, 3.0, 3.1, 1.0));
+    }
+
+    #[test]
+    fn circle_obb() {
+        let diamond = obb(0.0, 0.0, 1.0, 1.0, 45.0);
+        let depth = std::f32::consts::SQRT_2 - 1.0;
+
+        check(&diamond, &circle(2.0, 0.0, 1.0), (1.0, 0.0), depth, &[(1.0 + depth * 0.5, 0.0, depth)]);
+        check_apart(&diamond, &circle(2.5, 0.0, 1.0));
+    }
+
+    #[test]
+    fn circle_polygon() {
+        check(&circle(1.0, -0.5, 1.0), &triangle(), (0.0, 1.0), 0.5, &[(1.0, 0.25, 0.5)]);
+        check_apart(&circle(1.0, -1.1, 1.0), &triangle());
+    }
+
+    #[test]
+    fn circle_capsule() {
+        let a = capsule(0.0, 0.0, 4.0, 0.0, 1.0);
+
+        check(&a, &circle(2.0, 1.5, 1.0), (0.0, 1.0), 0.5, &[(2.0, 0.75, 0.5)]);
+        // Against a rounded end
+        check(&a, &circle(5.5, 0.0, 1.0), (1.0, 0.0), 0.5, &[(4.75, 0.0, 0.5)]);
+        check_apart(&a, &circle(2.0, 2.1, 1.0));
+
+        // The center on the capsule's segment
+        check(&a, &circle(2.0, 0.0, 0.5), (0.0, -1.0), 1.5, &[(2.0, -0.25, 1.5)]);
+    }
+
+    #[test]
+    fn obb_obb() {
+        let a = obb(0.0, 0.0, 2.0, 1.0, 90.0);
+
+        check(&a, &obb(1.5, 0.0, 1.0, 1.0, 0.0), (1.0, 0.0), 0.5, &[(0.75, -1.0, 0.5), (0.75, 1.0, 0.5)]);
+        check_apart(&a, &obb(2.1, 0.0, 1.0, 1.0, 0.0));
+        // Both turned the same way is like two boxes side by side, turned
+        let turn = |x: f32, y: f32| math2d::rotate(v(x, y), math2d::degrees_to_radians(30.0));
+        let offset = turn(1.5, 0.0);
+        let (normal, top, bottom) = (turn(1.0, 0.0), turn(0.75, 1.0), turn(0.75, -1.0));
+        check(&obb(0.0, 0.0, 1.0, 1.0, 30.0), &obb(offset.x, offset.y, 1.0, 1.0, 30.0), (normal.x, normal.y), 0.5,
+            &[(top.x, top.y, 0.5), (bottom.x, bottom.y, 0.5)]);
+    }
+
+    #[test]
+    fn obb_polygon() {
+        let diamond = obb(0.0, 0.0, 1.0, 1.0, 45.0);
+        let square = polygon(&[(1.2, -1.0), (3.2, -1.0), (3.2, 1.0), (1.2, 1.0)]);
+        let depth = std::f32::consts::SQRT_2 - 1.2;
+
+        check(&diamond, &square, (1.0, 0.0), depth, &[(1.2 + depth * 0.5, 0.0, depth)]);
+    }
+
+    #[test]
+    fn obb_capsule() {
+        let a = obb(0.0, 0.0, 2.0, 1.0, 0.0);
+
+        check(&a, &capsule(0.0, 1.5, 0.0, 3.0, 1.0), (0.0, 1.0), 0.5, &[(0.0, 0.75, 0.5)]);
+        check_apart(&a, &capsule(0.0, 2.1, 0.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn polygon_polygon() {
+        let square = polygon(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+
+        check(&square, &polygon(&[(0.5, 1.5), (1.5, 1.5), (1.0, 3.0)]), (0.0, 1.0), 0.5, &[(0.5, 1.75, 0.5), (1.5, 1.75, 0.5)]);
+        // Either winding
+        check(&square, &polygon(&[(1.0, 3.0), (1.5, 1.5), (0.5, 1.5)]), (0.0, 1.0), 0.5, &[(0.5, 1.75, 0.5), (1.5, 1.75, 0.5)]);
+        check_apart(&square, &polygon(&[(0.5, 2.5), (1.5, 2.5), (1.0, 3.0)]));
+    }
+
+    #[test]
+    fn polygon_capsule() {
+        check(&triangle(), &capsule(0.0, 2.5, 2.0, 2.5, 1.0), (0.0, 1.0), 0.5, &[(1.0, 1.75, 0.5)]);
+        check_apart(&triangle(), &capsule(0.0, 3.1, 2.0, 3.1, 1.0));
+    }
+
+    #[test]
+    fn capsule_capsule() {
+        let a = capsule(0.0, 0.0, 4.0, 0.0, 1.0);
+
+        // Lying on each other
+        check(&a, &capsule(2.0, 1.5, 6.0, 1.5, 1.0), (0.0, 1.0), 0.5, &[(2.0, 0.75, 0.5), (4.0, 0.75, 0.5)]);
+        // End to end
+        check(&a, &capsule(5.5, 0.0, 8.0, 0.0, 1.0), (1.0, 0.0), 0.5, &[(4.75, 0.0, 0.5)]);
+        check_apart(&a, &capsule(2.0, 2.1, 6.0, 2.1, 1.0));
+
+        // Crossing, so their segments intersect
+        let crossing = collide(&a, &capsule(2.0, -2.0, 2.0, 2.0, 0.5)).unwrap();
+        assert!(crossing.depth > 1.5);
+        assert!(collide(&a, &capsule(2.0, -2.0, 2.0, 2.0, 0.5).translated(math2d::scale(crossing.normal, crossing.depth + 0.01))).is_none());
+    }
+
+    #[test]
+    fn speculative_contacts_have_negative_depth() {
+        let a = aabb(0.0, 0.0, 2.0, 2.0);
+        let b = aabb(2.5, 0.5, 4.0, 1.5);
+
+        assert!(collide(&a, &b).is_none());
+        assert!(collide_speculative(&a, &b, 0.25).is_none());
+
+        let manifold = collide_speculative(&a, &b, 1.0).unwrap();
+        assert!(is_close_point(manifold.normal, v(1.0, 0.0)));
+        assert!(is_close(manifold.depth, -0.5));
+        assert!(manifold.get_points().iter().all(|point| is_close(point.depth, -0.5) && is_close(point.point.x, 2.25)));
+    }
+
+    #[test]
+    fn flipped_manifold() {
+        let manifold = collide(&circle(0.0, 0.0, 1.0), &circle(1.5, 0.0, 1.0)).unwrap();
+        let flipped = manifold.flipped();
+
+        assert!(is_close_point(flipped.normal, v(-1.0, 0.0)));
+        assert_eq!(flipped.depth, manifold.depth);
+        assert!(is_close_point(flipped.get_points()[0].point, manifold.get_points()[0].point));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::core::collision::shapes::{self, Shape, MAX_POLYGON_VERTICES};
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+// LEARN: Rounded convex shapes
+// Every shape is tested as a convex "core" grown by a radius: a circle is a point with a radius,
+// A capsule a segment with a radius, and boxes and polygons are polygons with no radius.
+// So one set of tests covers every pair of shapes.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Convex {
+    vertices: [Vector2; MAX_POLYGON_VERTICES],
+    count: usize,
+    pub radius: f32
+}
+
+impl Convex {
+    pub fn from_shape(shape: &Shape) -> Convex {
+        match shape {
+            Shape::Aabb(aabb) => Convex::new(&aabb.get_corners(), 0.0),
+            Shape::Circle(circle) => Convex::new(&[circle.center], circle.radius),
+            Shape::Obb(obb) => Convex::new(&obb.get_corners(), 0.0),
+            Shape::Polygon(polygon) => Convex::new(polygon.get_vertices(), 0.0),
+            Shape::Capsule(capsule) => {
+                // A capsule with no length is a circle
+                if math2d::length_squared(math2d::sub(capsule.end, capsule.start)) <= std::f32::EPSILON {
+                    Convex::new(&[capsule.start], capsule.radius)
+                } else {
+                    Convex::new(&[capsule.start, capsule.end], capsule.radius)
+                }
+            }
+        }
+    }
+
+    fn new(points: &[Vector2], radius: f32) -> Convex {
+        let mut vertices = [Vector2::new(0.0, 0.0); MAX_POLYGON_VERTICES];
+        vertices[..points.len()].copy_from_slice(points);
+
+        Convex {
+            vertices,
+            count: points.len(),
+            radius
+        }
+    }
+
+    pub fn get_vertices(&self) -> &[Vector2] {
+        &self.vertices[..self.count]
+    }
+
+    // A point has no edges. A segment has two, one facing each way.
+    pub fn get_edge_count(&self) -> usize {
+        if self.count < 2 {
+            0
+        } else {
+            self.count
+        }
+    }
+
+    pub fn get_edge(&self, index: usize) -> (Vector2, Vector2) {
+        (self.vertices[index], self.vertices[(index + 1) % self.count])
+    }
+
+    pub fn get_edge_normal(&self, index: usize) -> Vector2 {
+        let (a, b) = self.get_edge(index);
+        shapes::get_edge_normal(a, b)
+    }
+
+    pub fn translated(&self, offset: Vector2) -> Convex {
+        let mut convex = *self;
+
+        for vertex in convex.vertices[..convex.count].iter_mut() {
+            *vertex = math2d::add(*vertex, offset);
+        }
+
+        convex
+    }
+}
+
+// LEARN: Separating axis theorem
+// Two convex shapes don't overlap if there is a line between them. For polygons, if there is one,
+// One of the edges lies on such a line. So for each edge, see how far the other shape is in front of it:
+// If any edge has the whole other shape in front of it, the shapes are apart.
+
+// The edge of "a" with the other shape furthest in front of it, and how far in front that is.
+// Negative when the other shape reaches behind every edge. None for points, which have no edges.
+pub(crate) fn find_max_separation(a: &Convex, b: &Convex) -> Option<(f32, usize)> {
+    let mut best: Option<(f32, usize)> = None;
+
+    for edge in 0..a.get_edge_count() {
+        let normal = a.get_edge_normal(edge);
+        let start = a.get_vertices()[edge];
+
+        let separation = b.get_vertices().iter()
+            .map(|&vertex| math2d::dot(normal, math2d::sub(vertex, start)))
+            .fold(f32::INFINITY, f32::min);
+
+        if best.is_none_or(|(best_separation, _)| separation > best_separation) {
+            best = Some((separation, edge));
+        }
+    }
+
+    best
+}
+
+// The closest points between the cores of two shapes that don't overlap, their distance, and the direction from a's point to b's.
+// Between two convex shapes this is always at a vertex of one of them, so checking each vertex against
+// Each edge of the other shape finds it.
+pub(crate) fn get_closest_points(a: &Convex, b: &Convex) -> (f32, Vector2, Vector2, Vector2) {
+    let mut best = (f32::INFINITY, a.get_vertices()[0], b.get_vertices()[0], Vector2::new(0.0, 1.0));
+
+    let mut check = |point_a: Vector2, point_b: Vector2, edge_normal: Option<Vector2>| {
+        let distance = math2d::distance(point_a, point_b);
+
+        if distance < best.0 {
+            let normal = edge_normal.unwrap_or_else(|| math2d::scale(math2d::sub(point_b, point_a), 1.0 / distance));
+            best = (distance, point_a, point_b, normal);
+        }
+    };
+
+    for &vertex in a.get_vertices() {
+        for_each_feature(b, |start, end| {
+            // b's edge faces a, so a to b is against its normal
+            let normal = get_facing_normal(start, end, vertex).map(|normal| math2d::scale(normal, -1.0));
+            check(vertex, shapes::closest_point_on_segment(start, end, vertex), normal);
+        });
+    }
+
+    for &vertex in b.get_vertices() {
+        for_each_feature(a, |start, end| check(shapes::closest_point_on_segment(start, end, vertex), vertex, get_facing_normal(start, end, vertex)));
+    }
+
+    best
+}
+
+// The edge's normal, if "point" is in front of the edge rather than past one of its ends.
+// NOTE: The difference between two points that are very close together only roughly points the right way,
+// So a corner resting on a slope would get a slightly different normal every time. The edge's normal is exact.
+fn get_facing_normal(start: Vector2, end: Vector2, point: Vector2) -> Option<Vector2> {
+    let edge = math2d::sub(end, start);
+    let length_squared = math2d::length_squared(edge);
+
+    if length_squared <= std::f32::EPSILON {
+        return None;
+    }
+
+    let along = math2d::dot(math2d::sub(point, start), edge) / length_squared;
+    let normal = shapes::get_edge_normal(start, end);
+
+    // A capsule's core has the same edge both ways round, only one of them faces the point
+    if along > 0.0 && along < 1.0 && math2d::dot(normal, math2d::sub(point, start)) > 0.0 {
+        Some(normal)
+    } else {
+        None
+    }
+}
+
+// The edges of the shape, or its only point as an edge with no length.
+fn for_each_feature<F: FnMut(Vector2, Vector2)>(convex: &Convex, mut f: F) {
+    if convex.count == 1 {
+        f(convex.vertices[0], convex.vertices[0]);
+        return;
+    }
+
+    for edge in 0..convex.get_edge_count() {
+        let (start, end) = convex.get_edge(edge);
+        f(start, end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::collision::shapes::{Aabb, Circle, Capsule, Polygon};
+
+    fn v(x: f32, y: f32) -> Vector2 {
+        Vector2::new(x, y)
+    }
+
+    #[test]
+    fn shapes_as_cores_and_radii() {
+        let circle = Convex::from_shape(&Shape::Circle(Circle::new(v(1.0, 2.0), 3.0)));
+        assert_eq!(circle.get_vertices().len(), 1);
+        assert_eq!(circle.get_edge_count(), 0);
+        assert_eq!(circle.radius, 3.0);
+
+        let capsule = Convex::from_shape(&Shape::Capsule(Capsule::new(v(0.0, 0.0), v(2.0, 0.0), 1.0)));
+        assert_eq!(capsule.get_edge_count(), 2);
+        assert_eq!(capsule.get_edge_normal(0).y, -1.0);
+        assert_eq!(capsule.get_edge_normal(1).y, 1.0);
+
+        let dot = Convex::from_shape(&Shape::Capsule(Capsule::new(v(2.0, 0.0), v(2.0, 0.0), 1.0)));
+        assert_eq!(dot.get_vertices().len(), 1);
+
+        let aabb = Convex::from_shape(&Shape::Aabb(Aabb::new(v(0.0, 0.0), v(1.0, 1.0))));
+        assert_eq!(aabb.get_edge_count(), 4);
+        assert_eq!(aabb.radius, 0.0);
+    }
+
+    #[test]
+    fn max_separation() {
+        let a = Convex::from_shape(&Shape::Aabb(Aabb::new(v(0.0, 0.0), v(2.0, 2.0))));
+        let apart = Convex::from_shape(&Shape::Aabb(Aabb::new(v(3.0, 0.5), v(4.0, 1.5))));
+        let overlapping = Convex::from_shape(&Shape::Aabb(Aabb::new(v(1.5, 0.5), v(4.0, 1.5))));
+
+        // The right edge, index 1
+        assert_eq!(find_max_separation(&a, &apart), Some((1.0, 1)));
+        assert_eq!(find_max_separation(&a, &overlapping), Some((-0.5, 1)));
+
+        let point = Convex::from_shape(&Shape::Circle(Circle::new(v(1.0, 1.0), 1.0)));
+        assert_eq!(find_max_separation(&point, &a), None);
+        assert_eq!(find_max_separation(&a, &point), Some((-1.0, 0)));
+    }
+
+    #[test]
+    fn closest_points() {
+        let a = Convex::from_shape(&Shape::Aabb(Aabb::new(v(0.0, 0.0), v(2.0, 2.0))));
+        let segment = Convex::from_shape(&Shape::Capsule(Capsule::new(v(3.0, 3.0), v(5.0, 3.0), 0.5)));
+
+        let (distance, point_a, point_b, normal) = get_closest_points(&a, &segment);
+        assert!((distance - 2.0f32.sqrt()).abs() < 1e-5);
+        assert_eq!((point_a.x, point_a.y), (2.0, 2.0));
+        assert_eq!((point_b.x, point_b.y), (3.0, 3.0));
+        // Corner to corner
+        assert!((normal.x - 0.5f32.sqrt()).abs() < 1e-6 && (normal.y - 0.5f32.sqrt()).abs() < 1e-6);
+
+        let point = Convex::from_shape(&Shape::Circle(Circle::new(v(1.0, 5.0), 1.0)));
+        let (distance, point_a, point_b, normal_to_point) = get_closest_points(&a, &point);
+        assert_eq!(distance, 3.0);
+        assert_eq!((point_a.x, point_a.y), (1.0, 2.0));
+        assert_eq!((point_b.x, point_b.y), (1.0, 5.0));
+        // In front of the bottom edge, so its normal
+        assert_eq!((normal_to_point.x, normal_to_point.y), (0.0, 1.0));
+
+        // Moving the cores moves the points
+        let (distance, _, _, _) = get_closest_points(&a.translated(v(0.0, 1.0)), &point);
+        assert_eq!(distance, 2.0);
+    }
+
+    #[test]
+    fn normals_near_touching_are_exact() {
+        // A box corner a hair above a slope, far from the origin where the points are least precise
+        let slope = Convex::from_shape(&Shape::Polygon(Polygon::new(&[v(5000.0, 5000.0), v(5100.0, 4880.0), v(5100.0, 5000.0)])));
+        let expected = shapes::get_edge_normal(v(5000.0, 5000.0), v(5100.0, 4880.0));
+
+        for index in 0..20 {
+            let x = 5010.0 + index as f32 * 3.7;
+            let surface_y = 5000.0 - (x - 5000.0) * 1.2;
+            let corner = Convex::from_shape(&Shape::Aabb(Aabb::new(v(x - 16.0, surface_y - 28.003), v(x, surface_y - 0.003))));
+
+            let (_, _, _, normal) = get_closest_points(&corner, &slope);
+            assert_eq!((normal.x, normal.y), (-expected.x, -expected.y));
+        }
+
+        // Both sides of a capsule's core
+        let capsule = Convex::from_shape(&Shape::Capsule(Capsule::new(v(0.0, 0.0), v(10.0, 0.0), 1.0)));
+        let above = Convex::from_shape(&Shape::Circle(Circle::new(v(5.0, -3.0), 1.0)));
+        let below = Convex::from_shape(&Shape::Circle(Circle::new(v(5.0, 3.0), 1.0)));
+
+        assert_eq!(get_closest_points(&capsule, &above).3.y, -1.0);
+        assert_eq!(get_closest_points(&capsule, &below).3.y, 1.0);
+    }
+}
@@ -0,0 +1,410 @@
+use crate::core::collision::shapes::Aabb;
+use crate::core::collision::broadphase::{Broadphase, ProxyId, ProxySlots, Ray};
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+// A range of cells, inclusive
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct CellRange {
+    min_x: i32,
+    min_y: i32,
+    max_x: i32,
+    max_y: i32
+}
+
+impl CellRange {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+}
+
+struct GridProxy<T> {
+    aabb: Aabb,
+    cells: CellRange,
+    data: T
+}
+
+// LEARN: Spatial hashing
+// The world is split into square cells, and each object is listed in every cell its bounding box touches.
+// Objects can only overlap if they share a cell. Cells are kept in a hash map, so only cells with something
+// In them take memory, and the world has no edges.
+// Works best with cells a bit bigger than the typical object. Objects much bigger than a cell are listed in many cells,
+// Which makes them slow to move. Use an AabbTree for worlds with very different sizes.
+pub struct SpatialGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<u32>>,
+    proxies: ProxySlots<GridProxy<T>>,
+    // The range around every cell in use, so rays know when there is nothing more to find
+    bounds: Option<CellRange>,
+    // The objects a raycast already tested, kept between raycasts so it doesn't allocate every time
+    tested: RefCell<HashSet<u32>>
+}
+
+impl<T> SpatialGrid<T> {
+    pub fn new(cell_size: f32) -> SpatialGrid<T> {
+        if cell_size.is_nan() || cell_size <= 0.0 {
+            panic!("The cell size must be more than 0, got {}", cell_size);
+        }
+
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            proxies: ProxySlots::new(),
+            bounds: None,
+            tested: RefCell::new(HashSet::new())
+        }
+    }
+
+    pub fn get_cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn get_cell(&self, position: Vector2) -> (i32, i32) {
+        ((position.x / self.cell_size).floor() as i32, (position.y / self.cell_size).floor() as i32)
+    }
+
+    fn get_cell_range(&self, aabb: &Aabb) -> CellRange {
+        let (min_x, min_y) = self.get_cell(aabb.min);
+        let (max_x, max_y) = self.get_cell(aabb.max);
+
+        CellRange { min_x, min_y, max_x, max_y }
+    }
+
+    fn add_to_cells(&mut self, index: u32, range: CellRange, skip: Option<CellRange>) {
+        for y in range.min_y..=range.max_y {
+            for x in range.min_x..=range.max_x {
+                if skip.is_some_and(|skip| skip.contains(x, y)) {
+                    continue;
+                }
+
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+
+        self.bounds = Some(match self.bounds {
+            Some(bounds) => CellRange {
+                min_x: bounds.min_x.min(range.min_x),
+                min_y: bounds.min_y.min(range.min_y),
+                max_x: bounds.max_x.max(range.max_x),
+                max_y: bounds.max_y.max(range.max_y)
+            },
+            None => range
+        });
+    }
+
+    fn remove_from_cells(&mut self, index: u32, range: CellRange, skip: Option<CellRange>) {
+        let mut emptied_edge = false;
+
+        for y in range.min_y..=range.max_y {
+            for x in range.min_x..=range.max_x {
+                if skip.is_some_and(|skip| skip.contains(x, y)) {
+                    continue;
+                }
+
+                if let Some(cell) = self.cells.get_mut(&(x, y)) {
+                    if let Some(position) = cell.iter().position(|&other| other == index) {
+                        cell.swap_remove(position);
+                    }
+
+                    // Don't keep empty cells around
+                    if cell.is_empty() {
+                        self.cells.remove(&(x, y));
+                        emptied_edge |= self.is_on_bounds_edge(x, y);
+                    }
+                }
+            }
+        }
+
+        // The bounds can only shrink when a cell on their edge is emptied
+        if emptied_edge {
+            self.update_bounds();
+        }
+    }
+
+    fn is_on_bounds_edge(&self, x: i32, y: i32) -> bool {
+        self.bounds.is_some_and(|bounds| x == bounds.min_x || x == bounds.max_x || y == bounds.min_y || y == bounds.max_y)
+    }
+
+    fn update_bounds(&mut self) {
+        self.bounds = self.cells.keys().fold(None, |bounds, &(x, y)| Some(match bounds {
+            Some(bounds) => CellRange {
+                min_x: bounds.min_x.min(x),
+                min_y: bounds.min_y.min(y),
+                max_x: bounds.max_x.max(x),
+                max_y: bounds.max_y.max(y)
+            },
+            None => CellRange { min_x: x, min_y: y, max_x: x, max_y: y }
+        }));
+    }
+}
+
+impl<T> Broadphase<T> for SpatialGrid<T> {
+    fn insert(&mut self, aabb: Aabb, data: T) -> ProxyId {
+        let cells = self.get_cell_range(&aabb);
+        let id = self.proxies.insert(GridProxy { aabb, cells, data });
+
+        self.add_to_cells(id.index, cells, None);
+        id
+    }
+
+    fn remove(&mut self, id: ProxyId) -> Option<T> {
+        let proxy = self.proxies.remove(id)?;
+        self.remove_from_cells(id.index, proxy.cells, None);
+        Some(proxy.data)
+    }
+
+    fn move_proxy(&mut self, id: ProxyId, aabb: Aabb) {
+        let new_cells = self.get_cell_range(&aabb);
+
+        let proxy = self.proxies.get_mut(id).expect("Can't move a proxy that was removed");
+        let old_cells = proxy.cells;
+
+        proxy.aabb = aabb;
+        proxy.cells = new_cells;
+
+        // Most moves stay in the same cells
+        if old_cells != new_cells {
+            self.remove_from_cells(id.index, old_cells, Some(new_cells));
+            self.add_to_cells(id.index, new_cells, Some(old_cells));
+        }
+    }
+
+    fn contains(&self, id: ProxyId) -> bool {
+        self.proxies.get(id).is_some()
+    }
+
+    fn get_aabb(&self, id: ProxyId) -> Option<Aabb> {
+        self.proxies.get(id).map(|proxy| proxy.aabb)
+    }
+
+    fn get_data(&self, id: ProxyId) -> Option<&T> {
+        self.proxies.get(id).map(|proxy| &proxy.data)
+    }
+
+    fn get_data_mut(&mut self, id: ProxyId) -> Option<&mut T> {
+        self.proxies.get_mut(id).map(|proxy| &mut proxy.data)
+    }
+
+    fn get_proxy_count(&self) -> usize {
+        self.proxies.get_count()
+    }
+
+    fn for_each_in_region<F: FnMut(ProxyId)>(&self, region: &Aabb, mut f: F) {
+        let range = self.get_cell_range(region);
+
+        for y in range.min_y..=range.max_y {
+            for x in range.min_x..=range.max_x {
+                let cell = match self.cells.get(&(x, y)) {
+                    Some(cell) => cell,
+                    None => continue
+                };
+
+                for &index in cell {
+                    let proxy = self.proxies.get_by_index(index);
+
+                    // An object in many of the cells is only reported from the first cell it shares with the region
+                    if x != proxy.cells.min_x.max(range.min_x) || y != proxy.cells.min_y.max(range.min_y) {
+                        continue;
+                    }
+
+                    if proxy.aabb.overlaps(region) {
+                        f(self.proxies.get_id(index));
+                    }
+                }
+            }
+        }
+    }
+
+    fn for_each_pair<F: FnMut(ProxyId, ProxyId)>(&self, mut f: F) {
+        for (&(x, y), cell) in self.cells.iter() {
+            for (position, &a) in cell.iter().enumerate() {
+                let proxy_a = self.proxies.get_by_index(a);
+
+                for &b in &cell[position + 1..] {
+                    let proxy_b = self.proxies.get_by_index(b);
+
+                    // Objects sharing many cells are only paired in the first of them
+                    if x != proxy_a.cells.min_x.max(proxy_b.cells.min_x) || y != proxy_a.cells.min_y.max(proxy_b.cells.min_y) {
+                        continue;
+                    }
+
+                    if proxy_a.aabb.overlaps(&proxy_b.aabb) {
+                        let (id_a, id_b) = (self.proxies.get_id(a), self.proxies.get_id(b));
+
+                        if id_a < id_b {
+                            f(id_a, id_b);
+                        } else {
+                            f(id_b, id_a);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // LEARN: Grid traversal ("A Fast Voxel Traversal Algorithm" by Amanatides and Woo)
+    // The ray is followed from cell to cell in order, by working out how far it is to the next vertical
+    // And the next horizontal cell border, and stepping over whichever is closer.
+    fn raycast<F: FnMut(ProxyId, f32) -> Option<f32>>(&self, origin: Vector2, direction: Vector2, max_distance: f32, mut hit_test: F) -> Option<(ProxyId, f32)> {
+        let ray = Ray::new(origin, direction)?;
+        let bounds = self.bounds?;
+
+        // Start where the ray enters the used cells, if it starts outside them
+        let bounds_aabb = Aabb::new(
+            Vector2::new(bounds.min_x as f32 * self.cell_size, bounds.min_y as f32 * self.cell_size),
+            Vector2::new((bounds.max_x + 1) as f32 * self.cell_size, (bounds.max_y + 1) as f32 * self.cell_size));
+
+        let start_distance = ray.intersect_aabb(&bounds_aabb, max_distance)?;
+        let start = Vector2::new(origin.x + ray.direction.x * start_distance, origin.y + ray.direction.y * start_distance);
+
+        let (mut x, mut y) = self.get_cell(start);
+        x = x.clamp(bounds.min_x, bounds.max_x);
+        y = y.clamp(bounds.min_y, bounds.max_y);
+
+        let step_x = if ray.direction.x >= 0.0 { 1 } else { -1 };
+        let step_y = if ray.direction.y >= 0.0 { 1 } else { -1 };
+
+        // Distance along the ray to the next border on each axis, and between borders
+        let next_border = |cell: i32, step: i32, origin: f32, inverse: f32| {
+            let border = (cell + if step > 0 { 1 } else { 0 }) as f32 * self.cell_size;
+            if inverse.is_infinite() { f32::INFINITY } else { (border - origin) * inverse }
+        };
+
+        let mut next_x = next_border(x, step_x, origin.x, ray.inverse_direction.x);
+        let mut next_y = next_border(y, step_y, origin.y, ray.inverse_direction.y);
+        let delta_x = (self.cell_size * ray.inverse_direction.x).abs();
+        let delta_y = (self.cell_size * ray.inverse_direction.y).abs();
+
+        let mut closest: Option<(ProxyId, f32)> = None;
+        // Taken out rather than borrowed, so a hit test can raycast this grid again
+        let mut tested = self.tested.take();
+        tested.clear();
+
+        let mut cell_enter = start_distance;
+
+        loop {
+            let limit = closest.map_or(max_distance, |(_, distance)| distance);
+
+            // Everything further along can't beat what was found
+            if cell_enter > limit {
+                break;
+            }
+
+            if let Some(cell) = self.cells.get(&(x, y)) {
+                for &index in cell {
+                    if !tested.insert(index) {
+                        continue;
+                    }
+
+                    let limit = closest.map_or(max_distance, |(_, distance)| distance);
+
+                    if ray.intersect_aabb(&self.proxies.get_by_index(index).aabb, limit).is_none() {
+                        continue;
+                    }
+
+                    let id = self.proxies.get_id(index);
+
+                    if let Some(distance) = hit_test(id, limit) {
+                        if distance <= limit {
+                            closest = Some((id, distance));
+                        }
+                    }
+                }
+            }
+
+            if next_x < next_y {
+                cell_enter = next_x;
+                next_x += delta_x;
+                x += step_x;
+            } else {
+                cell_enter = next_y;
+                next_y += delta_y;
+                y += step_y;
+            }
+
+            if !bounds.contains(x, y) {
+                break;
+            }
+        }
+
+        self.tested.replace(tested);
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> Aabb {
+        Aabb::new(Vector2::new(x, y), Vector2::new(x + size, y + size))
+    }
+
+    #[test]
+    fn bounds_shrink_after_removals() {
+        let mut grid = SpatialGrid::new(10.0);
+
+        let middle = grid.insert(square(0.0, 0.0, 5.0), 0);
+        let far = grid.insert(square(95.0, -45.0, 10.0), 1);
+        assert_eq!(grid.bounds, Some(CellRange { min_x: 0, min_y: -5, max_x: 10, max_y: 0 }));
+
+        grid.remove(far);
+        assert_eq!(grid.bounds, Some(CellRange { min_x: 0, min_y: 0, max_x: 0, max_y: 0 }));
+
+        // Moving away leaves nothing behind
+        grid.move_proxy(middle, square(-30.0, 20.0, 5.0));
+        assert_eq!(grid.bounds, Some(CellRange { min_x: -3, min_y: 2, max_x: -3, max_y: 2 }));
+
+        grid.remove(middle);
+        assert_eq!(grid.bounds, None);
+        assert!(grid.cells.is_empty());
+    }
+
+    #[test]
+    fn rays_past_removed_objects_stop_early() {
+        let mut grid = SpatialGrid::new(10.0);
+
+        grid.insert(square(0.0, 0.0, 5.0), 0);
+        let far = grid.insert(square(10000.0, 0.0, 5.0), 1);
+        grid.remove(far);
+
+        // Without shrinking, this walked a thousand empty cells
+        let mut tested = 0;
+        grid.raycast(Vector2::new(-5.0, 2.0), Vector2::new(1.0, 0.0), 20000.0, |_, _| {
+            tested += 1;
+            None
+        });
+
+        assert_eq!(tested, 1);
+        assert_eq!(grid.bounds.unwrap().max_x, 0);
+    }
+
+    #[test]
+    fn hit_tests_can_raycast_again() {
+        let mut grid = SpatialGrid::new(10.0);
+
+        grid.insert(square(10.0, 0.0, 5.0), 0);
+        grid.insert(square(30.0, 0.0, 5.0), 1);
+
+        let hit = grid.raycast(Vector2::new(0.0, 2.0), Vector2::new(1.0, 0.0), 100.0, |id, _| {
+            // Like a hit test checking for something in the way
+            let inner = grid.query_ray(Vector2::new(0.0, 2.0), Vector2::new(1.0, 0.0), 100.0);
+            assert_eq!(inner.len(), 2);
+
+            Some(grid.get_aabb(id).unwrap().min.x)
+        });
+
+        assert_eq!(hit.map(|(_, distance)| distance), Some(10.0));
+        // Both objects are tested again next time
+        assert_eq!(grid.query_ray(Vector2::new(0.0, 2.0), Vector2::new(1.0, 0.0), 100.0).len(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_cell_size_panics() {
+        SpatialGrid::<u32>::new(0.0);
+    }
+}
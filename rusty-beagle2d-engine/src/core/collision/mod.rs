@@ -0,0 +1,8 @@
+pub mod shapes;
+mod convex;
+pub mod contact;
+pub mod raycast;
+pub mod broadphase;
+pub mod grid;
+pub mod aabb_tree;
+pub mod tiles;
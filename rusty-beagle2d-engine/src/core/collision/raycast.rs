@@ -0,0 +1,391 @@
+use crate::core::collision::shapes::{self, Shape};
+use crate::core::collision::convex::{self, Convex};
+use crate::core::collision::contact;
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+#[derive(Copy, Clone, Debug)]
+pub struct RaycastHit {
+    pub point: Vector2,
+    // The surface normal where the ray hit, unit length
+    pub normal: Vector2,
+    // From the ray's origin to the point
+    pub distance: f32
+}
+
+// Where a ray from "origin" going in "direction" first hits the shape, if within "max_distance".
+// "direction" doesn't need to be unit length. Rays starting inside the shape don't hit it.
+pub fn raycast(shape: &Shape, origin: Vector2, direction: Vector2, max_distance: f32) -> Option<RaycastHit> {
+    let direction = math2d::normalize(direction);
+
+    if math2d::length_squared(direction) == 0.0 || shape.contains_point(origin) {
+        return None;
+    }
+
+    let convex = Convex::from_shape(shape);
+    let vertices = convex.get_vertices();
+
+    match (vertices.len(), convex.radius > 0.0) {
+        (1, _) => raycast_circle(vertices[0], convex.radius, origin, direction, max_distance),
+        (2, true) => {
+            // A capsule is the two end circles and the box between them
+            let side = math2d::scale(shapes::get_edge_normal(vertices[0], vertices[1]), convex.radius);
+            let sides = [
+                math2d::add(vertices[0], side),
+                math2d::add(vertices[1], side),
+                math2d::sub(vertices[1], side),
+                math2d::sub(vertices[0], side)
+            ];
+
+            [
+                raycast_circle(vertices[0], convex.radius, origin, direction, max_distance),
+                raycast_circle(vertices[1], convex.radius, origin, direction, max_distance),
+                raycast_polygon(&sides, origin, direction, max_distance)
+            ]
+                .iter()
+                .flatten()
+                .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal))
+                .copied()
+        },
+        (2, false) => raycast_segment(vertices[0], vertices[1], origin, direction, max_distance),
+        _ => raycast_polygon(vertices, origin, direction, max_distance)
+    }
+}
+
+fn raycast_circle(center: Vector2, radius: f32, origin: Vector2, direction: Vector2, max_distance: f32) -> Option<RaycastHit> {
+    // Solve |origin + direction * t - center| = radius for t
+    let offset = math2d::sub(origin, center);
+    let b = math2d::dot(offset, direction);
+    let c = math2d::length_squared(offset) - radius * radius;
+
+    let discriminant = b * b - c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let distance = -b - discriminant.sqrt();
+
+    if distance < 0.0 || distance > max_distance {
+        return None;
+    }
+
+    let point = math2d::add(origin, math2d::scale(direction, distance));
+
+    Some(RaycastHit {
+        point,
+        normal: math2d::normalize(math2d::sub(point, center)),
+        distance
+    })
+}
+
+// LEARN: Cyrus-Beck clipping
+// Each edge splits the ray into a part in front of it and a part behind it. Inside the polygon is behind every edge,
+// So the ray is cut down edge by edge. The last edge the ray goes in through is the one it hits.
+fn raycast_polygon(vertices: &[Vector2], origin: Vector2, direction: Vector2, max_distance: f32) -> Option<RaycastHit> {
+    let mut lower = 0.0;
+    let mut upper = max_distance;
+    let mut hit_normal = None;
+
+    for index in 0..vertices.len() {
+        let start = vertices[index];
+        let normal = shapes::get_edge_normal(start, vertices[(index + 1) % vertices.len()]);
+
+        let numerator = math2d::dot(normal, math2d::sub(start, origin));
+        let denominator = math2d::dot(normal, direction);
+
+        if denominator == 0.0 {
+            // Parallel to the edge, and in front of it
+            if numerator < 0.0 {
+                return None;
+            }
+        } else if denominator < 0.0 && numerator < lower * denominator {
+            // Going in through the edge
+            lower = numerator / denominator;
+            hit_normal = Some(normal);
+        } else if denominator > 0.0 && numerator < upper * denominator {
+            // Going out through the edge
+            upper = numerator / denominator;
+        }
+
+        if upper < lower {
+            return None;
+        }
+    }
+
+    hit_normal.map(|normal| RaycastHit {
+        point: math2d::add(origin, math2d::scale(direction, lower)),
+        normal,
+        distance: lower
+    })
+}
+
+fn raycast_segment(start: Vector2, end: Vector2, origin: Vector2, direction: Vector2, max_distance: f32) -> Option<RaycastHit> {
+    let edge = math2d::sub(end, start);
+    let denominator = math2d::cross(direction, edge);
+
+    // Parallel rays slide past a segment without thickness
+    if denominator.abs() <= std::f32::EPSILON {
+        return None;
+    }
+
+    let to_start = math2d::sub(start, origin);
+    let distance = math2d::cross(to_start, edge) / denominator;
+    let along_edge = math2d::cross(to_start, direction) / denominator;
+
+    if distance < 0.0 || distance > max_distance || !(0.0..=1.0).contains(&along_edge) {
+        return None;
+    }
+
+    // The side of the segment facing the ray
+    let mut normal = shapes::get_edge_normal(start, end);
+    if math2d::dot(normal, direction) > 0.0 {
+        normal = math2d::scale(normal, -1.0);
+    }
+
+    Some(RaycastHit {
+        point: math2d::add(origin, math2d::scale(direction, distance)),
+        normal,
+        distance
+    })
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ShapeCastHit {
+    // How far along the translation the shapes first touch, from 0.0 (already touching) to 1.0
+    pub time: f32,
+    // Where they touch
+    pub point: Vector2,
+    // Unit length, pointing from the moving shape towards the target
+    pub normal: Vector2
+}
+
+// Gap left between the shapes when a cast stops
+const CAST_TOLERANCE: f32 = 0.01;
+const MAX_CAST_ITERATIONS: usize = 32;
+
+// Moves "moving" in a straight line by "translation" and finds where it first touches "target".
+// Useful for moving things without going through walls, however fast they go.
+pub fn shape_cast(moving: &Shape, translation: Vector2, target: &Shape) -> Option<ShapeCastHit> {
+    let moving = Convex::from_shape(moving);
+    let target = Convex::from_shape(target);
+
+    if let Some(manifold) = contact::collide_convex(&moving, &target) {
+        return Some(ShapeCastHit {
+            time: 0.0,
+            point: manifold.get_points()[0].point,
+            normal: manifold.normal
+        });
+    }
+
+    let total_radius = moving.radius + target.radius;
+    let mut time = 0.0;
+
+    // LEARN: Conservative advancement
+    // The shapes can't get closer faster than the moving shape goes towards the other one, so moving it
+    // By the gap divided by that speed never goes too far. Repeat until the gap is closed.
+    for _ in 0..MAX_CAST_ITERATIONS {
+        let moved = moving.translated(math2d::scale(translation, time));
+        let (distance, point_a, point_b, normal) = convex::get_closest_points(&moved, &target);
+        let gap = distance - total_radius;
+
+        if gap <= CAST_TOLERANCE {
+            let surface_a = math2d::add(point_a, math2d::scale(normal, moved.radius));
+            let surface_b = math2d::sub(point_b, math2d::scale(normal, target.radius));
+
+            return Some(ShapeCastHit {
+                time,
+                point: math2d::scale(math2d::add(surface_a, surface_b), 0.5),
+                normal
+            });
+        }
+
+        let approach_speed = math2d::dot(translation, normal);
+
+        if approach_speed <= 0.0 {
+            return None;
+        }
+
+        // Aim to stop a little short of touching, so the shapes never overlap
+        time += (gap - CAST_TOLERANCE * 0.5) / approach_speed;
+
+        if time > 1.0 {
+            return None;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::collision::shapes::{Aabb, Circle, Obb, Polygon, Capsule};
+
+    fn v(x: f32, y: f32) -> Vector2 {
+        Vector2::new(x, y)
+    }
+
+    fn is_close_point(a: Vector2, b: Vector2) -> bool {
+        (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3
+    }
+
+    fn check_hit(hit: Option<RaycastHit>, point: Vector2, normal: Vector2, distance: f32) {
+        let hit = hit.expect("The ray missed");
+        assert!(is_close_point(hit.point, point), "{:?}", hit);
+        assert!(is_close_point(hit.normal, normal), "{:?}", hit);
+        assert!((hit.distance - distance).abs() < 1e-3, "{:?}", hit);
+    }
+
+    fn box_shape() -> Shape {
+        Shape::Aabb(Aabb::new(v(0.0, 0.0), v(2.0, 2.0)))
+    }
+
+    #[test]
+    fn raycast_aabb() {
+        check_hit(raycast(&box_shape(), v(-1.0, 1.0), v(1.0, 0.0), 10.0), v(0.0, 1.0), v(-1.0, 0.0), 1.0);
+        check_hit(raycast(&box_shape(), v(1.0, 5.0), v(0.0, -3.0), 10.0), v(1.0, 2.0), v(0.0, 1.0), 3.0);
+        // The direction doesn't need to be unit length
+        check_hit(raycast(&box_shape(), v(-1.0, 1.0), v(5.0, 0.0), 10.0), v(0.0, 1.0), v(-1.0, 0.0), 1.0);
+        // Diagonally into a corner
+        check_hit(raycast(&box_shape(), v(-1.0, -2.0), v(1.0, 1.0), 10.0), v(1.0, 0.0), v(0.0, -1.0), 2.0f32.sqrt() * 2.0);
+
+        // Exactly long enough, too short, pointing away and passing by
+        assert!(raycast(&box_shape(), v(-1.0, 1.0), v(1.0, 0.0), 1.0).is_some());
+        assert!(raycast(&box_shape(), v(-1.0, 1.0), v(1.0, 0.0), 0.99).is_none());
+        assert!(raycast(&box_shape(), v(-1.0, 1.0), v(-1.0, 0.0), 10.0).is_none());
+        assert!(raycast(&box_shape(), v(-1.0, 3.0), v(1.0, 0.0), 10.0).is_none());
+        // Along an edge, on the outside
+        assert!(raycast(&box_shape(), v(-1.0, 2.5), v(1.0, 0.0), 10.0).is_none());
+    }
+
+    #[test]
+    fn rays_starting_inside_dont_hit() {
+        let shapes = [
+            box_shape(),
+            Shape::Circle(Circle::new(v(1.0, 1.0), 1.0)),
+            Shape::Obb(Obb::new(v(1.0, 1.0), v(1.0, 0.5), 30.0)),
+            Shape::Polygon(Polygon::new(&[v(0.0, 0.0), v(2.0, 0.0), v(1.0, 2.0)])),
+            Shape::Capsule(Capsule::new(v(0.0, 1.0), v(2.0, 1.0), 0.5))
+        ];
+
+        for shape in shapes.iter() {
+            for direction in [v(1.0, 0.0), v(0.0, -1.0), v(-1.0, 1.0)] {
+                assert!(raycast(shape, v(1.0, 0.9), direction, 10.0).is_none(), "{:?} was hit from inside", shape);
+            }
+        }
+
+        // On the edge counts as inside
+        assert!(raycast(&box_shape(), v(0.0, 1.0), v(1.0, 0.0), 10.0).is_none());
+    }
+
+    #[test]
+    fn zero_length_rays_dont_hit() {
+        assert!(raycast(&box_shape(), v(-1.0, 1.0), v(0.0, 0.0), 10.0).is_none());
+        assert!(raycast(&Shape::Circle(Circle::new(v(0.0, 0.0), 1.0)), v(-5.0, 0.0), v(0.0, 0.0), 10.0).is_none());
+
+        // A max distance of 0 only hits what the ray starts touching
+        assert!(raycast(&box_shape(), v(-1.0, 1.0), v(1.0, 0.0), 0.0).is_none());
+    }
+
+    #[test]
+    fn raycast_circle() {
+        let circle = Shape::Circle(Circle::new(v(5.0, 0.0), 1.0));
+
+        check_hit(raycast(&circle, v(0.0, 0.0), v(1.0, 0.0), 10.0), v(4.0, 0.0), v(-1.0, 0.0), 4.0);
+        check_hit(raycast(&circle, v(5.0, 3.0), v(0.0, -1.0), 10.0), v(5.0, 1.0), v(0.0, 1.0), 2.0);
+        // Grazing
+        check_hit(raycast(&circle, v(0.0, 1.0), v(1.0, 0.0), 10.0), v(5.0, 1.0), v(0.0, 1.0), 5.0);
+
+        assert!(raycast(&circle, v(0.0, 1.1), v(1.0, 0.0), 10.0).is_none());
+        assert!(raycast(&circle, v(0.0, 0.0), v(1.0, 0.0), 3.9).is_none());
+        assert!(raycast(&circle, v(10.0, 0.0), v(1.0, 0.0), 10.0).is_none());
+    }
+
+    #[test]
+    fn raycast_obb_and_polygon() {
+        let diamond = Shape::Obb(Obb::new(v(0.0, 0.0), v(1.0, 1.0), 45.0));
+        let edge_x = -(std::f32::consts::SQRT_2 - 0.5);
+        let normal = math2d::normalize(v(-1.0, 1.0));
+        check_hit(raycast(&diamond, v(-5.0, 0.5), v(1.0, 0.0), 10.0), v(edge_x, 0.5), normal, 5.0 + edge_x);
+
+        let triangle = Shape::Polygon(Polygon::new(&[v(0.0, 0.0), v(2.0, 0.0), v(1.0, 2.0)]));
+        check_hit(raycast(&triangle, v(1.0, -3.0), v(0.0, 1.0), 10.0), v(1.0, 0.0), v(0.0, -1.0), 3.0);
+        check_hit(raycast(&triangle, v(3.0, 1.0), v(-1.0, 0.0), 10.0), v(1.5, 1.0), math2d::normalize(v(2.0, 1.0)), 1.5);
+        assert!(raycast(&triangle, v(-1.0, 1.9), v(1.0, 0.0), 1.5).is_none());
+    }
+
+    #[test]
+    fn raycast_capsule() {
+        let capsule = Shape::Capsule(Capsule::new(v(0.0, 0.0), v(4.0, 0.0), 1.0));
+
+        // The side
+        check_hit(raycast(&capsule, v(2.0, 5.0), v(0.0, -1.0), 10.0), v(2.0, 1.0), v(0.0, 1.0), 4.0);
+        check_hit(raycast(&capsule, v(2.0, -5.0), v(0.0, 1.0), 10.0), v(2.0, -1.0), v(0.0, -1.0), 4.0);
+        // The rounded ends
+        check_hit(raycast(&capsule, v(-3.0, 0.0), v(1.0, 0.0), 10.0), v(-1.0, 0.0), v(-1.0, 0.0), 2.0);
+        check_hit(raycast(&capsule, v(7.0, 0.0), v(-1.0, 0.0), 10.0), v(5.0, 0.0), v(1.0, 0.0), 2.0);
+
+        assert!(raycast(&capsule, v(-3.0, 1.5), v(1.0, 0.0), 10.0).is_none());
+
+        // No radius is a segment, hit from either side
+        let segment = Shape::Capsule(Capsule::new(v(0.0, 0.0), v(4.0, 0.0), 0.0));
+        check_hit(raycast(&segment, v(2.0, 3.0), v(0.0, -1.0), 10.0), v(2.0, 0.0), v(0.0, 1.0), 3.0);
+        check_hit(raycast(&segment, v(1.0, -2.0), v(0.0, 1.0), 10.0), v(1.0, 0.0), v(0.0, -1.0), 2.0);
+        assert!(raycast(&segment, v(-3.0, 0.0), v(1.0, 0.0), 10.0).is_none());
+        assert!(raycast(&segment, v(5.0, 3.0), v(0.0, -1.0), 10.0).is_none());
+
+        // No length is a circle
+        let dot = Shape::Capsule(Capsule::new(v(5.0, 0.0), v(5.0, 0.0), 1.0));
+        check_hit(raycast(&dot, v(0.0, 0.0), v(1.0, 0.0), 10.0), v(4.0, 0.0), v(-1.0, 0.0), 4.0);
+    }
+
+    #[test]
+    fn shape_cast_stops_just_before_touching() {
+        let moving = Shape::Circle(Circle::new(v(0.0, 0.0), 1.0));
+        let wall = Shape::Aabb(Aabb::new(v(5.0, -1.0), v(7.0, 1.0)));
+
+        // Touches after moving 4 of the 10
+        let hit = shape_cast(&moving, v(10.0, 0.0), &wall).unwrap();
+        assert!(hit.time <= 0.4 && hit.time > 0.4 - CAST_TOLERANCE / 10.0, "{:?}", hit);
+        assert!(is_close_point(hit.normal, v(1.0, 0.0)), "{:?}", hit);
+        assert!((hit.point.x - 5.0).abs() < CAST_TOLERANCE && hit.point.y.abs() < 1e-3, "{:?}", hit);
+
+        // And never ends up overlapping
+        let moved = moving.translated(math2d::scale(v(10.0, 0.0), hit.time));
+        assert!(contact::collide(&moved, &wall).is_none());
+
+        // Too short, moving past it, and moving away
+        assert!(shape_cast(&moving, v(3.9, 0.0), &wall).is_none());
+        assert!(shape_cast(&moving, v(0.0, 10.0), &wall).is_none());
+        assert!(shape_cast(&moving, v(-10.0, 0.0), &wall).is_none());
+        assert!(shape_cast(&moving, v(0.0, 0.0), &wall).is_none());
+    }
+
+    #[test]
+    fn shape_cast_diagonally() {
+        let falling = Shape::Aabb(Aabb::new(v(0.0, 0.0), v(1.0, 1.0)));
+        let ground = Shape::Aabb(Aabb::new(v(-5.0, -6.0), v(5.0, -5.0)));
+
+        let hit = shape_cast(&falling, v(2.0, -10.0), &ground).unwrap();
+        assert!(hit.time <= 0.5 && hit.time > 0.5 - CAST_TOLERANCE / 10.0, "{:?}", hit);
+        assert!(is_close_point(hit.normal, v(0.0, -1.0)), "{:?}", hit);
+        assert!((hit.point.y + 5.0).abs() < CAST_TOLERANCE, "{:?}", hit);
+
+        let capsule = Shape::Capsule(Capsule::new(v(0.0, 3.0), v(0.0, 5.0), 0.5));
+        let hit = shape_cast(&capsule, v(0.0, -20.0), &ground).unwrap();
+        assert!((hit.time * 20.0 - 7.5).abs() < CAST_TOLERANCE, "{:?}", hit);
+    }
+
+    #[test]
+    fn shape_cast_already_touching() {
+        let moving = Shape::Circle(Circle::new(v(0.0, 0.0), 1.0));
+        let other = Shape::Circle(Circle::new(v(1.5, 0.0), 1.0));
+
+        let hit = shape_cast(&moving, v(-10.0, 0.0), &other).unwrap();
+        assert_eq!(hit.time, 0.0);
+        assert!(is_close_point(hit.normal, v(1.0, 0.0)));
+        assert!(is_close_point(hit.point, v(0.75, 0.0)));
+    }
+}
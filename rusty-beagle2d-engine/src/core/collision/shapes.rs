@@ -0,0 +1,523 @@
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+// NOTE: All shapes are in world space. Move them by making new ones, or with Shape::translated.
+
+// Axis aligned bounding box.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: Vector2,
+    pub max: Vector2
+}
+
+impl Aabb {
+    pub fn new(min: Vector2, max: Vector2) -> Aabb {
+        if min.x > max.x || min.y > max.y {
+            panic!("Aabb min ({}, {}) is past max ({}, {})", min.x, min.y, max.x, max.y);
+        }
+
+        Aabb {
+            min,
+            max
+        }
+    }
+
+    pub fn from_center(center: Vector2, half_extents: Vector2) -> Aabb {
+        Aabb::new(math2d::sub(center, half_extents), math2d::add(center, half_extents))
+    }
+
+    // The smallest box around all the points. Panics if there are none.
+    pub fn from_points(points: &[Vector2]) -> Aabb {
+        if points.is_empty() {
+            panic!("Can't make an Aabb around no points");
+        }
+
+        let mut aabb = Aabb { min: points[0], max: points[0] };
+
+        for point in &points[1..] {
+            aabb.min = Vector2::new(aabb.min.x.min(point.x), aabb.min.y.min(point.y));
+            aabb.max = Vector2::new(aabb.max.x.max(point.x), aabb.max.y.max(point.y));
+        }
+
+        aabb
+    }
+
+    pub fn get_center(&self) -> Vector2 {
+        math2d::scale(math2d::add(self.min, self.max), 0.5)
+    }
+
+    pub fn get_half_extents(&self) -> Vector2 {
+        math2d::scale(math2d::sub(self.max, self.min), 0.5)
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    pub fn get_area(&self) -> f32 {
+        self.get_width() * self.get_height()
+    }
+
+    pub fn get_perimeter(&self) -> f32 {
+        2.0 * (self.get_width() + self.get_height())
+    }
+
+    // The four corners, going around the box.
+    pub fn get_corners(&self) -> [Vector2; 4] {
+        [
+            self.min,
+            Vector2::new(self.max.x, self.min.y),
+            self.max,
+            Vector2::new(self.min.x, self.max.y)
+        ]
+    }
+
+    // Points on the edge count as inside.
+    pub fn contains_point(&self, point: Vector2) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        other.min.x >= self.min.x && other.max.x <= self.max.x && other.min.y >= self.min.y && other.max.y <= self.max.y
+    }
+
+    // Touching boxes count as overlapping.
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x && self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+
+    // The smallest box around both.
+    pub fn merge(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vector2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Vector2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y))
+        }
+    }
+
+    // Grown by "margin" on every side.
+    pub fn expanded(&self, margin: f32) -> Aabb {
+        Aabb::new(
+            Vector2::new(self.min.x - margin, self.min.y - margin),
+            Vector2::new(self.max.x + margin, self.max.y + margin))
+    }
+
+    pub fn translated(&self, offset: Vector2) -> Aabb {
+        Aabb {
+            min: math2d::add(self.min, offset),
+            max: math2d::add(self.max, offset)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Circle {
+    pub center: Vector2,
+    pub radius: f32
+}
+
+impl Circle {
+    pub fn new(center: Vector2, radius: f32) -> Circle {
+        if radius < 0.0 {
+            panic!("A circle can't have a negative radius, got {}", radius);
+        }
+
+        Circle {
+            center,
+            radius
+        }
+    }
+}
+
+// Oriented bounding box: a rectangle rotated around its center.
+#[derive(Copy, Clone, Debug)]
+pub struct Obb {
+    pub center: Vector2,
+    pub half_extents: Vector2,
+    // In degrees, like the rest of the engine
+    pub rotation: f32
+}
+
+impl Obb {
+    pub fn new(center: Vector2, half_extents: Vector2, rotation: f32) -> Obb {
+        if half_extents.x < 0.0 || half_extents.y < 0.0 {
+            panic!("An Obb can't have negative half extents, got ({}, {})", half_extents.x, half_extents.y);
+        }
+
+        Obb {
+            center,
+            half_extents,
+            rotation
+        }
+    }
+
+    // The four corners, going around the box in the same direction as Aabb::get_corners.
+    pub fn get_corners(&self) -> [Vector2; 4] {
+        let radians = math2d::degrees_to_radians(self.rotation);
+        let (hx, hy) = (self.half_extents.x, self.half_extents.y);
+
+        let corner = |x: f32, y: f32| math2d::add(self.center, math2d::rotate(Vector2::new(x, y), radians));
+
+        [corner(-hx, -hy), corner(hx, -hy), corner(hx, hy), corner(-hx, hy)]
+    }
+}
+
+// Polygons can have at most this many vertices, so collision tests don't need to allocate.
+pub const MAX_POLYGON_VERTICES: usize = 8;
+
+// A convex polygon.
+#[derive(Clone, Debug)]
+pub struct Polygon {
+    vertices: Vec<Vector2>
+}
+
+impl Polygon {
+    // The vertices go around the polygon, in either direction. Panics if the polygon isn't convex,
+    // Has fewer than 3 or more than MAX_POLYGON_VERTICES vertices, repeats a vertex or has no area.
+    pub fn new(vertices: &[Vector2]) -> Polygon {
+        if vertices.len() < 3 || vertices.len() > MAX_POLYGON_VERTICES {
+            panic!("A polygon needs 3 to {} vertices, got {}", MAX_POLYGON_VERTICES, vertices.len());
+        }
+
+        let mut vertices = vertices.to_vec();
+
+        // Keep a single winding, so edge normals always point out. See get_edge_normal.
+        if get_signed_area(&vertices) < 0.0 {
+            vertices.reverse();
+        }
+
+        if get_signed_area(&vertices) <= std::f32::EPSILON {
+            panic!("A polygon needs an area");
+        }
+
+        let count = vertices.len();
+        for index in 0..count {
+            let a = vertices[index];
+            let b = vertices[(index + 1) % count];
+            let c = vertices[(index + 2) % count];
+
+            if math2d::length_squared(math2d::sub(b, a)) <= std::f32::EPSILON {
+                panic!("The polygon has the same vertex twice, at {}", index);
+            }
+
+            if math2d::cross(math2d::sub(b, a), math2d::sub(c, b)) < 0.0 {
+                panic!("The polygon isn't convex at vertex {}", (index + 1) % count);
+            }
+        }
+
+        Polygon {
+            vertices
+        }
+    }
+
+    pub fn get_vertices(&self) -> &[Vector2] {
+        &self.vertices
+    }
+
+    // The center of mass.
+    pub fn get_centroid(&self) -> Vector2 {
+        let origin = self.vertices[0];
+        let mut area = 0.0;
+        let mut centroid = Vector2::new(0.0, 0.0);
+
+        // Sum the triangles of a fan from the first vertex
+        for index in 1..self.vertices.len() - 1 {
+            let a = math2d::sub(self.vertices[index], origin);
+            let b = math2d::sub(self.vertices[index + 1], origin);
+            let triangle_area = math2d::cross(a, b) * 0.5;
+
+            area += triangle_area;
+            centroid = math2d::add(centroid, math2d::scale(math2d::add(a, b), triangle_area / 3.0));
+        }
+
+        math2d::add(origin, math2d::scale(centroid, 1.0 / area))
+    }
+
+    pub fn get_area(&self) -> f32 {
+        get_signed_area(&self.vertices)
+    }
+
+    pub fn translated(&self, offset: Vector2) -> Polygon {
+        Polygon {
+            vertices: self.vertices.iter().map(|&vertex| math2d::add(vertex, offset)).collect()
+        }
+    }
+
+    // Rotated by "degrees" around "pivot".
+    pub fn rotated(&self, degrees: f32, pivot: Vector2) -> Polygon {
+        let radians = math2d::degrees_to_radians(degrees);
+
+        Polygon {
+            vertices: self.vertices.iter()
+                .map(|&vertex| math2d::add(pivot, math2d::rotate(math2d::sub(vertex, pivot), radians)))
+                .collect()
+        }
+    }
+}
+
+// A line segment with rounded ends: every point within "radius" of the segment from "start" to "end".
+// Good for characters, as it slides over bumps and steps.
+#[derive(Copy, Clone, Debug)]
+pub struct Capsule {
+    pub start: Vector2,
+    pub end: Vector2,
+    pub radius: f32
+}
+
+impl Capsule {
+    pub fn new(start: Vector2, end: Vector2, radius: f32) -> Capsule {
+        if radius < 0.0 {
+            panic!("A capsule can't have a negative radius, got {}", radius);
+        }
+
+        Capsule {
+            start,
+            end,
+            radius
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Aabb(Aabb),
+    Circle(Circle),
+    Obb(Obb),
+    Polygon(Polygon),
+    Capsule(Capsule)
+}
+
+impl Shape {
+    // The smallest Aabb around the shape.
+    pub fn get_aabb(&self) -> Aabb {
+        match self {
+            Shape::Aabb(aabb) => *aabb,
+            Shape::Circle(circle) => Aabb::from_center(circle.center, Vector2::new(circle.radius, circle.radius)),
+            Shape::Obb(obb) => Aabb::from_points(&obb.get_corners()),
+            Shape::Polygon(polygon) => Aabb::from_points(polygon.get_vertices()),
+            Shape::Capsule(capsule) => Aabb::from_points(&[capsule.start, capsule.end]).expanded(capsule.radius)
+        }
+    }
+
+    pub fn contains_point(&self, point: Vector2) -> bool {
+        match self {
+            Shape::Aabb(aabb) => aabb.contains_point(point),
+            Shape::Circle(circle) => math2d::length_squared(math2d::sub(point, circle.center)) <= circle.radius * circle.radius,
+            Shape::Obb(obb) => is_inside_convex(&obb.get_corners(), point),
+            Shape::Polygon(polygon) => is_inside_convex(polygon.get_vertices(), point),
+            Shape::Capsule(capsule) => {
+                let closest = closest_point_on_segment(capsule.start, capsule.end, point);
+                math2d::length_squared(math2d::sub(point, closest)) <= capsule.radius * capsule.radius
+            }
+        }
+    }
+
+    pub fn translated(&self, offset: Vector2) -> Shape {
+        match self {
+            Shape::Aabb(aabb) => Shape::Aabb(aabb.translated(offset)),
+            Shape::Circle(circle) => Shape::Circle(Circle::new(math2d::add(circle.center, offset), circle.radius)),
+            Shape::Obb(obb) => Shape::Obb(Obb::new(math2d::add(obb.center, offset), obb.half_extents, obb.rotation)),
+            Shape::Polygon(polygon) => Shape::Polygon(polygon.translated(offset)),
+            Shape::Capsule(capsule) => Shape::Capsule(Capsule::new(
+                math2d::add(capsule.start, offset),
+                math2d::add(capsule.end, offset),
+                capsule.radius))
+        }
+    }
+
+    // Rotated by "degrees" around "pivot". An Aabb turns into an Obb, unless it isn't rotated.
+    pub fn rotated(&self, degrees: f32, pivot: Vector2) -> Shape {
+        let radians = math2d::degrees_to_radians(degrees);
+        let rotate_point = |point: Vector2| math2d::add(pivot, math2d::rotate(math2d::sub(point, pivot), radians));
+
+        match self {
+            Shape::Aabb(aabb) if degrees == 0.0 => Shape::Aabb(*aabb),
+            Shape::Aabb(aabb) => Shape::Obb(Obb::new(rotate_point(aabb.get_center()), aabb.get_half_extents(), degrees)),
+            Shape::Circle(circle) => Shape::Circle(Circle::new(rotate_point(circle.center), circle.radius)),
+            Shape::Obb(obb) => Shape::Obb(Obb::new(rotate_point(obb.center), obb.half_extents, obb.rotation + degrees)),
+            Shape::Polygon(polygon) => Shape::Polygon(polygon.rotated(degrees, pivot)),
+            Shape::Capsule(capsule) => Shape::Capsule(Capsule::new(
+                rotate_point(capsule.start),
+                rotate_point(capsule.end),
+                capsule.radius))
+        }
+    }
+}
+
+// The point on the segment from "a" to "b" closest to "point".
+pub fn closest_point_on_segment(a: Vector2, b: Vector2, point: Vector2) -> Vector2 {
+    let ab = math2d::sub(b, a);
+    let length_squared = math2d::length_squared(ab);
+
+    if length_squared <= std::f32::EPSILON {
+        return a;
+    }
+
+    let t = (math2d::dot(math2d::sub(point, a), ab) / length_squared).clamp(0.0, 1.0);
+    math2d::add(a, math2d::scale(ab, t))
+}
+
+// The area of the polygon, positive for the winding all shapes here use.
+pub(crate) fn get_signed_area(vertices: &[Vector2]) -> f32 {
+    let mut area = 0.0;
+
+    for index in 0..vertices.len() {
+        let a = vertices[index];
+        let b = vertices[(index + 1) % vertices.len()];
+        area += math2d::cross(a, b);
+    }
+
+    area * 0.5
+}
+
+// The outward normal of the edge from "a" to "b", for vertices in the winding all shapes here use.
+pub(crate) fn get_edge_normal(a: Vector2, b: Vector2) -> Vector2 {
+    let edge = math2d::sub(b, a);
+    math2d::normalize(Vector2::new(edge.y, -edge.x))
+}
+
+fn is_inside_convex(vertices: &[Vector2], point: Vector2) -> bool {
+    (0..vertices.len()).all(|index| {
+        let a = vertices[index];
+        let b = vertices[(index + 1) % vertices.len()];
+        math2d::dot(get_edge_normal(a, b), math2d::sub(point, a)) <= 0.0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f32, y: f32) -> Vector2 {
+        Vector2::new(x, y)
+    }
+
+    fn is_close_point(a: Vector2, b: Vector2) -> bool {
+        (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4
+    }
+
+    fn check_aabb(aabb: Aabb, min: Vector2, max: Vector2) {
+        assert!(is_close_point(aabb.min, min) && is_close_point(aabb.max, max), "{:?}", aabb);
+    }
+
+    #[test]
+    fn aabbs_around_shapes() {
+        check_aabb(Shape::Circle(Circle::new(v(1.0, 2.0), 3.0)).get_aabb(), v(-2.0, -1.0), v(4.0, 5.0));
+        check_aabb(Shape::Capsule(Capsule::new(v(0.0, 0.0), v(4.0, 2.0), 1.0)).get_aabb(), v(-1.0, -1.0), v(5.0, 3.0));
+        check_aabb(Shape::Obb(Obb::new(v(0.0, 0.0), v(2.0, 1.0), 90.0)).get_aabb(), v(-1.0, -2.0), v(1.0, 2.0));
+
+        let half_diagonal = std::f32::consts::SQRT_2;
+        check_aabb(Shape::Obb(Obb::new(v(1.0, 1.0), v(1.0, 1.0), 45.0)).get_aabb(),
+            v(1.0 - half_diagonal, 1.0 - half_diagonal), v(1.0 + half_diagonal, 1.0 + half_diagonal));
+
+        let triangle = Polygon::new(&[v(0.0, 0.0), v(2.0, 0.0), v(1.0, 3.0)]);
+        check_aabb(Shape::Polygon(triangle).get_aabb(), v(0.0, 0.0), v(2.0, 3.0));
+    }
+
+    #[test]
+    fn aabb_overlaps_and_contains() {
+        let a = Aabb::new(v(0.0, 0.0), v(2.0, 2.0));
+
+        assert!(a.overlaps(&Aabb::new(v(2.0, 2.0), v(3.0, 3.0))));
+        assert!(!a.overlaps(&Aabb::new(v(2.1, 0.0), v(3.0, 3.0))));
+        assert!(a.contains(&Aabb::new(v(0.0, 0.5), v(2.0, 1.0))));
+        assert!(!a.contains(&Aabb::new(v(0.5, 0.5), v(2.5, 1.0))));
+
+        check_aabb(a.merge(&Aabb::new(v(-1.0, 1.0), v(1.0, 3.0))), v(-1.0, 0.0), v(2.0, 3.0));
+        check_aabb(a.expanded(0.5), v(-0.5, -0.5), v(2.5, 2.5));
+        assert_eq!(a.get_perimeter(), 8.0);
+        assert_eq!(a.get_area(), 4.0);
+    }
+
+    #[test]
+    fn contains_point() {
+        let shapes = [
+            Shape::Aabb(Aabb::new(v(-1.0, -1.0), v(1.0, 1.0))),
+            Shape::Circle(Circle::new(v(0.0, 0.0), 1.0)),
+            Shape::Obb(Obb::new(v(0.0, 0.0), v(1.0, 1.0), 45.0)),
+            Shape::Polygon(Polygon::new(&[v(-1.0, -1.0), v(1.0, -1.0), v(0.0, 1.0)])),
+            Shape::Capsule(Capsule::new(v(-1.0, 0.0), v(1.0, 0.0), 0.5))
+        ];
+
+        for shape in shapes.iter() {
+            assert!(shape.contains_point(v(0.0, 0.0)), "{:?}", shape);
+            assert!(!shape.contains_point(v(0.0, 1.5)), "{:?}", shape);
+            assert!(!shape.contains_point(v(2.0, 0.0)), "{:?}", shape);
+        }
+
+        // Edges count as inside
+        assert!(shapes[0].contains_point(v(1.0, 0.5)));
+        assert!(shapes[1].contains_point(v(0.0, -1.0)));
+        assert!(shapes[4].contains_point(v(1.5, 0.0)));
+        assert!(!shapes[4].contains_point(v(1.0, 0.6)));
+        // Outside the diamond, inside its Aabb
+        assert!(!shapes[2].contains_point(v(1.0, 1.0)));
+    }
+
+    #[test]
+    fn polygons_are_wound_the_same_way() {
+        let clockwise = Polygon::new(&[v(0.0, 0.0), v(0.0, 2.0), v(2.0, 2.0), v(2.0, 0.0)]);
+
+        assert_eq!(clockwise.get_area(), 4.0);
+        assert!(is_close_point(clockwise.get_centroid(), v(1.0, 1.0)));
+
+        let triangle = Polygon::new(&[v(0.0, 0.0), v(3.0, 0.0), v(0.0, 3.0)]);
+        assert!(is_close_point(triangle.get_centroid(), v(1.0, 1.0)));
+
+        // Edge normals point out
+        let vertices = clockwise.get_vertices();
+        for index in 0..vertices.len() {
+            let normal = get_edge_normal(vertices[index], vertices[(index + 1) % vertices.len()]);
+            let middle = math2d::scale(math2d::add(vertices[index], vertices[(index + 1) % vertices.len()]), 0.5);
+            assert!(math2d::dot(normal, math2d::sub(middle, v(1.0, 1.0))) > 0.0);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn concave_polygons_panic() {
+        Polygon::new(&[v(0.0, 0.0), v(4.0, 0.0), v(1.0, 1.0), v(0.0, 4.0)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn flat_polygons_panic() {
+        Polygon::new(&[v(0.0, 0.0), v(1.0, 0.0), v(2.0, 0.0)]);
+    }
+
+    #[test]
+    fn moving_shapes() {
+        let aabb = Shape::Aabb(Aabb::new(v(0.0, 0.0), v(2.0, 1.0)));
+
+        check_aabb(aabb.translated(v(1.0, -1.0)).get_aabb(), v(1.0, -1.0), v(3.0, 0.0));
+
+        // A turned Aabb becomes an Obb, turned around the pivot
+        match aabb.rotated(90.0, v(0.0, 0.0)) {
+            Shape::Obb(obb) => {
+                assert!(is_close_point(obb.center, v(-0.5, 1.0)), "{:?}", obb);
+                assert_eq!(obb.rotation, 90.0);
+            },
+            other => panic!("Expected an Obb, got {:?}", other)
+        }
+
+        assert!(matches!(aabb.rotated(0.0, v(5.0, 5.0)), Shape::Aabb(_)));
+
+        let capsule = Shape::Capsule(Capsule::new(v(1.0, 0.0), v(2.0, 0.0), 0.5)).rotated(180.0, v(0.0, 0.0));
+        check_aabb(capsule.get_aabb(), v(-2.5, -0.5), v(-0.5, 0.5));
+    }
+
+    #[test]
+    fn closest_point_on_segments() {
+        let (a, b) = (v(0.0, 0.0), v(4.0, 0.0));
+
+        assert!(is_close_point(closest_point_on_segment(a, b, v(1.0, 3.0)), v(1.0, 0.0)));
+        assert!(is_close_point(closest_point_on_segment(a, b, v(-2.0, 1.0)), a));
+        assert!(is_close_point(closest_point_on_segment(a, b, v(7.0, -1.0)), b));
+        // A segment with no length is a point
+        assert!(is_close_point(closest_point_on_segment(a, a, v(7.0, -1.0)), a));
+    }
+}
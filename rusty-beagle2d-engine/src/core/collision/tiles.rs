@@ -0,0 +1,190 @@
+use crate::core::collision::shapes::{Aabb, Polygon, Shape};
+use crate::core::tilemap::{Tile, Tilemap};
+
+use linear_beaglebra::vector2::Vector2;
+
+// How a tile collides.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TileCollision {
+    Empty,
+    Solid,
+    // Only blocks things coming from above, like a platform that can be jumped through from below
+    OneWay,
+    // Ground going from "left" at the tile's left edge to "right" at its right edge.
+    // Heights go from 0.0 (the bottom of the tile) to 1.0 (the top). Slope { left: 0.0, right: 1.0 } rises to the right.
+    Slope { left: f32, right: f32 }
+}
+
+// The solid parts of a level, as a grid of tiles. Unlike a Tilemap, it needs no textures, so it works in tests and on servers.
+// Tile (0, 0) has its top left corner at the grid's position.
+pub struct TileCollisionGrid {
+    width: u32,
+    height: u32,
+    tile_width: f32,
+    tile_height: f32,
+    position: Vector2,
+    tiles: Vec<TileCollision>
+}
+
+impl TileCollisionGrid {
+    // width and height in tiles, tile_width and tile_height in pixels. Every tile starts empty.
+    pub fn new(width: u32, height: u32, tile_width: f32, tile_height: f32) -> TileCollisionGrid {
+        if tile_width <= 0.0 || tile_height <= 0.0 {
+            panic!("Tiles must be bigger than 0, got {}x{}", tile_width, tile_height);
+        }
+
+        TileCollisionGrid {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            position: Vector2::new(0.0, 0.0),
+            tiles: vec![TileCollision::Empty; (width * height) as usize]
+        }
+    }
+
+    // Builds the grid from a layer of a tilemap, with "get_collision" deciding how each tile collides.
+    // TileCollisionGrid::from_tilemap_layer(&tilemap, 0, |tile| if tile.id == 3 { TileCollision::OneWay } else { TileCollision::Solid })
+    pub fn from_tilemap_layer<F: Fn(Tile) -> TileCollision>(tilemap: &Tilemap, layer_index: usize, get_collision: F) -> TileCollisionGrid {
+        let layer = tilemap.get_layer(layer_index);
+        let mut grid = TileCollisionGrid::new(
+            layer.get_width(),
+            layer.get_height(),
+            tilemap.get_tile_width() as f32,
+            tilemap.get_tile_height() as f32);
+
+        let (offset_x, offset_y) = layer.get_offset();
+        let position = tilemap.get_position();
+        grid.position = Vector2::new(position.x + offset_x, position.y + offset_y);
+
+        for (index, tile) in layer.get_tiles().iter().enumerate() {
+            if !tile.is_empty() {
+                grid.tiles[index] = get_collision(*tile);
+            }
+        }
+
+        grid
+    }
+
+    // Builds a grid from text, one line per row of tiles. Handy for test levels.
+    // '#' is solid, '-' is one way, '/' and '\' are slopes going up to the right and to the left,
+    // And '.' or ' ' are empty. Shorter lines are filled up with empty tiles.
+    pub fn from_text(text: &str, tile_width: f32, tile_height: f32) -> Result<TileCollisionGrid, String> {
+        let rows: Vec<&str> = text.lines().collect();
+        let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+
+        let mut grid = TileCollisionGrid::new(width as u32, rows.len() as u32, tile_width, tile_height);
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, character) in row.chars().enumerate() {
+                let collision = match character {
+                    '#' => TileCollision::Solid,
+                    '-' => TileCollision::OneWay,
+                    '/' => TileCollision::Slope { left: 0.0, right: 1.0 },
+                    '\\' => TileCollision::Slope { left: 1.0, right: 0.0 },
+                    '.' | ' ' => TileCollision::Empty,
+                    _ => return Err(format!("Unknown tile '{}' at column {}, row {}", character, x + 1, y + 1))
+                };
+
+                grid.set_tile(x as u32, y as u32, collision);
+            }
+        }
+
+        Ok(grid)
+    }
+
+    pub fn with_position(mut self, position: Vector2) -> TileCollisionGrid {
+        self.position = position;
+        self
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_tile_width(&self) -> f32 {
+        self.tile_width
+    }
+
+    pub fn get_tile_height(&self) -> f32 {
+        self.tile_height
+    }
+
+    pub fn get_position(&self) -> Vector2 {
+        self.position
+    }
+
+    // Outside the grid is empty.
+    pub fn get_tile(&self, x: i32, y: i32) -> TileCollision {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return TileCollision::Empty;
+        }
+
+        self.tiles[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, collision: TileCollision) {
+        if x >= self.width || y >= self.height {
+            panic!("Tile ({}, {}) is outside the {}x{} grid", x, y, self.width, self.height);
+        }
+
+        self.tiles[(y * self.width + x) as usize] = collision;
+    }
+
+    // The tile under a world position. Can be outside the grid.
+    pub fn world_to_tile(&self, position: Vector2) -> (i32, i32) {
+        (
+            ((position.x - self.position.x) / self.tile_width).floor() as i32,
+            ((position.y - self.position.y) / self.tile_height).floor() as i32
+        )
+    }
+
+    // The shape of a tile in the world, None if it's empty.
+    pub fn get_tile_shape(&self, x: i32, y: i32) -> Option<Shape> {
+        let left = self.position.x + x as f32 * self.tile_width;
+        let top = self.position.y + y as f32 * self.tile_height;
+        let right = left + self.tile_width;
+        let bottom = top + self.tile_height;
+
+        match self.get_tile(x, y) {
+            TileCollision::Empty => None,
+            TileCollision::Solid | TileCollision::OneWay => Some(Shape::Aabb(Aabb::new(Vector2::new(left, top), Vector2::new(right, bottom)))),
+            TileCollision::Slope { left: left_height, right: right_height } => {
+                let mut vertices = vec![Vector2::new(left, bottom), Vector2::new(right, bottom)];
+
+                // A side with no height is a corner, not an edge
+                if right_height > 0.0 {
+                    vertices.push(Vector2::new(right, bottom - right_height * self.tile_height));
+                }
+
+                if left_height > 0.0 {
+                    vertices.push(Vector2::new(left, bottom - left_height * self.tile_height));
+                }
+
+                if vertices.len() < 3 {
+                    return None;
+                }
+
+                Some(Shape::Polygon(Polygon::new(&vertices)))
+            }
+        }
+    }
+
+    // Calls "f" with the shape of every non empty tile touching "region", and whether the tile is one way.
+    pub fn for_each_shape_in_region<F: FnMut(Shape, bool)>(&self, region: &Aabb, mut f: F) {
+        let (min_x, min_y) = self.world_to_tile(region.min);
+        let (max_x, max_y) = self.world_to_tile(region.max);
+
+        for y in min_y.max(0)..=max_y.min(self.height as i32 - 1) {
+            for x in min_x.max(0)..=max_x.min(self.width as i32 - 1) {
+                if let Some(shape) = self.get_tile_shape(x, y) {
+                    f(shape, self.get_tile(x, y) == TileCollision::OneWay);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::core::color::Color;
+use crate::core::rect::Rect;
+use crate::core::renderer2d::Renderer2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::ops::Range;
+
+#[derive(Clone, Debug)]
+pub enum DrawPrimitive {
+    FillRect { rect: Rect, color: Color },
+    // The outline is drawn inside the rectangle
+    Rect { rect: Rect, thickness: f32, color: Color },
+    Line { start: Vector2, end: Vector2, thickness: f32, color: Color },
+    // "range" is where the text is in the draw list's text buffer
+    Text { position: Vector2, range: Range<usize>, scale: f32, color: Color }
+}
+
+// The shapes and text a UI wants drawn, collected before anything is drawn.
+// That lets a window put down its background after its contents have decided how big it is,
+// And lets the UI be built and checked without a renderer.
+// The text of every Text primitive shares one buffer, so a frame of UI doesn't allocate once the buffers have grown.
+pub struct DrawList {
+    primitives: Vec<DrawPrimitive>,
+    text: String
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList {
+            primitives: Vec::new(),
+            text: String::new()
+        }
+    }
+
+    // Empties the list, keeping the allocations around for the next frame.
+    pub fn clear(&mut self) {
+        self.primitives.clear();
+        self.text.clear();
+    }
+
+    // Returns the index of the primitive, for changing its rectangle later with set_rect.
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) -> usize {
+        self.primitives.push(DrawPrimitive::FillRect { rect, color });
+        self.primitives.len() - 1
+    }
+
+    pub fn draw_rect(&mut self, rect: Rect, thickness: f32, color: Color) -> usize {
+        self.primitives.push(DrawPrimitive::Rect { rect, thickness, color });
+        self.primitives.len() - 1
+    }
+
+    pub fn draw_line(&mut self, start: Vector2, end: Vector2, thickness: f32, color: Color) {
+        self.primitives.push(DrawPrimitive::Line { start, end, thickness, color });
+    }
+
+    pub fn draw_text(&mut self, text: &str, position: Vector2, scale: f32, color: Color) {
+        let start = self.text.len();
+        self.text.push_str(text);
+
+        self.primitives.push(DrawPrimitive::Text { position, range: start..self.text.len(), scale, color });
+    }
+
+    // Moves a rectangle added earlier. Does nothing for other primitives.
+    pub fn set_rect(&mut self, index: usize, new_rect: Rect) {
+        match &mut self.primitives[index] {
+            DrawPrimitive::FillRect { rect, .. } | DrawPrimitive::Rect { rect, .. } => *rect = new_rect,
+            _ => {}
+        }
+    }
+
+    pub fn get_primitives(&self) -> &[DrawPrimitive] {
+        &self.primitives
+    }
+
+    // The text of a Text primitive.
+    pub fn get_text(&self, range: &Range<usize>) -> &str {
+        &self.text[range.clone()]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+
+    // Draws the primitives in the order they were added, with the renderer's current camera and layer.
+    pub fn render(&self, renderer: &mut Renderer2d) {
+        for primitive in &self.primitives {
+            match primitive {
+                DrawPrimitive::FillRect { rect, color } => renderer.fill_rect(rect.x, rect.y, rect.width, rect.height, *color),
+                DrawPrimitive::Rect { rect, thickness, color } => renderer.draw_rect(rect.x, rect.y, rect.width, rect.height, *thickness, *color),
+                DrawPrimitive::Line { start, end, thickness, color } => renderer.draw_line(*start, *end, *thickness, *color),
+                DrawPrimitive::Text { position, range, scale, color } => renderer.draw_text(self.get_text(range), *position, *scale, *color)
+            }
+        }
+    }
+}
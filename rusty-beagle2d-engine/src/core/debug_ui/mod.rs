@@ -0,0 +1,3 @@
+pub mod style;
+pub mod draw_list;
+pub mod ui;
\ No newline at end of file
@@ -0,0 +1,49 @@
+use crate::core::color::Color;
+
+// Sizes and colors of the debug UI. Sizes are in pixels, and scale with text_scale.
+#[derive(Copy, Clone, Debug)]
+pub struct DebugUiStyle {
+    pub text_scale: f32,
+    // Between the edge of a window and its widgets
+    pub window_padding: f32,
+    // Between the edge of a widget and its text
+    pub frame_padding: f32,
+    // Between widgets
+    pub spacing: f32,
+    // How far the widgets of an open tree node are moved in
+    pub indent: f32,
+    // Width of sliders, text inputs and plots
+    pub item_width: f32,
+    pub plot_height: f32,
+    pub window_color: Color,
+    pub title_color: Color,
+    pub text_color: Color,
+    pub border_color: Color,
+    pub widget_color: Color,
+    pub widget_hovered_color: Color,
+    pub widget_active_color: Color,
+    // Slider grabs, check marks, text cursors and plot lines
+    pub accent_color: Color
+}
+
+impl DebugUiStyle {
+    pub fn new() -> DebugUiStyle {
+        DebugUiStyle {
+            text_scale: 1.0,
+            window_padding: 8.0,
+            frame_padding: 4.0,
+            spacing: 4.0,
+            indent: 14.0,
+            item_width: 200.0,
+            plot_height: 60.0,
+            window_color: Color::from_rgba8(20, 22, 28, 225),
+            title_color: Color::from_rgba8(45, 70, 120, 255),
+            text_color: Color::from_rgba8(235, 235, 235, 255),
+            border_color: Color::from_rgba8(90, 95, 110, 255),
+            widget_color: Color::from_rgba8(50, 55, 68, 255),
+            widget_hovered_color: Color::from_rgba8(70, 80, 100, 255),
+            widget_active_color: Color::from_rgba8(90, 110, 150, 255),
+            accent_color: Color::from_rgba8(110, 170, 255, 255)
+        }
+    }
+}
@@ -0,0 +1,1176 @@
+use crate::core::color::Color;
+use crate::core::font::FontMetrics;
+use crate::core::input::{Key, MouseButton, WindowEvent};
+use crate::core::math2d;
+use crate::core::rect::Rect;
+use crate::core::renderer2d::Renderer2d;
+use crate::core::debug_ui::draw_list::DrawList;
+use crate::core::debug_ui::style::DebugUiStyle;
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+// The renderer layer the debug UI is drawn on, above everything else
+pub const DEBUG_UI_LAYER: i32 = 1_000_000;
+
+// Widgets are told apart by a hash of their label and the window and tree nodes they are in
+type WidgetId = u64;
+
+struct Window {
+    id: WidgetId,
+    position: Vector2,
+    // As big as it was the last time it was shown
+    size: Vector2,
+    collapsed: bool,
+    // Whether it was shown during the current frame
+    shown: bool,
+    draw_list: DrawList
+}
+
+// Where the widgets of the window being built go.
+struct Layout {
+    window_index: usize,
+    window_id: WidgetId,
+    collapsed: bool,
+    title_width: f32,
+    // Filled in by end_window, once the size of the contents is known
+    background_index: usize,
+    title_bar_index: usize,
+    content_x: f32,
+    indent: f32,
+    next_y: f32,
+    same_line: bool,
+    last_item: Rect,
+    // Bottom of the tallest widget on the current line
+    line_bottom: f32,
+    content_right: f32,
+    content_bottom: f32
+}
+
+#[derive(Copy, Clone)]
+struct Interaction {
+    hovered: bool,
+    // The mouse went down on the widget this frame
+    pressed: bool,
+    // The mouse is held down after going down on the widget
+    active: bool,
+    // The mouse went down and came back up on the widget
+    clicked: bool
+}
+
+// LEARN: Immediate mode UI ("Immediate-Mode Graphical User Interfaces" by Casey Muratori, and Dear ImGui)
+// There are no widget objects to create, keep and update. Every frame, the code that owns the values
+// Calls a function per widget, which draws it and handles its input right there:
+//
+// ui.begin_frame();
+// if ui.begin_window("Stats", Vector2::new(10.0, 10.0)) {
+//     ui.label(&format!("FPS: {:.1}", fps));
+//     ui.slider("Time scale", &mut time_scale, 0.0, 4.0);
+//     if ui.button("Reset") { ... }
+// }
+// ui.end_window();
+// ui.end_frame();
+// ...
+// ui.render(renderer);
+//
+// Only the few things that have to outlive a frame are kept, like window positions and which tree nodes are open.
+// That makes it quick to show any value and throw it away again, which is what a debug overlay wants.
+// Text is drawn as ASCII, the same as Renderer2d::draw_text, and only ASCII can be typed. Text inputs still edit
+// Strings holding other characters without breaking them. Labels can end in "##something", which is left out
+// When shown but still tells apart widgets that would otherwise have the same label.
+pub struct DebugUi {
+    font: FontMetrics,
+    style: DebugUiStyle,
+    // Back to front
+    windows: Vec<Window>,
+    layout: Option<Layout>,
+    id_stack: Vec<WidgetId>,
+    open_tree_nodes: HashMap<WidgetId, bool>,
+    hovered_window: Option<WidgetId>,
+    // The widget the mouse went down on, until it comes back up
+    active_id: Option<WidgetId>,
+    // The text input that gets the keyboard
+    focused_id: Option<WidgetId>,
+    // Where the text cursor is in the focused text input, in bytes. Always on a character boundary.
+    text_cursor: usize,
+    // Where the window being dragged was grabbed, relative to its corner
+    drag_offset: Option<Vector2>,
+    mouse_position: Vector2,
+    previous_mouse_position: Vector2,
+    mouse_down: bool,
+    mouse_pressed: bool,
+    mouse_released: bool,
+    // Key presses and typed characters, in the order they came
+    keyboard_events: Vec<WindowEvent>,
+    // For formatting numbers without allocating
+    scratch: String
+}
+
+impl DebugUi {
+    // Measures text with "font", which should be the font the renderer draws with (Renderer2d::get_font).
+    pub fn new(font: FontMetrics) -> DebugUi {
+        DebugUi {
+            font,
+            style: DebugUiStyle::new(),
+            windows: Vec::new(),
+            layout: None,
+            id_stack: Vec::new(),
+            open_tree_nodes: HashMap::new(),
+            hovered_window: None,
+            active_id: None,
+            focused_id: None,
+            text_cursor: 0,
+            drag_offset: None,
+            mouse_position: Vector2::new(0.0, 0.0),
+            previous_mouse_position: Vector2::new(0.0, 0.0),
+            mouse_down: false,
+            mouse_pressed: false,
+            mouse_released: false,
+            keyboard_events: Vec::new(),
+            scratch: String::new()
+        }
+    }
+
+    pub fn with_style(mut self, style: DebugUiStyle) -> DebugUi {
+        self.style = style;
+        self
+    }
+
+    pub fn get_style(&self) -> &DebugUiStyle {
+        &self.style
+    }
+
+    pub fn get_style_mut(&mut self) -> &mut DebugUiStyle {
+        &mut self.style
+    }
+
+    // Feed it every window event, for example from Game::handle_event. They are used by the next frame's widgets.
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::MouseMoved { position } => self.mouse_position = position,
+            WindowEvent::MouseButtonPressed { button: MouseButton::Left } => {
+                self.mouse_down = true;
+                self.mouse_pressed = true;
+            },
+            WindowEvent::MouseButtonReleased { button: MouseButton::Left } => {
+                self.mouse_down = false;
+                self.mouse_released = true;
+            },
+            WindowEvent::KeyPressed { .. } | WindowEvent::CharacterTyped(_) => self.keyboard_events.push(*event),
+            _ => {}
+        }
+    }
+
+    // Whether the mouse is over the UI or dragging something in it, so the game should leave the mouse alone.
+    pub fn wants_mouse(&self) -> bool {
+        self.hovered_window.is_some() || self.active_id.is_some()
+    }
+
+    // Whether a text input is being typed in, so the game should leave the keyboard alone.
+    pub fn wants_keyboard(&self) -> bool {
+        self.focused_id.is_some()
+    }
+
+    // Call once per frame before any windows, even in frames that show none.
+    pub fn begin_frame(&mut self) {
+        let title_height = self.get_frame_height();
+
+        // The topmost window under the mouse, going by where the windows were last frame
+        self.hovered_window = self.windows.iter()
+            .rev()
+            .filter(|window| window.shown)
+            .find(|window| {
+                let height = if window.collapsed { title_height } else { window.size.y };
+                Rect::new(window.position.x, window.position.y, window.size.x, height).contains(self.mouse_position)
+            })
+            .map(|window| window.id);
+
+        // Clicking a window brings it to the front
+        if let (true, Some(hovered_window)) = (self.mouse_pressed, self.hovered_window) {
+            if let Some(index) = self.windows.iter().position(|window| window.id == hovered_window) {
+                let window = self.windows.remove(index);
+                self.windows.push(window);
+            }
+        }
+
+        for window in &mut self.windows {
+            window.shown = false;
+            window.draw_list.clear();
+        }
+    }
+
+    // Call once per frame after the last window.
+    pub fn end_frame(&mut self) {
+        if self.layout.is_some() {
+            panic!("end_window has to be called for every begin_window before end_frame");
+        }
+
+        // Clicking anywhere else lets go of the keyboard
+        if self.mouse_pressed && self.focused_id.is_some() && self.active_id != self.focused_id {
+            self.focused_id = None;
+        }
+
+        if !self.mouse_down {
+            self.active_id = None;
+            self.drag_offset = None;
+        }
+
+        self.mouse_pressed = false;
+        self.mouse_released = false;
+        self.keyboard_events.clear();
+        self.previous_mouse_position = self.mouse_position;
+    }
+
+    // Starts a window, placed at "initial_position" the first time it's shown. The title bar drags it around,
+    // And the arrow in it collapses it. Returns false while collapsed, when there's no need to add widgets.
+    // end_window has to be called either way.
+    pub fn begin_window(&mut self, title: &str, initial_position: Vector2) -> bool {
+        if self.layout.is_some() {
+            panic!("Can't begin window \"{}\" inside another window. Call end_window first", title);
+        }
+
+        let id = DebugUi::hash_id(0, title);
+        let window_index = match self.windows.iter().position(|window| window.id == id) {
+            Some(index) => index,
+            None => {
+                self.windows.push(Window {
+                    id,
+                    position: initial_position,
+                    size: Vector2::new(0.0, 0.0),
+                    collapsed: false,
+                    shown: false,
+                    draw_list: DrawList::new()
+                });
+
+                self.windows.len() - 1
+            }
+        };
+
+        self.id_stack.push(id);
+
+        let title_height = self.get_frame_height();
+        let text = DebugUi::get_display_text(title);
+        let title_width = title_height + self.font.measure_text(text, self.style.text_scale).x + self.style.frame_padding * 2.0;
+
+        // Title bar
+        let title_id = self.make_id("##title");
+        let window_position = self.windows[window_index].position;
+        let title_rect = Rect::new(window_position.x, window_position.y, self.windows[window_index].size.x.max(title_width), title_height);
+        let arrow_rect = Rect::new(window_position.x, window_position.y, title_height, title_height);
+        let interaction = self.interact(id, title_id, title_rect);
+
+        if interaction.pressed {
+            if arrow_rect.contains(self.mouse_position) {
+                self.windows[window_index].collapsed = !self.windows[window_index].collapsed;
+            } else {
+                self.drag_offset = Some(math2d::sub(self.mouse_position, window_position));
+            }
+        }
+
+        if let (true, Some(drag_offset)) = (interaction.active, self.drag_offset) {
+            self.windows[window_index].position = math2d::sub(self.mouse_position, drag_offset);
+        }
+
+        let window = &mut self.windows[window_index];
+        window.shown = true;
+
+        let position = window.position;
+        let collapsed = window.collapsed;
+        let style = self.style;
+
+        // The background and title bar are drawn first, but only sized in end_window
+        let background_index = window.draw_list.fill_rect(Rect::new(position.x, position.y, 0.0, 0.0), style.window_color);
+        let title_bar_index = window.draw_list.fill_rect(Rect::new(position.x, position.y, 0.0, title_height), style.title_color);
+
+        let arrow = if collapsed { ">" } else { "v" };
+        let arrow_width = self.font.measure_text(arrow, style.text_scale).x;
+        window.draw_list.draw_text(arrow, Vector2::new(position.x + (title_height - arrow_width) * 0.5, position.y + style.frame_padding), style.text_scale, style.text_color);
+        window.draw_list.draw_text(text, Vector2::new(position.x + title_height, position.y + style.frame_padding), style.text_scale, style.text_color);
+
+        let content_y = position.y + title_height + style.window_padding;
+
+        self.layout = Some(Layout {
+            window_index,
+            window_id: id,
+            collapsed,
+            title_width,
+            background_index,
+            title_bar_index,
+            content_x: position.x + style.window_padding,
+            indent: 0.0,
+            next_y: content_y,
+            same_line: false,
+            last_item: Rect::new(position.x + style.window_padding, content_y, 0.0, 0.0),
+            line_bottom: content_y,
+            content_right: position.x,
+            content_bottom: content_y
+        });
+
+        !collapsed
+    }
+
+    pub fn end_window(&mut self) {
+        let layout = self.layout.take().expect("end_window has to come after begin_window");
+        self.id_stack.clear();
+
+        let title_height = self.get_frame_height();
+        let padding = self.style.window_padding;
+        let window = &mut self.windows[layout.window_index];
+
+        window.size = if layout.collapsed {
+            Vector2::new(window.size.x.max(layout.title_width), title_height)
+        } else {
+            Vector2::new(
+                (layout.content_right + padding - window.position.x).max(layout.title_width),
+                layout.content_bottom + padding - window.position.y)
+        };
+
+        let bounds = Rect::new(window.position.x, window.position.y, window.size.x, window.size.y);
+        window.draw_list.set_rect(layout.background_index, bounds);
+        window.draw_list.set_rect(layout.title_bar_index, Rect::new(bounds.x, bounds.y, bounds.width, title_height));
+        window.draw_list.draw_rect(bounds, 1.0, self.style.border_color);
+
+        // Keeps the mouse from falling through empty parts of the window to the game
+        if self.mouse_pressed && self.active_id.is_none() && self.hovered_window == Some(layout.window_id) {
+            self.active_id = Some(layout.window_id);
+        }
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let size = self.font.measure_text(text, self.style.text_scale);
+        let rect = self.allocate(size.x, size.y);
+        let (scale, color) = (self.style.text_scale, self.style.text_color);
+
+        self.get_draw_list().draw_text(text, Vector2::new(rect.x, rect.y), scale, color);
+    }
+
+    // Returns true when clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        let id = self.make_id(label);
+        let text = DebugUi::get_display_text(label);
+        let padding = self.style.frame_padding;
+        let text_width = self.font.measure_text(text, self.style.text_scale).x;
+
+        let rect = self.allocate(text_width + padding * 2.0, self.get_frame_height());
+        let interaction = self.interact_current(id, rect);
+        let color = self.get_widget_color(interaction);
+        let (scale, text_color) = (self.style.text_scale, self.style.text_color);
+
+        let draw_list = self.get_draw_list();
+        draw_list.fill_rect(rect, color);
+        draw_list.draw_text(text, Vector2::new(rect.x + padding, rect.y + padding), scale, text_color);
+
+        interaction.clicked
+    }
+
+    // Returns true when clicked, which flips "value".
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let id = self.make_id(label);
+        let text = DebugUi::get_display_text(label);
+        let box_size = self.get_frame_height();
+        let text_width = self.font.measure_text(text, self.style.text_scale).x;
+
+        let rect = self.allocate(box_size + self.style.spacing + text_width, box_size);
+        let interaction = self.interact_current(id, rect);
+
+        if interaction.clicked {
+            *value = !*value;
+        }
+
+        let box_rect = Rect::new(rect.x, rect.y, box_size, box_size);
+        let color = self.get_widget_color(interaction);
+        let style = self.style;
+
+        let draw_list = self.get_draw_list();
+        draw_list.fill_rect(box_rect, color);
+
+        if *value {
+            draw_list.fill_rect(box_rect.shrink(box_size * 0.25), style.accent_color);
+        }
+
+        draw_list.draw_text(text, Vector2::new(box_rect.get_right() + style.spacing, rect.y + style.frame_padding), style.text_scale, style.text_color);
+
+        interaction.clicked
+    }
+
+    // Drag along the bar to pick a value between "min" and "max". Returns true when the value changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = self.make_id(label);
+        let (track, label_position) = self.allocate_item(label);
+        let interaction = self.interact_current(id, track);
+
+        let grab_width = track.height * 0.6;
+        let range = max - min;
+        let mut changed = false;
+
+        if interaction.active && range > 0.0 {
+            let fraction = ((self.mouse_position.x - track.x - grab_width * 0.5) / (track.width - grab_width)).clamp(0.0, 1.0);
+            let new_value = min + fraction * range;
+
+            if new_value != *value {
+                *value = new_value;
+                changed = true;
+            }
+        }
+
+        let fraction = if range > 0.0 { ((*value - min) / range).clamp(0.0, 1.0) } else { 0.0 };
+        let grab = Rect::new(track.x + fraction * (track.width - grab_width), track.y, grab_width, track.height);
+        let color = self.get_widget_color(interaction);
+        let accent_color = self.style.accent_color;
+
+        let draw_list = self.get_draw_list();
+        draw_list.fill_rect(track, color);
+        draw_list.fill_rect(grab.shrink(2.0), accent_color);
+
+        self.draw_value(*value, track);
+        self.draw_label(label, label_position);
+
+        changed
+    }
+
+    // Drag left or right on the box to change the value by "speed" per pixel. Returns true when the value changed.
+    // For values without a useful range, like positions.
+    pub fn drag(&mut self, label: &str, value: &mut f32, speed: f32) -> bool {
+        let id = self.make_id(label);
+        let (frame, label_position) = self.allocate_item(label);
+        let interaction = self.interact_current(id, frame);
+
+        let moved = self.mouse_position.x - self.previous_mouse_position.x;
+        let changed = interaction.active && !interaction.pressed && moved != 0.0;
+
+        if changed {
+            *value += moved * speed;
+        }
+
+        let color = self.get_widget_color(interaction);
+        self.get_draw_list().fill_rect(frame, color);
+
+        self.draw_value(*value, frame);
+        self.draw_label(label, label_position);
+
+        changed
+    }
+
+    // A line of editable text. Click it to type, press enter or escape or click elsewhere to stop.
+    // Returns true when the text changed. Only characters the font has can be typed.
+    pub fn text_input(&mut self, label: &str, text: &mut String) -> bool {
+        let id = self.make_id(label);
+        let (frame, label_position) = self.allocate_item(label);
+        let interaction = self.interact_current(id, frame);
+
+        let scale = self.style.text_scale;
+        let padding = self.style.frame_padding;
+        let inner_width = frame.width - padding * 2.0;
+
+        if interaction.pressed {
+            self.focused_id = Some(id);
+            self.text_cursor = self.font.get_index_at(text, scale, self.mouse_position.x - frame.x - padding);
+        }
+
+        let mut changed = false;
+
+        if self.focused_id == Some(id) {
+            // The text may have been changed since the cursor was placed
+            let mut cursor = self.text_cursor.min(text.len());
+            while !text.is_char_boundary(cursor) {
+                cursor -= 1;
+            }
+
+            for event in &self.keyboard_events {
+                match *event {
+                    WindowEvent::CharacterTyped(character) if character.is_ascii() && !character.is_ascii_control()
+                        && self.font.get_glyph(character as u8).is_some() =>
+                    {
+                        text.insert(cursor, character);
+                        cursor += character.len_utf8();
+                        changed = true;
+                    },
+                    WindowEvent::KeyPressed { key, .. } => match key {
+                        Key::Backspace if cursor > 0 => {
+                            cursor = DebugUi::get_previous_boundary(text, cursor);
+                            text.remove(cursor);
+                            changed = true;
+                        },
+                        Key::Delete if cursor < text.len() => {
+                            text.remove(cursor);
+                            changed = true;
+                        },
+                        Key::Left => cursor = DebugUi::get_previous_boundary(text, cursor),
+                        Key::Right => cursor = DebugUi::get_next_boundary(text, cursor),
+                        Key::Home => cursor = 0,
+                        Key::End => cursor = text.len(),
+                        // Whatever was typed after this frame doesn't go in anymore
+                        Key::Enter | Key::Escape => {
+                            self.focused_id = None;
+                            break;
+                        },
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+
+            self.text_cursor = cursor;
+        }
+
+        let focused = self.focused_id == Some(id);
+        let cursor_x = if focused { self.font.measure_text(&text[..self.text_cursor], scale).x } else { 0.0 };
+
+        // Scrolled so the cursor stays in view
+        let scroll = (cursor_x - inner_width).max(0.0);
+        let visible = self.get_visible_range(text, scroll, inner_width);
+        let visible_x = self.font.measure_text(&text[..visible.start], scale).x - scroll;
+
+        let color = self.get_widget_color(interaction);
+        let style = self.style;
+
+        let draw_list = self.get_draw_list();
+        draw_list.fill_rect(frame, color);
+        draw_list.draw_text(&text[visible], Vector2::new(frame.x + padding + visible_x, frame.y + padding), scale, style.text_color);
+
+        if focused {
+            let x = frame.x + padding + cursor_x - scroll;
+            draw_list.draw_line(Vector2::new(x, frame.y + padding), Vector2::new(x, frame.get_bottom() - padding), 1.0, style.accent_color);
+            draw_list.draw_rect(frame, 1.0, style.accent_color);
+        }
+
+        self.draw_label(label, label_position);
+
+        changed
+    }
+
+    // A line that opens and closes the widgets under it. Returns true while open,
+    // In which case the widgets after it are indented until the matching tree_pop.
+    //
+    // if ui.tree_node("Camera") {
+    //     ui.drag("x", &mut x, 1.0);
+    //     ui.tree_pop();
+    // }
+    pub fn tree_node(&mut self, label: &str) -> bool {
+        let id = self.make_id(label);
+        let text = DebugUi::get_display_text(label);
+        let scale = self.style.text_scale;
+        let arrow_width = self.get_line_height();
+        let text_width = self.font.measure_text(text, scale).x;
+
+        let rect = self.allocate(arrow_width + text_width + self.style.frame_padding, self.get_line_height());
+        let interaction = self.interact_current(id, rect);
+
+        let open = self.open_tree_nodes.entry(id).or_insert(false);
+
+        if interaction.clicked {
+            *open = !*open;
+        }
+
+        let open = *open;
+        let style = self.style;
+        let draw_list = self.get_draw_list();
+
+        if interaction.hovered {
+            draw_list.fill_rect(rect, style.widget_hovered_color);
+        }
+
+        draw_list.draw_text(if open { "v" } else { ">" }, Vector2::new(rect.x + 2.0, rect.y), scale, style.text_color);
+        draw_list.draw_text(text, Vector2::new(rect.x + arrow_width, rect.y), scale, style.text_color);
+
+        if open {
+            self.id_stack.push(id);
+            self.get_layout().indent += style.indent;
+        }
+
+        open
+    }
+
+    // Ends the widgets of an open tree node.
+    pub fn tree_pop(&mut self) {
+        // The window's id is always at the bottom
+        if self.id_stack.len() < 2 {
+            panic!("tree_pop has to come after a tree_node that returned true");
+        }
+
+        self.id_stack.pop();
+
+        let indent = self.style.indent;
+        self.get_layout().indent -= indent;
+    }
+
+    // A line graph of "values", oldest on the left. Values outside "min" to "max" are drawn at the edges.
+    // Hovering it shows the value under the mouse. See PlotHistory for keeping the last few values of something.
+    pub fn plot(&mut self, label: &str, values: &[f32], min: f32, max: f32) {
+        let id = self.make_id(label);
+        let text = DebugUi::get_display_text(label);
+        let rect = self.allocate(self.style.item_width, self.style.plot_height);
+        let interaction = self.interact_current(id, rect);
+        let style = self.style;
+
+        let range = if max > min { max - min } else { 1.0 };
+        let get_point = |index: usize, value: f32| {
+            let x = rect.x + index as f32 / (values.len() - 1) as f32 * rect.width;
+            let y = rect.get_bottom() - ((value - min) / range).clamp(0.0, 1.0) * rect.height;
+            Vector2::new(x, y)
+        };
+
+        let draw_list = self.get_draw_list();
+        draw_list.fill_rect(rect, style.widget_color);
+
+        if values.len() >= 2 {
+            for index in 1..values.len() {
+                draw_list.draw_line(get_point(index - 1, values[index - 1]), get_point(index, values[index]), 1.0, style.accent_color);
+            }
+        }
+
+        draw_list.draw_text(text, Vector2::new(rect.x + style.frame_padding, rect.y + style.frame_padding), style.text_scale, style.text_color);
+
+        if interaction.hovered && values.len() >= 2 {
+            let index = (((self.mouse_position.x - rect.x) / rect.width * (values.len() - 1) as f32).round() as usize).min(values.len() - 1);
+            let point = get_point(index, values[index]);
+
+            let draw_list = self.get_draw_list();
+            draw_list.draw_line(Vector2::new(point.x, rect.y), Vector2::new(point.x, rect.get_bottom()), 1.0, style.border_color);
+            draw_list.fill_rect(Rect::new(point.x - 2.0, point.y - 2.0, 4.0, 4.0), style.text_color);
+
+            let value_rect = Rect::new(rect.x, rect.get_bottom() - self.get_frame_height(), rect.width, self.get_frame_height());
+            self.draw_value(values[index], value_rect);
+        }
+    }
+
+    // A horizontal line between groups of widgets.
+    pub fn separator(&mut self) {
+        let rect = self.allocate(self.style.item_width, self.style.spacing);
+        let color = self.style.border_color;
+        let y = rect.y + rect.height * 0.5;
+
+        self.get_draw_list().draw_line(Vector2::new(rect.x, y), Vector2::new(rect.get_right(), y), 1.0, color);
+    }
+
+    // Puts the next widget to the right of the last one, rather than under it.
+    pub fn same_line(&mut self) {
+        self.get_layout().same_line = true;
+    }
+
+    // Draws the windows shown this frame on top of everything, in screen coordinates.
+    // Call it last in Game::render. The renderer's camera and layer are left as they were.
+    pub fn render(&self, renderer: &mut Renderer2d) {
+        let camera_position = renderer.get_camera_position();
+        let layer = renderer.get_layer();
+
+        renderer.set_camera_position(0.0, 0.0);
+        renderer.set_layer(DEBUG_UI_LAYER);
+
+        for window in self.windows.iter().filter(|window| window.shown) {
+            window.draw_list.render(renderer);
+        }
+
+        renderer.set_layer(layer);
+        renderer.set_camera_position(camera_position.x, camera_position.y);
+    }
+
+    // What render would draw, front window last. For checking the UI without a renderer.
+    pub fn get_draw_lists(&self) -> impl Iterator<Item = &DrawList> + '_ {
+        self.windows.iter().filter(|window| window.shown).map(|window| &window.draw_list)
+    }
+
+    fn get_layout(&mut self) -> &mut Layout {
+        self.layout.as_mut().expect("Widgets have to go between begin_window and end_window")
+    }
+
+    fn get_draw_list(&mut self) -> &mut DrawList {
+        let window_index = self.get_layout().window_index;
+        &mut self.windows[window_index].draw_list
+    }
+
+    fn get_line_height(&self) -> f32 {
+        self.font.get_line_height() * self.style.text_scale
+    }
+
+    // Height of widgets with a frame around their text, like buttons
+    fn get_frame_height(&self) -> f32 {
+        self.get_line_height() + self.style.frame_padding * 2.0
+    }
+
+    // Takes the next spot in the window for a widget of the given size.
+    fn allocate(&mut self, width: f32, height: f32) -> Rect {
+        let spacing = self.style.spacing;
+        let layout = self.get_layout();
+
+        let rect = if layout.same_line {
+            Rect::new(layout.last_item.get_right() + spacing, layout.last_item.y, width, height)
+        } else {
+            Rect::new(layout.content_x + layout.indent, layout.next_y, width, height)
+        };
+
+        layout.line_bottom = if layout.same_line { layout.line_bottom.max(rect.get_bottom()) } else { rect.get_bottom() };
+        layout.next_y = layout.line_bottom + spacing;
+        layout.same_line = false;
+        layout.last_item = rect;
+        layout.content_right = layout.content_right.max(rect.get_right());
+        layout.content_bottom = layout.content_bottom.max(rect.get_bottom());
+
+        rect
+    }
+
+    // Takes the spot for an item_width wide framed widget with the label to its right.
+    // Returns the widget's frame and where the label goes.
+    fn allocate_item(&mut self, label: &str) -> (Rect, Vector2) {
+        let text_width = self.font.measure_text(DebugUi::get_display_text(label), self.style.text_scale).x;
+        let item_width = self.style.item_width;
+        let height = self.get_frame_height();
+        let label_width = if text_width > 0.0 { self.style.spacing + text_width } else { 0.0 };
+
+        let rect = self.allocate(item_width + label_width, height);
+        let label_position = Vector2::new(rect.x + item_width + self.style.spacing, rect.y + self.style.frame_padding);
+
+        (Rect::new(rect.x, rect.y, item_width, height), label_position)
+    }
+
+    fn interact_current(&mut self, id: WidgetId, rect: Rect) -> Interaction {
+        let window_id = self.get_layout().window_id;
+        self.interact(window_id, id, rect)
+    }
+
+    fn interact(&mut self, window_id: WidgetId, id: WidgetId, rect: Rect) -> Interaction {
+        // While something is held, nothing else reacts to the mouse
+        let free = self.active_id.is_none() || self.active_id == Some(id);
+        let hovered = free && self.hovered_window == Some(window_id) && rect.contains(self.mouse_position);
+        let pressed = hovered && self.mouse_pressed;
+
+        if pressed {
+            self.active_id = Some(id);
+        }
+
+        let active = self.active_id == Some(id);
+
+        Interaction {
+            hovered,
+            pressed,
+            active,
+            clicked: active && self.mouse_released && hovered
+        }
+    }
+
+    fn get_widget_color(&self, interaction: Interaction) -> Color {
+        if interaction.active {
+            self.style.widget_active_color
+        } else if interaction.hovered {
+            self.style.widget_hovered_color
+        } else {
+            self.style.widget_color
+        }
+    }
+
+    fn draw_label(&mut self, label: &str, position: Vector2) {
+        let text = DebugUi::get_display_text(label);
+        let (scale, color) = (self.style.text_scale, self.style.text_color);
+
+        self.get_draw_list().draw_text(text, position, scale, color);
+    }
+
+    // Draws a number centered in "rect".
+    fn draw_value(&mut self, value: f32, rect: Rect) {
+        let mut text = std::mem::take(&mut self.scratch);
+        text.clear();
+        write!(text, "{:.3}", value).unwrap();
+
+        let size = self.font.measure_text(&text, self.style.text_scale);
+        let position = Vector2::new(rect.x + (rect.width - size.x) * 0.5, rect.y + (rect.height - size.y) * 0.5);
+        let (scale, color) = (self.style.text_scale, self.style.text_color);
+
+        self.get_draw_list().draw_text(&text, position, scale, color);
+        self.scratch = text;
+    }
+
+    // The characters of "text" that fit in "width" pixels, after skipping the first "scroll" pixels.
+    fn get_visible_range(&self, text: &str, scroll: f32, width: f32) -> Range<usize> {
+        let mut pen = 0.0;
+        let mut start = text.len();
+        let mut end = text.len();
+
+        for (index, character) in text.char_indices() {
+            let advance = self.font.measure_text(&text[index..index + character.len_utf8()], self.style.text_scale).x;
+
+            if start == text.len() && pen >= scroll - 0.01 {
+                start = index;
+            }
+
+            if pen + advance - scroll > width + 0.01 {
+                end = index;
+                break;
+            }
+
+            pen += advance;
+        }
+
+        start.min(end)..end
+    }
+
+    // Where the character before "index" starts.
+    fn get_previous_boundary(text: &str, index: usize) -> usize {
+        text[..index].chars().next_back().map_or(0, |character| index - character.len_utf8())
+    }
+
+    // Where the character after "index" ends.
+    fn get_next_boundary(text: &str, index: usize) -> usize {
+        text[index..].chars().next().map_or(index, |character| index + character.len_utf8())
+    }
+
+    fn make_id(&self, label: &str) -> WidgetId {
+        DebugUi::hash_id(*self.id_stack.last().unwrap_or(&0), label)
+    }
+
+    fn hash_id(seed: WidgetId, label: &str) -> WidgetId {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        label.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // The part of a label before "##".
+    fn get_display_text(label: &str) -> &str {
+        match label.find("##") {
+            Some(index) => &label[..index],
+            None => label
+        }
+    }
+}
+
+// The last few values of something, like frame times, for plotting.
+pub struct PlotHistory {
+    values: Vec<f32>,
+    capacity: usize
+}
+
+impl PlotHistory {
+    pub fn new(capacity: usize) -> PlotHistory {
+        if capacity == 0 {
+            panic!("A PlotHistory has to keep at least one value");
+        }
+
+        PlotHistory {
+            values: Vec::with_capacity(capacity),
+            capacity
+        }
+    }
+
+    // Adds a value, dropping the oldest one when full.
+    pub fn push(&mut self, value: f32) {
+        if self.values.len() == self.capacity {
+            self.values.remove(0);
+        }
+
+        self.values.push(value);
+    }
+
+    // Oldest first.
+    pub fn get_values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn get_latest(&self) -> Option<f32> {
+        self.values.last().cloned()
+    }
+
+    pub fn get_average(&self) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+
+        self.values.iter().sum::<f32>() / self.values.len() as f32
+    }
+
+    // 0.0 when empty.
+    pub fn get_max(&self) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+
+        self.values.iter().cloned().fold(f32::MIN, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::debug_ui::draw_list::DrawPrimitive;
+
+    // Every ASCII character 10 pixels wide, lines 16 pixels tall. Frames are 24 tall with the default style.
+    fn make_font() -> FontMetrics {
+        let mut text = String::from("info face=\"Test\" padding=0,0,0,0\ncommon lineHeight=16 base=12\n");
+
+        for character in 32..127 {
+            text.push_str(&format!("char id={} x=0 y=0 width=8 height=12 xoffset=0 yoffset=2 xadvance=10 page=0 chnl=0\n", character));
+        }
+
+        FontMetrics::parse(&text).unwrap()
+    }
+
+    // The window sits at (0, 0), so its first widget is at (8, 32), under the title bar and padding
+    const FIRST_WIDGET: (f32, f32) = (8.0, 32.0);
+
+    // Feeds "events" to the UI, then builds a frame with one window.
+    fn run_frame<F: FnMut(&mut DebugUi)>(ui: &mut DebugUi, events: &[WindowEvent], mut build: F) {
+        for event in events {
+            ui.handle_event(event);
+        }
+
+        ui.begin_frame();
+        if ui.begin_window("Test", Vector2::new(0.0, 0.0)) {
+            build(ui);
+        }
+        ui.end_window();
+        ui.end_frame();
+    }
+
+    fn move_to(x: f32, y: f32) -> WindowEvent {
+        WindowEvent::MouseMoved { position: Vector2::new(x, y) }
+    }
+
+    fn press() -> WindowEvent {
+        WindowEvent::MouseButtonPressed { button: MouseButton::Left }
+    }
+
+    fn release() -> WindowEvent {
+        WindowEvent::MouseButtonReleased { button: MouseButton::Left }
+    }
+
+    fn key(key: Key) -> WindowEvent {
+        WindowEvent::KeyPressed { key, repeat: false }
+    }
+
+    // Where the text was drawn this frame
+    fn find_text(ui: &DebugUi, text: &str) -> Option<Vector2> {
+        ui.get_draw_lists()
+            .flat_map(|draw_list| draw_list.get_primitives().iter().map(move |primitive| (draw_list, primitive)))
+            .find_map(|(draw_list, primitive)| match primitive {
+                DrawPrimitive::Text { position, range, .. } if draw_list.get_text(range) == text => Some(*position),
+                _ => None
+            })
+    }
+
+    #[test]
+    fn buttons_report_clicks() {
+        let mut ui = DebugUi::new(make_font());
+        let mut clicks = Vec::new();
+
+        // The window is only under the mouse once it has been shown and sized
+        let frames = [
+            vec![],
+            vec![move_to(FIRST_WIDGET.0 + 2.0, FIRST_WIDGET.1 + 2.0), press()],
+            vec![],
+            vec![release()],
+            vec![]
+        ];
+
+        for events in frames.iter() {
+            run_frame(&mut ui, events, |ui| clicks.push(ui.button("Go")));
+        }
+
+        assert_eq!(clicks, vec![false, false, false, true, false]);
+
+        // Letting go somewhere else isn't a click
+        let mut clicked = false;
+        run_frame(&mut ui, &[press()], |ui| clicked |= ui.button("Go"));
+        run_frame(&mut ui, &[move_to(300.0, 300.0), release()], |ui| clicked |= ui.button("Go"));
+        assert!(!clicked);
+        assert!(!ui.wants_mouse());
+    }
+
+    #[test]
+    fn checkboxes_and_sliders_change_their_values() {
+        let mut ui = DebugUi::new(make_font());
+        let mut enabled = false;
+        let mut speed = 2.0;
+        let mut changes = Vec::new();
+
+        let mut build = |ui: &mut DebugUi| {
+            let checkbox_changed = ui.checkbox("Enabled", &mut enabled);
+            let slider_changed = ui.slider("Speed", &mut speed, 0.0, 10.0);
+            changes.push((checkbox_changed, slider_changed));
+        };
+
+        run_frame(&mut ui, &[], &mut build);
+        run_frame(&mut ui, &[move_to(10.0, 40.0), press(), release()], &mut build);
+
+        // The slider is under the checkbox. Its grab is 14.4 wide, so the middle of the track is 8 + 7.2 + 92.8 across.
+        run_frame(&mut ui, &[move_to(108.0, 60.0), press()], &mut build);
+        run_frame(&mut ui, &[move_to(1000.0, 0.0)], &mut build);
+        run_frame(&mut ui, &[release()], &mut build);
+        run_frame(&mut ui, &[move_to(8.0, 60.0)], &mut build);
+
+        assert_eq!(changes, vec![(false, false), (true, false), (false, true), (false, true), (false, false), (false, false)]);
+        assert!(enabled);
+        assert_eq!(speed, 10.0);
+
+        // Clicking at the far left goes to the minimum
+        run_frame(&mut ui, &[move_to(8.0, 40.0), press(), release()], |ui| { ui.slider("Speed", &mut speed, 0.0, 10.0); });
+        assert_eq!(speed, 0.0);
+    }
+
+    #[test]
+    fn editing_text() {
+        let mut ui = DebugUi::new(make_font());
+        let mut text = String::from("ac");
+        let mut changed = false;
+
+        run_frame(&mut ui, &[], |ui| { ui.text_input("Name", &mut text); });
+        assert!(!ui.wants_keyboard());
+
+        // Clicking between the a and the c puts the cursor there
+        run_frame(&mut ui, &[move_to(FIRST_WIDGET.0 + 4.0 + 12.0, FIRST_WIDGET.1 + 2.0), press(), release()], |ui| changed |= ui.text_input("Name", &mut text));
+        assert!(ui.wants_keyboard());
+        assert!(!changed);
+
+        let typing = [WindowEvent::CharacterTyped('b'), WindowEvent::CharacterTyped('\n'), key(Key::End), WindowEvent::CharacterTyped('d')];
+        run_frame(&mut ui, &typing, |ui| changed |= ui.text_input("Name", &mut text));
+        assert_eq!(text, "abcd");
+        assert!(changed);
+
+        let editing = [key(Key::Left), key(Key::Backspace), key(Key::Home), key(Key::Delete), key(Key::Right), WindowEvent::CharacterTyped('!')];
+        run_frame(&mut ui, &editing, |ui| { ui.text_input("Name", &mut text); });
+        assert_eq!(text, "b!d");
+
+        // Typing stops with enter, even within the same frame
+        run_frame(&mut ui, &[key(Key::Enter), WindowEvent::CharacterTyped('x')], |ui| { ui.text_input("Name", &mut text); });
+        assert_eq!(text, "b!d");
+        assert!(!ui.wants_keyboard());
+
+        // Clicking elsewhere lets go of the keyboard too
+        run_frame(&mut ui, &[move_to(FIRST_WIDGET.0 + 4.0, FIRST_WIDGET.1 + 2.0), press(), release()], |ui| { ui.text_input("Name", &mut text); });
+        assert!(ui.wants_keyboard());
+        run_frame(&mut ui, &[move_to(500.0, 500.0), press(), release()], |ui| { ui.text_input("Name", &mut text); });
+        assert!(!ui.wants_keyboard());
+    }
+
+    #[test]
+    fn editing_text_with_other_characters() {
+        let mut ui = DebugUi::new(make_font());
+        let mut text = String::from("é");
+
+        let edit = |ui: &mut DebugUi, text: &mut String, events: &[WindowEvent]| {
+            run_frame(ui, events, |ui| { ui.text_input("Name", text); });
+        };
+
+        edit(&mut ui, &mut text, &[]);
+        edit(&mut ui, &mut text, &[move_to(FIRST_WIDGET.0 + 4.0, FIRST_WIDGET.1 + 2.0), press(), release()]);
+        assert!(ui.wants_keyboard());
+
+        // The cursor steps over the whole character
+        edit(&mut ui, &mut text, &[key(Key::End), key(Key::Left), WindowEvent::CharacterTyped('x')]);
+        assert_eq!(text, "xé");
+        edit(&mut ui, &mut text, &[key(Key::Right), WindowEvent::CharacterTyped('y'), key(Key::Left), key(Key::Backspace)]);
+        assert_eq!(text, "xy");
+
+        // Only ASCII can be typed
+        edit(&mut ui, &mut text, &[key(Key::End), WindowEvent::CharacterTyped('ü')]);
+        assert_eq!(text, "xy");
+
+        // The é takes up no space, so clicking right after the a puts the cursor after it, before the b
+        text = String::from("aéb");
+        edit(&mut ui, &mut text, &[move_to(FIRST_WIDGET.0 + 4.0 + 10.0, FIRST_WIDGET.1 + 2.0), press(), release(), key(Key::Backspace)]);
+        assert_eq!(text, "ab");
+
+        // Changed from outside, leaving the cursor inside the first é. It moves back to the start of it.
+        text = "éa".repeat(30);
+        edit(&mut ui, &mut text, &[key(Key::Delete), key(Key::Backspace)]);
+        assert_eq!(text, format!("a{}", "éa".repeat(29)));
+
+        // Long enough to scroll. The 290 pixels up to the cursor are scrolled by 98, so drawing starts at the 11th a,
+        // With the é before it, and stops at the last a, which would stick out
+        edit(&mut ui, &mut text, &[key(Key::Home), key(Key::Delete), key(Key::End), key(Key::Left), key(Key::Left), WindowEvent::CharacterTyped('z')]);
+        assert_eq!(text, format!("{}zéa", "éa".repeat(28)));
+        assert!(find_text(&ui, &format!("{}zé", "éa".repeat(18))).is_some());
+    }
+
+    #[test]
+    fn tree_nodes_indent_their_widgets() {
+        let mut ui = DebugUi::new(make_font());
+        let mut open_states = Vec::new();
+
+        let mut build = |ui: &mut DebugUi| {
+            let open = ui.tree_node("Node");
+            open_states.push(open);
+
+            if open {
+                ui.button("Inside");
+                ui.tree_pop();
+            }
+
+            ui.button("After");
+        };
+
+        run_frame(&mut ui, &[], &mut build);
+        assert_eq!(find_text(&ui, "Inside"), None);
+        assert_eq!(find_text(&ui, "After").unwrap().x, FIRST_WIDGET.0 + 4.0);
+
+        run_frame(&mut ui, &[move_to(FIRST_WIDGET.0 + 2.0, FIRST_WIDGET.1 + 2.0), press(), release()], &mut build);
+        run_frame(&mut ui, &[], &mut build);
+        assert_eq!(open_states, vec![false, true, true]);
+
+        // Moved in by the indent, and back out after tree_pop
+        assert_eq!(find_text(&ui, "Inside").unwrap().x, FIRST_WIDGET.0 + 14.0 + 4.0);
+        assert_eq!(find_text(&ui, "After").unwrap().x, FIRST_WIDGET.0 + 4.0);
+    }
+
+    #[test]
+    fn nested_tree_nodes_tell_apart_widgets_with_the_same_label() {
+        let mut ui = DebugUi::new(make_font());
+        let mut clicks = Vec::new();
+
+        // Both nodes start open after clicking them in turn
+        run_frame(&mut ui, &[], |ui| { ui.tree_node("A"); ui.tree_node("B"); });
+        run_frame(&mut ui, &[move_to(FIRST_WIDGET.0 + 2.0, FIRST_WIDGET.1 + 2.0), press(), release()], |ui| {
+            if ui.tree_node("A") {
+                ui.tree_pop();
+            }
+        });
+
+        let mut build = |ui: &mut DebugUi| {
+            if ui.tree_node("A") {
+                clicks.push(("A", ui.button("Reset")));
+                ui.tree_pop();
+            }
+        };
+
+        run_frame(&mut ui, &[], &mut build);
+        let reset = find_text(&ui, "Reset").unwrap();
+        run_frame(&mut ui, &[move_to(reset.x, reset.y), press(), release()], &mut build);
+        assert_eq!(clicks, vec![("A", false), ("A", true)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "tree_pop has to come after a tree_node that returned true")]
+    fn tree_pop_without_an_open_tree_node_panics() {
+        let mut ui = DebugUi::new(make_font());
+        run_frame(&mut ui, &[], |ui| ui.tree_pop());
+    }
+
+    #[test]
+    fn plot_history_drops_the_oldest_value() {
+        let mut history = PlotHistory::new(3);
+        assert_eq!(history.get_latest(), None);
+        assert_eq!(history.get_average(), 0.0);
+        assert_eq!(history.get_max(), 0.0);
+
+        for value in [1.0, 5.0, 2.0, 3.0] {
+            history.push(value);
+        }
+
+        assert_eq!(history.get_values(), &[5.0, 2.0, 3.0]);
+        assert_eq!(history.get_latest(), Some(3.0));
+        assert_eq!(history.get_max(), 5.0);
+
+        history.push(4.0);
+        assert_eq!(history.get_values(), &[2.0, 3.0, 4.0]);
+        assert_eq!(history.get_average(), 3.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one value")]
+    fn empty_plot_histories_panic() {
+        PlotHistory::new(0);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::core::matrix3x2::Matrix3x2;
+use crate::core::sprite::Sprite;
+
+use linear_beaglebra::vector2::Vector2;
+
+// Where an entity is in the world. Entities with a Transform and a Sprite are drawn by Renderer2d::draw_entities.
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    pub position: Vector2,
+    // Degrees, clockwise on screen
+    pub rotation: f32,
+    pub scale: Vector2
+}
+
+impl Transform {
+    pub fn new(x: f32, y: f32) -> Transform {
+        Transform {
+            position: Vector2::new(x, y),
+            rotation: 0.0,
+            scale: Vector2::new(1.0, 1.0)
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Transform {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale_x: f32, scale_y: f32) -> Transform {
+        self.scale = Vector2::new(scale_x, scale_y);
+        self
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.position = Vector2::new(self.position.x + x, self.position.y + y);
+    }
+
+    // Scales, then rotates, then moves to the position.
+    pub fn get_matrix(&self) -> Matrix3x2 {
+        Matrix3x2::identity()
+            .translate(self.position)
+            .rotate(self.rotation)
+            .scale(self.scale.x, self.scale.y)
+    }
+
+    // Copies the transform onto the sprite. The sprite's origin stays what it is.
+    pub fn apply_to(&self, sprite: &mut Sprite) {
+        sprite.position_x = self.position.x;
+        sprite.position_y = self.position.y;
+        sprite.angle = self.rotation;
+        sprite.scale_x = self.scale.x;
+        sprite.scale_y = self.scale.y;
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::new(0.0, 0.0)
+    }
+}
+
+// Marks the entity whose Transform the view follows. The position is the top left corner of the view.
+// NOTE: Only the position is used for now, the renderer can't rotate or zoom the view.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Camera;
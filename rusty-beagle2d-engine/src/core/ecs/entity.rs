@@ -0,0 +1,130 @@
+// An entity is just an id that components are attached to.
+// LEARN: Generational indices
+// Indices of despawned entities are reused, so the id also carries a generation that is bumped on every reuse.
+// An old Entity value kept around somewhere then no longer matches, instead of silently pointing at a new entity.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32
+}
+
+impl Entity {
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn get_generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// Hands out entity ids and keeps track of which are alive.
+pub struct EntityAllocator {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<u32>,
+    alive_count: usize
+}
+
+impl EntityAllocator {
+    pub fn new() -> EntityAllocator {
+        EntityAllocator {
+            generations: Vec::new(),
+            alive: Vec::new(),
+            free_indices: Vec::new(),
+            alive_count: 0
+        }
+    }
+
+    pub fn allocate(&mut self) -> Entity {
+        self.alive_count += 1;
+
+        match self.free_indices.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity { index, generation: self.generations[index as usize] }
+            },
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity { index: self.generations.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    // Returns false if the entity was already freed.
+    pub fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_indices.push(entity.index);
+        self.alive_count -= 1;
+
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len() && self.alive[index] && self.generations[index] == entity.generation
+    }
+
+    pub fn get_alive_count(&self) -> usize {
+        self.alive_count
+    }
+
+    // All living entities, ordered by index.
+    pub fn get_alive_entities(&self) -> Vec<Entity> {
+        self.iter_alive_entities().collect()
+    }
+
+    pub fn iter_alive_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        (0..self.alive.len())
+            .filter(move |&index| self.alive[index])
+            .map(move |index| Entity { index: index as u32, generation: self.generations[index] })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_indices_are_reused_with_a_new_generation() {
+        let mut allocator = EntityAllocator::new();
+        let first = allocator.allocate();
+        let second = allocator.allocate();
+        assert_eq!((first.get_index(), second.get_index()), (0, 1));
+
+        assert!(allocator.free(first));
+        assert!(!allocator.free(first));
+        assert!(!allocator.is_alive(first));
+
+        let reused = allocator.allocate();
+        assert_eq!(reused.get_index(), 0);
+        assert_eq!(reused.get_generation(), 1);
+        assert!(allocator.is_alive(reused));
+        assert!(!allocator.is_alive(first));
+
+        allocator.free(reused);
+        assert_eq!(allocator.allocate().get_generation(), 2);
+    }
+
+    #[test]
+    fn alive_entities() {
+        let mut allocator = EntityAllocator::new();
+        let entities: Vec<Entity> = (0..4).map(|_| allocator.allocate()).collect();
+
+        allocator.free(entities[1]);
+        allocator.free(entities[2]);
+        assert_eq!(allocator.get_alive_count(), 2);
+        assert_eq!(allocator.get_alive_entities(), vec![entities[0], entities[3]]);
+
+        // The most recently freed index is reused first
+        assert_eq!(allocator.allocate().get_index(), 2);
+        assert_eq!(allocator.get_alive_count(), 3);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod entity;
+pub mod storage;
+pub mod world;
+pub mod query;
+pub mod schedule;
+pub mod components;
+pub mod systems;
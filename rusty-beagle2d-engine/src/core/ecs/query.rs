@@ -0,0 +1,120 @@
+use crate::core::ecs::entity::Entity;
+use crate::core::ecs::storage::SparseSet;
+use crate::core::ecs::world::World;
+
+use std::cell::{Ref, RefMut};
+
+// Something that can be asked of the world for each entity: &T, &mut T, Option<&T>, or a tuple of those.
+// LEARN: Generic associated types
+// "Item" has its own lifetime parameter, so each fetched item only borrows the query state for as long as
+// The caller uses it. That lets for_each hand out &mut components one entity at a time without any unsafe code.
+pub trait Query {
+    // The borrowed storages, held for the whole query
+    type State<'w>;
+    type Item<'s>;
+
+    fn borrow(world: &World) -> Self::State<'_>;
+
+    // The entities that can possibly match, or None if this part of the query doesn't narrow them down.
+    fn get_candidates<'a>(state: &'a Self::State<'_>) -> Option<&'a [Entity]>;
+
+    fn fetch<'s>(state: &'s mut Self::State<'_>, entity: Entity) -> Option<Self::Item<'s>>;
+}
+
+impl<T: 'static> Query for &T {
+    // None if no entity ever had a T
+    type State<'w> = Option<Ref<'w, SparseSet<T>>>;
+    type Item<'s> = &'s T;
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.get_storage::<T>()
+    }
+
+    fn get_candidates<'a>(state: &'a Self::State<'_>) -> Option<&'a [Entity]> {
+        Some(state.as_ref().map_or(&[], |storage| storage.get_entities()))
+    }
+
+    fn fetch<'s>(state: &'s mut Self::State<'_>, entity: Entity) -> Option<Self::Item<'s>> {
+        state.as_ref()?.get(entity)
+    }
+}
+
+impl<T: 'static> Query for &mut T {
+    type State<'w> = Option<RefMut<'w, SparseSet<T>>>;
+    type Item<'s> = &'s mut T;
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.get_storage_mut::<T>()
+    }
+
+    fn get_candidates<'a>(state: &'a Self::State<'_>) -> Option<&'a [Entity]> {
+        Some(state.as_ref().map_or(&[], |storage| storage.get_entities()))
+    }
+
+    fn fetch<'s>(state: &'s mut Self::State<'_>, entity: Entity) -> Option<Self::Item<'s>> {
+        state.as_mut()?.get_mut(entity)
+    }
+}
+
+// Matches every entity, with Some(component) for the ones that have it.
+impl<T: 'static> Query for Option<&T> {
+    type State<'w> = Option<Ref<'w, SparseSet<T>>>;
+    type Item<'s> = Option<&'s T>;
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.get_storage::<T>()
+    }
+
+    fn get_candidates<'a>(_state: &'a Self::State<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn fetch<'s>(state: &'s mut Self::State<'_>, entity: Entity) -> Option<Self::Item<'s>> {
+        Some(state.as_ref().and_then(|storage| storage.get(entity)))
+    }
+}
+
+// LEARN: Macros for tuple impls
+// Rust has no variadic generics, so the Query impl is written once as a macro and stamped out for each tuple size.
+// A tuple matches an entity when every part matches. The shortest candidate list drives the iteration.
+macro_rules! tuple_query {
+    ($($name:ident),+) => {
+        impl<$($name: Query),+> Query for ($($name,)+) {
+            type State<'w> = ($($name::State<'w>,)+);
+            type Item<'s> = ($($name::Item<'s>,)+);
+
+            fn borrow(world: &World) -> Self::State<'_> {
+                ($($name::borrow(world),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn get_candidates<'a>(state: &'a Self::State<'_>) -> Option<&'a [Entity]> {
+                let ($($name,)+) = state;
+                let mut shortest: Option<&'a [Entity]> = None;
+
+                $(
+                    if let Some(candidates) = $name::get_candidates($name) {
+                        if shortest.map_or(true, |shortest| candidates.len() < shortest.len()) {
+                            shortest = Some(candidates);
+                        }
+                    }
+                )+
+
+                shortest
+            }
+
+            #[allow(non_snake_case)]
+            fn fetch<'s>(state: &'s mut Self::State<'_>, entity: Entity) -> Option<Self::Item<'s>> {
+                let ($($name,)+) = state;
+                Some(($($name::fetch($name, entity)?,)+))
+            }
+        }
+    };
+}
+
+tuple_query!(A);
+tuple_query!(A, B);
+tuple_query!(A, B, C);
+tuple_query!(A, B, C, D);
+tuple_query!(A, B, C, D, E);
+tuple_query!(A, B, C, D, E, F);
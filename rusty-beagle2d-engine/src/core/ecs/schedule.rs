@@ -0,0 +1,159 @@
+use crate::core::ecs::world::World;
+
+// When a system runs in the game loop.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Stage {
+    // Zero or more times per frame, with a fixed time step. Game logic and physics go here.
+    FixedUpdate,
+    // Once per frame, after the fixed updates.
+    Render
+}
+
+// A piece of game logic that works on the world.
+// Any closure or function taking &mut World is a system.
+pub trait System {
+    fn run(&mut self, world: &mut World);
+}
+
+impl<F: FnMut(&mut World)> System for F {
+    fn run(&mut self, world: &mut World) {
+        self(world)
+    }
+}
+
+// Resource holding the time step of the stage that is running, in seconds.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DeltaTime {
+    pub seconds: f32
+}
+
+struct ScheduledSystem {
+    name: String,
+    stage: Stage,
+    system: Box<dyn System>
+}
+
+// The systems of a game, in the order they run within each stage.
+pub struct Schedule {
+    systems: Vec<ScheduledSystem>
+}
+
+impl Schedule {
+    pub fn new() -> Schedule {
+        Schedule {
+            systems: Vec::new()
+        }
+    }
+
+    // Systems of the same stage run in the order they were added.
+    pub fn add_system<S: System + 'static>(&mut self, stage: Stage, name: &str, system: S) {
+        self.systems.push(ScheduledSystem {
+            name: String::from(name),
+            stage,
+            system: Box::new(system)
+        });
+    }
+
+    // Returns false if there was no system with that name.
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        let count = self.systems.len();
+        self.systems.retain(|scheduled| scheduled.name != name);
+        self.systems.len() != count
+    }
+
+    // Names of the systems in a stage, in the order they run.
+    pub fn get_system_names(&self, stage: Stage) -> Vec<&str> {
+        self.systems.iter()
+            .filter(|scheduled| scheduled.stage == stage)
+            .map(|scheduled| &scheduled.name[..])
+            .collect()
+    }
+
+    pub fn run_stage(&mut self, stage: Stage, world: &mut World) {
+        for scheduled in self.systems.iter_mut().filter(|scheduled| scheduled.stage == stage) {
+            scheduled.system.run(world);
+        }
+    }
+
+    // Runs the fixed update systems with "dt" stored in the DeltaTime resource.
+    pub fn run_fixed_update(&mut self, world: &mut World, dt: f32) {
+        world.insert_resource(DeltaTime { seconds: dt });
+        self.run_stage(Stage::FixedUpdate, world);
+    }
+
+    // Runs the render systems with the frame time stored in the DeltaTime resource.
+    pub fn run_render(&mut self, world: &mut World, frame_time: f32) {
+        world.insert_resource(DeltaTime { seconds: frame_time });
+        self.run_stage(Stage::Render, world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The order the systems ran in, as a resource
+    struct Log(Vec<String>);
+
+    fn logger(name: &'static str) -> impl FnMut(&mut World) {
+        move |world: &mut World| {
+            let seconds = world.get_resource::<DeltaTime>().map_or(0.0, |delta_time| delta_time.seconds);
+            world.get_resource_mut::<Log>().unwrap().0.push(format!("{} {}", name, seconds));
+        }
+    }
+
+    fn take_log(world: &mut World) -> Vec<String> {
+        std::mem::take(&mut world.get_resource_mut::<Log>().unwrap().0)
+    }
+
+    #[test]
+    fn systems_run_in_order_per_stage() {
+        let mut world = World::new();
+        world.insert_resource(Log(Vec::new()));
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::FixedUpdate, "input", logger("input"));
+        schedule.add_system(Stage::Render, "draw", logger("draw"));
+        schedule.add_system(Stage::FixedUpdate, "physics", logger("physics"));
+        schedule.add_system(Stage::FixedUpdate, "gameplay", logger("gameplay"));
+
+        assert_eq!(schedule.get_system_names(Stage::FixedUpdate), vec!["input", "physics", "gameplay"]);
+        assert_eq!(schedule.get_system_names(Stage::Render), vec!["draw"]);
+
+        schedule.run_fixed_update(&mut world, 0.5);
+        assert_eq!(take_log(&mut world), vec!["input 0.5", "physics 0.5", "gameplay 0.5"]);
+
+        schedule.run_render(&mut world, 0.25);
+        assert_eq!(take_log(&mut world), vec!["draw 0.25"]);
+    }
+
+    #[test]
+    fn remove_system() {
+        let mut world = World::new();
+        world.insert_resource(Log(Vec::new()));
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::FixedUpdate, "a", logger("a"));
+        schedule.add_system(Stage::FixedUpdate, "b", logger("b"));
+
+        assert!(schedule.remove_system("a"));
+        assert!(!schedule.remove_system("a"));
+
+        schedule.run_stage(Stage::FixedUpdate, &mut world);
+        assert_eq!(take_log(&mut world), vec!["b 0"]);
+    }
+
+    #[test]
+    fn systems_see_each_others_changes() {
+        let mut world = World::new();
+        world.insert_resource(0_u32);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::FixedUpdate, "double", |world: &mut World| *world.get_resource_mut::<u32>().unwrap() *= 2);
+        schedule.add_system(Stage::FixedUpdate, "add", |world: &mut World| *world.get_resource_mut::<u32>().unwrap() += 3);
+
+        schedule.run_fixed_update(&mut world, 0.1);
+        schedule.run_fixed_update(&mut world, 0.1);
+        assert_eq!(*world.get_resource::<u32>().unwrap(), 9);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::core::ecs::entity::Entity;
+
+use std::any::Any;
+
+// LEARN: Sparse sets
+// Components are packed tightly in "dense", so iterating a component type touches contiguous memory.
+// "sparse" maps an entity index to the component's position in "dense".
+// Removing swaps the last component into the hole, so nothing ever has to shift.
+pub struct SparseSet<T> {
+    sparse: Vec<Option<usize>>,
+    dense: Vec<T>,
+    entities: Vec<Entity>
+}
+
+impl<T> SparseSet<T> {
+    pub fn new() -> SparseSet<T> {
+        SparseSet {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            entities: Vec::new()
+        }
+    }
+
+    // Adds the component, returning the one the entity had before if any.
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        let index = entity.get_index() as usize;
+
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+
+        match self.sparse[index] {
+            Some(dense_index) => {
+                self.entities[dense_index] = entity;
+                Some(std::mem::replace(&mut self.dense[dense_index], component))
+            },
+            None => {
+                self.sparse[index] = Some(self.dense.len());
+                self.dense.push(component);
+                self.entities.push(entity);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let dense_index = self.get_dense_index(entity)?;
+
+        self.sparse[entity.get_index() as usize] = None;
+        self.entities.swap_remove(dense_index);
+        let component = self.dense.swap_remove(dense_index);
+
+        // The previously last component now sits where the removed one was
+        if dense_index < self.dense.len() {
+            let moved_entity = self.entities[dense_index];
+            self.sparse[moved_entity.get_index() as usize] = Some(dense_index);
+        }
+
+        Some(component)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.get_dense_index(entity).map(|dense_index| &self.dense[dense_index])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.get_dense_index(entity).map(move |dense_index| &mut self.dense[dense_index])
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get_dense_index(entity).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    // The entities that have this component, in storage order.
+    pub fn get_entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn get_dense_index(&self, entity: Entity) -> Option<usize> {
+        let dense_index = (*self.sparse.get(entity.get_index() as usize)?)?;
+
+        // An entity with the same index but another generation is a different entity
+        if self.entities[dense_index] != entity {
+            return None;
+        }
+
+        Some(dense_index)
+    }
+}
+
+// Lets the world keep storages of different component types in one map, and clean them up on despawn.
+pub trait AnyStorage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ecs::entity::EntityAllocator;
+
+    #[test]
+    fn swap_remove_keeps_the_others_reachable() {
+        let mut allocator = EntityAllocator::new();
+        let entities: Vec<Entity> = (0..4).map(|_| allocator.allocate()).collect();
+
+        let mut set = SparseSet::new();
+        for (index, entity) in entities.iter().enumerate() {
+            assert_eq!(set.insert(*entity, index * 10), None);
+        }
+
+        assert_eq!(set.remove(entities[1]), Some(10));
+        assert_eq!(set.remove(entities[1]), None);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.get_entities(), &[entities[0], entities[3], entities[2]]);
+
+        assert_eq!(set.get(entities[0]), Some(&0));
+        assert_eq!(set.get(entities[2]), Some(&20));
+        assert_eq!(set.get(entities[3]), Some(&30));
+
+        // Removing the last one doesn't move anything
+        assert_eq!(set.remove(entities[2]), Some(20));
+        assert_eq!(set.get(entities[3]), Some(&30));
+    }
+
+    #[test]
+    fn stale_entities_miss() {
+        let mut allocator = EntityAllocator::new();
+        let old = allocator.allocate();
+        allocator.free(old);
+        let new = allocator.allocate();
+
+        let mut set = SparseSet::new();
+        set.insert(new, "new");
+
+        assert!(!set.contains(old));
+        assert_eq!(set.get(old), None);
+        assert_eq!(set.remove(old), None);
+        assert_eq!(set.insert(new, "newer"), Some("new"));
+        assert!(!set.is_empty());
+    }
+}
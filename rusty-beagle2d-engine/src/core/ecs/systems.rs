@@ -0,0 +1,97 @@
+use crate::core::ecs::components::{Camera, Transform};
+use crate::core::ecs::world::World;
+use crate::core::renderer2d::Renderer2d;
+use crate::core::audio::engine::AudioEngine;
+use crate::core::audio::spatial::AudioEmitter;
+use crate::core::physics::body::PhysicsBody;
+use crate::core::physics::world::PhysicsWorld;
+use crate::core::ecs::schedule::DeltaTime;
+use crate::core::character_controller::{CharacterController, CollisionSource, ControllerInput};
+use crate::core::collision::tiles::TileCollisionGrid;
+
+// Built in systems. The render stage ones expect the Renderer2d to be stored as a resource in the world.
+
+// Moves the renderer's camera to the entity marked with Camera.
+pub fn apply_camera(world: &mut World) {
+    let mut camera_position = None;
+    world.for_each::<(&Transform, &Camera), _>(|_, (transform, _)| {
+        camera_position = Some(transform.position);
+    });
+
+    if let (Some(position), Some(mut renderer)) = (camera_position, world.get_resource_mut::<Renderer2d>()) {
+        // The renderer moves the world, so it moves the opposite way of the camera
+        renderer.set_camera_position(-position.x, -position.y);
+    }
+}
+
+// Draws every entity with a Transform and a Sprite.
+pub fn render_sprites(world: &mut World) {
+    if let Some(mut renderer) = world.get_resource_mut::<Renderer2d>() {
+        renderer.draw_entities(world);
+    }
+}
+
+// Moves the audio listener to the middle of the view. Run it after apply_camera.
+// Expects the AudioEngine to be stored as a resource too.
+pub fn update_audio_listener(world: &mut World) {
+    if let (Some(mut audio), Some(renderer)) = (world.get_resource_mut::<AudioEngine>(), world.get_resource::<Renderer2d>()) {
+        audio.update_listener(&renderer);
+    }
+}
+
+// Moves the voices of entities with an AudioEmitter to their Transform.
+pub fn update_audio_emitters(world: &mut World) {
+    if let Some(mut audio) = world.get_resource_mut::<AudioEngine>() {
+        world.for_each::<(&Transform, &AudioEmitter), _>(|_, (transform, emitter)| {
+            audio.set_position(emitter.voice, transform.position);
+        });
+    }
+}
+
+// Fixed update system stepping the PhysicsWorld resource, then moving the Transform of every entity with a PhysicsBody
+// To its body. Add it before systems that read the Transforms or trigger events.
+// NOTE: Despawning an entity doesn't remove its body. Call PhysicsWorld::remove_body too.
+pub fn step_physics(world: &mut World) {
+    let dt = world.get_resource::<DeltaTime>().map_or(0.0, |delta_time| delta_time.seconds);
+
+    if let Some(mut physics) = world.get_resource_mut::<PhysicsWorld>() {
+        physics.step(dt);
+
+        world.for_each::<(&mut Transform, &PhysicsBody), _>(|_, (transform, physics_body)| {
+            if let Some(body) = physics.get_body(physics_body.body) {
+                transform.position = body.get_position();
+                transform.rotation = body.get_rotation();
+            }
+        });
+    }
+}
+
+// Fixed update system moving every entity with a CharacterController by its ControllerInput component,
+// And its Transform along with it. It collides with the TileCollisionGrid and PhysicsWorld resources, if there are any.
+pub fn update_character_controllers(world: &mut World) {
+    let dt = world.get_resource::<DeltaTime>().map_or(0.0, |delta_time| delta_time.seconds);
+    let grid = world.get_resource::<TileCollisionGrid>();
+    let physics = world.get_resource::<PhysicsWorld>();
+
+    let grid_source = grid.as_deref().map(|grid| grid as &dyn CollisionSource);
+    let physics_source = physics.as_deref().map(|physics| physics as &dyn CollisionSource);
+
+    // There are at most two sources, so they are gathered on the stack instead of in a new Vec every tick
+    let buffer: [&dyn CollisionSource; 2];
+    let sources: &[&dyn CollisionSource] = match (grid_source, physics_source) {
+        (Some(grid), Some(physics)) => {
+            buffer = [grid, physics];
+            &buffer
+        },
+        (Some(source), None) | (None, Some(source)) => {
+            buffer = [source, source];
+            &buffer[..1]
+        },
+        (None, None) => &[]
+    };
+
+    world.for_each::<(&mut Transform, &mut CharacterController, &ControllerInput), _>(|_, (transform, controller, input)| {
+        controller.update(dt, input, sources);
+        transform.position = controller.get_position();
+    });
+}
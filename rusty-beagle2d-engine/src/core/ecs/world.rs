@@ -0,0 +1,367 @@
+use crate::core::ecs::entity::{Entity, EntityAllocator};
+use crate::core::ecs::query::Query;
+use crate::core::ecs::storage::{AnyStorage, SparseSet};
+
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+
+// LEARN: RefCell for component storages
+// A query like (&mut Transform, &Velocity) needs a mutable borrow of one storage and a shared borrow of another
+// At the same time, while only having &World. Each storage sits in its own RefCell, so the borrows are checked at runtime instead.
+// A query that asks for the same component twice, with at least one of them mutable, panics instead of aliasing.
+pub struct World {
+    entities: EntityAllocator,
+    storages: HashMap<TypeId, RefCell<Box<dyn AnyStorage>>>,
+    // Data that exists once, rather than per entity
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            entities: EntityAllocator::new(),
+            storages: HashMap::new(),
+            resources: HashMap::new()
+        }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        self.entities.allocate()
+    }
+
+    // Spawns an entity and adds components to it with chained "with" calls.
+    pub fn create_entity(&mut self) -> EntityBuilder<'_> {
+        let entity = self.spawn();
+        EntityBuilder { world: self, entity }
+    }
+
+    // Removes the entity with all its components. Returns false if it was already despawned.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    pub fn get_entity_count(&self) -> usize {
+        self.entities.get_alive_count()
+    }
+
+    pub fn get_entities(&self) -> Vec<Entity> {
+        self.entities.get_alive_entities()
+    }
+
+    // Adds a component to the entity, replacing (and returning) any component of the same type it had.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.is_alive(entity) {
+            panic!("Can't add a {} to {:?}, which has been despawned", std::any::type_name::<T>(), entity);
+        }
+
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(SparseSet::<T>::new())))
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .expect("Component storage has the wrong type")
+            .insert(entity, component)
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storages.get_mut(&TypeId::of::<T>())?
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .expect("Component storage has the wrong type")
+            .remove(entity)
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get_storage::<T>().is_some_and(|storage| storage.contains(entity))
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        let storage = self.get_storage::<T>()?;
+        Ref::filter_map(storage, |storage| storage.get(entity)).ok()
+    }
+
+    pub fn get_mut<T: 'static>(&self, entity: Entity) -> Option<RefMut<'_, T>> {
+        let storage = self.get_storage_mut::<T>()?;
+        RefMut::filter_map(storage, |storage| storage.get_mut(entity)).ok()
+    }
+
+    // Calls "function" for every entity that matches the query, for example:
+    // world.for_each::<(&mut Transform, &Velocity), _>(|entity, (transform, velocity)| { ... });
+    pub fn for_each<Q: Query, F: FnMut(Entity, Q::Item<'_>)>(&self, mut function: F) {
+        let mut state = Q::borrow(self);
+
+        match Q::get_candidates(&state).map(|candidates| candidates.len()) {
+            Some(candidate_count) => {
+                // Fetching needs the state mutably, so the candidates are looked up again for each entity instead of copied.
+                // The storages stay borrowed until the query ends, so the list can't change in between.
+                for index in 0..candidate_count {
+                    let entity = Q::get_candidates(&state).expect("The candidates of a query can't change while it runs")[index];
+
+                    if let Some(item) = Q::fetch(&mut state, entity) {
+                        function(entity, item);
+                    }
+                }
+            },
+            // Nothing in the query narrows down the entities (only optional components), so try them all
+            None => {
+                for entity in self.entities.iter_alive_entities() {
+                    if let Some(item) = Q::fetch(&mut state, entity) {
+                        function(entity, item);
+                    }
+                }
+            }
+        }
+    }
+
+    // The entities that match the query.
+    pub fn query_entities<Q: Query>(&self) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.for_each::<Q, _>(|entity, _| entities.push(entity));
+        entities
+    }
+
+    // Borrows the storage of a component type, or returns None if no entity ever had that component.
+    pub fn get_storage<T: 'static>(&self) -> Option<Ref<'_, SparseSet<T>>> {
+        let cell = self.storages.get(&TypeId::of::<T>())?;
+
+        let storage = cell.try_borrow().unwrap_or_else(|_| {
+            panic!("{} is already borrowed mutably (by a query or get_mut)", std::any::type_name::<T>())
+        });
+
+        Some(Ref::map(storage, |storage| storage.as_any().downcast_ref::<SparseSet<T>>().expect("Component storage has the wrong type")))
+    }
+
+    pub fn get_storage_mut<T: 'static>(&self) -> Option<RefMut<'_, SparseSet<T>>> {
+        let cell = self.storages.get(&TypeId::of::<T>())?;
+
+        let storage = cell.try_borrow_mut().unwrap_or_else(|_| {
+            panic!("{} is already borrowed (by a query or get), and can't be borrowed mutably", std::any::type_name::<T>())
+        });
+
+        Some(RefMut::map(storage, |storage| storage.as_any_mut().downcast_mut::<SparseSet<T>>().expect("Component storage has the wrong type")))
+    }
+
+    // Adds a resource, replacing (and returning) any resource of the same type.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), RefCell::new(Box::new(resource)))
+            .map(|old| *old.into_inner().downcast::<R>().expect("Resource has the wrong type"))
+    }
+
+    pub fn remove_resource<R: 'static>(&mut self) -> Option<R> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .map(|old| *old.into_inner().downcast::<R>().expect("Resource has the wrong type"))
+    }
+
+    pub fn has_resource<R: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn get_resource<R: 'static>(&self) -> Option<Ref<'_, R>> {
+        let cell = self.resources.get(&TypeId::of::<R>())?;
+
+        let resource = cell.try_borrow().unwrap_or_else(|_| {
+            panic!("Resource {} is already borrowed mutably", std::any::type_name::<R>())
+        });
+
+        Some(Ref::map(resource, |resource| resource.downcast_ref::<R>().expect("Resource has the wrong type")))
+    }
+
+    pub fn get_resource_mut<R: 'static>(&self) -> Option<RefMut<'_, R>> {
+        let cell = self.resources.get(&TypeId::of::<R>())?;
+
+        let resource = cell.try_borrow_mut().unwrap_or_else(|_| {
+            panic!("Resource {} is already borrowed", std::any::type_name::<R>())
+        });
+
+        Some(RefMut::map(resource, |resource| resource.downcast_mut::<R>().expect("Resource has the wrong type")))
+    }
+}
+
+pub struct EntityBuilder<'a> {
+    world: &'a mut World,
+    entity: Entity
+}
+
+impl<'a> EntityBuilder<'a> {
+    pub fn with<T: 'static>(self, component: T) -> EntityBuilder<'a> {
+        self.world.insert(self.entity, component);
+        self
+    }
+
+    pub fn build(self) -> Entity {
+        self.entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct Position(i32, i32);
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct Velocity(i32, i32);
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct Health(u32);
+
+    struct Gravity(i32);
+
+    // Three entities: one moving, one standing still, one with only health
+    fn spawn_test_world() -> (World, Entity, Entity, Entity) {
+        let mut world = World::new();
+        let moving = world.create_entity().with(Position(0, 0)).with(Velocity(1, 2)).with(Health(10)).build();
+        let still = world.create_entity().with(Position(5, 5)).build();
+        let ghost = world.create_entity().with(Health(3)).build();
+        (world, moving, still, ghost)
+    }
+
+    #[test]
+    fn components() {
+        let (mut world, moving, still, _) = spawn_test_world();
+
+        assert_eq!(*world.get::<Position>(still).unwrap(), Position(5, 5));
+        assert!(world.has::<Velocity>(moving));
+        assert!(!world.has::<Velocity>(still));
+
+        world.get_mut::<Position>(still).unwrap().0 = 6;
+        assert_eq!(world.insert(still, Position(7, 7)), Some(Position(6, 5)));
+        assert_eq!(world.remove::<Position>(still), Some(Position(7, 7)));
+        assert_eq!(world.remove::<Position>(still), None);
+        assert!(world.get::<Position>(still).is_none());
+
+        // A component type no entity ever had
+        assert!(world.get::<String>(still).is_none());
+        assert_eq!(world.remove::<String>(still), None);
+    }
+
+    #[test]
+    fn mixed_queries() {
+        let (world, moving, _, _) = spawn_test_world();
+
+        world.for_each::<(&mut Position, &Velocity), _>(|_, (position, velocity)| {
+            position.0 += velocity.0;
+            position.1 += velocity.1;
+        });
+
+        assert_eq!(*world.get::<Position>(moving).unwrap(), Position(1, 2));
+        assert_eq!(world.query_entities::<(&mut Position, &Velocity)>(), vec![moving]);
+
+        // Two mutable parts at once
+        world.for_each::<(&mut Position, &mut Health), _>(|_, (position, health)| {
+            position.0 = 100;
+            health.0 -= 1;
+        });
+
+        assert_eq!(*world.get::<Position>(moving).unwrap(), Position(100, 2));
+        assert_eq!(*world.get::<Health>(moving).unwrap(), Health(9));
+    }
+
+    #[test]
+    fn single_component_queries() {
+        let (world, moving, still, ghost) = spawn_test_world();
+
+        assert_eq!(world.query_entities::<&Position>(), vec![moving, still]);
+        assert_eq!(world.query_entities::<&mut Health>(), vec![moving, ghost]);
+        assert!(world.query_entities::<&String>().is_empty());
+        assert!(world.query_entities::<(&Position, &String)>().is_empty());
+    }
+
+    #[test]
+    fn optional_components() {
+        let (world, moving, still, ghost) = spawn_test_world();
+
+        let mut seen = Vec::new();
+        world.for_each::<(&Position, Option<&Velocity>), _>(|entity, (_, velocity)| seen.push((entity, velocity.copied())));
+        assert_eq!(seen, vec![(moving, Some(Velocity(1, 2))), (still, None)]);
+
+        // Only optional parts: every living entity matches
+        let mut seen = Vec::new();
+        world.for_each::<Option<&Velocity>, _>(|entity, velocity| seen.push((entity, velocity.is_some())));
+        assert_eq!(seen, vec![(moving, true), (still, false), (ghost, false)]);
+
+        assert_eq!(world.query_entities::<Option<&String>>(), vec![moving, still, ghost]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn aliasing_query_panics() {
+        let (world, _, _, _) = spawn_test_world();
+        world.for_each::<(&mut Position, &Position), _>(|_, _| {});
+    }
+
+    #[test]
+    fn despawn_removes_components() {
+        let (mut world, moving, still, ghost) = spawn_test_world();
+
+        assert!(world.despawn(moving));
+        assert!(!world.despawn(moving));
+        assert!(!world.is_alive(moving));
+        assert_eq!(world.get_entity_count(), 2);
+
+        assert!(world.get::<Position>(moving).is_none());
+        assert_eq!(world.query_entities::<&Position>(), vec![still]);
+        assert_eq!(world.query_entities::<&Health>(), vec![ghost]);
+        assert_eq!(world.query_entities::<Option<&Velocity>>(), vec![still, ghost]);
+        assert_eq!(world.get_entities(), vec![still, ghost]);
+    }
+
+    #[test]
+    fn reused_indices_get_a_new_generation() {
+        let (mut world, moving, _, _) = spawn_test_world();
+        world.despawn(moving);
+
+        let reused = world.create_entity().with(Position(9, 9)).build();
+        assert_eq!(reused.get_index(), moving.get_index());
+        assert_ne!(reused.get_generation(), moving.get_generation());
+
+        // The old handle doesn't reach the new entity's components
+        assert!(world.is_alive(reused));
+        assert!(!world.is_alive(moving));
+        assert!(world.get::<Position>(moving).is_none());
+        assert!(!world.has::<Position>(moving));
+        assert!(!world.despawn(moving));
+        assert_eq!(*world.get::<Position>(reused).unwrap(), Position(9, 9));
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_on_despawned_entity_panics() {
+        let (mut world, moving, _, _) = spawn_test_world();
+        world.despawn(moving);
+        world.insert(moving, Health(1));
+    }
+
+    #[test]
+    fn resources() {
+        let mut world = World::new();
+        assert!(!world.has_resource::<Gravity>());
+        assert!(world.get_resource::<Gravity>().is_none());
+
+        assert!(world.insert_resource(Gravity(10)).is_none());
+        world.get_resource_mut::<Gravity>().unwrap().0 += 1;
+        assert_eq!(world.get_resource::<Gravity>().unwrap().0, 11);
+
+        assert_eq!(world.insert_resource(Gravity(3)).map(|gravity| gravity.0), Some(11));
+        assert_eq!(world.remove_resource::<Gravity>().map(|gravity| gravity.0), Some(3));
+        assert!(!world.has_resource::<Gravity>());
+    }
+}
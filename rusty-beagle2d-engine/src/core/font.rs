@@ -0,0 +1,140 @@
+use linear_beaglebra::vector2::Vector2;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+// Where a character is in the font texture, and how to place it.
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph {
+    // Top left corner in the texture, in pixels
+    pub texture_position: Vector2,
+    pub size: Vector2,
+    // Offset from the pen position to the top left corner of the glyph
+    pub bearing: Vector2,
+    // How far the pen moves to the right after the glyph
+    pub advance: f32
+}
+
+// The layout of a bitmap font, read from an AngelCode .fnt text file (as exported by Hiero).
+// Doesn't need the font texture, so text can be measured without a GPU.
+#[derive(Clone, Debug)]
+pub struct FontMetrics {
+    line_height: f32,
+    glyphs: HashMap<u8, Glyph>
+}
+
+impl FontMetrics {
+    pub fn from_file(path: &str) -> Result<FontMetrics, String> {
+        let text = std::fs::read_to_string(Path::new(path))
+            .map_err(|error| format!("Failed to read font file {}: {}", path, error))?;
+
+        FontMetrics::parse(&text)
+    }
+
+    // Reads the "info", "common" and "char" lines of a .fnt file. Other lines, like kernings, are skipped.
+    pub fn parse(text: &str) -> Result<FontMetrics, String> {
+        let mut padding = [0; 4];
+        let mut line_height = None;
+        let mut glyphs = HashMap::new();
+
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+
+            match words.next() {
+                Some("info") => {
+                    if let Some(value) = words.find_map(|word| word.strip_prefix("padding=")) {
+                        let values: Vec<&str> = value.split(',').collect();
+
+                        if values.len() != 4 {
+                            return Err(format!("Expected 4 padding values, got \"{}\"", value));
+                        }
+
+                        for (index, value) in values.iter().enumerate() {
+                            padding[index] = value.parse::<i32>().map_err(|_| format!("Invalid padding value \"{}\"", value))?;
+                        }
+                    }
+                },
+                Some("common") => {
+                    line_height = Some(FontMetrics::get_value(line, "lineHeight")?);
+                },
+                Some("char") => {
+                    let id = FontMetrics::get_value(line, "id")?;
+
+                    if !(0..=255).contains(&id) {
+                        continue;
+                    }
+
+                    let (padding_right, padding_left) = (padding[1], padding[3]);
+
+                    // TODO: When using Hiero to export .fnt, padding is encoded oddly...
+                    // Left padding is subtracted from the original xoffset value, so you have to add padding to xoffset to value you need for text rendering
+                    // Left + Right Padding is added to xadvance, so these have to be subtracted from xadvance to get the value you need for text rendering
+                    // Up padding is substracted from yoffset value, so you have to add it back to get original yoffset value.
+                    glyphs.insert(id as u8, Glyph {
+                        texture_position: Vector2::new(FontMetrics::get_value(line, "x")? as f32, FontMetrics::get_value(line, "y")? as f32),
+                        size: Vector2::new(FontMetrics::get_value(line, "width")? as f32, FontMetrics::get_value(line, "height")? as f32),
+                        bearing: Vector2::new(
+                            (FontMetrics::get_value(line, "xoffset")? + padding_left) as f32,
+                            FontMetrics::get_value(line, "yoffset")? as f32),
+                        advance: (FontMetrics::get_value(line, "xadvance")? - (padding_left + padding_right)) as f32
+                    });
+                },
+                _ => {}
+            }
+        }
+
+        let line_height = line_height.ok_or_else(|| String::from("The font has no \"common\" line with the line height"))?;
+
+        Ok(FontMetrics {
+            line_height: line_height as f32,
+            glyphs
+        })
+    }
+
+    // Finds "key=value" in a line and parses the value.
+    fn get_value(line: &str, key: &str) -> Result<i32, String> {
+        let value = line.split_whitespace()
+            .find_map(|word| word.strip_prefix(key).and_then(|rest| rest.strip_prefix('=')))
+            .ok_or_else(|| format!("Missing \"{}\" in font line \"{}\"", key, line))?;
+
+        value.parse::<i32>().map_err(|_| format!("Invalid value for \"{}\" in font line \"{}\"", key, line))
+    }
+
+    // Distance between two lines of text, in pixels at scale 1.
+    pub fn get_line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    pub fn get_glyph(&self, character: u8) -> Option<&Glyph> {
+        self.glyphs.get(&character)
+    }
+
+    // Width and height of a line of text drawn with Renderer2d::draw_text at the given scale.
+    // Characters missing from the font take up no space.
+    pub fn measure_text(&self, text: &str, scale: f32) -> Vector2 {
+        let width: f32 = text.bytes()
+            .filter_map(|character| self.glyphs.get(&character))
+            .map(|glyph| glyph.advance)
+            .sum();
+
+        Vector2::new(width * scale, self.line_height * scale)
+    }
+
+    // How many bytes of "text" fit before "x" pixels, rounded to the nearest character edge.
+    // For placing a text cursor where the text was clicked.
+    pub fn get_index_at(&self, text: &str, scale: f32, x: f32) -> usize {
+        let mut pen = 0.0;
+
+        for (index, character) in text.char_indices() {
+            let advance = self.measure_text(&text[index..index + character.len_utf8()], scale).x;
+
+            if x < pen + advance * 0.5 {
+                return index;
+            }
+
+            pen += advance;
+        }
+
+        text.len()
+    }
+}
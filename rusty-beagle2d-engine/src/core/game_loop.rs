@@ -0,0 +1,314 @@
+use crate::core::renderer2d::Renderer2d;
+use crate::core::time::{Clock, Time};
+use crate::core::input::WindowEvent;
+
+use std::time::Duration;
+
+// The hooks a game gives the game loop. See App::run.
+// Each hook gets the loop's Time, which can also be used to pause, step or slow down the game.
+pub trait Game {
+    // Called for every key press, mouse move and so on, before the frame's updates.
+    fn handle_event(&mut self, _event: &WindowEvent) {}
+
+    // Advances the game by exactly "dt" seconds. Called zero or more times per frame, always with the same dt.
+    // Game logic and physics go here, so they behave the same no matter how fast the computer is.
+    fn fixed_update(&mut self, dt: f32, time: &mut Time);
+
+    // Called once per frame, after the fixed updates, with the real time the frame took.
+    // For things that should follow the frame rate rather than the simulation, like UI animations.
+    // Keeps running while the game is paused. Use time.get_delta() for the game time the frame added.
+    fn update(&mut self, _frame_dt: f32, _time: &mut Time) {}
+
+    // Draws the game. "alpha" is how far the time is between the last fixed update and the next one (0.0 - 1.0).
+    // Drawing things at lerp(previous_position, position, alpha) hides the steps of the fixed updates.
+    fn render(&mut self, renderer: &mut Renderer2d, alpha: f32);
+}
+
+// LEARN: Fixed timestep game loops ("Fix Your Timestep!" by Glenn Fiedler)
+// Frames take however long they take, but the game is always advanced in steps of the same size.
+// The time of each frame goes into an accumulator, and fixed updates are run until less than one step is left over.
+// What is left over is carried to the next frame, and tells the renderer how far it is towards the next step (alpha).
+pub struct GameLoop {
+    clock: Box<dyn Clock>,
+    time: Time,
+    max_frame_time: Duration,
+    accumulator: Duration,
+    last_time: Option<Duration>
+}
+
+impl GameLoop {
+    // 60 fixed updates per second, frames counted as at most 250 milliseconds.
+    pub fn new(clock: Box<dyn Clock>) -> GameLoop {
+        GameLoop {
+            clock,
+            time: Time::new(Duration::from_secs(1) / 60),
+            max_frame_time: Duration::from_millis(250),
+            accumulator: Duration::from_secs(0),
+            last_time: None
+        }
+    }
+
+    // Number of fixed updates per second.
+    pub fn with_tick_rate(mut self, ticks_per_second: u32) -> GameLoop {
+        if ticks_per_second == 0 {
+            panic!("The tick rate must be at least 1 tick per second");
+        }
+
+        self.time.set_fixed_timestep(Duration::from_secs(1) / ticks_per_second);
+        self
+    }
+
+    // The longest time a single frame is counted as. See run_frame.
+    pub fn with_max_frame_time(mut self, max_frame_time: Duration) -> GameLoop {
+        self.max_frame_time = max_frame_time;
+        self
+    }
+
+    // Replaces where the time comes from. The next frame starts measuring from the new clock.
+    pub fn with_clock(mut self, clock: Box<dyn Clock>) -> GameLoop {
+        self.clock = clock;
+        self.last_time = None;
+        self
+    }
+
+    pub fn get_max_frame_time(&self) -> Duration {
+        self.max_frame_time
+    }
+
+    pub fn get_time(&self) -> &Time {
+        &self.time
+    }
+
+    // For pausing, stepping and scaling time from outside the game hooks.
+    pub fn get_time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    // Runs the fixed updates that are due and the frame update. Returns the alpha to render with.
+    pub fn run_frame<G: Game>(&mut self, game: &mut G) -> f32 {
+        let now = self.clock.now();
+
+        // The first frame has nothing to measure from
+        let mut frame_time = match self.last_time {
+            Some(last_time) => now.checked_sub(last_time).unwrap_or_default(),
+            None => Duration::from_secs(0)
+        };
+
+        self.last_time = Some(now);
+
+        // LEARN: The spiral of death
+        // If fixed updates take longer to run than the time they simulate, every frame leaves more time to catch up on,
+        // Which makes the next frame even slower, until the game freezes. A long pause (a breakpoint, dragging the window)
+        // Would also be caught up on in one go. So a frame never counts as more than max_frame_time,
+        // And the game slows down instead.
+        if frame_time > self.max_frame_time {
+            frame_time = self.max_frame_time;
+        }
+
+        // Scaled, or nothing while paused
+        self.accumulator += self.time.begin_frame(frame_time);
+
+        let fixed_timestep = self.time.get_fixed_timestep();
+        let dt = fixed_timestep.as_secs_f32();
+
+        while self.accumulator >= fixed_timestep {
+            self.time.begin_tick();
+            game.fixed_update(dt, &mut self.time);
+            self.accumulator -= fixed_timestep;
+        }
+
+        game.update(frame_time.as_secs_f32(), &mut self.time);
+
+        self.get_alpha()
+    }
+
+    // How far the time is between the last fixed update and the next one, from 0.0 to 1.0.
+    pub fn get_alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.time.get_fixed_delta()
+    }
+}
+// Counts frames to work out the frame rate, averaged over about a second.
+pub struct FpsCounter {
+    fps: f32,
+    frames: u32,
+    elapsed: f32
+}
+
+impl FpsCounter {
+    pub fn new() -> FpsCounter {
+        FpsCounter {
+            fps: 0.0,
+            frames: 0,
+            elapsed: 0.0
+        }
+    }
+
+    // Call once per frame with the frame's time in seconds, for example from Game::update.
+    pub fn update(&mut self, frame_dt: f32) {
+        self.frames += 1;
+        self.elapsed += frame_dt;
+
+        if self.elapsed >= 1.0 {
+            self.fps = self.frames as f32 / self.elapsed;
+            self.frames = 0;
+            self.elapsed = 0.0;
+        }
+    }
+
+    pub fn get_fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::time::ManualClock;
+
+    // Records what the loop calls
+    struct Recorder {
+        fixed_dts: Vec<f32>,
+        frame_dts: Vec<f32>
+    }
+
+    impl Recorder {
+        fn new() -> Recorder {
+            Recorder {
+                fixed_dts: Vec::new(),
+                frame_dts: Vec::new()
+            }
+        }
+    }
+
+    impl Game for Recorder {
+        fn fixed_update(&mut self, dt: f32, _time: &mut Time) {
+            self.fixed_dts.push(dt);
+        }
+
+        fn update(&mut self, frame_dt: f32, _time: &mut Time) {
+            self.frame_dts.push(frame_dt);
+        }
+
+        fn render(&mut self, _renderer: &mut Renderer2d, _alpha: f32) {}
+    }
+
+    // 100 ticks per second, so every tick is exactly 10 milliseconds
+    fn create_loop() -> (GameLoop, ManualClock) {
+        let clock = ManualClock::new();
+        let game_loop = GameLoop::new(Box::new(clock.clone())).with_tick_rate(100);
+        (game_loop, clock)
+    }
+
+    // Runs a frame that took "milliseconds". Returns the number of fixed updates it ran and the alpha.
+    fn run_frame(game_loop: &mut GameLoop, clock: &ManualClock, game: &mut Recorder, milliseconds: u64) -> (usize, f32) {
+        let ticks_before = game.fixed_dts.len();
+        clock.advance(Duration::from_millis(milliseconds));
+        let alpha = game_loop.run_frame(game);
+        (game.fixed_dts.len() - ticks_before, alpha)
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-4, "{} isn't {}", value, expected);
+    }
+
+    #[test]
+    fn first_frame_runs_no_fixed_updates() {
+        let (mut game_loop, clock) = create_loop();
+        let mut game = Recorder::new();
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(game_loop.run_frame(&mut game), 0.0);
+        assert!(game.fixed_dts.is_empty());
+        assert_eq!(game.frame_dts, vec![0.0]);
+        assert_eq!(game_loop.get_time().get_frame_count(), 1);
+    }
+
+    #[test]
+    fn fixed_updates_per_frame_and_alpha() {
+        let (mut game_loop, clock) = create_loop();
+        let mut game = Recorder::new();
+        game_loop.run_frame(&mut game);
+
+        let (ticks, alpha) = run_frame(&mut game_loop, &clock, &mut game, 35);
+        assert_eq!(ticks, 3);
+        assert_close(alpha, 0.5);
+
+        // The 5 left over milliseconds add up with the next frame's
+        let (ticks, alpha) = run_frame(&mut game_loop, &clock, &mut game, 5);
+        assert_eq!(ticks, 1);
+        assert_close(alpha, 0.0);
+
+        let (ticks, alpha) = run_frame(&mut game_loop, &clock, &mut game, 4);
+        assert_eq!(ticks, 0);
+        assert_close(alpha, 0.4);
+
+        let (ticks, alpha) = run_frame(&mut game_loop, &clock, &mut game, 16);
+        assert_eq!(ticks, 2);
+        assert_close(alpha, 0.0);
+
+        assert!(game.fixed_dts.iter().all(|&dt| dt == 0.01));
+        assert_close(game.frame_dts[1], 0.035);
+
+        let time = game_loop.get_time();
+        assert_eq!(time.get_tick_count(), 6);
+        assert_eq!(time.get_game_time(), Duration::from_millis(60));
+        assert_eq!(time.get_real_time(), Duration::from_millis(60));
+        assert_eq!(time.get_frame_count(), 5);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let (mut game_loop, clock) = create_loop();
+        let mut game = Recorder::new();
+        game_loop.run_frame(&mut game);
+
+        // A 2 second hitch only counts as 250 milliseconds
+        let (ticks, alpha) = run_frame(&mut game_loop, &clock, &mut game, 2000);
+        assert_eq!(ticks, 25);
+        assert_close(alpha, 0.0);
+        assert_eq!(game.frame_dts[1], 0.25);
+        assert_eq!(game_loop.get_time().get_real_time(), Duration::from_millis(250));
+
+        // The time over the limit is dropped, not caught up on later
+        let (ticks, _) = run_frame(&mut game_loop, &clock, &mut game, 10);
+        assert_eq!(ticks, 1);
+
+        let mut game_loop = game_loop.with_max_frame_time(Duration::from_millis(100));
+        let (ticks, _) = run_frame(&mut game_loop, &clock, &mut game, 1000);
+        assert_eq!(ticks, 10);
+    }
+
+    #[test]
+    fn clock_going_backwards_counts_as_no_time() {
+        let (mut game_loop, clock) = create_loop();
+        let mut game = Recorder::new();
+
+        clock.set(Duration::from_secs(10));
+        game_loop.run_frame(&mut game);
+        clock.set(Duration::from_secs(9));
+        game_loop.run_frame(&mut game);
+
+        assert!(game.fixed_dts.is_empty());
+        assert_eq!(game.frame_dts, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn new_clock_starts_measuring_again() {
+        let (game_loop, clock) = create_loop();
+        let mut game = Recorder::new();
+
+        clock.set(Duration::from_secs(100));
+        let new_clock = ManualClock::new();
+        let mut game_loop = game_loop.with_clock(Box::new(new_clock.clone()));
+        game_loop.run_frame(&mut game);
+
+        let (ticks, _) = run_frame(&mut game_loop, &new_clock, &mut game, 20);
+        assert_eq!(ticks, 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_tick_rate_panics() {
+        GameLoop::new(Box::new(ManualClock::new())).with_tick_rate(0);
+    }
+}
@@ -0,0 +1,282 @@
+// Importer for Aseprite's "Export Sprite Sheet" JSON data (hash or array layout), with tags and slices.
+
+use crate::core::animation::{Animation, AnimationFrame, AnimationLibrary, PlaybackMode};
+use crate::core::importers::texture_packer::{self, JsonFrames, JsonMeta, JsonPoint, JsonRect};
+use crate::core::texture_atlas::{AtlasSlice, SliceBounds, SliceKey, TextureAtlas};
+
+use serde::Deserialize;
+
+use std::path::Path;
+
+// Frames without a duration are shown for Aseprite's default of 100 milliseconds
+const DEFAULT_FRAME_DURATION_MS: f32 = 100.0;
+
+#[derive(Deserialize)]
+struct JsonTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default = "default_direction")]
+    direction: String,
+    // Newer Aseprite versions: how many times the tag plays. Missing or "0" means forever.
+    #[serde(default)]
+    repeat: Option<String>
+}
+
+fn default_direction() -> String {
+    String::from("forward")
+}
+
+#[derive(Deserialize)]
+struct JsonSliceKey {
+    frame: usize,
+    bounds: JsonRect,
+    #[serde(default)]
+    center: Option<JsonRect>,
+    #[serde(default)]
+    pivot: Option<JsonPoint>
+}
+
+#[derive(Deserialize)]
+struct JsonSlice {
+    name: String,
+    keys: Vec<JsonSliceKey>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonAsepriteMeta {
+    #[serde(flatten)]
+    meta: JsonMeta,
+    #[serde(default)]
+    frame_tags: Vec<JsonTag>,
+    #[serde(default)]
+    slices: Vec<JsonSlice>
+}
+
+#[derive(Deserialize)]
+struct JsonSpriteSheet {
+    frames: JsonFrames,
+    meta: JsonAsepriteMeta
+}
+
+// The content of an Aseprite export: the frames as atlas regions, and an animation per tag.
+pub struct AsepriteSheet {
+    pub atlas: TextureAtlas,
+    pub animations: Vec<Animation>
+}
+
+impl AsepriteSheet {
+    pub fn to_animation_library(&self) -> Result<AnimationLibrary, String> {
+        let mut library = AnimationLibrary::new();
+
+        for animation in &self.animations {
+            library.add(animation.clone())?;
+        }
+
+        Ok(library)
+    }
+}
+
+pub fn load_aseprite_sheet(path: &Path) -> Result<AsepriteSheet, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read Aseprite sheet {}: {}", path.display(), error))?;
+
+    parse_aseprite_sheet(&json, path)
+        .map_err(|error| format!("Failed to load Aseprite sheet {}: {}", path.display(), error))
+}
+
+// "sheet_path" is only used to locate the sheet image next to the JSON file.
+pub fn parse_aseprite_sheet(json: &str, sheet_path: &Path) -> Result<AsepriteSheet, String> {
+    let sprite_sheet: JsonSpriteSheet = serde_json::from_str(json).map_err(|error| error.to_string())?;
+
+    let mut atlas = TextureAtlas::new(
+        texture_packer::resolve_image_path(sheet_path, &sprite_sheet.meta.meta.image),
+        sprite_sheet.meta.meta.size.w,
+        sprite_sheet.meta.meta.size.h);
+
+    let frames = sprite_sheet.frames.into_named_frames()?;
+
+    // Frame index -> animation frame, used to build the tags below
+    let mut animation_frames: Vec<AnimationFrame> = Vec::with_capacity(frames.len());
+
+    for (name, frame) in frames {
+        animation_frames.push(AnimationFrame {
+            region: frame.to_texture_region(),
+            duration: frame.duration.unwrap_or(DEFAULT_FRAME_DURATION_MS) / 1000.0,
+            events: Vec::new()
+        });
+
+        atlas.add_region(frame.to_atlas_region(name))?;
+    }
+
+    let mut animations = Vec::with_capacity(sprite_sheet.meta.frame_tags.len());
+
+    for tag in &sprite_sheet.meta.frame_tags {
+        if tag.from > tag.to || tag.to >= animation_frames.len() {
+            return Err(format!("Tag '{}' refers to frames {}-{}, but the sheet has {} frames",
+                tag.name, tag.from, tag.to, animation_frames.len()));
+        }
+
+        let animation = Animation {
+            name: tag.name.clone(),
+            frames: animation_frames[tag.from..=tag.to].to_vec(),
+            playback: get_playback_mode(tag)?
+        };
+
+        animation.validate()?;
+        animations.push(animation);
+    }
+
+    for slice in sprite_sheet.meta.slices {
+        atlas.add_slice(AtlasSlice {
+            name: slice.name,
+            keys: slice.keys.into_iter().map(|key| SliceKey {
+                frame: key.frame,
+                bounds: to_slice_bounds(&key.bounds),
+                center: key.center.as_ref().map(to_slice_bounds),
+                pivot: key.pivot.map(|pivot| (pivot.x, pivot.y))
+            }).collect()
+        });
+    }
+
+    Ok(AsepriteSheet { atlas, animations })
+}
+
+fn get_playback_mode(tag: &JsonTag) -> Result<PlaybackMode, String> {
+    let plays_once = match &tag.repeat {
+        Some(repeat) => repeat.as_str() != "0",
+        None => false
+    };
+
+    match (tag.direction.as_str(), plays_once) {
+        ("forward", false) => Ok(PlaybackMode::Loop),
+        ("forward", true) => Ok(PlaybackMode::Once),
+        ("reverse", false) => Ok(PlaybackMode::Reverse),
+        ("reverse", true) => Ok(PlaybackMode::ReverseOnce),
+        // NOTE: Ping-pong tags always loop here, even when Aseprite would stop them after "repeat" passes
+        ("pingpong", _) => Ok(PlaybackMode::PingPong),
+        ("pingpong_reverse", _) => Ok(PlaybackMode::PingPongReverse),
+        (direction, _) => Err(format!("Tag '{}' has unknown direction '{}'", tag.name, direction))
+    }
+}
+
+fn to_slice_bounds(rect: &JsonRect) -> SliceBounds {
+    SliceBounds { x: rect.x, y: rect.y, width: rect.w, height: rect.h }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::animation::AnimationPlayer;
+    use crate::core::texture_region::{TextureRegion, Trim};
+
+    use std::rc::Rc;
+
+    fn fixture_path(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/aseprite").join(name)
+    }
+
+    fn load_knight() -> AsepriteSheet {
+        load_aseprite_sheet(&fixture_path("knight.json")).unwrap()
+    }
+
+    fn get_animation<'a>(sheet: &'a AsepriteSheet, name: &str) -> &'a Animation {
+        sheet.animations.iter().find(|animation| animation.name == name).unwrap()
+    }
+
+    #[test]
+    fn frames_become_atlas_regions() {
+        let sheet = load_knight();
+
+        assert_eq!(sheet.atlas.get_image_path(), fixture_path("knight.png"));
+        assert_eq!(sheet.atlas.get_regions().len(), 5);
+        assert_eq!(sheet.atlas.get_region("knight 1.aseprite").unwrap().region, TextureRegion::new(32.0, 0.0, 32.0, 32.0));
+
+        let trimmed = sheet.atlas.get_region("knight 2.aseprite").unwrap();
+        assert_eq!(trimmed.region.trim, Some(Trim { offset_x: 2.0, offset_y: 1.0, source_width: 32.0, source_height: 32.0 }));
+    }
+
+    #[test]
+    fn tags_become_animations() {
+        let sheet = load_knight();
+
+        let names: Vec<&str> = sheet.animations.iter().map(|animation| animation.name.as_str()).collect();
+        assert_eq!(names, vec!["idle", "attack", "walk_back", "fall", "breathe", "sway", "blink"]);
+
+        let idle = get_animation(&sheet, "idle");
+        assert_eq!(idle.playback, PlaybackMode::Loop);
+        let durations: Vec<f32> = idle.frames.iter().map(|frame| frame.duration).collect();
+        assert_eq!(durations, vec![0.1, 0.15]);
+
+        // Frame 4 has no duration, so it gets the default
+        let attack = get_animation(&sheet, "attack");
+        assert_eq!(attack.playback, PlaybackMode::Once);
+        assert_eq!(attack.frames.len(), 3);
+        assert_eq!(attack.frames[0].region.x, 64.0);
+        assert_eq!(attack.frames[2].duration, 0.1);
+
+        assert_eq!(get_animation(&sheet, "walk_back").playback, PlaybackMode::Reverse);
+        assert_eq!(get_animation(&sheet, "fall").playback, PlaybackMode::ReverseOnce);
+        assert_eq!(get_animation(&sheet, "breathe").playback, PlaybackMode::PingPong);
+        assert_eq!(get_animation(&sheet, "sway").playback, PlaybackMode::PingPongReverse);
+        assert_eq!(get_animation(&sheet, "blink").playback, PlaybackMode::Loop);
+
+        let library = sheet.to_animation_library().unwrap();
+        assert!(library.get("sway").is_some());
+    }
+
+    #[test]
+    fn pingpong_reverse_starts_at_the_last_frame() {
+        let sheet = load_knight();
+        let mut player = AnimationPlayer::new(Rc::new(get_animation(&sheet, "sway").clone())).unwrap();
+
+        let mut frames = vec![player.get_frame_index()];
+        for _ in 0..4 {
+            let duration = player.get_current_frame().duration;
+            player.update(duration + 0.0001);
+            frames.push(player.get_frame_index());
+        }
+
+        assert_eq!(frames, vec![2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn slices() {
+        let sheet = load_knight();
+
+        let hitbox = sheet.atlas.get_slice("hitbox").unwrap();
+        assert_eq!(hitbox.keys.len(), 2);
+        assert_eq!(hitbox.get_key(1).unwrap().bounds, SliceBounds { x: 8.0, y: 4.0, width: 16.0, height: 28.0 });
+        assert_eq!(hitbox.get_key(1).unwrap().pivot, Some((8.0, 28.0)));
+        assert_eq!(hitbox.get_key(3).unwrap().bounds, SliceBounds { x: 10.0, y: 6.0, width: 20.0, height: 26.0 });
+
+        let panel = sheet.atlas.get_slice("panel").unwrap();
+        assert_eq!(panel.get_key(0).unwrap().center, Some(SliceBounds { x: 4.0, y: 4.0, width: 24.0, height: 24.0 }));
+    }
+
+    #[test]
+    fn array_layout() {
+        let sheet = load_aseprite_sheet(&fixture_path("knight_array.json")).unwrap();
+
+        assert_eq!(sheet.atlas.get_image_path(), fixture_path("sheets/knight_array.png"));
+        assert_eq!(sheet.atlas.get_regions().len(), 2);
+
+        let run = get_animation(&sheet, "run");
+        let durations: Vec<f32> = run.frames.iter().map(|frame| frame.duration).collect();
+        assert_eq!(durations, vec![0.08, 0.12]);
+    }
+
+    #[test]
+    fn invalid_tags() {
+        let frames = r#""frames": [ { "filename": "a", "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "duration": 100 },
+            { "filename": "b", "frame": { "x": 8, "y": 0, "w": 8, "h": 8 }, "duration": 0 } ]"#;
+        let sheet = |tag: &str| format!(r#"{{ {}, "meta": {{ "image": "a.png", "size": {{ "w": 16, "h": 8 }}, "frameTags": [ {} ] }} }}"#, frames, tag);
+
+        assert!(parse_aseprite_sheet(&sheet(r#"{ "name": "a", "from": 0, "to": 0 }"#), Path::new("a.json")).is_ok());
+        assert!(parse_aseprite_sheet(&sheet(r#"{ "name": "a", "from": 0, "to": 2 }"#), Path::new("a.json")).is_err());
+        assert!(parse_aseprite_sheet(&sheet(r#"{ "name": "a", "from": 0, "to": 0, "direction": "sideways" }"#), Path::new("a.json")).is_err());
+        // The zero duration frame would never let a player move on
+        assert!(parse_aseprite_sheet(&sheet(r#"{ "name": "a", "from": 0, "to": 1 }"#), Path::new("a.json")).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod texture_packer;
+pub mod aseprite;
+pub mod tiled;
\ No newline at end of file
@@ -0,0 +1,232 @@
+// Importer for the JSON atlas formats of TexturePacker ("JSON (Hash)" and "JSON (Array)").
+// Aseprite exports its sprite sheets in the same format, so the frame parsing is shared with the Aseprite importer.
+
+use crate::core::texture_atlas::{AtlasRegion, TextureAtlas};
+use crate::core::texture_region::{TextureRegion, Trim};
+
+use serde::Deserialize;
+
+use std::path::Path;
+
+#[derive(Deserialize)]
+pub(crate) struct JsonRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32
+}
+
+#[derive(Deserialize)]
+pub(crate) struct JsonSize {
+    pub w: f32,
+    pub h: f32
+}
+
+#[derive(Deserialize)]
+pub(crate) struct JsonPoint {
+    pub x: f32,
+    pub y: f32
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonFrame {
+    // Only present in the array format. In the hash format the name is the key.
+    #[serde(default)]
+    pub filename: Option<String>,
+    pub frame: JsonRect,
+    #[serde(default)]
+    pub rotated: bool,
+    #[serde(default)]
+    pub trimmed: bool,
+    #[serde(default)]
+    pub sprite_source_size: Option<JsonRect>,
+    #[serde(default)]
+    pub source_size: Option<JsonSize>,
+    #[serde(default)]
+    pub pivot: Option<JsonPoint>,
+    // Aseprite only, in milliseconds
+    #[serde(default)]
+    pub duration: Option<f32>
+}
+
+// LEARN: Untagged enums
+// serde tries each variant in order and picks the first one the JSON fits.
+// That lets one field accept either an array or an object.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum JsonFrames {
+    Array(Vec<JsonFrame>),
+    // NOTE: Relies on serde_json's "preserve_order" feature, as the frame order matters for animations.
+    Hash(serde_json::Map<String, serde_json::Value>)
+}
+
+#[derive(Deserialize)]
+pub(crate) struct JsonMeta {
+    pub image: String,
+    pub size: JsonSize
+}
+
+#[derive(Deserialize)]
+struct JsonAtlas {
+    frames: JsonFrames,
+    meta: JsonMeta
+}
+
+impl JsonFrames {
+    // Returns the frames with their names, in file order.
+    pub(crate) fn into_named_frames(self) -> Result<Vec<(String, JsonFrame)>, String> {
+        match self {
+            JsonFrames::Array(frames) => {
+                frames.into_iter()
+                    .enumerate()
+                    .map(|(index, frame)| {
+                        let name = frame.filename.clone().ok_or_else(|| format!("Frame {} has no filename", index))?;
+                        Ok((name, frame))
+                    })
+                    .collect()
+            },
+            JsonFrames::Hash(frames) => {
+                frames.into_iter()
+                    .map(|(name, value)| {
+                        let frame: JsonFrame = serde_json::from_value(value)
+                            .map_err(|error| format!("Invalid frame '{}': {}", name, error))?;
+                        Ok((name, frame))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl JsonFrame {
+    pub(crate) fn to_texture_region(&self) -> TextureRegion {
+        // For rotated frames, "frame" still holds the unrotated size. The image covers h x w pixels in the texture.
+        let mut region = TextureRegion::new(self.frame.x, self.frame.y, self.frame.w, self.frame.h);
+        region.rotated = self.rotated;
+
+        if self.trimmed {
+            if let (Some(sprite_source_size), Some(source_size)) = (&self.sprite_source_size, &self.source_size) {
+                region.trim = Some(Trim {
+                    offset_x: sprite_source_size.x,
+                    offset_y: sprite_source_size.y,
+                    source_width: source_size.w,
+                    source_height: source_size.h
+                });
+            }
+        }
+
+        region
+    }
+
+    pub(crate) fn to_atlas_region(&self, name: String) -> AtlasRegion {
+        AtlasRegion {
+            name,
+            region: self.to_texture_region(),
+            pivot: self.pivot.as_ref().map(|pivot| (pivot.x, pivot.y))
+        }
+    }
+}
+
+// The image path in the meta data is relative to the atlas file.
+pub(crate) fn resolve_image_path(atlas_path: &Path, image: &str) -> std::path::PathBuf {
+    match atlas_path.parent() {
+        Some(directory) => directory.join(image),
+        None => Path::new(image).to_path_buf()
+    }
+}
+
+pub fn load_texture_packer_atlas(path: &Path) -> Result<TextureAtlas, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read TexturePacker atlas {}: {}", path.display(), error))?;
+
+    parse_texture_packer_atlas(&json, path)
+        .map_err(|error| format!("Failed to load TexturePacker atlas {}: {}", path.display(), error))
+}
+
+// "atlas_path" is only used to locate the atlas image next to the JSON file.
+pub fn parse_texture_packer_atlas(json: &str, atlas_path: &Path) -> Result<TextureAtlas, String> {
+    let json_atlas: JsonAtlas = serde_json::from_str(json).map_err(|error| error.to_string())?;
+
+    let mut atlas = TextureAtlas::new(
+        resolve_image_path(atlas_path, &json_atlas.meta.image),
+        json_atlas.meta.size.w,
+        json_atlas.meta.size.h);
+
+    for (name, frame) in json_atlas.frames.into_named_frames()? {
+        atlas.add_region(frame.to_atlas_region(name))?;
+    }
+
+    Ok(atlas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_path(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/texture_packer").join(name)
+    }
+
+    fn check_items_atlas(atlas: &TextureAtlas) {
+        assert_eq!(atlas.get_image_path(), fixture_path("items.png"));
+        assert_eq!((atlas.get_width(), atlas.get_height()), (64.0, 64.0));
+
+        let names: Vec<&str> = atlas.get_regions().iter().map(|region| region.name.as_str()).collect();
+        assert_eq!(names, vec!["coin.png", "sword.png", "potion.png"]);
+
+        let coin = atlas.get_region("coin.png").unwrap();
+        assert_eq!(coin.region, TextureRegion::new(2.0, 2.0, 16.0, 16.0));
+        assert_eq!(coin.pivot, Some((0.5, 0.5)));
+
+        // Rotated and trimmed: the frame keeps the unrotated size, and covers 40x12 pixels of the texture
+        let sword = atlas.get_region("sword.png").unwrap();
+        assert!(sword.region.rotated);
+        assert_eq!((sword.region.width, sword.region.height), (12.0, 40.0));
+        assert_eq!(sword.region.get_texture_size(), (40.0, 12.0));
+        assert_eq!(sword.region.trim, Some(Trim { offset_x: 10.0, offset_y: 0.0, source_width: 32.0, source_height: 40.0 }));
+        assert_eq!(sword.region.get_source_size(), (32.0, 40.0));
+        assert_eq!(sword.pivot, Some((0.5, 1.0)));
+
+        let potion = atlas.get_region("potion.png").unwrap();
+        assert!(!potion.region.rotated);
+        assert_eq!(potion.region.trim, Some(Trim { offset_x: 3.0, offset_y: 2.0, source_width: 16.0, source_height: 16.0 }));
+        assert_eq!(potion.pivot, None);
+    }
+
+    #[test]
+    fn hash_atlas() {
+        check_items_atlas(&load_texture_packer_atlas(&fixture_path("items_hash.json")).unwrap());
+    }
+
+    #[test]
+    fn array_atlas() {
+        check_items_atlas(&load_texture_packer_atlas(&fixture_path("items_array.json")).unwrap());
+    }
+
+    #[test]
+    fn hash_and_array_layouts_match() {
+        let hash = load_texture_packer_atlas(&fixture_path("items_hash.json")).unwrap();
+        let array = load_texture_packer_atlas(&fixture_path("items_array.json")).unwrap();
+        assert_eq!(hash.get_regions(), array.get_regions());
+    }
+
+    #[test]
+    fn trimmed_flag_without_sizes_is_not_trimmed() {
+        let json = r#"{ "frames": { "a.png": { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "trimmed": true } },
+            "meta": { "image": "a.png", "size": { "w": 8, "h": 8 } } }"#;
+
+        let atlas = parse_texture_packer_atlas(json, Path::new("a.json")).unwrap();
+        assert_eq!(atlas.get_region("a.png").unwrap().region.trim, None);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(load_texture_packer_atlas(&fixture_path("missing.json")).is_err());
+        assert!(parse_texture_packer_atlas("{ \"frames\": [] }", Path::new("a.json")).is_err());
+
+        // Array frames need a filename
+        let json = r#"{ "frames": [ { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 } } ], "meta": { "image": "a.png", "size": { "w": 8, "h": 8 } } }"#;
+        assert!(parse_texture_packer_atlas(json, Path::new("a.json")).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+// Decoding of the values that look the same in TMX and TMJ files: tile data, gids and colors.
+
+use crate::core::color::Color;
+use crate::core::tilemap::Tile;
+
+use base64::Engine;
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+use std::io::Read;
+
+// The top bits of a gid hold the flip flags of the tile
+const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY_FLAG: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY_FLAG: u32 = 0x2000_0000;
+// Only used by hexagonal maps, which we don't support, but it must still be masked out of the id
+const ROTATED_HEXAGONAL_120_FLAG: u32 = 0x1000_0000;
+
+pub fn gid_to_tile(gid: u32) -> Tile {
+    Tile {
+        id: gid & !(FLIPPED_HORIZONTALLY_FLAG | FLIPPED_VERTICALLY_FLAG | FLIPPED_DIAGONALLY_FLAG | ROTATED_HEXAGONAL_120_FLAG),
+        flip_x: gid & FLIPPED_HORIZONTALLY_FLAG != 0,
+        flip_y: gid & FLIPPED_VERTICALLY_FLAG != 0,
+        flip_diagonal: gid & FLIPPED_DIAGONALLY_FLAG != 0
+    }
+}
+
+pub fn decode_csv(text: &str) -> Result<Vec<u32>, String> {
+    text.split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<u32>().map_err(|_| format!("Invalid tile gid '{}' in CSV data", value)))
+        .collect()
+}
+
+// Base64 encoded little endian u32 gids, optionally compressed with "zlib" or "gzip".
+pub fn decode_base64(text: &str, compression: Option<&str>) -> Result<Vec<u32>, String> {
+    // Tiled wraps the encoded data in whitespace and newlines in TMX files
+    let encoded: String = text.chars().filter(|character| !character.is_whitespace()).collect();
+
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)
+        .map_err(|error| format!("Invalid base64 tile data: {}", error))?;
+
+    let bytes = match compression {
+        None | Some("") => bytes,
+        Some("zlib") => decompress(ZlibDecoder::new(&bytes[..]))?,
+        Some("gzip") => decompress(GzDecoder::new(&bytes[..]))?,
+        Some(other) => return Err(format!("Unsupported tile data compression '{}'", other))
+    };
+
+    if bytes.len() % 4 != 0 {
+        return Err(format!("Tile data is {} bytes long, which isn't a whole number of gids", bytes.len()));
+    }
+
+    Ok(bytes.chunks_exact(4).map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]])).collect())
+}
+
+fn decompress(mut decoder: impl Read) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    decoder.read_to_end(&mut bytes).map_err(|error| format!("Failed to decompress tile data: {}", error))?;
+    Ok(bytes)
+}
+
+// Tiled writes colors as "#RRGGBB" or "#AARRGGBB" (the hash is optional).
+pub fn parse_color(text: &str) -> Result<Color, String> {
+    let hex = text.trim_start_matches('#');
+
+    let value = u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid color '{}'", text))?;
+    let channel = |shift: u32| ((value >> shift) & 0xFF) as u8;
+
+    match hex.len() {
+        6 => Ok(Color::from_rgba8(channel(16), channel(8), channel(0), 255)),
+        8 => Ok(Color::from_rgba8(channel(16), channel(8), channel(0), channel(24))),
+        _ => Err(format!("Invalid color '{}'", text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gid_flags() {
+        assert_eq!(gid_to_tile(0), Tile::EMPTY);
+        assert_eq!(gid_to_tile(42), Tile::new(42));
+
+        let tile = gid_to_tile(42 | FLIPPED_HORIZONTALLY_FLAG | FLIPPED_DIAGONALLY_FLAG | ROTATED_HEXAGONAL_120_FLAG);
+        assert_eq!(tile, Tile { id: 42, flip_x: true, flip_y: false, flip_diagonal: true });
+        assert!(gid_to_tile(7 | FLIPPED_VERTICALLY_FLAG).flip_y);
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(decode_csv("1,2,\n3, 0,\n").unwrap(), vec![1, 2, 3, 0]);
+        assert_eq!(decode_csv("").unwrap(), Vec::<u32>::new());
+        assert!(decode_csv("1,x").is_err());
+    }
+
+    #[test]
+    fn base64() {
+        // 1, 2 and 0x80000003 as little endian u32s
+        let encoded = "AQAAAAIAAAADAACA";
+        assert_eq!(decode_base64(encoded, None).unwrap(), vec![1, 2, 0x8000_0003]);
+        assert_eq!(decode_base64(&format!("\n   {}\n  ", encoded), Some("")).unwrap(), vec![1, 2, 0x8000_0003]);
+
+        assert!(decode_base64("AQAA", None).is_err());
+        assert!(decode_base64("not base64!", None).is_err());
+        assert!(decode_base64(encoded, Some("zstd")).is_err());
+        assert!(decode_base64(encoded, Some("zlib")).is_err());
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#ff8000").unwrap(), Color::from_rgba8(255, 128, 0, 255));
+        assert_eq!(parse_color("80ff8000").unwrap(), Color::from_rgba8(255, 128, 0, 128));
+        assert!(parse_color("#fff").is_err());
+        assert!(parse_color("#gggggg").is_err());
+    }
+}
@@ -0,0 +1,613 @@
+// Importer for maps made with the Tiled editor (https://www.mapeditor.org), in both its XML (.tmx) and JSON (.tmj) formats.
+// Loading a map gives a TiledMap, which holds everything from the file without touching the GPU.
+// TiledMap::create_tilemap then turns its tile layers into an engine Tilemap,
+// While object and image layers are left for the game code to spawn entities from.
+
+pub mod tmx;
+pub mod tmj;
+mod data;
+
+use crate::core::color::Color;
+use crate::core::texture::Texture;
+use crate::core::tilemap::{Tile, Tilemap};
+use crate::core::tileset::{TileAnimationFrame, Tileset};
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Color(Color),
+    // Path resolved relative to the file the property was read from
+    File(PathBuf),
+    // Id of an object in the map, 0 for none
+    Object(u32),
+    // A custom class, holding its own members
+    Class(Properties)
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TiledImage {
+    // Resolved relative to the file that referenced the image
+    pub source: PathBuf,
+    pub width: u32,
+    pub height: u32
+}
+
+// The shape of an object. Polygon and polyline points are relative to the object's position.
+#[derive(Clone)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    Polygon(Vec<Vector2>),
+    Polyline(Vec<Vector2>),
+    Text(String),
+    // A tile placed as an object. Its position is the bottom left corner of the tile.
+    Tile(Tile)
+}
+
+#[derive(Clone)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    // Called "type" before Tiled 1.9
+    pub class: String,
+    // Pixels, relative to the map's top left corner (not including any layer offset)
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // Degrees, clockwise
+    pub rotation: f32,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    pub properties: Properties
+}
+
+#[derive(Clone)]
+pub struct TiledTileData {
+    // Id within its tileset
+    pub id: u32,
+    pub class: String,
+    pub properties: Properties,
+    pub animation: Vec<TileAnimationFrame>,
+    // Only for "collection of images" tilesets, where each tile has its own image
+    pub image: Option<TiledImage>,
+    // Collision shapes made in the tile collision editor
+    pub objects: Vec<TiledObject>
+}
+
+#[derive(Clone)]
+pub struct TiledTileset {
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub margin: u32,
+    pub spacing: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub image: Option<TiledImage>,
+    // Only tiles with properties, animations or collision shapes are listed
+    pub tiles: Vec<TiledTileData>
+}
+
+impl TiledTileset {
+    pub fn get_tile_data(&self, id: u32) -> Option<&TiledTileData> {
+        self.tiles.iter().find(|tile| tile.id == id)
+    }
+}
+
+#[derive(Clone)]
+pub enum TiledLayerContent {
+    // One tile per cell of the map, row by row
+    Tiles(Vec<Tile>),
+    Objects(Vec<TiledObject>),
+    Image { image: TiledImage, repeat_x: bool, repeat_y: bool }
+}
+
+// Layers inside group layers are flattened into the map's layer list,
+// With the group's offset, opacity, tint and visibility already applied to them.
+#[derive(Clone)]
+pub struct TiledLayer {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub tint: Color,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub properties: Properties,
+    pub content: TiledLayerContent
+}
+
+#[derive(Clone)]
+pub struct TiledMap {
+    pub orientation: String,
+    // In tiles. For infinite maps, the size of the area covered by all chunks.
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub infinite: bool,
+    // The tile coordinate of the top left cell. Only infinite maps can have cells at negative coordinates.
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub background_color: Option<Color>,
+    pub properties: Properties,
+    pub tilesets: Vec<TiledTileset>,
+    pub layers: Vec<TiledLayer>,
+    // Ids of the objects that were left out because they use a template, which isn't supported yet
+    pub skipped_objects: Vec<u32>
+}
+
+impl TiledMap {
+    pub fn get_layer(&self, name: &str) -> Option<&TiledLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    // Every object of every object layer, with the layer it is on.
+    pub fn get_objects(&self) -> impl Iterator<Item = (&TiledLayer, &TiledObject)> {
+        self.layers.iter().flat_map(|layer| {
+            let objects: &[TiledObject] = match &layer.content {
+                TiledLayerContent::Objects(objects) => objects,
+                _ => &[]
+            };
+
+            objects.iter().map(move |object| (layer, object))
+        })
+    }
+
+    pub fn find_object(&self, name: &str) -> Option<&TiledObject> {
+        self.get_objects().map(|(_, object)| object).find(|object| object.name == name)
+    }
+
+    // The tileset a tile belongs to, and its data within the tileset if there is any.
+    pub fn get_tile_data(&self, tile: Tile) -> Option<(&TiledTileset, Option<&TiledTileData>)> {
+        let tileset = self.tilesets.iter().rfind(|tileset| tileset.first_gid <= tile.id && tile.id != 0)?;
+        Some((tileset, tileset.get_tile_data(tile.id - tileset.first_gid)))
+    }
+
+    // Builds an engine tilemap from the tile layers, loading the tileset textures.
+    // The tilemap gets a layer for every tile layer, in the same order and with the same names.
+    pub fn create_tilemap(&self) -> Result<Tilemap, String> {
+        if self.orientation != "orthogonal" {
+            return Err(format!("Only orthogonal maps are supported, but the map is {}", self.orientation));
+        }
+
+        let mut tilemap = Tilemap::new(self.width, self.height, self.tile_width, self.tile_height);
+        tilemap.set_position((self.origin_x * self.tile_width as i32) as f32, (self.origin_y * self.tile_height as i32) as f32);
+
+        for tiled_tileset in &self.tilesets {
+            let image = tiled_tileset.image.as_ref()
+                .ok_or_else(|| format!("Tileset '{}' is a collection of images, which isn't supported for tile layers", tiled_tileset.name))?;
+
+            let texture = Box::new(Texture::new(image.source.to_string_lossy().into_owned()));
+            let mut tileset = Tileset::new(tiled_tileset.name.clone(), texture, tiled_tileset.tile_width, tiled_tileset.tile_height)
+                .with_spacing(tiled_tileset.margin, tiled_tileset.spacing);
+
+            for tile in &tiled_tileset.tiles {
+                if !tile.animation.is_empty() {
+                    tileset.set_animation(tile.id, tile.animation.clone());
+                }
+            }
+
+            tilemap.add_tileset_with_first_id(tileset, tiled_tileset.first_gid)?;
+        }
+
+        for tiled_layer in &self.layers {
+            let tiles = match &tiled_layer.content {
+                TiledLayerContent::Tiles(tiles) => tiles,
+                _ => continue
+            };
+
+            let layer_index = tilemap.add_layer(tiled_layer.name.clone());
+            let layer = tilemap.get_layer_mut(layer_index);
+
+            layer.set_visible(tiled_layer.visible);
+            layer.set_tint(tiled_layer.tint.with_alpha(tiled_layer.tint.a * tiled_layer.opacity));
+            layer.set_offset(tiled_layer.offset_x, tiled_layer.offset_y);
+
+            for (index, tile) in tiles.iter().enumerate() {
+                if !tile.is_empty() {
+                    layer.set_tile(index as u32 % self.width, index as u32 / self.width, *tile);
+                }
+            }
+        }
+
+        Ok(tilemap)
+    }
+}
+
+// Loads a .tmx or .tmj map. External tilesets and images are looked up relative to the map file.
+pub fn load_tiled_map(path: &Path) -> Result<TiledMap, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read Tiled map {}: {}", path.display(), error))?;
+
+    let result = match get_extension(path).as_str() {
+        "tmx" | "xml" => tmx::parse_map(&content, path),
+        "tmj" | "json" => tmj::parse_map(&content, path),
+        extension => Err(format!("Unknown map format '.{}'", extension))
+    };
+
+    result.map_err(|error| format!("Failed to load Tiled map {}: {}", path.display(), error))
+}
+
+// Loads an external .tsx or .tsj tileset, referenced by a map with the given first gid.
+pub(crate) fn load_external_tileset(path: &Path, first_gid: u32) -> Result<TiledTileset, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read tileset {}: {}", path.display(), error))?;
+
+    let result = match get_extension(path).as_str() {
+        "tsx" | "xml" => tmx::parse_tileset(&content, path, first_gid),
+        "tsj" | "json" => tmj::parse_tileset(&content, path, first_gid),
+        extension => Err(format!("Unknown tileset format '.{}'", extension))
+    };
+
+    result.map_err(|error| format!("Failed to load tileset {}: {}", path.display(), error))
+}
+
+fn get_extension(path: &Path) -> String {
+    path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+// A rectangle of tile gids, as read from a layer (or one chunk of a layer in an infinite map).
+pub(crate) struct TileChunk {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub gids: Vec<u32>
+}
+
+// A layer as parsed. Tile layers stay as chunks until the size of the whole map is known.
+pub(crate) struct ParsedLayer {
+    pub layer: TiledLayer,
+    pub chunks: Option<Vec<TileChunk>>,
+    pub skipped_objects: Vec<u32>
+}
+
+// Offset, opacity, tint and visibility a group layer passes on to the layers inside it.
+#[derive(Copy, Clone)]
+pub(crate) struct GroupState {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub opacity: f32,
+    pub tint: Color,
+    pub visible: bool
+}
+
+impl GroupState {
+    pub fn root() -> GroupState {
+        GroupState { offset_x: 0.0, offset_y: 0.0, opacity: 1.0, tint: Color::WHITE, visible: true }
+    }
+
+    pub fn apply(&self, layer: &mut TiledLayer) {
+        layer.offset_x += self.offset_x;
+        layer.offset_y += self.offset_y;
+        layer.opacity *= self.opacity;
+        layer.tint = layer.tint.multiply(self.tint);
+        layer.visible &= self.visible;
+    }
+
+    pub fn enter(&self, group: &TiledLayer) -> GroupState {
+        GroupState {
+            offset_x: self.offset_x + group.offset_x,
+            offset_y: self.offset_y + group.offset_y,
+            opacity: self.opacity * group.opacity,
+            tint: self.tint.multiply(group.tint),
+            visible: self.visible && group.visible
+        }
+    }
+}
+
+// The header fields shared by both map formats.
+pub(crate) struct MapHeader {
+    pub orientation: String,
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub infinite: bool,
+    pub background_color: Option<Color>,
+    pub properties: Properties
+}
+
+// Puts the tiles of every layer into one grid covering the whole map.
+pub(crate) fn assemble_map(header: MapHeader, tilesets: Vec<TiledTileset>, parsed_layers: Vec<ParsedLayer>) -> Result<TiledMap, String> {
+    let (origin_x, origin_y, width, height) = if header.infinite {
+        let chunks = parsed_layers.iter().filter_map(|parsed| parsed.chunks.as_ref()).flatten();
+
+        let mut bounds: Option<(i32, i32, i32, i32)> = None;
+        for chunk in chunks {
+            let (left, top, right, bottom) = (chunk.x, chunk.y, chunk.x + chunk.width as i32, chunk.y + chunk.height as i32);
+            bounds = Some(match bounds {
+                Some((min_x, min_y, max_x, max_y)) => (min_x.min(left), min_y.min(top), max_x.max(right), max_y.max(bottom)),
+                None => (left, top, right, bottom)
+            });
+        }
+
+        match bounds {
+            Some((min_x, min_y, max_x, max_y)) => (min_x, min_y, (max_x - min_x) as u32, (max_y - min_y) as u32),
+            None => (0, 0, 0, 0)
+        }
+    } else {
+        (0, 0, header.width, header.height)
+    };
+
+    let mut layers = Vec::with_capacity(parsed_layers.len());
+    let mut skipped_objects = Vec::new();
+
+    for parsed in parsed_layers {
+        skipped_objects.extend(parsed.skipped_objects);
+
+        let mut layer = parsed.layer;
+
+        if let Some(chunks) = parsed.chunks {
+            let mut tiles = vec![Tile::EMPTY; (width * height) as usize];
+
+            for chunk in chunks {
+                if chunk.gids.len() != (chunk.width * chunk.height) as usize {
+                    return Err(format!("Layer '{}' has {} tiles in a {}x{} area", layer.name, chunk.gids.len(), chunk.width, chunk.height));
+                }
+
+                for (index, gid) in chunk.gids.iter().enumerate() {
+                    let x = chunk.x + (index as u32 % chunk.width) as i32 - origin_x;
+                    let y = chunk.y + (index as u32 / chunk.width) as i32 - origin_y;
+
+                    if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+                        tiles[(y as u32 * width + x as u32) as usize] = data::gid_to_tile(*gid);
+                    }
+                }
+            }
+
+            layer.content = TiledLayerContent::Tiles(tiles);
+        }
+
+        layers.push(layer);
+    }
+
+    Ok(TiledMap {
+        orientation: header.orientation,
+        width,
+        height,
+        tile_width: header.tile_width,
+        tile_height: header.tile_height,
+        infinite: header.infinite,
+        origin_x,
+        origin_y,
+        background_color: header.background_color,
+        properties: header.properties,
+        tilesets,
+        layers,
+        skipped_objects
+    })
+}
+
+// Paths in Tiled files are relative to the file itself.
+pub(crate) fn resolve_path(file_path: &Path, relative_path: &str) -> PathBuf {
+    match file_path.parent() {
+        Some(directory) => directory.join(relative_path),
+        None => PathBuf::from(relative_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tiled").join(name)
+    }
+
+    fn get_tiles<'a>(map: &'a TiledMap, layer_name: &str) -> &'a [Tile] {
+        match &map.get_layer(layer_name).unwrap().content {
+            TiledLayerContent::Tiles(tiles) => tiles,
+            _ => panic!("'{}' isn't a tile layer", layer_name)
+        }
+    }
+
+    fn get_objects<'a>(map: &'a TiledMap, layer_name: &str) -> &'a [TiledObject] {
+        match &map.get_layer(layer_name).unwrap().content {
+            TiledLayerContent::Objects(objects) => objects,
+            _ => panic!("'{}' isn't an object layer", layer_name)
+        }
+    }
+
+    fn flipped(id: u32, flip_x: bool, flip_y: bool, flip_diagonal: bool) -> Tile {
+        Tile { id, flip_x, flip_y, flip_diagonal }
+    }
+
+    fn expected_ground() -> Vec<Tile> {
+        vec![
+            Tile::new(1), Tile::new(2), Tile::new(3), Tile::new(4),
+            flipped(5, true, false, false), Tile::EMPTY, Tile::new(9), Tile::new(10),
+            Tile::EMPTY, flipped(6, true, true, false), flipped(7, false, false, true), Tile::EMPTY
+        ]
+    }
+
+    fn get_points(shape: &ObjectShape) -> Vec<(f32, f32)> {
+        match shape {
+            ObjectShape::Polygon(points) | ObjectShape::Polyline(points) => points.iter().map(|point| (point.x, point.y)).collect(),
+            _ => panic!("The shape has no points")
+        }
+    }
+
+    // map.tmx and map.tmj describe the same map
+    fn check_map(map: &TiledMap, tile_layers: &[&str]) {
+        assert_eq!(map.orientation, "orthogonal");
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (4, 3, 16, 16));
+        assert!(!map.infinite);
+        assert_eq!((map.origin_x, map.origin_y), (0, 0));
+        assert_eq!(map.background_color, Some(Color::from_rgba8(0x20, 0x30, 0x40, 0x80)));
+
+        assert_eq!(map.properties.get("music"), Some(&PropertyValue::File(fixture_path("audio/theme.ogg"))));
+        assert_eq!(map.properties.get("gravity"), Some(&PropertyValue::Float(9.5)));
+        assert_eq!(map.properties.get("boss"), Some(&PropertyValue::Object(4)));
+        let mut spawn = Properties::new();
+        spawn.insert(String::from("count"), PropertyValue::Int(3));
+        assert_eq!(map.properties.get("spawn"), Some(&PropertyValue::Class(spawn)));
+
+        // Embedded tileset
+        let terrain = &map.tilesets[0];
+        assert_eq!((terrain.name.as_str(), terrain.first_gid), ("terrain", 1));
+        assert_eq!((terrain.tile_width, terrain.tile_height, terrain.margin, terrain.spacing), (16, 16, 2, 1));
+        assert_eq!((terrain.columns, terrain.tile_count), (4, 8));
+        assert_eq!(terrain.image, Some(TiledImage { source: fixture_path("terrain.png"), width: 69, height: 36 }));
+
+        let solid_tile = terrain.get_tile_data(0).unwrap();
+        assert_eq!(solid_tile.properties.get("solid"), Some(&PropertyValue::Bool(true)));
+        assert_eq!(solid_tile.objects.len(), 1);
+        assert_eq!((solid_tile.objects[0].y, solid_tile.objects[0].height), (8.0, 8.0));
+
+        let animated_tile = terrain.get_tile_data(2).unwrap();
+        assert_eq!(animated_tile.animation, vec![
+            TileAnimationFrame { tile_id: 2, duration: 0.1 },
+            TileAnimationFrame { tile_id: 3, duration: 0.25 }
+        ]);
+
+        // External tileset, with its image relative to the tileset file
+        let props = &map.tilesets[1];
+        assert_eq!((props.name.as_str(), props.first_gid), ("props", 9));
+        assert_eq!((props.tile_width, props.tile_height), (16, 32));
+        assert_eq!(props.image.as_ref().unwrap().source, fixture_path("images/props.png"));
+
+        let (tileset, chest) = map.get_tile_data(Tile::new(10)).unwrap();
+        assert_eq!(tileset.name, "props");
+        let chest = chest.unwrap();
+        assert_eq!(chest.class, "Chest");
+        assert_eq!(chest.properties.get("loot"), Some(&PropertyValue::String(String::from("gold"))));
+        assert_eq!(chest.properties.get("amount"), Some(&PropertyValue::Int(25)));
+        assert!(map.get_tile_data(Tile::new(4)).unwrap().1.is_none());
+        assert!(map.get_tile_data(Tile::EMPTY).is_none());
+
+        // Every encoding decodes to the same tiles
+        for layer_name in tile_layers {
+            assert_eq!(get_tiles(map, layer_name), &expected_ground()[..], "Layer {}", layer_name);
+        }
+
+        let layer_names: Vec<&str> = map.layers.iter().map(|layer| layer.name.as_str()).collect();
+        let mut expected_names = tile_layers.to_vec();
+        expected_names.extend(["objects", "sky"]);
+        assert_eq!(layer_names, expected_names);
+
+        // The group's offset, opacity and visibility are passed on to its layers
+        let objects_layer = map.get_layer("objects").unwrap();
+        assert_eq!((objects_layer.offset_x, objects_layer.offset_y), (10.0, 5.0));
+        assert_eq!(objects_layer.opacity, 0.5);
+        assert_eq!(objects_layer.tint, Color::from_rgba8(255, 0, 0, 255));
+        assert!(objects_layer.visible);
+
+        // The object using a template is skipped, the rest of the layer still loads
+        let objects = get_objects(map, "objects");
+        let names: Vec<&str> = objects.iter().map(|object| object.name.as_str()).collect();
+        assert_eq!(names, vec!["spawn", "zone", "pond", "rock", "path", "sign", "chest", "after_template"]);
+        assert_eq!(map.skipped_objects, vec![8]);
+
+        let spawn = map.find_object("spawn").unwrap();
+        assert!(matches!(spawn.shape, ObjectShape::Point));
+        assert_eq!((spawn.class.as_str(), spawn.x, spawn.y), ("SpawnPoint", 24.0, 40.0));
+        assert_eq!(spawn.properties.get("facing"), Some(&PropertyValue::String(String::from("left"))));
+
+        let zone = map.find_object("zone").unwrap();
+        assert!(matches!(zone.shape, ObjectShape::Rectangle));
+        assert_eq!((zone.class.as_str(), zone.width, zone.height, zone.rotation), ("Trigger", 32.0, 16.0, 45.0));
+
+        let pond = map.find_object("pond").unwrap();
+        assert!(matches!(pond.shape, ObjectShape::Ellipse));
+        assert!(!pond.visible);
+
+        assert_eq!(get_points(&map.find_object("rock").unwrap().shape), vec![(0.0, 0.0), (10.0, 0.0), (5.0, -8.0)]);
+        let path = map.find_object("path").unwrap();
+        assert!(matches!(path.shape, ObjectShape::Polyline(_)));
+        assert_eq!(get_points(&path.shape), vec![(0.0, 0.0), (4.5, 2.0), (9.0, 0.0)]);
+
+        match &map.find_object("sign").unwrap().shape {
+            ObjectShape::Text(text) => assert_eq!(text, "Hello there"),
+            _ => panic!("The sign should be a text object")
+        }
+
+        match map.find_object("chest").unwrap().shape {
+            ObjectShape::Tile(tile) => assert_eq!(tile, flipped(10, true, false, false)),
+            _ => panic!("The chest should be a tile object")
+        }
+
+        let sky = map.get_layer("sky").unwrap();
+        assert_eq!((sky.offset_x, sky.offset_y, sky.opacity), (8.0, 4.0, 0.5));
+        match &sky.content {
+            TiledLayerContent::Image { image, repeat_x, repeat_y } => {
+                assert_eq!(image, &TiledImage { source: fixture_path("images/sky.png"), width: 320, height: 180 });
+                assert!(*repeat_x && !*repeat_y);
+            },
+            _ => panic!("'sky' should be an image layer")
+        }
+
+        assert_eq!(map.get_objects().count(), 8);
+    }
+
+    // infinite.tmx and infinite.tmj: chunks at (-2, -2) and (2, 0), each 2x2 tiles
+    fn check_infinite_map(map: &TiledMap, layer_names: &[&str]) {
+        assert!(map.infinite);
+        assert_eq!((map.origin_x, map.origin_y, map.width, map.height), (-2, -2, 6, 4));
+
+        let mut expected = [Tile::EMPTY; 6 * 4];
+        expected[0] = Tile::new(1);
+        expected[1] = Tile::new(2);
+        expected[6] = Tile::new(3);
+        expected[7] = Tile::new(4);
+        expected[2 * 6 + 4] = Tile::new(5);
+        expected[3 * 6 + 5] = flipped(6, true, false, false);
+
+        for layer_name in layer_names {
+            assert_eq!(get_tiles(map, layer_name), &expected[..], "Layer {}", layer_name);
+        }
+    }
+
+    #[test]
+    fn tmx_map() {
+        let map = load_tiled_map(&fixture_path("map.tmx")).unwrap();
+        check_map(&map, &["ground_csv", "ground_base64", "ground_zlib", "ground_gzip", "ground_xml"]);
+    }
+
+    #[test]
+    fn tmj_map() {
+        let map = load_tiled_map(&fixture_path("map.tmj")).unwrap();
+        check_map(&map, &["ground_csv", "ground_base64", "ground_zlib", "ground_gzip"]);
+    }
+
+    #[test]
+    fn infinite_maps() {
+        check_infinite_map(&load_tiled_map(&fixture_path("infinite.tmx")).unwrap(), &["chunks_csv", "chunks_zlib"]);
+        check_infinite_map(&load_tiled_map(&fixture_path("infinite.tmj")).unwrap(), &["chunks_csv", "chunks_gzip"]);
+    }
+
+    #[test]
+    fn external_tileset_formats_match() {
+        let tsx = load_external_tileset(&fixture_path("props.tsx"), 5).unwrap();
+        let tsj = load_external_tileset(&fixture_path("props.tsj"), 5).unwrap();
+
+        assert_eq!(tsx.first_gid, 5);
+        assert_eq!((tsx.name.as_str(), tsx.tile_count, tsx.columns), (tsj.name.as_str(), tsj.tile_count, tsj.columns));
+        assert_eq!(tsx.image, tsj.image);
+        assert_eq!(tsx.get_tile_data(1).unwrap().properties, tsj.get_tile_data(1).unwrap().properties);
+    }
+
+    #[test]
+    fn load_errors() {
+        assert!(load_tiled_map(&fixture_path("missing.tmx")).is_err());
+        assert!(load_tiled_map(&fixture_path("props.tsx")).is_err());
+
+        let error = load_tiled_map(&fixture_path("../aseprite/knight.json")).err().unwrap();
+        assert!(error.contains("knight.json"), "{}", error);
+    }
+}
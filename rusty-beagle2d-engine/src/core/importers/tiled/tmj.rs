@@ -0,0 +1,517 @@
+// The JSON flavour of Tiled maps (.tmj) and tilesets (.tsj).
+
+use crate::core::color::Color;
+use crate::core::importers::tiled::{self, data};
+use crate::core::importers::tiled::{GroupState, MapHeader, ObjectShape, ParsedLayer, Properties, PropertyValue, TileChunk};
+use crate::core::importers::tiled::{TiledImage, TiledLayer, TiledLayerContent, TiledMap, TiledObject, TiledTileData, TiledTileset};
+use crate::core::tileset::TileAnimationFrame;
+
+use linear_beaglebra::vector2::Vector2;
+use serde::Deserialize;
+
+use std::path::Path;
+
+fn default_orientation() -> String {
+    String::from("orthogonal")
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    #[serde(default = "default_orientation")]
+    orientation: String,
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    backgroundcolor: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    #[serde(rename = "type", default)]
+    property_type: Option<String>,
+    value: serde_json::Value
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    // Only set when the tileset is embedded in a map
+    #[serde(default)]
+    firstgid: u32,
+    // Set instead of everything else for external tilesets
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    tiles: Vec<JsonTile>
+}
+
+#[derive(Deserialize)]
+struct JsonTile {
+    id: u32,
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(rename = "type", default)]
+    tile_type: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    animation: Vec<JsonFrame>,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    objectgroup: Option<JsonLayer>
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    tileid: u32,
+    // Milliseconds
+    duration: f32
+}
+
+// Tile data is either an array of gids, or a base64 string (see "encoding")
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonTileData {
+    Gids(Vec<u32>),
+    Encoded(String)
+}
+
+#[derive(Deserialize)]
+struct JsonChunk {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    data: JsonTileData
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    layer_type: String,
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default = "default_opacity")]
+    opacity: f32,
+    #[serde(default)]
+    tintcolor: Option<String>,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    // Tile layers
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    #[serde(default)]
+    data: Option<JsonTileData>,
+    #[serde(default)]
+    chunks: Vec<JsonChunk>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    compression: Option<String>,
+    // Object layers
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    // Image layers
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    repeatx: bool,
+    #[serde(default)]
+    repeaty: bool,
+    // Group layers
+    #[serde(default)]
+    layers: Vec<JsonLayer>
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32
+}
+
+#[derive(Deserialize)]
+struct JsonText {
+    #[serde(default)]
+    text: String
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(rename = "type", default)]
+    object_type: Option<String>,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default)]
+    gid: Option<u32>,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    #[serde(default)]
+    polygon: Option<Vec<JsonPoint>>,
+    #[serde(default)]
+    polyline: Option<Vec<JsonPoint>>,
+    #[serde(default)]
+    text: Option<JsonText>,
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>
+}
+
+// "map_path" is used to find external tilesets and images, which are relative to the map file.
+pub fn parse_map(json: &str, map_path: &Path) -> Result<TiledMap, String> {
+    let json_map: JsonMap = serde_json::from_str(json).map_err(|error| error.to_string())?;
+
+    let header = MapHeader {
+        orientation: json_map.orientation,
+        width: json_map.width,
+        height: json_map.height,
+        tile_width: json_map.tilewidth,
+        tile_height: json_map.tileheight,
+        infinite: json_map.infinite,
+        background_color: optional_color(&json_map.backgroundcolor)?,
+        properties: convert_properties(&json_map.properties, map_path)?
+    };
+
+    let mut tilesets = Vec::with_capacity(json_map.tilesets.len());
+
+    for json_tileset in &json_map.tilesets {
+        let tileset = match &json_tileset.source {
+            Some(source) => tiled::load_external_tileset(&tiled::resolve_path(map_path, source), json_tileset.firstgid)?,
+            None => convert_tileset(json_tileset, map_path, json_tileset.firstgid)?
+        };
+
+        tilesets.push(tileset);
+    }
+
+    let mut layers = Vec::new();
+    convert_layers(&json_map.layers, map_path, GroupState::root(), &mut layers)?;
+
+    tiled::assemble_map(header, tilesets, layers)
+}
+
+// Parses an external .tsj tileset file.
+pub fn parse_tileset(json: &str, tileset_path: &Path, first_gid: u32) -> Result<TiledTileset, String> {
+    let json_tileset: JsonTileset = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    convert_tileset(&json_tileset, tileset_path, first_gid)
+}
+
+fn convert_tileset(json_tileset: &JsonTileset, file_path: &Path, first_gid: u32) -> Result<TiledTileset, String> {
+    let mut tiles = Vec::with_capacity(json_tileset.tiles.len());
+
+    for json_tile in &json_tileset.tiles {
+        let mut skipped_objects = Vec::new();
+
+        let objects = match &json_tile.objectgroup {
+            Some(object_group) => convert_objects(&object_group.objects, file_path, &mut skipped_objects)?,
+            None => Vec::new()
+        };
+
+        if !skipped_objects.is_empty() {
+            return Err(format!("The collision shapes of tile {} use templates, which aren't supported yet", json_tile.id));
+        }
+
+        tiles.push(TiledTileData {
+            id: json_tile.id,
+            class: get_class(&json_tile.class, &json_tile.tile_type),
+            properties: convert_properties(&json_tile.properties, file_path)?,
+            animation: json_tile.animation.iter()
+                .map(|frame| TileAnimationFrame { tile_id: frame.tileid, duration: frame.duration / 1000.0 })
+                .collect(),
+            image: convert_image(&json_tile.image, json_tile.imagewidth, json_tile.imageheight, file_path),
+            objects
+        });
+    }
+
+    Ok(TiledTileset {
+        first_gid,
+        name: json_tileset.name.clone(),
+        tile_width: json_tileset.tilewidth,
+        tile_height: json_tileset.tileheight,
+        margin: json_tileset.margin,
+        spacing: json_tileset.spacing,
+        columns: json_tileset.columns,
+        tile_count: json_tileset.tilecount,
+        image: convert_image(&json_tileset.image, json_tileset.imagewidth, json_tileset.imageheight, file_path),
+        tiles
+    })
+}
+
+// Converts the layers in order, flattening group layers into "output".
+fn convert_layers(json_layers: &[JsonLayer], file_path: &Path, group: GroupState, output: &mut Vec<ParsedLayer>) -> Result<(), String> {
+    for json_layer in json_layers {
+        let mut parsed = match json_layer.layer_type.as_str() {
+            "group" => {
+                let group_layer = convert_layer_common(json_layer, file_path, TiledLayerContent::Objects(Vec::new()))?;
+                convert_layers(&json_layer.layers, file_path, group.enter(&group_layer), output)?;
+                continue;
+            },
+            "tilelayer" => ParsedLayer {
+                layer: convert_layer_common(json_layer, file_path, TiledLayerContent::Tiles(Vec::new()))?,
+                chunks: Some(convert_tile_data(json_layer)?),
+                skipped_objects: Vec::new()
+            },
+            "objectgroup" => {
+                let mut skipped_objects = Vec::new();
+                let objects = convert_objects(&json_layer.objects, file_path, &mut skipped_objects)?;
+
+                ParsedLayer {
+                    layer: convert_layer_common(json_layer, file_path, TiledLayerContent::Objects(objects))?,
+                    chunks: None,
+                    skipped_objects
+                }
+            },
+            "imagelayer" => {
+                // An image layer without an image is allowed in Tiled, but there is nothing to load
+                let image = match convert_image(&json_layer.image, json_layer.imagewidth, json_layer.imageheight, file_path) {
+                    Some(image) => image,
+                    None => continue
+                };
+
+                let content = TiledLayerContent::Image { image, repeat_x: json_layer.repeatx, repeat_y: json_layer.repeaty };
+                ParsedLayer { layer: convert_layer_common(json_layer, file_path, content)?, chunks: None, skipped_objects: Vec::new() }
+            },
+            _ => continue
+        };
+
+        group.apply(&mut parsed.layer);
+        output.push(parsed);
+    }
+
+    Ok(())
+}
+
+fn convert_layer_common(json_layer: &JsonLayer, file_path: &Path, content: TiledLayerContent) -> Result<TiledLayer, String> {
+    Ok(TiledLayer {
+        id: json_layer.id,
+        name: json_layer.name.clone(),
+        class: json_layer.class.clone().unwrap_or_default(),
+        visible: json_layer.visible,
+        opacity: json_layer.opacity,
+        tint: optional_color(&json_layer.tintcolor)?.unwrap_or(Color::WHITE),
+        offset_x: json_layer.offsetx,
+        offset_y: json_layer.offsety,
+        properties: convert_properties(&json_layer.properties, file_path)?,
+        content
+    })
+}
+
+// Finite maps have the tiles directly in "data", infinite maps split them into "chunks".
+fn convert_tile_data(json_layer: &JsonLayer) -> Result<Vec<TileChunk>, String> {
+    let decode = |tile_data: &JsonTileData| -> Result<Vec<u32>, String> {
+        match tile_data {
+            JsonTileData::Gids(gids) => Ok(gids.clone()),
+            JsonTileData::Encoded(text) => match json_layer.encoding.as_deref() {
+                Some("base64") => data::decode_base64(text, json_layer.compression.as_deref()),
+                encoding => Err(format!("Layer '{}' has string data with unsupported encoding {:?}", json_layer.name, encoding))
+            }
+        }
+    };
+
+    if let Some(tile_data) = &json_layer.data {
+        return Ok(vec![TileChunk { x: 0, y: 0, width: json_layer.width, height: json_layer.height, gids: decode(tile_data)? }]);
+    }
+
+    json_layer.chunks.iter().map(|chunk| {
+        Ok(TileChunk { x: chunk.x, y: chunk.y, width: chunk.width, height: chunk.height, gids: decode(&chunk.data)? })
+    }).collect()
+}
+
+// Objects using a template are left out, and their ids added to "skipped_objects".
+fn convert_objects(json_objects: &[JsonObject], file_path: &Path, skipped_objects: &mut Vec<u32>) -> Result<Vec<TiledObject>, String> {
+    let mut objects = Vec::with_capacity(json_objects.len());
+
+    for json_object in json_objects {
+        // TODO: Support object templates (.tx and .tj files). Until then their objects are left out, so the rest of the map still loads.
+        if json_object.template.is_some() {
+            skipped_objects.push(json_object.id);
+            continue;
+        }
+
+        objects.push(convert_object(json_object, file_path)?);
+    }
+
+    Ok(objects)
+}
+
+fn convert_object(json_object: &JsonObject, file_path: &Path) -> Result<TiledObject, String> {
+    let to_points = |points: &[JsonPoint]| points.iter().map(|point| Vector2::new(point.x, point.y)).collect();
+
+    let shape = if let Some(gid) = json_object.gid {
+        ObjectShape::Tile(data::gid_to_tile(gid))
+    } else if json_object.ellipse {
+        ObjectShape::Ellipse
+    } else if json_object.point {
+        ObjectShape::Point
+    } else if let Some(polygon) = &json_object.polygon {
+        ObjectShape::Polygon(to_points(polygon))
+    } else if let Some(polyline) = &json_object.polyline {
+        ObjectShape::Polyline(to_points(polyline))
+    } else if let Some(text) = &json_object.text {
+        ObjectShape::Text(text.text.clone())
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    Ok(TiledObject {
+        id: json_object.id,
+        name: json_object.name.clone(),
+        class: get_class(&json_object.class, &json_object.object_type),
+        x: json_object.x,
+        y: json_object.y,
+        width: json_object.width,
+        height: json_object.height,
+        rotation: json_object.rotation,
+        visible: json_object.visible,
+        shape,
+        properties: convert_properties(&json_object.properties, file_path)?
+    })
+}
+
+fn convert_image(source: &Option<String>, width: u32, height: u32, file_path: &Path) -> Option<TiledImage> {
+    match source {
+        Some(source) if !source.is_empty() => Some(TiledImage { source: tiled::resolve_path(file_path, source), width, height }),
+        _ => None
+    }
+}
+
+fn convert_properties(json_properties: &[JsonProperty], file_path: &Path) -> Result<Properties, String> {
+    let mut properties = Properties::new();
+
+    for json_property in json_properties {
+        let property_type = json_property.property_type.as_deref().unwrap_or("string");
+        let value = &json_property.value;
+        let invalid = || format!("Property '{}' has an invalid {} value: {}", json_property.name, property_type, value);
+
+        let property_value = match property_type {
+            "string" => PropertyValue::String(value.as_str().ok_or_else(invalid)?.to_string()),
+            "int" => PropertyValue::Int(value.as_i64().ok_or_else(invalid)?),
+            "float" => PropertyValue::Float(value.as_f64().ok_or_else(invalid)?),
+            "bool" => PropertyValue::Bool(value.as_bool().ok_or_else(invalid)?),
+            "color" => match value.as_str().ok_or_else(invalid)? {
+                // An unset color is written as an empty string
+                "" => PropertyValue::Color(Color::TRANSPARENT),
+                color => PropertyValue::Color(data::parse_color(color)?)
+            },
+            "file" => PropertyValue::File(tiled::resolve_path(file_path, value.as_str().ok_or_else(invalid)?)),
+            "object" => PropertyValue::Object(value.as_u64().ok_or_else(invalid)? as u32),
+            "class" => PropertyValue::Class(convert_class_members(value)),
+            other => return Err(format!("Property '{}' has unknown type '{}'", json_property.name, other))
+        };
+
+        properties.insert(json_property.name.clone(), property_value);
+    }
+
+    Ok(properties)
+}
+
+// Class members in JSON carry no type information, so the type is guessed from the JSON value.
+// NOTE: Colors and files come out as strings, and object references as ints.
+fn convert_class_members(value: &serde_json::Value) -> Properties {
+    let mut members = Properties::new();
+
+    if let serde_json::Value::Object(object) = value {
+        for (name, member) in object {
+            let member_value = match member {
+                serde_json::Value::Bool(value) => PropertyValue::Bool(*value),
+                serde_json::Value::Number(number) if number.is_i64() => PropertyValue::Int(number.as_i64().unwrap_or(0)),
+                serde_json::Value::Number(number) => PropertyValue::Float(number.as_f64().unwrap_or(0.0)),
+                serde_json::Value::Object(_) => PropertyValue::Class(convert_class_members(member)),
+                serde_json::Value::String(text) => PropertyValue::String(text.clone()),
+                _ => continue
+            };
+
+            members.insert(name.clone(), member_value);
+        }
+    }
+
+    members
+}
+
+// Tiled 1.9 renamed "type" to "class"
+fn get_class(class: &Option<String>, legacy_type: &Option<String>) -> String {
+    class.clone().or_else(|| legacy_type.clone()).unwrap_or_default()
+}
+
+fn optional_color(color: &Option<String>) -> Result<Option<Color>, String> {
+    match color {
+        Some(color) => data::parse_color(color).map(Some),
+        None => Ok(None)
+    }
+}
@@ -0,0 +1,376 @@
+// The XML flavour of Tiled maps (.tmx) and tilesets (.tsx).
+
+use crate::core::color::Color;
+use crate::core::importers::tiled::{self, data};
+use crate::core::importers::tiled::{GroupState, MapHeader, ObjectShape, ParsedLayer, Properties, PropertyValue, TileChunk};
+use crate::core::importers::tiled::{TiledImage, TiledLayer, TiledLayerContent, TiledMap, TiledObject, TiledTileData, TiledTileset};
+use crate::core::tileset::TileAnimationFrame;
+
+use linear_beaglebra::vector2::Vector2;
+use roxmltree::Node;
+
+use std::path::Path;
+use std::str::FromStr;
+
+// "map_path" is used to find external tilesets and images, which are relative to the map file.
+pub fn parse_map(xml: &str, map_path: &Path) -> Result<TiledMap, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|error| error.to_string())?;
+    let root = document.root_element();
+
+    if root.tag_name().name() != "map" {
+        return Err(format!("Expected a <map> element, but found <{}>", root.tag_name().name()));
+    }
+
+    let header = MapHeader {
+        orientation: attribute_or(root, "orientation", String::from("orthogonal"))?,
+        width: required_attribute(root, "width")?,
+        height: required_attribute(root, "height")?,
+        tile_width: required_attribute(root, "tilewidth")?,
+        tile_height: required_attribute(root, "tileheight")?,
+        infinite: attribute_or(root, "infinite", 0)? == 1,
+        background_color: optional_color(root, "backgroundcolor")?,
+        properties: parse_properties(root, map_path)?
+    };
+
+    let mut tilesets = Vec::new();
+
+    for tileset_node in child_elements(root, "tileset") {
+        let first_gid = required_attribute(tileset_node, "firstgid")?;
+
+        let tileset = match tileset_node.attribute("source") {
+            Some(source) => tiled::load_external_tileset(&tiled::resolve_path(map_path, source), first_gid)?,
+            None => parse_tileset_element(tileset_node, map_path, first_gid)?
+        };
+
+        tilesets.push(tileset);
+    }
+
+    let mut layers = Vec::new();
+    parse_layers(root, map_path, GroupState::root(), &mut layers)?;
+
+    tiled::assemble_map(header, tilesets, layers)
+}
+
+// Parses an external .tsx tileset file.
+pub fn parse_tileset(xml: &str, tileset_path: &Path, first_gid: u32) -> Result<TiledTileset, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|error| error.to_string())?;
+    let root = document.root_element();
+
+    if root.tag_name().name() != "tileset" {
+        return Err(format!("Expected a <tileset> element, but found <{}>", root.tag_name().name()));
+    }
+
+    parse_tileset_element(root, tileset_path, first_gid)
+}
+
+fn parse_tileset_element(node: Node, file_path: &Path, first_gid: u32) -> Result<TiledTileset, String> {
+    let mut tiles = Vec::new();
+
+    for tile_node in child_elements(node, "tile") {
+        let animation = match child_element(tile_node, "animation") {
+            Some(animation_node) => child_elements(animation_node, "frame")
+                .map(|frame_node| Ok(TileAnimationFrame {
+                    tile_id: required_attribute(frame_node, "tileid")?,
+                    duration: required_attribute::<f32>(frame_node, "duration")? / 1000.0
+                }))
+                .collect::<Result<Vec<TileAnimationFrame>, String>>()?,
+            None => Vec::new()
+        };
+
+        let id = required_attribute(tile_node, "id")?;
+        let mut skipped_objects = Vec::new();
+
+        let objects = match child_element(tile_node, "objectgroup") {
+            Some(object_group) => parse_objects(object_group, file_path, &mut skipped_objects)?,
+            None => Vec::new()
+        };
+
+        if !skipped_objects.is_empty() {
+            return Err(format!("The collision shapes of tile {} use templates, which aren't supported yet", id));
+        }
+
+        tiles.push(TiledTileData {
+            id,
+            class: get_class(tile_node),
+            properties: parse_properties(tile_node, file_path)?,
+            animation,
+            image: match child_element(tile_node, "image") {
+                Some(image_node) => Some(parse_image(image_node, file_path)?),
+                None => None
+            },
+            objects
+        });
+    }
+
+    Ok(TiledTileset {
+        first_gid,
+        name: attribute_or(node, "name", String::new())?,
+        tile_width: required_attribute(node, "tilewidth")?,
+        tile_height: required_attribute(node, "tileheight")?,
+        margin: attribute_or(node, "margin", 0)?,
+        spacing: attribute_or(node, "spacing", 0)?,
+        columns: attribute_or(node, "columns", 0)?,
+        tile_count: attribute_or(node, "tilecount", 0)?,
+        image: match child_element(node, "image") {
+            Some(image_node) => Some(parse_image(image_node, file_path)?),
+            None => None
+        },
+        tiles
+    })
+}
+
+// Parses the layers in document order, flattening group layers into "output".
+fn parse_layers(parent: Node, file_path: &Path, group: GroupState, output: &mut Vec<ParsedLayer>) -> Result<(), String> {
+    for node in parent.children().filter(|node| node.is_element()) {
+        let name = node.tag_name().name();
+
+        if name == "group" {
+            let group_layer = parse_layer_common(node, file_path, TiledLayerContent::Objects(Vec::new()))?;
+            parse_layers(node, file_path, group.enter(&group_layer), output)?;
+            continue;
+        }
+
+        let mut parsed = match name {
+            "layer" => {
+                let data_node = child_element(node, "data")
+                    .ok_or_else(|| format!("Tile layer '{}' has no <data>", attribute_or(node, "name", String::new()).unwrap_or_default()))?;
+
+                ParsedLayer {
+                    layer: parse_layer_common(node, file_path, TiledLayerContent::Tiles(Vec::new()))?,
+                    chunks: Some(parse_tile_data(node, data_node)?),
+                    skipped_objects: Vec::new()
+                }
+            },
+            "objectgroup" => {
+                let mut skipped_objects = Vec::new();
+                let objects = parse_objects(node, file_path, &mut skipped_objects)?;
+
+                ParsedLayer {
+                    layer: parse_layer_common(node, file_path, TiledLayerContent::Objects(objects))?,
+                    chunks: None,
+                    skipped_objects
+                }
+            },
+            "imagelayer" => {
+                // An image layer without an image is allowed in Tiled, but there is nothing to load
+                let image_node = match child_element(node, "image") {
+                    Some(image_node) => image_node,
+                    None => continue
+                };
+
+                let content = TiledLayerContent::Image {
+                    image: parse_image(image_node, file_path)?,
+                    repeat_x: attribute_or(node, "repeatx", 0)? == 1,
+                    repeat_y: attribute_or(node, "repeaty", 0)? == 1
+                };
+
+                ParsedLayer { layer: parse_layer_common(node, file_path, content)?, chunks: None, skipped_objects: Vec::new() }
+            },
+            _ => continue
+        };
+
+        group.apply(&mut parsed.layer);
+        output.push(parsed);
+    }
+
+    Ok(())
+}
+
+fn parse_layer_common(node: Node, file_path: &Path, content: TiledLayerContent) -> Result<TiledLayer, String> {
+    Ok(TiledLayer {
+        id: attribute_or(node, "id", 0)?,
+        name: attribute_or(node, "name", String::new())?,
+        class: get_class(node),
+        visible: attribute_or(node, "visible", 1)? == 1,
+        opacity: attribute_or(node, "opacity", 1.0)?,
+        tint: optional_color(node, "tintcolor")?.unwrap_or(Color::WHITE),
+        offset_x: attribute_or(node, "offsetx", 0.0)?,
+        offset_y: attribute_or(node, "offsety", 0.0)?,
+        properties: parse_properties(node, file_path)?,
+        content
+    })
+}
+
+// Finite maps have the tiles directly in <data>, infinite maps split them into <chunk> elements.
+fn parse_tile_data(layer_node: Node, data_node: Node) -> Result<Vec<TileChunk>, String> {
+    let encoding = data_node.attribute("encoding");
+    let compression = data_node.attribute("compression");
+
+    let chunk_nodes: Vec<Node> = child_elements(data_node, "chunk").collect();
+
+    if chunk_nodes.is_empty() {
+        return Ok(vec![TileChunk {
+            x: 0,
+            y: 0,
+            width: required_attribute(layer_node, "width")?,
+            height: required_attribute(layer_node, "height")?,
+            gids: read_gids(data_node, encoding, compression)?
+        }]);
+    }
+
+    chunk_nodes.into_iter().map(|chunk_node| {
+        Ok(TileChunk {
+            x: required_attribute(chunk_node, "x")?,
+            y: required_attribute(chunk_node, "y")?,
+            width: required_attribute(chunk_node, "width")?,
+            height: required_attribute(chunk_node, "height")?,
+            gids: read_gids(chunk_node, encoding, compression)?
+        })
+    }).collect()
+}
+
+fn read_gids(node: Node, encoding: Option<&str>, compression: Option<&str>) -> Result<Vec<u32>, String> {
+    let text = node.text().unwrap_or("");
+
+    match encoding {
+        Some("csv") => data::decode_csv(text),
+        Some("base64") => data::decode_base64(text, compression),
+        // The deprecated plain XML format, with a <tile gid="..."/> element per cell
+        None => child_elements(node, "tile").map(|tile_node| attribute_or(tile_node, "gid", 0)).collect(),
+        Some(other) => Err(format!("Unsupported tile data encoding '{}'", other))
+    }
+}
+
+// Objects using a template are left out, and their ids added to "skipped_objects".
+fn parse_objects(object_group: Node, file_path: &Path, skipped_objects: &mut Vec<u32>) -> Result<Vec<TiledObject>, String> {
+    let mut objects = Vec::new();
+
+    for node in child_elements(object_group, "object") {
+        // TODO: Support object templates (.tx files). Until then their objects are left out, so the rest of the map still loads.
+        if node.has_attribute("template") {
+            skipped_objects.push(attribute_or(node, "id", 0)?);
+            continue;
+        }
+
+        objects.push(parse_object(node, file_path)?);
+    }
+
+    Ok(objects)
+}
+
+fn parse_object(node: Node, file_path: &Path) -> Result<TiledObject, String> {
+    let shape = if let Some(gid) = attribute::<u32>(node, "gid")? {
+        ObjectShape::Tile(data::gid_to_tile(gid))
+    } else if child_element(node, "ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if child_element(node, "point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = child_element(node, "polygon") {
+        ObjectShape::Polygon(parse_points(required_attribute::<String>(polygon, "points")?.as_str())?)
+    } else if let Some(polyline) = child_element(node, "polyline") {
+        ObjectShape::Polyline(parse_points(required_attribute::<String>(polyline, "points")?.as_str())?)
+    } else if let Some(text) = child_element(node, "text") {
+        ObjectShape::Text(String::from(text.text().unwrap_or("")))
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    Ok(TiledObject {
+        id: attribute_or(node, "id", 0)?,
+        name: attribute_or(node, "name", String::new())?,
+        class: get_class(node),
+        x: attribute_or(node, "x", 0.0)?,
+        y: attribute_or(node, "y", 0.0)?,
+        width: attribute_or(node, "width", 0.0)?,
+        height: attribute_or(node, "height", 0.0)?,
+        rotation: attribute_or(node, "rotation", 0.0)?,
+        visible: attribute_or(node, "visible", 1)? == 1,
+        shape,
+        properties: parse_properties(node, file_path)?
+    })
+}
+
+// Points are written as "x1,y1 x2,y2 ..."
+fn parse_points(text: &str) -> Result<Vec<Vector2>, String> {
+    text.split_whitespace().map(|point| {
+        let mut coordinates = point.split(',').map(|value| value.parse::<f32>());
+
+        match (coordinates.next(), coordinates.next(), coordinates.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => Ok(Vector2::new(x, y)),
+            _ => Err(format!("Invalid point '{}'", point))
+        }
+    }).collect()
+}
+
+fn parse_image(node: Node, file_path: &Path) -> Result<TiledImage, String> {
+    Ok(TiledImage {
+        source: tiled::resolve_path(file_path, &required_attribute::<String>(node, "source")?),
+        width: attribute_or(node, "width", 0)?,
+        height: attribute_or(node, "height", 0)?
+    })
+}
+
+fn parse_properties(node: Node, file_path: &Path) -> Result<Properties, String> {
+    let mut properties = Properties::new();
+
+    let properties_node = match child_element(node, "properties") {
+        Some(properties_node) => properties_node,
+        None => return Ok(properties)
+    };
+
+    for property_node in child_elements(properties_node, "property") {
+        let name: String = required_attribute(property_node, "name")?;
+        let property_type = property_node.attribute("type").unwrap_or("string");
+
+        // Multi-line strings are stored as the element's text instead of the value attribute
+        let value = property_node.attribute("value").or_else(|| property_node.text()).unwrap_or("");
+
+        let property_value = match property_type {
+            "string" => PropertyValue::String(String::from(value)),
+            "int" => PropertyValue::Int(parse_value(value, &name)?),
+            "float" => PropertyValue::Float(parse_value(value, &name)?),
+            "bool" => PropertyValue::Bool(value == "true"),
+            // An unset color is written as an empty string
+            "color" if value.is_empty() => PropertyValue::Color(Color::TRANSPARENT),
+            "color" => PropertyValue::Color(data::parse_color(value)?),
+            "file" => PropertyValue::File(tiled::resolve_path(file_path, value)),
+            "object" => PropertyValue::Object(parse_value(value, &name)?),
+            "class" => PropertyValue::Class(parse_properties(property_node, file_path)?),
+            other => return Err(format!("Property '{}' has unknown type '{}'", name, other))
+        };
+
+        properties.insert(name, property_value);
+    }
+
+    Ok(properties)
+}
+
+// Tiled 1.9 renamed the "type" attribute to "class"
+fn get_class(node: Node) -> String {
+    String::from(node.attribute("class").or_else(|| node.attribute("type")).unwrap_or(""))
+}
+
+fn child_elements<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child_element<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> Option<Node<'a, 'input>> {
+    child_elements(node, name).next()
+}
+
+fn parse_value<T: FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| format!("Invalid value '{}' for '{}'", value, name))
+}
+
+fn attribute<T: FromStr>(node: Node, name: &str) -> Result<Option<T>, String> {
+    match node.attribute(name) {
+        Some(value) => parse_value(value, name).map(Some)
+            .map_err(|error| format!("{} on <{}>", error, node.tag_name().name())),
+        None => Ok(None)
+    }
+}
+
+fn attribute_or<T: FromStr>(node: Node, name: &str, default: T) -> Result<T, String> {
+    Ok(attribute(node, name)?.unwrap_or(default))
+}
+
+fn required_attribute<T: FromStr>(node: Node, name: &str) -> Result<T, String> {
+    attribute(node, name)?.ok_or_else(|| format!("<{}> is missing the '{}' attribute", node.tag_name().name(), name))
+}
+
+fn optional_color(node: Node, name: &str) -> Result<Option<Color>, String> {
+    match node.attribute(name) {
+        Some(value) => data::parse_color(value).map(Some),
+        None => Ok(None)
+    }
+}
@@ -0,0 +1,85 @@
+use rusty_beagle2d_glfw::ogl;
+
+use std::mem;
+
+// A GPU buffer of u32 element indices.
+// Binding it happens through VertexArray::set_index_buffer, because the element buffer binding
+// Is part of the vertex array state in OpenGL.
+pub struct IndexBuffer {
+    opengl_object_id: ogl::BufferId,
+    usage: ogl::Usage,
+    capacity: usize,
+    len: usize
+}
+
+impl IndexBuffer {
+    pub fn new(capacity: usize, usage: ogl::Usage) -> IndexBuffer {
+        let buffer_object = ogl::gl_gen_buffer();
+        ogl::gl_bind_buffer(ogl::BufferTarget::ElementArrayBuffer, buffer_object);
+        ogl::buffer_data_uninitialized(ogl::BufferTarget::ElementArrayBuffer, IndexBuffer::byte_size(capacity), usage);
+
+        IndexBuffer { opengl_object_id: buffer_object, usage, capacity, len: 0 }
+    }
+
+    pub fn from_indices(indices: &[u32], usage: ogl::Usage) -> IndexBuffer {
+        let buffer_object = ogl::gl_gen_buffer();
+        ogl::gl_bind_buffer(ogl::BufferTarget::ElementArrayBuffer, buffer_object);
+        ogl::buffer_data(ogl::BufferTarget::ElementArrayBuffer, indices, usage);
+
+        IndexBuffer { opengl_object_id: buffer_object, usage, capacity: indices.len(), len: indices.len() }
+    }
+
+    // NOTE: Binding an element buffer while a vertex array is bound attaches it to that vertex array.
+    pub fn bind(&self) {
+        ogl::gl_bind_buffer(ogl::BufferTarget::ElementArrayBuffer, self.opengl_object_id);
+    }
+
+    pub fn set_data(&mut self, indices: &[u32]) {
+        self.bind();
+
+        if indices.len() > self.capacity {
+            ogl::buffer_data(ogl::BufferTarget::ElementArrayBuffer, indices, self.usage);
+            self.capacity = indices.len();
+        } else {
+            ogl::buffer_sub_data(ogl::BufferTarget::ElementArrayBuffer, 0, indices);
+        }
+
+        self.len = indices.len();
+    }
+
+    pub fn update(&mut self, first_index: usize, indices: &[u32]) {
+        if first_index + indices.len() > self.capacity {
+            panic!("Index buffer update out of range: {} indices at {} exceeds capacity {}",
+                indices.len(), first_index, self.capacity);
+        }
+
+        self.bind();
+        ogl::buffer_sub_data(ogl::BufferTarget::ElementArrayBuffer, IndexBuffer::byte_size(first_index), indices);
+
+        self.len = self.len.max(first_index + indices.len());
+    }
+
+    pub fn orphan(&mut self) {
+        self.bind();
+        ogl::buffer_data_uninitialized(ogl::BufferTarget::ElementArrayBuffer, IndexBuffer::byte_size(self.capacity), self.usage);
+        self.len = 0;
+    }
+
+    pub fn get_len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn byte_size(index_count: usize) -> isize {
+        (index_count * mem::size_of::<u32>()) as isize
+    }
+}
+
+impl Drop for IndexBuffer {
+    fn drop(&mut self) {
+        ogl::delete_buffer(self.opengl_object_id);
+    }
+}
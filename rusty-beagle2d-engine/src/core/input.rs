@@ -0,0 +1,205 @@
+use rusty_beagle2d_glfw::glfw;
+use linear_beaglebra::vector2::Vector2;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::os::raw::{c_int, c_uint};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Escape,
+    Backspace,
+    Delete,
+    Tab,
+    Home,
+    End,
+    Space,
+    LeftShift,
+    RightShift,
+    LeftControl,
+    RightControl,
+    // The key left of 1
+    GraveAccent,
+    // 'A' to 'Z'
+    Letter(char),
+    // 1 to 12
+    Function(u8),
+    // Any other key, by its GLFW key code
+    Other(i32)
+}
+
+impl Key {
+    pub fn from_glfw(key: i32) -> Key {
+        let key_code = key as u32;
+
+        match key_code {
+            glfw::GLFW_KEY_LEFT => Key::Left,
+            glfw::GLFW_KEY_RIGHT => Key::Right,
+            glfw::GLFW_KEY_UP => Key::Up,
+            glfw::GLFW_KEY_DOWN => Key::Down,
+            glfw::GLFW_KEY_ENTER | glfw::GLFW_KEY_KP_ENTER => Key::Enter,
+            glfw::GLFW_KEY_ESCAPE => Key::Escape,
+            glfw::GLFW_KEY_BACKSPACE => Key::Backspace,
+            glfw::GLFW_KEY_DELETE => Key::Delete,
+            glfw::GLFW_KEY_TAB => Key::Tab,
+            glfw::GLFW_KEY_HOME => Key::Home,
+            glfw::GLFW_KEY_END => Key::End,
+            glfw::GLFW_KEY_SPACE => Key::Space,
+            glfw::GLFW_KEY_LEFT_SHIFT => Key::LeftShift,
+            glfw::GLFW_KEY_RIGHT_SHIFT => Key::RightShift,
+            glfw::GLFW_KEY_LEFT_CONTROL => Key::LeftControl,
+            glfw::GLFW_KEY_RIGHT_CONTROL => Key::RightControl,
+            glfw::GLFW_KEY_GRAVE_ACCENT => Key::GraveAccent,
+            glfw::GLFW_KEY_A..=glfw::GLFW_KEY_Z => Key::Letter((b'A' + (key_code - glfw::GLFW_KEY_A) as u8) as char),
+            glfw::GLFW_KEY_F1..=glfw::GLFW_KEY_F12 => Key::Function((key_code - glfw::GLFW_KEY_F1 + 1) as u8),
+            _ => Key::Other(key)
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(i32)
+}
+
+impl MouseButton {
+    pub fn from_glfw(button: i32) -> MouseButton {
+        match button as u32 {
+            glfw::GLFW_MOUSE_BUTTON_LEFT => MouseButton::Left,
+            glfw::GLFW_MOUSE_BUTTON_RIGHT => MouseButton::Right,
+            glfw::GLFW_MOUSE_BUTTON_MIDDLE => MouseButton::Middle,
+            _ => MouseButton::Other(button)
+        }
+    }
+}
+
+// Something that happened to the window. Positions are in pixels from the top left corner of the window.
+#[derive(Copy, Clone, Debug)]
+pub enum WindowEvent {
+    // "repeat" is true for the presses sent while a key is held down
+    KeyPressed { key: Key, repeat: bool },
+    KeyReleased { key: Key },
+    // Text typed, with the keyboard layout and shift applied. Comes in addition to the KeyPressed.
+    CharacterTyped(char),
+    MouseMoved { position: Vector2 },
+    MouseButtonPressed { button: MouseButton },
+    MouseButtonReleased { button: MouseButton },
+    // Positive y is scrolling up, away from the user
+    MouseScrolled { delta: Vector2 }
+}
+
+thread_local! {
+    // NOTE: GLFW calls plain extern "C" functions with nowhere else to put the events,
+    // So they wait here until App hands them to the game. GLFW only calls them from the main thread.
+    static PENDING_EVENTS: RefCell<Vec<WindowEvent>> = RefCell::new(Vec::new());
+}
+
+// Makes the window's input end up in the event queue. App does this when it opens its window.
+// NOTE: Setting another callback of the same kind on the window replaces these, and those events stop being queued.
+pub fn install_callbacks(window: *mut glfw::GLFWwindow) {
+    glfw::set_key_callback(window, Some(key_callback));
+    glfw::set_char_callback(window, Some(char_callback));
+    glfw::set_mouse_button_callback(window, Some(mouse_button_callback));
+    glfw::set_cursor_pos_callback(window, Some(cursor_pos_callback));
+    glfw::set_scroll_callback(window, Some(scroll_callback));
+}
+
+// Moves the events that came in since the last call to the end of "events", oldest first.
+pub fn take_events(events: &mut Vec<WindowEvent>) {
+    PENDING_EVENTS.with(|pending| events.append(&mut pending.borrow_mut()));
+}
+
+// Adds an event to the queue as if it came from the window. Handy for scripted input.
+pub fn push_event(event: WindowEvent) {
+    PENDING_EVENTS.with(|pending| pending.borrow_mut().push(event));
+}
+
+extern "C" fn key_callback(_window: *mut glfw::GLFWwindow, key: c_int, _scancode: c_int, action: c_int, _mods: c_int) {
+    let key = Key::from_glfw(key);
+
+    match action as u32 {
+        glfw::GLFW_PRESS => push_event(WindowEvent::KeyPressed { key, repeat: false }),
+        glfw::GLFW_REPEAT => push_event(WindowEvent::KeyPressed { key, repeat: true }),
+        glfw::GLFW_RELEASE => push_event(WindowEvent::KeyReleased { key }),
+        _ => {}
+    }
+}
+
+extern "C" fn char_callback(_window: *mut glfw::GLFWwindow, codepoint: c_uint) {
+    if let Some(character) = std::char::from_u32(codepoint) {
+        push_event(WindowEvent::CharacterTyped(character));
+    }
+}
+
+extern "C" fn mouse_button_callback(_window: *mut glfw::GLFWwindow, button: c_int, action: c_int, _mods: c_int) {
+    let button = MouseButton::from_glfw(button);
+
+    if action as u32 == glfw::GLFW_PRESS {
+        push_event(WindowEvent::MouseButtonPressed { button });
+    } else {
+        push_event(WindowEvent::MouseButtonReleased { button });
+    }
+}
+
+extern "C" fn cursor_pos_callback(_window: *mut glfw::GLFWwindow, x: f64, y: f64) {
+    push_event(WindowEvent::MouseMoved { position: Vector2::new(x as f32, y as f32) });
+}
+
+extern "C" fn scroll_callback(_window: *mut glfw::GLFWwindow, x: f64, y: f64) {
+    push_event(WindowEvent::MouseScrolled { delta: Vector2::new(x as f32, y as f32) });
+}
+
+// What is held down right now, built up from the window events.
+// Feed it every event with handle_event, for example from Game::handle_event.
+pub struct InputState {
+    keys_down: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+    mouse_position: Vector2
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState {
+            keys_down: HashSet::new(),
+            buttons_down: HashSet::new(),
+            mouse_position: Vector2::new(0.0, 0.0)
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::KeyPressed { key, .. } => { self.keys_down.insert(key); },
+            WindowEvent::KeyReleased { key } => { self.keys_down.remove(&key); },
+            WindowEvent::MouseButtonPressed { button } => { self.buttons_down.insert(button); },
+            WindowEvent::MouseButtonReleased { button } => { self.buttons_down.remove(&button); },
+            WindowEvent::MouseMoved { position } => self.mouse_position = position,
+            WindowEvent::CharacterTyped(_) | WindowEvent::MouseScrolled { .. } => {}
+        }
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn get_mouse_position(&self) -> Vector2 {
+        self.mouse_position
+    }
+
+    // Forgets everything held down, for example when the window loses focus and the releases never arrive.
+    pub fn clear(&mut self) {
+        self.keys_down.clear();
+        self.buttons_down.clear();
+    }
+}
@@ -0,0 +1,63 @@
+use linear_beaglebra::vector2::Vector2;
+
+// Small vector helpers the renderer and geometry code need on top of linear-beaglebra's Vector2.
+// TODO: Move these into linear-beaglebra once the API there settles.
+
+pub fn add(a: Vector2, b: Vector2) -> Vector2 {
+    Vector2::new(a.x + b.x, a.y + b.y)
+}
+
+pub fn sub(a: Vector2, b: Vector2) -> Vector2 {
+    Vector2::new(a.x - b.x, a.y - b.y)
+}
+
+pub fn scale(a: Vector2, factor: f32) -> Vector2 {
+    Vector2::new(a.x * factor, a.y * factor)
+}
+
+pub fn dot(a: Vector2, b: Vector2) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+// The z component of the 3D cross product of (a, 0) and (b, 0).
+// Positive when b is counter-clockwise from a in a y-up coordinate system.
+pub fn cross(a: Vector2, b: Vector2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+pub fn length_squared(a: Vector2) -> f32 {
+    dot(a, a)
+}
+
+pub fn length(a: Vector2) -> f32 {
+    length_squared(a).sqrt()
+}
+
+pub fn distance(a: Vector2, b: Vector2) -> f32 {
+    length(sub(a, b))
+}
+
+// Returns the zero vector for zero length input, instead of dividing by zero.
+pub fn normalize(a: Vector2) -> Vector2 {
+    let vector_length = length(a);
+
+    if vector_length <= std::f32::EPSILON {
+        Vector2::new(0.0, 0.0)
+    } else {
+        scale(a, 1.0 / vector_length)
+    }
+}
+
+// The vector rotated 90 degrees.
+pub fn perpendicular(a: Vector2) -> Vector2 {
+    Vector2::new(-a.y, a.x)
+}
+
+pub fn rotate(a: Vector2, radians: f32) -> Vector2 {
+    let (sin, cos) = radians.sin_cos();
+    Vector2::new(a.x * cos - a.y * sin, a.x * sin + a.y * cos)
+}
+
+pub fn degrees_to_radians(degrees: f32) -> f32 {
+    (std::f32::consts::PI / 180.0) * degrees
+}
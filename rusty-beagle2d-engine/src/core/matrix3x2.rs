@@ -0,0 +1,254 @@
+use linear_beaglebra::vector2::Vector2;
+use crate::core::math2d;
+
+// A 2D affine transform: the upper 2x2 part of a matrix holds rotation and scale,
+// And the last column holds the translation.
+//
+// | a  c  tx |
+// | b  d  ty |
+// | 0  0  1  |
+//
+// This is all a 2D sprite needs, at 6 floats instead of the 16 of a Matrix4x4.
+// The chaining functions (translate, rotate, scale) work like the ones on linear-beaglebra's Matrix4x4:
+// Each one multiplies onto the right, so the last one added is applied to a point first.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Matrix3x2 {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32
+}
+
+impl Matrix3x2 {
+    pub fn identity() -> Matrix3x2 {
+        Matrix3x2 { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 }
+    }
+
+    pub fn from_translation(x: f32, y: f32) -> Matrix3x2 {
+        Matrix3x2 { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: x, ty: y }
+    }
+
+    // Angle in degrees, like Sprite::angle.
+    pub fn from_rotation(degrees: f32) -> Matrix3x2 {
+        let (sin, cos) = math2d::degrees_to_radians(degrees).sin_cos();
+        Matrix3x2 { a: cos, b: sin, c: -sin, d: cos, tx: 0.0, ty: 0.0 }
+    }
+
+    pub fn from_scale(x: f32, y: f32) -> Matrix3x2 {
+        Matrix3x2 { a: x, b: 0.0, c: 0.0, d: y, tx: 0.0, ty: 0.0 }
+    }
+
+    // Translation, rotation and scale around a pivot, the same way sprites and scene nodes are placed:
+    // The pivot is moved to (0, 0), scaled, rotated and then moved to the position.
+    pub fn from_transform(position: Vector2, degrees: f32, scale: Vector2, pivot: Vector2) -> Matrix3x2 {
+        Matrix3x2::from_translation(position.x, position.y)
+            .rotate(degrees)
+            .scale(scale.x, scale.y)
+            .translate(Vector2::new(-pivot.x, -pivot.y))
+    }
+
+    // Returns self * other, the transform that applies "other" first and then "self".
+    pub fn multiply(&self, other: &Matrix3x2) -> Matrix3x2 {
+        Matrix3x2 {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            tx: self.a * other.tx + self.c * other.ty + self.tx,
+            ty: self.b * other.tx + self.d * other.ty + self.ty
+        }
+    }
+
+    pub fn translate(&self, translation: Vector2) -> Matrix3x2 {
+        self.multiply(&Matrix3x2::from_translation(translation.x, translation.y))
+    }
+
+    pub fn rotate(&self, degrees: f32) -> Matrix3x2 {
+        self.multiply(&Matrix3x2::from_rotation(degrees))
+    }
+
+    pub fn scale(&self, x: f32, y: f32) -> Matrix3x2 {
+        self.multiply(&Matrix3x2::from_scale(x, y))
+    }
+
+    pub fn transform_point(&self, point: Vector2) -> Vector2 {
+        Vector2::new(
+            self.a * point.x + self.c * point.y + self.tx,
+            self.b * point.x + self.d * point.y + self.ty)
+    }
+
+    // Like transform_point, but ignores the translation. Use for directions and sizes.
+    pub fn transform_vector(&self, vector: Vector2) -> Vector2 {
+        Vector2::new(
+            self.a * vector.x + self.c * vector.y,
+            self.b * vector.x + self.d * vector.y)
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+
+    // The transform that undoes this one. None if it squashes everything onto a line or point (a scale of 0).
+    pub fn inverse(&self) -> Option<Matrix3x2> {
+        let determinant = self.determinant();
+
+        if determinant.abs() < std::f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+
+        Some(Matrix3x2 {
+            a: self.d * inverse_determinant,
+            b: -self.b * inverse_determinant,
+            c: -self.c * inverse_determinant,
+            d: self.a * inverse_determinant,
+            tx: (self.c * self.ty - self.d * self.tx) * inverse_determinant,
+            ty: (self.b * self.tx - self.a * self.ty) * inverse_determinant
+        })
+    }
+
+    pub fn get_translation(&self) -> Vector2 {
+        Vector2::new(self.tx, self.ty)
+    }
+
+    // LEARN: Decomposing a matrix
+    // For a matrix built as rotate(r).scale(sx, sy), the first column is the x axis turned by r and stretched by sx.
+    // So its angle gives the rotation and its length the x scale. The determinant is sx * sy, which gives the y scale,
+    // Including its sign if the matrix mirrors.
+    // NOTE: A matrix with skew (a non-uniformly scaled parent with a rotated child) can't be decomposed exactly.
+    // The rotation and x scale still come out right, but the skew is lost.
+
+    // Degrees, like from_rotation.
+    pub fn get_rotation(&self) -> f32 {
+        self.b.atan2(self.a).to_degrees()
+    }
+
+    pub fn get_scale(&self) -> Vector2 {
+        let scale_x = (self.a * self.a + self.b * self.b).sqrt();
+
+        if scale_x < std::f32::EPSILON {
+            return Vector2::new(0.0, (self.c * self.c + self.d * self.d).sqrt());
+        }
+
+        Vector2::new(scale_x, self.determinant() / scale_x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vector2, expected: (f32, f32)) {
+        assert!((actual.x - expected.0).abs() < 1e-4 && (actual.y - expected.1).abs() < 1e-4,
+            "Expected {:?}, got {:?}", expected, actual);
+    }
+
+    fn assert_matrix_near(actual: &Matrix3x2, expected: &Matrix3x2) {
+        let pairs = [(actual.a, expected.a), (actual.b, expected.b), (actual.c, expected.c), (actual.d, expected.d), (actual.tx, expected.tx), (actual.ty, expected.ty)];
+        assert!(pairs.iter().all(|(a, b)| (a - b).abs() < 1e-4), "Expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn multiply_applies_the_right_hand_side_first() {
+        let translation = Matrix3x2::from_translation(10.0, 0.0);
+        let rotation = Matrix3x2::from_rotation(90.0);
+        let point = Vector2::new(1.0, 0.0);
+
+        // Rotated to (0, 1) first, then moved
+        assert_near(translation.multiply(&rotation).transform_point(point), (10.0, 1.0));
+        // Moved to (11, 0) first, then rotated around the origin
+        assert_near(rotation.multiply(&translation).transform_point(point), (0.0, 11.0));
+    }
+
+    #[test]
+    fn chaining_matches_multiply() {
+        let chained = Matrix3x2::identity().translate(Vector2::new(5.0, 6.0)).rotate(30.0).scale(2.0, 3.0);
+        let multiplied = Matrix3x2::from_translation(5.0, 6.0)
+            .multiply(&Matrix3x2::from_rotation(30.0))
+            .multiply(&Matrix3x2::from_scale(2.0, 3.0));
+
+        assert_matrix_near(&chained, &multiplied);
+    }
+
+    #[test]
+    fn multiply_is_associative() {
+        let first = Matrix3x2::from_rotation(25.0);
+        let second = Matrix3x2::from_scale(2.0, -1.0);
+        let third = Matrix3x2::from_translation(-3.0, 7.0);
+
+        assert_matrix_near(&first.multiply(&second).multiply(&third), &first.multiply(&second.multiply(&third)));
+    }
+
+    #[test]
+    fn rotation_is_clockwise_on_screen() {
+        // With y pointing down, turning right (1, 0) by 90 degrees points down
+        assert_near(Matrix3x2::from_rotation(90.0).transform_point(Vector2::new(1.0, 0.0)), (0.0, 1.0));
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let transform = Matrix3x2::from_transform(Vector2::new(100.0, 50.0), 37.0, Vector2::new(2.0, -0.5), Vector2::new(8.0, 4.0));
+        let inverse = transform.inverse().unwrap();
+
+        assert_matrix_near(&transform.multiply(&inverse), &Matrix3x2::identity());
+        assert_matrix_near(&inverse.multiply(&transform), &Matrix3x2::identity());
+
+        let point = Vector2::new(-12.0, 33.0);
+        let back = inverse.transform_point(transform.transform_point(point));
+        assert_near(back, (point.x, point.y));
+    }
+
+    #[test]
+    fn zero_scale_has_no_inverse() {
+        assert!(Matrix3x2::from_scale(0.0, 1.0).inverse().is_none());
+        assert!(Matrix3x2::from_scale(3.0, 0.0).rotate(45.0).inverse().is_none());
+    }
+
+    #[test]
+    fn from_transform_turns_around_the_pivot() {
+        // A 20x10 rectangle with its pivot in the middle, placed at (100, 100) and turned 90 degrees
+        let transform = Matrix3x2::from_transform(Vector2::new(100.0, 100.0), 90.0, Vector2::new(1.0, 1.0), Vector2::new(10.0, 5.0));
+
+        // The pivot ends up on the position
+        assert_near(transform.transform_point(Vector2::new(10.0, 5.0)), (100.0, 100.0));
+        // The top left corner, 10 left and 5 up of the pivot, ends up 10 up and 5 right of it
+        assert_near(transform.transform_point(Vector2::new(0.0, 0.0)), (105.0, 90.0));
+    }
+
+    #[test]
+    fn from_transform_scales_around_the_pivot() {
+        let transform = Matrix3x2::from_transform(Vector2::new(0.0, 0.0), 0.0, Vector2::new(2.0, 3.0), Vector2::new(4.0, 4.0));
+
+        assert_near(transform.transform_point(Vector2::new(4.0, 4.0)), (0.0, 0.0));
+        assert_near(transform.transform_point(Vector2::new(5.0, 6.0)), (2.0, 6.0));
+        // Sizes ignore the translation and the pivot
+        assert_near(transform.transform_vector(Vector2::new(1.0, 1.0)), (2.0, 3.0));
+    }
+
+    #[test]
+    fn decomposes_rotation_and_scale() {
+        let transform = Matrix3x2::from_transform(Vector2::new(7.0, 8.0), 120.0, Vector2::new(2.0, 3.0), Vector2::new(1.0, 1.0));
+
+        assert!((transform.get_rotation() - 120.0).abs() < 1e-3);
+        assert_near(transform.get_scale(), (2.0, 3.0));
+
+        // Mirroring shows up as a negative y scale
+        let mirrored = Matrix3x2::from_scale(2.0, -4.0);
+        assert_near(mirrored.get_scale(), (2.0, -4.0));
+    }
+
+    #[test]
+    fn parent_and_child_compose() {
+        // A child 10 to the right of a parent that is turned 90 degrees and doubled in size
+        let parent = Matrix3x2::from_transform(Vector2::new(50.0, 50.0), 90.0, Vector2::new(2.0, 2.0), Vector2::new(0.0, 0.0));
+        let child = Matrix3x2::from_translation(10.0, 0.0);
+        let world = parent.multiply(&child);
+
+        assert_near(world.get_translation(), (50.0, 70.0));
+        assert!((world.get_rotation() - 90.0).abs() < 1e-3);
+        assert_near(world.get_scale(), (2.0, 2.0));
+    }
+}
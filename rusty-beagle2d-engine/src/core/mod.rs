@@ -2,4 +2,36 @@ pub mod shader;
 pub mod shader_program;
 pub mod renderer2d;
 pub mod texture;
-pub mod sprite;
\ No newline at end of file
+pub mod sprite;
+pub mod vertex_layout;
+pub mod vertex_buffer;
+pub mod index_buffer;
+pub mod vertex_array;
+pub mod color;
+pub mod math2d;
+pub mod triangulation;
+pub mod batch;
+pub mod blend_mode;
+pub mod matrix3x2;
+pub mod texture_region;
+pub mod animation;
+pub mod texture_atlas;
+pub mod importers;
+pub mod tileset;
+pub mod tilemap;
+pub mod ecs;
+pub mod scene;
+pub mod time;
+pub mod game_loop;
+pub mod app;
+pub mod audio;
+pub mod collision;
+pub mod physics;
+pub mod character_controller;
+pub mod rect;
+pub mod font;
+pub mod input;
+pub mod debug_ui;
+pub mod ui;
+pub mod nine_slice;
+pub mod particles;
\ No newline at end of file
@@ -0,0 +1,417 @@
+use crate::core::texture;
+use crate::core::color::Color;
+use crate::core::blend_mode::BlendMode;
+use crate::core::matrix3x2::Matrix3x2;
+use crate::core::rect::Rect;
+use crate::core::sprite::Origin;
+use crate::core::texture_region::TextureRegion;
+
+use linear_beaglebra::vector2::Vector2;
+
+// Widths of the four borders of a nine slice, in pixels of the untrimmed image.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Insets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32
+}
+
+impl Insets {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Insets {
+        Insets { left, top, right, bottom }
+    }
+
+    pub fn all(amount: f32) -> Insets {
+        Insets::new(amount, amount, amount, amount)
+    }
+
+    pub fn get_horizontal(&self) -> f32 {
+        self.left + self.right
+    }
+
+    pub fn get_vertical(&self) -> f32 {
+        self.top + self.bottom
+    }
+}
+
+// How the edges or the center of a nine slice fill their space.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SliceMode {
+    Stretch,
+    // Repeated at the texture's size, with the last repeat cut off where the space ends.
+    // Edges only repeat along their length.
+    Tile
+}
+
+// One quad of a nine slice.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Patch {
+    // The part of the texture, in pixels
+    pub source: Rect,
+    // Where it goes, in the nine slice's own space from (0, 0) to (width, height)
+    pub target: Rect,
+    // The source is stored turned 90 degrees clockwise, like TextureRegion::rotated
+    pub rotated: bool
+}
+
+// Part of a row or column of patches: where it is in the texture and where it goes.
+#[derive(Copy, Clone)]
+struct Span {
+    source_start: f32,
+    source_length: f32,
+    target_start: f32,
+    target_length: f32
+}
+
+impl Span {
+    // How many patches the span is cut into. Tiled spans repeat the source at its size.
+    fn get_segment_count(&self, tiled: bool) -> usize {
+        if self.source_length <= 0.0 || self.target_length <= 0.0 {
+            return 0;
+        }
+
+        if !tiled {
+            return 1;
+        }
+
+        // A little slack, so rounding errors don't add a sliver of a tile at the end
+        ((self.target_length / self.source_length - 0.001).ceil() as usize).max(1)
+    }
+
+    fn get_segment(&self, tiled: bool, index: usize) -> Span {
+        if !tiled {
+            return *self;
+        }
+
+        let target_start = self.target_start + index as f32 * self.source_length;
+        let length = self.source_length.min(self.target_start + self.target_length - target_start);
+
+        Span { source_start: self.source_start, source_length: length, target_start, target_length: length }
+    }
+
+    // Cuts away the parts of the span whose source is outside "start" to "end", shrinking the target to match.
+    // Used for the transparent border a packer trimmed away, which isn't in the texture.
+    fn clip(&self, start: f32, end: f32) -> Option<Span> {
+        let source_start = self.source_start.max(start);
+        let source_end = (self.source_start + self.source_length).min(end);
+
+        if source_end <= source_start {
+            return None;
+        }
+
+        let scale = self.target_length / self.source_length;
+
+        Some(Span {
+            source_start,
+            source_length: source_end - source_start,
+            target_start: self.target_start + (source_start - self.source_start) * scale,
+            target_length: (source_end - source_start) * scale
+        })
+    }
+}
+
+// An image that can be drawn at any size without stretching its corners, for UI panels and dialogue boxes.
+// The texture region is cut into a 3x3 grid by the insets. The corners are drawn as they are,
+// The edges stretch or tile along their length, and the center fills the rest.
+// When drawn smaller than its borders, the borders shrink to fit.
+// Regions from a packed atlas can be rotated or trimmed. The slicing is then done on the untrimmed image,
+// And the trimmed away parts are left out.
+pub struct NineSlice {
+    pub position_x: f32,
+    pub position_y: f32,
+    // Size it is drawn at, in pixels
+    pub width: f32,
+    pub height: f32,
+    // Degrees, clockwise on screen
+    pub angle: f32,
+    // Relative to width and height, see Sprite::origin
+    pub origin: Origin,
+    pub insets: Insets,
+    pub edge_mode: SliceMode,
+    pub center_mode: SliceMode,
+    // See the fields of the same names on Sprite
+    pub layer: i32,
+    pub tint: Color,
+    pub blend_mode: BlendMode,
+    region: TextureRegion,
+    pub texture: Box<texture::Texture>
+}
+
+impl NineSlice {
+    // Slices the whole texture, and starts out at the texture's size.
+    pub fn new(texture: Box<texture::Texture>, insets: Insets) -> NineSlice {
+        let region = TextureRegion::new(0.0, 0.0, texture.get_width() as f32, texture.get_height() as f32);
+
+        NineSlice {
+            position_x: 0.0,
+            position_y: 0.0,
+            width: region.width,
+            height: region.height,
+            angle: 0.0,
+            origin: Origin::Normalized(0.0, 0.0),
+            insets,
+            edge_mode: SliceMode::Stretch,
+            center_mode: SliceMode::Stretch,
+            layer: 0,
+            tint: Color::WHITE,
+            blend_mode: BlendMode::Alpha,
+            region,
+            texture
+        }
+    }
+
+    // Slices a part of the texture instead, for example a frame from an atlas.
+    // Also sets the size to the region's, before any trimming.
+    pub fn with_region(mut self, region: TextureRegion) -> NineSlice {
+        self.set_region(region);
+        (self.width, self.height) = region.get_source_size();
+        self
+    }
+
+    pub fn with_modes(mut self, edge_mode: SliceMode, center_mode: SliceMode) -> NineSlice {
+        self.edge_mode = edge_mode;
+        self.center_mode = center_mode;
+        self
+    }
+
+    pub fn set_region(&mut self, region: TextureRegion) {
+        self.region = region;
+    }
+
+    pub fn get_region(&self) -> &TextureRegion {
+        &self.region
+    }
+
+    pub fn set_position(&mut self, position_x: f32, position_y: f32) {
+        self.position_x = position_x;
+        self.position_y = position_y;
+    }
+
+    pub fn set_size(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn set_tint(&mut self, tint: Color) {
+        self.tint = tint;
+    }
+
+    // The smallest size the borders fit in without shrinking.
+    pub fn get_min_size(&self) -> (f32, f32) {
+        (self.insets.get_horizontal(), self.insets.get_vertical())
+    }
+
+    // From the nine slice's own space, where it spans (0, 0) to (width, height), into world space.
+    pub fn get_transform(&self) -> Matrix3x2 {
+        let origin = match self.origin {
+            Origin::Normalized(x, y) => Vector2::new(x * self.width, y * self.height),
+            Origin::Pixels(x, y) => Vector2::new(x, y)
+        };
+
+        Matrix3x2::identity()
+            .translate(Vector2::new(self.position_x, self.position_y))
+            .rotate(self.angle)
+            .translate(Vector2::new(-origin.x, -origin.y))
+    }
+
+    // Calls "function" with every quad to draw, row by row from the top left.
+    pub fn for_each_patch<F: FnMut(Patch)>(&self, function: F) {
+        NineSlice::get_patches(&self.region, self.insets, self.width, self.height, self.edge_mode, self.center_mode, function);
+    }
+
+    // The work of for_each_patch, apart from the texture so it can be tested without one.
+    fn get_patches<F: FnMut(Patch)>(region: &TextureRegion, insets: Insets, width: f32, height: f32, edge_mode: SliceMode, center_mode: SliceMode, mut function: F) {
+        let width = width.max(0.0);
+        let height = height.max(0.0);
+
+        // Slice the untrimmed image, then keep only the part of it that is in the texture
+        let (source_width, source_height) = region.get_source_size();
+        let (offset_x, offset_y) = match region.trim {
+            Some(trim) => (trim.offset_x, trim.offset_y),
+            None => (0.0, 0.0)
+        };
+
+        let columns = NineSlice::get_spans(source_width, insets.left, insets.right, width);
+        let rows = NineSlice::get_spans(source_height, insets.top, insets.bottom, height);
+
+        for (row_index, row) in rows.iter().enumerate() {
+            for (column_index, column) in columns.iter().enumerate() {
+                let mode = match (column_index == 1, row_index == 1) {
+                    (true, true) => center_mode,
+                    (false, false) => SliceMode::Stretch,
+                    _ => edge_mode
+                };
+
+                // Corners never tile, edges only along their length
+                let tile_x = mode == SliceMode::Tile && column_index == 1;
+                let tile_y = mode == SliceMode::Tile && row_index == 1;
+
+                for y in 0..row.get_segment_count(tile_y) {
+                    let row_segment = match row.get_segment(tile_y, y).clip(offset_y, offset_y + region.height) {
+                        Some(segment) => segment,
+                        None => continue
+                    };
+
+                    for x in 0..column.get_segment_count(tile_x) {
+                        let column_segment = match column.get_segment(tile_x, x).clip(offset_x, offset_x + region.width) {
+                            Some(segment) => segment,
+                            None => continue
+                        };
+
+                        // Where the patch is inside the region as it is displayed
+                        let left = column_segment.source_start - offset_x;
+                        let top = row_segment.source_start - offset_y;
+
+                        // Turned clockwise, the displayed left edge is the top of the texture and the top is on the right
+                        let source = if region.rotated {
+                            Rect::new(region.x + region.height - top - row_segment.source_length, region.y + left, row_segment.source_length, column_segment.source_length)
+                        } else {
+                            Rect::new(region.x + left, region.y + top, column_segment.source_length, row_segment.source_length)
+                        };
+
+                        function(Patch {
+                            source,
+                            target: Rect::new(column_segment.target_start, row_segment.target_start, column_segment.target_length, row_segment.target_length),
+                            rotated: region.rotated
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // The start border, middle and end border along one axis, with sources measured from the start of the image.
+    fn get_spans(image_length: f32, start_inset: f32, end_inset: f32, length: f32) -> [Span; 3] {
+        let borders = start_inset + end_inset;
+
+        // Both borders shrink by the same factor when they don't fit
+        let border_scale = if borders > length && borders > 0.0 { length / borders } else { 1.0 };
+        let start_length = start_inset * border_scale;
+        let end_length = end_inset * border_scale;
+
+        [
+            Span { source_start: 0.0, source_length: start_inset, target_start: 0.0, target_length: start_length },
+            Span { source_start: start_inset, source_length: image_length - borders, target_start: start_length, target_length: length - start_length - end_length },
+            Span { source_start: image_length - end_inset, source_length: end_inset, target_start: length - end_length, target_length: end_length }
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::texture_region::Trim;
+
+    fn get_patches(region: &TextureRegion, insets: Insets, width: f32, height: f32, edge_mode: SliceMode, center_mode: SliceMode) -> Vec<Patch> {
+        let mut patches = Vec::new();
+        NineSlice::get_patches(region, insets, width, height, edge_mode, center_mode, |patch| patches.push(patch));
+        patches
+    }
+
+    fn get_area(patches: &[Patch]) -> f32 {
+        patches.iter().map(|patch| patch.target.width * patch.target.height).sum()
+    }
+
+    fn patch(source: Rect, target: Rect) -> Patch {
+        Patch { source, target, rotated: false }
+    }
+
+    #[test]
+    fn stretching() {
+        let region = TextureRegion::new(16.0, 16.0, 32.0, 32.0);
+        let patches = get_patches(&region, Insets::new(4.0, 6.0, 8.0, 10.0), 100.0, 50.0, SliceMode::Stretch, SliceMode::Stretch);
+
+        assert_eq!(patches.len(), 9);
+        assert_eq!(patches[0], patch(Rect::new(16.0, 16.0, 4.0, 6.0), Rect::new(0.0, 0.0, 4.0, 6.0)));
+        assert_eq!(patches[4], patch(Rect::new(20.0, 22.0, 20.0, 16.0), Rect::new(4.0, 6.0, 88.0, 34.0)));
+        assert_eq!(patches[8], patch(Rect::new(40.0, 38.0, 8.0, 10.0), Rect::new(92.0, 40.0, 8.0, 10.0)));
+        assert_eq!(get_area(&patches), 100.0 * 50.0);
+    }
+
+    #[test]
+    fn borders_shrink_to_fit() {
+        let region = TextureRegion::new(16.0, 16.0, 32.0, 32.0);
+        let insets = Insets::new(4.0, 6.0, 8.0, 10.0);
+
+        // Only the corners are left, at half and a third of their size
+        let patches = get_patches(&region, insets, 6.0, 8.0, SliceMode::Stretch, SliceMode::Stretch);
+        assert_eq!(patches.len(), 4);
+        assert_eq!(patches[0], patch(Rect::new(16.0, 16.0, 4.0, 6.0), Rect::new(0.0, 0.0, 2.0, 3.0)));
+        assert_eq!(patches[3], patch(Rect::new(40.0, 38.0, 8.0, 10.0), Rect::new(2.0, 3.0, 4.0, 5.0)));
+        assert_eq!(get_area(&patches), 6.0 * 8.0);
+
+        assert!(get_patches(&region, insets, 0.0, 0.0, SliceMode::Stretch, SliceMode::Stretch).is_empty());
+        assert!(get_patches(&region, insets, -5.0, 10.0, SliceMode::Stretch, SliceMode::Stretch).is_empty());
+    }
+
+    #[test]
+    fn tiling() {
+        let region = TextureRegion::new(0.0, 0.0, 30.0, 30.0);
+        let insets = Insets::all(10.0);
+
+        // The 25x20 center takes 3x2 tiles, the top and bottom edges 3 each and the sides 2 each
+        let patches = get_patches(&region, insets, 45.0, 40.0, SliceMode::Tile, SliceMode::Tile);
+        assert_eq!(patches.len(), 4 + 3 * 2 + 2 * 2 + 6);
+        assert_eq!(get_area(&patches), 45.0 * 40.0);
+
+        // The last tile of an edge is cut off where the edge ends
+        assert!(patches.contains(&patch(Rect::new(10.0, 0.0, 10.0, 10.0), Rect::new(20.0, 0.0, 10.0, 10.0))));
+        assert!(patches.contains(&patch(Rect::new(10.0, 0.0, 5.0, 10.0), Rect::new(30.0, 0.0, 5.0, 10.0))));
+        assert!(patches.contains(&patch(Rect::new(20.0, 0.0, 10.0, 10.0), Rect::new(35.0, 0.0, 10.0, 10.0))));
+
+        // No slivers when the tiles fit exactly
+        assert_eq!(get_patches(&region, insets, 50.0, 30.0, SliceMode::Tile, SliceMode::Tile).len(), 4 + 3 * 2 + 2 + 3);
+
+        // Stretched edges around a tiled center
+        assert_eq!(get_patches(&region, insets, 45.0, 40.0, SliceMode::Stretch, SliceMode::Tile).len(), 4 + 4 + 6);
+    }
+
+    #[test]
+    fn rotated_regions() {
+        // Displayed 30 wide and 20 tall, so 20 wide and 30 tall in the texture
+        let mut region = TextureRegion::new(50.0, 20.0, 30.0, 20.0);
+        region.rotated = true;
+
+        let patches = get_patches(&region, Insets::all(5.0), 30.0, 20.0, SliceMode::Stretch, SliceMode::Stretch);
+        assert_eq!(patches.len(), 9);
+        assert!(patches.iter().all(|patch| patch.rotated));
+
+        // Turned clockwise, the top left corner is at the top right of the texture, and so on around
+        assert_eq!(patches[0].source, Rect::new(65.0, 20.0, 5.0, 5.0));
+        assert_eq!(patches[2].source, Rect::new(65.0, 45.0, 5.0, 5.0));
+        assert_eq!(patches[6].source, Rect::new(50.0, 20.0, 5.0, 5.0));
+        assert_eq!(patches[8].source, Rect::new(50.0, 45.0, 5.0, 5.0));
+
+        // The center is taller than it is wide in the texture
+        assert_eq!(patches[4], Patch { source: Rect::new(55.0, 25.0, 10.0, 20.0), target: Rect::new(5.0, 5.0, 20.0, 10.0), rotated: true });
+        assert_eq!(get_area(&patches), 30.0 * 20.0);
+    }
+
+    #[test]
+    fn trimmed_regions() {
+        // A 40x40 image with its left 10 columns and bottom 4 rows trimmed away
+        let mut region = TextureRegion::new(100.0, 100.0, 30.0, 36.0);
+        region.trim = Some(Trim { offset_x: 10.0, offset_y: 0.0, source_width: 40.0, source_height: 40.0 });
+
+        // The middle column is stretched twice as wide, 24 image pixels to 48
+        let patches = get_patches(&region, Insets::all(8.0), 64.0, 40.0, SliceMode::Stretch, SliceMode::Stretch);
+
+        // The left column is all trimmed away, and so is part of the middle one and the bottom row
+        assert_eq!(patches.len(), 6);
+        assert_eq!(patches[0], patch(Rect::new(100.0, 100.0, 22.0, 8.0), Rect::new(12.0, 0.0, 44.0, 8.0)));
+        assert_eq!(patches[1], patch(Rect::new(122.0, 100.0, 8.0, 8.0), Rect::new(56.0, 0.0, 8.0, 8.0)));
+        assert_eq!(patches[2], patch(Rect::new(100.0, 108.0, 22.0, 24.0), Rect::new(12.0, 8.0, 44.0, 24.0)));
+        assert_eq!(patches[5], patch(Rect::new(122.0, 132.0, 8.0, 4.0), Rect::new(56.0, 32.0, 8.0, 4.0)));
+
+        // Every tile repeats the trimmed image, so each tile of the top edge loses its left two pixels
+        let tiled = get_patches(&region, Insets::all(8.0), 64.0, 40.0, SliceMode::Tile, SliceMode::Stretch);
+        assert_eq!(tiled[0], patch(Rect::new(100.0, 100.0, 22.0, 8.0), Rect::new(10.0, 0.0, 22.0, 8.0)));
+        assert_eq!(tiled[1], patch(Rect::new(100.0, 100.0, 22.0, 8.0), Rect::new(34.0, 0.0, 22.0, 8.0)));
+
+        // Both at once
+        region.rotated = true;
+        let patches = get_patches(&region, Insets::all(8.0), 64.0, 40.0, SliceMode::Stretch, SliceMode::Stretch);
+        assert_eq!(patches[2], Patch { source: Rect::new(104.0, 100.0, 24.0, 22.0), target: Rect::new(12.0, 8.0, 44.0, 24.0), rotated: true });
+    }
+}
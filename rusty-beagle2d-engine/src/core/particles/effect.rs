@@ -0,0 +1,487 @@
+// The data side of particle effects: what an effect file contains, and the values ParticleSystem reads from it.
+//
+// An effect file is JSON like:
+// {
+//   "image": "dat/textures/particles.png",
+//   "emitters": [
+//     {
+//       "name": "sparks",
+//       "shape": { "type": "circle", "radius": 8 },
+//       "rate": 200,
+//       "bursts": [ { "time": 0, "count": 50 } ],
+//       "lifetime": [0.5, 1.0],
+//       "speed": [50, 120],
+//       "direction": -90,
+//       "spread": 40,
+//       "size": 6,
+//       "blend_mode": "Additive",
+//       "affectors": [
+//         { "type": "gravity", "x": 0, "y": 300 },
+//         { "type": "color_over_life", "keys": [ { "time": 0, "color": [1, 1, 0.5, 1] }, { "time": 1, "color": [1, 0.2, 0, 0] } ] }
+//       ]
+//     }
+//   ]
+// }
+// Ranges like "lifetime" are either [min, max], picked from at random for every particle, or a single number.
+
+use crate::core::blend_mode::BlendMode;
+use crate::core::color::Color;
+use crate::core::texture::Texture;
+
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ParticleEffect {
+    // Path of the image the particles show, from the working directory like Texture::new.
+    // Without one, particles are plain squares in their color.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    // Drawn in this order, so later emitters are on top
+    pub emitters: Vec<EmitterDefinition>
+}
+
+impl ParticleEffect {
+    pub fn load(path: &Path) -> Result<ParticleEffect, String> {
+        let file_content = std::fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read particle effect {}: {}", path.display(), error))?;
+
+        ParticleEffect::from_json(&file_content)
+            .map_err(|error| format!("Failed to load particle effect {}: {}", path.display(), error))
+    }
+
+    pub fn from_json(json: &str) -> Result<ParticleEffect, String> {
+        let effect: ParticleEffect = serde_json::from_str(json).map_err(|error| error.to_string())?;
+        effect.validate()?;
+        Ok(effect)
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|error| error.to_string())
+    }
+
+    // The texture for a ParticleSystem of this effect, if it has an image.
+    pub fn load_texture(&self) -> Result<Option<Box<Texture>>, String> {
+        match &self.image {
+            Some(image) => {
+                // Texture::new panics on files it can't load, so at least catch the missing ones
+                if !Path::new(image).is_file() {
+                    return Err(format!("Image {} doesn't exist", image));
+                }
+
+                Ok(Some(Box::new(Texture::new(image.clone()))))
+            },
+            None => Ok(None)
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, emitter) in self.emitters.iter().enumerate() {
+            emitter.validate().map_err(|error| format!("Emitter {} ('{}'): {}", index, emitter.name, error))?;
+        }
+
+        Ok(())
+    }
+}
+
+// A value picked at random between two limits, written as [min, max] or as a single number.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(from = "RangeValue", into = "RangeValue")]
+pub struct ValueRange {
+    pub min: f32,
+    pub max: f32
+}
+
+impl ValueRange {
+    pub fn new(min: f32, max: f32) -> ValueRange {
+        ValueRange { min, max }
+    }
+
+    pub fn constant(value: f32) -> ValueRange {
+        ValueRange::new(value, value)
+    }
+
+    // The value a fraction of the way from min to max.
+    pub fn lerp(&self, t: f32) -> f32 {
+        self.min + (self.max - self.min) * t
+    }
+}
+
+// LEARN: Untagged enums
+// Serde tries the variants in order and takes the first that fits, so both 5 and [1, 5] can be read.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RangeValue {
+    Constant(f32),
+    Range([f32; 2])
+}
+
+impl From<RangeValue> for ValueRange {
+    fn from(value: RangeValue) -> ValueRange {
+        match value {
+            RangeValue::Constant(value) => ValueRange::constant(value),
+            RangeValue::Range([min, max]) => ValueRange::new(min, max)
+        }
+    }
+}
+
+impl From<ValueRange> for RangeValue {
+    fn from(range: ValueRange) -> RangeValue {
+        if range.min == range.max {
+            RangeValue::Constant(range.min)
+        } else {
+            RangeValue::Range([range.min, range.max])
+        }
+    }
+}
+
+// Where around the system's position particles start. Shapes turn with the system's angle.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EmitterShape {
+    #[default]
+    Point,
+    // Along a horizontal line through the position
+    Line { length: f32 },
+    // Inside the circle, or only on its outline with "edge"
+    Circle {
+        radius: f32,
+        #[serde(default)]
+        edge: bool
+    },
+    // Inside a rectangle centered on the position, or only on its outline with "edge"
+    Rect {
+        width: f32,
+        height: f32,
+        #[serde(default)]
+        edge: bool
+    }
+}
+
+// A number of particles let out at once.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct Burst {
+    // Seconds after the emitter starts, or after every restart of a looping emitter
+    pub time: f32,
+    pub count: u32
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct ColorKey {
+    // 0.0 when a particle is born, 1.0 when it dies
+    pub time: f32,
+    // r, g, b, a
+    pub color: [f32; 4]
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct SizeKey {
+    // 0.0 when a particle is born, 1.0 when it dies
+    pub time: f32,
+    // Multiplies the size the particle started with
+    pub size: f32
+}
+
+// Changes particles while they live. Applied in the order they are listed.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Affector {
+    // Pixels per second, per second
+    Gravity { x: f32, y: f32 },
+    // How quickly particles slow down. Each second, the velocity is multiplied by e^-amount.
+    Drag { amount: f32 },
+    // Multiplies the emitter's color, blending between the keys
+    ColorOverLife { keys: Vec<ColorKey> },
+    SizeOverLife { keys: Vec<SizeKey> },
+    // Degrees per second, clockwise on screen, picked for every particle
+    Rotation { speed: ValueRange },
+    // Treats the region as a grid of frames, left to right and top to bottom, played over the life of a particle.
+    TextureSheet {
+        columns: u32,
+        rows: u32,
+        // For sheets with empty cells at the end. All cells if left out.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        frame_count: Option<u32>,
+        // Times the frames are played through during a particle's life
+        #[serde(default = "default_cycles")]
+        cycles: f32,
+        // Starts every particle on a random frame
+        #[serde(default)]
+        random_start: bool
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EmitterDefinition {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub shape: EmitterShape,
+    // Particles per second, let out continuously
+    #[serde(default)]
+    pub rate: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bursts: Vec<Burst>,
+    // Seconds the emitter runs for. It runs forever when this is 0.
+    #[serde(default)]
+    pub duration: f32,
+    // Starts over after the duration instead of stopping, bursts included
+    #[serde(default = "default_true")]
+    pub looping: bool,
+    // The size of the particle pool. New particles are skipped while it is full.
+    #[serde(default = "default_max_particles")]
+    pub max_particles: usize,
+    // Seconds
+    pub lifetime: ValueRange,
+    // Pixels per second
+    #[serde(default = "default_zero")]
+    pub speed: ValueRange,
+    // Degrees, clockwise on screen from the right, turned by the system's angle
+    #[serde(default)]
+    pub direction: f32,
+    // Degrees. Particles leave in a cone this wide around the direction.
+    #[serde(default)]
+    pub spread: f32,
+    // How much of the system's own velocity particles start with, from 0.0 to 1.0
+    #[serde(default)]
+    pub inherit_velocity: f32,
+    // Width in pixels. The height follows the shape of the region or frame.
+    pub size: ValueRange,
+    // Degrees the particles start turned by
+    #[serde(default = "default_zero")]
+    pub angle: ValueRange,
+    // r, g, b, a
+    #[serde(default = "default_color")]
+    pub color: [f32; 4],
+    // x, y, width and height of the part of the image to show. The whole image if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<[f32; 4]>,
+    #[serde(default = "default_blend_mode")]
+    pub blend_mode: BlendMode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub affectors: Vec<Affector>
+}
+
+impl EmitterDefinition {
+    pub fn get_color(&self) -> Color {
+        let [r, g, b, a] = self.color;
+        Color::new(r, g, b, a)
+    }
+
+    // Columns, rows and frame count of the texture sheet affector, or a single frame without one.
+    pub fn get_texture_sheet(&self) -> (u32, u32, u32) {
+        self.affectors.iter()
+            .find_map(|affector| match affector {
+                Affector::TextureSheet { columns, rows, frame_count, .. } => Some((*columns, *rows, frame_count.unwrap_or(columns * rows))),
+                _ => None
+            })
+            .unwrap_or((1, 1, 1))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.max_particles == 0 {
+            return Err(String::from("max_particles must be above zero"));
+        }
+
+        if self.lifetime.min <= 0.0 || self.lifetime.max < self.lifetime.min {
+            return Err(String::from("lifetime must be above zero, with max not below min"));
+        }
+
+        if self.rate < 0.0 || self.duration < 0.0 {
+            return Err(String::from("rate and duration can't be negative"));
+        }
+
+        for affector in &self.affectors {
+            match affector {
+                Affector::ColorOverLife { keys } => validate_keys(keys.iter().map(|key| key.time))?,
+                Affector::SizeOverLife { keys } => validate_keys(keys.iter().map(|key| key.time))?,
+                Affector::TextureSheet { columns, rows, frame_count, .. } => {
+                    if *columns == 0 || *rows == 0 {
+                        return Err(String::from("A texture sheet needs at least one column and row"));
+                    }
+
+                    if frame_count.is_some_and(|frame_count| frame_count == 0 || frame_count > columns * rows) {
+                        return Err(format!("A {}x{} texture sheet can't have {} frames", columns, rows, frame_count.unwrap_or(0)));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The color at "time", from 0.0 at the start of a particle's life to 1.0 at the end.
+pub fn sample_color_keys(keys: &[ColorKey], time: f32) -> Color {
+    let (from, to, t) = find_keys(keys, time, |key| key.time);
+    let [r, g, b, a] = keys[from].color;
+    let [to_r, to_g, to_b, to_a] = keys[to].color;
+
+    Color::new(r, g, b, a).lerp(Color::new(to_r, to_g, to_b, to_a), t)
+}
+
+pub fn sample_size_keys(keys: &[SizeKey], time: f32) -> f32 {
+    let (from, to, t) = find_keys(keys, time, |key| key.time);
+    keys[from].size + (keys[to].size - keys[from].size) * t
+}
+
+// The two keys around "time" and how far between them it is. Before the first and after the last key, that key holds.
+fn find_keys<K>(keys: &[K], time: f32, get_time: impl Fn(&K) -> f32) -> (usize, usize, f32) {
+    let next = keys.iter().position(|key| get_time(key) > time).unwrap_or(keys.len());
+
+    if next == 0 {
+        return (0, 0, 0.0);
+    }
+
+    if next == keys.len() {
+        return (next - 1, next - 1, 0.0);
+    }
+
+    let start = get_time(&keys[next - 1]);
+    let end = get_time(&keys[next]);
+
+    (next - 1, next, (time - start) / (end - start))
+}
+
+fn validate_keys(times: impl Iterator<Item = f32>) -> Result<(), String> {
+    let mut previous: Option<f32> = None;
+    let mut count = 0;
+
+    for time in times {
+        if previous.is_some_and(|previous| time < previous) {
+            return Err(String::from("Keys must be in order of time"));
+        }
+
+        previous = Some(time);
+        count += 1;
+    }
+
+    if count == 0 {
+        return Err(String::from("Needs at least one key"));
+    }
+
+    Ok(())
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_particles() -> usize {
+    1000
+}
+
+fn default_zero() -> ValueRange {
+    ValueRange::constant(0.0)
+}
+
+fn default_cycles() -> f32 {
+    1.0
+}
+
+fn default_color() -> [f32; 4] {
+    [1.0, 1.0, 1.0, 1.0]
+}
+
+fn default_blend_mode() -> BlendMode {
+    BlendMode::Alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An effect with one emitter, with "fields" added to its name and size
+    fn parse_emitter(fields: &str) -> Result<ParticleEffect, String> {
+        ParticleEffect::from_json(&format!("{{ \"emitters\": [ {{ \"name\": \"test\", \"size\": 4, {} }} ] }}", fields))
+    }
+
+    #[test]
+    fn ranges_are_a_number_or_two() {
+        let effect = parse_emitter("\"lifetime\": 1, \"speed\": [10, 20], \"angle\": 45").unwrap();
+        let emitter = &effect.emitters[0];
+
+        assert_eq!(emitter.speed, ValueRange::new(10.0, 20.0));
+        assert_eq!(emitter.angle, ValueRange::constant(45.0));
+        assert_eq!(emitter.speed.lerp(0.25), 12.5);
+
+        // Everything left out has its default
+        assert_eq!(emitter.shape, EmitterShape::Point);
+        assert_eq!(emitter.max_particles, 1000);
+        assert!(emitter.looping);
+        assert_eq!(emitter.get_texture_sheet(), (1, 1, 1));
+    }
+
+    #[test]
+    fn bad_definitions_are_rejected() {
+        let bad_fields = [
+            "\"lifetime\": 1, \"max_particles\": 0",
+            "\"lifetime\": 0",
+            "\"lifetime\": [2, 1]",
+            "\"lifetime\": 1, \"rate\": -1",
+            "\"lifetime\": 1, \"duration\": -1",
+            "\"lifetime\": 1, \"affectors\": [ { \"type\": \"color_over_life\", \"keys\": [] } ]",
+            "\"lifetime\": 1, \"affectors\": [ { \"type\": \"size_over_life\", \"keys\": [ { \"time\": 1, \"size\": 1 }, { \"time\": 0, \"size\": 2 } ] } ]",
+            "\"lifetime\": 1, \"affectors\": [ { \"type\": \"texture_sheet\", \"columns\": 0, \"rows\": 2 } ]",
+            "\"lifetime\": 1, \"affectors\": [ { \"type\": \"texture_sheet\", \"columns\": 2, \"rows\": 2, \"frame_count\": 0 } ]",
+            "\"lifetime\": 1, \"affectors\": [ { \"type\": \"texture_sheet\", \"columns\": 2, \"rows\": 2, \"frame_count\": 5 } ]"
+        ];
+
+        for fields in bad_fields.iter() {
+            let error = parse_emitter(fields).expect_err(fields);
+            assert!(error.starts_with("Emitter 0 ('test'): "), "{}", error);
+        }
+
+        // Missing fields the emitter can't do without
+        assert!(ParticleEffect::from_json("{ \"emitters\": [ { \"size\": 4 } ] }").is_err());
+
+        let effect = parse_emitter("\"lifetime\": 1, \"affectors\": [ { \"type\": \"texture_sheet\", \"columns\": 2, \"rows\": 2, \"frame_count\": 3 } ]").unwrap();
+        assert_eq!(effect.emitters[0].get_texture_sheet(), (2, 2, 3));
+    }
+
+    #[test]
+    fn keys_blend_and_hold_at_the_ends() {
+        let keys = [
+            SizeKey { time: 0.25, size: 1.0 },
+            SizeKey { time: 0.75, size: 3.0 }
+        ];
+
+        assert_eq!(sample_size_keys(&keys, 0.0), 1.0);
+        assert_eq!(sample_size_keys(&keys, 0.5), 2.0);
+        assert_eq!(sample_size_keys(&keys, 1.0), 3.0);
+
+        let color_keys = [
+            ColorKey { time: 0.0, color: [1.0, 1.0, 1.0, 1.0] },
+            ColorKey { time: 1.0, color: [0.0, 0.5, 1.0, 0.0] }
+        ];
+
+        let color = sample_color_keys(&color_keys, 0.5);
+        assert_eq!((color.r, color.g, color.b, color.a), (0.5, 0.75, 1.0, 0.5));
+    }
+
+    #[test]
+    fn loading_the_fountain() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../dat/particles/fountain.json");
+        let effect = ParticleEffect::load(&path).unwrap();
+
+        assert_eq!(effect.image, None);
+        assert_eq!(effect.emitters.iter().map(|emitter| emitter.name.as_str()).collect::<Vec<_>>(), vec!["sparks", "smoke"]);
+
+        let sparks = &effect.emitters[0];
+        assert_eq!(sparks.shape, EmitterShape::Line { length: 24.0 });
+        assert_eq!(sparks.lifetime, ValueRange::new(1.0, 1.6));
+        assert_eq!(sparks.blend_mode, BlendMode::Additive);
+        assert_eq!(sparks.affectors[0], Affector::Gravity { x: 0.0, y: 400.0 });
+
+        let smoke = &effect.emitters[1];
+        assert_eq!(smoke.bursts, vec![Burst { time: 0.0, count: 10 }]);
+        assert_eq!(smoke.blend_mode, BlendMode::Alpha);
+
+        // Saving and loading again gives the same effect
+        assert_eq!(ParticleEffect::from_json(&effect.to_json().unwrap()).unwrap(), effect);
+
+        assert!(ParticleEffect::load(Path::new("dat/particles/missing.json")).is_err());
+    }
+}
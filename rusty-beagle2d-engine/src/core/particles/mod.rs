@@ -0,0 +1,2 @@
+pub mod effect;
+pub mod particle_system;
\ No newline at end of file
@@ -0,0 +1,663 @@
+use crate::core::color::Color;
+use crate::core::math2d;
+use crate::core::texture::Texture;
+use crate::core::particles::effect::{self, Affector, EmitterDefinition, EmitterShape, ParticleEffect};
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::rc::Rc;
+
+// One particle, in world space.
+// Kept small and Copy, since a system moves tens of thousands of them around every frame.
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    pub position_x: f32,
+    pub position_y: f32,
+    // Pixels per second
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    // Degrees, clockwise on screen
+    pub angle: f32,
+    // Degrees per second
+    pub angular_velocity: f32,
+    // Seconds
+    pub age: f32,
+    pub lifetime: f32,
+    pub start_size: f32,
+    // Width in pixels
+    pub size: f32,
+    pub color: Color,
+    // Frame of the texture sheet it shows
+    pub frame: u32,
+    frame_offset: u32
+}
+
+impl Particle {
+    // 0.0 when the particle is born, 1.0 when it dies.
+    pub fn get_life_fraction(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+// The live particles of one of an effect's emitters, and how far along its emission is.
+pub struct ParticleEmitter {
+    // NOTE: Allocated at the size of the pool up front and never grown, so updates don't allocate.
+    // Dead particles are swapped out with the last one, which changes the draw order a little.
+    particles: Vec<Particle>,
+    // Seconds since it started, or since its last loop
+    time: f32,
+    // Continuous emission owed but not let out yet, as it is less than one particle
+    emission: f32,
+    next_burst: usize,
+    finished: bool
+}
+
+impl ParticleEmitter {
+    fn new(definition: &EmitterDefinition) -> ParticleEmitter {
+        ParticleEmitter {
+            particles: Vec::with_capacity(definition.max_particles),
+            time: 0.0,
+            emission: 0.0,
+            next_burst: 0,
+            finished: false
+        }
+    }
+
+    pub fn get_particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    // No more particles will be let out, unless it is restarted.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn restart(&mut self) {
+        self.time = 0.0;
+        self.emission = 0.0;
+        self.next_burst = 0;
+        self.finished = false;
+    }
+
+    // Ages and moves the particles, and removes the ones that died.
+    fn simulate(&mut self, definition: &EmitterDefinition, dt: f32) {
+        let mut index = 0;
+        while index < self.particles.len() {
+            self.particles[index].age += dt;
+
+            if self.particles[index].age >= self.particles[index].lifetime {
+                self.particles.swap_remove(index);
+            } else {
+                index += 1;
+            }
+        }
+
+        for affector in &definition.affectors {
+            match affector {
+                Affector::Gravity { x, y } => {
+                    for particle in self.particles.iter_mut() {
+                        particle.velocity_x += x * dt;
+                        particle.velocity_y += y * dt;
+                    }
+                },
+                Affector::Drag { amount } => {
+                    let factor = (-amount * dt).exp();
+
+                    for particle in self.particles.iter_mut() {
+                        particle.velocity_x *= factor;
+                        particle.velocity_y *= factor;
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        for particle in self.particles.iter_mut() {
+            particle.position_x += particle.velocity_x * dt;
+            particle.position_y += particle.velocity_y * dt;
+            particle.angle += particle.angular_velocity * dt;
+
+            ParticleEmitter::apply_over_life(definition, particle);
+        }
+    }
+
+    // Lets out the bursts and continuous emission due in the next "dt" seconds.
+    fn emit(&mut self, definition: &EmitterDefinition, random: &mut Random, motion: &Motion, dt: f32) {
+        if self.finished {
+            return;
+        }
+
+        let mut remaining = dt;
+
+        // A long frame can pass the end of a looping emitter several times, bursts go off every time
+        loop {
+            let step = if definition.duration > 0.0 { remaining.min(definition.duration - self.time) } else { remaining };
+            self.time += step;
+            remaining -= step;
+
+            while self.next_burst < definition.bursts.len() && definition.bursts[self.next_burst].time <= self.time {
+                for _ in 0..definition.bursts[self.next_burst].count {
+                    self.spawn(definition, random, motion, 1.0 - remaining / dt.max(f32::EPSILON));
+                }
+
+                self.next_burst += 1;
+            }
+
+            self.emission += definition.rate * step;
+            let count = self.emission.floor();
+            self.emission -= count;
+
+            // Spread out over the movement during the step, so fast systems leave a trail instead of clumps
+            let step_start = 1.0 - (remaining + step) / dt.max(f32::EPSILON);
+            let step_length = step / dt.max(f32::EPSILON);
+            for index in 0..count as u32 {
+                self.spawn(definition, random, motion, step_start + step_length * (index + 1) as f32 / count);
+            }
+
+            if definition.duration <= 0.0 || self.time < definition.duration {
+                break;
+            }
+
+            if !definition.looping {
+                self.finished = true;
+                break;
+            }
+
+            self.time = 0.0;
+            self.next_burst = 0;
+
+            if remaining <= 0.0 {
+                break;
+            }
+        }
+    }
+
+    // "along" is how far through the frame's movement of the system the particle starts, from 0.0 to 1.0.
+    fn spawn(&mut self, definition: &EmitterDefinition, random: &mut Random, motion: &Motion, along: f32) {
+        // The pool is full
+        if self.particles.len() >= definition.max_particles {
+            return;
+        }
+
+        let angle = math2d::degrees_to_radians(motion.angle);
+        let (shape_x, shape_y) = sample_shape(&definition.shape, random);
+        let offset = math2d::rotate(Vector2::new(shape_x, shape_y), angle);
+
+        let direction = definition.direction + motion.angle + random.range(-0.5, 0.5) * definition.spread;
+        let velocity = math2d::rotate(Vector2::new(definition.speed.lerp(random.next_f32()), 0.0), math2d::degrees_to_radians(direction));
+
+        let mut particle = Particle {
+            position_x: motion.from_x + (motion.to_x - motion.from_x) * along + offset.x,
+            position_y: motion.from_y + (motion.to_y - motion.from_y) * along + offset.y,
+            velocity_x: velocity.x + motion.velocity_x * definition.inherit_velocity,
+            velocity_y: velocity.y + motion.velocity_y * definition.inherit_velocity,
+            angle: definition.angle.lerp(random.next_f32()),
+            angular_velocity: 0.0,
+            age: 0.0,
+            lifetime: definition.lifetime.lerp(random.next_f32()),
+            start_size: definition.size.lerp(random.next_f32()),
+            size: 0.0,
+            color: Color::WHITE,
+            frame: 0,
+            frame_offset: 0
+        };
+
+        for affector in &definition.affectors {
+            match affector {
+                Affector::Rotation { speed } => particle.angular_velocity = speed.lerp(random.next_f32()),
+                Affector::TextureSheet { random_start: true, .. } => {
+                    let (_, _, frame_count) = definition.get_texture_sheet();
+                    particle.frame_offset = random.next_u32() % frame_count;
+                },
+                _ => {}
+            }
+        }
+
+        ParticleEmitter::apply_over_life(definition, &mut particle);
+        self.particles.push(particle);
+    }
+
+    // Sets the color, size and frame for where the particle is in its life.
+    fn apply_over_life(definition: &EmitterDefinition, particle: &mut Particle) {
+        let life = particle.get_life_fraction();
+
+        particle.color = definition.get_color();
+        particle.size = particle.start_size;
+        particle.frame = particle.frame_offset;
+
+        for affector in &definition.affectors {
+            match affector {
+                Affector::ColorOverLife { keys } => particle.color = particle.color.multiply(effect::sample_color_keys(keys, life)),
+                Affector::SizeOverLife { keys } => particle.size = particle.start_size * effect::sample_size_keys(keys, life),
+                Affector::TextureSheet { cycles, .. } => {
+                    let (_, _, frame_count) = definition.get_texture_sheet();
+                    let frame = (life * cycles * frame_count as f32) as u32;
+                    particle.frame = (frame + particle.frame_offset) % frame_count;
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+// A running particle effect: one ParticleEmitter for each of the effect's emitters, moved around as one.
+// Call update every frame with the frame's delta time, and draw it with Renderer2d::draw_particles.
+pub struct ParticleSystem {
+    effect: Rc<ParticleEffect>,
+    texture: Option<Box<Texture>>,
+    emitters: Vec<ParticleEmitter>,
+    position: Vector2,
+    // Where it was at the last update, to work out its velocity from
+    previous_position: Vector2,
+    // Degrees, clockwise on screen. Turns the emitter shapes and directions.
+    angle: f32,
+    emitting: bool,
+    random: Random,
+    // See Sprite::layer
+    pub layer: i32
+}
+
+impl ParticleSystem {
+    // Starts emitting right away. Without a texture, particles are drawn as plain squares.
+    pub fn new(effect: Rc<ParticleEffect>, texture: Option<Box<Texture>>) -> ParticleSystem {
+        let emitters = effect.emitters.iter().map(ParticleEmitter::new).collect();
+
+        // Different every time, so several systems of the same effect don't look the same
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.subsec_nanos());
+
+        ParticleSystem {
+            effect,
+            texture,
+            emitters,
+            position: Vector2::new(0.0, 0.0),
+            previous_position: Vector2::new(0.0, 0.0),
+            angle: 0.0,
+            emitting: true,
+            random: Random::new(seed),
+            layer: 0
+        }
+    }
+
+    // The same seed gives the same particles, for replays and tests.
+    pub fn with_seed(mut self, seed: u32) -> ParticleSystem {
+        self.random = Random::new(seed);
+        self
+    }
+
+    pub fn get_effect(&self) -> &Rc<ParticleEffect> {
+        &self.effect
+    }
+
+    pub fn get_texture(&self) -> Option<&Texture> {
+        self.texture.as_deref()
+    }
+
+    pub fn get_emitters(&self) -> &[ParticleEmitter] {
+        &self.emitters
+    }
+
+    // Moves the system. Particles let out during the next update are spread along the way,
+    // And emitters with inherit_velocity pass on the speed of the movement.
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.position = Vector2::new(x, y);
+    }
+
+    // Moves the system without it counting as movement, for placing it somewhere new.
+    pub fn teleport(&mut self, x: f32, y: f32) {
+        self.position = Vector2::new(x, y);
+        self.previous_position = self.position;
+    }
+
+    pub fn get_position(&self) -> Vector2 {
+        self.position
+    }
+
+    pub fn set_angle(&mut self, angle: f32) {
+        self.angle = angle;
+    }
+
+    pub fn get_angle(&self) -> f32 {
+        self.angle
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+
+        let motion = Motion {
+            from_x: self.previous_position.x,
+            from_y: self.previous_position.y,
+            to_x: self.position.x,
+            to_y: self.position.y,
+            velocity_x: (self.position.x - self.previous_position.x) / dt,
+            velocity_y: (self.position.y - self.previous_position.y) / dt,
+            angle: self.angle
+        };
+
+        for (definition, emitter) in self.effect.emitters.iter().zip(self.emitters.iter_mut()) {
+            emitter.simulate(definition, dt);
+
+            if self.emitting {
+                emitter.emit(definition, &mut self.random, &motion, dt);
+            }
+        }
+
+        self.previous_position = self.position;
+    }
+
+    // Lets out "count" particles from every emitter at once, on top of their own bursts and rate.
+    pub fn burst(&mut self, count: u32) {
+        let motion = Motion {
+            from_x: self.position.x,
+            from_y: self.position.y,
+            to_x: self.position.x,
+            to_y: self.position.y,
+            velocity_x: 0.0,
+            velocity_y: 0.0,
+            angle: self.angle
+        };
+
+        for (definition, emitter) in self.effect.emitters.iter().zip(self.emitters.iter_mut()) {
+            for _ in 0..count {
+                emitter.spawn(definition, &mut self.random, &motion, 1.0);
+            }
+        }
+    }
+
+    // Stops letting out particles. The ones alive live out their lifetime.
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    // Starts emitting from the beginning again, bursts included.
+    pub fn restart(&mut self) {
+        self.emitting = true;
+
+        for emitter in self.emitters.iter_mut() {
+            emitter.restart();
+        }
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting && self.emitters.iter().any(|emitter| !emitter.finished)
+    }
+
+    // Nothing is alive and nothing more will be let out, so the system can be dropped.
+    pub fn is_finished(&self) -> bool {
+        !self.is_emitting() && self.get_particle_count() == 0
+    }
+
+    // Removes every particle.
+    pub fn clear(&mut self) {
+        for emitter in self.emitters.iter_mut() {
+            emitter.particles.clear();
+        }
+    }
+
+    pub fn get_particle_count(&self) -> usize {
+        self.emitters.iter().map(|emitter| emitter.particles.len()).sum()
+    }
+}
+
+// How the system moved during an update.
+struct Motion {
+    from_x: f32,
+    from_y: f32,
+    to_x: f32,
+    to_y: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    angle: f32
+}
+
+// A point in or on the shape, relative to the system's position before it is turned.
+fn sample_shape(shape: &EmitterShape, random: &mut Random) -> (f32, f32) {
+    match *shape {
+        EmitterShape::Point => (0.0, 0.0),
+        EmitterShape::Line { length } => (random.range(-0.5, 0.5) * length, 0.0),
+        EmitterShape::Circle { radius, edge } => {
+            // LEARN: Taking the square root of the random distance spreads points evenly over the area.
+            // Without it they bunch up in the middle, as the inner rings are shorter.
+            let distance = if edge { radius } else { radius * random.next_f32().sqrt() };
+            let angle = random.next_f32() * std::f32::consts::PI * 2.0;
+            (angle.cos() * distance, angle.sin() * distance)
+        },
+        EmitterShape::Rect { width, height, edge: false } => (random.range(-0.5, 0.5) * width, random.range(-0.5, 0.5) * height),
+        EmitterShape::Rect { width, height, edge: true } => {
+            // A point along the outline, going clockwise from the top left corner
+            let distance = random.next_f32() * (width + height) * 2.0;
+
+            if distance < width {
+                (distance - width * 0.5, -height * 0.5)
+            } else if distance < width + height {
+                (width * 0.5, distance - width - height * 0.5)
+            } else if distance < width * 2.0 + height {
+                (width * 1.5 + height - distance, height * 0.5)
+            } else {
+                (-width * 0.5, width * 2.0 + height * 1.5 - distance)
+            }
+        }
+    }
+}
+
+// LEARN: Xorshift
+// A tiny random number generator: a few shifts and xors per number, good enough for effects.
+// It must never be seeded with 0, as it would only return zeros.
+struct Random {
+    state: u32
+}
+
+impl Random {
+    fn new(seed: u32) -> Random {
+        Random { state: if seed == 0 { 0x9E37_79B9 } else { seed } }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    // From 0.0 up to, but not including, 1.0.
+    fn next_f32(&mut self) -> f32 {
+        // The top 24 bits, as that is all the precision an f32 has between 0 and 1
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A system without a texture, of one emitter with "fields" added to its size
+    fn create_system(fields: &str) -> ParticleSystem {
+        let effect = ParticleEffect::from_json(&format!("{{ \"emitters\": [ {{ \"size\": 4, {} }} ] }}", fields)).unwrap();
+        ParticleSystem::new(Rc::new(effect), None).with_seed(1)
+    }
+
+    fn get_particles(system: &ParticleSystem) -> &[Particle] {
+        system.get_emitters()[0].get_particles()
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 0.001, "{} is not {}", value, expected);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_particles() {
+        let fields = "\"shape\": { \"type\": \"circle\", \"radius\": 10 }, \"rate\": 100, \"speed\": [10, 50], \"spread\": 90, \"lifetime\": [0.5, 1], \"affectors\": [ { \"type\": \"rotation\", \"speed\": [-90, 90] } ]";
+
+        let run = |seed: u32| {
+            let mut system = create_system(fields).with_seed(seed);
+
+            for step in 0..20 {
+                system.set_position(step as f32 * 3.0, 0.0);
+                system.update(0.05);
+            }
+
+            get_particles(&system).iter()
+                .map(|particle| (particle.position_x, particle.position_y, particle.angle, particle.age, particle.start_size))
+                .collect::<Vec<_>>()
+        };
+
+        let particles = run(7);
+        assert!(!particles.is_empty());
+        assert_eq!(particles, run(7));
+        assert_ne!(particles, run(8));
+    }
+
+    #[test]
+    fn bursts_at_the_start_let_out_their_count() {
+        let mut system = create_system("\"lifetime\": 10, \"bursts\": [ { \"time\": 0, \"count\": 25 } ]");
+        assert_eq!(system.get_particle_count(), 0);
+
+        system.update(0.001);
+        assert_eq!(system.get_particle_count(), 25);
+
+        // Without a duration the emitter doesn't loop, so the burst doesn't go off again
+        system.update(1.0);
+        assert_eq!(system.get_particle_count(), 25);
+        assert!(system.is_emitting());
+    }
+
+    #[test]
+    fn continuous_emission_carries_fractions_over() {
+        let mut system = create_system("\"lifetime\": 10, \"rate\": 4");
+
+        // 4 particles a second make half, a quarter, one and a quarter and then one particle in each frame
+        let counts: Vec<usize> = [0.125, 0.0625, 0.3125, 0.25, 0.25].iter()
+            .map(|&dt| {
+                system.update(dt);
+                system.get_particle_count()
+            })
+            .collect();
+
+        assert_eq!(counts, vec![0, 0, 2, 3, 4]);
+
+        // Nothing is owed for frames that didn't take time
+        system.update(0.0);
+        assert_eq!(system.get_particle_count(), 4);
+    }
+
+    #[test]
+    fn looping_emitters_burst_every_loop_of_a_long_frame() {
+        let mut system = create_system("\"lifetime\": 10, \"duration\": 0.5, \"bursts\": [ { \"time\": 0, \"count\": 3 } ]");
+
+        // Starts, and loops at 0.5, 1.0 and 1.5 seconds
+        system.update(1.75);
+        assert_eq!(system.get_particle_count(), 12);
+        assert!(!system.get_emitters()[0].is_finished());
+
+        // The loop that starts at the end of a frame bursts in the next one
+        system.update(0.25);
+        assert_eq!(system.get_particle_count(), 12);
+
+        system.update(0.125);
+        assert_eq!(system.get_particle_count(), 15);
+    }
+
+    #[test]
+    fn emitters_that_dont_loop_finish() {
+        let mut system = create_system("\"duration\": 0.5, \"looping\": false, \"lifetime\": 0.5, \"bursts\": [ { \"time\": 0, \"count\": 2 } ]");
+
+        system.update(0.25);
+        assert!(!system.get_emitters()[0].is_finished());
+
+        system.update(0.25);
+        assert!(system.get_emitters()[0].is_finished());
+        assert!(!system.is_emitting());
+
+        // The last particles still have to die
+        assert_eq!(system.get_particle_count(), 2);
+        assert!(!system.is_finished());
+
+        system.update(0.25);
+        assert_eq!(system.get_particle_count(), 0);
+        assert!(system.is_finished());
+
+        // Until it is started again
+        system.restart();
+        assert!(system.is_emitting());
+        system.update(0.125);
+        assert_eq!(system.get_particle_count(), 2);
+    }
+
+    #[test]
+    fn the_pool_never_grows() {
+        let mut system = create_system("\"lifetime\": 10, \"max_particles\": 10, \"rate\": 1000, \"bursts\": [ { \"time\": 0, \"count\": 50 } ]");
+        let capacity = system.get_emitters()[0].particles.capacity();
+        assert_eq!(capacity, 10);
+
+        system.update(0.5);
+        system.burst(20);
+        system.update(0.5);
+
+        assert_eq!(system.get_particle_count(), 10);
+        assert_eq!(system.get_emitters()[0].particles.capacity(), capacity);
+
+        system.stop();
+        system.clear();
+        assert!(system.is_finished());
+    }
+
+    #[test]
+    fn gravity_speeds_particles_up() {
+        let mut system = create_system("\"lifetime\": 10, \"affectors\": [ { \"type\": \"gravity\", \"x\": 0, \"y\": 100 } ]");
+        system.teleport(10.0, 20.0);
+        system.burst(1);
+
+        // The velocity changes before the particle moves
+        system.update(0.5);
+        let particle = get_particles(&system)[0];
+        assert_close(particle.velocity_y, 50.0);
+        assert_close(particle.position_y, 45.0);
+
+        system.update(0.5);
+        let particle = get_particles(&system)[0];
+        assert_close(particle.velocity_y, 100.0);
+        assert_close(particle.position_y, 95.0);
+        assert_close(particle.position_x, 10.0);
+    }
+
+    #[test]
+    fn drag_slows_particles_down() {
+        let mut system = create_system("\"lifetime\": 10, \"speed\": 100, \"direction\": 90, \"affectors\": [ { \"type\": \"drag\", \"amount\": 0.6931472 } ]");
+        system.burst(1);
+
+        // e^-ln(2) halves the velocity every second, however the second is cut up
+        system.update(1.0);
+        assert_close(get_particles(&system)[0].velocity_y, 50.0);
+
+        for _ in 0..4 {
+            system.update(0.25);
+        }
+
+        let particle = get_particles(&system)[0];
+        assert_close(particle.velocity_y, 25.0);
+        assert_close(particle.velocity_x, 0.0);
+    }
+
+    #[test]
+    fn particles_change_over_their_life() {
+        let mut system = create_system("\"lifetime\": 2, \"affectors\": [ { \"type\": \"size_over_life\", \"keys\": [ { \"time\": 0, \"size\": 1 }, { \"time\": 1, \"size\": 0 } ] }, { \"type\": \"texture_sheet\", \"columns\": 2, \"rows\": 2 } ]");
+        system.burst(1);
+        assert_eq!(get_particles(&system)[0].size, 4.0);
+
+        system.update(1.0);
+        let particle = get_particles(&system)[0];
+        assert_eq!(particle.get_life_fraction(), 0.5);
+        assert_eq!(particle.size, 2.0);
+        assert_eq!(particle.frame, 2);
+
+        system.update(1.0);
+        assert_eq!(system.get_particle_count(), 0);
+    }
+}
@@ -0,0 +1,484 @@
+use crate::core::collision::shapes::Shape;
+use crate::core::collision::broadphase::ProxyId;
+use crate::core::sprite::Sprite;
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+// Refers to a body in a PhysicsWorld. Only valid for the world that made it.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct BodyId {
+    pub(crate) index: u32,
+    pub(crate) generation: u32
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BodyType {
+    // Moved by gravity, forces and collisions
+    Dynamic,
+    // Moved only by its velocity, and pushes dynamic bodies out of the way. For moving platforms and doors.
+    Kinematic,
+    // Never moves. For the ground and walls.
+    Static
+}
+
+// Marks an entity whose Transform follows a body. See ecs::systems::step_physics.
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicsBody {
+    pub body: BodyId
+}
+
+// A body in a PhysicsWorld, with a single shape.
+// The shape is relative to the body: a body at (100, 50) with Shape::Circle(Circle::new((0, 0), 16)) is a circle around (100, 50).
+//
+// RigidBody::new(BodyType::Dynamic, Shape::Aabb(Aabb::from_center(Vector2::new(0.0, 0.0), Vector2::new(16.0, 16.0))))
+//     .with_position(Vector2::new(100.0, 50.0))
+//     .with_restitution(0.5)
+#[derive(Clone, Debug)]
+pub struct RigidBody {
+    body_type: BodyType,
+    shape: Shape,
+
+    position: Vector2,
+    // Degrees, clockwise on screen
+    rotation: f32,
+    linear_velocity: Vector2,
+    // Radians per second, for the solver
+    angular_velocity: f32,
+
+    density: f32,
+    friction: f32,
+    restitution: f32,
+    sensor: bool,
+    gravity_scale: f32,
+    linear_damping: f32,
+    angular_damping: f32,
+    fixed_rotation: bool,
+
+    // Worked out from the shape and density
+    pub(crate) mass: f32,
+    pub(crate) inverse_mass: f32,
+    pub(crate) inertia: f32,
+    pub(crate) inverse_inertia: f32,
+    // The center of mass, relative to the position
+    local_center: Vector2,
+
+    force: Vector2,
+    torque: f32,
+
+    // Where the body was before the last step, to interpolate from when rendering
+    previous_position: Vector2,
+    previous_rotation: f32,
+
+    pub(crate) proxy: Option<ProxyId>
+}
+
+impl RigidBody {
+    // A body at (0, 0) with a density of 1, friction of 0.5 and no bounce.
+    pub fn new(body_type: BodyType, shape: Shape) -> RigidBody {
+        let mut body = RigidBody {
+            body_type,
+            shape,
+            position: Vector2::new(0.0, 0.0),
+            rotation: 0.0,
+            linear_velocity: Vector2::new(0.0, 0.0),
+            angular_velocity: 0.0,
+            density: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+            sensor: false,
+            gravity_scale: 1.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            fixed_rotation: false,
+            mass: 0.0,
+            inverse_mass: 0.0,
+            inertia: 0.0,
+            inverse_inertia: 0.0,
+            local_center: Vector2::new(0.0, 0.0),
+            force: Vector2::new(0.0, 0.0),
+            torque: 0.0,
+            previous_position: Vector2::new(0.0, 0.0),
+            previous_rotation: 0.0,
+            proxy: None
+        };
+
+        body.update_mass();
+        body
+    }
+
+    pub fn with_position(mut self, position: Vector2) -> RigidBody {
+        self.set_position(position);
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> RigidBody {
+        self.set_rotation(rotation);
+        self
+    }
+
+    pub fn with_linear_velocity(mut self, velocity: Vector2) -> RigidBody {
+        self.linear_velocity = velocity;
+        self
+    }
+
+    // Degrees per second.
+    pub fn with_angular_velocity(mut self, velocity: f32) -> RigidBody {
+        self.set_angular_velocity(velocity);
+        self
+    }
+
+    // Mass per square pixel. The mass of the body is its area times its density.
+    pub fn with_density(mut self, density: f32) -> RigidBody {
+        self.set_density(density);
+        self
+    }
+
+    // 0.0 is ice. The friction of two bodies touching is the square root of theirs multiplied.
+    pub fn with_friction(mut self, friction: f32) -> RigidBody {
+        self.set_friction(friction);
+        self
+    }
+
+    // How bouncy the body is, 0.0 (no bounce) to 1.0 (bounces back as fast as it came). The bouncier body of two decides.
+    pub fn with_restitution(mut self, restitution: f32) -> RigidBody {
+        self.set_restitution(restitution);
+        self
+    }
+
+    // Sensors don't collide, they only report bodies going in and out of them. See PhysicsWorld::get_trigger_events.
+    pub fn with_sensor(mut self, sensor: bool) -> RigidBody {
+        self.sensor = sensor;
+        self
+    }
+
+    // 0.0 for bodies that float, -1.0 for ones that fall up.
+    pub fn with_gravity_scale(mut self, gravity_scale: f32) -> RigidBody {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+
+    // How quickly the body slows down by itself, like air resistance. 0.0 for none.
+    pub fn with_damping(mut self, linear_damping: f32, angular_damping: f32) -> RigidBody {
+        if linear_damping < 0.0 || angular_damping < 0.0 {
+            panic!("Damping can't be negative, got {} and {}", linear_damping, angular_damping);
+        }
+
+        self.linear_damping = linear_damping;
+        self.angular_damping = angular_damping;
+        self
+    }
+
+    // Bodies with fixed rotation never turn, like most characters.
+    pub fn with_fixed_rotation(mut self, fixed_rotation: bool) -> RigidBody {
+        self.fixed_rotation = fixed_rotation;
+        self.update_mass();
+        self
+    }
+
+    pub fn get_body_type(&self) -> BodyType {
+        self.body_type
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.body_type == BodyType::Dynamic
+    }
+
+    pub fn is_sensor(&self) -> bool {
+        self.sensor
+    }
+
+    // The shape, relative to the body.
+    pub fn get_shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    // The shape where the body is now.
+    pub fn get_world_shape(&self) -> Shape {
+        self.shape
+            .rotated(self.rotation, Vector2::new(0.0, 0.0))
+            .translated(self.position)
+    }
+
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+        self.update_mass();
+    }
+
+    pub fn get_position(&self) -> Vector2 {
+        self.position
+    }
+
+    // Moves the body straight there, without hitting anything on the way.
+    pub fn set_position(&mut self, position: Vector2) {
+        self.position = position;
+        self.previous_position = position;
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+        self.previous_rotation = rotation;
+    }
+
+    pub fn get_linear_velocity(&self) -> Vector2 {
+        self.linear_velocity
+    }
+
+    pub fn set_linear_velocity(&mut self, velocity: Vector2) {
+        if self.body_type != BodyType::Static {
+            self.linear_velocity = velocity;
+        }
+    }
+
+    // Degrees per second.
+    pub fn get_angular_velocity(&self) -> f32 {
+        self.angular_velocity.to_degrees()
+    }
+
+    pub fn set_angular_velocity(&mut self, velocity: f32) {
+        if self.body_type != BodyType::Static {
+            self.angular_velocity = math2d::degrees_to_radians(velocity);
+        }
+    }
+
+    pub fn get_density(&self) -> f32 {
+        self.density
+    }
+
+    pub fn set_density(&mut self, density: f32) {
+        if density.is_nan() || density < 0.0 {
+            panic!("Density can't be negative, got {}", density);
+        }
+
+        self.density = density;
+        self.update_mass();
+    }
+
+    pub fn get_friction(&self) -> f32 {
+        self.friction
+    }
+
+    pub fn set_friction(&mut self, friction: f32) {
+        if friction.is_nan() || friction < 0.0 {
+            panic!("Friction can't be negative, got {}", friction);
+        }
+
+        self.friction = friction;
+    }
+
+    pub fn get_restitution(&self) -> f32 {
+        self.restitution
+    }
+
+    pub fn set_restitution(&mut self, restitution: f32) {
+        if restitution.is_nan() || restitution < 0.0 {
+            panic!("Restitution can't be negative, got {}", restitution);
+        }
+
+        self.restitution = restitution;
+    }
+
+    pub fn get_gravity_scale(&self) -> f32 {
+        self.gravity_scale
+    }
+
+    // 0.0 for kinematic and static bodies.
+    pub fn get_mass(&self) -> f32 {
+        self.mass
+    }
+
+    // How hard the body is to turn, around its center of mass.
+    pub fn get_inertia(&self) -> f32 {
+        self.inertia
+    }
+
+    // Where the center of mass is in the world.
+    pub fn get_center_of_mass(&self) -> Vector2 {
+        math2d::add(self.position, math2d::rotate(self.local_center, math2d::degrees_to_radians(self.rotation)))
+    }
+
+    // The velocity of the point of the body that is at "point" in the world.
+    pub fn get_velocity_at(&self, point: Vector2) -> Vector2 {
+        let offset = math2d::sub(point, self.get_center_of_mass());
+        math2d::add(self.linear_velocity, math2d::scale(math2d::perpendicular(offset), self.angular_velocity))
+    }
+
+    // Pushes on the center of mass during the next step. Forces are cleared after each step.
+    pub fn apply_force(&mut self, force: Vector2) {
+        if self.is_dynamic() {
+            self.force = math2d::add(self.force, force);
+        }
+    }
+
+    // Pushes on the point of the body at "point" in the world, which turns it too.
+    pub fn apply_force_at(&mut self, force: Vector2, point: Vector2) {
+        if self.is_dynamic() {
+            let offset = math2d::sub(point, self.get_center_of_mass());
+            self.force = math2d::add(self.force, force);
+            self.torque += math2d::cross(offset, force);
+        }
+    }
+
+    pub fn apply_torque(&mut self, torque: f32) {
+        if self.is_dynamic() {
+            self.torque += torque;
+        }
+    }
+
+    // Changes the velocity straight away, like a hit or a jump.
+    pub fn apply_impulse(&mut self, impulse: Vector2) {
+        if self.is_dynamic() {
+            self.linear_velocity = math2d::add(self.linear_velocity, math2d::scale(impulse, self.inverse_mass));
+        }
+    }
+
+    pub fn apply_impulse_at(&mut self, impulse: Vector2, point: Vector2) {
+        if self.is_dynamic() {
+            let offset = math2d::sub(point, self.get_center_of_mass());
+            self.linear_velocity = math2d::add(self.linear_velocity, math2d::scale(impulse, self.inverse_mass));
+            self.angular_velocity += math2d::cross(offset, impulse) * self.inverse_inertia;
+        }
+    }
+
+    // Where the body is between the last two steps, with "alpha" from the game loop. See Game::render.
+    pub fn get_interpolated_position(&self, alpha: f32) -> Vector2 {
+        math2d::add(self.previous_position, math2d::scale(math2d::sub(self.position, self.previous_position), alpha))
+    }
+
+    pub fn get_interpolated_rotation(&self, alpha: f32) -> f32 {
+        self.previous_rotation + (self.rotation - self.previous_rotation) * alpha
+    }
+
+    // Moves the sprite to the body. The sprite's origin should be where the body's position is on the sprite,
+    // Usually its center.
+    pub fn apply_to(&self, sprite: &mut Sprite) {
+        sprite.position_x = self.position.x;
+        sprite.position_y = self.position.y;
+        sprite.angle = self.rotation;
+    }
+
+    // Gravity, forces and damping. Called at the start of each step.
+    pub(crate) fn integrate_forces(&mut self, gravity: Vector2, dt: f32) {
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+
+        if self.is_dynamic() {
+            let acceleration = math2d::add(math2d::scale(gravity, self.gravity_scale), math2d::scale(self.force, self.inverse_mass));
+            self.linear_velocity = math2d::add(self.linear_velocity, math2d::scale(acceleration, dt));
+            self.angular_velocity += self.torque * self.inverse_inertia * dt;
+
+            // NOTE: 1 / (1 + dt * damping) rather than (1 - dt * damping), so large damping can't reverse the velocity
+            self.linear_velocity = math2d::scale(self.linear_velocity, 1.0 / (1.0 + dt * self.linear_damping));
+            self.angular_velocity /= 1.0 + dt * self.angular_damping;
+        }
+
+        self.force = Vector2::new(0.0, 0.0);
+        self.torque = 0.0;
+    }
+
+    // Moves the body by its velocity. Called at the end of each step.
+    pub(crate) fn integrate_velocity(&mut self, dt: f32) {
+        if self.body_type == BodyType::Static {
+            return;
+        }
+
+        // The body turns around its center of mass, which isn't always its position
+        let center = math2d::add(self.get_center_of_mass(), math2d::scale(self.linear_velocity, dt));
+        self.rotation += (self.angular_velocity * dt).to_degrees();
+        self.position = math2d::sub(center, math2d::rotate(self.local_center, math2d::degrees_to_radians(self.rotation)));
+    }
+
+    pub(crate) fn get_angular_velocity_radians(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    pub(crate) fn set_velocities(&mut self, linear_velocity: Vector2, angular_velocity: f32) {
+        self.linear_velocity = linear_velocity;
+        self.angular_velocity = angular_velocity;
+    }
+
+    fn update_mass(&mut self) {
+        self.mass = 0.0;
+        self.inverse_mass = 0.0;
+        self.inertia = 0.0;
+        self.inverse_inertia = 0.0;
+        self.local_center = Vector2::new(0.0, 0.0);
+
+        if !self.is_dynamic() {
+            return;
+        }
+
+        let (mass, center, inertia) = get_mass_data(&self.shape, self.density);
+        self.local_center = center;
+
+        // NOTE: A dynamic body with no mass would fly off at the first touch, so it gets a mass of 1
+        if mass > 0.0 {
+            self.mass = mass;
+            self.inertia = inertia;
+        } else {
+            self.mass = 1.0;
+            self.inertia = 1.0;
+        }
+
+        self.inverse_mass = 1.0 / self.mass;
+
+        if !self.fixed_rotation && self.inertia > 0.0 {
+            self.inverse_inertia = 1.0 / self.inertia;
+        }
+    }
+}
+
+// LEARN: Mass and moment of inertia
+// Mass is how hard something is to push, and the moment of inertia how hard it is to turn. Both grow with the area,
+// But the inertia also grows with how far the area is from the center of mass: a long stick is harder to turn than a ball.
+// Returns the mass, the center of mass, and the inertia around the center of mass.
+fn get_mass_data(shape: &Shape, density: f32) -> (f32, Vector2, f32) {
+    let get_box_data = |center: Vector2, half_extents: Vector2| {
+        let (width, height) = (half_extents.x * 2.0, half_extents.y * 2.0);
+        let mass = density * width * height;
+        (mass, center, mass * (width * width + height * height) / 12.0)
+    };
+
+    match shape {
+        Shape::Aabb(aabb) => get_box_data(aabb.get_center(), aabb.get_half_extents()),
+        Shape::Obb(obb) => get_box_data(obb.center, obb.half_extents),
+        Shape::Circle(circle) => {
+            let mass = density * std::f32::consts::PI * circle.radius * circle.radius;
+            (mass, circle.center, mass * circle.radius * circle.radius * 0.5)
+        },
+        Shape::Polygon(polygon) => {
+            let centroid = polygon.get_centroid();
+            let vertices = polygon.get_vertices();
+            let mut inertia = 0.0;
+
+            // Sum the triangles of a fan from the centroid
+            for index in 0..vertices.len() {
+                let a = math2d::sub(vertices[index], centroid);
+                let b = math2d::sub(vertices[(index + 1) % vertices.len()], centroid);
+
+                let squared_x = a.x * a.x + a.x * b.x + b.x * b.x;
+                let squared_y = a.y * a.y + a.y * b.y + b.y * b.y;
+                inertia += math2d::cross(a, b) * (squared_x + squared_y) / 12.0;
+            }
+
+            (density * polygon.get_area(), centroid, density * inertia)
+        },
+        Shape::Capsule(capsule) => {
+            // A box between the ends, and a circle split over the two ends
+            let length = math2d::distance(capsule.start, capsule.end);
+            let width = capsule.radius * 2.0;
+            let box_mass = density * length * width;
+            let circle_mass = density * std::f32::consts::PI * capsule.radius * capsule.radius;
+
+            let box_inertia = box_mass * (length * length + width * width) / 12.0;
+            let circle_inertia = circle_mass * (capsule.radius * capsule.radius * 0.5 + length * length * 0.25);
+
+            let center = math2d::scale(math2d::add(capsule.start, capsule.end), 0.5);
+            (box_mass + circle_mass, center, box_inertia + circle_inertia)
+        }
+    }
+}
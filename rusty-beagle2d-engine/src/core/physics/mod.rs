@@ -0,0 +1,3 @@
+pub mod body;
+mod solver;
+pub mod world;
@@ -0,0 +1,171 @@
+use crate::core::collision::contact::Manifold;
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+// How much overlap is left alone, so resting bodies keep touching instead of jittering in and out of contact
+const ALLOWED_PENETRATION: f32 = 0.5;
+// How much of the overlap is pushed out each step
+const BAUMGARTE: f32 = 0.2;
+// Slower hits than this don't bounce, so resting bodies settle
+const RESTITUTION_THRESHOLD: f32 = 30.0;
+
+// The velocity of a body while the solver runs, indexed the same as the world's body slots.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Velocity {
+    pub linear: Vector2,
+    pub angular: f32
+}
+
+// What the solver needs to know about a body.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct SolverBody {
+    pub index: usize,
+    pub center: Vector2,
+    pub inverse_mass: f32,
+    pub inverse_inertia: f32
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ConstraintPoint {
+    pub point: Vector2,
+    // From each body's center of mass to the point
+    offset_a: Vector2,
+    offset_b: Vector2,
+    normal_mass: f32,
+    tangent_mass: f32,
+    // Velocity the solver aims for along the normal, to push out overlap and bounce
+    bias: f32,
+    // Summed over the step, and kept for the next step
+    pub normal_impulse: f32,
+    pub tangent_impulse: f32
+}
+
+// LEARN: Sequential impulses ("Iterative Dynamics with Temporal Coherence" by Erin Catto)
+// Each contact point is fixed on its own, by an impulse that stops the bodies moving into each other there.
+// Fixing one point can break another, so all points are fixed again a few times, which quickly settles.
+// The total impulse of a point can push, but never pull, so it's clamped to 0 or more. Friction is a second
+// Impulse along the surface, at most the friction times the push.
+#[derive(Clone, Debug)]
+pub(crate) struct ContactConstraint {
+    pub body_a: SolverBody,
+    pub body_b: SolverBody,
+    pub normal: Vector2,
+    friction: f32,
+    pub points: Vec<ConstraintPoint>
+}
+
+impl ContactConstraint {
+    pub fn new(body_a: SolverBody, body_b: SolverBody, manifold: &Manifold, friction: f32, restitution: f32, velocities: &[Velocity], dt: f32) -> ContactConstraint {
+        let normal = manifold.normal;
+        let tangent = math2d::perpendicular(normal);
+        let velocity_a = velocities[body_a.index];
+        let velocity_b = velocities[body_b.index];
+
+        let points = manifold.get_points().iter().map(|contact| {
+            let offset_a = math2d::sub(contact.point, body_a.center);
+            let offset_b = math2d::sub(contact.point, body_b.center);
+
+            let get_effective_mass = |direction: Vector2| {
+                let turn_a = math2d::cross(offset_a, direction);
+                let turn_b = math2d::cross(offset_b, direction);
+                let k = body_a.inverse_mass + body_b.inverse_mass
+                    + body_a.inverse_inertia * turn_a * turn_a
+                    + body_b.inverse_inertia * turn_b * turn_b;
+
+                if k > 0.0 { 1.0 / k } else { 0.0 }
+            };
+
+            let relative_velocity = get_relative_velocity(velocity_a, velocity_b, offset_a, offset_b);
+            let approach_speed = math2d::dot(relative_velocity, normal);
+
+            // Points that are still apart let the bodies close the gap, but no more.
+            // Overlapping points push the bodies apart, a bit at a time so they don't jump.
+            let mut bias = if contact.depth < 0.0 {
+                contact.depth / dt
+            } else {
+                BAUMGARTE / dt * (contact.depth - ALLOWED_PENETRATION).max(0.0)
+            };
+
+            // Only bounce if they'll hit within this step
+            if approach_speed < -RESTITUTION_THRESHOLD && -approach_speed * dt >= -contact.depth {
+                bias = bias.max(-restitution * approach_speed);
+            }
+
+            ConstraintPoint {
+                point: contact.point,
+                offset_a,
+                offset_b,
+                normal_mass: get_effective_mass(normal),
+                tangent_mass: get_effective_mass(tangent),
+                bias,
+                normal_impulse: 0.0,
+                tangent_impulse: 0.0
+            }
+        }).collect();
+
+        ContactConstraint {
+            body_a,
+            body_b,
+            normal,
+            friction,
+            points
+        }
+    }
+
+    // Applies the impulses carried over from the last step, so stacks don't have to settle from scratch every step.
+    pub fn warm_start(&self, velocities: &mut [Velocity]) {
+        let tangent = math2d::perpendicular(self.normal);
+
+        for point in &self.points {
+            let impulse = math2d::add(math2d::scale(self.normal, point.normal_impulse), math2d::scale(tangent, point.tangent_impulse));
+            self.apply_impulse(velocities, point, impulse);
+        }
+    }
+
+    pub fn solve(&mut self, velocities: &mut [Velocity]) {
+        let tangent = math2d::perpendicular(self.normal);
+
+        // Friction first, as the push matters more and so gets the last say
+        for index in 0..self.points.len() {
+            let point = self.points[index];
+            let relative_velocity = get_relative_velocity(velocities[self.body_a.index], velocities[self.body_b.index], point.offset_a, point.offset_b);
+
+            let max_friction = self.friction * point.normal_impulse;
+            let total = (point.tangent_impulse - math2d::dot(relative_velocity, tangent) * point.tangent_mass).clamp(-max_friction, max_friction);
+            let change = total - point.tangent_impulse;
+            self.points[index].tangent_impulse = total;
+
+            self.apply_impulse(velocities, &point, math2d::scale(tangent, change));
+        }
+
+        for index in 0..self.points.len() {
+            let point = self.points[index];
+            let relative_velocity = get_relative_velocity(velocities[self.body_a.index], velocities[self.body_b.index], point.offset_a, point.offset_b);
+
+            let total = (point.normal_impulse + (point.bias - math2d::dot(relative_velocity, self.normal)) * point.normal_mass).max(0.0);
+            let change = total - point.normal_impulse;
+            self.points[index].normal_impulse = total;
+
+            self.apply_impulse(velocities, &point, math2d::scale(self.normal, change));
+        }
+    }
+
+    // Pushes body B by "impulse" and body A the opposite way.
+    fn apply_impulse(&self, velocities: &mut [Velocity], point: &ConstraintPoint, impulse: Vector2) {
+        let a = &mut velocities[self.body_a.index];
+        a.linear = math2d::sub(a.linear, math2d::scale(impulse, self.body_a.inverse_mass));
+        a.angular -= self.body_a.inverse_inertia * math2d::cross(point.offset_a, impulse);
+
+        let b = &mut velocities[self.body_b.index];
+        b.linear = math2d::add(b.linear, math2d::scale(impulse, self.body_b.inverse_mass));
+        b.angular += self.body_b.inverse_inertia * math2d::cross(point.offset_b, impulse);
+    }
+}
+
+// How fast the point of B moves relative to the point of A.
+fn get_relative_velocity(a: Velocity, b: Velocity, offset_a: Vector2, offset_b: Vector2) -> Vector2 {
+    let point_a = math2d::add(a.linear, math2d::scale(math2d::perpendicular(offset_a), a.angular));
+    let point_b = math2d::add(b.linear, math2d::scale(math2d::perpendicular(offset_b), b.angular));
+    math2d::sub(point_b, point_a)
+}
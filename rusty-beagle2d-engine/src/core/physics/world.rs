@@ -0,0 +1,675 @@
+use crate::core::physics::body::{BodyId, BodyType, RigidBody};
+use crate::core::physics::solver::{ContactConstraint, SolverBody, Velocity};
+use crate::core::collision::shapes::{Aabb, Shape};
+use crate::core::collision::contact::{self, Manifold};
+use crate::core::collision::raycast::{self, RaycastHit};
+use crate::core::collision::broadphase::Broadphase;
+use crate::core::collision::aabb_tree::AabbTree;
+use crate::core::math2d;
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+// How far bodies can move before the broadphase tree is updated
+const BROADPHASE_MARGIN: f32 = 4.0;
+// Bodies closer than this count as touching, so they're stopped before they hit rather than after
+const SPECULATIVE_DISTANCE: f32 = 1.0;
+// Contact points closer than this to one from the last step are taken as the same point, for warm starting
+const WARM_START_DISTANCE: f32 = 2.0;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TriggerEvent {
+    // "other" started overlapping "sensor"
+    Enter { sensor: BodyId, other: BodyId },
+    // "other" stopped overlapping "sensor", or one of them was removed
+    Exit { sensor: BodyId, other: BodyId }
+}
+
+struct Contact {
+    manifold: Manifold,
+    // The points and their impulses at the end of the step, to warm start the next step with
+    points: Vec<(Vector2, f32, f32)>
+}
+
+// LEARN: Physics steps
+// Each step:
+// 1. Gravity and forces change the velocities
+// 2. The broadphase finds bodies that might touch, and the exact tests find the ones that do
+// 3. The solver changes the velocities so touching bodies stop moving into each other
+// 4. The bodies move by their velocities
+//
+// NOTE: Bodies are kept in slots and pairs are handled in BodyId order, never in hash map order,
+// So the same bodies and the same steps always give exactly the same result. Replays rely on this.
+//
+// Units are pixels and seconds. Gravity pulls down at 980 pixels per second squared by default,
+// Which feels right for things around 100 pixels tall.
+pub struct PhysicsWorld {
+    gravity: Vector2,
+    velocity_iterations: u32,
+    bodies: Vec<Option<RigidBody>>,
+    generations: Vec<u32>,
+    free: Vec<u32>,
+    body_count: usize,
+    broadphase: AabbTree<BodyId>,
+    contacts: BTreeMap<(BodyId, BodyId), Contact>,
+    // Sensor first
+    triggers: BTreeSet<(BodyId, BodyId)>,
+    trigger_events: Vec<TriggerEvent>,
+    // Events from removed bodies, reported with the next step's events
+    pending_trigger_events: Vec<TriggerEvent>,
+    // Scratch space for step, kept between steps so stepping doesn't allocate once the world has settled
+    shapes: Vec<Option<Shape>>,
+    pairs: Vec<(BodyId, BodyId)>,
+    velocities: Vec<Velocity>,
+    constraints: Vec<ContactConstraint>,
+    manifolds: Vec<((BodyId, BodyId), Manifold)>,
+    new_triggers: BTreeSet<(BodyId, BodyId)>
+}
+
+impl PhysicsWorld {
+    pub fn new() -> PhysicsWorld {
+        PhysicsWorld {
+            gravity: Vector2::new(0.0, 980.0),
+            velocity_iterations: 8,
+            bodies: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+            body_count: 0,
+            broadphase: AabbTree::new(BROADPHASE_MARGIN),
+            contacts: BTreeMap::new(),
+            triggers: BTreeSet::new(),
+            trigger_events: Vec::new(),
+            pending_trigger_events: Vec::new(),
+            shapes: Vec::new(),
+            pairs: Vec::new(),
+            velocities: Vec::new(),
+            constraints: Vec::new(),
+            manifolds: Vec::new(),
+            new_triggers: BTreeSet::new()
+        }
+    }
+
+    pub fn with_gravity(mut self, gravity: Vector2) -> PhysicsWorld {
+        self.gravity = gravity;
+        self
+    }
+
+    // How many times each step the solver goes over the contacts. More is steadier but slower. 8 by default.
+    pub fn with_velocity_iterations(mut self, iterations: u32) -> PhysicsWorld {
+        if iterations == 0 {
+            panic!("The solver needs at least 1 iteration");
+        }
+
+        self.velocity_iterations = iterations;
+        self
+    }
+
+    pub fn get_gravity(&self) -> Vector2 {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vector2) {
+        self.gravity = gravity;
+    }
+
+    pub fn add_body(&mut self, body: RigidBody) -> BodyId {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.bodies.push(None);
+                self.generations.push(0);
+                (self.bodies.len() - 1) as u32
+            }
+        };
+
+        let id = BodyId {
+            index,
+            generation: self.generations[index as usize]
+        };
+
+        let mut body = body;
+        body.proxy = Some(self.broadphase.insert(body.get_world_shape().get_aabb().expanded(SPECULATIVE_DISTANCE * 0.5), id));
+
+        self.bodies[index as usize] = Some(body);
+        self.body_count += 1;
+
+        id
+    }
+
+    // Returns the body, or None if it was already removed. Sensors it was overlapping get an exit event.
+    pub fn remove_body(&mut self, id: BodyId) -> Option<RigidBody> {
+        self.get_body(id)?;
+
+        let mut body = self.bodies[id.index as usize].take().unwrap();
+        self.generations[id.index as usize] = self.generations[id.index as usize].wrapping_add(1);
+        self.free.push(id.index);
+        self.body_count -= 1;
+
+        if let Some(proxy) = body.proxy.take() {
+            self.broadphase.remove(proxy);
+        }
+
+        self.contacts.retain(|&(a, b), _| a != id && b != id);
+
+        let pending = &mut self.pending_trigger_events;
+        self.triggers.retain(|&(sensor, other)| {
+            if sensor == id || other == id {
+                pending.push(TriggerEvent::Exit { sensor, other });
+                return false;
+            }
+
+            true
+        });
+
+        Some(body)
+    }
+
+    pub fn contains(&self, id: BodyId) -> bool {
+        self.get_body(id).is_some()
+    }
+
+    pub fn get_body(&self, id: BodyId) -> Option<&RigidBody> {
+        if self.generations.get(id.index as usize) != Some(&id.generation) {
+            return None;
+        }
+
+        self.bodies[id.index as usize].as_ref()
+    }
+
+    // Changes to the body take effect in the next step.
+    pub fn get_body_mut(&mut self, id: BodyId) -> Option<&mut RigidBody> {
+        if self.generations.get(id.index as usize) != Some(&id.generation) {
+            return None;
+        }
+
+        self.bodies[id.index as usize].as_mut()
+    }
+
+    pub fn get_body_count(&self) -> usize {
+        self.body_count
+    }
+
+    // Every body, in the order they're updated.
+    pub fn get_bodies(&self) -> Vec<BodyId> {
+        (0..self.bodies.len() as u32)
+            .filter(|&index| self.bodies[index as usize].is_some())
+            .map(|index| self.get_id(index))
+            .collect()
+    }
+
+    // Sensor events from the last step, sensors in BodyId order.
+    pub fn get_trigger_events(&self) -> &[TriggerEvent] {
+        &self.trigger_events
+    }
+
+    // Calls "f" with every pair of touching bodies from the last step, and how they touch. Sensors aren't included.
+    pub fn for_each_contact<F: FnMut(BodyId, BodyId, &Manifold)>(&self, mut f: F) {
+        for (&(a, b), contact) in self.contacts.iter() {
+            // Bodies that were only about to touch don't count
+            if contact.manifold.depth >= 0.0 {
+                f(a, b, &contact.manifold);
+            }
+        }
+    }
+
+    // The closest body a ray hits, if any, ignoring sensors.
+    pub fn raycast(&self, origin: Vector2, direction: Vector2, max_distance: f32) -> Option<(BodyId, RaycastHit)> {
+        let mut closest = None;
+
+        self.broadphase.raycast(origin, direction, max_distance, |proxy, max_distance| {
+            let id = *self.broadphase.get_data(proxy).unwrap();
+            let body = self.get_body(id).unwrap();
+
+            if body.is_sensor() {
+                return None;
+            }
+
+            let hit = raycast::raycast(&body.get_world_shape(), origin, direction, max_distance)?;
+            closest = Some((id, hit));
+            Some(hit.distance)
+        })?;
+
+        closest
+    }
+
+    // Bodies whose shapes overlap "region", in BodyId order.
+    pub fn query_region(&self, region: &Aabb) -> Vec<BodyId> {
+        let region_shape = Shape::Aabb(*region);
+        let mut ids = Vec::new();
+
+        self.broadphase.for_each_in_region(region, |proxy| {
+            let id = *self.broadphase.get_data(proxy).unwrap();
+
+            if contact::intersects(&self.get_body(id).unwrap().get_world_shape(), &region_shape) {
+                ids.push(id);
+            }
+        });
+
+        ids.sort();
+        ids
+    }
+
+    // Advances the world by "dt" seconds. Call it from Game::fixed_update, with the fixed update's dt.
+    pub fn step(&mut self, dt: f32) {
+        self.trigger_events.clear();
+        self.trigger_events.append(&mut self.pending_trigger_events);
+
+        if dt <= 0.0 {
+            return;
+        }
+
+        for body in self.bodies.iter_mut().flatten() {
+            body.integrate_forces(self.gravity, dt);
+        }
+
+        // Where every body is now, by slot
+        self.shapes.clear();
+        self.shapes.extend(self.bodies.iter().map(|body| body.as_ref().map(|body| body.get_world_shape())));
+
+        for (body, shape) in self.bodies.iter().zip(self.shapes.iter()) {
+            if let (Some(body), Some(shape)) = (body, shape) {
+                self.broadphase.move_proxy(body.proxy.unwrap(), shape.get_aabb().expanded(SPECULATIVE_DISTANCE * 0.5));
+            }
+        }
+
+        // Taken out while it's gone over, since the loop changes other fields
+        let mut pairs = std::mem::take(&mut self.pairs);
+        self.find_pairs(&mut pairs);
+
+        self.velocities.clear();
+        self.velocities.extend(self.bodies.iter()
+            .map(|body| match body {
+                Some(body) => Velocity { linear: body.get_linear_velocity(), angular: body.get_angular_velocity_radians() },
+                None => Velocity { linear: Vector2::new(0.0, 0.0), angular: 0.0 }
+            }));
+
+        self.constraints.clear();
+        self.manifolds.clear();
+        self.new_triggers.clear();
+
+        for &(a, b) in pairs.iter() {
+            let body_a = self.bodies[a.index as usize].as_ref().unwrap();
+            let body_b = self.bodies[b.index as usize].as_ref().unwrap();
+
+            let (shape_a, shape_b) = (self.shapes[a.index as usize].as_ref().unwrap(), self.shapes[b.index as usize].as_ref().unwrap());
+
+            if body_a.is_sensor() || body_b.is_sensor() {
+                if contact::intersects(shape_a, shape_b) {
+                    self.new_triggers.insert(if body_a.is_sensor() { (a, b) } else { (b, a) });
+                }
+
+                continue;
+            }
+
+            let manifold = match contact::collide_speculative(shape_a, shape_b, SPECULATIVE_DISTANCE) {
+                Some(manifold) => manifold,
+                None => continue
+            };
+
+            let friction = (body_a.get_friction() * body_b.get_friction()).sqrt();
+            let restitution = body_a.get_restitution().max(body_b.get_restitution());
+
+            let mut constraint = ContactConstraint::new(
+                get_solver_body(a, body_a),
+                get_solver_body(b, body_b),
+                &manifold,
+                friction,
+                restitution,
+                &self.velocities,
+                dt);
+
+            // Carry over the impulses of points that are still about where they were
+            if let Some(previous) = self.contacts.get(&(a, b)) {
+                for point in constraint.points.iter_mut() {
+                    let matching = previous.points.iter()
+                        .find(|(previous_point, _, _)| math2d::distance(*previous_point, point.point) <= WARM_START_DISTANCE);
+
+                    if let Some(&(_, normal_impulse, tangent_impulse)) = matching {
+                        point.normal_impulse = normal_impulse;
+                        point.tangent_impulse = tangent_impulse;
+                    }
+                }
+            }
+
+            self.constraints.push(constraint);
+            self.manifolds.push(((a, b), manifold));
+        }
+
+        self.pairs = pairs;
+
+        for constraint in &self.constraints {
+            constraint.warm_start(&mut self.velocities);
+        }
+
+        for _ in 0..self.velocity_iterations {
+            for constraint in self.constraints.iter_mut() {
+                constraint.solve(&mut self.velocities);
+            }
+        }
+
+        self.contacts.clear();
+
+        for (constraint, &(pair, manifold)) in self.constraints.iter().zip(self.manifolds.iter()) {
+            self.contacts.insert(pair, Contact {
+                manifold,
+                points: constraint.points.iter().map(|point| (point.point, point.normal_impulse, point.tangent_impulse)).collect()
+            });
+        }
+
+        for (body, velocity) in self.bodies.iter_mut().zip(self.velocities.iter()) {
+            if let Some(body) = body {
+                if body.is_dynamic() {
+                    body.set_velocities(velocity.linear, velocity.angular);
+                }
+
+                body.integrate_velocity(dt);
+            }
+        }
+
+        self.update_triggers();
+    }
+
+    // Fills "pairs" with the bodies whose boxes overlap and that can affect each other, lower BodyId first, sorted
+    fn find_pairs(&self, pairs: &mut Vec<(BodyId, BodyId)>) {
+        pairs.clear();
+
+        self.broadphase.for_each_pair(|proxy_a, proxy_b| {
+            let a = *self.broadphase.get_data(proxy_a).unwrap();
+            let b = *self.broadphase.get_data(proxy_b).unwrap();
+            let body_a = self.get_body(a).unwrap();
+            let body_b = self.get_body(b).unwrap();
+
+            // NOTE: Sensors don't detect each other, and bodies that can't be pushed don't collide with each other.
+            // But a moving kinematic body still triggers sensors.
+            if body_a.is_sensor() && body_b.is_sensor() {
+                return;
+            }
+
+            let either_dynamic = body_a.is_dynamic() || body_b.is_dynamic();
+            let either_sensor = body_a.is_sensor() || body_b.is_sensor();
+            let either_kinematic = body_a.get_body_type() == BodyType::Kinematic || body_b.get_body_type() == BodyType::Kinematic;
+
+            if either_dynamic || (either_sensor && either_kinematic) {
+                pairs.push(if a < b { (a, b) } else { (b, a) });
+            }
+        });
+
+        // The broadphase finds pairs in whatever order its tree is in
+        pairs.sort();
+    }
+
+    // Compares the sensor overlaps found this step (new_triggers) with the last step's
+    fn update_triggers(&mut self) {
+        for &(sensor, other) in self.new_triggers.difference(&self.triggers) {
+            self.trigger_events.push(TriggerEvent::Enter { sensor, other });
+        }
+
+        for &(sensor, other) in self.triggers.difference(&self.new_triggers) {
+            self.trigger_events.push(TriggerEvent::Exit { sensor, other });
+        }
+
+        std::mem::swap(&mut self.triggers, &mut self.new_triggers);
+    }
+
+    fn get_id(&self, index: u32) -> BodyId {
+        BodyId {
+            index,
+            generation: self.generations[index as usize]
+        }
+    }
+}
+
+fn get_solver_body(id: BodyId, body: &RigidBody) -> SolverBody {
+    SolverBody {
+        index: id.index as usize,
+        center: body.get_center_of_mass(),
+        inverse_mass: body.inverse_mass,
+        inverse_inertia: body.inverse_inertia
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::collision::shapes::{Circle, Obb, Polygon, Capsule};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn v(x: f32, y: f32) -> Vector2 {
+        Vector2::new(x, y)
+    }
+
+    fn square(size: f32) -> Shape {
+        Shape::Aabb(Aabb::new(v(-size * 0.5, -size * 0.5), v(size * 0.5, size * 0.5)))
+    }
+
+    fn circle(radius: f32) -> Shape {
+        Shape::Circle(Circle::new(v(0.0, 0.0), radius))
+    }
+
+    // A floor and walls, with a pile of every kind of shape falling, bouncing and rolling on it
+    fn make_scene() -> PhysicsWorld {
+        let mut world = PhysicsWorld::new();
+
+        world.add_body(RigidBody::new(BodyType::Static, Shape::Aabb(Aabb::new(v(-400.0, 0.0), v(400.0, 40.0)))).with_position(v(0.0, 400.0)));
+        world.add_body(RigidBody::new(BodyType::Static, Shape::Aabb(Aabb::new(v(0.0, -400.0), v(40.0, 0.0)))).with_position(v(-440.0, 400.0)));
+        world.add_body(RigidBody::new(BodyType::Static, Shape::Aabb(Aabb::new(v(0.0, -400.0), v(40.0, 0.0)))).with_position(v(400.0, 400.0)));
+        world.add_body(RigidBody::new(BodyType::Static, Shape::Obb(Obb::new(v(0.0, 0.0), v(120.0, 10.0), 20.0))).with_position(v(-200.0, 250.0)));
+
+        for row in 0..4 {
+            for column in 0..6 {
+                let position = v(-150.0 + column as f32 * 50.0 + row as f32 * 7.0, 100.0 - row as f32 * 60.0);
+
+                let shape = match (row + column) % 4 {
+                    0 => square(30.0),
+                    1 => circle(14.0),
+                    2 => Shape::Polygon(Polygon::new(&[v(-15.0, 10.0), v(0.0, -15.0), v(15.0, 10.0)])),
+                    _ => Shape::Capsule(Capsule::new(v(-12.0, 0.0), v(12.0, 0.0), 8.0))
+                };
+
+                world.add_body(RigidBody::new(BodyType::Dynamic, shape)
+                    .with_position(position)
+                    .with_rotation(column as f32 * 13.0)
+                    .with_linear_velocity(v(column as f32 * 20.0 - 50.0, 0.0))
+                    .with_restitution(if row == 0 { 0.5 } else { 0.0 })
+                    .with_friction(0.2 + 0.1 * column as f32));
+            }
+        }
+
+        // A platform moving through the pile, and a sensor it goes through
+        world.add_body(RigidBody::new(BodyType::Kinematic, Shape::Aabb(Aabb::new(v(-40.0, -5.0), v(40.0, 5.0))))
+            .with_position(v(-300.0, 320.0))
+            .with_linear_velocity(v(60.0, 0.0)));
+        world.add_body(RigidBody::new(BodyType::Static, square(100.0)).with_position(v(0.0, 330.0)).with_sensor(true));
+
+        world
+    }
+
+    // Every body's state as bits, so "close enough" doesn't count
+    type StateBits = Vec<(BodyId, [u32; 6])>;
+
+    fn get_state_bits(world: &PhysicsWorld) -> StateBits {
+        world.get_bodies().into_iter().map(|id| {
+            let body = world.get_body(id).unwrap();
+            let (position, velocity) = (body.get_position(), body.get_linear_velocity());
+
+            (id, [
+                position.x.to_bits(),
+                position.y.to_bits(),
+                body.get_rotation().to_bits(),
+                velocity.x.to_bits(),
+                velocity.y.to_bits(),
+                body.get_angular_velocity().to_bits()
+            ])
+        }).collect()
+    }
+
+    // Steps the scene, removing and adding bodies along the way, and records every state and event
+    fn run_scene() -> (Vec<StateBits>, Vec<TriggerEvent>) {
+        let mut world = make_scene();
+        let mut states = Vec::new();
+        let mut events = Vec::new();
+
+        for step in 0..300 {
+            if step == 100 {
+                let removed = world.get_bodies()[10];
+                world.remove_body(removed);
+            }
+
+            if step == 150 {
+                world.add_body(RigidBody::new(BodyType::Dynamic, circle(20.0)).with_position(v(0.0, -100.0)));
+            }
+
+            world.step(DT);
+            states.push(get_state_bits(&world));
+            events.extend_from_slice(world.get_trigger_events());
+        }
+
+        (states, events)
+    }
+
+    #[test]
+    fn same_scene_gives_the_same_result() {
+        let (first_states, first_events) = run_scene();
+        let (second_states, second_events) = run_scene();
+
+        for (step, (first, second)) in first_states.iter().zip(second_states.iter()).enumerate() {
+            assert_eq!(first, second, "Bodies differ after step {}", step + 1);
+        }
+
+        assert_eq!(first_events, second_events);
+
+        // The pile has to have done something for this to mean anything
+        assert!(!first_events.is_empty());
+        assert_ne!(first_states[0], first_states[299]);
+    }
+
+    #[test]
+    fn stepping_doesnt_depend_on_what_was_stepped_before() {
+        // The scratch space from a busy world is reused by later steps, and can't leak into them
+        let mut world = make_scene();
+
+        for _ in 0..50 {
+            world.step(DT);
+        }
+
+        for id in world.get_bodies() {
+            world.remove_body(id);
+        }
+
+        world.step(DT);
+
+        let mut fresh = make_scene();
+
+        // Freed slots are reused last one first, so adding backwards puts every body back in its slot
+        for id in fresh.get_bodies().into_iter().rev() {
+            let mut body = fresh.get_body(id).unwrap().clone();
+            body.proxy = None;
+            assert_eq!(world.add_body(body).index, id.index);
+        }
+
+        for _ in 0..100 {
+            world.step(DT);
+            fresh.step(DT);
+        }
+
+        let bits = |world: &PhysicsWorld| get_state_bits(world).into_iter().map(|(_, bits)| bits).collect::<Vec<_>>();
+        assert_eq!(bits(&world), bits(&fresh));
+    }
+
+    // A sensor box from (100, 0) to (120, 20), with nothing pulling things down
+    fn make_sensor_world() -> (PhysicsWorld, BodyId) {
+        let mut world = PhysicsWorld::new().with_gravity(v(0.0, 0.0));
+        let sensor = world.add_body(RigidBody::new(BodyType::Static, Shape::Aabb(Aabb::new(v(0.0, 0.0), v(20.0, 20.0))))
+            .with_position(v(100.0, 0.0))
+            .with_sensor(true));
+
+        (world, sensor)
+    }
+
+    // The events of each step, with the step's number
+    fn run_steps(world: &mut PhysicsWorld, steps: u32, dt: f32) -> Vec<(u32, TriggerEvent)> {
+        let mut events = Vec::new();
+
+        for step in 1..=steps {
+            world.step(dt);
+            events.extend(world.get_trigger_events().iter().map(|&event| (step, event)));
+        }
+
+        events
+    }
+
+    #[test]
+    fn bodies_passing_through_enter_and_exit_once() {
+        let (mut world, sensor) = make_sensor_world();
+        let other = world.add_body(RigidBody::new(BodyType::Dynamic, circle(5.0)).with_position(v(50.0, 10.0)).with_linear_velocity(v(100.0, 0.0)));
+
+        // Overlaps are found before moving, so step 6 sees the circle at x = 100, and step 9 at x = 130
+        let events = run_steps(&mut world, 15, 0.1);
+        assert_eq!(events, vec![
+            (6, TriggerEvent::Enter { sensor, other }),
+            (9, TriggerEvent::Exit { sensor, other })
+        ]);
+
+        // Sensors don't push back
+        assert_eq!(world.get_body(other).unwrap().get_linear_velocity().x, 100.0);
+    }
+
+    #[test]
+    fn removing_the_other_body_exits() {
+        let (mut world, sensor) = make_sensor_world();
+        let other = world.add_body(RigidBody::new(BodyType::Dynamic, circle(5.0)).with_position(v(110.0, 10.0)));
+
+        assert_eq!(run_steps(&mut world, 3, DT), vec![(1, TriggerEvent::Enter { sensor, other })]);
+
+        world.remove_body(other);
+        // Reported with the next step's events
+        assert!(world.get_trigger_events().is_empty());
+        assert_eq!(run_steps(&mut world, 3, DT), vec![(1, TriggerEvent::Exit { sensor, other })]);
+
+        // A new body in the removed body's slot is a new body
+        let new_other = world.add_body(RigidBody::new(BodyType::Dynamic, circle(5.0)).with_position(v(110.0, 10.0)));
+        assert_ne!(new_other, other);
+        assert_eq!(run_steps(&mut world, 1, DT), vec![(1, TriggerEvent::Enter { sensor, other: new_other })]);
+    }
+
+    #[test]
+    fn removing_the_sensor_exits() {
+        let (mut world, sensor) = make_sensor_world();
+        let first = world.add_body(RigidBody::new(BodyType::Dynamic, circle(5.0)).with_position(v(105.0, 5.0)));
+        let second = world.add_body(RigidBody::new(BodyType::Dynamic, circle(5.0)).with_position(v(115.0, 15.0)));
+        let outside = world.add_body(RigidBody::new(BodyType::Dynamic, circle(5.0)).with_position(v(200.0, 10.0)));
+
+        assert_eq!(run_steps(&mut world, 1, DT).len(), 2);
+
+        world.remove_body(sensor);
+        world.remove_body(outside);
+
+        // Even a step that does nothing reports them
+        world.step(0.0);
+        assert_eq!(world.get_trigger_events(), &[
+            TriggerEvent::Exit { sensor, other: first },
+            TriggerEvent::Exit { sensor, other: second }
+        ]);
+
+        assert!(run_steps(&mut world, 3, DT).is_empty());
+    }
+
+    #[test]
+    fn what_triggers_sensors() {
+        let (mut world, sensor) = make_sensor_world();
+
+        // Neither moves nor can be moved, so it's never checked
+        world.add_body(RigidBody::new(BodyType::Static, circle(5.0)).with_position(v(110.0, 10.0)));
+        // Sensors don't see each other, even when one can move
+        world.add_body(RigidBody::new(BodyType::Dynamic, circle(2.0)).with_position(v(110.0, 19.0)).with_sensor(true));
+        let platform = world.add_body(RigidBody::new(BodyType::Kinematic, square(10.0)).with_position(v(80.0, 10.0)).with_linear_velocity(v(100.0, 0.0)));
+
+        let events = run_steps(&mut world, 8, 0.1);
+        assert_eq!(events, vec![
+            (3, TriggerEvent::Enter { sensor, other: platform }),
+            (6, TriggerEvent::Exit { sensor, other: platform })
+        ]);
+    }
+}
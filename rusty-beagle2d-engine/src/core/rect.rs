@@ -0,0 +1,55 @@
+use linear_beaglebra::vector2::Vector2;
+
+// An axis aligned rectangle from its top left corner, for laying out UI on screen.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    pub fn get_right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn get_bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn get_center(&self) -> Vector2 {
+        Vector2::new(self.x + self.width * 0.5, self.y + self.height * 0.5)
+    }
+
+    // Points on the left and top edges are inside, points on the right and bottom edges are not,
+    // So a point is never inside two rectangles lying next to each other.
+    pub fn contains(&self, point: Vector2) -> bool {
+        point.x >= self.x && point.y >= self.y && point.x < self.get_right() && point.y < self.get_bottom()
+    }
+
+    // Smaller by "amount" on every side. Never smaller than nothing.
+    pub fn shrink(&self, amount: f32) -> Rect {
+        Rect::new(
+            self.x + amount,
+            self.y + amount,
+            (self.width - amount * 2.0).max(0.0),
+            (self.height - amount * 2.0).max(0.0))
+    }
+
+    // The part both rectangles cover. Has no size if they don't overlap.
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+
+        Rect::new(x, y, (self.get_right().min(other.get_right()) - x).max(0.0), (self.get_bottom().min(other.get_bottom()) - y).max(0.0))
+    }
+
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.get_right() && other.x < self.get_right() && self.y < other.get_bottom() && other.y < self.get_bottom()
+    }
+}
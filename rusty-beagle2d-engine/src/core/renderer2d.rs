@@ -1,60 +1,152 @@
 use rusty_beagle2d_glfw::ogl;
 use linear_beaglebra::{matrix4x4, vector2::Vector2};
-use std::mem;
-use std::collections::{HashMap};
 
 use crate::core::sprite;
 use crate::core::shader;
 use crate::{core::shader_program};
 
 use crate::core::texture;
+use crate::core::batch::{Batch, BatchMode, BatchVertex, DrawState};
+use crate::core::color::Color;
+use crate::core::blend_mode::BlendMode;
+use crate::core::math2d;
+use crate::core::triangulation;
+use crate::core::tilemap::Tilemap;
+use crate::core::matrix3x2::Matrix3x2;
+use crate::core::scene::scene_graph::SceneGraph;
+use crate::core::scene::node::Attachment;
+use crate::core::ecs::world::World;
+use crate::core::ecs::components::Transform;
+use crate::core::collision::shapes::Aabb;
+use crate::core::collision::broadphase::Broadphase;
+use crate::core::font::FontMetrics;
+use crate::core::rect::Rect;
+use crate::core::nine_slice::NineSlice;
+use crate::core::particles::particle_system::ParticleSystem;
 
 use std::boxed;
 
 use std::fs::{File};
-use std::path::{Path};
 
 use std::ffi::{c_void, CString};
 use std::ptr;
+use std::mem;
 
-struct Character {
-    TextureId: u32, // ID Handle of the glyph texture,
-    TexturePosition: Vector2,
-    Size: Vector2, // Size of glyph
-    Bearing: Vector2, // Offset from baseline to left/top of glyph
-    Advance: u32, // Offset to advance to next glyph
-    MaxHeight: u32
+// Size of the area the projection maps to the window
+const VIEW_WIDTH: f32 = 1024.0;
+const VIEW_HEIGHT: f32 = 768.0;
+
+// What the renderer did, for showing in debug overlays.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub triangles: u32,
+    // Times the batch was sent to the GPU. More than one per frame when the camera moves mid frame.
+    pub flushes: u32
 }
 
 pub struct Renderer2d {
     shader_program: shader_program::ShaderProgram,
+    batch: Batch,
+    // 1x1 white texture, so untextured shapes can go through the same shader and batch as sprites
+    white_texture: texture::Texture,
+    // Reused between fill_polygon calls to avoid allocating every frame
+    polygon_indices: Vec<u32>,
+    // Blend mode and layer for text and shapes. Sprites carry their own.
+    blend_mode: BlendMode,
+    layer: i32,
     camera_position_x: f32,
     camera_position_y: f32,
+    // Drawing outside of it is cut off. In view pixels, not moved by the camera.
+    clip_rect: Option<Rect>,
     text_sprite_atlas: sprite::Sprite,
-    character_info: HashMap<u8, Character>
+    font: FontMetrics,
+    // Counted up during the frame, then moved to last_frame_stats by end_frame
+    stats: RenderStats,
+    last_frame_stats: RenderStats
 }
 
 impl Renderer2d {
     pub fn set_camera_position(&mut self, position_x: f32, position_y: f32) {
+        if position_x != self.camera_position_x || position_y != self.camera_position_y {
+            // Geometry drawn so far was meant for the old camera
+            self.flush();
+        }
+
         self.camera_position_x = position_x;
         self.camera_position_y = position_y;
     }
 
-    pub fn new() -> Renderer2d {
-        // TODO: Read up on the OpenGL coords... clearly don't understand that, or how it works with orthographic cameras...
-        let mut vertices: Vec<f32> = vec![
-            // Positions       // Texture Coords
-             1.0,  1.0, 0.0,   1.0, 1.0,         // Top Right
-             1.0,  0.0, 0.0,   1.0, 0.0,         // Bottom Right
-             0.0,  0.0, 0.0,   0.0, 0.0,         // Bottom Left
-             0.0,  1.0, 0.0,   0.0, 1.0,         // Top Left
-        ];
-
-        let mut indices: Vec<u32> = vec![
-            0, 1, 3, // First Triangle
-            1, 2, 3  // Second Triangle
-        ];
+    pub fn get_camera_position(&self) -> Vector2 {
+        Vector2::new(self.camera_position_x, self.camera_position_y)
+    }
+
+    // The world position in the middle of the view.
+    pub fn get_view_center(&self) -> Vector2 {
+        // The camera position moves the world, so the view's top left corner is at minus the camera position
+        Vector2::new(-self.camera_position_x + VIEW_WIDTH / 2.0, -self.camera_position_y + VIEW_HEIGHT / 2.0)
+    }
+
+    // The part of the world in view.
+    pub fn get_view_bounds(&self) -> Aabb {
+        let top_left = Vector2::new(-self.camera_position_x, -self.camera_position_y);
+        Aabb::new(top_left, Vector2::new(top_left.x + VIEW_WIDTH, top_left.y + VIEW_HEIGHT))
+    }
+
+    // Selects how the following text and shape draws are blended.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    // Selects the draw order layer of the following text and shape draws. See Sprite::layer.
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    pub fn get_layer(&self) -> i32 {
+        self.layer
+    }
+
+    // Cuts off everything drawn after this outside of "clip_rect", until it is set to None again.
+    // The rectangle is in view pixels from the top left corner of the view, and doesn't move with the camera.
+    // NOTE: Like moving the camera, changing the clip rectangle flushes, so what was drawn before ends up below what comes after.
+    pub fn set_clip_rect(&mut self, clip_rect: Option<Rect>) {
+        if clip_rect == self.clip_rect {
+            return;
+        }
 
+        self.flush();
+        self.clip_rect = clip_rect;
+
+        match clip_rect {
+            Some(rect) => {
+                // The viewport is in window pixels, which can be more or less than view pixels
+                let viewport = ogl::get_viewport();
+                let scale_x = viewport[2] as f32 / VIEW_WIDTH;
+                let scale_y = viewport[3] as f32 / VIEW_HEIGHT;
+
+                // OpenGL counts from the bottom of the window, the view from the top
+                let left = (viewport[0] as f32 + rect.x * scale_x).round() as i32;
+                let right = (viewport[0] as f32 + rect.get_right() * scale_x).round() as i32;
+                let bottom = (viewport[1] as f32 + (VIEW_HEIGHT - rect.get_bottom()) * scale_y).round() as i32;
+                let top = (viewport[1] as f32 + (VIEW_HEIGHT - rect.y) * scale_y).round() as i32;
+
+                ogl::scissor(left, bottom, (right - left).max(0), (top - bottom).max(0));
+                ogl::enable(ogl::Cap::ScissorTest);
+            },
+            None => ogl::disable(ogl::Cap::ScissorTest)
+        }
+    }
+
+    pub fn get_clip_rect(&self) -> Option<Rect> {
+        self.clip_rect
+    }
+
+    pub fn new() -> Renderer2d {
         // Load OpenGl functions
         ogl::init();
 
@@ -68,42 +160,11 @@ impl Renderer2d {
 
         // Enable opacity
         ogl::enable(ogl::Cap::Blend);
-        ogl::blend_func(ogl::BlendFactor::SrcAlpha, ogl::BlendFactor::OneMinusSrcAlpha);
+        BlendMode::Alpha.apply();
 
-        // Bind VAO
-        let vao = ogl::gen_vertex_array();
-        ogl::bind_vertex_array(vao);
-
-        // Bind indice buffer
-        let ebo = ogl::gl_gen_buffer();
-
-        ogl::gl_bind_buffer(ogl::BufferTarget::ElementArrayBuffer, ebo);
-        ogl::buffer_data(ogl::BufferTarget::ElementArrayBuffer, &mut indices, ogl::Usage::DynamicDraw);
-
-        let vertex_buffer = ogl::gl_gen_buffer();
-        ogl::gl_bind_buffer(ogl::BufferTarget::ArrayBuffer, vertex_buffer);
-        ogl::buffer_data(ogl::BufferTarget::ArrayBuffer, &mut vertices, ogl::Usage::DynamicDraw);
-
-        ogl::vertex_attrib_pointer(
-            0, 
-            3, 
-            ogl::DataType::Float,
-            false, 
-            mem::size_of::<f32>() as i32 * 5,
-            0);
-
-        ogl::enable_vertex_attrib_array(0);
-
-        let offset : u32 = (3 * mem::size_of::<f32>()) as u32;
-
-        ogl::vertex_attrib_pointer(1,
-             2, 
-             ogl::DataType::Float,
-             false, 
-             (mem::size_of::<f32>() * 5) as i32, 
-             offset);
-        
-        ogl::enable_vertex_attrib_array(1);
+        // All drawing goes through one dynamic batch, which grows as needed
+        let batch = Batch::new(4 * 1024);
+        let white_texture = texture::Texture::from_rgba(1, 1, vec![255, 255, 255, 255]);
 
         // Shader compilation
         let vertex_shader = shader::Shader::new(shader::ShaderType::VertexShader, String::from("dat/shaders/vertex.shader"));
@@ -115,198 +176,503 @@ impl Renderer2d {
         // Font Texture Setup
         let text_atlas = std::boxed::Box::new(texture::Texture::new(String::from("test-dat/fonts/bitmap-fonts/verdana-signed.png")));
 
-        // LEARN: Read up on Rust iterators
-        let mut characters: HashMap<u8, Character> = HashMap::new();
-
-        let character_atlas_info = std::fs::read_to_string(Path::new("test-dat/fonts/bitmap-fonts/verdana-signed.fnt"))
+        let font = FontMetrics::from_file("test-dat/fonts/bitmap-fonts/verdana-signed.fnt")
             .expect("Failed to load character atlas info");
 
-        let line_iterator: Vec<&str> = character_atlas_info.lines().collect();
-
-        let max_height_line: Vec<&str> = line_iterator[1]
-            .split_whitespace()
-            .skip(1)
-            .collect();
-
-        let max_height = Renderer2d::parse_value(max_height_line[0]);
-
-        // TODO: When using Hiero to export .fnt, padding is encoded oddly...
-        // Left padding is subtracted from the original xoffset value, so you have to add padding to xoffset to value you need for text rendering
-        // Left + Right Padding is added to xadvance, so these have to be subtracted from xadvance to get the value you need for text rendering
-        // Up padding is substracted from yoffset value, so you have to add it back to get original yoffset value. 
-        let padding_line: Vec<&str> = line_iterator[0].split_whitespace().collect();
-        let padding_text: Vec<&str> = padding_line[11].rsplit('=').collect();
-        let padding_values: Vec<&str> = padding_text[0].split(',').collect();
-        
-        let padding_up = i32::from_str_radix( padding_values[0], 10).expect("Failed to convert padding to int value.");
-        let padding_right = i32::from_str_radix( padding_values[1], 10).expect("Failed to convert padding to int value.");
-        let padding_down = i32::from_str_radix( padding_values[2], 10).expect("Failed to convert padding to int value.");
-        let padding_left = i32::from_str_radix( padding_values[3], 10).expect("Failed to convert padding to int value.");
-
-        for character_line in &line_iterator[4..line_iterator.len()-1] {
-            let line_words: Vec<&str> = character_line
-                .split_whitespace()
-                .skip(1)
-                .collect();
-
-            let character_id = Renderer2d::parse_value(line_words[0]);
-            let character_texture_position_x = Renderer2d::parse_value(line_words[1]);
-            let character_texture_position_y = Renderer2d::parse_value(line_words[2]);
-            let character_width = Renderer2d::parse_value(line_words[3]);
-            let character_height = Renderer2d::parse_value(line_words[4]);
-            let character_x_offset = Renderer2d::parse_value(line_words[5]) + padding_left;
-            let character_y_offset = Renderer2d::parse_value(line_words[6]);
-            let character_x_advance = Renderer2d::parse_value(line_words[7]) - (padding_left + padding_right);
-
-            characters.insert(character_id as u8, Character {
-                TextureId: text_atlas.get_opengl_texture_id(),
-                TexturePosition: Vector2::new(character_texture_position_x as f32, character_texture_position_y as f32),
-                Size: Vector2::new(character_width as f32, character_height as f32),
-                Bearing: Vector2::new(character_x_offset as f32, character_y_offset as f32),
-                Advance: character_x_advance as u32,
-                MaxHeight: max_height as u32
-            });
-        }
-
         let text_sprite = sprite::Sprite::new(text_atlas);
 
         Renderer2d {
             shader_program: shader_program,
+            batch,
+            white_texture,
+            polygon_indices: Vec::new(),
+            blend_mode: BlendMode::Alpha,
+            layer: 0,
             camera_position_x: 0.0,
             camera_position_y: 0.0,
+            clip_rect: None,
             text_sprite_atlas: text_sprite,
-            character_info: characters
+            font,
+            stats: RenderStats::default(),
+            last_frame_stats: RenderStats::default()
         }
     }
 
-    fn parse_value(value_pair: &str) -> i32 {
-        let split_values: Vec<&str> = value_pair.rsplit('=').collect();
-        i32::from_str_radix(split_values[0], 10).expect("Failed to parse value.")
+    // The layout of the font draw_text uses.
+    pub fn get_font(&self) -> &FontMetrics {
+        &self.font
     }
 
-    pub fn draw_sprite(&self, sprite: &sprite::Sprite) {
-        // Activate the Sprite's texture for the OpenGl context
-        sprite.texture.activate();
+    // Width and height draw_text would take up.
+    pub fn measure_text(&self, text: &str, scale: f32) -> Vector2 {
+        self.font.measure_text(text, scale)
+    }
 
-        let transform_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "transform");
-        let projection_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "projection");
-        let texture_bounding_box_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "bounding_box");
 
-        // Set texture bounding box
-        // TODO: Make vector 4 in linear-beaglebra
-        let bounding_box_array = [sprite.texture_x, sprite.texture_y, sprite.texture_width, sprite.texture_height];
-        ogl::uniform4fv(texture_bounding_box_location, 1, bounding_box_array.first().expect("Failed to read bounding box values"));
+    pub fn draw_sprite(&mut self, sprite: &sprite::Sprite) {
+        self.draw_sprite_with_parent(sprite, &Matrix3x2::identity());
+    }
 
-        // TODO yo read up on orthographic projections again!
-        let mut homemade_orthographic_projection = matrix4x4::Matrix4x4::orthographic(0.0, 1024.0, 768.0, 0.0, -1.0, 1.0);
-        let homemade_camera_translate = Vector2::new(self.camera_position_x, self.camera_position_y);
-        homemade_orthographic_projection = homemade_orthographic_projection.translate(homemade_camera_translate);
+    // Draws the sprite inside a parent transform. The sprite's position, angle and scale are relative to the parent.
+    pub fn draw_sprite_with_parent(&mut self, sprite: &sprite::Sprite, parent: &Matrix3x2) {
+        let texture_width = sprite.texture.get_width() as f32;
+        let texture_height = sprite.texture.get_height() as f32;
+
+        // Rotated regions occupy a rectangle with width and height swapped in the texture
+        let (region_width, region_height) = if sprite.texture_rotated {
+            (sprite.texture_height, sprite.texture_width)
+        } else {
+            (sprite.texture_width, sprite.texture_height)
+        };
+
+        let u_min = sprite.texture_x / texture_width;
+        let v_min = sprite.texture_y / texture_height;
+        let u_size = region_width / texture_width;
+        let v_size = region_height / texture_height;
+
+        // Position of each corner within the displayed image, from (0, 0) top left to (1, 1) bottom right.
+        // Flipping mirrors these, so the sprite mirrors in place around its own bounds.
+        let mut tex_coords = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        for tex_coord in tex_coords.iter_mut() {
+            let (mut s, mut t) = *tex_coord;
+
+            if sprite.flip_x {
+                s = 1.0 - s;
+            }
+
+            if sprite.flip_y {
+                t = 1.0 - t;
+            }
+
+            // An image stored turned clockwise has its top left corner at the top right of the region
+            if sprite.texture_rotated {
+                let rotated = (1.0 - t, s);
+                s = rotated.0;
+                t = rotated.1;
+            }
+
+            *tex_coord = (u_min + s * u_size, v_min + t * v_size);
+        }
 
-        ogl::uniform_matrix_4fv(projection_location, 1, false, homemade_orthographic_projection.first());
+        let corners = sprite.get_corners_with_parent(parent);
+        let corner_colors = sprite.get_corner_colors();
+
+        let state = DrawState {
+            texture: sprite.texture.get_opengl_texture_id(),
+            mode: BatchMode::Textured,
+            blend_mode: sprite.blend_mode
+        };
+        self.push_textured_quad(state, sprite.layer, corners, tex_coords, corner_colors);
+    }
 
-        // Transformation testing
-        // TODO yo read up on matrix math again!
-        let homemade_sprite_translation_matrix = Vector2::new(sprite.position_x, sprite.position_y);
+    // Draws the nine slice the same way as a sprite, one quad per patch.
+    pub fn draw_nine_slice(&mut self, nine_slice: &NineSlice) {
+        let texture_width = nine_slice.texture.get_width() as f32;
+        let texture_height = nine_slice.texture.get_height() as f32;
+        let transform = nine_slice.get_transform();
+
+        let state = DrawState {
+            texture: nine_slice.texture.get_opengl_texture_id(),
+            mode: BatchMode::Textured,
+            blend_mode: nine_slice.blend_mode
+        };
+
+        nine_slice.for_each_patch(|patch| {
+            let target = patch.target;
+            let corners = [
+                transform.transform_point(Vector2::new(target.x, target.y)),
+                transform.transform_point(Vector2::new(target.x + target.width, target.y)),
+                transform.transform_point(Vector2::new(target.x + target.width, target.y + target.height)),
+                transform.transform_point(Vector2::new(target.x, target.y + target.height))
+            ];
+
+            let u_min = patch.source.x / texture_width;
+            let v_min = patch.source.y / texture_height;
+            let u_size = patch.source.width / texture_width;
+            let v_size = patch.source.height / texture_height;
+
+            // Same as for sprites, a source stored turned clockwise has its top left corner at its top right
+            let mut tex_coords = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+            for tex_coord in tex_coords.iter_mut() {
+                let (s, t) = if patch.rotated { (1.0 - tex_coord.1, tex_coord.0) } else { *tex_coord };
+                *tex_coord = (u_min + s * u_size, v_min + t * v_size);
+            }
+
+            self.push_textured_quad(state, nine_slice.layer, corners, tex_coords, [nine_slice.tint; 4]);
+        });
+    }
 
-        let mut homemade_sprite_matrix = matrix4x4::Matrix4x4::identity();
-        homemade_sprite_matrix = homemade_sprite_matrix.translate(homemade_sprite_translation_matrix);
-        homemade_sprite_matrix = homemade_sprite_matrix.rotate(0.0, 0.0, sprite.angle);
-        homemade_sprite_matrix = homemade_sprite_matrix.scale(
-            sprite.texture_width as f32,
-            sprite.texture_height as f32,
-            1.0
-        );
+    // Draws every live particle of the system as a quad, emitter by emitter.
+    // Each emitter is one draw call unless something else is drawn on the same layer in between.
+    pub fn draw_particles(&mut self, system: &ParticleSystem) {
+        let texture = system.get_texture().unwrap_or(&self.white_texture);
+        let texture_id = texture.get_opengl_texture_id();
+        let texture_width = texture.get_width() as f32;
+        let texture_height = texture.get_height() as f32;
+
+        for (definition, emitter) in system.get_effect().emitters.iter().zip(system.get_emitters()) {
+            let state = DrawState {
+                texture: texture_id,
+                mode: BatchMode::Textured,
+                blend_mode: definition.blend_mode
+            };
+
+            let [region_x, region_y, region_width, region_height] = definition.region.unwrap_or([0.0, 0.0, texture_width, texture_height]);
+            let (columns, rows, _) = definition.get_texture_sheet();
+            let frame_width = region_width / columns as f32;
+            let frame_height = region_height / rows as f32;
+
+            // Particles keep the shape of their frame
+            let aspect = if frame_width > 0.0 { frame_height / frame_width } else { 1.0 };
+
+            for particle in emitter.get_particles() {
+                let (sin, cos) = math2d::degrees_to_radians(particle.angle).sin_cos();
+                let half_width = particle.size * 0.5;
+                let half_height = particle.size * aspect * 0.5;
+
+                let corner = |x: f32, y: f32| Vector2::new(particle.position_x + x * cos - y * sin, particle.position_y + x * sin + y * cos);
+                let corners = [
+                    corner(-half_width, -half_height),
+                    corner(half_width, -half_height),
+                    corner(half_width, half_height),
+                    corner(-half_width, half_height)
+                ];
+
+                let column = (particle.frame % columns) as f32;
+                let row = (particle.frame / columns) as f32;
+                let u_min = (region_x + column * frame_width) / texture_width;
+                let v_min = (region_y + row * frame_height) / texture_height;
+                let u_max = u_min + frame_width / texture_width;
+                let v_max = v_min + frame_height / texture_height;
+                let tex_coords = [(u_min, v_min), (u_max, v_min), (u_max, v_max), (u_min, v_max)];
+
+                self.push_textured_quad(state, system.layer, corners, tex_coords, [particle.color; 4]);
+            }
+        }
+    }
+
+    // Draws the sprites and text attached to the shown nodes of the scene, parents below their children.
+    // If the scene has a camera node, the view is moved to it first.
+    // NOTE: Text can't be rotated yet, so it only follows the position and the horizontal scale of its node.
+    pub fn draw_scene(&mut self, scene: &SceneGraph) {
+        if let Some(camera_position) = scene.get_camera_position() {
+            self.set_camera_position(-camera_position.x, -camera_position.y);
+        }
+
+        scene.for_each_visible(|_, node, world_matrix| {
+            match node.get_attachment() {
+                Attachment::Sprite(sprite) => self.draw_sprite_with_parent(sprite, world_matrix),
+                Attachment::Text(text) => {
+                    let position = world_matrix.transform_point(Vector2::new(0.0, 0.0));
+                    let scale = text.scale * world_matrix.get_scale().x;
+                    self.draw_text(&text.text, position, scale, text.color);
+                },
+                Attachment::Empty | Attachment::Camera => {}
+            }
+        });
+    }
+
+    // Draws every entity that has a Transform and a Sprite, with the sprite placed by the transform.
+    pub fn draw_entities(&mut self, world: &World) {
+        world.for_each::<(&Transform, &mut sprite::Sprite), _>(|_, (transform, sprite)| {
+            transform.apply_to(sprite);
+            self.draw_sprite(sprite);
+        });
+    }
 
-        ogl::uniform_matrix_4fv(transform_location, 1, false, homemade_sprite_matrix.first());
+    // Draws the sprites in a broadphase whose bounding box is in view, and skips the rest.
+    // Keep each sprite's box up to date with Broadphase::move_proxy when it moves.
+    pub fn draw_visible_sprites<B: Broadphase<sprite::Sprite>>(&mut self, sprites: &B) {
+        let view_bounds = self.get_view_bounds();
 
-        ogl::draw_elements(ogl::DrawMode::Triangles, 6, ogl::ElementsDataType::UnsignedInt);
+        sprites.for_each_in_region(&view_bounds, |id| {
+            if let Some(sprite) = sprites.get_data(id) {
+                self.draw_sprite(sprite);
+            }
+        });
     }
 
-    pub fn draw_text(&mut self, text: &str, position: Vector2, scale: f32) {
+    pub fn draw_text(&mut self, text: &str, position: Vector2, scale: f32, color: Color) {
         // Check if string is purely ASCII
         if text.is_ascii() == false {
             panic!("The provided text is not ASCII!");
         }
 
-        self.text_sprite_atlas.texture.activate();
+        let atlas_width = self.text_sprite_atlas.texture.get_width() as f32;
+        let atlas_height = self.text_sprite_atlas.texture.get_height() as f32;
 
-        // Get shader uniforms
-        let transform_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "transform");
-        let projection_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "projection");
-        let texture_bounding_box_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "bounding_box");
-        let is_text_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "isText");
-
-        // Activate font rendering in shader
-        ogl::uniform_1i(is_text_location, 1);
+        let state = DrawState {
+            texture: self.text_sprite_atlas.texture.get_opengl_texture_id(),
+            mode: BatchMode::Text,
+            blend_mode: self.blend_mode
+        };
 
         // Text render variables
         let mut pen_point = position;
 
         for my_char in text.bytes() {
-            let current_character = self.character_info.get(&my_char).expect("Failed to find character.");
-
-            self.text_sprite_atlas.position_x = pen_point.x + ((current_character.Bearing.x) * scale);
-            self.text_sprite_atlas.position_y = pen_point.y + ((current_character.Bearing.y) * scale);
-
-            self.text_sprite_atlas.set_render_view(
-                current_character.TexturePosition.x, 
-                current_character.TexturePosition.y, 
-                current_character.Size.x, 
-                current_character.Size.y);
-
-            // Set texture bounding box
-            // TODO: Make vector 4 in linear-beaglebra
-            let bounding_box_array = [
-                current_character.TexturePosition.x,
-                current_character.TexturePosition.y, 
-                current_character.Size.x,
-                current_character.Size.y];
-            
-            ogl::uniform4fv(
-                texture_bounding_box_location,
-                1,
-                bounding_box_array.first().expect("Failed to read bounding box values"));
-
-            // TODO yo read up on orthographic projections again!
-            let mut homemade_orthographic_projection = matrix4x4::Matrix4x4::orthographic(0.0, 1024.0, 768.0, 0.0, -1.0, 1.0);
-            let homemade_camera_translate = Vector2::new(self.camera_position_x, self.camera_position_y);
-            homemade_orthographic_projection = homemade_orthographic_projection.translate(homemade_camera_translate);
-
-            ogl::uniform_matrix_4fv(projection_location, 1, false, homemade_orthographic_projection.first());
-
-            // Transformation testing
-            // TODO yo read up on matrix math again!
-            let homemade_sprite_translation_matrix = 
-                Vector2::new(
-                    self.text_sprite_atlas.position_x, 
-                    self.text_sprite_atlas.position_y);
-
-            let mut homemade_sprite_matrix = matrix4x4::Matrix4x4::identity();
-            homemade_sprite_matrix = homemade_sprite_matrix.translate(homemade_sprite_translation_matrix);
-            homemade_sprite_matrix = homemade_sprite_matrix.rotate(0.0, 0.0, self.text_sprite_atlas.angle);
-            homemade_sprite_matrix = homemade_sprite_matrix.scale(
-                self.text_sprite_atlas.texture_width as f32 * scale,
-                self.text_sprite_atlas.texture_height as f32 * scale,
-                1.0
-            );
-
-            ogl::uniform_matrix_4fv(transform_location, 1, false, homemade_sprite_matrix.first());
-
-            ogl::draw_elements(ogl::DrawMode::Triangles, 6, ogl::ElementsDataType::UnsignedInt);
-
-            pen_point.x += (current_character.Advance as f32) * scale;
+            let glyph = *self.font.get_glyph(my_char).expect("Failed to find character.");
+
+            let x = pen_point.x + (glyph.bearing.x * scale);
+            let y = pen_point.y + (glyph.bearing.y * scale);
+            let width = glyph.size.x * scale;
+            let height = glyph.size.y * scale;
+
+            let u_min = glyph.texture_position.x / atlas_width;
+            let v_min = glyph.texture_position.y / atlas_height;
+            let u_max = (glyph.texture_position.x + glyph.size.x) / atlas_width;
+            let v_max = (glyph.texture_position.y + glyph.size.y) / atlas_height;
+
+            self.batch.push_quad(state, self.layer, [
+                Renderer2d::vertex(Vector2::new(x, y), u_min, v_min, color),
+                Renderer2d::vertex(Vector2::new(x + width, y), u_max, v_min, color),
+                Renderer2d::vertex(Vector2::new(x + width, y + height), u_max, v_max, color),
+                Renderer2d::vertex(Vector2::new(x, y + height), u_min, v_max, color)
+            ]);
+
+            pen_point.x += glyph.advance * scale;
+        }
+    }
+
+    // A line of the given thickness, centered on the segment from start to end.
+    pub fn draw_line(&mut self, start: Vector2, end: Vector2, thickness: f32, color: Color) {
+        let direction = math2d::normalize(math2d::sub(end, start));
+        let half_width = math2d::scale(math2d::perpendicular(direction), thickness * 0.5);
+
+        let state = self.solid_draw_state();
+        self.batch.push_quad(state, self.layer, [
+            Renderer2d::solid_vertex(math2d::add(start, half_width), color),
+            Renderer2d::solid_vertex(math2d::add(end, half_width), color),
+            Renderer2d::solid_vertex(math2d::sub(end, half_width), color),
+            Renderer2d::solid_vertex(math2d::sub(start, half_width), color)
+        ]);
+    }
+
+    // Outline of a rectangle. The outline is drawn inside the rectangle's bounds.
+    pub fn draw_rect(&mut self, x: f32, y: f32, width: f32, height: f32, thickness: f32, color: Color) {
+        let thickness = thickness.min(width * 0.5).min(height * 0.5);
+
+        self.fill_rect(x, y, width, thickness, color);                                          // Top
+        self.fill_rect(x, y + height - thickness, width, thickness, color);                     // Bottom
+        self.fill_rect(x, y + thickness, thickness, height - thickness * 2.0, color);           // Left
+        self.fill_rect(x + width - thickness, y + thickness, thickness, height - thickness * 2.0, color); // Right
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let state = self.solid_draw_state();
+        self.batch.push_quad(state, self.layer, [
+            Renderer2d::solid_vertex(Vector2::new(x, y), color),
+            Renderer2d::solid_vertex(Vector2::new(x + width, y), color),
+            Renderer2d::solid_vertex(Vector2::new(x + width, y + height), color),
+            Renderer2d::solid_vertex(Vector2::new(x, y + height), color)
+        ]);
+    }
+
+    // Outline of a circle, centered on its radius.
+    pub fn draw_circle(&mut self, center: Vector2, radius: f32, thickness: f32, color: Color) {
+        let segments = Renderer2d::circle_segment_count(radius);
+        let inner_radius = (radius - thickness * 0.5).max(0.0);
+        let outer_radius = radius + thickness * 0.5;
+
+        // A ring of quads, with the inner and outer point of each segment alternating
+        let vertices = (0..segments).flat_map(move |segment| {
+            let direction = Renderer2d::circle_direction(segment, segments);
+            [
+                Renderer2d::solid_vertex(math2d::add(center, math2d::scale(direction, inner_radius)), color),
+                Renderer2d::solid_vertex(math2d::add(center, math2d::scale(direction, outer_radius)), color)
+            ]
+        });
+
+        let indices = (0..segments).flat_map(move |segment| {
+            let inner = segment * 2;
+            let outer = inner + 1;
+            let next_inner = ((segment + 1) % segments) * 2;
+            let next_outer = next_inner + 1;
+            [inner, outer, next_outer, inner, next_outer, next_inner]
+        });
+
+        let state = self.solid_draw_state();
+        self.batch.push(state, self.layer, vertices, indices);
+    }
+
+    pub fn fill_circle(&mut self, center: Vector2, radius: f32, color: Color) {
+        let segments = Renderer2d::circle_segment_count(radius);
+
+        // A triangle fan around the center vertex
+        let vertices = std::iter::once(Renderer2d::solid_vertex(center, color))
+            .chain((0..segments).map(move |segment| {
+                let direction = Renderer2d::circle_direction(segment, segments);
+                Renderer2d::solid_vertex(math2d::add(center, math2d::scale(direction, radius)), color)
+            }));
+
+        let indices = (0..segments).flat_map(move |segment| {
+            [0, segment + 1, ((segment + 1) % segments) + 1]
+        });
+
+        let state = self.solid_draw_state();
+        self.batch.push(state, self.layer, vertices, indices);
+    }
+
+    // Outline of a closed polygon, one line per edge.
+    pub fn draw_polygon(&mut self, points: &[Vector2], thickness: f32, color: Color) {
+        for index in 0..points.len() {
+            let start = points[index];
+            let end = points[(index + 1) % points.len()];
+
+            // Extend each edge by half the thickness at both ends, so the corners are closed
+            let extension = math2d::scale(math2d::normalize(math2d::sub(end, start)), thickness * 0.5);
+            self.draw_line(math2d::sub(start, extension), math2d::add(end, extension), thickness, color);
+        }
+    }
+
+    // Fills a simple polygon, which may be concave. See triangulation::triangulate.
+    pub fn fill_polygon(&mut self, points: &[Vector2], color: Color) {
+        self.polygon_indices.clear();
+        triangulation::triangulate(points, &mut self.polygon_indices);
+
+        let state = self.solid_draw_state();
+        let vertices = points.iter().map(|&point| Renderer2d::solid_vertex(point, color));
+        self.batch.push(state, self.layer, vertices, self.polygon_indices.iter().cloned());
+    }
+
+    // Draws everything batched since the last flush.
+    // Call this once at the end of the frame, before swapping buffers.
+    pub fn flush(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+
+        self.activate_shader();
+
+        let is_text_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "isText");
+        let blend_mode_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "blendMode");
+
+        self.batch.upload();
+
+        self.stats.flushes += 1;
+
+        for command in self.batch.get_commands() {
+            self.stats.draw_calls += 1;
+            self.stats.triangles += (command.index_count / 3) as u32;
+
+            ogl::bind_texture(ogl::TextureTarget::Texture2d, command.state.texture);
+            ogl::uniform_1i(is_text_location, if command.state.mode == BatchMode::Text { 1 } else { 0 });
+            ogl::uniform_1i(blend_mode_location, command.state.blend_mode.get_shader_value());
+            command.state.blend_mode.apply();
+
+            ogl::draw_elements_with_offset(
+                ogl::DrawMode::Triangles,
+                command.index_count as i32,
+                ogl::ElementsDataType::UnsignedInt,
+                command.first_index * mem::size_of::<u32>());
         }
 
         // Disable font rendering in fragment shader
         ogl::uniform_1i(is_text_location, 0);
 
-        // Unbind font texture atlas
-        ogl::bind_texture(ogl::TextureTarget::Texture2d, 0);
+        // Back to regular transparency
+        BlendMode::Alpha.apply();
+
+        // Unbind textures
+        ogl::bind_texture(ogl::TextureTarget::Texture2d, ogl::TextureId::NONE);
+
+        self.batch.clear();
+    }
+
+    // Call after the last flush of a frame, so get_stats reports the finished frame. App does this.
+    pub fn end_frame(&mut self) {
+        self.last_frame_stats = self.stats;
+        self.stats = RenderStats::default();
+    }
+
+    // What was drawn during the last finished frame.
+    pub fn get_stats(&self) -> RenderStats {
+        self.last_frame_stats
+    }
+
+    // Draws every layer of the tilemap, in order.
+    pub fn draw_tilemap(&mut self, tilemap: &mut Tilemap) {
+        for layer_index in 0..tilemap.get_layer_count() {
+            self.draw_tilemap_layer(tilemap, layer_index);
+        }
+    }
+
+    // Draws the chunks of a tilemap layer that are in view of the camera.
+    // NOTE: Tile layers are drawn straight away from their cached geometry instead of going through the batch,
+    // So everything drawn before is flushed first and ends up below the layer, regardless of sprite layers.
+    // Draw a layer between your sprite draws to put sprites behind or in front of it.
+    pub fn draw_tilemap_layer(&mut self, tilemap: &mut Tilemap, layer_index: usize) {
+        self.flush();
+        self.activate_shader();
+
+        // The projection moves the world by the camera position, so the view starts at minus the camera position
+        let view_left = -self.camera_position_x;
+        let view_top = -self.camera_position_y;
+
+        tilemap.draw_layer(layer_index, view_left, view_top, view_left + VIEW_WIDTH, view_top + VIEW_HEIGHT);
+
+        ogl::bind_texture(ogl::TextureTarget::Texture2d, ogl::TextureId::NONE);
+    }
+
+    // Activates the shader with the camera projection and the default settings for textured, alpha blended geometry.
+    fn activate_shader(&self) {
+        self.shader_program.activate();
+
+        let projection_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "projection");
+        let is_text_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "isText");
+        let blend_mode_location = ogl::get_uniform_location(self.shader_program.get_opengl_object_id(), "blendMode");
+
+        // TODO yo read up on orthographic projections again!
+        let mut homemade_orthographic_projection = matrix4x4::Matrix4x4::orthographic(0.0, VIEW_WIDTH, VIEW_HEIGHT, 0.0, -1.0, 1.0);
+        let homemade_camera_translate = Vector2::new(self.camera_position_x, self.camera_position_y);
+        homemade_orthographic_projection = homemade_orthographic_projection.translate(homemade_camera_translate);
+
+        ogl::uniform_matrix_4fv(projection_location, 1, false, homemade_orthographic_projection.first());
+        ogl::uniform_1i(is_text_location, 0);
+        ogl::uniform_1i(blend_mode_location, BlendMode::Alpha.get_shader_value());
+        BlendMode::Alpha.apply();
+    }
+
+    fn solid_draw_state(&self) -> DrawState {
+        DrawState {
+            texture: self.white_texture.get_opengl_texture_id(),
+            mode: BatchMode::Textured,
+            blend_mode: self.blend_mode
+        }
+    }
+
+    // Corners and texture coordinates go clockwise from the top left.
+    fn push_textured_quad(&mut self, state: DrawState, layer: i32, corners: [Vector2; 4], tex_coords: [(f32, f32); 4], colors: [Color; 4]) {
+        let mut vertices = [Renderer2d::vertex(corners[0], 0.0, 0.0, Color::WHITE); 4];
+        for index in 0..4 {
+            let (u, v) = tex_coords[index];
+            vertices[index] = Renderer2d::vertex(corners[index], u, v, colors[index]);
+        }
+
+        self.batch.push_quad(state, layer, vertices);
+    }
+
+    fn vertex(position: Vector2, u: f32, v: f32, color: Color) -> BatchVertex {
+        BatchVertex {
+            position: [position.x, position.y, 0.0],
+            tex_coord: [u, v],
+            color: color.to_array()
+        }
+    }
+
+    fn solid_vertex(position: Vector2, color: Color) -> BatchVertex {
+        Renderer2d::vertex(position, 0.5, 0.5, color)
+    }
+
+    // Enough segments that the edges of the circle are a few pixels long at most.
+    fn circle_segment_count(radius: f32) -> u32 {
+        let circumference = 2.0 * std::f32::consts::PI * radius;
+        ((circumference / 6.0) as u32).max(12).min(256)
     }
 
-    // TODO: Does nalgebra_glm seriously not have this? Gotta look more into this
-    fn degree_to_radians(degrees: f32) -> f32 {
-        (std::f32::consts::PI / 180.0) * degrees
+    fn circle_direction(segment: u32, segments: u32) -> Vector2 {
+        let angle = (segment as f32 / segments as f32) * 2.0 * std::f32::consts::PI;
+        Vector2::new(angle.cos(), angle.sin())
     }
 }
 
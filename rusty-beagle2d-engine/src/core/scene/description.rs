@@ -0,0 +1,216 @@
+// The data side of scenes: what a scene file contains, before any nodes or textures are created.
+// Kept apart from SceneGraph so scene files can be read, written and checked without a window.
+//
+// A scene file is JSON like:
+// {
+//   "prefabs": {
+//     "torch": { "name": "torch", "attachment": { "type": "sprite", "image": "dat/textures/torch.png" } }
+//   },
+//   "nodes": [
+//     { "name": "camera", "attachment": { "type": "camera" } },
+//     { "name": "left_torch", "prefab": "torch", "position": [100, 200] }
+//   ]
+// }
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct SceneDescription {
+    // Reusable node trees, by name. Nodes refer to them with "prefab".
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prefabs: BTreeMap<String, NodeDescription>,
+    // The top level nodes, in draw order
+    #[serde(default)]
+    pub nodes: Vec<NodeDescription>
+}
+
+// A node and everything below it.
+// Left out fields use the defaults of a new node, or the prefab's values if the node is a prefab instance.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct NodeDescription {
+    pub name: String,
+    // Name of a prefab to start from. Everything else set on this node overrides the prefab.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefab: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[f32; 2]>,
+    // Degrees, clockwise on screen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<[f32; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pivot: Option<[f32; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<AttachmentDescription>,
+    // Game specific components by their registered name. See ComponentRegistry.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<NodeDescription>
+}
+
+// LEARN: Internally tagged enums
+// With #[serde(tag = "type")], the variant is picked by a "type" field inside the object itself,
+// So { "type": "text", "text": "Hello" } reads as AttachmentDescription::Text.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AttachmentDescription {
+    Sprite(SpriteDescription),
+    Text(TextDescription),
+    Camera
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SpriteDescription {
+    // Path of the image, from the working directory like Texture::new
+    pub image: String,
+    // x, y, width and height of the part of the image to show. The whole image if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<[f32; 4]>,
+    // As fractions of the sprite's size, see Origin::Normalized. The top left corner if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<[f32; 2]>,
+    // r, g, b, a
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tint: Option<[f32; 4]>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub layer: i32,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_x: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_y: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TextDescription {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f32>,
+    // r, g, b, a. Black if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[f32; 4]>
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl SceneDescription {
+    pub fn from_json(json: &str) -> Result<SceneDescription, String> {
+        serde_json::from_str(json).map_err(|error| error.to_string())
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|error| error.to_string())
+    }
+
+    // The top level nodes with all prefab instances replaced by the prefab's nodes, overrides applied.
+    // Instances keep the prefab's name in "prefab", so saving can tell them apart from plain nodes.
+    pub fn resolve_nodes(&self) -> Result<Vec<NodeDescription>, String> {
+        self.nodes.iter().map(|node| self.resolve_node(node, &mut Vec::new())).collect()
+    }
+
+    // The nodes of a prefab, for spawning it while the game runs.
+    pub fn resolve_prefab(&self, prefab_name: &str) -> Result<NodeDescription, String> {
+        let prefab = self.prefabs.get(prefab_name).ok_or(format!("Unknown prefab \"{}\"", prefab_name))?;
+        self.resolve_node(prefab, &mut vec![String::from(prefab_name)])
+    }
+
+    // "prefab_stack" holds the prefabs being resolved, to catch prefabs that contain themselves.
+    // Children are resolved before the overrides are applied, so the prefab's own nodes are never resolved twice.
+    fn resolve_node(&self, node: &NodeDescription, prefab_stack: &mut Vec<String>) -> Result<NodeDescription, String> {
+        let mut resolved = node.clone();
+        resolved.children = node.children.iter()
+            .map(|child| self.resolve_node(child, prefab_stack))
+            .collect::<Result<Vec<NodeDescription>, String>>()?;
+
+        match &node.prefab {
+            Some(prefab_name) => {
+                if prefab_stack.contains(prefab_name) {
+                    return Err(format!("Prefab \"{}\" contains itself", prefab_name));
+                }
+
+                let prefab = self.prefabs.get(prefab_name)
+                    .ok_or(format!("Node \"{}\" uses unknown prefab \"{}\"", node.name, prefab_name))?;
+
+                prefab_stack.push(prefab_name.clone());
+                let base = self.resolve_node(prefab, prefab_stack)?;
+                prefab_stack.pop();
+
+                Ok(resolved.apply_overrides(base))
+            },
+            None => Ok(resolved)
+        }
+    }
+}
+
+impl NodeDescription {
+    pub fn new(name: &str) -> NodeDescription {
+        NodeDescription {
+            name: String::from(name),
+            ..Default::default()
+        }
+    }
+
+    // Lays this node's values over "base", which is what the node starts from as a prefab instance.
+    // Fields set here win, components are merged field by field, and children override the base's children
+    // With the same name. Other children are added after the base's.
+    // Children of the base that are prefab instances stay marked as such when only overridden.
+    fn apply_overrides(&self, mut base: NodeDescription) -> NodeDescription {
+        base.name = self.name.clone();
+        base.prefab = self.prefab.clone().or(base.prefab);
+        base.position = self.position.or(base.position);
+        base.rotation = self.rotation.or(base.rotation);
+        base.scale = self.scale.or(base.scale);
+        base.pivot = self.pivot.or(base.pivot);
+        base.visible = self.visible.or(base.visible);
+        base.attachment = self.attachment.clone().or(base.attachment);
+
+        for (name, value) in self.components.iter() {
+            match base.components.get_mut(name) {
+                Some(base_value) => merge_json(base_value, value),
+                None => {
+                    base.components.insert(name.clone(), value.clone());
+                }
+            }
+        }
+
+        for child in self.children.iter() {
+            match base.children.iter_mut().find(|base_child| base_child.name == child.name) {
+                // A child that is a prefab instance itself replaces the base's child instead
+                Some(base_child) if child.prefab.is_none() => *base_child = child.apply_overrides(base_child.clone()),
+                Some(base_child) => *base_child = child.clone(),
+                None => base.children.push(child.clone())
+            }
+        }
+
+        base
+    }
+}
+
+// Merges "overrides" into "base". Objects are merged key by key, anything else is replaced.
+fn merge_json(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base_map), Value::Object(override_map)) => {
+            for (key, value) in override_map.iter() {
+                match base_map.get_mut(key) {
+                    Some(base_value) => merge_json(base_value, value),
+                    None => {
+                        base_map.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        },
+        (base, overrides) => *base = overrides.clone()
+    }
+}
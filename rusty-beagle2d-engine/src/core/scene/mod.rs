@@ -0,0 +1,4 @@
+pub mod node;
+pub mod scene_graph;
+pub mod description;
+pub mod serialization;
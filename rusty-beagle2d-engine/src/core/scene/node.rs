@@ -0,0 +1,162 @@
+use crate::core::sprite::Sprite;
+use crate::core::color::Color;
+use crate::core::matrix3x2::Matrix3x2;
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::any::{Any, TypeId};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+// Refers to a node in a SceneGraph.
+// Like ecs::Entity, it carries a generation, so an id of a removed node never points at a node created later in its slot.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId {
+    pub(crate) index: u32,
+    pub(crate) generation: u32
+}
+
+// Text drawn at a node's origin.
+#[derive(Clone, Debug)]
+pub struct TextAttachment {
+    pub text: String,
+    pub scale: f32,
+    pub color: Color
+}
+
+impl TextAttachment {
+    pub fn new(text: &str, scale: f32, color: Color) -> TextAttachment {
+        TextAttachment {
+            text: String::from(text),
+            scale,
+            color
+        }
+    }
+}
+
+// What a node shows, if anything.
+pub enum Attachment {
+    // Only groups its children
+    Empty,
+    // The sprite's position, angle and scale are relative to the node, so usually left at their defaults
+    Sprite(Sprite),
+    Text(TextAttachment),
+    // The view follows the node's origin, which ends up at the top left corner of the view
+    Camera
+}
+
+pub struct Node {
+    pub(crate) name: String,
+    pub(crate) parent: Option<NodeId>,
+    pub(crate) children: Vec<NodeId>,
+    // Local transform, relative to the parent
+    pub(crate) position: Vector2,
+    // Degrees, clockwise on screen
+    pub(crate) rotation: f32,
+    pub(crate) scale: Vector2,
+    // The point in the node's own space that sits at its position, and that it rotates and scales around
+    pub(crate) pivot: Vector2,
+    pub(crate) visible: bool,
+    // The prefab the node was created from, if it is a prefab instance. See capture_scene.
+    pub(crate) prefab: Option<String>,
+    pub attachment: Attachment,
+    // Game specific data, one value per type. See ComponentRegistry for saving them with a scene.
+    pub(crate) components: HashMap<TypeId, Box<dyn Any>>,
+    // LEARN: Cell for caches
+    // The world matrix is worked out the first time someone asks for it after a change, which happens through &self.
+    // Cell lets the cache be written through a shared reference, for Copy values, with no runtime borrow checks.
+    pub(crate) world_matrix: Cell<Matrix3x2>,
+    pub(crate) world_dirty: Cell<bool>
+}
+
+impl Node {
+    pub(crate) fn new(name: &str, parent: Option<NodeId>) -> Node {
+        Node {
+            name: String::from(name),
+            parent,
+            children: Vec::new(),
+            position: Vector2::new(0.0, 0.0),
+            rotation: 0.0,
+            scale: Vector2::new(1.0, 1.0),
+            pivot: Vector2::new(0.0, 0.0),
+            visible: true,
+            prefab: None,
+            attachment: Attachment::Empty,
+            components: HashMap::new(),
+            world_matrix: Cell::new(Matrix3x2::identity()),
+            world_dirty: Cell::new(true)
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    // In draw order. Later children are drawn on top of earlier ones.
+    pub fn get_children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    pub fn get_position(&self) -> Vector2 {
+        self.position
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn get_scale(&self) -> Vector2 {
+        self.scale
+    }
+
+    pub fn get_pivot(&self) -> Vector2 {
+        self.pivot
+    }
+
+    // Only says whether this node itself is hidden. A visible node with a hidden ancestor isn't drawn either.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn get_prefab(&self) -> Option<&str> {
+        self.prefab.as_deref()
+    }
+
+    pub fn get_attachment(&self) -> &Attachment {
+        &self.attachment
+    }
+
+    // The transform from this node's space into its parent's.
+    pub fn get_local_matrix(&self) -> Matrix3x2 {
+        Matrix3x2::from_transform(self.position, self.rotation, self.scale, self.pivot)
+    }
+
+    // Adds a component, replacing (and returning) any component of the same type the node had.
+    pub fn insert_component<T: 'static>(&mut self, component: T) -> Option<T> {
+        self.components
+            .insert(TypeId::of::<T>(), Box::new(component))
+            .map(|old| *old.downcast::<T>().expect("Component has the wrong type"))
+    }
+
+    pub fn remove_component<T: 'static>(&mut self) -> Option<T> {
+        self.components
+            .remove(&TypeId::of::<T>())
+            .map(|old| *old.downcast::<T>().expect("Component has the wrong type"))
+    }
+
+    pub fn get_component<T: 'static>(&self) -> Option<&T> {
+        self.components.get(&TypeId::of::<T>())?.downcast_ref::<T>()
+    }
+
+    pub fn get_component_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.components.get_mut(&TypeId::of::<T>())?.downcast_mut::<T>()
+    }
+
+    pub fn has_component<T: 'static>(&self) -> bool {
+        self.components.contains_key(&TypeId::of::<T>())
+    }
+}
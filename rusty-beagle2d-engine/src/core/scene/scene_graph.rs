@@ -0,0 +1,576 @@
+use crate::core::scene::node::{Node, NodeId, Attachment};
+use crate::core::scene::description::NodeDescription;
+use crate::core::sprite::Sprite;
+use crate::core::matrix3x2::Matrix3x2;
+
+use linear_beaglebra::vector2::Vector2;
+
+use std::collections::BTreeMap;
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>
+}
+
+// A tree of nodes, where each node is placed relative to its parent.
+// Moving, rotating or scaling a node moves everything attached below it, like a sword held in a character's hand.
+// LEARN: Arenas instead of references
+// A tree where children point at parents and parents at children doesn't fit Rust's ownership rules with plain references.
+// Instead all nodes live in one Vec owned by the graph, and refer to each other by NodeId.
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free_indices: Vec<u32>,
+    roots: Vec<NodeId>,
+    node_count: usize,
+    // The prefabs of the scene files loaded into the graph, so saving can write their instances as instances again
+    pub(crate) prefabs: BTreeMap<String, NodeDescription>
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph {
+            slots: Vec::new(),
+            free_indices: Vec::new(),
+            roots: Vec::new(),
+            node_count: 0,
+            prefabs: BTreeMap::new()
+        }
+    }
+
+    // Adds a node at the top level of the scene.
+    pub fn create_node(&mut self, name: &str) -> NodeId {
+        let id = self.allocate(Node::new(name, None));
+        self.roots.push(id);
+        id
+    }
+
+    // Adds a node as the last child of "parent", drawn on top of its siblings.
+    pub fn create_child(&mut self, parent: NodeId, name: &str) -> NodeId {
+        self.get_node_or_panic(parent);
+
+        let id = self.allocate(Node::new(name, Some(parent)));
+        self.get_node_mut_or_panic(parent).children.push(id);
+        id
+    }
+
+    // Removes the node and everything below it. Returns false if it was already removed.
+    pub fn remove_node(&mut self, id: NodeId) -> bool {
+        let parent = match self.get_node(id) {
+            Some(node) => node.parent,
+            None => return false
+        };
+
+        self.detach(id, parent);
+
+        let mut to_remove = vec![id];
+        while let Some(current) = to_remove.pop() {
+            let slot = &mut self.slots[current.index as usize];
+            let node = slot.node.take().expect("Child of a node was already removed");
+
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_indices.push(current.index);
+            self.node_count -= 1;
+
+            to_remove.extend(node.children);
+        }
+
+        true
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get_node(id).is_some()
+    }
+
+    pub fn get_node(&self, id: NodeId) -> Option<&Node> {
+        let slot = self.slots.get(id.index as usize)?;
+
+        if slot.generation != id.generation {
+            return None;
+        }
+
+        slot.node.as_ref()
+    }
+
+    pub fn get_node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+
+        if slot.generation != id.generation {
+            return None;
+        }
+
+        slot.node.as_mut()
+    }
+
+    // The top level nodes, in draw order.
+    pub fn get_roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn get_node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn get_prefabs(&self) -> &BTreeMap<String, NodeDescription> {
+        &self.prefabs
+    }
+
+    // The first node with the name, searching depth first in draw order.
+    pub fn find_node(&self, name: &str) -> Option<NodeId> {
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().cloned().collect();
+
+        while let Some(id) = stack.pop() {
+            let node = self.get_node_or_panic(id);
+
+            if node.name == name {
+                return Some(id);
+            }
+
+            stack.extend(node.children.iter().rev());
+        }
+
+        None
+    }
+
+    pub fn set_position(&mut self, id: NodeId, x: f32, y: f32) {
+        self.get_node_mut_or_panic(id).position = Vector2::new(x, y);
+        self.mark_dirty(id);
+    }
+
+    pub fn translate(&mut self, id: NodeId, x: f32, y: f32) {
+        let position = self.get_node_or_panic(id).position;
+        self.set_position(id, position.x + x, position.y + y);
+    }
+
+    // Degrees, clockwise on screen.
+    pub fn set_rotation(&mut self, id: NodeId, degrees: f32) {
+        self.get_node_mut_or_panic(id).rotation = degrees;
+        self.mark_dirty(id);
+    }
+
+    pub fn rotate(&mut self, id: NodeId, degrees: f32) {
+        let rotation = self.get_node_or_panic(id).rotation;
+        self.set_rotation(id, rotation + degrees);
+    }
+
+    pub fn set_scale(&mut self, id: NodeId, scale_x: f32, scale_y: f32) {
+        self.get_node_mut_or_panic(id).scale = Vector2::new(scale_x, scale_y);
+        self.mark_dirty(id);
+    }
+
+    // The point in the node's own space that sits at its position, and that it rotates and scales around.
+    pub fn set_pivot(&mut self, id: NodeId, x: f32, y: f32) {
+        self.get_node_mut_or_panic(id).pivot = Vector2::new(x, y);
+        self.mark_dirty(id);
+    }
+
+    // Hiding a node also hides everything below it.
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) {
+        self.get_node_mut_or_panic(id).visible = visible;
+    }
+
+    pub fn set_attachment(&mut self, id: NodeId, attachment: Attachment) {
+        self.get_node_mut_or_panic(id).attachment = attachment;
+    }
+
+    // The sprite attached to the node, if it has one.
+    pub fn get_sprite_mut(&mut self, id: NodeId) -> Option<&mut Sprite> {
+        match &mut self.get_node_mut(id)?.attachment {
+            Attachment::Sprite(sprite) => Some(sprite),
+            _ => None
+        }
+    }
+
+    // The transform from the node's space into world space.
+    // Only worked out again if the node or one of its ancestors changed since the last call.
+    pub fn get_world_matrix(&self, id: NodeId) -> Matrix3x2 {
+        let node = self.get_node_or_panic(id);
+
+        if !node.world_dirty.get() {
+            return node.world_matrix.get();
+        }
+
+        let parent_matrix = match node.parent {
+            Some(parent) => self.get_world_matrix(parent),
+            None => Matrix3x2::identity()
+        };
+
+        let world_matrix = parent_matrix.multiply(&node.get_local_matrix());
+        node.world_matrix.set(world_matrix);
+        node.world_dirty.set(false);
+
+        world_matrix
+    }
+
+    // Where the node's pivot ends up in the world.
+    pub fn get_world_position(&self, id: NodeId) -> Vector2 {
+        let pivot = self.get_node_or_panic(id).pivot;
+        self.get_world_matrix(id).transform_point(pivot)
+    }
+
+    pub fn get_world_rotation(&self, id: NodeId) -> f32 {
+        self.get_world_matrix(id).get_rotation()
+    }
+
+    pub fn get_world_scale(&self, id: NodeId) -> Vector2 {
+        self.get_world_matrix(id).get_scale()
+    }
+
+    // Moves the node so its pivot ends up at the world position.
+    pub fn set_world_position(&mut self, id: NodeId, x: f32, y: f32) {
+        let parent_matrix = self.get_parent_world_matrix(id);
+
+        let position = match parent_matrix.inverse() {
+            Some(inverse) => inverse.transform_point(Vector2::new(x, y)),
+            // The parent is scaled to nothing, so any position ends up in the same spot
+            None => return
+        };
+
+        self.set_position(id, position.x, position.y);
+    }
+
+    // Moves the node under a new parent (or to the top level with None), as its last child.
+    // The node stays where it was in the world: its local transform is changed to make up for the new parent's.
+    // NOTE: If the new parent is scaled to nothing, the world transform can't be kept and the local one is kept instead.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let world_matrix = self.get_world_matrix(id);
+
+        self.set_parent_keep_local(id, parent);
+
+        let local_matrix = match self.get_parent_world_matrix(id).inverse() {
+            Some(inverse) => inverse.multiply(&world_matrix),
+            None => return
+        };
+
+        let node = self.get_node_mut_or_panic(id);
+        node.position = local_matrix.transform_point(node.pivot);
+        node.rotation = local_matrix.get_rotation();
+        node.scale = local_matrix.get_scale();
+        self.mark_dirty(id);
+    }
+
+    // Like set_parent, but keeps the local transform, so the node moves along with its new parent.
+    pub fn set_parent_keep_local(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let old_parent = self.get_node_or_panic(id).parent;
+
+        if let Some(new_parent) = parent {
+            // Walk up from the new parent. Running into the node means it would become its own ancestor.
+            let mut ancestor = Some(new_parent);
+            while let Some(current) = ancestor {
+                if current == id {
+                    panic!("Can't move node {:?} below itself or one of its children", id);
+                }
+
+                ancestor = self.get_node_or_panic(current).parent;
+            }
+        }
+
+        self.detach(id, old_parent);
+
+        match parent {
+            Some(new_parent) => self.get_node_mut_or_panic(new_parent).children.push(id),
+            None => self.roots.push(id)
+        }
+
+        self.get_node_mut_or_panic(id).parent = parent;
+        self.mark_dirty(id);
+    }
+
+    // Calls "function" for every node that is shown, parents before their children, in draw order.
+    // Hidden nodes are skipped along with everything below them.
+    pub fn for_each_visible<F: FnMut(NodeId, &Node, &Matrix3x2)>(&self, mut function: F) {
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().cloned().collect();
+
+        while let Some(id) = stack.pop() {
+            let node = self.get_node_or_panic(id);
+
+            if !node.visible {
+                continue;
+            }
+
+            function(id, node, &self.get_world_matrix(id));
+
+            stack.extend(node.children.iter().rev());
+        }
+    }
+
+    // World position of the first shown camera node, which is where the top left corner of the view should be.
+    pub fn get_camera_position(&self) -> Option<Vector2> {
+        let mut camera = None;
+
+        self.for_each_visible(|id, node, _| {
+            if camera.is_none() {
+                if let Attachment::Camera = node.attachment {
+                    camera = Some(id);
+                }
+            }
+        });
+
+        camera.map(|id| self.get_world_position(id))
+    }
+
+    fn allocate(&mut self, node: Node) -> NodeId {
+        self.node_count += 1;
+
+        match self.free_indices.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: self.slots.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    // Takes the node out of its parent's children, or out of the roots.
+    fn detach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let siblings = match parent {
+            Some(parent) => &mut self.get_node_mut_or_panic(parent).children,
+            None => &mut self.roots
+        };
+
+        siblings.retain(|&sibling| sibling != id);
+    }
+
+    fn get_parent_world_matrix(&self, id: NodeId) -> Matrix3x2 {
+        match self.get_node_or_panic(id).parent {
+            Some(parent) => self.get_world_matrix(parent),
+            None => Matrix3x2::identity()
+        }
+    }
+
+    // Flags the node's world matrix, and those of everything below it, to be worked out again.
+    // A clean node always has clean ancestors, since working out a world matrix works out the parent's first.
+    // So once a dirty node is found, everything below it is dirty already and can be skipped.
+    fn mark_dirty(&self, id: NodeId) {
+        let mut stack = vec![id];
+
+        while let Some(current) = stack.pop() {
+            let node = self.get_node_or_panic(current);
+
+            if node.world_dirty.get() && current != id {
+                continue;
+            }
+
+            node.world_dirty.set(true);
+            stack.extend(node.children.iter());
+        }
+    }
+
+    fn get_node_or_panic(&self, id: NodeId) -> &Node {
+        self.get_node(id).unwrap_or_else(|| panic!("Node {:?} has been removed from the scene", id))
+    }
+
+    fn get_node_mut_or_panic(&mut self, id: NodeId) -> &mut Node {
+        self.get_node_mut(id).unwrap_or_else(|| panic!("Node {:?} has been removed from the scene", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vector2, expected: (f32, f32)) {
+        assert!((actual.x - expected.0).abs() < 1e-3 && (actual.y - expected.1).abs() < 1e-3,
+            "Expected {:?}, got {:?}", expected, actual);
+    }
+
+    fn assert_angle_near(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "Expected {} degrees, got {}", expected, actual);
+    }
+
+    #[test]
+    fn moving_a_parent_moves_cached_children() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.create_node("parent");
+        let child = graph.create_child(parent, "child");
+        let grandchild = graph.create_child(child, "grandchild");
+        graph.set_position(child, 10.0, 0.0);
+        graph.set_position(grandchild, 5.0, 0.0);
+
+        // Caches every world matrix
+        assert_near(graph.get_world_position(grandchild), (15.0, 0.0));
+
+        graph.set_position(parent, 100.0, 0.0);
+        assert_near(graph.get_world_position(child), (110.0, 0.0));
+        assert_near(graph.get_world_position(grandchild), (115.0, 0.0));
+
+        // The child is dirty and the grandchild still cached when the parent changes again
+        graph.set_position(child, 20.0, 0.0);
+        assert_near(graph.get_world_position(parent), (100.0, 0.0));
+        graph.set_rotation(parent, 90.0);
+        assert_near(graph.get_world_position(grandchild), (100.0, 25.0));
+        assert_angle_near(graph.get_world_rotation(grandchild), 90.0);
+
+        graph.set_scale(parent, 2.0, 2.0);
+        assert_near(graph.get_world_position(grandchild), (100.0, 50.0));
+        assert_near(graph.get_world_scale(grandchild), (2.0, 2.0));
+    }
+
+    #[test]
+    fn reparenting_keeps_the_world_transform() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.create_node("parent");
+        graph.set_position(parent, 100.0, 50.0);
+        graph.set_rotation(parent, 90.0);
+        graph.set_scale(parent, 2.0, 2.0);
+
+        let node = graph.create_node("node");
+        graph.set_position(node, 120.0, 60.0);
+        graph.set_rotation(node, 30.0);
+        graph.set_scale(node, 3.0, 1.0);
+
+        graph.set_parent(node, Some(parent));
+        assert_eq!(graph.get_roots(), &[parent]);
+        assert_eq!(graph.get_node(parent).unwrap().children, vec![node]);
+
+        assert_near(graph.get_world_position(node), (120.0, 60.0));
+        assert_angle_near(graph.get_world_rotation(node), 30.0);
+        assert_near(graph.get_world_scale(node), (3.0, 1.0));
+
+        // The local transform makes up for the parent's
+        let local = graph.get_node(node).unwrap();
+        assert_near(local.position, (5.0, -10.0));
+        assert_angle_near(local.rotation, -60.0);
+        assert_near(local.scale, (1.5, 0.5));
+
+        // And back out to the top level
+        graph.set_parent(node, None);
+        let local = graph.get_node(node).unwrap();
+        assert_near(local.position, (120.0, 60.0));
+        assert_angle_near(local.rotation, 30.0);
+        assert_near(local.scale, (3.0, 1.0));
+    }
+
+    #[test]
+    fn reparenting_can_keep_the_local_transform() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.create_node("parent");
+        graph.set_position(parent, 100.0, 50.0);
+        graph.set_rotation(parent, 90.0);
+        graph.set_scale(parent, 2.0, 2.0);
+
+        let node = graph.create_node("node");
+        graph.set_position(node, 120.0, 60.0);
+        graph.set_rotation(node, 30.0);
+        graph.set_scale(node, 3.0, 1.0);
+        assert_near(graph.get_world_position(node), (120.0, 60.0));
+
+        // Moves along with the parent, from the cached world matrix
+        graph.set_parent_keep_local(node, Some(parent));
+        assert_near(graph.get_world_position(node), (-20.0, 290.0));
+        assert_angle_near(graph.get_world_rotation(node), 120.0);
+        assert_near(graph.get_world_scale(node), (6.0, 2.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "below itself")]
+    fn reparenting_below_a_descendant_panics() {
+        let mut graph = SceneGraph::new();
+        let node = graph.create_node("node");
+        let child = graph.create_child(node, "child");
+        let grandchild = graph.create_child(child, "grandchild");
+
+        graph.set_parent(node, Some(grandchild));
+    }
+
+    #[test]
+    #[should_panic(expected = "below itself")]
+    fn reparenting_below_itself_panics() {
+        let mut graph = SceneGraph::new();
+        let node = graph.create_node("node");
+
+        graph.set_parent_keep_local(node, Some(node));
+    }
+
+    #[test]
+    fn removing_a_node_removes_its_subtree() {
+        let mut graph = SceneGraph::new();
+        let root = graph.create_node("root");
+        let removed = graph.create_child(root, "removed");
+        let removed_child = graph.create_child(removed, "removed_child");
+        let kept = graph.create_child(root, "kept");
+        assert_eq!(graph.get_node_count(), 4);
+
+        assert!(graph.remove_node(removed));
+        assert!(!graph.contains(removed));
+        assert!(!graph.contains(removed_child));
+        assert_eq!(graph.get_node_count(), 2);
+        assert_eq!(graph.get_node(root).unwrap().children, vec![kept]);
+        assert_eq!(graph.find_node("removed_child"), None);
+        assert!(!graph.remove_node(removed));
+
+        // The slots are reused, but the old ids stay invalid
+        let new_nodes = [graph.create_node("new"), graph.create_node("newer")];
+        let mut reused: Vec<u32> = new_nodes.iter().map(|id| id.index).collect();
+        reused.sort();
+        assert_eq!(reused, vec![removed.index, removed_child.index]);
+
+        for old in [removed, removed_child] {
+            assert!(!graph.contains(old));
+            assert!(graph.get_node(old).is_none());
+            assert!(graph.get_node_mut(old).is_none());
+            assert!(!graph.remove_node(old));
+        }
+
+        assert_eq!(graph.get_node_count(), 4);
+        assert_eq!(graph.find_node("newer"), Some(new_nodes[1]));
+    }
+
+    #[test]
+    fn visiting_visible_nodes() {
+        let mut graph = SceneGraph::new();
+        let first = graph.create_node("first");
+        let shown = graph.create_child(first, "shown");
+        let shown_child = graph.create_child(shown, "shown_child");
+        let hidden = graph.create_child(first, "hidden");
+        graph.create_child(hidden, "hidden_child");
+        let last = graph.create_child(first, "last");
+        let second = graph.create_node("second");
+        let hidden_root = graph.create_node("hidden_root");
+        graph.create_child(hidden_root, "below_hidden_root");
+
+        graph.set_visible(hidden, false);
+        graph.set_visible(hidden_root, false);
+        graph.set_position(first, 10.0, 20.0);
+        graph.set_position(shown_child, 1.0, 2.0);
+
+        let mut visited = Vec::new();
+        graph.for_each_visible(|id, _, world_matrix| {
+            visited.push(id);
+            assert_eq!(*world_matrix, graph.get_world_matrix(id));
+        });
+
+        // Depth first, parents before children, in draw order
+        assert_eq!(visited, vec![first, shown, shown_child, last, second]);
+        assert_near(graph.get_world_matrix(shown_child).get_translation(), (11.0, 22.0));
+    }
+
+    #[test]
+    fn camera_position() {
+        let mut graph = SceneGraph::new();
+        assert_eq!(graph.get_camera_position(), None);
+
+        let player = graph.create_node("player");
+        graph.set_position(player, 100.0, 50.0);
+        let hidden_camera = graph.create_child(player, "hidden_camera");
+        graph.set_attachment(hidden_camera, Attachment::Camera);
+        graph.set_visible(hidden_camera, false);
+        assert_eq!(graph.get_camera_position(), None);
+
+        // Follows the player
+        let camera = graph.create_child(player, "camera");
+        graph.set_attachment(camera, Attachment::Camera);
+        graph.set_position(camera, -10.0, -20.0);
+        assert_near(graph.get_camera_position().unwrap(), (90.0, 30.0));
+
+        graph.translate(player, 5.0, 5.0);
+        assert_near(graph.get_camera_position().unwrap(), (95.0, 35.0));
+
+        // The first shown camera wins
+        graph.set_visible(hidden_camera, true);
+        assert_near(graph.get_camera_position().unwrap(), (105.0, 55.0));
+    }
+}
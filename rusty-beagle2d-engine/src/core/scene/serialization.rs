@@ -0,0 +1,614 @@
+// Loading scene files into a SceneGraph and saving a SceneGraph back to a scene file.
+// See description.rs for the file format.
+
+use crate::core::scene::description::{SceneDescription, NodeDescription, AttachmentDescription, SpriteDescription, TextDescription};
+use crate::core::scene::node::{Node, NodeId, Attachment, TextAttachment};
+use crate::core::scene::scene_graph::SceneGraph;
+use crate::core::sprite::{Sprite, Origin};
+use crate::core::texture::Texture;
+use crate::core::color::Color;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use std::any::{Any, TypeId};
+use std::path::Path;
+
+struct RegisteredComponent {
+    name: String,
+    type_id: TypeId,
+    load: fn(&Value) -> Result<Box<dyn Any>, String>,
+    save: fn(&dyn Any) -> Result<Value, String>
+}
+
+// The game's own component types that may appear in scene files, and the names they go by there.
+// Node components of types that aren't registered are left out when saving.
+pub struct ComponentRegistry {
+    components: Vec<RegisteredComponent>
+}
+
+impl ComponentRegistry {
+    pub fn new() -> ComponentRegistry {
+        ComponentRegistry {
+            components: Vec::new()
+        }
+    }
+
+    // Lets scene files hold components of type T under "name", for example:
+    // registry.register::<Health>("health") for "components": { "health": { "max": 10 } }
+    pub fn register<T: Serialize + DeserializeOwned + 'static>(&mut self, name: &str) {
+        if self.components.iter().any(|component| component.name == name || component.type_id == TypeId::of::<T>()) {
+            panic!("Component {} or the name \"{}\" was already registered", std::any::type_name::<T>(), name);
+        }
+
+        self.components.push(RegisteredComponent {
+            name: String::from(name),
+            type_id: TypeId::of::<T>(),
+            load: load_component::<T>,
+            save: save_component::<T>
+        });
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<&RegisteredComponent> {
+        self.components.iter().find(|component| component.name == name)
+    }
+
+    fn find_by_type(&self, type_id: TypeId) -> Option<&RegisteredComponent> {
+        self.components.iter().find(|component| component.type_id == type_id)
+    }
+}
+
+// LEARN: Generic functions as function pointers
+// load_component::<Health> is a plain fn with the type baked in, so the registry can store
+// One per component type without knowing the types itself.
+fn load_component<T: DeserializeOwned + 'static>(value: &Value) -> Result<Box<dyn Any>, String> {
+    let component: T = serde_json::from_value(value.clone()).map_err(|error| error.to_string())?;
+    Ok(Box::new(component))
+}
+
+fn save_component<T: Serialize + 'static>(component: &dyn Any) -> Result<Value, String> {
+    let component = component.downcast_ref::<T>().expect("Component has the wrong type");
+    serde_json::to_value(component).map_err(|error| error.to_string())
+}
+
+pub fn load_scene_description(path: &Path) -> Result<SceneDescription, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read scene {}: {}", path.display(), error))?;
+
+    SceneDescription::from_json(&json)
+        .map_err(|error| format!("Failed to load scene {}: {}", path.display(), error))
+}
+
+pub fn save_scene_description(path: &Path, description: &SceneDescription) -> Result<(), String> {
+    let json = description.to_json()?;
+
+    std::fs::write(path, json)
+        .map_err(|error| format!("Failed to write scene {}: {}", path.display(), error))
+}
+
+// Reads a scene file and adds its nodes to the top level of "scene". Returns the added top level nodes.
+pub fn load_scene(path: &Path, scene: &mut SceneGraph, registry: &ComponentRegistry) -> Result<Vec<NodeId>, String> {
+    let description = load_scene_description(path)?;
+
+    instantiate_scene(&description, scene, registry)
+        .map_err(|error| format!("Failed to load scene {}: {}", path.display(), error))
+}
+
+// Writes every node of "scene" to a scene file.
+pub fn save_scene(path: &Path, scene: &SceneGraph, registry: &ComponentRegistry) -> Result<(), String> {
+    let description = capture_scene(scene, registry)?;
+    save_scene_description(path, &description)
+}
+
+// Adds the nodes of the description to the top level of "scene". Returns the added top level nodes.
+// If anything fails, nothing is added.
+pub fn instantiate_scene(description: &SceneDescription, scene: &mut SceneGraph, registry: &ComponentRegistry) -> Result<Vec<NodeId>, String> {
+    let nodes = description.resolve_nodes()?;
+    add_prefabs(description, scene);
+
+    let mut created: Vec<NodeId> = Vec::with_capacity(nodes.len());
+
+    for node in nodes.iter() {
+        match create_node(node, None, scene, registry) {
+            Ok(id) => created.push(id),
+            Err(error) => {
+                for id in created {
+                    scene.remove_node(id);
+                }
+
+                return Err(error);
+            }
+        }
+    }
+
+    Ok(created)
+}
+
+// Creates an instance of one of the description's prefabs below "parent", or at the top level with None.
+pub fn instantiate_prefab(description: &SceneDescription, prefab_name: &str, parent: Option<NodeId>, scene: &mut SceneGraph, registry: &ComponentRegistry) -> Result<NodeId, String> {
+    let mut node = description.resolve_prefab(prefab_name)?;
+    node.prefab = Some(String::from(prefab_name));
+    add_prefabs(description, scene);
+
+    create_node(&node, parent, scene, registry)
+}
+
+// Describes every node of "scene", so it can be saved.
+// Prefab instances are written as the prefab's name plus whatever differs from the prefab now,
+// And the prefabs of all loaded scene files are written along with them.
+// NOTE: Overrides can only add or change things, so children or components removed from an instance
+// Come back when the scene is loaded again.
+pub fn capture_scene(scene: &SceneGraph, registry: &ComponentRegistry) -> Result<SceneDescription, String> {
+    let mut description = SceneDescription {
+        prefabs: scene.get_prefabs().clone(),
+        nodes: Vec::new()
+    };
+
+    for &id in scene.get_roots() {
+        let node = capture_node(scene, id, registry)?;
+        let node = capture_instances(node, &description, registry)?;
+        description.nodes.push(node);
+    }
+
+    Ok(description)
+}
+
+// NOTE: A prefab with the same name as one loaded before replaces it, also for saving the earlier instances.
+fn add_prefabs(description: &SceneDescription, scene: &mut SceneGraph) {
+    for (name, prefab) in description.prefabs.iter() {
+        scene.prefabs.insert(name.clone(), prefab.clone());
+    }
+}
+
+// Expects a description with its prefabs resolved. Removes what it created if anything fails.
+fn create_node(description: &NodeDescription, parent: Option<NodeId>, scene: &mut SceneGraph, registry: &ComponentRegistry) -> Result<NodeId, String> {
+    let id = match parent {
+        Some(parent) => scene.create_child(parent, &description.name),
+        None => scene.create_node(&description.name)
+    };
+
+    if let Err(error) = fill_node(id, description, scene, registry) {
+        scene.remove_node(id);
+        return Err(format!("Node \"{}\": {}", description.name, error));
+    }
+
+    Ok(id)
+}
+
+fn fill_node(id: NodeId, description: &NodeDescription, scene: &mut SceneGraph, registry: &ComponentRegistry) -> Result<(), String> {
+    if let Some([x, y]) = description.position {
+        scene.set_position(id, x, y);
+    }
+
+    if let Some(rotation) = description.rotation {
+        scene.set_rotation(id, rotation);
+    }
+
+    if let Some([x, y]) = description.scale {
+        scene.set_scale(id, x, y);
+    }
+
+    if let Some([x, y]) = description.pivot {
+        scene.set_pivot(id, x, y);
+    }
+
+    if let Some(visible) = description.visible {
+        scene.set_visible(id, visible);
+    }
+
+    scene.get_node_mut(id).expect("Node was just created").prefab = description.prefab.clone();
+
+    if let Some(attachment) = &description.attachment {
+        scene.set_attachment(id, create_attachment(attachment)?);
+    }
+
+    for (name, value) in description.components.iter() {
+        let registered = registry.find_by_name(name).ok_or(format!("Unknown component \"{}\"", name))?;
+        let component = (registered.load)(value).map_err(|error| format!("Component \"{}\": {}", name, error))?;
+
+        let node = scene.get_node_mut(id).expect("Node was just created");
+        node.components.insert(registered.type_id, component);
+    }
+
+    for child in description.children.iter() {
+        create_node(child, Some(id), scene, registry)?;
+    }
+
+    Ok(())
+}
+
+fn create_attachment(description: &AttachmentDescription) -> Result<Attachment, String> {
+    match description {
+        AttachmentDescription::Sprite(sprite) => Ok(Attachment::Sprite(create_sprite(sprite)?)),
+        AttachmentDescription::Text(text) => Ok(Attachment::Text(TextAttachment::new(
+            &text.text,
+            text.scale.unwrap_or(1.0),
+            text.color.map_or(Color::BLACK, to_color)))),
+        AttachmentDescription::Camera => Ok(Attachment::Camera)
+    }
+}
+
+// TODO: Sprites using the same image each load their own texture. Share them once there is an asset cache.
+fn create_sprite(description: &SpriteDescription) -> Result<Sprite, String> {
+    // Texture::new panics on files it can't load, so at least catch the missing ones
+    if !Path::new(&description.image).is_file() {
+        return Err(format!("Image {} doesn't exist", description.image));
+    }
+
+    let mut sprite = Sprite::new(Box::new(Texture::new(description.image.clone())));
+
+    if let Some([x, y, width, height]) = description.region {
+        sprite.set_render_view(x, y, width, height);
+    }
+
+    if let Some([x, y]) = description.origin {
+        sprite.set_origin(Origin::Normalized(x, y));
+    }
+
+    if let Some(tint) = description.tint {
+        sprite.set_tint(to_color(tint));
+    }
+
+    sprite.layer = description.layer;
+    sprite.flip_x = description.flip_x;
+    sprite.flip_y = description.flip_y;
+
+    Ok(sprite)
+}
+
+// Only values that differ from a new node's are written, to keep the files short.
+// Prefab instances are described in full here too, with the prefab's name. See capture_instances.
+fn capture_node(scene: &SceneGraph, id: NodeId, registry: &ComponentRegistry) -> Result<NodeDescription, String> {
+    let node = scene.get_node(id).expect("Scene graph holds a removed node");
+    let mut description = NodeDescription::new(node.get_name());
+    description.prefab = node.prefab.clone();
+
+    let position = node.get_position();
+    if position.x != 0.0 || position.y != 0.0 {
+        description.position = Some([position.x, position.y]);
+    }
+
+    if node.get_rotation() != 0.0 {
+        description.rotation = Some(node.get_rotation());
+    }
+
+    let scale = node.get_scale();
+    if scale.x != 1.0 || scale.y != 1.0 {
+        description.scale = Some([scale.x, scale.y]);
+    }
+
+    let pivot = node.get_pivot();
+    if pivot.x != 0.0 || pivot.y != 0.0 {
+        description.pivot = Some([pivot.x, pivot.y]);
+    }
+
+    if !node.is_visible() {
+        description.visible = Some(false);
+    }
+
+    description.attachment = capture_attachment(node)
+        .map_err(|error| format!("Node \"{}\": {}", node.get_name(), error))?;
+
+    for (type_id, component) in node.components.iter() {
+        if let Some(registered) = registry.find_by_type(*type_id) {
+            let value = (registered.save)(component.as_ref())
+                .map_err(|error| format!("Node \"{}\", component \"{}\": {}", node.get_name(), registered.name, error))?;
+
+            description.components.insert(registered.name.clone(), value);
+        }
+    }
+
+    for &child in node.get_children() {
+        description.children.push(capture_node(scene, child, registry)?);
+    }
+
+    Ok(description)
+}
+
+// Turns the prefab instances in a captured node tree back into the prefab's name and their overrides.
+fn capture_instances(mut node: NodeDescription, scene: &SceneDescription, registry: &ComponentRegistry) -> Result<NodeDescription, String> {
+    match node.prefab.clone() {
+        Some(prefab_name) => {
+            let base = scene.resolve_prefab(&prefab_name)
+                .map_err(|error| format!("Node \"{}\": {}", node.name, error))?;
+
+            get_overrides(node, &base, scene, registry)
+        },
+        None => {
+            node.children = node.children.into_iter()
+                .map(|child| capture_instances(child, scene, registry))
+                .collect::<Result<Vec<NodeDescription>, String>>()?;
+
+            Ok(node)
+        }
+    }
+}
+
+// The opposite of NodeDescription::apply_overrides: only what "node" changes about "base".
+fn get_overrides(node: NodeDescription, base: &NodeDescription, scene: &SceneDescription, registry: &ComponentRegistry) -> Result<NodeDescription, String> {
+    let mut overrides = NodeDescription::new(&node.name);
+    overrides.prefab = node.prefab;
+    overrides.position = get_override(node.position, base.position, [0.0, 0.0]);
+    overrides.rotation = get_override(node.rotation, base.rotation, 0.0);
+    overrides.scale = get_override(node.scale, base.scale, [1.0, 1.0]);
+    overrides.pivot = get_override(node.pivot, base.pivot, [0.0, 0.0]);
+    overrides.visible = get_override(node.visible, base.visible, true);
+
+    if node.attachment != base.attachment {
+        overrides.attachment = node.attachment;
+    }
+
+    for (name, value) in node.components.into_iter() {
+        let base_value = match base.components.get(name.as_str()) {
+            Some(base_value) => base_value,
+            None => {
+                overrides.components.insert(name, value);
+                continue;
+            }
+        };
+
+        // The prefab may leave out fields the component type fills in with defaults, which the captured value has
+        let base_value = match registry.find_by_name(&name) {
+            Some(registered) => (registered.load)(base_value).and_then(|component| (registered.save)(component.as_ref()))?,
+            None => base_value.clone()
+        };
+
+        if let Some(difference) = get_json_difference(&value, &base_value) {
+            overrides.components.insert(name, difference);
+        }
+    }
+
+    for child in node.children.into_iter() {
+        match base.children.iter().find(|base_child| base_child.name == child.name) {
+            // Part of the prefab, so only its own overrides are needed
+            Some(base_child) if base_child.prefab == child.prefab => {
+                let mut child_overrides = get_overrides(child, base_child, scene, registry)?;
+                child_overrides.prefab = None;
+
+                if child_overrides != NodeDescription::new(&child_overrides.name) {
+                    overrides.children.push(child_overrides);
+                }
+            },
+            _ => overrides.children.push(capture_instances(child, scene, registry)?)
+        }
+    }
+
+    Ok(overrides)
+}
+
+// The value if it differs from the base's, where left out values are the defaults of a new node.
+fn get_override<T: PartialEq + Copy>(value: Option<T>, base: Option<T>, default: T) -> Option<T> {
+    let value = value.unwrap_or(default);
+
+    if value == base.unwrap_or(default) {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+// What has to be merged into "base" to get "value", or None if they're the same. See description::merge_json.
+fn get_json_difference(value: &Value, base: &Value) -> Option<Value> {
+    match (value, base) {
+        (Value::Object(map), Value::Object(base_map)) => {
+            let mut difference = serde_json::Map::new();
+
+            for (key, value) in map.iter() {
+                let changed = match base_map.get(key) {
+                    Some(base_value) => get_json_difference(value, base_value),
+                    None => Some(value.clone())
+                };
+
+                if let Some(changed) = changed {
+                    difference.insert(key.clone(), changed);
+                }
+            }
+
+            if difference.is_empty() { None } else { Some(Value::Object(difference)) }
+        },
+        (value, base) if value == base => None,
+        (value, _) => Some(value.clone())
+    }
+}
+
+// NOTE: Sprites are saved with their image, region, origin, tint, layer and flips.
+// Their own position, angle and scale aren't, so move, turn and scale the node instead.
+// Regions from atlases that were rotated or trimmed when packed can't be saved yet.
+fn capture_attachment(node: &Node) -> Result<Option<AttachmentDescription>, String> {
+    match node.get_attachment() {
+        Attachment::Empty => Ok(None),
+        Attachment::Camera => Ok(Some(AttachmentDescription::Camera)),
+        Attachment::Text(text) => Ok(Some(AttachmentDescription::Text(TextDescription {
+            text: text.text.clone(),
+            scale: if text.scale != 1.0 { Some(text.scale) } else { None },
+            color: if text.color != Color::BLACK { Some(from_color(text.color)) } else { None }
+        }))),
+        Attachment::Sprite(sprite) => {
+            let image = sprite.texture.get_image_path()
+                .ok_or("The sprite's texture wasn't loaded from a file")?;
+
+            if sprite.texture_rotated || sprite.trim.is_some() {
+                return Err(String::from("Sprites with rotated or trimmed regions can't be saved"));
+            }
+
+            let whole_image = sprite.texture_x == 0.0 && sprite.texture_y == 0.0
+                && sprite.texture_width == sprite.texture.get_width() as f32
+                && sprite.texture_height == sprite.texture.get_height() as f32;
+
+            let (source_width, source_height) = sprite.get_source_size();
+            let origin = sprite.get_origin_in_pixels();
+            let normalized_origin = [origin.x / source_width, origin.y / source_height];
+
+            Ok(Some(AttachmentDescription::Sprite(SpriteDescription {
+                image: String::from(image),
+                region: if whole_image { None } else { Some([sprite.texture_x, sprite.texture_y, sprite.texture_width, sprite.texture_height]) },
+                origin: if origin.x != 0.0 || origin.y != 0.0 { Some(normalized_origin) } else { None },
+                tint: if sprite.tint != Color::WHITE { Some(from_color(sprite.tint)) } else { None },
+                layer: sprite.layer,
+                flip_x: sprite.flip_x,
+                flip_y: sprite.flip_y
+            })))
+        }
+    }
+}
+
+fn to_color(rgba: [f32; 4]) -> Color {
+    Color::new(rgba[0], rgba[1], rgba[2], rgba[3])
+}
+
+fn from_color(color: Color) -> [f32; 4] {
+    [color.r, color.g, color.b, color.a]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Health {
+        max: i32,
+        #[serde(default)]
+        current: i32
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Tags {
+        names: Vec<String>
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Loot {
+        gold: u32
+    }
+
+    fn create_registry() -> ComponentRegistry {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Health>("health");
+        registry.register::<Tags>("tags");
+        registry.register::<Loot>("loot");
+        registry
+    }
+
+    fn load_fixture(name: &str) -> SceneDescription {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/scenes").join(name);
+        load_scene_description(&path).unwrap()
+    }
+
+    fn load_fixture_scene(name: &str, registry: &ComponentRegistry) -> (SceneDescription, SceneGraph) {
+        let description = load_fixture(name);
+        let mut scene = SceneGraph::new();
+        instantiate_scene(&description, &mut scene, registry).unwrap();
+        (description, scene)
+    }
+
+    #[test]
+    fn fixture_scenes_round_trip() {
+        let registry = create_registry();
+
+        for name in ["prefab_overrides.json", "nested_prefabs.json", "components.json"] {
+            let (description, scene) = load_fixture_scene(name, &registry);
+            let captured = capture_scene(&scene, &registry).unwrap();
+
+            assert_eq!(captured, description, "{} didn't survive loading and saving", name);
+            assert_eq!(SceneDescription::from_json(&captured.to_json().unwrap()).unwrap(), description);
+        }
+    }
+
+    #[test]
+    fn instances_remember_their_prefab() {
+        let registry = create_registry();
+        let (_, scene) = load_fixture_scene("nested_prefabs.json", &registry);
+
+        let house = scene.find_node("house_1").unwrap();
+        assert_eq!(scene.get_node(house).unwrap().get_prefab(), Some("house"));
+        assert_eq!(scene.get_node(scene.find_node("door").unwrap()).unwrap().get_prefab(), None);
+        assert_eq!(scene.get_node(scene.find_node("street").unwrap()).unwrap().get_prefab(), None);
+
+        let lamps: Vec<Option<&str>> = scene.get_node(house).unwrap().get_children().iter()
+            .map(|&child| scene.get_node(child).unwrap().get_prefab())
+            .collect();
+        assert_eq!(lamps, vec![None, Some("lamp"), Some("lamp")]);
+
+        // The variant keeps its own name, and the scale it adds to the lamp
+        let right_lamp = scene.find_node("right_lamp").unwrap();
+        assert_eq!(scene.get_node(right_lamp).unwrap().get_prefab(), Some("big_lamp"));
+        assert_eq!(scene.get_node(right_lamp).unwrap().get_scale().x, 2.0);
+        assert_eq!(scene.get_node(right_lamp).unwrap().get_component::<Health>(), Some(&Health { max: 1, current: 1 }));
+    }
+
+    #[test]
+    fn changes_to_instances_are_saved_as_overrides() {
+        let registry = create_registry();
+        let (description, mut scene) = load_fixture_scene("prefab_overrides.json", &registry);
+
+        let sign = scene.find_node("plain_sign").unwrap();
+        scene.set_position(sign, 5.0, 6.0);
+        scene.get_node_mut(sign).unwrap().get_component_mut::<Health>().unwrap().current = 7;
+        let post = scene.get_node(sign).unwrap().get_children()[0];
+        scene.set_visible(post, false);
+
+        let captured = capture_scene(&scene, &registry).unwrap();
+        assert_eq!(captured.prefabs, description.prefabs);
+
+        let saved_sign = &captured.nodes[1];
+        assert_eq!(saved_sign.prefab.as_deref(), Some("sign"));
+        assert_eq!(saved_sign.position, Some([5.0, 6.0]));
+        assert_eq!(saved_sign.attachment, None);
+        assert_eq!(saved_sign.components.get("health"), Some(&serde_json::json!({ "current": 7 })));
+        assert_eq!(saved_sign.children.len(), 1);
+        assert_eq!(saved_sign.children[0].name, "post");
+        assert_eq!(saved_sign.children[0].prefab, None);
+        assert_eq!(saved_sign.children[0].visible, Some(false));
+        assert_eq!(saved_sign.children[0].position, None);
+
+        // And loading the saved scene gives the changed scene back
+        let mut loaded = SceneGraph::new();
+        instantiate_scene(&captured, &mut loaded, &registry).unwrap();
+        assert_eq!(capture_scene(&loaded, &registry).unwrap(), captured);
+    }
+
+    #[test]
+    fn spawned_prefabs_are_saved_as_instances() {
+        let registry = create_registry();
+        let description = load_fixture("nested_prefabs.json");
+        let mut scene = SceneGraph::new();
+
+        let lamp = instantiate_prefab(&description, "big_lamp", None, &mut scene, &registry).unwrap();
+        scene.set_rotation(lamp, 30.0);
+
+        let captured = capture_scene(&scene, &registry).unwrap();
+        assert_eq!(captured.prefabs, description.prefabs);
+
+        let mut expected = NodeDescription::new("big_lamp");
+        expected.prefab = Some(String::from("big_lamp"));
+        expected.rotation = Some(30.0);
+        assert_eq!(captured.nodes, vec![expected]);
+    }
+
+    #[test]
+    fn nodes_made_in_code_are_saved_in_full() {
+        let registry = create_registry();
+        let mut scene = SceneGraph::new();
+
+        let player = scene.create_node("player");
+        scene.set_scale(player, 2.0, 3.0);
+        scene.get_node_mut(player).unwrap().insert_component(Health { max: 4, current: 2 });
+        let label = scene.create_child(player, "label");
+        scene.set_attachment(label, Attachment::Text(TextAttachment::new("Hi", 1.0, Color::BLACK)));
+
+        let captured = capture_scene(&scene, &registry).unwrap();
+        assert!(captured.prefabs.is_empty());
+
+        let saved = &captured.nodes[0];
+        assert_eq!(saved.prefab, None);
+        assert_eq!(saved.scale, Some([2.0, 3.0]));
+        assert_eq!(saved.components.get("health"), Some(&serde_json::json!({ "max": 4, "current": 2 })));
+        assert_eq!(saved.children[0].attachment, Some(AttachmentDescription::Text(TextDescription {
+            text: String::from("Hi"),
+            scale: None,
+            color: None
+        })));
+    }
+}
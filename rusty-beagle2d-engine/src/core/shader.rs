@@ -16,7 +16,7 @@ pub enum ShaderType {
 pub struct Shader {
     source_code: String,
     shader_type: ShaderType,
-    opengl_object_id: u32
+    opengl_object_id: ogl::ShaderId
 }
 
 // LEARN: impl blocks
@@ -51,7 +51,7 @@ impl Shader {
         self.shader_type
     }
 
-    pub fn get_opengl_object_id(&self) -> u32 {
+    pub fn get_opengl_object_id(&self) -> ogl::ShaderId {
         self.opengl_object_id
     }
 }
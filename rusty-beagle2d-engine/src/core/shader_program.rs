@@ -2,7 +2,7 @@ use rusty_beagle2d_glfw::ogl;
 use crate::core::shader;
 
 pub struct ShaderProgram {
-    opengl_object_id: u32
+    opengl_object_id: ogl::ProgramId
 }
 
 impl ShaderProgram {
@@ -31,7 +31,7 @@ impl ShaderProgram {
         ogl::use_program(self.opengl_object_id);
     }
 
-    pub fn get_opengl_object_id(&self) -> u32 {
+    pub fn get_opengl_object_id(&self) -> ogl::ProgramId {
         self.opengl_object_id
     }
 }
\ No newline at end of file
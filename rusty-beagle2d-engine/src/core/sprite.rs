@@ -1,7 +1,22 @@
 use crate::core::texture;
+use crate::core::color::Color;
+use crate::core::blend_mode::BlendMode;
+use crate::core::matrix3x2::Matrix3x2;
+use crate::core::texture_region::Trim;
+
+use linear_beaglebra::vector2::Vector2;
 
 use std::boxed::{Box};
 
+// The point of the sprite that sits at its position, and that it rotates and scales around.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Origin {
+    // Fractions of the sprite's size. (0.0, 0.0) is the top left corner, (0.5, 0.5) the center.
+    Normalized(f32, f32),
+    // Pixels from the top left corner of the sprite's render view.
+    Pixels(f32, f32)
+}
+
 // LEARN Lifetime types in Structs
 // The "texture" field of Sprite is a reference to an already existing Texture instance
 // That exists in another part of the program.
@@ -16,8 +31,26 @@ pub struct Sprite {
     pub texture_y: f32,
     pub texture_width: f32,
     pub texture_height: f32,
+    // The render view is stored turned 90 degrees clockwise in the texture (see TextureRegion::rotated)
+    pub texture_rotated: bool,
+    // Where the render view sits inside the untrimmed image, for regions from a packed atlas
+    pub trim: Option<Trim>,
+    // Degrees, clockwise on screen
     pub angle: f32,
-    pub uniform_scale: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub origin: Origin,
+    // Mirror the image without moving the sprite
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // Draw order. Higher layers are drawn on top, sprites on the same layer in the order they were drawn.
+    pub layer: i32,
+    // Multiplied with the texture color. White leaves the texture unchanged, alpha fades the sprite.
+    pub tint: Color,
+    pub blend_mode: BlendMode,
+    // Per corner colors (top left, top right, bottom right, bottom left), multiplied with the tint.
+    // Used for gradients. None means plain white corners.
+    pub corner_colors: Option<[Color; 4]>,
     pub texture: Box<texture::Texture> // LEARN: Read up on Rust Box's
 }
 
@@ -29,23 +62,240 @@ pub struct Sprite {
 // the struct's type.
 impl Sprite {
     pub fn new(sprite_texture: Box<texture::Texture>) -> Sprite {
-        Sprite { 
+        Sprite {
             position_x: 0.0,
             position_y: 0.0,
             texture_x: 0.0,
             texture_y: 0.0,
             texture_width: sprite_texture.get_width() as f32,
             texture_height: sprite_texture.get_height() as f32,
+            texture_rotated: false,
+            trim: None,
             angle: 0.0,
-            uniform_scale: 1.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            origin: Origin::Normalized(0.0, 0.0),
+            flip_x: false,
+            flip_y: false,
+            layer: 0,
+            tint: Color::WHITE,
+            blend_mode: BlendMode::Alpha,
+            corner_colors: None,
             texture: sprite_texture
         }
     }
 
+    pub fn set_tint(&mut self, tint: Color) {
+        self.tint = tint;
+    }
+
+    pub fn set_corner_colors(&mut self, top_left: Color, top_right: Color, bottom_right: Color, bottom_left: Color) {
+        self.corner_colors = Some([top_left, top_right, bottom_right, bottom_left]);
+    }
+
+    // Returns the final color of each corner: tint multiplied with the corner color.
+    pub fn get_corner_colors(&self) -> [Color; 4] {
+        match self.corner_colors {
+            Some(corner_colors) => [
+                self.tint.multiply(corner_colors[0]),
+                self.tint.multiply(corner_colors[1]),
+                self.tint.multiply(corner_colors[2]),
+                self.tint.multiply(corner_colors[3])
+            ],
+            None => [self.tint; 4]
+        }
+    }
+
     pub fn set_render_view(&mut self, x: f32, y: f32, width: f32, height: f32) {
         self.texture_x = x;
         self.texture_y = y;
         self.texture_width = width;
         self.texture_height = height;
     }
-}
\ No newline at end of file
+
+    pub fn set_scale(&mut self, scale_x: f32, scale_y: f32) {
+        self.scale_x = scale_x;
+        self.scale_y = scale_y;
+    }
+
+    pub fn set_uniform_scale(&mut self, scale: f32) {
+        self.set_scale(scale, scale);
+    }
+
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin;
+    }
+
+    pub fn center_origin(&mut self) {
+        self.origin = Origin::Normalized(0.5, 0.5);
+    }
+
+    // Size of the image before trimming. Origins are relative to this, so trimmed animation frames stay aligned.
+    pub fn get_source_size(&self) -> (f32, f32) {
+        match self.trim {
+            Some(trim) => (trim.source_width, trim.source_height),
+            None => (self.texture_width, self.texture_height)
+        }
+    }
+
+    pub fn get_origin_in_pixels(&self) -> Vector2 {
+        match self.origin {
+            Origin::Normalized(x, y) => {
+                let (source_width, source_height) = self.get_source_size();
+                Vector2::new(x * source_width, y * source_height)
+            },
+            Origin::Pixels(x, y) => Vector2::new(x, y)
+        }
+    }
+
+    // The transform from the sprite's local space, where the untrimmed image spans
+    // (0, 0) to its source size, into world space.
+    // The origin is moved to (0, 0) first, then the sprite is scaled, rotated and finally placed at its position.
+    pub fn get_transform(&self) -> Matrix3x2 {
+        let origin = self.get_origin_in_pixels();
+
+        Matrix3x2::identity()
+            .translate(Vector2::new(self.position_x, self.position_y))
+            .rotate(self.angle)
+            .scale(self.scale_x, self.scale_y)
+            .translate(Vector2::new(-origin.x, -origin.y))
+    }
+
+    // World space corners in the order top left, top right, bottom right, bottom left (before any rotation).
+    pub fn get_corners(&self) -> [Vector2; 4] {
+        self.get_corners_with_parent(&Matrix3x2::identity())
+    }
+
+    // Like get_corners, for a sprite placed inside a parent, for example a scene node.
+    // The sprite's own position, angle and scale are then relative to the parent.
+    pub fn get_corners_with_parent(&self, parent: &Matrix3x2) -> [Vector2; 4] {
+        let transform = parent.multiply(&self.get_transform());
+
+        let (mut left, mut top) = match self.trim {
+            Some(trim) => (trim.offset_x, trim.offset_y),
+            None => (0.0, 0.0)
+        };
+
+        // A flipped image also mirrors where its trimmed part sits inside the source image
+        let (source_width, source_height) = self.get_source_size();
+
+        if self.flip_x {
+            left = source_width - left - self.texture_width;
+        }
+
+        if self.flip_y {
+            top = source_height - top - self.texture_height;
+        }
+
+        let right = left + self.texture_width;
+        let bottom = top + self.texture_height;
+
+        [
+            transform.transform_point(Vector2::new(left, top)),
+            transform.transform_point(Vector2::new(right, top)),
+            transform.transform_point(Vector2::new(right, bottom)),
+            transform.transform_point(Vector2::new(left, bottom))
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::texture_region::Trim;
+
+    fn assert_near(actual: Vector2, expected: (f32, f32)) {
+        assert!((actual.x - expected.0).abs() < 1e-4 && (actual.y - expected.1).abs() < 1e-4,
+            "Expected {:?}, got {:?}", expected, actual);
+    }
+
+    fn assert_corners(sprite: &Sprite, parent: &Matrix3x2, expected: [(f32, f32); 4]) {
+        for (corner, expected) in sprite.get_corners_with_parent(parent).iter().zip(expected) {
+            assert_near(*corner, expected);
+        }
+    }
+
+    // A 40x20 sprite
+    fn make_sprite() -> Sprite {
+        Sprite::new(Box::new(texture::Texture::without_pixels(40, 20)))
+    }
+
+    #[test]
+    fn centered_origins_turn_around_the_middle() {
+        let mut sprite = make_sprite();
+        sprite.position_x = 100.0;
+        sprite.position_y = 50.0;
+        sprite.center_origin();
+
+        assert_corners(&sprite, &Matrix3x2::identity(), [(80.0, 40.0), (120.0, 40.0), (120.0, 60.0), (80.0, 60.0)]);
+
+        // Clockwise on screen, so the top left corner ends up at the top right
+        sprite.angle = 90.0;
+        assert_near(sprite.get_transform().transform_point(Vector2::new(20.0, 10.0)), (100.0, 50.0));
+        assert_corners(&sprite, &Matrix3x2::identity(), [(110.0, 30.0), (110.0, 70.0), (90.0, 70.0), (90.0, 30.0)]);
+    }
+
+    #[test]
+    fn pixel_origins() {
+        let mut sprite = make_sprite();
+        sprite.position_x = 10.0;
+        sprite.position_y = 10.0;
+        sprite.set_origin(Origin::Pixels(5.0, 5.0));
+
+        assert_corners(&sprite, &Matrix3x2::identity(), [(5.0, 5.0), (45.0, 5.0), (45.0, 25.0), (5.0, 25.0)]);
+
+        // Pixel origins don't grow with the scale, the sprite grows around them
+        sprite.set_uniform_scale(2.0);
+        assert_corners(&sprite, &Matrix3x2::identity(), [(0.0, 0.0), (80.0, 0.0), (80.0, 40.0), (0.0, 40.0)]);
+    }
+
+    #[test]
+    fn non_uniform_scale() {
+        let mut sprite = make_sprite();
+        sprite.position_x = 1.0;
+        sprite.position_y = 1.0;
+        sprite.set_scale(2.0, 3.0);
+
+        assert_corners(&sprite, &Matrix3x2::identity(), [(1.0, 1.0), (81.0, 1.0), (81.0, 61.0), (1.0, 61.0)]);
+
+        // Scaled before it is rotated, so the sprite stays a rectangle
+        sprite.angle = 90.0;
+        assert_corners(&sprite, &Matrix3x2::identity(), [(1.0, 1.0), (1.0, 81.0), (-59.0, 81.0), (-59.0, 1.0)]);
+    }
+
+    #[test]
+    fn flipping_mirrors_the_trim() {
+        // A 10x8 region trimmed out of a 20x16 image, 2 pixels from the left and 3 from the top
+        let mut sprite = make_sprite();
+        sprite.set_render_view(0.0, 0.0, 10.0, 8.0);
+        sprite.trim = Some(Trim { offset_x: 2.0, offset_y: 3.0, source_width: 20.0, source_height: 16.0 });
+
+        assert_corners(&sprite, &Matrix3x2::identity(), [(2.0, 3.0), (12.0, 3.0), (12.0, 11.0), (2.0, 11.0)]);
+
+        sprite.flip_x = true;
+        assert_corners(&sprite, &Matrix3x2::identity(), [(8.0, 3.0), (18.0, 3.0), (18.0, 11.0), (8.0, 11.0)]);
+
+        sprite.flip_y = true;
+        assert_corners(&sprite, &Matrix3x2::identity(), [(8.0, 5.0), (18.0, 5.0), (18.0, 13.0), (8.0, 13.0)]);
+
+        // Centered origins are the middle of the untrimmed image, so flipping stays in place around it
+        sprite.center_origin();
+        assert_corners(&sprite, &Matrix3x2::identity(), [(-2.0, -3.0), (8.0, -3.0), (8.0, 5.0), (-2.0, 5.0)]);
+    }
+
+    #[test]
+    fn parents() {
+        let mut sprite = make_sprite();
+        sprite.position_x = 10.0;
+        sprite.position_y = 0.0;
+
+        // The sprite's position is turned and scaled with the parent
+        let parent = Matrix3x2::identity()
+            .translate(Vector2::new(100.0, 100.0))
+            .rotate(90.0)
+            .scale(2.0, 2.0);
+
+        assert_corners(&sprite, &parent, [(100.0, 120.0), (100.0, 200.0), (60.0, 200.0), (60.0, 120.0)]);
+        assert_corners(&sprite, &Matrix3x2::identity(), [(10.0, 0.0), (50.0, 0.0), (50.0, 20.0), (10.0, 20.0)]);
+    }
+}
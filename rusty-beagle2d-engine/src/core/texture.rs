@@ -3,10 +3,12 @@ use stb_image::image;
 use std::path;
 
 pub struct Texture {
-    opengl_object_id: u32,
+    opengl_object_id: ogl::TextureId,
     width: usize,
     height: usize,
-    depth: usize
+    depth: usize,
+    // The file the texture was loaded from, if any. Used when saving scenes.
+    image_path: Option<String>
 }
 
 impl Texture {
@@ -28,6 +30,17 @@ impl Texture {
             image::LoadResult::ImageU8(imageu8) => imageu8
         };
 
+        let mut texture = Texture::from_rgba(image_data.width, image_data.height, image_data.data);
+        texture.image_path = Some(image_filename);
+        texture
+    }
+
+    // Creates a texture from raw pixel data, 4 bytes (RGBA) per pixel, rows from top to bottom.
+    pub fn from_rgba(width: usize, height: usize, pixels: Vec<u8>) -> Texture {
+        if pixels.len() != width * height * 4 {
+            panic!("Expected {} bytes of RGBA pixel data, got {}", width * height * 4, pixels.len());
+        }
+
         let texture_object = ogl::gen_texture();
         ogl::bind_texture(ogl::TextureTarget::Texture2d, texture_object);
 
@@ -39,20 +52,27 @@ impl Texture {
         ogl::tex_image_2d::<u8>(ogl::TextureTarget::Texture2d,
              0,
             ogl::TextureInternalFormat::Rgba8,
-            image_data.width as i32,
-            image_data.height as i32,
+            width as i32,
+            height as i32,
             0, 
             ogl::TextureFormat::Rgba,
             ogl::ElementsDataType::UnsignedByte,
-            image_data.data);
+            pixels);
 
         ogl::generate_mipmap(ogl::TextureTarget::Texture2d);
 
         Texture { 
             opengl_object_id: texture_object, 
-            width: image_data.width, 
-            height: image_data.height, 
-            depth: image_data.depth }
+            width, 
+            height, 
+            depth: 4,
+            image_path: None }
+    }
+
+    // A texture with nothing on the GPU, for tests of code that only needs its size.
+    #[cfg(test)]
+    pub(crate) fn without_pixels(width: usize, height: usize) -> Texture {
+        Texture { opengl_object_id: ogl::TextureId::NONE, width, height, depth: 4, image_path: None }
     }
 
     pub fn get_width(&self) -> usize {
@@ -67,7 +87,11 @@ impl Texture {
         self.depth
     }
 
-    pub fn get_opengl_texture_id(&self) -> u32 {
+    pub fn get_image_path(&self) -> Option<&str> {
+        self.image_path.as_deref()
+    }
+
+    pub fn get_opengl_texture_id(&self) -> ogl::TextureId {
         self.opengl_object_id
     }
 
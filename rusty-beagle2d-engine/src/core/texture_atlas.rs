@@ -0,0 +1,140 @@
+use crate::core::sprite::{Origin, Sprite};
+use crate::core::texture::Texture;
+use crate::core::texture_region::TextureRegion;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// A named image packed into an atlas texture.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AtlasRegion {
+    pub name: String,
+    pub region: TextureRegion,
+    // Normalized pivot point set in the packing tool, if any
+    pub pivot: Option<(f32, f32)>
+}
+
+impl AtlasRegion {
+    // Shows this region on the sprite, and uses its pivot as the sprite's origin if it has one.
+    pub fn apply_to(&self, sprite: &mut Sprite) {
+        self.region.apply_to(sprite);
+
+        if let Some((pivot_x, pivot_y)) = self.pivot {
+            sprite.set_origin(Origin::Normalized(pivot_x, pivot_y));
+        }
+    }
+}
+
+// A rectangle in the local pixel space of a sprite (not the atlas texture).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SliceBounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+
+// The shape of a slice from a given frame onwards, until the next key.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SliceKey {
+    pub frame: usize,
+    pub bounds: SliceBounds,
+    // The stretchable center of a nine-slice
+    pub center: Option<SliceBounds>,
+    // Pivot in pixels relative to the bounds
+    pub pivot: Option<(f32, f32)>
+}
+
+// A named rectangle an artist marked on the sprite, such as a hitbox or nine-slice borders.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AtlasSlice {
+    pub name: String,
+    pub keys: Vec<SliceKey>
+}
+
+impl AtlasSlice {
+    // The key in effect on the given frame
+    pub fn get_key(&self, frame: usize) -> Option<&SliceKey> {
+        self.keys.iter().rfind(|key| key.frame <= frame)
+    }
+}
+
+// Describes the regions of an atlas texture. The texture itself is only loaded on demand.
+pub struct TextureAtlas {
+    image_path: PathBuf,
+    width: f32,
+    height: f32,
+    regions: Vec<AtlasRegion>,
+    region_indices: HashMap<String, usize>,
+    slices: Vec<AtlasSlice>
+}
+
+impl TextureAtlas {
+    pub fn new(image_path: PathBuf, width: f32, height: f32) -> TextureAtlas {
+        TextureAtlas {
+            image_path,
+            width,
+            height,
+            regions: Vec::new(),
+            region_indices: HashMap::new(),
+            slices: Vec::new()
+        }
+    }
+
+    pub fn add_region(&mut self, region: AtlasRegion) -> Result<(), String> {
+        if self.region_indices.contains_key(&region.name) {
+            return Err(format!("The atlas already has a region named '{}'", region.name));
+        }
+
+        self.region_indices.insert(region.name.clone(), self.regions.len());
+        self.regions.push(region);
+        Ok(())
+    }
+
+    pub fn add_slice(&mut self, slice: AtlasSlice) {
+        self.slices.push(slice);
+    }
+
+    pub fn get_region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.region_indices.get(name).map(|&index| &self.regions[index])
+    }
+
+    // All regions, in the order they appear in the atlas file.
+    pub fn get_regions(&self) -> &[AtlasRegion] {
+        &self.regions
+    }
+
+    pub fn get_slice(&self, name: &str) -> Option<&AtlasSlice> {
+        self.slices.iter().find(|slice| slice.name == name)
+    }
+
+    pub fn get_slices(&self) -> &[AtlasSlice] {
+        &self.slices
+    }
+
+    pub fn get_image_path(&self) -> &Path {
+        &self.image_path
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn load_texture(&self) -> Box<Texture> {
+        Box::new(Texture::new(self.image_path.to_string_lossy().into_owned()))
+    }
+
+    // Creates a sprite showing the named region of the atlas texture.
+    pub fn create_sprite(&self, texture: Box<Texture>, region_name: &str) -> Result<Sprite, String> {
+        let region = self.get_region(region_name)
+            .ok_or_else(|| format!("The atlas has no region named '{}'", region_name))?;
+
+        let mut sprite = Sprite::new(texture);
+        region.apply_to(&mut sprite);
+        Ok(sprite)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::core::sprite::Sprite;
+
+use serde::{Deserialize, Serialize};
+
+// Describes the transparent border an atlas packer cut away from an image.
+// The region only holds the opaque part, which sits at (offset_x, offset_y) inside the original image.
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct Trim {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub source_width: f32,
+    pub source_height: f32
+}
+
+// A rectangle of a texture in pixels, measured from the top left corner.
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct TextureRegion {
+    pub x: f32,
+    pub y: f32,
+    // Size of the image as it is displayed
+    pub width: f32,
+    pub height: f32,
+    // The packer stored the image turned 90 degrees clockwise,
+    // So it occupies "height" pixels horizontally and "width" pixels vertically in the texture.
+    #[serde(default)]
+    pub rotated: bool,
+    #[serde(default)]
+    pub trim: Option<Trim>
+}
+
+impl TextureRegion {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> TextureRegion {
+        TextureRegion { x, y, width, height, rotated: false, trim: None }
+    }
+
+    // Width and height of the pixels the region covers in the texture.
+    pub fn get_texture_size(&self) -> (f32, f32) {
+        if self.rotated { (self.height, self.width) } else { (self.width, self.height) }
+    }
+
+    // Size of the original image, before any trimming.
+    pub fn get_source_size(&self) -> (f32, f32) {
+        match self.trim {
+            Some(trim) => (trim.source_width, trim.source_height),
+            None => (self.width, self.height)
+        }
+    }
+
+    // Makes the sprite display this region of its texture.
+    pub fn apply_to(&self, sprite: &mut Sprite) {
+        sprite.set_render_view(self.x, self.y, self.width, self.height);
+        sprite.texture_rotated = self.rotated;
+        sprite.trim = self.trim;
+    }
+}
@@ -0,0 +1,680 @@
+use rusty_beagle2d_glfw::ogl;
+use linear_beaglebra::vector2::Vector2;
+
+use crate::core::batch::BatchVertex;
+use crate::core::color::Color;
+use crate::core::tileset::Tileset;
+use crate::core::vertex_buffer::VertexBuffer;
+use crate::core::index_buffer::IndexBuffer;
+use crate::core::vertex_array::VertexArray;
+
+// Width and height of a chunk in tiles
+pub const DEFAULT_CHUNK_SIZE: u32 = 16;
+
+// One cell of a tile layer.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Tile {
+    // Map wide tile id. 0 is an empty cell. Each tileset owns the ids from its first id onwards (see Tilemap::add_tileset).
+    pub id: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // Mirrors the image along its top left to bottom right diagonal, swapping x and y.
+    // Combined with the other flips this gives the 90 degree rotations (see rotate_clockwise).
+    pub flip_diagonal: bool
+}
+
+impl Tile {
+    pub const EMPTY: Tile = Tile { id: 0, flip_x: false, flip_y: false, flip_diagonal: false };
+
+    pub fn new(id: u32) -> Tile {
+        Tile { id, ..Tile::EMPTY }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id == 0
+    }
+
+    pub fn with_flip(self, flip_x: bool, flip_y: bool) -> Tile {
+        Tile { flip_x, flip_y, ..self }
+    }
+
+    // LEARN: Tile rotation with flips
+    // The flips are applied to the image in the order: diagonal, then x, then y.
+    // Turning the result another 90 degrees clockwise works out to toggling the diagonal flip,
+    // With the new x flip being the inverse of the old y flip, and the new y flip the old x flip.
+    pub fn rotate_clockwise(self) -> Tile {
+        Tile {
+            id: self.id,
+            flip_x: !self.flip_y,
+            flip_y: self.flip_x,
+            flip_diagonal: !self.flip_diagonal
+        }
+    }
+
+    pub fn rotate_counter_clockwise(self) -> Tile {
+        self.rotate_clockwise().rotate_clockwise().rotate_clockwise()
+    }
+}
+
+// A tile whose image changes over time, and where its vertices sit in the chunk's vertex buffer.
+struct AnimatedQuad {
+    first_vertex: usize,
+    tile: Tile,
+    column: u32,
+    row: u32,
+    shown_tile_id: u32
+}
+
+// The geometry of the tiles in a chunk that come from one tileset.
+struct ChunkMesh {
+    tileset_index: usize,
+    vertex_buffer: VertexBuffer<BatchVertex>,
+    // Never read, but has to stay alive for as long as the vertex array uses it
+    _index_buffer: IndexBuffer,
+    vertex_array: VertexArray,
+    index_count: usize,
+    animated_quads: Vec<AnimatedQuad>
+}
+
+struct Chunk {
+    // The tiles changed since the meshes were built
+    dirty: bool,
+    meshes: Vec<ChunkMesh>
+}
+
+// Everything needed to turn a tile into vertices, besides the tile itself.
+struct TileGeometry<'a> {
+    tilesets: &'a [(u32, Tileset)],
+    origin_x: f32,
+    origin_y: f32,
+    tile_width: f32,
+    tile_height: f32,
+    color: Color,
+    animation_time: f32
+}
+
+// A grid of tiles. The grid is split into square chunks, which are turned into vertex buffers
+// The first time they are visible and kept on the GPU until one of their tiles changes.
+pub struct TileLayer {
+    name: String,
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    visible: bool,
+    // Multiplied with every tile. The alpha is the layer's opacity.
+    tint: Color,
+    // Pixels the layer is moved from the map position
+    offset_x: f32,
+    offset_y: f32,
+    chunk_size: u32,
+    chunks_x: u32,
+    chunks: Vec<Chunk>
+}
+
+impl TileLayer {
+    fn new(name: String, width: u32, height: u32, chunk_size: u32) -> TileLayer {
+        let chunks_x = width.div_ceil(chunk_size);
+        let chunks_y = height.div_ceil(chunk_size);
+
+        TileLayer {
+            name,
+            width,
+            height,
+            tiles: vec![Tile::EMPTY; (width * height) as usize],
+            visible: true,
+            tint: Color::WHITE,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            chunk_size,
+            chunks_x,
+            chunks: (0..chunks_x * chunks_y).map(|_| Chunk { dirty: true, meshes: Vec::new() }).collect()
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    // Returns None outside of the layer
+    pub fn get_tile(&self, x: u32, y: u32) -> Option<Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some(self.tiles[(y * self.width + x) as usize])
+    }
+
+    // All tiles, row by row from the top left
+    pub fn get_tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Tile) {
+        if x >= self.width || y >= self.height {
+            panic!("Tile ({}, {}) is outside of layer '{}' ({}x{})", x, y, self.name, self.width, self.height);
+        }
+
+        let index = (y * self.width + x) as usize;
+
+        if self.tiles[index] != tile {
+            self.tiles[index] = tile;
+
+            let chunk_index = (y / self.chunk_size) * self.chunks_x + x / self.chunk_size;
+            self.chunks[chunk_index as usize].dirty = true;
+        }
+    }
+
+    pub fn clear_tile(&mut self, x: u32, y: u32) {
+        self.set_tile(x, y, Tile::EMPTY);
+    }
+
+    // Sets every tile of the rectangle, clipped to the layer.
+    pub fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, tile: Tile) {
+        for tile_y in y..(y + height).min(self.height) {
+            for tile_x in x..(x + width).min(self.width) {
+                self.set_tile(tile_x, tile_y, tile);
+            }
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn get_tint(&self) -> Color {
+        self.tint
+    }
+
+    pub fn set_tint(&mut self, tint: Color) {
+        if tint != self.tint {
+            self.tint = tint;
+            self.mark_all_dirty();
+        }
+    }
+
+    pub fn get_offset(&self) -> (f32, f32) {
+        (self.offset_x, self.offset_y)
+    }
+
+    pub fn set_offset(&mut self, offset_x: f32, offset_y: f32) {
+        if offset_x != self.offset_x || offset_y != self.offset_y {
+            self.offset_x = offset_x;
+            self.offset_y = offset_y;
+            self.mark_all_dirty();
+        }
+    }
+
+    fn mark_all_dirty(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.dirty = true;
+        }
+    }
+
+    fn rebuild_chunk(&mut self, chunk_x: u32, chunk_y: u32, geometry: &TileGeometry) {
+        // Per tileset used in the chunk: tileset index, vertices, indices and animated quads
+        type MeshBuilder = (usize, Vec<BatchVertex>, Vec<u32>, Vec<AnimatedQuad>);
+        let mut builders: Vec<MeshBuilder> = Vec::new();
+
+        let first_column = chunk_x * self.chunk_size;
+        let first_row = chunk_y * self.chunk_size;
+
+        for row in first_row..(first_row + self.chunk_size).min(self.height) {
+            for column in first_column..(first_column + self.chunk_size).min(self.width) {
+                let tile = self.tiles[(row * self.width + column) as usize];
+
+                let (tileset_index, local_id) = match find_tileset(geometry.tilesets, tile.id) {
+                    Some(found) => found,
+                    None => continue
+                };
+
+                let tileset = &geometry.tilesets[tileset_index].1;
+
+                let builder_index = match builders.iter().position(|builder| builder.0 == tileset_index) {
+                    Some(builder_index) => builder_index,
+                    None => {
+                        builders.push((tileset_index, Vec::new(), Vec::new(), Vec::new()));
+                        builders.len() - 1
+                    }
+                };
+
+                let (_, vertices, indices, animated_quads) = &mut builders[builder_index];
+                let first_vertex = vertices.len();
+                let shown_tile_id = tileset.get_animated_tile(local_id, geometry.animation_time);
+
+                vertices.extend_from_slice(&build_tile_quad(tileset, shown_tile_id, tile, column, row, geometry));
+                indices.extend([0, 1, 2, 0, 2, 3].iter().map(|index| first_vertex as u32 + index));
+
+                if tileset.is_animated(local_id) {
+                    animated_quads.push(AnimatedQuad { first_vertex, tile, column, row, shown_tile_id });
+                }
+            }
+        }
+
+        let chunk = &mut self.chunks[(chunk_y * self.chunks_x + chunk_x) as usize];
+        chunk.dirty = false;
+
+        // The old buffers are deleted when the meshes are dropped here
+        chunk.meshes = builders.into_iter().map(|(tileset_index, vertices, indices, animated_quads)| {
+            let usage = if animated_quads.is_empty() { ogl::Usage::StaticDraw } else { ogl::Usage::DynamicDraw };
+
+            let vertex_buffer = VertexBuffer::from_vertices(&vertices, usage);
+            let index_buffer = IndexBuffer::from_indices(&indices, ogl::Usage::StaticDraw);
+
+            let mut vertex_array = VertexArray::new();
+            vertex_array.add_vertex_buffer(&vertex_buffer);
+            vertex_array.set_index_buffer(&index_buffer);
+            vertex_array.unbind();
+
+            ChunkMesh {
+                tileset_index,
+                vertex_buffer,
+                _index_buffer: index_buffer,
+                vertex_array,
+                index_count: indices.len(),
+                animated_quads
+            }
+        }).collect();
+    }
+}
+
+// A map built from one or more tile layers sharing the same grid.
+// Layers are drawn from cached per chunk geometry, and only the chunks the camera sees are drawn (see Renderer2d::draw_tilemap).
+pub struct Tilemap {
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    chunk_size: u32,
+    position_x: f32,
+    position_y: f32,
+    // Tilesets with their first tile id, ordered by first id
+    tilesets: Vec<(u32, Tileset)>,
+    layers: Vec<TileLayer>,
+    // Seconds, drives the animated tiles
+    animation_time: f32
+}
+
+impl Tilemap {
+    // width and height in tiles, tile_width and tile_height in pixels.
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Tilemap {
+        Tilemap {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            position_x: 0.0,
+            position_y: 0.0,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            animation_time: 0.0
+        }
+    }
+
+    // Only affects layers added afterwards.
+    pub fn with_chunk_size(mut self, chunk_size: u32) -> Tilemap {
+        if chunk_size == 0 {
+            panic!("The chunk size of a tilemap must be at least 1");
+        }
+
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    // Adds a tileset after the existing ones, and returns the map wide id of its first tile.
+    pub fn add_tileset(&mut self, tileset: Tileset) -> u32 {
+        let first_id = match self.tilesets.last() {
+            Some((last_first_id, last_tileset)) => last_first_id + last_tileset.get_tile_count().max(1),
+            None => 1
+        };
+
+        self.tilesets.push((first_id, tileset));
+        first_id
+    }
+
+    // Adds a tileset with a given first id, for maps made in an editor which assigns the ids itself.
+    pub fn add_tileset_with_first_id(&mut self, tileset: Tileset, first_id: u32) -> Result<(), String> {
+        if first_id == 0 {
+            return Err(format!("Tileset '{}' can't start at id 0, which means an empty tile", tileset.get_name()));
+        }
+
+        if self.tilesets.iter().any(|(existing_first_id, _)| *existing_first_id == first_id) {
+            return Err(format!("Tileset '{}' starts at id {}, which is already taken", tileset.get_name(), first_id));
+        }
+
+        let insert_index = self.tilesets.iter().position(|(existing_first_id, _)| *existing_first_id > first_id).unwrap_or(self.tilesets.len());
+        self.tilesets.insert(insert_index, (first_id, tileset));
+
+        for layer in self.layers.iter_mut() {
+            layer.mark_all_dirty();
+        }
+
+        Ok(())
+    }
+
+    pub fn get_tileset_count(&self) -> usize {
+        self.tilesets.len()
+    }
+
+    pub fn get_tileset(&self, index: usize) -> &Tileset {
+        &self.tilesets[index].1
+    }
+
+    pub fn get_tileset_first_id(&self, index: usize) -> u32 {
+        self.tilesets[index].0
+    }
+
+    // The tileset a tile id belongs to, and the tile's id within that tileset.
+    pub fn find_tileset(&self, tile_id: u32) -> Option<(usize, u32)> {
+        find_tileset(&self.tilesets, tile_id)
+    }
+
+    // Adds an empty layer on top of the existing ones and returns its index.
+    pub fn add_layer(&mut self, name: String) -> usize {
+        self.layers.push(TileLayer::new(name, self.width, self.height, self.chunk_size));
+        self.layers.len() - 1
+    }
+
+    pub fn get_layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn get_layer(&self, index: usize) -> &TileLayer {
+        &self.layers[index]
+    }
+
+    pub fn get_layer_mut(&mut self, index: usize) -> &mut TileLayer {
+        &mut self.layers[index]
+    }
+
+    pub fn get_layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    pub fn get_tile(&self, layer_index: usize, x: u32, y: u32) -> Option<Tile> {
+        self.layers[layer_index].get_tile(x, y)
+    }
+
+    pub fn set_tile(&mut self, layer_index: usize, x: u32, y: u32, tile: Tile) {
+        self.layers[layer_index].set_tile(x, y, tile);
+    }
+
+    // The tile under a world position, ignoring the layer's offset.
+    pub fn get_tile_at(&self, layer_index: usize, position: Vector2) -> Option<Tile> {
+        let (x, y) = self.world_to_tile(position)?;
+        self.get_tile(layer_index, x, y)
+    }
+
+    // The grid cell under a world position, or None outside of the map.
+    pub fn world_to_tile(&self, position: Vector2) -> Option<(u32, u32)> {
+        let x = ((position.x - self.position_x) / self.tile_width as f32).floor();
+        let y = ((position.y - self.position_y) / self.tile_height as f32).floor();
+
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+
+        Some((x as u32, y as u32))
+    }
+
+    // World position of the top left corner of a grid cell.
+    pub fn tile_to_world(&self, x: u32, y: u32) -> Vector2 {
+        Vector2::new(
+            self.position_x + (x * self.tile_width) as f32,
+            self.position_y + (y * self.tile_height) as f32)
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_tile_width(&self) -> u32 {
+        self.tile_width
+    }
+
+    pub fn get_tile_height(&self) -> u32 {
+        self.tile_height
+    }
+
+    pub fn get_position(&self) -> Vector2 {
+        Vector2::new(self.position_x, self.position_y)
+    }
+
+    // NOTE: The chunk geometry is in world space, so moving the map rebuilds every chunk.
+    pub fn set_position(&mut self, position_x: f32, position_y: f32) {
+        if position_x != self.position_x || position_y != self.position_y {
+            self.position_x = position_x;
+            self.position_y = position_y;
+
+            for layer in self.layers.iter_mut() {
+                layer.mark_all_dirty();
+            }
+        }
+    }
+
+    // Advances the tile animations.
+    pub fn update(&mut self, delta_time: f32) {
+        self.animation_time += delta_time;
+    }
+
+    // Draws the chunks of the layer overlapping the view rectangle (in world space).
+    // Expects the tile shader to be active with its projection set. See Renderer2d::draw_tilemap_layer.
+    pub(crate) fn draw_layer(&mut self, layer_index: usize, view_left: f32, view_top: f32, view_right: f32, view_bottom: f32) {
+        let layer = &mut self.layers[layer_index];
+
+        if !layer.visible || layer.chunks.is_empty() {
+            return;
+        }
+
+        let geometry = TileGeometry {
+            tilesets: &self.tilesets,
+            origin_x: self.position_x + layer.offset_x,
+            origin_y: self.position_y + layer.offset_y,
+            tile_width: self.tile_width as f32,
+            tile_height: self.tile_height as f32,
+            color: layer.tint,
+            animation_time: self.animation_time
+        };
+
+        // Tiles bigger than a cell stick out of it to the right and upwards (see build_tile_quad),
+        // So chunks just left of or below the view may still reach into it
+        let (tile_width, tile_height) = (self.tile_width, self.tile_height);
+        let overhang_x = self.tilesets.iter().map(|(_, tileset)| tileset.get_tile_width().saturating_sub(tile_width)).max().unwrap_or(0) as f32;
+        let overhang_y = self.tilesets.iter().map(|(_, tileset)| tileset.get_tile_height().saturating_sub(tile_height)).max().unwrap_or(0) as f32;
+
+        let chunk_width = (layer.chunk_size * self.tile_width) as f32;
+        let chunk_height = (layer.chunk_size * self.tile_height) as f32;
+        let chunks_y = layer.chunks.len() as u32 / layer.chunks_x;
+
+        let first_chunk_x = ((view_left - overhang_x - geometry.origin_x) / chunk_width).floor().max(0.0) as u32;
+        let first_chunk_y = ((view_top - geometry.origin_y) / chunk_height).floor().max(0.0) as u32;
+        let last_chunk_x = ((view_right - geometry.origin_x) / chunk_width).floor();
+        let last_chunk_y = ((view_bottom + overhang_y - geometry.origin_y) / chunk_height).floor();
+
+        if last_chunk_x < 0.0 || last_chunk_y < 0.0 {
+            return;
+        }
+
+        let last_chunk_x = (last_chunk_x as u32).min(layer.chunks_x - 1);
+        let last_chunk_y = (last_chunk_y as u32).min(chunks_y - 1);
+
+        for chunk_y in first_chunk_y..=last_chunk_y {
+            for chunk_x in first_chunk_x..=last_chunk_x {
+                let chunk_index = (chunk_y * layer.chunks_x + chunk_x) as usize;
+
+                if layer.chunks[chunk_index].dirty {
+                    layer.rebuild_chunk(chunk_x, chunk_y, &geometry);
+                }
+
+                for mesh in layer.chunks[chunk_index].meshes.iter_mut() {
+                    let tileset = &geometry.tilesets[mesh.tileset_index].1;
+
+                    // Only the vertices of tiles that moved on to another frame are rewritten
+                    for quad in mesh.animated_quads.iter_mut() {
+                        let local_id = quad.tile.id - geometry.tilesets[mesh.tileset_index].0;
+                        let shown_tile_id = tileset.get_animated_tile(local_id, geometry.animation_time);
+
+                        if shown_tile_id != quad.shown_tile_id {
+                            quad.shown_tile_id = shown_tile_id;
+                            let vertices = build_tile_quad(tileset, shown_tile_id, quad.tile, quad.column, quad.row, &geometry);
+                            mesh.vertex_buffer.update(quad.first_vertex, &vertices);
+                        }
+                    }
+
+                    ogl::bind_texture(ogl::TextureTarget::Texture2d, tileset.get_texture().get_opengl_texture_id());
+                    mesh.vertex_array.bind();
+
+                    ogl::draw_elements_with_offset(
+                        ogl::DrawMode::Triangles,
+                        mesh.index_count as i32,
+                        ogl::ElementsDataType::UnsignedInt,
+                        0);
+                }
+            }
+        }
+    }
+}
+
+fn find_tileset(tilesets: &[(u32, Tileset)], tile_id: u32) -> Option<(usize, u32)> {
+    if tile_id == 0 {
+        return None;
+    }
+
+    // The last tileset starting at or before the id
+    let index = tilesets.iter().rposition(|(first_id, _)| *first_id <= tile_id)?;
+    let local_id = tile_id - tilesets[index].0;
+
+    if local_id >= tilesets[index].1.get_tile_count() {
+        return None;
+    }
+
+    Some((index, local_id))
+}
+
+// The corners of a tile, top left, top right, bottom right, bottom left.
+// The tile image sits on the bottom left corner of its cell, so tiles taller than the map's grid reach upwards.
+fn build_tile_quad(tileset: &Tileset, tile_id: u32, tile: Tile, column: u32, row: u32, geometry: &TileGeometry) -> [BatchVertex; 4] {
+    let texture_width = tileset.get_texture().get_width() as f32;
+    let texture_height = tileset.get_texture().get_height() as f32;
+    let region = tileset.get_tile_region(tile_id);
+
+    let left = geometry.origin_x + column as f32 * geometry.tile_width;
+    let bottom = geometry.origin_y + (row + 1) as f32 * geometry.tile_height;
+    let (positions, tex_coords) = get_tile_corners(tile, left, bottom, region.width, region.height);
+
+    let mut vertices = [BatchVertex { position: [0.0; 3], tex_coord: [0.0; 2], color: geometry.color.to_array() }; 4];
+
+    for index in 0..4 {
+        let (x, y) = positions[index];
+        let (s, t) = tex_coords[index];
+
+        vertices[index].position = [x, y, 0.0];
+        vertices[index].tex_coord = [
+            (region.x + s * region.width) / texture_width,
+            (region.y + t * region.height) / texture_height
+        ];
+    }
+
+    vertices
+}
+
+type Corners = [(f32, f32); 4];
+
+// Positions of the corners of a tile whose bottom left corner is at (left, bottom),
+// And the point of the tile image (from (0, 0) to (1, 1)) shown at each of them.
+fn get_tile_corners(tile: Tile, left: f32, bottom: f32, image_width: f32, image_height: f32) -> (Corners, Corners) {
+    // Flipping along the diagonal turns a wide image into a tall one
+    let (width, height) = if tile.flip_diagonal { (image_height, image_width) } else { (image_width, image_height) };
+
+    let right = left + width;
+    let top = bottom - height;
+
+    let positions = [(left, top), (right, top), (right, bottom), (left, bottom)];
+    let mut tex_coords = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+    // Undo the flips in reverse order to find the image position shown at each corner
+    for tex_coord in tex_coords.iter_mut() {
+        let (mut s, mut t) = *tex_coord;
+
+        if tile.flip_y {
+            t = 1.0 - t;
+        }
+
+        if tile.flip_x {
+            s = 1.0 - s;
+        }
+
+        if tile.flip_diagonal {
+            std::mem::swap(&mut s, &mut t);
+        }
+
+        *tex_coord = (s, t);
+    }
+
+    (positions, tex_coords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_and_mirrored_tiles() {
+        let (positions, tex_coords) = get_tile_corners(Tile::new(1), 10.0, 50.0, 32.0, 16.0);
+        assert_eq!(positions, [(10.0, 34.0), (42.0, 34.0), (42.0, 50.0), (10.0, 50.0)]);
+        assert_eq!(tex_coords, [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+
+        let (positions, tex_coords) = get_tile_corners(Tile::new(1).with_flip(true, false), 0.0, 16.0, 32.0, 16.0);
+        assert_eq!(positions, [(0.0, 0.0), (32.0, 0.0), (32.0, 16.0), (0.0, 16.0)]);
+        assert_eq!(tex_coords, [(1.0, 0.0), (0.0, 0.0), (0.0, 1.0), (1.0, 1.0)]);
+
+        let (_, tex_coords) = get_tile_corners(Tile::new(1).with_flip(false, true), 0.0, 16.0, 32.0, 16.0);
+        assert_eq!(tex_coords, [(0.0, 1.0), (1.0, 1.0), (1.0, 0.0), (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn diagonal_flip_swaps_width_and_height() {
+        let tile = Tile { flip_diagonal: true, ..Tile::new(1) };
+
+        // A wide 32x16 image becomes a tall 16x32 quad, still standing on the bottom left of its cell
+        let (positions, tex_coords) = get_tile_corners(tile, 0.0, 64.0, 32.0, 16.0);
+        assert_eq!(positions, [(0.0, 32.0), (16.0, 32.0), (16.0, 64.0), (0.0, 64.0)]);
+        assert_eq!(tex_coords, [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)]);
+    }
+
+    #[test]
+    fn rotated_tiles() {
+        // Turned clockwise, the top left corner shows the bottom left of the image
+        let (positions, tex_coords) = get_tile_corners(Tile::new(1).rotate_clockwise(), 0.0, 64.0, 32.0, 16.0);
+        assert_eq!(positions, [(0.0, 32.0), (16.0, 32.0), (16.0, 64.0), (0.0, 64.0)]);
+        assert_eq!(tex_coords, [(0.0, 1.0), (0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+
+        let (_, tex_coords) = get_tile_corners(Tile::new(1).rotate_counter_clockwise(), 0.0, 64.0, 32.0, 16.0);
+        assert_eq!(tex_coords, [(1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)]);
+
+        // Half a turn keeps the size
+        let (positions, tex_coords) = get_tile_corners(Tile::new(1).rotate_clockwise().rotate_clockwise(), 0.0, 64.0, 32.0, 16.0);
+        assert_eq!(positions, [(0.0, 48.0), (32.0, 48.0), (32.0, 64.0), (0.0, 64.0)]);
+        assert_eq!(tex_coords, [(1.0, 1.0), (0.0, 1.0), (0.0, 0.0), (1.0, 0.0)]);
+
+        let tile = Tile::new(7).with_flip(true, false);
+        assert_eq!(tile.rotate_clockwise().rotate_clockwise().rotate_clockwise().rotate_clockwise(), tile);
+        assert_eq!(tile.rotate_clockwise().rotate_counter_clockwise(), tile);
+    }
+}
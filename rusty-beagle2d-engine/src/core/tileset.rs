@@ -0,0 +1,139 @@
+use crate::core::texture::Texture;
+use crate::core::texture_region::TextureRegion;
+
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TileAnimationFrame {
+    // Tile id within the same tileset
+    pub tile_id: u32,
+    // Seconds
+    pub duration: f32
+}
+
+// A texture cut into a grid of equally sized tiles.
+// Tiles are numbered row by row from the top left, starting at 0.
+pub struct Tileset {
+    name: String,
+    texture: Box<Texture>,
+    tile_width: u32,
+    tile_height: u32,
+    // Pixels around the whole grid, and between neighbouring tiles
+    margin: u32,
+    spacing: u32,
+    columns: u32,
+    tile_count: u32,
+    animations: HashMap<u32, Vec<TileAnimationFrame>>
+}
+
+impl Tileset {
+    pub fn new(name: String, texture: Box<Texture>, tile_width: u32, tile_height: u32) -> Tileset {
+        let mut tileset = Tileset {
+            name,
+            texture,
+            tile_width,
+            tile_height,
+            margin: 0,
+            spacing: 0,
+            columns: 0,
+            tile_count: 0,
+            animations: HashMap::new()
+        };
+
+        tileset.calculate_grid();
+        tileset
+    }
+
+    pub fn with_spacing(mut self, margin: u32, spacing: u32) -> Tileset {
+        self.margin = margin;
+        self.spacing = spacing;
+        self.calculate_grid();
+        self
+    }
+
+    // Makes the tile cycle through the given frames. Every tile using this id animates in sync.
+    pub fn set_animation(&mut self, tile_id: u32, frames: Vec<TileAnimationFrame>) {
+        if frames.is_empty() {
+            self.animations.remove(&tile_id);
+        } else {
+            self.animations.insert(tile_id, frames);
+        }
+    }
+
+    pub fn get_animation(&self, tile_id: u32) -> Option<&[TileAnimationFrame]> {
+        self.animations.get(&tile_id).map(|frames| frames.as_slice())
+    }
+
+    pub fn is_animated(&self, tile_id: u32) -> bool {
+        self.animations.contains_key(&tile_id)
+    }
+
+    // The tile shown for "tile_id" after "time" seconds of animation.
+    pub fn get_animated_tile(&self, tile_id: u32, time: f32) -> u32 {
+        let frames = match self.animations.get(&tile_id) {
+            Some(frames) => frames,
+            None => return tile_id
+        };
+
+        let total_duration: f32 = frames.iter().map(|frame| frame.duration).sum();
+
+        if total_duration <= 0.0 {
+            return frames[0].tile_id;
+        }
+
+        let mut frame_time = time % total_duration;
+
+        for frame in frames {
+            if frame_time < frame.duration {
+                return frame.tile_id;
+            }
+
+            frame_time -= frame.duration;
+        }
+
+        frames[frames.len() - 1].tile_id
+    }
+
+    pub fn get_tile_region(&self, tile_id: u32) -> TextureRegion {
+        let column = tile_id % self.columns.max(1);
+        let row = tile_id / self.columns.max(1);
+
+        TextureRegion::new(
+            (self.margin + column * (self.tile_width + self.spacing)) as f32,
+            (self.margin + row * (self.tile_height + self.spacing)) as f32,
+            self.tile_width as f32,
+            self.tile_height as f32)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn get_tile_width(&self) -> u32 {
+        self.tile_width
+    }
+
+    pub fn get_tile_height(&self) -> u32 {
+        self.tile_height
+    }
+
+    pub fn get_columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn get_tile_count(&self) -> u32 {
+        self.tile_count
+    }
+
+    fn calculate_grid(&mut self) {
+        let usable_width = (self.texture.get_width() as u32 + self.spacing).saturating_sub(self.margin * 2);
+        let usable_height = (self.texture.get_height() as u32 + self.spacing).saturating_sub(self.margin * 2);
+
+        self.columns = usable_width / (self.tile_width + self.spacing);
+        self.tile_count = self.columns * (usable_height / (self.tile_height + self.spacing));
+    }
+}
@@ -0,0 +1,557 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// Where the game loop gets the time from.
+// Lets tests step through frames with exact times instead of depending on how fast they run.
+pub trait Clock {
+    // Time passed since some fixed point, like the start of the game.
+    fn now(&self) -> Duration;
+}
+
+// The real time, measured from when the clock was created.
+pub struct SystemClock {
+    start: Instant
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            start: Instant::now()
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// A clock that only moves when told to.
+// Clones share the same time, so a test can keep one and hand another to the game loop.
+#[derive(Clone)]
+pub struct ManualClock {
+    time: Rc<Cell<Duration>>
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock {
+            time: Rc::new(Cell::new(Duration::from_secs(0)))
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.time.set(self.time.get() + duration);
+    }
+
+    pub fn set(&self, time: Duration) {
+        self.time.set(time);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.time.get()
+    }
+}
+
+// The game's view of time, kept by the GameLoop and handed to the Game hooks.
+// Game time is the time the game has simulated. It advances by the fixed timestep with every fixed update,
+// So it can run slower or faster than real time (time scale), or stop altogether (pause).
+#[derive(Clone, Debug)]
+pub struct Time {
+    real_time: Duration,
+    game_time: Duration,
+    real_delta: Duration,
+    delta: Duration,
+    fixed_delta: Duration,
+    tick_count: u64,
+    frame_count: u64,
+    time_scale: f32,
+    paused: bool,
+    // Fixed updates to run while paused, see step
+    pending_steps: u32
+}
+
+impl Time {
+    pub fn new(fixed_delta: Duration) -> Time {
+        Time {
+            real_time: Duration::from_secs(0),
+            game_time: Duration::from_secs(0),
+            real_delta: Duration::from_secs(0),
+            delta: Duration::from_secs(0),
+            fixed_delta,
+            tick_count: 0,
+            frame_count: 0,
+            time_scale: 1.0,
+            paused: false,
+            pending_steps: 0
+        }
+    }
+
+    // Real time passed since the game loop started, as counted by the loop (long frames are cut short).
+    pub fn get_real_time(&self) -> Duration {
+        self.real_time
+    }
+
+    // Time simulated so far: the number of fixed updates times the fixed timestep.
+    pub fn get_game_time(&self) -> Duration {
+        self.game_time
+    }
+
+    // Real seconds the last frame took.
+    pub fn get_real_delta(&self) -> f32 {
+        self.real_delta.as_secs_f32()
+    }
+
+    // Game seconds added in the last frame: the real delta times the time scale, or 0.0 while paused.
+    pub fn get_delta(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    // Seconds each fixed update advances the game by.
+    pub fn get_fixed_delta(&self) -> f32 {
+        self.fixed_delta.as_secs_f32()
+    }
+
+    pub fn get_fixed_timestep(&self) -> Duration {
+        self.fixed_delta
+    }
+
+    // Number of fixed updates run so far.
+    pub fn get_tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn get_time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    // 0.5 runs the game at half speed, 2.0 at double speed.
+    // NOTE: The fixed updates keep their dt, there are just fewer or more of them per second.
+    // So the game plays out the same at any time scale, only slower or faster.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        if time_scale.is_nan() || time_scale < 0.0 {
+            panic!("Time scale must be 0.0 or more, got {}", time_scale);
+        }
+
+        self.time_scale = time_scale;
+    }
+
+    // Stops game time. Frames, frame updates and rendering go on.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // While paused, runs exactly one fixed update in the next frame. Handy for going through a bug frame by frame.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    // Called by the game loop at the start of a frame. Returns the game time the frame adds.
+    pub(crate) fn begin_frame(&mut self, real_frame_time: Duration) -> Duration {
+        self.real_time += real_frame_time;
+        self.real_delta = real_frame_time;
+        self.frame_count += 1;
+
+        self.delta = if self.paused {
+            let steps = self.pending_steps;
+            self.pending_steps = 0;
+            self.fixed_delta * steps
+        } else {
+            real_frame_time.mul_f32(self.time_scale)
+        };
+
+        self.delta
+    }
+
+    // Called by the game loop before each fixed update.
+    pub(crate) fn begin_tick(&mut self) {
+        self.tick_count += 1;
+        self.game_time += self.fixed_delta;
+    }
+
+    pub(crate) fn set_fixed_timestep(&mut self, fixed_delta: Duration) {
+        self.fixed_delta = fixed_delta;
+    }
+}
+
+// Counts down from a duration, for cooldowns, spawn waves and the like.
+// Advance it with tick, usually with the dt of a fixed update.
+#[derive(Clone, Debug)]
+pub struct Timer {
+    duration: f32,
+    elapsed: f32,
+    repeating: bool,
+    paused: bool,
+    // Times the timer ran out during the last tick
+    times_finished: u32
+}
+
+impl Timer {
+    // Runs out once after "duration" seconds.
+    pub fn new(duration: f32) -> Timer {
+        if duration.is_nan() || duration <= 0.0 {
+            panic!("Timer duration must be more than 0.0 seconds, got {}", duration);
+        }
+
+        Timer {
+            duration,
+            elapsed: 0.0,
+            repeating: false,
+            paused: false,
+            times_finished: 0
+        }
+    }
+
+    // Runs out every "duration" seconds.
+    pub fn repeating(duration: f32) -> Timer {
+        let mut timer = Timer::new(duration);
+        timer.repeating = true;
+        timer
+    }
+
+    // Advances the timer. Returns true if it ran out during this tick.
+    pub fn tick(&mut self, dt: f32) -> bool {
+        self.times_finished = 0;
+
+        if self.paused || (!self.repeating && self.is_finished()) {
+            return false;
+        }
+
+        self.elapsed += dt;
+
+        if self.elapsed >= self.duration {
+            if self.repeating {
+                // A long tick can run through the duration several times
+                self.times_finished = (self.elapsed / self.duration) as u32;
+                self.elapsed -= self.times_finished as f32 * self.duration;
+            } else {
+                self.times_finished = 1;
+                self.elapsed = self.duration;
+            }
+        }
+
+        self.times_finished > 0
+    }
+
+    // How many times the timer ran out during the last tick. Can be more than 1 for repeating timers.
+    pub fn get_times_finished(&self) -> u32 {
+        self.times_finished
+    }
+
+    // Only ever true for timers that don't repeat.
+    pub fn is_finished(&self) -> bool {
+        !self.repeating && self.elapsed >= self.duration
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn get_elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn get_remaining(&self) -> f32 {
+        self.duration - self.elapsed
+    }
+
+    // From 0.0 when started to 1.0 when run out.
+    pub fn get_fraction(&self) -> f32 {
+        self.elapsed / self.duration
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Starts counting down from the full duration again.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.times_finished = 0;
+    }
+}
+
+// Counts up, for measuring how long something takes, like a level run.
+#[derive(Clone, Debug)]
+pub struct Stopwatch {
+    elapsed: f32,
+    paused: bool
+}
+
+impl Stopwatch {
+    pub fn new() -> Stopwatch {
+        Stopwatch {
+            elapsed: 0.0,
+            paused: false
+        }
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        if !self.paused {
+            self.elapsed += dt;
+        }
+    }
+
+    // Seconds counted so far.
+    pub fn get_elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::game_loop::{Game, GameLoop};
+    use crate::core::renderer2d::Renderer2d;
+
+    // Counts fixed updates and keeps the game time each one saw
+    struct TickCounter {
+        ticks: u32,
+        frames: u32,
+        game_times: Vec<Duration>
+    }
+
+    impl Game for TickCounter {
+        fn fixed_update(&mut self, _dt: f32, time: &mut Time) {
+            self.ticks += 1;
+            self.game_times.push(time.get_game_time());
+        }
+
+        fn update(&mut self, _frame_dt: f32, _time: &mut Time) {
+            self.frames += 1;
+        }
+
+        fn render(&mut self, _renderer: &mut Renderer2d, _alpha: f32) {}
+    }
+
+    // A loop at 100 ticks per second that has run its first frame, so the next frame measures from now
+    fn create_loop() -> (GameLoop, ManualClock, TickCounter) {
+        let clock = ManualClock::new();
+        let mut game_loop = GameLoop::new(Box::new(clock.clone())).with_tick_rate(100);
+        let mut game = TickCounter { ticks: 0, frames: 0, game_times: Vec::new() };
+        game_loop.run_frame(&mut game);
+        (game_loop, clock, game)
+    }
+
+    // Runs a frame that took "milliseconds" and returns the number of fixed updates it ran.
+    fn run_frame(game_loop: &mut GameLoop, clock: &ManualClock, game: &mut TickCounter, milliseconds: u64) -> u32 {
+        let ticks_before = game.ticks;
+        clock.advance(Duration::from_millis(milliseconds));
+        game_loop.run_frame(game);
+        game.ticks - ticks_before
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-5, "{} isn't {}", value, expected);
+    }
+
+    #[test]
+    fn manual_clock_clones_share_the_time() {
+        let clock = ManualClock::new();
+        let other = clock.clone();
+
+        clock.advance(Duration::from_millis(30));
+        assert_eq!(other.now(), Duration::from_millis(30));
+
+        other.set(Duration::from_secs(2));
+        assert_eq!(clock.now(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn time_scale() {
+        let (mut game_loop, clock, mut game) = create_loop();
+
+        game_loop.get_time_mut().set_time_scale(0.5);
+        let ticks: Vec<u32> = (0..4).map(|_| run_frame(&mut game_loop, &clock, &mut game, 10)).collect();
+        assert_eq!(ticks, vec![0, 1, 0, 1]);
+        assert_close(game_loop.get_time().get_delta(), 0.005);
+        assert_close(game_loop.get_time().get_real_delta(), 0.01);
+
+        game_loop.get_time_mut().set_time_scale(2.0);
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 10), 2);
+
+        game_loop.get_time_mut().set_time_scale(0.0);
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 100), 0);
+
+        // Game time only counts simulated ticks, real time counts every frame
+        let time = game_loop.get_time();
+        assert_eq!(time.get_game_time(), Duration::from_millis(40));
+        assert_eq!(time.get_real_time(), Duration::from_millis(150));
+        assert_eq!(time.get_tick_count(), 4);
+        assert_eq!(game.game_times, vec![Duration::from_millis(10), Duration::from_millis(20), Duration::from_millis(30), Duration::from_millis(40)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_time_scale_panics() {
+        Time::new(Duration::from_millis(10)).set_time_scale(-1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn nan_time_scale_panics() {
+        Time::new(Duration::from_millis(10)).set_time_scale(f32::NAN);
+    }
+
+    #[test]
+    fn pause_and_step() {
+        let (mut game_loop, clock, mut game) = create_loop();
+
+        // 5 milliseconds are left over when pausing
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 15), 1);
+        game_loop.get_time_mut().pause();
+
+        // Frame updates go on, fixed updates don't
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 100), 0);
+        assert_eq!(game.frames, 3);
+        assert_eq!(game_loop.get_time().get_delta(), 0.0);
+
+        // A step runs exactly one tick, whatever the frame took
+        game_loop.get_time_mut().step();
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 100), 1);
+        assert_close(game_loop.get_time().get_delta(), 0.01);
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 100), 0);
+
+        game_loop.get_time_mut().step();
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 0), 1);
+
+        game_loop.get_time_mut().step();
+        game_loop.get_time_mut().step();
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 0), 2);
+
+        // Steps asked for before resuming are dropped
+        game_loop.get_time_mut().step();
+        game_loop.get_time_mut().resume();
+        assert!(!game_loop.get_time().is_paused());
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 5), 1);
+        assert_eq!(game_loop.get_time().get_tick_count(), 6);
+
+        // And stepping while running does nothing extra
+        game_loop.get_time_mut().step();
+        assert_eq!(run_frame(&mut game_loop, &clock, &mut game, 10), 1);
+    }
+
+    #[test]
+    fn one_shot_timer() {
+        let mut timer = Timer::new(1.0);
+
+        assert!(!timer.tick(0.25));
+        assert_close(timer.get_remaining(), 0.75);
+        assert_close(timer.get_fraction(), 0.25);
+
+        assert!(timer.tick(1.0));
+        assert_eq!(timer.get_times_finished(), 1);
+        assert!(timer.is_finished());
+        assert_eq!(timer.get_elapsed(), 1.0);
+        assert_eq!(timer.get_fraction(), 1.0);
+
+        // Finishes only once
+        assert!(!timer.tick(1.0));
+        assert_eq!(timer.get_times_finished(), 0);
+        assert!(timer.is_finished());
+
+        timer.reset();
+        assert!(!timer.is_finished());
+        assert_eq!(timer.get_elapsed(), 0.0);
+    }
+
+    #[test]
+    fn repeating_timer_finishes_several_times_in_one_tick() {
+        let mut timer = Timer::repeating(0.5);
+
+        assert!(timer.tick(1.75));
+        assert_eq!(timer.get_times_finished(), 3);
+        assert_close(timer.get_elapsed(), 0.25);
+        assert!(!timer.is_finished());
+
+        assert!(!timer.tick(0.125));
+        assert_eq!(timer.get_times_finished(), 0);
+
+        assert!(timer.tick(0.125));
+        assert_eq!(timer.get_times_finished(), 1);
+        assert_close(timer.get_elapsed(), 0.0);
+    }
+
+    #[test]
+    fn paused_timer_doesnt_advance() {
+        let mut timer = Timer::repeating(1.0);
+        timer.tick(0.5);
+
+        timer.pause();
+        assert!(timer.is_paused());
+        assert!(!timer.tick(10.0));
+        assert_eq!(timer.get_elapsed(), 0.5);
+
+        timer.resume();
+        assert!(timer.tick(0.5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_duration_timer_panics() {
+        Timer::new(0.0);
+    }
+
+    #[test]
+    fn stopwatch() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.tick(0.5);
+        stopwatch.tick(0.25);
+        assert_eq!(stopwatch.get_elapsed(), 0.75);
+
+        stopwatch.pause();
+        stopwatch.tick(1.0);
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.get_elapsed(), 0.75);
+
+        stopwatch.resume();
+        stopwatch.tick(0.25);
+        assert_eq!(stopwatch.get_elapsed(), 1.0);
+
+        stopwatch.reset();
+        assert_eq!(stopwatch.get_elapsed(), 0.0);
+    }
+}
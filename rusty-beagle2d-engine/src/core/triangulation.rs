@@ -0,0 +1,221 @@
+use linear_beaglebra::vector2::Vector2;
+use crate::core::math2d;
+
+// Triangulates a simple polygon (convex or concave, either winding order) using ear clipping.
+// The triangles are appended to "indices" as triplets of indices into "polygon".
+// Ear clipping is O(n^2), which is fine for the hand made shapes we draw for debugging and UI.
+// NOTE: Self-intersecting polygons have no proper triangulation. They still produce triangles
+// (so drawing never fails), but the filled result will overlap itself.
+pub fn triangulate(polygon: &[Vector2], indices: &mut Vec<u32>) {
+    if polygon.len() < 3 {
+        return;
+    }
+
+    let is_counter_clockwise = signed_area(polygon) >= 0.0;
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+
+    while remaining.len() > 3 {
+        let vertex_count = remaining.len();
+        let mut clipped_ear = false;
+
+        for current in 0..vertex_count {
+            let previous = remaining[(current + vertex_count - 1) % vertex_count];
+            let next = remaining[(current + 1) % vertex_count];
+
+            if is_ear(polygon, &remaining, previous, remaining[current], next, is_counter_clockwise) {
+                indices.push(previous as u32);
+                indices.push(remaining[current] as u32);
+                indices.push(next as u32);
+
+                remaining.remove(current);
+                clipped_ear = true;
+                break;
+            }
+        }
+
+        if !clipped_ear {
+            // No ear means the remaining outline is degenerate (collinear points) or self-intersecting.
+            // Drop a collinear point if there is one, since it adds no area, otherwise clip anyway to make progress.
+            let collinear = (0..vertex_count).find(|&current| {
+                let previous = polygon[remaining[(current + vertex_count - 1) % vertex_count]];
+                let next = polygon[remaining[(current + 1) % vertex_count]];
+                corner_turn(previous, polygon[remaining[current]], next).abs() <= std::f32::EPSILON
+            });
+
+            match collinear {
+                Some(current) => {
+                    remaining.remove(current);
+                },
+                None => {
+                    indices.push(remaining[vertex_count - 1] as u32);
+                    indices.push(remaining[0] as u32);
+                    indices.push(remaining[1] as u32);
+                    remaining.remove(0);
+                }
+            }
+        }
+    }
+
+    indices.push(remaining[0] as u32);
+    indices.push(remaining[1] as u32);
+    indices.push(remaining[2] as u32);
+}
+
+// Shoelace formula. Positive for counter-clockwise polygons in a y-up coordinate system.
+pub fn signed_area(polygon: &[Vector2]) -> f32 {
+    let mut area = 0.0;
+
+    for index in 0..polygon.len() {
+        let current = polygon[index];
+        let next = polygon[(index + 1) % polygon.len()];
+        area += math2d::cross(current, next);
+    }
+
+    area * 0.5
+}
+
+fn corner_turn(previous: Vector2, current: Vector2, next: Vector2) -> f32 {
+    math2d::cross(math2d::sub(current, previous), math2d::sub(next, current))
+}
+
+fn is_ear(polygon: &[Vector2], remaining: &[usize], previous: usize, current: usize, next: usize, is_counter_clockwise: bool) -> bool {
+    let a = polygon[previous];
+    let b = polygon[current];
+    let c = polygon[next];
+
+    // Reflex corners (turning against the winding) can never be ears.
+    let turn = corner_turn(a, b, c);
+    let is_convex = if is_counter_clockwise { turn > std::f32::EPSILON } else { turn < -std::f32::EPSILON };
+
+    if !is_convex {
+        return false;
+    }
+
+    // An ear must not contain any other vertex of the polygon
+    remaining.iter()
+        .filter(|&&index| index != previous && index != current && index != next)
+        .map(|&index| polygon[index])
+        .filter(|&point| !same_point(point, a) && !same_point(point, b) && !same_point(point, c))
+        .all(|point| !is_point_in_triangle(point, a, b, c))
+}
+
+fn same_point(a: Vector2, b: Vector2) -> bool {
+    a.x == b.x && a.y == b.y
+}
+
+// Points on the edges count as inside, so an ear touching another vertex is rejected.
+fn is_point_in_triangle(point: Vector2, a: Vector2, b: Vector2, c: Vector2) -> bool {
+    let d1 = corner_turn(a, b, point);
+    let d2 = corner_turn(b, c, point);
+    let d3 = corner_turn(c, a, point);
+
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+
+    !(has_negative && has_positive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(points: &[(f32, f32)]) -> Vec<Vector2> {
+        points.iter().map(|&(x, y)| Vector2::new(x, y)).collect()
+    }
+
+    fn triangle_area(polygon: &[Vector2], triangle: &[u32]) -> f32 {
+        signed_area(&[polygon[triangle[0] as usize], polygon[triangle[1] as usize], polygon[triangle[2] as usize]])
+    }
+
+    // Every triangle turns the same way as the polygon, and together they cover exactly its area.
+    fn check_triangulation(polygon: &[Vector2]) -> Vec<u32> {
+        let mut indices = Vec::new();
+        triangulate(polygon, &mut indices);
+
+        assert_eq!(indices.len() % 3, 0);
+        assert!(indices.iter().all(|&index| (index as usize) < polygon.len()));
+
+        let area = signed_area(polygon);
+        let mut covered = 0.0;
+        for triangle in indices.chunks(3) {
+            let triangle_area = triangle_area(polygon, triangle);
+            assert!(triangle_area * area >= 0.0, "Triangle {:?} is wound against the polygon", triangle);
+            covered += triangle_area;
+        }
+
+        assert!((covered - area).abs() < 1e-3, "Triangles cover {} of {}", covered, area);
+        indices
+    }
+
+    #[test]
+    fn convex_polygon_gives_n_minus_2_triangles() {
+        let hexagon: Vec<Vector2> = (0..6)
+            .map(|index| math2d::rotate(Vector2::new(10.0, 0.0), index as f32 * std::f32::consts::PI / 3.0))
+            .collect();
+
+        assert_eq!(check_triangulation(&hexagon).len(), 4 * 3);
+    }
+
+    #[test]
+    fn either_winding_order() {
+        let counter_clockwise = polygon(&[(0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)]);
+        let clockwise: Vec<Vector2> = counter_clockwise.iter().rev().cloned().collect();
+
+        assert_eq!(signed_area(&counter_clockwise), 12.0);
+        assert_eq!(signed_area(&clockwise), -12.0);
+        assert_eq!(check_triangulation(&counter_clockwise).len(), 6);
+        assert_eq!(check_triangulation(&clockwise).len(), 6);
+    }
+
+    #[test]
+    fn concave_polygons() {
+        let l_shape = polygon(&[(0.0, 0.0), (4.0, 0.0), (4.0, 1.0), (1.0, 1.0), (1.0, 4.0), (0.0, 4.0)]);
+        assert_eq!(check_triangulation(&l_shape).len(), 4 * 3);
+
+        let arrow = polygon(&[(0.0, 0.0), (5.0, 2.0), (0.0, 4.0), (2.0, 2.0)]);
+        let indices = check_triangulation(&arrow);
+        // The reflex corner can't be clipped as an ear
+        assert_eq!(indices.len(), 6);
+
+        let star: Vec<Vector2> = (0..10)
+            .map(|index| {
+                let radius = if index % 2 == 0 { 10.0 } else { 4.0 };
+                math2d::rotate(Vector2::new(radius, 0.0), index as f32 * std::f32::consts::PI / 5.0)
+            })
+            .collect();
+        assert_eq!(check_triangulation(&star).len(), 8 * 3);
+
+        let clockwise_star: Vec<Vector2> = star.iter().rev().cloned().collect();
+        assert_eq!(check_triangulation(&clockwise_star).len(), 8 * 3);
+    }
+
+    #[test]
+    fn collinear_points() {
+        // Extra points along the edges of a square add no area and no slivers
+        let square = polygon(&[(0.0, 0.0), (2.0, 0.0), (4.0, 0.0), (4.0, 2.0), (4.0, 4.0), (0.0, 4.0), (0.0, 2.0)]);
+        let indices = check_triangulation(&square);
+        assert!(indices.chunks(3).all(|triangle| triangle_area(&square, triangle).abs() > 0.0));
+
+        // A polygon that is just a line covers nothing, but still terminates
+        let line = polygon(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]);
+        check_triangulation(&line);
+    }
+
+    #[test]
+    fn too_few_points() {
+        let mut indices = Vec::new();
+        triangulate(&polygon(&[(0.0, 0.0), (1.0, 0.0)]), &mut indices);
+        assert!(indices.is_empty());
+
+        triangulate(&polygon(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]), &mut indices);
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn appends_to_existing_indices() {
+        let mut indices = vec![7, 8, 9];
+        triangulate(&polygon(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]), &mut indices);
+        assert_eq!(indices, vec![7, 8, 9, 0, 1, 2]);
+    }
+}
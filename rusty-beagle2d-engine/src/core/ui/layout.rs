@@ -0,0 +1,284 @@
+use linear_beaglebra::vector2::Vector2;
+
+// Which way a node lays out its children.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Direction {
+    // Left to right
+    Row,
+    // Top to bottom
+    Column
+}
+
+// How big a node is along one axis.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Size {
+    // Just big enough for its content and padding
+    Fit,
+    Pixels(f32),
+    // Of the parent's size inside its padding. 100.0 is all of it.
+    Percent(f32),
+    // Along the parent's direction, shares the space left over by its siblings with the other Grow nodes, by weight.
+    // Never smaller than it would be with Fit. Across the parent's direction, or when anchored, it fills the parent.
+    Grow(f32)
+}
+
+impl Size {
+    // The size for everything but Grow along the parent's direction.
+    // "available" is the parent's size inside its padding, "fit" the node's size with Fit.
+    pub fn resolve(self, available: f32, fit: f32) -> f32 {
+        match self {
+            Size::Fit => fit,
+            Size::Pixels(pixels) => pixels,
+            Size::Percent(percent) => available * percent / 100.0,
+            Size::Grow(_) => available.max(fit)
+        }
+    }
+}
+
+// Where the children of a node go along its direction, when they don't take up all the space.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    // The first child at the start, the last at the end, and the space shared out between them
+    SpaceBetween
+}
+
+// Where the children of a node go across its direction.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    // Children with a Fit size fill the parent
+    Stretch
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Edges {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32
+}
+
+impl Edges {
+    pub const ZERO: Edges = Edges { left: 0.0, top: 0.0, right: 0.0, bottom: 0.0 };
+
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Edges {
+        Edges { left, top, right, bottom }
+    }
+
+    pub fn all(amount: f32) -> Edges {
+        Edges::new(amount, amount, amount, amount)
+    }
+
+    pub fn symmetric(horizontal: f32, vertical: f32) -> Edges {
+        Edges::new(horizontal, vertical, horizontal, vertical)
+    }
+
+    pub fn get_horizontal(&self) -> f32 {
+        self.left + self.right
+    }
+
+    pub fn get_vertical(&self) -> f32 {
+        self.top + self.bottom
+    }
+}
+
+// A point of a rectangle.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight
+}
+
+impl Anchor {
+    // The point as fractions of the rectangle's size, from (0, 0) top left to (1, 1) bottom right.
+    pub fn get_fractions(self) -> Vector2 {
+        match self {
+            Anchor::TopLeft => Vector2::new(0.0, 0.0),
+            Anchor::Top => Vector2::new(0.5, 0.0),
+            Anchor::TopRight => Vector2::new(1.0, 0.0),
+            Anchor::Left => Vector2::new(0.0, 0.5),
+            Anchor::Center => Vector2::new(0.5, 0.5),
+            Anchor::Right => Vector2::new(1.0, 0.5),
+            Anchor::BottomLeft => Vector2::new(0.0, 1.0),
+            Anchor::Bottom => Vector2::new(0.5, 1.0),
+            Anchor::BottomRight => Vector2::new(1.0, 1.0)
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Position {
+    // Placed after the previous sibling, in the parent's direction
+    Flow,
+    // Left out of the parent's row or column, and pinned to a point of the parent's inside instead.
+    // The same point of the node goes there, so TopRight puts the node's top right corner in the parent's top right corner.
+    // "offset" moves it from there, in pixels.
+    Anchored { anchor: Anchor, offset: Vector2 }
+}
+
+// How a node is sized and placed, and how it places its children. Like a small subset of CSS flexbox.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LayoutStyle {
+    pub width: Size,
+    pub height: Size,
+    pub direction: Direction,
+    // Between the node's edges and its children or content
+    pub padding: Edges,
+    // Between the children
+    pub spacing: f32,
+    pub justify: Justify,
+    pub align: Align,
+    pub position: Position
+}
+
+impl LayoutStyle {
+    // A column that fits its content.
+    pub fn new() -> LayoutStyle {
+        LayoutStyle {
+            width: Size::Fit,
+            height: Size::Fit,
+            direction: Direction::Column,
+            padding: Edges::ZERO,
+            spacing: 0.0,
+            justify: Justify::Start,
+            align: Align::Start,
+            position: Position::Flow
+        }
+    }
+
+    pub fn row() -> LayoutStyle {
+        LayoutStyle::new().with_direction(Direction::Row)
+    }
+
+    pub fn column() -> LayoutStyle {
+        LayoutStyle::new()
+    }
+
+    pub fn with_size(mut self, width: Size, height: Size) -> LayoutStyle {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_width(mut self, width: Size) -> LayoutStyle {
+        self.width = width;
+        self
+    }
+
+    pub fn with_height(mut self, height: Size) -> LayoutStyle {
+        self.height = height;
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> LayoutStyle {
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: Edges) -> LayoutStyle {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: f32) -> LayoutStyle {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn with_justify(mut self, justify: Justify) -> LayoutStyle {
+        self.justify = justify;
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> LayoutStyle {
+        self.align = align;
+        self
+    }
+
+    pub fn anchored(mut self, anchor: Anchor, offset: Vector2) -> LayoutStyle {
+        self.position = Position::Anchored { anchor, offset };
+        self
+    }
+
+    // The size along the direction of "direction", and across it.
+    pub(crate) fn get_sizes(&self, direction: Direction) -> (Size, Size) {
+        match direction {
+            Direction::Row => (self.width, self.height),
+            Direction::Column => (self.height, self.width)
+        }
+    }
+}
+
+// Splits a vector into the part along "direction" and the part across it.
+pub(crate) fn split(vector: Vector2, direction: Direction) -> (f32, f32) {
+    match direction {
+        Direction::Row => (vector.x, vector.y),
+        Direction::Column => (vector.y, vector.x)
+    }
+}
+
+// The opposite of split.
+pub(crate) fn join(main: f32, cross: f32, direction: Direction) -> Vector2 {
+    match direction {
+        Direction::Row => Vector2::new(main, cross),
+        Direction::Column => Vector2::new(cross, main)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolving_sizes() {
+        assert_eq!(Size::Fit.resolve(300.0, 40.0), 40.0);
+        assert_eq!(Size::Pixels(25.0).resolve(300.0, 40.0), 25.0);
+        assert_eq!(Size::Percent(50.0).resolve(300.0, 40.0), 150.0);
+        // Filling the parent, but never smaller than it fits
+        assert_eq!(Size::Grow(1.0).resolve(300.0, 40.0), 300.0);
+        assert_eq!(Size::Grow(1.0).resolve(30.0, 40.0), 40.0);
+    }
+
+    #[test]
+    fn splitting_by_direction() {
+        let vector = Vector2::new(3.0, 7.0);
+
+        assert_eq!(split(vector, Direction::Row), (3.0, 7.0));
+        assert_eq!(split(vector, Direction::Column), (7.0, 3.0));
+
+        for direction in [Direction::Row, Direction::Column] {
+            let (main, cross) = split(vector, direction);
+            let joined = join(main, cross, direction);
+            assert_eq!((joined.x, joined.y), (3.0, 7.0));
+        }
+
+        let style = LayoutStyle::new().with_size(Size::Pixels(1.0), Size::Fit);
+        assert_eq!(style.get_sizes(Direction::Row), (Size::Pixels(1.0), Size::Fit));
+        assert_eq!(style.get_sizes(Direction::Column), (Size::Fit, Size::Pixels(1.0)));
+    }
+
+    #[test]
+    fn edges_and_anchors() {
+        let edges = Edges::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(edges.get_horizontal(), 4.0);
+        assert_eq!(edges.get_vertical(), 6.0);
+        assert_eq!(Edges::symmetric(5.0, 6.0), Edges::new(5.0, 6.0, 5.0, 6.0));
+
+        let bottom_right = Anchor::BottomRight.get_fractions();
+        assert_eq!((bottom_right.x, bottom_right.y), (1.0, 1.0));
+        let top = Anchor::Top.get_fractions();
+        assert_eq!((top.x, top.y), (0.5, 0.0));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod layout;
+pub mod theme;
+pub mod widget;
+pub mod ui_tree;
\ No newline at end of file
@@ -0,0 +1,177 @@
+// The look of a UiTree: colors, text size and the padding inside widgets.
+// Themes can be loaded from JSON. Anything left out keeps the value of Theme::new, down to single colors of a box,
+// So a theme file only needs what it changes:
+// {
+//   "text_scale": 0.8,
+//   "button": { "background": "#30384a", "border": "#5a6070", "text": "#ffffff" },
+//   "focus_color": "#ffcc00"
+// }
+// Colors are "#RRGGBB", or "#RRGGBBAA" with alpha.
+
+use crate::core::color::Color;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use std::path::Path;
+
+// Colors of a widget that draws a box with text in it.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BoxStyle {
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub background: Color,
+    // Not drawn when fully transparent
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub border: Color,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub text: Color
+}
+
+impl BoxStyle {
+    pub fn new(background: Color, border: Color, text: Color) -> BoxStyle {
+        BoxStyle { background, border, text }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Theme {
+    pub text_scale: f32,
+    // Between the edges of buttons, list items and text fields and their text
+    pub widget_padding: f32,
+    pub border_thickness: f32,
+    // Default width of progress bars, lists and text fields
+    pub item_width: f32,
+    // Pixels scrolled per step of the mouse wheel
+    pub scroll_speed: f32,
+    pub scroll_bar_width: f32,
+    pub panel: BoxStyle,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub label_color: Color,
+    pub button: BoxStyle,
+    pub button_hovered: BoxStyle,
+    pub button_pressed: BoxStyle,
+    pub button_disabled: BoxStyle,
+    // Outline around the widget with keyboard or gamepad focus
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub focus_color: Color,
+    pub focus_thickness: f32,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub progress_track_color: Color,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub progress_fill_color: Color,
+    pub list: BoxStyle,
+    pub list_item_hovered: BoxStyle,
+    pub list_item_selected: BoxStyle,
+    pub text_field: BoxStyle,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub placeholder_color: Color,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub cursor_color: Color,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub scroll_bar_color: Color,
+    #[serde(serialize_with = "serialize_color", deserialize_with = "deserialize_color")]
+    pub scroll_grab_color: Color
+}
+
+impl Theme {
+    pub fn new() -> Theme {
+        let text = Color::from_rgba8(235, 235, 235, 255);
+        let border = Color::from_rgba8(90, 95, 110, 255);
+
+        Theme {
+            text_scale: 1.0,
+            widget_padding: 6.0,
+            border_thickness: 1.0,
+            item_width: 200.0,
+            scroll_speed: 40.0,
+            scroll_bar_width: 6.0,
+            panel: BoxStyle::new(Color::from_rgba8(20, 22, 28, 235), border, text),
+            label_color: text,
+            button: BoxStyle::new(Color::from_rgba8(50, 55, 68, 255), border, text),
+            button_hovered: BoxStyle::new(Color::from_rgba8(70, 80, 100, 255), border, text),
+            button_pressed: BoxStyle::new(Color::from_rgba8(90, 110, 150, 255), border, text),
+            button_disabled: BoxStyle::new(Color::from_rgba8(40, 42, 48, 255), border, Color::from_rgba8(120, 120, 120, 255)),
+            focus_color: Color::from_rgba8(255, 200, 60, 255),
+            focus_thickness: 2.0,
+            progress_track_color: Color::from_rgba8(40, 42, 48, 255),
+            progress_fill_color: Color::from_rgba8(110, 170, 255, 255),
+            list: BoxStyle::new(Color::from_rgba8(30, 33, 40, 255), border, text),
+            list_item_hovered: BoxStyle::new(Color::from_rgba8(55, 62, 78, 255), Color::TRANSPARENT, text),
+            list_item_selected: BoxStyle::new(Color::from_rgba8(90, 110, 150, 255), Color::TRANSPARENT, Color::WHITE),
+            text_field: BoxStyle::new(Color::from_rgba8(30, 33, 40, 255), border, text),
+            placeholder_color: Color::from_rgba8(130, 130, 140, 255),
+            cursor_color: Color::from_rgba8(110, 170, 255, 255),
+            scroll_bar_color: Color::from_rgba8(40, 42, 48, 200),
+            scroll_grab_color: Color::from_rgba8(110, 115, 130, 255)
+        }
+    }
+
+    // Theme::new with the values in "json" put over it.
+    pub fn from_json(json: &str) -> Result<Theme, String> {
+        let changes: Value = serde_json::from_str(json).map_err(|error| error.to_string())?;
+
+        let mut theme = serde_json::to_value(Theme::new()).map_err(|error| error.to_string())?;
+        merge_json(&mut theme, changes);
+
+        serde_json::from_value(theme).map_err(|error| error.to_string())
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|error| error.to_string())
+    }
+
+    // Height of a button, list item or text field: one line of text and the padding around it.
+    pub fn get_item_height(&self, line_height: f32) -> f32 {
+        line_height * self.text_scale + self.widget_padding * 2.0
+    }
+}
+
+pub fn load_theme(path: &Path) -> Result<Theme, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read theme {}: {}", path.display(), error))?;
+
+    Theme::from_json(&json)
+        .map_err(|error| format!("Failed to load theme {}: {}", path.display(), error))
+}
+
+// "#RRGGBB" or "#RRGGBBAA".
+pub fn parse_color(text: &str) -> Result<Color, String> {
+    let error = || format!("Invalid color \"{}\", expected \"#RRGGBB\" or \"#RRGGBBAA\"", text);
+
+    let hex = text.strip_prefix('#').ok_or_else(error)?;
+
+    if !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(error());
+    }
+
+    let value = u32::from_str_radix(hex, 16).map_err(|_| error())?;
+    let channel = |shift: u32| ((value >> shift) & 0xFF) as u8;
+
+    match hex.len() {
+        6 => Ok(Color::from_rgba8(channel(16), channel(8), channel(0), 255)),
+        8 => Ok(Color::from_rgba8(channel(24), channel(16), channel(8), channel(0))),
+        _ => Err(error())
+    }
+}
+
+// Puts the values of "changes" over "base". Objects are merged key by key, everything else is replaced.
+fn merge_json(base: &mut Value, changes: Value) {
+    match (base, changes) {
+        (Value::Object(base), Value::Object(changes)) => {
+            for (key, value) in changes {
+                merge_json(base.entry(key).or_insert(Value::Null), value);
+            }
+        },
+        (base, changes) => *base = changes
+    }
+}
+
+fn serialize_color<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}{:02x}", channel(color.r), channel(color.g), channel(color.b), channel(color.a)))
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_color(&text).map_err(serde::de::Error::custom)
+}
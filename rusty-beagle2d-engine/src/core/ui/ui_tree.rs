@@ -0,0 +1,1678 @@
+use crate::core::font::FontMetrics;
+use crate::core::input::{Key, MouseButton, WindowEvent};
+use crate::core::nine_slice::NineSlice;
+use crate::core::rect::Rect;
+use crate::core::renderer2d::Renderer2d;
+use crate::core::sprite::{Origin, Sprite};
+use crate::core::ui::layout::{self, Align, Direction, Justify, LayoutStyle, Position, Size};
+use crate::core::ui::theme::{BoxStyle, Theme};
+use crate::core::ui::widget::Widget;
+
+use linear_beaglebra::vector2::Vector2;
+
+// Layer the UI is drawn on, above the game and below the debug UI.
+pub const UI_LAYER: i32 = 900_000;
+
+// Refers to a node in a UiTree. Like scene::NodeId, an id of a removed node never points at a node created later.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct UiNodeId {
+    index: u32,
+    generation: u32
+}
+
+// The ways of moving through a UI without a mouse.
+// Keyboard events are turned into these by UiTree::handle_event. For a gamepad, call UiTree::navigate
+// With the d-pad and the confirm and back buttons mapped to them.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Navigation {
+    Up,
+    Down,
+    Left,
+    Right,
+    // The next and previous focusable widget in tree order, like tab and shift tab
+    Next,
+    Previous,
+    // Presses the focused widget
+    Activate,
+    // Backs out, like escape. The UI only reports it, the game decides what it means.
+    Cancel
+}
+
+// Something the player did to the UI, for the game to react to.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UiEvent {
+    // A button was clicked, or activated while focused
+    Clicked(UiNodeId),
+    // Another item of a list was selected
+    SelectionChanged { list: UiNodeId, index: usize },
+    // The selected item of a list was clicked again, or activated while focused
+    ItemActivated { list: UiNodeId, index: usize },
+    // The text of a text field was typed into
+    TextChanged(UiNodeId),
+    // Enter was pressed in a text field
+    TextSubmitted(UiNodeId),
+    FocusChanged(Option<UiNodeId>),
+    Cancelled
+}
+
+pub struct UiNode {
+    pub widget: Widget,
+    pub layout: LayoutStyle,
+    visible: bool,
+    // Disabled widgets are greyed out and can't be clicked or focused, and neither can anything below them
+    enabled: bool,
+    parent: Option<UiNodeId>,
+    children: Vec<UiNodeId>,
+    // Size before the parent had its say, from the last update_layout. Pixels sizes, or what fits the content otherwise.
+    measured_size: Vector2,
+    // Where the node ended up in the last update_layout
+    rect: Rect,
+    // For scroll views, how far the children reach along the direction, padding left out
+    content_length: f32
+}
+
+impl UiNode {
+    fn new(widget: Widget, layout: LayoutStyle, parent: Option<UiNodeId>) -> UiNode {
+        UiNode {
+            widget,
+            layout,
+            visible: true,
+            enabled: true,
+            parent,
+            children: Vec::new(),
+            measured_size: Vector2::new(0.0, 0.0),
+            rect: Rect::new(0.0, 0.0, 0.0, 0.0),
+            content_length: 0.0
+        }
+    }
+
+    // The rectangle inside the padding, where the children and content go.
+    fn get_inner_rect(&self) -> Rect {
+        let padding = self.layout.padding;
+
+        Rect::new(
+            self.rect.x + padding.left,
+            self.rect.y + padding.top,
+            (self.rect.width - padding.get_horizontal()).max(0.0),
+            (self.rect.height - padding.get_vertical()).max(0.0))
+    }
+
+    // How far a scroll view can scroll before its content ends.
+    fn get_max_scroll_offset(&self) -> f32 {
+        let inner = self.get_inner_rect();
+        let (view_length, _) = layout::split(Vector2::new(inner.width, inner.height), self.layout.direction);
+
+        (self.content_length - view_length).max(0.0)
+    }
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<UiNode>
+}
+
+// A retained UI for menus and HUDs: a tree of widgets that stays around between frames, laid out like flexbox.
+// Build it once, then every frame:
+//
+// for event in events { ui.handle_event(&event); }
+// ui.update_layout(Rect::new(0.0, 0.0, 1024.0, 768.0));
+// ui.take_events(&mut ui_events);   // React to clicks and such
+// ui.render(renderer);
+//
+// Layout and input only need the font's metrics, not a renderer, so they work without a window.
+pub struct UiTree {
+    slots: Vec<Slot>,
+    free_indices: Vec<u32>,
+    root: UiNodeId,
+    theme: Theme,
+    font: FontMetrics,
+    events: Vec<UiEvent>,
+    focused: Option<UiNodeId>,
+    // The topmost node under the mouse that draws something
+    hovered: Option<UiNodeId>,
+    // The widget the left mouse button went down on
+    pressed: Option<UiNodeId>,
+    mouse_position: Vector2,
+    shift_down: bool
+}
+
+impl UiTree {
+    pub fn new(font: FontMetrics, theme: Theme) -> UiTree {
+        let mut tree = UiTree {
+            slots: Vec::new(),
+            free_indices: Vec::new(),
+            root: UiNodeId { index: 0, generation: 0 },
+            theme,
+            font,
+            events: Vec::new(),
+            focused: None,
+            hovered: None,
+            pressed: None,
+            mouse_position: Vector2::new(-1.0, -1.0),
+            shift_down: false
+        };
+
+        tree.root = tree.allocate(UiNode::new(Widget::Empty, LayoutStyle::new(), None));
+        tree
+    }
+
+    // The node covering the screen rectangle given to update_layout, which everything else goes in.
+    // It lays out its children in a column by default. Change that with get_layout_mut.
+    pub fn get_root(&self) -> UiNodeId {
+        self.root
+    }
+
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    // Adds a node as the last child of "parent", placed after its siblings and drawn on top of them.
+    pub fn add(&mut self, parent: UiNodeId, widget: Widget, layout: LayoutStyle) -> UiNodeId {
+        self.get_node_or_panic(parent);
+
+        let id = self.allocate(UiNode::new(widget, layout, Some(parent)));
+        self.get_node_mut_or_panic(parent).children.push(id);
+        id
+    }
+
+    pub fn add_panel(&mut self, parent: UiNodeId, layout: LayoutStyle) -> UiNodeId {
+        self.add(parent, Widget::Panel, layout)
+    }
+
+    pub fn add_label(&mut self, parent: UiNodeId, text: &str) -> UiNodeId {
+        self.add(parent, Widget::label(text), LayoutStyle::new())
+    }
+
+    pub fn add_button(&mut self, parent: UiNodeId, text: &str) -> UiNodeId {
+        self.add(parent, Widget::button(text), LayoutStyle::new())
+    }
+
+    // Removes the node and everything below it. Returns false if it was already removed.
+    pub fn remove(&mut self, id: UiNodeId) -> bool {
+        if id == self.root {
+            panic!("The root of a UiTree can't be removed");
+        }
+
+        let parent = match self.get_node(id) {
+            Some(node) => node.parent,
+            None => return false
+        };
+
+        if let Some(parent) = parent {
+            self.get_node_mut_or_panic(parent).children.retain(|&child| child != id);
+        }
+
+        let mut to_remove = vec![id];
+        while let Some(current) = to_remove.pop() {
+            let slot = &mut self.slots[current.index as usize];
+            let node = slot.node.take().expect("Child of a node was already removed");
+
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_indices.push(current.index);
+
+            to_remove.extend(node.children);
+        }
+
+        if self.focused.is_some_and(|focused| !self.contains(focused)) {
+            self.set_focus(None);
+        }
+
+        self.hovered = self.hovered.filter(|&hovered| self.contains(hovered));
+        self.pressed = self.pressed.filter(|&pressed| self.contains(pressed));
+
+        true
+    }
+
+    pub fn contains(&self, id: UiNodeId) -> bool {
+        self.get_node(id).is_some()
+    }
+
+    pub fn get_node(&self, id: UiNodeId) -> Option<&UiNode> {
+        let slot = self.slots.get(id.index as usize)?;
+
+        if slot.generation != id.generation {
+            return None;
+        }
+
+        slot.node.as_ref()
+    }
+
+    pub fn get_node_mut(&mut self, id: UiNodeId) -> Option<&mut UiNode> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+
+        if slot.generation != id.generation {
+            return None;
+        }
+
+        slot.node.as_mut()
+    }
+
+    pub fn get_widget(&self, id: UiNodeId) -> &Widget {
+        &self.get_node_or_panic(id).widget
+    }
+
+    pub fn get_widget_mut(&mut self, id: UiNodeId) -> &mut Widget {
+        &mut self.get_node_mut_or_panic(id).widget
+    }
+
+    // Changes take effect at the next update_layout.
+    pub fn get_layout_mut(&mut self, id: UiNodeId) -> &mut LayoutStyle {
+        &mut self.get_node_mut_or_panic(id).layout
+    }
+
+    pub fn get_parent(&self, id: UiNodeId) -> Option<UiNodeId> {
+        self.get_node_or_panic(id).parent
+    }
+
+    pub fn get_children(&self, id: UiNodeId) -> &[UiNodeId] {
+        &self.get_node_or_panic(id).children
+    }
+
+    // Hidden nodes take up no space, and nothing below them is drawn or can be focused.
+    pub fn set_visible(&mut self, id: UiNodeId, visible: bool) {
+        self.get_node_mut_or_panic(id).visible = visible;
+        self.drop_focus_if_unreachable();
+    }
+
+    pub fn is_visible(&self, id: UiNodeId) -> bool {
+        self.get_node_or_panic(id).visible
+    }
+
+    pub fn set_enabled(&mut self, id: UiNodeId, enabled: bool) {
+        self.get_node_mut_or_panic(id).enabled = enabled;
+        self.drop_focus_if_unreachable();
+    }
+
+    pub fn is_enabled(&self, id: UiNodeId) -> bool {
+        self.get_node_or_panic(id).enabled
+    }
+
+    // Where the node was put by the last update_layout.
+    pub fn get_rect(&self, id: UiNodeId) -> Rect {
+        self.get_node_or_panic(id).rect
+    }
+
+    // Sets the text of a label, button or text field.
+    pub fn set_text(&mut self, id: UiNodeId, new_text: &str) {
+        match &mut self.get_node_mut_or_panic(id).widget {
+            Widget::Label(text) | Widget::Button(text) => {
+                text.clear();
+                text.push_str(new_text);
+            },
+            Widget::TextField(text_field) => {
+                text_field.text.clear();
+                text_field.text.push_str(new_text);
+                text_field.cursor = text_field.text.len();
+            },
+            _ => panic!("Only labels, buttons and text fields have text")
+        }
+    }
+
+    // The text of a label, button or text field.
+    pub fn get_text(&self, id: UiNodeId) -> &str {
+        self.get_widget(id).get_text().expect("Only labels, buttons and text fields have text")
+    }
+
+    // Sets how full a progress bar is, from 0.0 to 1.0.
+    pub fn set_progress(&mut self, id: UiNodeId, value: f32) {
+        match &mut self.get_node_mut_or_panic(id).widget {
+            Widget::ProgressBar(progress) => *progress = value.clamp(0.0, 1.0),
+            _ => panic!("The node is not a progress bar")
+        }
+    }
+
+    // The selected item of a list.
+    pub fn get_selected(&self, id: UiNodeId) -> Option<usize> {
+        match self.get_widget(id) {
+            Widget::List(list) => list.selected,
+            _ => panic!("The node is not a list")
+        }
+    }
+
+    // Selects an item of a list, without sending a SelectionChanged event.
+    pub fn set_selected(&mut self, id: UiNodeId, selected: Option<usize>) {
+        match &mut self.get_node_mut_or_panic(id).widget {
+            Widget::List(list) => list.selected = selected.filter(|&index| index < list.items.len()),
+            _ => panic!("The node is not a list")
+        }
+    }
+
+    pub fn get_focused(&self) -> Option<UiNodeId> {
+        self.focused
+    }
+
+    // Gives a button, list or text field the keyboard and gamepad focus, or takes it away with None.
+    // Ids of other widgets, or of hidden or disabled ones, are ignored.
+    pub fn set_focus(&mut self, id: Option<UiNodeId>) {
+        let id = id.filter(|&id| self.get_widget(id).is_focusable() && self.is_reachable(id));
+
+        if id == self.focused {
+            return;
+        }
+
+        self.focused = id;
+
+        if let Some(id) = id {
+            if let Widget::TextField(text_field) = self.get_widget_mut(id) {
+                text_field.cursor = text_field.text.len();
+            }
+        }
+
+        self.events.push(UiEvent::FocusChanged(id));
+    }
+
+    // The node under the mouse, if it is over anything the UI draws.
+    pub fn get_hovered(&self) -> Option<UiNodeId> {
+        self.hovered
+    }
+
+    // True while the mouse is over the UI, so the game can ignore clicks that were meant for it.
+    pub fn wants_mouse(&self) -> bool {
+        self.hovered.is_some() || self.pressed.is_some()
+    }
+
+    // True while a text field is being typed into.
+    pub fn wants_keyboard(&self) -> bool {
+        self.focused.is_some_and(|focused| matches!(self.get_widget(focused), Widget::TextField(_)))
+    }
+
+    // Moves what happened since the last call to the end of "events", oldest first.
+    pub fn take_events(&mut self, events: &mut Vec<UiEvent>) {
+        events.append(&mut self.events);
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::MouseMoved { position } => {
+                self.mouse_position = position;
+                self.hovered = self.hit_test(position);
+            },
+            WindowEvent::MouseButtonPressed { button: MouseButton::Left } => self.press_mouse(),
+            WindowEvent::MouseButtonReleased { button: MouseButton::Left } => self.release_mouse(),
+            WindowEvent::MouseScrolled { delta } => self.scroll_at_mouse(delta.y),
+            WindowEvent::KeyPressed { key: Key::LeftShift, .. } | WindowEvent::KeyPressed { key: Key::RightShift, .. } => self.shift_down = true,
+            WindowEvent::KeyReleased { key: Key::LeftShift } | WindowEvent::KeyReleased { key: Key::RightShift } => self.shift_down = false,
+            WindowEvent::KeyPressed { key, .. } => self.press_key(key),
+            WindowEvent::CharacterTyped(character) => self.type_character(character),
+            _ => {}
+        }
+    }
+
+    pub fn navigate(&mut self, navigation: Navigation) {
+        match navigation {
+            Navigation::Activate => {
+                if let Some(focused) = self.focused {
+                    self.activate(focused);
+                }
+            },
+            Navigation::Cancel => self.events.push(UiEvent::Cancelled),
+            Navigation::Next | Navigation::Previous => {
+                let focusable = self.get_focusable_nodes();
+
+                if focusable.is_empty() {
+                    return;
+                }
+
+                let current = self.focused.and_then(|focused| focusable.iter().position(|&id| id == focused));
+                let next = match (current, navigation) {
+                    (Some(index), Navigation::Next) => (index + 1) % focusable.len(),
+                    (Some(index), _) => (index + focusable.len() - 1) % focusable.len(),
+                    (None, Navigation::Next) => 0,
+                    (None, _) => focusable.len() - 1
+                };
+
+                self.focus_and_show(focusable[next]);
+            },
+            Navigation::Up | Navigation::Down | Navigation::Left | Navigation::Right => {
+                let focused = match self.focused {
+                    Some(focused) => focused,
+                    None => {
+                        if let Some(&first) = self.get_focusable_nodes().first() {
+                            self.focus_and_show(first);
+                        }
+
+                        return;
+                    }
+                };
+
+                // Lists move their selection first, and only pass the focus on past their ends
+                if self.move_list_selection(focused, navigation) {
+                    return;
+                }
+
+                if let Some(target) = self.find_in_direction(focused, navigation) {
+                    self.focus_and_show(target);
+                }
+            }
+        }
+    }
+
+    // Sizes and places every node to fit in "screen", usually the whole view.
+    // Call after changing the tree and before rendering. Input uses the rectangles of the last call.
+    pub fn update_layout(&mut self, screen: Rect) {
+        let root = self.root;
+
+        self.measure(root);
+        self.arrange(root, screen);
+
+        self.hovered = self.hit_test(self.mouse_position);
+    }
+
+    // Draws the tree on the UI layer, in view coordinates regardless of the camera.
+    pub fn render(&mut self, renderer: &mut Renderer2d) {
+        let camera_position = renderer.get_camera_position();
+        let layer = renderer.get_layer();
+        let clip_rect = renderer.get_clip_rect();
+
+        renderer.set_camera_position(0.0, 0.0);
+        renderer.set_layer(UI_LAYER);
+
+        let root = self.root;
+        self.render_node(renderer, root, clip_rect);
+
+        renderer.set_clip_rect(clip_rect);
+        renderer.set_layer(layer);
+        renderer.set_camera_position(camera_position.x, camera_position.y);
+    }
+
+    fn allocate(&mut self, node: UiNode) -> UiNodeId {
+        match self.free_indices.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                UiNodeId { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                UiNodeId { index: self.slots.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    fn get_node_or_panic(&self, id: UiNodeId) -> &UiNode {
+        self.get_node(id).expect("The UI node was removed")
+    }
+
+    fn get_node_mut_or_panic(&mut self, id: UiNodeId) -> &mut UiNode {
+        self.get_node_mut(id).expect("The UI node was removed")
+    }
+
+    // Visible and enabled, and so is everything above it.
+    fn is_reachable(&self, id: UiNodeId) -> bool {
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            let node = self.get_node_or_panic(id);
+
+            if !node.visible || !node.enabled {
+                return false;
+            }
+
+            current = node.parent;
+        }
+
+        true
+    }
+
+    fn drop_focus_if_unreachable(&mut self) {
+        if self.focused.is_some_and(|focused| !self.is_reachable(focused)) {
+            self.set_focus(None);
+        }
+
+        if self.pressed.is_some_and(|pressed| !self.is_reachable(pressed)) {
+            self.pressed = None;
+        }
+    }
+
+    // Line height of text at the theme's scale.
+    fn get_text_height(&self) -> f32 {
+        self.font.get_line_height() * self.theme.text_scale
+    }
+
+    fn get_item_height(&self) -> f32 {
+        self.theme.get_item_height(self.font.get_line_height())
+    }
+
+    // ----- Layout -----
+
+    // Size of what the widget itself shows, without padding or children.
+    fn get_content_size(&self, widget: &Widget) -> Vector2 {
+        let theme = &self.theme;
+        let padding = theme.widget_padding * 2.0;
+
+        match widget {
+            Widget::Empty | Widget::Panel | Widget::ScrollView(_) => Vector2::new(0.0, 0.0),
+            Widget::Label(text) => self.font.measure_text(text, theme.text_scale),
+            Widget::Button(text) => {
+                let text_size = self.font.measure_text(text, theme.text_scale);
+                Vector2::new(text_size.x + padding, text_size.y + padding)
+            },
+            Widget::Image(sprite) => {
+                let (width, height) = sprite.get_source_size();
+                Vector2::new(width, height)
+            },
+            Widget::NineSlice(nine_slice) => {
+                let (width, height) = nine_slice.get_min_size();
+                Vector2::new(width, height)
+            },
+            Widget::ProgressBar(_) => Vector2::new(theme.item_width, padding),
+            Widget::List(list) => {
+                let widest_item = list.items.iter()
+                    .map(|item| self.font.measure_text(item, theme.text_scale).x + padding)
+                    .fold(theme.item_width, f32::max);
+
+                Vector2::new(widest_item, list.items.len() as f32 * self.get_item_height())
+            },
+            Widget::TextField(_) => Vector2::new(theme.item_width, self.get_item_height())
+        }
+    }
+
+    // Works out the measured size of the node and everything below it, children first.
+    fn measure(&mut self, id: UiNodeId) -> Vector2 {
+        let layout = self.get_node_or_panic(id).layout;
+        let direction = layout.direction;
+
+        let mut main_length = 0.0;
+        let mut cross_length: f32 = 0.0;
+        let mut flow_count = 0;
+
+        for index in 0..self.get_node_or_panic(id).children.len() {
+            let child = self.get_node_or_panic(id).children[index];
+
+            if !self.get_node_or_panic(child).visible {
+                continue;
+            }
+
+            let child_size = self.measure(child);
+
+            if self.get_node_or_panic(child).layout.position == Position::Flow {
+                let (main, cross) = layout::split(child_size, direction);
+                main_length += main;
+                cross_length = cross_length.max(cross);
+                flow_count += 1;
+            }
+        }
+
+        if flow_count > 1 {
+            main_length += layout.spacing * (flow_count - 1) as f32;
+        }
+
+        let children_size = layout::join(main_length, cross_length, direction);
+        let content_size = self.get_content_size(&self.get_node_or_panic(id).widget);
+
+        let fit_width = content_size.x.max(children_size.x) + layout.padding.get_horizontal();
+        let fit_height = content_size.y.max(children_size.y) + layout.padding.get_vertical();
+
+        let measured_size = Vector2::new(
+            match layout.width { Size::Pixels(pixels) => pixels, _ => fit_width },
+            match layout.height { Size::Pixels(pixels) => pixels, _ => fit_height });
+
+        self.get_node_mut_or_panic(id).measured_size = measured_size;
+        measured_size
+    }
+
+    // Places the node at "rect", then its children inside it.
+    fn arrange(&mut self, id: UiNodeId, rect: Rect) {
+        self.get_node_mut_or_panic(id).rect = rect;
+
+        let node = self.get_node_or_panic(id);
+        let layout = node.layout;
+        let direction = layout.direction;
+        let inner = node.get_inner_rect();
+        let child_count = node.children.len();
+
+        let (inner_main, inner_cross) = layout::split(Vector2::new(inner.width, inner.height), direction);
+        let (inner_main_start, inner_cross_start) = layout::split(Vector2::new(inner.x, inner.y), direction);
+
+        // What the children not growing take up, and how much the growing ones want
+        let mut fixed_length = 0.0;
+        let mut total_weight = 0.0;
+        let mut flow_count = 0;
+
+        for index in 0..child_count {
+            let child = self.get_node_or_panic(self.get_node_or_panic(id).children[index]);
+
+            if !child.visible || child.layout.position != Position::Flow {
+                continue;
+            }
+
+            match child.layout.get_sizes(direction).0 {
+                Size::Grow(weight) => total_weight += weight.max(0.0),
+                main_size => fixed_length += main_size.resolve(inner_main, layout::split(child.measured_size, direction).0)
+            }
+
+            flow_count += 1;
+        }
+
+        let total_spacing = if flow_count > 1 { layout.spacing * (flow_count - 1) as f32 } else { 0.0 };
+        let leftover = (inner_main - fixed_length - total_spacing).max(0.0);
+
+        let get_main_length = |child: &UiNode| {
+            let measured = layout::split(child.measured_size, direction).0;
+
+            match child.layout.get_sizes(direction).0 {
+                Size::Grow(weight) if total_weight > 0.0 => (leftover * weight.max(0.0) / total_weight).max(measured),
+                Size::Grow(_) => measured,
+                main_size => main_size.resolve(inner_main, measured)
+            }
+        };
+
+        let mut used_length = total_spacing;
+        for index in 0..child_count {
+            let child = self.get_node_or_panic(self.get_node_or_panic(id).children[index]);
+
+            if child.visible && child.layout.position == Position::Flow {
+                used_length += get_main_length(child);
+            }
+        }
+
+        // Scroll views keep what is past their end reachable, everything else lets it stick out
+        let scroll_offset = match self.get_node_or_panic(id).widget {
+            Widget::ScrollView(offset) => {
+                let offset = offset.clamp(0.0, (used_length - inner_main).max(0.0));
+
+                let node = self.get_node_mut_or_panic(id);
+                node.content_length = used_length;
+                node.widget = Widget::ScrollView(offset);
+                offset
+            },
+            _ => 0.0
+        };
+
+        let free_length = (inner_main - used_length).max(0.0);
+        let (start, gap) = match layout.justify {
+            Justify::Start => (0.0, layout.spacing),
+            Justify::Center => (free_length * 0.5, layout.spacing),
+            Justify::End => (free_length, layout.spacing),
+            Justify::SpaceBetween if flow_count > 1 => (0.0, layout.spacing + free_length / (flow_count - 1) as f32),
+            Justify::SpaceBetween => (0.0, layout.spacing)
+        };
+
+        let mut pen = start - scroll_offset;
+
+        for index in 0..child_count {
+            let child_id = self.get_node_or_panic(id).children[index];
+            let child = self.get_node_or_panic(child_id);
+
+            if !child.visible {
+                continue;
+            }
+
+            let child_rect = match child.layout.position {
+                Position::Flow => {
+                    let main_length = get_main_length(child);
+                    let measured_cross = layout::split(child.measured_size, direction).1;
+
+                    let cross_length = match child.layout.get_sizes(direction).1 {
+                        Size::Fit if layout.align == Align::Stretch => inner_cross,
+                        cross_size => cross_size.resolve(inner_cross, measured_cross)
+                    };
+
+                    let cross_offset = match layout.align {
+                        Align::Start | Align::Stretch => 0.0,
+                        Align::Center => (inner_cross - cross_length) * 0.5,
+                        Align::End => inner_cross - cross_length
+                    };
+
+                    let position = layout::join(inner_main_start + pen, inner_cross_start + cross_offset, direction);
+                    let size = layout::join(main_length, cross_length, direction);
+                    pen += main_length + gap;
+
+                    Rect::new(position.x, position.y, size.x, size.y)
+                },
+                Position::Anchored { anchor, offset } => {
+                    let width = child.layout.width.resolve(inner.width, child.measured_size.x);
+                    let height = child.layout.height.resolve(inner.height, child.measured_size.y);
+                    let fractions = anchor.get_fractions();
+
+                    Rect::new(
+                        inner.x + (inner.width - width) * fractions.x + offset.x,
+                        inner.y + (inner.height - height) * fractions.y + offset.y,
+                        width,
+                        height)
+                }
+            };
+
+            self.arrange(child_id, child_rect);
+        }
+    }
+
+    // ----- Input -----
+
+    // The topmost visible node at "point" that isn't Empty.
+    fn hit_test(&self, point: Vector2) -> Option<UiNodeId> {
+        let mut hit = None;
+        self.hit_test_node(self.root, point, None, &mut hit);
+        hit
+    }
+
+    fn hit_test_node(&self, id: UiNodeId, point: Vector2, clip_rect: Option<Rect>, hit: &mut Option<UiNodeId>) {
+        let node = self.get_node_or_panic(id);
+
+        if !node.visible || clip_rect.is_some_and(|clip_rect| !clip_rect.contains(point)) {
+            return;
+        }
+
+        if !matches!(node.widget, Widget::Empty) && node.rect.contains(point) {
+            *hit = Some(id);
+        }
+
+        let child_clip_rect = self.get_child_clip_rect(node, clip_rect);
+
+        for &child in &node.children {
+            self.hit_test_node(child, point, child_clip_rect, hit);
+        }
+    }
+
+    // Scroll views cut their children off at their padding.
+    fn get_child_clip_rect(&self, node: &UiNode, clip_rect: Option<Rect>) -> Option<Rect> {
+        match node.widget {
+            Widget::ScrollView(_) => {
+                let inner = node.get_inner_rect();
+                Some(clip_rect.map_or(inner, |clip_rect| clip_rect.intersect(&inner)))
+            },
+            _ => clip_rect
+        }
+    }
+
+    // The enabled focusable widget at or above the node, which is what a click on the node goes to.
+    fn find_interactive(&self, id: UiNodeId) -> Option<UiNodeId> {
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            if self.get_widget(id).is_focusable() {
+                return Some(id).filter(|&id| self.is_reachable(id));
+            }
+
+            current = self.get_node_or_panic(id).parent;
+        }
+
+        None
+    }
+
+    fn press_mouse(&mut self) {
+        let target = self.hovered.and_then(|hovered| self.find_interactive(hovered));
+
+        self.pressed = target;
+        self.set_focus(target);
+
+        let target = match target {
+            Some(target) => target,
+            None => return
+        };
+
+        let mouse_position = self.mouse_position;
+        let inner = self.get_node_or_panic(target).get_inner_rect();
+        let item_height = self.get_item_height();
+        let text_scale = self.theme.text_scale;
+        let text_padding = self.theme.widget_padding;
+
+        let mut event = None;
+
+        // NOTE: The node is borrowed straight from the slots, so the font can be borrowed next to it
+        let font = &self.font;
+        let node = self.slots[target.index as usize].node.as_mut().expect("The UI node was removed");
+
+        match &mut node.widget {
+            Widget::List(list) => {
+                let index = ((mouse_position.y - inner.y) / item_height).floor();
+
+                if index >= 0.0 && (index as usize) < list.items.len() {
+                    let index = index as usize;
+
+                    if list.selected == Some(index) {
+                        event = Some(UiEvent::ItemActivated { list: target, index });
+                    } else {
+                        list.selected = Some(index);
+                        event = Some(UiEvent::SelectionChanged { list: target, index });
+                    }
+                }
+            },
+            Widget::TextField(text_field) => {
+                let scroll = UiTree::get_text_field_scroll(font, text_field.cursor, &text_field.text, text_scale, inner.width - text_padding * 2.0);
+                text_field.cursor = font.get_index_at(&text_field.text, text_scale, mouse_position.x - inner.x - text_padding + scroll);
+            },
+            _ => {}
+        }
+
+        self.events.extend(event);
+    }
+
+    fn release_mouse(&mut self) {
+        let pressed = match self.pressed.take() {
+            Some(pressed) => pressed,
+            None => return
+        };
+
+        let released_on = self.hovered.and_then(|hovered| self.find_interactive(hovered));
+
+        if released_on == Some(pressed) && matches!(self.get_widget(pressed), Widget::Button(_)) {
+            self.events.push(UiEvent::Clicked(pressed));
+        }
+    }
+
+    // Scrolls the innermost scroll view under the mouse.
+    fn scroll_at_mouse(&mut self, delta: f32) {
+        let scroll_speed = self.theme.scroll_speed;
+        let mut current = self.hovered;
+
+        while let Some(id) = current {
+            let node = self.get_node_mut_or_panic(id);
+
+            let max_offset = node.get_max_scroll_offset();
+
+            if let Widget::ScrollView(offset) = &mut node.widget {
+                *offset = (*offset - delta * scroll_speed).clamp(0.0, max_offset);
+                return;
+            }
+
+            current = node.parent;
+        }
+    }
+
+    fn press_key(&mut self, key: Key) {
+        if let Some(focused) = self.focused {
+            if self.edit_text_field(focused, key) {
+                return;
+            }
+        }
+
+        let navigation = match key {
+            Key::Up => Navigation::Up,
+            Key::Down => Navigation::Down,
+            Key::Left => Navigation::Left,
+            Key::Right => Navigation::Right,
+            Key::Tab if self.shift_down => Navigation::Previous,
+            Key::Tab => Navigation::Next,
+            Key::Enter | Key::Space => Navigation::Activate,
+            Key::Escape => Navigation::Cancel,
+            _ => return
+        };
+
+        self.navigate(navigation);
+    }
+
+    // Moves the cursor or deletes text if "id" is a text field. Returns true if it used the key.
+    // Space is used too, since it is typed as a character.
+    fn edit_text_field(&mut self, id: UiNodeId, key: Key) -> bool {
+        let text_field = match &mut self.get_node_mut_or_panic(id).widget {
+            Widget::TextField(text_field) => text_field,
+            _ => return false
+        };
+
+        let cursor = text_field.cursor.min(text_field.text.len());
+        let mut event = None;
+
+        match key {
+            Key::Backspace => {
+                if cursor > 0 {
+                    text_field.text.remove(cursor - 1);
+                    text_field.cursor = cursor - 1;
+                    event = Some(UiEvent::TextChanged(id));
+                }
+            },
+            Key::Delete => {
+                if cursor < text_field.text.len() {
+                    text_field.text.remove(cursor);
+                    event = Some(UiEvent::TextChanged(id));
+                }
+            },
+            Key::Left => text_field.cursor = cursor.saturating_sub(1),
+            Key::Right => text_field.cursor = (cursor + 1).min(text_field.text.len()),
+            Key::Home => text_field.cursor = 0,
+            Key::End => text_field.cursor = text_field.text.len(),
+            Key::Enter => event = Some(UiEvent::TextSubmitted(id)),
+            Key::Space => {},
+            _ => return false
+        }
+
+        self.events.extend(event);
+        true
+    }
+
+    fn type_character(&mut self, character: char) {
+        let focused = match self.focused {
+            Some(focused) => focused,
+            None => return
+        };
+
+        // Only what the font can draw
+        if !character.is_ascii() || character.is_ascii_control() || self.font.get_glyph(character as u8).is_none() {
+            return;
+        }
+
+        if let Widget::TextField(text_field) = &mut self.get_node_mut_or_panic(focused).widget {
+            if text_field.max_length.is_some_and(|max_length| text_field.text.len() >= max_length) {
+                return;
+            }
+
+            let cursor = text_field.cursor.min(text_field.text.len());
+            text_field.text.insert(cursor, character);
+            text_field.cursor = cursor + 1;
+
+            self.events.push(UiEvent::TextChanged(focused));
+        }
+    }
+
+    fn activate(&mut self, id: UiNodeId) {
+        match self.get_widget(id) {
+            Widget::Button(_) => self.events.push(UiEvent::Clicked(id)),
+            Widget::List(list) => {
+                if let Some(index) = list.selected {
+                    self.events.push(UiEvent::ItemActivated { list: id, index });
+                }
+            },
+            _ => {}
+        }
+    }
+
+    // ----- Focus navigation -----
+
+    // Every reachable focusable widget, in tree order.
+    fn get_focusable_nodes(&self) -> Vec<UiNodeId> {
+        let mut focusable = Vec::new();
+        let mut stack = vec![self.root];
+
+        while let Some(id) = stack.pop() {
+            let node = self.get_node_or_panic(id);
+
+            if !node.visible || !node.enabled {
+                continue;
+            }
+
+            if node.widget.is_focusable() {
+                focusable.push(id);
+            }
+
+            stack.extend(node.children.iter().rev());
+        }
+
+        focusable
+    }
+
+    fn focus_and_show(&mut self, id: UiNodeId) {
+        self.set_focus(Some(id));
+
+        let rect = self.get_rect(id);
+        self.scroll_into_view(id, rect);
+    }
+
+    // Up and down move the selection of a list. Returns false when the list isn't focused or the selection is at the end.
+    fn move_list_selection(&mut self, id: UiNodeId, navigation: Navigation) -> bool {
+        let list = match &mut self.get_node_mut_or_panic(id).widget {
+            Widget::List(list) => list,
+            _ => return false
+        };
+
+        let index = match (list.selected, navigation) {
+            (None, Navigation::Down) | (None, Navigation::Up) if !list.items.is_empty() => 0,
+            (Some(selected), Navigation::Down) if selected + 1 < list.items.len() => selected + 1,
+            (Some(selected), Navigation::Up) if selected > 0 => selected - 1,
+            _ => return false
+        };
+
+        list.selected = Some(index);
+        self.events.push(UiEvent::SelectionChanged { list: id, index });
+
+        let inner = self.get_node_or_panic(id).get_inner_rect();
+        let item_height = self.get_item_height();
+        self.scroll_into_view(id, Rect::new(inner.x, inner.y + index as f32 * item_height, inner.width, item_height));
+
+        true
+    }
+
+    // The focusable widget nearest to "from" in the direction, favouring ones lined up with it.
+    fn find_in_direction(&self, from: UiNodeId, navigation: Navigation) -> Option<UiNodeId> {
+        let from_rect = self.get_rect(from);
+        let from_center = from_rect.get_center();
+
+        let mut best = None;
+        let mut best_score = f32::MAX;
+
+        for id in self.get_focusable_nodes() {
+            if id == from {
+                continue;
+            }
+
+            let rect = self.get_rect(id);
+            let center = rect.get_center();
+
+            // Distance in the direction, and the gap between the two across it (0 when they overlap)
+            let (distance, gap, center_offset) = match navigation {
+                Navigation::Up => (from_center.y - center.y, UiTree::get_gap(from_rect.x, from_rect.get_right(), rect.x, rect.get_right()), center.x - from_center.x),
+                Navigation::Down => (center.y - from_center.y, UiTree::get_gap(from_rect.x, from_rect.get_right(), rect.x, rect.get_right()), center.x - from_center.x),
+                Navigation::Left => (from_center.x - center.x, UiTree::get_gap(from_rect.y, from_rect.get_bottom(), rect.y, rect.get_bottom()), center.y - from_center.y),
+                Navigation::Right => (center.x - from_center.x, UiTree::get_gap(from_rect.y, from_rect.get_bottom(), rect.y, rect.get_bottom()), center.y - from_center.y),
+                _ => return None
+            };
+
+            if distance <= 0.0 {
+                continue;
+            }
+
+            let score = distance + gap * 2.0 + center_offset.abs() * 0.1;
+
+            if score < best_score {
+                best_score = score;
+                best = Some(id);
+            }
+        }
+
+        best
+    }
+
+    fn get_gap(start: f32, end: f32, other_start: f32, other_end: f32) -> f32 {
+        (other_start - end).max(start - other_end).max(0.0)
+    }
+
+    // Scrolls the scroll views above the node so "rect" is inside them.
+    fn scroll_into_view(&mut self, id: UiNodeId, mut rect: Rect) {
+        let mut current = self.get_node_or_panic(id).parent;
+
+        while let Some(id) = current {
+            let node = self.get_node_mut_or_panic(id);
+            let inner = node.get_inner_rect();
+            let direction = node.layout.direction;
+            let max_offset = node.get_max_scroll_offset();
+
+            if let Widget::ScrollView(offset) = &mut node.widget {
+                let (start, _) = layout::split(Vector2::new(rect.x, rect.y), direction);
+                let (length, _) = layout::split(Vector2::new(rect.width, rect.height), direction);
+                let (view_start, _) = layout::split(Vector2::new(inner.x, inner.y), direction);
+                let (view_length, _) = layout::split(Vector2::new(inner.width, inner.height), direction);
+
+                let change = if start < view_start {
+                    start - view_start
+                } else if start + length > view_start + view_length {
+                    (start + length - (view_start + view_length)).min(start - view_start)
+                } else {
+                    0.0
+                };
+
+                let new_offset = (*offset + change).clamp(0.0, max_offset);
+                let moved = layout::join(new_offset - *offset, 0.0, direction);
+                *offset = new_offset;
+
+                // The rectangle moves with the scrolled content, for the scroll views further up
+                rect.x -= moved.x;
+                rect.y -= moved.y;
+            }
+
+            current = node.parent;
+        }
+    }
+
+    // ----- Rendering -----
+
+    fn render_node(&mut self, renderer: &mut Renderer2d, id: UiNodeId, clip_rect: Option<Rect>) {
+        let node = self.get_node_or_panic(id);
+
+        if !node.visible {
+            return;
+        }
+
+        let rect = node.rect;
+        let visible_on_screen = clip_rect.is_none_or(|clip_rect| clip_rect.overlaps(&rect));
+
+        if visible_on_screen {
+            self.render_widget(renderer, id, clip_rect);
+
+            if self.focused == Some(id) {
+                renderer.draw_rect(rect.x, rect.y, rect.width, rect.height, self.theme.focus_thickness, self.theme.focus_color);
+            }
+        }
+
+        let node = self.get_node_or_panic(id);
+        let child_clip_rect = self.get_child_clip_rect(node, clip_rect);
+        renderer.set_clip_rect(child_clip_rect);
+
+        for index in 0..node.children.len() {
+            let child = self.get_node_or_panic(id).children[index];
+            self.render_node(renderer, child, child_clip_rect);
+        }
+
+        renderer.set_clip_rect(clip_rect);
+
+        // The scroll bar goes on top of the children
+        if visible_on_screen {
+            self.render_scroll_bar(renderer, id);
+        }
+    }
+
+    fn render_widget(&mut self, renderer: &mut Renderer2d, id: UiNodeId, clip_rect: Option<Rect>) {
+        let theme = self.theme;
+        let item_height = self.get_item_height();
+        let text_height = self.get_text_height();
+        let is_hovered = self.hovered.and_then(|hovered| self.find_interactive(hovered)) == Some(id);
+        let is_pressed = self.pressed == Some(id);
+        let is_reachable = self.is_reachable(id);
+        let is_focused = self.focused == Some(id);
+        let mouse_position = self.mouse_position;
+
+        let font = &self.font;
+        let node = self.slots[id.index as usize].node.as_mut().expect("The UI node was removed");
+        let rect = node.rect;
+        let inner = node.get_inner_rect();
+
+        match &mut node.widget {
+            Widget::Empty | Widget::ScrollView(_) => {},
+            Widget::Panel => UiTree::draw_box(renderer, rect, &theme.panel, theme.border_thickness),
+            Widget::Label(text) => renderer.draw_text(text, Vector2::new(inner.x, inner.y), theme.text_scale, theme.label_color),
+            Widget::Button(text) => {
+                let style = if !is_reachable {
+                    theme.button_disabled
+                } else if is_pressed && is_hovered {
+                    theme.button_pressed
+                } else if is_hovered {
+                    theme.button_hovered
+                } else {
+                    theme.button
+                };
+
+                UiTree::draw_box(renderer, rect, &style, theme.border_thickness);
+
+                let text_size = font.measure_text(text, theme.text_scale);
+                let center = inner.get_center();
+                renderer.draw_text(text, Vector2::new(center.x - text_size.x * 0.5, center.y - text_size.y * 0.5), theme.text_scale, style.text);
+            },
+            Widget::Image(sprite) => UiTree::draw_image(renderer, sprite, inner),
+            Widget::NineSlice(nine_slice) => UiTree::draw_nine_slice(renderer, nine_slice, inner),
+            Widget::ProgressBar(value) => {
+                renderer.fill_rect(inner.x, inner.y, inner.width, inner.height, theme.progress_track_color);
+                renderer.fill_rect(inner.x, inner.y, inner.width * value.clamp(0.0, 1.0), inner.height, theme.progress_fill_color);
+            },
+            Widget::List(list) => {
+                UiTree::draw_box(renderer, rect, &theme.list, theme.border_thickness);
+
+                // Items past the end of a list with a fixed size are cut off
+                let list_clip_rect = clip_rect.map_or(inner, |clip_rect| clip_rect.intersect(&inner));
+                renderer.set_clip_rect(Some(list_clip_rect));
+
+                for (index, item) in list.items.iter().enumerate() {
+                    let item_rect = Rect::new(inner.x, inner.y + index as f32 * item_height, inner.width, item_height);
+
+                    if !list_clip_rect.overlaps(&item_rect) {
+                        continue;
+                    }
+
+                    let style = if list.selected == Some(index) {
+                        theme.list_item_selected
+                    } else if is_hovered && is_reachable && item_rect.contains(mouse_position) {
+                        theme.list_item_hovered
+                    } else {
+                        theme.list
+                    };
+
+                    if style.background != theme.list.background {
+                        renderer.fill_rect(item_rect.x, item_rect.y, item_rect.width, item_rect.height, style.background);
+                    }
+
+                    renderer.draw_text(item, Vector2::new(item_rect.x + theme.widget_padding, item_rect.y + theme.widget_padding), theme.text_scale, style.text);
+                }
+
+                renderer.set_clip_rect(clip_rect);
+            },
+            Widget::TextField(text_field) => {
+                UiTree::draw_box(renderer, rect, &theme.text_field, theme.border_thickness);
+
+                let text_rect = Rect::new(inner.x + theme.widget_padding, inner.y, (inner.width - theme.widget_padding * 2.0).max(0.0), inner.height);
+                let text_y = inner.get_center().y - text_height * 0.5;
+                let scroll = UiTree::get_text_field_scroll(font, text_field.cursor, &text_field.text, theme.text_scale, text_rect.width);
+
+                renderer.set_clip_rect(Some(clip_rect.map_or(text_rect, |clip_rect| clip_rect.intersect(&text_rect))));
+
+                if text_field.text.is_empty() && !is_focused {
+                    renderer.draw_text(&text_field.placeholder, Vector2::new(text_rect.x, text_y), theme.text_scale, theme.placeholder_color);
+                } else {
+                    renderer.draw_text(&text_field.text, Vector2::new(text_rect.x - scroll, text_y), theme.text_scale, theme.text_field.text);
+                }
+
+                renderer.set_clip_rect(clip_rect);
+
+                if is_focused {
+                    let cursor = text_field.cursor.min(text_field.text.len());
+                    let x = text_rect.x - scroll + font.measure_text(&text_field.text[..cursor], theme.text_scale).x;
+                    renderer.draw_line(Vector2::new(x, text_y), Vector2::new(x, text_y + text_height), 1.0, theme.cursor_color);
+                }
+            }
+        }
+    }
+
+    fn render_scroll_bar(&self, renderer: &mut Renderer2d, id: UiNodeId) {
+        let node = self.get_node_or_panic(id);
+
+        let offset = match node.widget {
+            Widget::ScrollView(offset) => offset,
+            _ => return
+        };
+
+        let inner = node.get_inner_rect();
+        let direction = node.layout.direction;
+        let (view_length, _) = layout::split(Vector2::new(inner.width, inner.height), direction);
+
+        if node.content_length <= view_length || node.content_length <= 0.0 {
+            return;
+        }
+
+        // Along the far edge of the view
+        let bar_width = self.theme.scroll_bar_width;
+        let grab_start = offset / node.content_length * view_length;
+        let grab_length = view_length / node.content_length * view_length;
+
+        let (track, grab) = match direction {
+            Direction::Column => (
+                Rect::new(node.rect.get_right() - bar_width, inner.y, bar_width, inner.height),
+                Rect::new(node.rect.get_right() - bar_width, inner.y + grab_start, bar_width, grab_length)),
+            Direction::Row => (
+                Rect::new(inner.x, node.rect.get_bottom() - bar_width, inner.width, bar_width),
+                Rect::new(inner.x + grab_start, node.rect.get_bottom() - bar_width, grab_length, bar_width))
+        };
+
+        renderer.fill_rect(track.x, track.y, track.width, track.height, self.theme.scroll_bar_color);
+        renderer.fill_rect(grab.x, grab.y, grab.width, grab.height, self.theme.scroll_grab_color);
+    }
+
+    fn draw_box(renderer: &mut Renderer2d, rect: Rect, style: &BoxStyle, border_thickness: f32) {
+        if style.background.a > 0.0 {
+            renderer.fill_rect(rect.x, rect.y, rect.width, rect.height, style.background);
+        }
+
+        if style.border.a > 0.0 && border_thickness > 0.0 {
+            renderer.draw_rect(rect.x, rect.y, rect.width, rect.height, border_thickness, style.border);
+        }
+    }
+
+    fn draw_image(renderer: &mut Renderer2d, sprite: &mut Sprite, rect: Rect) {
+        let (source_width, source_height) = sprite.get_source_size();
+
+        if source_width <= 0.0 || source_height <= 0.0 {
+            return;
+        }
+
+        sprite.position_x = rect.x;
+        sprite.position_y = rect.y;
+        sprite.angle = 0.0;
+        sprite.origin = Origin::Normalized(0.0, 0.0);
+        sprite.set_scale(rect.width / source_width, rect.height / source_height);
+        sprite.layer = renderer.get_layer();
+
+        renderer.draw_sprite(sprite);
+    }
+
+    fn draw_nine_slice(renderer: &mut Renderer2d, nine_slice: &mut NineSlice, rect: Rect) {
+        nine_slice.set_position(rect.x, rect.y);
+        nine_slice.set_size(rect.width, rect.height);
+        nine_slice.angle = 0.0;
+        nine_slice.origin = Origin::Normalized(0.0, 0.0);
+        nine_slice.layer = renderer.get_layer();
+
+        renderer.draw_nine_slice(nine_slice);
+    }
+
+    // How far the text of a text field is moved left, so the cursor stays in view.
+    fn get_text_field_scroll(font: &FontMetrics, cursor: usize, text: &str, scale: f32, width: f32) -> f32 {
+        let cursor_x = font.measure_text(&text[..cursor.min(text.len())], scale).x;
+        (cursor_x - width).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ui::layout::{Anchor, Edges};
+
+    // Every character 10 pixels wide and 16 tall, so text sizes are easy to work out
+    fn make_font() -> FontMetrics {
+        let mut text = String::from("info face=\"Test\" padding=0,0,0,0\ncommon lineHeight=16 base=12\n");
+
+        for character in 32..127 {
+            text.push_str(&format!("char id={} x={} y=0 width=8 height=12 xoffset=0 yoffset=2 xadvance=10 page=0 chnl=0\n", character, character));
+        }
+
+        FontMetrics::parse(&text).unwrap()
+    }
+
+    // Buttons are their text with 5 pixels of padding, so 26 pixels tall
+    fn make_tree() -> UiTree {
+        let mut theme = Theme::new();
+        theme.widget_padding = 5.0;
+        theme.item_width = 100.0;
+
+        UiTree::new(make_font(), theme)
+    }
+
+    fn screen() -> Rect {
+        Rect::new(0.0, 0.0, 1024.0, 768.0)
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect::new(x, y, width, height)
+    }
+
+    fn press(ui: &mut UiTree, key: Key) {
+        ui.handle_event(&WindowEvent::KeyPressed { key, repeat: false });
+    }
+
+    fn click(ui: &mut UiTree, x: f32, y: f32) {
+        ui.handle_event(&WindowEvent::MouseMoved { position: Vector2::new(x, y) });
+        ui.handle_event(&WindowEvent::MouseButtonPressed { button: MouseButton::Left });
+        ui.handle_event(&WindowEvent::MouseButtonReleased { button: MouseButton::Left });
+    }
+
+    fn take_events(ui: &mut UiTree) -> Vec<UiEvent> {
+        let mut events = Vec::new();
+        ui.take_events(&mut events);
+        events
+    }
+
+    #[test]
+    fn columns_and_rows() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let panel = ui.add_panel(root, LayoutStyle::column().with_padding(Edges::all(10.0)).with_spacing(4.0));
+        let label = ui.add_label(panel, "Hello");
+        let button = ui.add_button(panel, "Play");
+        let row = ui.add(panel, Widget::Empty, LayoutStyle::row().with_spacing(2.0).with_width(Size::Pixels(200.0)));
+        let first = ui.add(row, Widget::button("A"), LayoutStyle::new().with_width(Size::Grow(1.0)));
+        let second = ui.add(row, Widget::button("B"), LayoutStyle::new().with_width(Size::Grow(3.0)));
+        let third = ui.add(row, Widget::button("CCC"), LayoutStyle::new());
+
+        ui.update_layout(screen());
+
+        // The panel fits its widest child and all of the children's heights, with padding and spacing
+        assert_eq!(ui.get_rect(panel), rect(0.0, 0.0, 220.0, 10.0 + 16.0 + 4.0 + 26.0 + 4.0 + 26.0 + 10.0));
+        assert_eq!(ui.get_rect(label), rect(10.0, 10.0, 50.0, 16.0));
+        assert_eq!(ui.get_rect(button), rect(10.0, 30.0, 50.0, 26.0));
+        assert_eq!(ui.get_rect(row), rect(10.0, 60.0, 200.0, 26.0));
+
+        // 200 - 40 for CCC - 4 of spacing leaves 156, shared 1 to 3
+        assert_eq!(ui.get_rect(first), rect(10.0, 60.0, 39.0, 26.0));
+        assert_eq!(ui.get_rect(second), rect(51.0, 60.0, 117.0, 26.0));
+        assert_eq!(ui.get_rect(third), rect(170.0, 60.0, 40.0, 26.0));
+    }
+
+    #[test]
+    fn grow_is_never_smaller_than_fit() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let row = ui.add(root, Widget::Empty, LayoutStyle::row().with_width(Size::Pixels(100.0)));
+        let fixed = ui.add(row, Widget::Empty, LayoutStyle::new().with_width(Size::Pixels(90.0)));
+        let growing = ui.add(row, Widget::button("Long text"), LayoutStyle::new().with_width(Size::Grow(1.0)));
+
+        ui.update_layout(screen());
+
+        assert_eq!(ui.get_rect(fixed).width, 90.0);
+        assert_eq!(ui.get_rect(growing), rect(90.0, 0.0, 100.0, 26.0));
+    }
+
+    #[test]
+    fn percent_sizes_are_of_the_inside_of_the_parent() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let panel = ui.add_panel(root, LayoutStyle::column().with_size(Size::Pixels(220.0), Size::Pixels(120.0)).with_padding(Edges::all(10.0)));
+        let half = ui.add(panel, Widget::Empty, LayoutStyle::new().with_size(Size::Percent(50.0), Size::Percent(25.0)));
+
+        ui.update_layout(screen());
+        assert_eq!(ui.get_rect(half), rect(10.0, 10.0, 100.0, 25.0));
+    }
+
+    #[test]
+    fn justify_align_and_anchors() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        *ui.get_layout_mut(root) = LayoutStyle::row().with_justify(Justify::Center).with_align(Align::Center);
+        let button = ui.add_button(root, "Go");
+        let hud = ui.add(root, Widget::progress_bar(0.5), LayoutStyle::new()
+            .with_size(Size::Percent(25.0), Size::Pixels(10.0))
+            .anchored(Anchor::TopRight, Vector2::new(-8.0, 8.0)));
+        let bottom = ui.add(root, Widget::label("x"), LayoutStyle::new()
+            .with_width(Size::Grow(1.0))
+            .anchored(Anchor::Bottom, Vector2::new(0.0, 0.0)));
+
+        ui.update_layout(screen());
+
+        // Anchored nodes don't take space from the row
+        assert_eq!(ui.get_rect(button), rect(497.0, 371.0, 30.0, 26.0));
+        assert_eq!(ui.get_rect(hud), rect(1024.0 - 256.0 - 8.0, 8.0, 256.0, 10.0));
+        assert_eq!(ui.get_rect(bottom), rect(0.0, 768.0 - 16.0, 1024.0, 16.0));
+
+        *ui.get_layout_mut(root) = LayoutStyle::column()
+            .with_justify(Justify::SpaceBetween)
+            .with_align(Align::Stretch)
+            .with_padding(Edges::symmetric(10.0, 20.0));
+        let second = ui.add_button(root, "Two");
+
+        ui.update_layout(screen());
+        assert_eq!(ui.get_rect(button), rect(10.0, 20.0, 1004.0, 26.0));
+        assert_eq!(ui.get_rect(second), rect(10.0, 768.0 - 20.0 - 26.0, 1004.0, 26.0));
+
+        // Hidden nodes take no space
+        ui.set_visible(second, false);
+        ui.update_layout(screen());
+        assert_eq!(ui.get_rect(button), rect(10.0, 20.0, 1004.0, 26.0));
+    }
+
+    #[test]
+    fn justify_end_and_align_end() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let row = ui.add(root, Widget::Empty, LayoutStyle::row()
+            .with_size(Size::Pixels(200.0), Size::Pixels(50.0))
+            .with_spacing(10.0)
+            .with_justify(Justify::End)
+            .with_align(Align::End));
+        let first = ui.add_button(row, "A");
+        let second = ui.add_button(row, "B");
+
+        ui.update_layout(screen());
+        assert_eq!(ui.get_rect(first), rect(200.0 - 20.0 - 10.0 - 20.0, 24.0, 20.0, 26.0));
+        assert_eq!(ui.get_rect(second), rect(180.0, 24.0, 20.0, 26.0));
+    }
+
+    #[test]
+    fn focus_navigation_order() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        *ui.get_layout_mut(root) = LayoutStyle::column().with_spacing(10.0);
+        let play = ui.add_button(root, "Play");
+        let row = ui.add(root, Widget::Empty, LayoutStyle::row().with_spacing(10.0));
+        let left = ui.add_button(row, "Left");
+        let right = ui.add_button(row, "Right");
+        let quit = ui.add_button(root, "Quit");
+        ui.update_layout(screen());
+
+        // The first press focuses the first widget
+        press(&mut ui, Key::Down);
+        assert_eq!(ui.get_focused(), Some(play));
+        press(&mut ui, Key::Down);
+        assert_eq!(ui.get_focused(), Some(left));
+        press(&mut ui, Key::Right);
+        assert_eq!(ui.get_focused(), Some(right));
+        // Nothing further right
+        press(&mut ui, Key::Right);
+        assert_eq!(ui.get_focused(), Some(right));
+        press(&mut ui, Key::Down);
+        assert_eq!(ui.get_focused(), Some(quit));
+        // Left is lined up with quit more than right is
+        press(&mut ui, Key::Up);
+        assert_eq!(ui.get_focused(), Some(left));
+
+        press(&mut ui, Key::Enter);
+        assert_eq!(take_events(&mut ui).last(), Some(&UiEvent::Clicked(left)));
+
+        // Tab goes in tree order and wraps around, shift tab goes back
+        press(&mut ui, Key::Tab);
+        assert_eq!(ui.get_focused(), Some(right));
+        press(&mut ui, Key::Tab);
+        assert_eq!(ui.get_focused(), Some(quit));
+        press(&mut ui, Key::Tab);
+        assert_eq!(ui.get_focused(), Some(play));
+
+        ui.handle_event(&WindowEvent::KeyPressed { key: Key::LeftShift, repeat: false });
+        press(&mut ui, Key::Tab);
+        assert_eq!(ui.get_focused(), Some(quit));
+        ui.handle_event(&WindowEvent::KeyReleased { key: Key::LeftShift });
+
+        // Disabled widgets are skipped
+        ui.set_enabled(row, false);
+        press(&mut ui, Key::Up);
+        assert_eq!(ui.get_focused(), Some(play));
+
+        ui.navigate(Navigation::Cancel);
+        assert_eq!(take_events(&mut ui).last(), Some(&UiEvent::Cancelled));
+    }
+
+    #[test]
+    fn focus_is_dropped_when_unreachable() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let panel = ui.add_panel(root, LayoutStyle::column());
+        let button = ui.add_button(panel, "Play");
+        ui.update_layout(screen());
+
+        ui.set_focus(Some(button));
+        ui.set_visible(panel, false);
+        assert_eq!(ui.get_focused(), None);
+
+        // Can't be focused while hidden either
+        ui.set_focus(Some(button));
+        assert_eq!(ui.get_focused(), None);
+
+        ui.set_visible(panel, true);
+        ui.set_focus(Some(button));
+        ui.remove(panel);
+        assert_eq!(ui.get_focused(), None);
+        assert!(!ui.contains(button));
+    }
+
+    #[test]
+    fn mouse_clicks() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let play = ui.add_button(root, "Play");
+        let quit = ui.add_button(root, "Quit");
+        ui.update_layout(screen());
+
+        let play_rect = ui.get_rect(play);
+        click(&mut ui, play_rect.x + 1.0, play_rect.y + 1.0);
+        assert!(take_events(&mut ui).contains(&UiEvent::Clicked(play)));
+        assert!(ui.wants_mouse());
+
+        // Letting go somewhere else isn't a click
+        ui.handle_event(&WindowEvent::MouseMoved { position: Vector2::new(play_rect.x + 1.0, play_rect.y + 1.0) });
+        ui.handle_event(&WindowEvent::MouseButtonPressed { button: MouseButton::Left });
+        ui.handle_event(&WindowEvent::MouseMoved { position: Vector2::new(900.0, 700.0) });
+        ui.handle_event(&WindowEvent::MouseButtonReleased { button: MouseButton::Left });
+        assert!(!take_events(&mut ui).iter().any(|event| matches!(event, UiEvent::Clicked(_))));
+        assert!(!ui.wants_mouse());
+
+        ui.set_enabled(quit, false);
+        let quit_rect = ui.get_rect(quit);
+        click(&mut ui, quit_rect.x + 1.0, quit_rect.y + 1.0);
+        assert!(!take_events(&mut ui).iter().any(|event| matches!(event, UiEvent::Clicked(_))));
+    }
+
+    #[test]
+    fn scroll_views_scroll_focus_into_view() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let scroll = ui.add(root, Widget::scroll_view(), LayoutStyle::column().with_size(Size::Pixels(200.0), Size::Pixels(100.0)).with_spacing(10.0));
+        // 26 tall, 36 apart, 206 in all
+        let buttons: Vec<_> = (0..6).map(|index| ui.add_button(scroll, &format!("B{}", index))).collect();
+        let list = ui.add(root, Widget::list(&["one", "two", "three"]), LayoutStyle::new());
+        let field = ui.add(root, Widget::text_field("Name"), LayoutStyle::new());
+        ui.update_layout(screen());
+
+        assert_eq!(ui.get_rect(list), rect(0.0, 100.0, 100.0, 78.0));
+        assert_eq!(ui.get_rect(field), rect(0.0, 178.0, 100.0, 26.0));
+
+        ui.handle_event(&WindowEvent::MouseMoved { position: Vector2::new(10.0, 50.0) });
+        ui.handle_event(&WindowEvent::MouseScrolled { delta: Vector2::new(0.0, -1.0) });
+        ui.update_layout(screen());
+        assert_eq!(ui.get_rect(buttons[0]).y, -40.0);
+
+        // Stops at the end
+        ui.handle_event(&WindowEvent::MouseScrolled { delta: Vector2::new(0.0, -10.0) });
+        ui.update_layout(screen());
+        assert_eq!(ui.get_rect(buttons[5]).get_bottom(), 100.0);
+
+        match ui.get_widget(scroll) {
+            Widget::ScrollView(offset) => assert_eq!(*offset, 106.0),
+            _ => panic!("Expected a scroll view")
+        }
+
+        ui.set_focus(Some(buttons[4]));
+
+        for _ in 0..3 {
+            press(&mut ui, Key::Up);
+            ui.update_layout(screen());
+        }
+
+        assert_eq!(ui.get_focused(), Some(buttons[1]));
+        assert_eq!(ui.get_rect(buttons[1]).y, 0.0);
+
+        // Out of the scroll view, into the list, through its items and on to the text field
+        for _ in 0..4 {
+            press(&mut ui, Key::Down);
+            ui.update_layout(screen());
+        }
+
+        assert_eq!(ui.get_focused(), Some(buttons[5]));
+        assert_eq!(ui.get_rect(buttons[5]).get_bottom(), 100.0);
+        press(&mut ui, Key::Down);
+        assert_eq!(ui.get_focused(), Some(list));
+        press(&mut ui, Key::Down);
+        assert_eq!(ui.get_selected(list), Some(0));
+
+        for _ in 0..3 {
+            press(&mut ui, Key::Down);
+        }
+
+        assert_eq!(ui.get_selected(list), Some(2));
+        assert_eq!(ui.get_focused(), Some(field));
+        assert!(take_events(&mut ui).contains(&UiEvent::SelectionChanged { list, index: 2 }));
+    }
+
+    #[test]
+    fn text_fields() {
+        let mut ui = make_tree();
+        let root = ui.get_root();
+
+        let field = ui.add(root, Widget::text_field("Name"), LayoutStyle::new());
+        ui.update_layout(screen());
+        ui.set_focus(Some(field));
+
+        for character in "Bob".chars() {
+            ui.handle_event(&WindowEvent::CharacterTyped(character));
+        }
+
+        press(&mut ui, Key::Left);
+        press(&mut ui, Key::Backspace);
+        // Space types through CharacterTyped, not the key
+        press(&mut ui, Key::Space);
+        ui.handle_event(&WindowEvent::CharacterTyped(' '));
+        assert_eq!(ui.get_text(field), "B b");
+        assert!(ui.wants_keyboard());
+
+        press(&mut ui, Key::Enter);
+        assert_eq!(take_events(&mut ui).last(), Some(&UiEvent::TextSubmitted(field)));
+
+        // Clicking puts the cursor where it was clicked, after the B
+        click(&mut ui, 5.0 + 10.0 + 1.0, 5.0);
+        ui.handle_event(&WindowEvent::CharacterTyped('x'));
+        assert_eq!(ui.get_text(field), "Bx b");
+    }
+}
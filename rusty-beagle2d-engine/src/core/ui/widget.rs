@@ -0,0 +1,96 @@
+use crate::core::sprite::Sprite;
+use crate::core::nine_slice::NineSlice;
+
+// A column of selectable lines of text.
+pub struct ListWidget {
+    pub items: Vec<String>,
+    pub selected: Option<usize>
+}
+
+// A line of editable text.
+pub struct TextFieldWidget {
+    pub text: String,
+    // Shown greyed out while the text is empty
+    pub placeholder: String,
+    // No more than this many characters can be typed
+    pub max_length: Option<usize>,
+    // Byte index in "text"
+    pub(crate) cursor: usize
+}
+
+// What a node of a UiTree is, and the state of it that the game reads and changes.
+pub enum Widget {
+    // Draws nothing, only lays out its children
+    Empty,
+    // The theme's panel box behind its children
+    Panel,
+    Label(String),
+    Button(String),
+    // Shows the sprite's render view stretched over the node
+    Image(Sprite),
+    // Stretched over the node, the smallest size is its borders
+    NineSlice(NineSlice),
+    // How full it is, from 0.0 to 1.0
+    ProgressBar(f32),
+    List(ListWidget),
+    // Scrolls its children along its direction when they don't fit, and cuts off what is outside.
+    // The value is how far it is scrolled, in pixels.
+    ScrollView(f32),
+    TextField(TextFieldWidget)
+}
+
+impl Widget {
+    pub fn label(text: &str) -> Widget {
+        Widget::Label(String::from(text))
+    }
+
+    pub fn button(text: &str) -> Widget {
+        Widget::Button(String::from(text))
+    }
+
+    pub fn image(sprite: Sprite) -> Widget {
+        Widget::Image(sprite)
+    }
+
+    pub fn nine_slice(nine_slice: NineSlice) -> Widget {
+        Widget::NineSlice(nine_slice)
+    }
+
+    pub fn progress_bar(value: f32) -> Widget {
+        Widget::ProgressBar(value)
+    }
+
+    pub fn list(items: &[&str]) -> Widget {
+        Widget::List(ListWidget {
+            items: items.iter().map(|item| String::from(*item)).collect(),
+            selected: None
+        })
+    }
+
+    pub fn scroll_view() -> Widget {
+        Widget::ScrollView(0.0)
+    }
+
+    pub fn text_field(placeholder: &str) -> Widget {
+        Widget::TextField(TextFieldWidget {
+            text: String::new(),
+            placeholder: String::from(placeholder),
+            max_length: None,
+            cursor: 0
+        })
+    }
+
+    // Widgets that can have keyboard and gamepad focus.
+    pub fn is_focusable(&self) -> bool {
+        matches!(self, Widget::Button(_) | Widget::List(_) | Widget::TextField(_))
+    }
+
+    // The text of labels, buttons and text fields.
+    pub fn get_text(&self) -> Option<&str> {
+        match self {
+            Widget::Label(text) | Widget::Button(text) => Some(text),
+            Widget::TextField(text_field) => Some(&text_field.text),
+            _ => None
+        }
+    }
+}
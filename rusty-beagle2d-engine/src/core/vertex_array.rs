@@ -0,0 +1,80 @@
+use rusty_beagle2d_glfw::ogl;
+use crate::core::index_buffer::IndexBuffer;
+use crate::core::vertex_buffer::VertexBuffer;
+use crate::core::vertex_layout::Vertex;
+
+// Wraps an OpenGL vertex array object, which remembers the attribute setup and the element buffer.
+// NOTE: The vertex array does not own the buffers attached to it. Whoever creates them
+// Must keep them alive for as long as the vertex array is drawn from.
+pub struct VertexArray {
+    opengl_object_id: ogl::VertexArrayId,
+    next_attribute_index: u32
+}
+
+impl VertexArray {
+    pub fn new() -> VertexArray {
+        VertexArray {
+            opengl_object_id: ogl::gen_vertex_array(),
+            next_attribute_index: 0
+        }
+    }
+
+    pub fn bind(&self) {
+        ogl::bind_vertex_array(self.opengl_object_id);
+    }
+
+    pub fn unbind(&self) {
+        ogl::bind_vertex_array(ogl::VertexArrayId::NONE);
+    }
+
+    // Configures one attribute pointer per entry of T's VertexLayout.
+    // Attribute locations continue where the previously added buffer stopped, so several buffers
+    // (for example positions and per-vertex colors) can feed a single vertex array.
+    pub fn add_vertex_buffer<T: Vertex>(&mut self, vertex_buffer: &VertexBuffer<T>) {
+        let layout = T::layout();
+
+        layout.check_size_of::<T>();
+
+        self.bind();
+        vertex_buffer.bind();
+
+        for attribute in layout.get_attributes() {
+            // glVertexAttribPointer would turn integers into floats, even without normalizing them
+            if attribute.attribute_type.is_integer() && !attribute.normalized {
+                ogl::vertex_attrib_i_pointer(
+                    self.next_attribute_index,
+                    attribute.components,
+                    attribute.attribute_type.to_ogl(),
+                    layout.get_stride() as i32,
+                    attribute.offset);
+            } else {
+                ogl::vertex_attrib_pointer(
+                    self.next_attribute_index,
+                    attribute.components,
+                    attribute.attribute_type.to_ogl(),
+                    attribute.normalized,
+                    layout.get_stride() as i32,
+                    attribute.offset);
+            }
+
+            ogl::enable_vertex_attrib_array(self.next_attribute_index);
+
+            self.next_attribute_index += 1;
+        }
+    }
+
+    pub fn set_index_buffer(&mut self, index_buffer: &IndexBuffer) {
+        self.bind();
+        index_buffer.bind();
+    }
+
+    pub fn get_opengl_object_id(&self) -> ogl::VertexArrayId {
+        self.opengl_object_id
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        ogl::delete_vertex_array(self.opengl_object_id);
+    }
+}
@@ -0,0 +1,113 @@
+use rusty_beagle2d_glfw::ogl;
+use crate::core::vertex_layout::Vertex;
+
+use std::marker::PhantomData;
+use std::mem;
+
+// A GPU buffer of vertices of one type.
+// The type parameter ties the buffer to its vertex struct, so a VertexArray can read the matching
+// VertexLayout when the buffer is attached, and nothing else can be uploaded into it.
+pub struct VertexBuffer<T: Vertex> {
+    opengl_object_id: ogl::BufferId,
+    usage: ogl::Usage,
+    capacity: usize,
+    len: usize,
+    // LEARN: PhantomData
+    // We never store a T, but the struct must still "use" the type parameter.
+    // PhantomData is a zero-sized marker that tells the compiler we logically own T's.
+    vertex_type: PhantomData<T>
+}
+
+impl<T: Vertex> VertexBuffer<T> {
+    // Allocates room for "capacity" vertices without uploading anything yet.
+    pub fn new(capacity: usize, usage: ogl::Usage) -> VertexBuffer<T> {
+        let buffer_object = ogl::gl_gen_buffer();
+        ogl::gl_bind_buffer(ogl::BufferTarget::ArrayBuffer, buffer_object);
+        ogl::buffer_data_uninitialized(ogl::BufferTarget::ArrayBuffer, VertexBuffer::<T>::byte_size(capacity), usage);
+
+        VertexBuffer {
+            opengl_object_id: buffer_object,
+            usage,
+            capacity,
+            len: 0,
+            vertex_type: PhantomData
+        }
+    }
+
+    pub fn from_vertices(vertices: &[T], usage: ogl::Usage) -> VertexBuffer<T> {
+        let buffer_object = ogl::gl_gen_buffer();
+        ogl::gl_bind_buffer(ogl::BufferTarget::ArrayBuffer, buffer_object);
+        ogl::buffer_data(ogl::BufferTarget::ArrayBuffer, vertices, usage);
+
+        VertexBuffer {
+            opengl_object_id: buffer_object,
+            usage,
+            capacity: vertices.len(),
+            len: vertices.len(),
+            vertex_type: PhantomData
+        }
+    }
+
+    pub fn bind(&self) {
+        ogl::gl_bind_buffer(ogl::BufferTarget::ArrayBuffer, self.opengl_object_id);
+    }
+
+    // Replaces the whole content of the buffer.
+    // The storage is only reallocated when the new data does not fit in the current capacity.
+    pub fn set_data(&mut self, vertices: &[T]) {
+        self.bind();
+
+        if vertices.len() > self.capacity {
+            ogl::buffer_data(ogl::BufferTarget::ArrayBuffer, vertices, self.usage);
+            self.capacity = vertices.len();
+        } else {
+            ogl::buffer_sub_data(ogl::BufferTarget::ArrayBuffer, 0, vertices);
+        }
+
+        self.len = vertices.len();
+    }
+
+    // Overwrites vertices in place, starting at vertex index "first_vertex".
+    pub fn update(&mut self, first_vertex: usize, vertices: &[T]) {
+        if first_vertex + vertices.len() > self.capacity {
+            panic!("Vertex buffer update out of range: {} vertices at {} exceeds capacity {}",
+                vertices.len(), first_vertex, self.capacity);
+        }
+
+        self.bind();
+        ogl::buffer_sub_data(ogl::BufferTarget::ArrayBuffer, VertexBuffer::<T>::byte_size(first_vertex), vertices);
+
+        self.len = self.len.max(first_vertex + vertices.len());
+    }
+
+    // Throws away the current storage and gets a fresh block of the same capacity.
+    // Do this before rewriting a buffer that was drawn from this frame, so we don't stall
+    // Waiting for the GPU to finish reading the old content.
+    pub fn orphan(&mut self) {
+        self.bind();
+        ogl::buffer_data_uninitialized(ogl::BufferTarget::ArrayBuffer, VertexBuffer::<T>::byte_size(self.capacity), self.usage);
+        self.len = 0;
+    }
+
+    pub fn get_len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get_opengl_object_id(&self) -> ogl::BufferId {
+        self.opengl_object_id
+    }
+
+    fn byte_size(vertex_count: usize) -> isize {
+        (vertex_count * mem::size_of::<T>()) as isize
+    }
+}
+
+impl<T: Vertex> Drop for VertexBuffer<T> {
+    fn drop(&mut self) {
+        ogl::delete_buffer(self.opengl_object_id);
+    }
+}
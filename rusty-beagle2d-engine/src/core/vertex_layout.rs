@@ -0,0 +1,165 @@
+use rusty_beagle2d_glfw::ogl;
+
+use std::mem;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AttributeType {
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Float
+}
+
+impl AttributeType {
+    pub fn size_in_bytes(&self) -> u32 {
+        (match self {
+            AttributeType::Byte => mem::size_of::<i8>(),
+            AttributeType::UnsignedByte => mem::size_of::<u8>(),
+            AttributeType::Short => mem::size_of::<i16>(),
+            AttributeType::UnsignedShort => mem::size_of::<u16>(),
+            AttributeType::Int => mem::size_of::<i32>(),
+            AttributeType::UnsignedInt => mem::size_of::<u32>(),
+            AttributeType::Float => mem::size_of::<f32>()
+        }) as u32
+    }
+
+    pub fn is_integer(&self) -> bool {
+        *self != AttributeType::Float
+    }
+
+    pub fn to_ogl(&self) -> ogl::DataType {
+        match self {
+            AttributeType::Byte => ogl::DataType::Byte,
+            AttributeType::UnsignedByte => ogl::DataType::UnsignedByte,
+            AttributeType::Short => ogl::DataType::Short,
+            AttributeType::UnsignedShort => ogl::DataType::UnsignedShort,
+            AttributeType::Int => ogl::DataType::Int,
+            AttributeType::UnsignedInt => ogl::DataType::UnsignedInt,
+            AttributeType::Float => ogl::DataType::Float
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct VertexAttribute {
+    pub components: i32,
+    pub attribute_type: AttributeType,
+    // Integers are turned into floats from 0 to 1 (-1 to 1 when signed), for example for colors stored as bytes.
+    // Integers that aren't normalized reach the shader as integers, so declare them as int, ivec or uvec there.
+    pub normalized: bool,
+    pub offset: u32
+}
+
+// Describes how the fields of a vertex struct are laid out in memory, so a VertexArray
+// Can configure the attribute pointers without anyone typing strides or offsets by hand.
+// Attributes are numbered in the order they are added, matching the "layout (location = n)"
+// Declarations of the vertex shader.
+#[derive(Clone, PartialEq, Debug)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    stride: u32
+}
+
+impl VertexLayout {
+    pub fn new() -> VertexLayout {
+        VertexLayout { attributes: Vec::new(), stride: 0 }
+    }
+
+    // LEARN: Builder pattern
+    // Each "with_" function takes the layout by value and hands it back, so calls can be chained:
+    // VertexLayout::new().with_floats(3).with_floats(2)
+    pub fn with_attribute(mut self, components: i32, attribute_type: AttributeType, normalized: bool) -> VertexLayout {
+        self.attributes.push(VertexAttribute {
+            components,
+            attribute_type,
+            normalized,
+            offset: self.stride
+        });
+
+        self.stride += components as u32 * attribute_type.size_in_bytes();
+        self
+    }
+
+    pub fn with_floats(self, components: i32) -> VertexLayout {
+        self.with_attribute(components, AttributeType::Float, false)
+    }
+
+    // Skips bytes the compiler inserts between fields of a #[repr(C)] struct to keep them aligned.
+    pub fn with_padding(mut self, bytes: u32) -> VertexLayout {
+        self.stride += bytes;
+        self
+    }
+
+    pub fn get_attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn get_stride(&self) -> u32 {
+        self.stride
+    }
+
+    // Panics when the layout doesn't describe all of T, which usually means a forgotten field or padding.
+    pub fn check_size_of<T>(&self) {
+        if self.stride as usize != mem::size_of::<T>() {
+            panic!("Vertex layout stride ({} bytes) does not match the size of the vertex struct ({} bytes)",
+                self.stride, mem::size_of::<T>());
+        }
+    }
+}
+
+// Implemented by the Rust structs we upload into a VertexBuffer.
+// The struct must be #[repr(C)], so the field order we describe in "layout" is the order in memory.
+pub trait Vertex: Copy {
+    fn layout() -> VertexLayout;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::batch::BatchVertex;
+
+    #[repr(C)]
+    #[derive(Copy, Clone)]
+    struct PaddedVertex {
+        position: [f32; 2],
+        kind: u8,
+        weight: f32
+    }
+
+    #[test]
+    fn batch_vertex_layout() {
+        let layout = BatchVertex::layout();
+        let offsets: Vec<u32> = layout.get_attributes().iter().map(|attribute| attribute.offset).collect();
+
+        assert_eq!(offsets, vec![0, 12, 20]);
+        assert_eq!(layout.get_stride() as usize, mem::size_of::<BatchVertex>());
+        layout.check_size_of::<BatchVertex>();
+    }
+
+    #[test]
+    fn padding_moves_the_following_offsets() {
+        let layout = VertexLayout::new()
+            .with_floats(2)
+            .with_attribute(1, AttributeType::UnsignedByte, false)
+            .with_padding(3)
+            .with_floats(1);
+
+        assert_eq!(layout.get_attributes()[1].offset, 8);
+        assert_eq!(layout.get_attributes()[2].offset, 12);
+        layout.check_size_of::<PaddedVertex>();
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match the size of the vertex struct")]
+    fn stride_mismatch_panics() {
+        // Missing the padding after the byte
+        VertexLayout::new()
+            .with_floats(2)
+            .with_attribute(1, AttributeType::UnsignedByte, false)
+            .with_floats(1)
+            .check_size_of::<PaddedVertex>();
+    }
+}
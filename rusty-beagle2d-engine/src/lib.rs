@@ -0,0 +1,2 @@
+// The engine as a library, so the sandbox in main.rs, the benches and other games can use it.
+pub mod core;
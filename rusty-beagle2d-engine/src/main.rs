@@ -1,177 +1,322 @@
 use rusty_beagle2d_glfw;
-use rusty_beagle2d_glfw::glfw;
 use rusty_beagle2d_glfw::ogl;
 
 use linear_beaglebra::{vector2::Vector2, matrix4x4::Matrix4x4};
 
-use std::time::{Instant, Duration};
-
-mod core;
-use crate::core::texture;
-use crate::core::renderer2d;
-use crate::core::sprite;
+use rusty_beagle2d_engine::core::texture;
+use rusty_beagle2d_engine::core::renderer2d::Renderer2d;
+use rusty_beagle2d_engine::core::sprite;
+use rusty_beagle2d_engine::core::color::Color;
+use rusty_beagle2d_engine::core::app::App;
+use rusty_beagle2d_engine::core::game_loop::{Game, FpsCounter};
+use rusty_beagle2d_engine::core::time::Time;
+use rusty_beagle2d_engine::core::ecs::world::World;
+use rusty_beagle2d_engine::core::ecs::schedule::{Schedule, Stage, DeltaTime};
+use rusty_beagle2d_engine::core::ecs::components::{Transform, Camera};
+use rusty_beagle2d_engine::core::ecs::systems;
+use rusty_beagle2d_engine::core::physics::world::PhysicsWorld;
+use rusty_beagle2d_engine::core::input::{InputState, Key, WindowEvent};
+use rusty_beagle2d_engine::core::debug_ui::ui::{DebugUi, PlotHistory};
+use rusty_beagle2d_engine::core::renderer2d::RenderStats;
+use rusty_beagle2d_engine::core::rect::Rect;
+use rusty_beagle2d_engine::core::ui::ui_tree::{UiTree, UiNodeId, UiEvent};
+use rusty_beagle2d_engine::core::ui::layout::{LayoutStyle, Edges, Align, Justify};
+use rusty_beagle2d_engine::core::ui::theme::{self, Theme};
+use rusty_beagle2d_engine::core::particles::effect::ParticleEffect;
+use rusty_beagle2d_engine::core::particles::particle_system::ParticleSystem;
+
+use std::path::Path;
+use std::rc::Rc;
+
+// Lets the arrow keys move the entity
+struct CameraController {
+    speed: f32,
+    // Where the entity was before the last fixed update, to interpolate from when rendering
+    previous_position: Vector2
+}
 
-static mut cam_x: f32 = 0.0;
-static mut cam_y: f32 = 0.0;
+struct Sandbox {
+    world: World,
+    schedule: Schedule,
+    fps_counter: FpsCounter,
+    debug_ui: DebugUi,
+    show_debug_ui: bool,
+    // In milliseconds
+    frame_times: PlotHistory,
+    // Of the last finished frame
+    render_stats: RenderStats,
+    menu: UiTree,
+    pause_menu: UiNodeId,
+    resume_button: UiNodeId,
+    step_button: UiNodeId,
+    // Escape was pressed, the menu opens in the next update where the game time can be paused
+    open_pause_menu: bool,
+    ui_events: Vec<UiEvent>,
+    // None if the effect file failed to load
+    fountain: Option<ParticleSystem>
+}
 
-static mut button_states: u32 = 0;
+impl Sandbox {
+    fn new(renderer: &Renderer2d) -> Sandbox {
+        let mut world = World::new();
+
+        world.create_entity()
+            .with(Transform::new(0.0, 0.0))
+            .with(Camera)
+            .with(CameraController { speed: 500.0, previous_position: Vector2::new(0.0, 0.0) })
+            .build();
+
+        world.insert_resource(PhysicsWorld::new());
+        world.insert_resource(InputState::new());
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::FixedUpdate, "camera_controller", camera_controller);
+        schedule.add_system(Stage::FixedUpdate, "physics", systems::step_physics);
+        schedule.add_system(Stage::FixedUpdate, "character_controllers", systems::update_character_controllers);
+
+        let theme = theme::load_theme(Path::new("dat/ui/theme.json")).unwrap_or_else(|error| {
+            println!("{}", error);
+            Theme::new()
+        });
+
+        // A pause menu in the middle of the screen
+        let mut menu = UiTree::new(renderer.get_font().clone(), theme);
+        let root = menu.get_root();
+        *menu.get_layout_mut(root) = LayoutStyle::column().with_justify(Justify::Center).with_align(Align::Center);
+
+        let pause_menu = menu.add_panel(root, LayoutStyle::column().with_padding(Edges::all(16.0)).with_spacing(8.0).with_align(Align::Stretch));
+        menu.add_label(pause_menu, "Paused");
+        let resume_button = menu.add_button(pause_menu, "Resume");
+        let step_button = menu.add_button(pause_menu, "Step");
+        menu.set_visible(pause_menu, false);
+
+        // A fountain of sparks near the bottom of the screen
+        let fountain = ParticleEffect::load(Path::new("dat/particles/fountain.json"))
+            .and_then(|effect| {
+                let texture = effect.load_texture()?;
+                let mut fountain = ParticleSystem::new(Rc::new(effect), texture);
+                fountain.teleport(512.0, 700.0);
+                Ok(fountain)
+            })
+            .map_err(|error| println!("{}", error))
+            .ok();
+
+        Sandbox {
+            world,
+            schedule,
+            fps_counter: FpsCounter::new(),
+            debug_ui: DebugUi::new(renderer.get_font().clone()),
+            show_debug_ui: true,
+            frame_times: PlotHistory::new(120),
+            render_stats: RenderStats::default(),
+            menu,
+            pause_menu,
+            resume_button,
+            step_button,
+            open_pause_menu: false,
+            ui_events: Vec::new(),
+            fountain
+        }
+    }
 
-fn main() {
-    glfw::init().expect("Failed to initialize GLFW!");
+    fn update_menu(&mut self, time: &mut Time) {
+        if self.open_pause_menu {
+            self.open_pause_menu = false;
 
-    glfw::window_hint(glfw::WindowHint::Resizable as u32, glfw::GlfwBoolean::False as u32);
-    glfw::window_hint(glfw::WindowHint::OpenGlProfile as u32, glfw::WindowHintValue::OpenGlCoreProfile as u32);
-    glfw::window_hint(glfw::WindowHint::ContextVersionMajor as u32, 3);
-    glfw::window_hint(glfw::WindowHint::ContextVersionMinor as u32, 3);
-    glfw::window_hint(glfw::WindowHint::OpenGlDebugContext as u32, glfw::GlfwBoolean::True as u32);
+            time.pause();
+            self.menu.set_visible(self.pause_menu, true);
+            self.menu.set_focus(Some(self.resume_button));
+        }
 
-    let main_window = 
-        glfw::create_window(1024, 768, String::from("Rusty Beagle! :D"), None, None).expect("Failed to create main window!");
+        self.menu.take_events(&mut self.ui_events);
 
-    glfw::make_context_current(main_window);
+        for index in 0..self.ui_events.len() {
+            match self.ui_events[index] {
+                UiEvent::Clicked(button) if button == self.step_button => time.step(),
+                UiEvent::Clicked(button) if button == self.resume_button => self.close_menu(time),
+                UiEvent::Cancelled => self.close_menu(time),
+                _ => {}
+            }
+        }
 
-    glfw::set_key_callback(main_window, Some(glfw_key_callback));
+        self.ui_events.clear();
+        self.menu.update_layout(Rect::new(0.0, 0.0, 1024.0, 768.0));
+    }
 
-    let mut renderer2d = renderer2d::Renderer2d::new();
+    fn close_menu(&mut self, time: &mut Time) {
+        time.resume();
+        self.menu.set_visible(self.pause_menu, false);
+    }
 
-    // Game loop variables
-    let mut t = Duration::from_millis(0);
-    let dt = Duration::from_millis(1);
+    fn build_debug_ui(&mut self, time: &mut Time) {
+        let ui = &mut self.debug_ui;
+
+        if ui.begin_window("Debug (F1 to hide)", Vector2::new(10.0, 10.0)) {
+            ui.label(&format!("FPS: {:.1}", self.fps_counter.get_fps()));
+            ui.plot(&format!("Frame time: {:.2} ms##frame_time", self.frame_times.get_latest().unwrap_or(0.0)), self.frame_times.get_values(), 0.0, 33.0);
+            ui.label(&format!("Draw calls: {}  Triangles: {}  Flushes: {}", self.render_stats.draw_calls, self.render_stats.triangles, self.render_stats.flushes));
+            ui.label(&format!("Entities: {}", self.world.get_entity_count()));
+            ui.label(&format!("Particles: {}", self.fountain.as_ref().map_or(0, |fountain| fountain.get_particle_count())));
+
+            if ui.tree_node("Time") {
+                let mut time_scale = time.get_time_scale();
+                if ui.slider("Time scale", &mut time_scale, 0.0, 4.0) {
+                    time.set_time_scale(time_scale);
+                }
+
+                let mut paused = time.is_paused();
+                if ui.checkbox("Paused", &mut paused) {
+                    if paused { time.pause() } else { time.resume() }
+                }
+
+                ui.same_line();
+                if ui.button("Step") {
+                    time.step();
+                }
+
+                ui.label(&format!("Game time: {:.2} s  Ticks: {}", time.get_game_time().as_secs_f32(), time.get_tick_count()));
+                ui.tree_pop();
+            }
 
-    let mut current_time = Instant::now();
-    let mut accumulator = Duration::new(0, 0);
+            if ui.tree_node("Camera") {
+                self.world.for_each::<(&mut Transform, &mut CameraController), _>(|_, (transform, controller)| {
+                    let mut changed = ui.drag("x", &mut transform.position.x, 1.0);
+                    changed |= ui.drag("y", &mut transform.position.y, 1.0);
+                    ui.slider("Speed", &mut controller.speed, 0.0, 2000.0);
 
-    let mut fps = 0.0;
-    let mut frame_counter = 0.0;
-    let mut passed_time = Duration::from_millis(0);
+                    // Jump straight there instead of interpolating from the old position
+                    if changed {
+                        controller.previous_position = transform.position;
+                    }
+                });
 
-    // Disable v-sync
-    glfw::swap_interval(0);
+                ui.tree_pop();
+            }
+        }
 
-    // A game look typically consists of multiple different subsystems that needs "servicing" at different rates.
-    // For example, rendering the scene and updating the game's physics state need not be done in synchronization, and most
-    // Often is actually not.
-    // Right now, my physics are updated at a rate of 1 millisecond
-    // Currently, my game loop consists of the flow:
-    // 1. Read I/O
-    // 2. Update Game Physics
-    // 3. Render scene
-    while !glfw::window_should_close(main_window).expect("Failed to get window should close status.") {
-        let new_time = Instant::now();
-        let mut frame_time = new_time - current_time;
+        ui.end_window();
+    }
+}
 
-        passed_time += frame_time;
+impl Game for Sandbox {
+    fn handle_event(&mut self, event: &WindowEvent) {
+        self.debug_ui.handle_event(event);
 
-        fps = frame_counter / (passed_time.as_millis() as f64 / 1000.0);
+        let menu_open = self.menu.is_visible(self.pause_menu);
 
-        // TODO: Figure out why this is done
-        if frame_time > Duration::from_millis(250) {
-            frame_time = Duration::from_millis(250);
+        if menu_open {
+            self.menu.handle_event(event);
+        } else if let WindowEvent::KeyPressed { key: Key::Escape, repeat: false } = *event {
+            self.open_pause_menu = true;
         }
 
-        current_time = new_time;
-        accumulator += frame_time;
+        if let WindowEvent::KeyPressed { key: Key::Function(1), repeat: false } = *event {
+            if !self.debug_ui.wants_keyboard() {
+                self.show_debug_ui = !self.show_debug_ui;
+            }
+        }
 
-        // I/O Subsystem
-        // For continuous rendering, poll_events is the best way to process pending events.
-        // This is a non-blocking event processing call.
-        glfw::poll_events();
+        // Presses meant for the menu or debug UI don't move the camera. Releases always go through, so no key gets stuck.
+        let taken_by_ui = match event {
+            WindowEvent::KeyPressed { .. } => menu_open || self.debug_ui.wants_keyboard(),
+            WindowEvent::MouseButtonPressed { .. } => self.menu.wants_mouse() || self.debug_ui.wants_mouse(),
+            _ => false
+        };
 
-        // Physics Subsystem
-        while accumulator >= dt {
-            unsafe {
-                renderer2d.set_camera_position(cam_x, cam_y);
+        if !taken_by_ui {
+            if let Some(mut input) = self.world.get_resource_mut::<InputState>() {
+                input.handle_event(event);
             }
-
-            integrate(dt.as_secs_f32());
-            t += dt;
-            accumulator -= dt;
         }
+    }
 
-        // Render Loop / Rendering Subsystem
-        ogl::clear_color(
-            100.0 / 255.0, 
-            149.0 / 255.0,
-            237.0 / 255.0, 
-            1.0);
-
-        ogl::clear(ogl::ClearMask::ColorBufferBit);
+    fn fixed_update(&mut self, dt: f32, time: &mut Time) {
+        // Lets systems read the game time
+        self.world.insert_resource(time.clone());
+        self.schedule.run_fixed_update(&mut self.world, dt);
+    }
 
-        renderer2d.draw_text(&format!("FPS: {:.3}", fps)[..], Vector2::new(0.0, 0.0), 2.0);
+    fn update(&mut self, frame_dt: f32, time: &mut Time) {
+        self.fps_counter.update(frame_dt);
+        self.frame_times.push(frame_dt * 1000.0);
 
-        glfw::swap_buffers(main_window).expect("Failed to swap buffers for window!");
+        self.debug_ui.begin_frame();
 
-        frame_counter += 1.0;
-    }
+        if self.show_debug_ui {
+            self.build_debug_ui(time);
+        }
 
-    glfw::terminate();
-}
+        self.debug_ui.end_frame();
 
-fn integrate(dt : f32) {
-    // KEY RIGHT
-    if is_key_down(0) {
-        unsafe {
-            cam_x -= 500.0 * dt;
+        if let Some(fountain) = &mut self.fountain {
+            fountain.update(time.get_delta());
         }
-    }
 
-    // KEY LEFT
-    if is_key_down(1) {
-        unsafe {
-            cam_x += 500.0 * dt;
-        }
+        self.update_menu(time);
     }
 
-    // KEY UP
-    if is_key_down(2) {
-        unsafe {
-            cam_y += 500.0 * dt;
+    fn render(&mut self, renderer: &mut Renderer2d, alpha: f32) {
+        self.render_stats = renderer.get_stats();
+
+        // Place the camera between its last two fixed update positions, so it moves smoothly at any frame rate
+        self.world.for_each::<(&Transform, &CameraController), _>(|_, (transform, controller)| {
+            let x = controller.previous_position.x + (transform.position.x - controller.previous_position.x) * alpha;
+            let y = controller.previous_position.y + (transform.position.y - controller.previous_position.y) * alpha;
+            renderer.set_camera_position(-x, -y);
+        });
+
+        renderer.draw_entities(&self.world);
+
+        if let Some(fountain) = &self.fountain {
+            renderer.draw_particles(fountain);
         }
-    }
 
-    // KEY DOWN
-    if is_key_down(3) {
-        unsafe {
-            cam_y -= 500.0 * dt;
+        self.menu.render(renderer);
+
+        if self.show_debug_ui {
+            self.debug_ui.render(renderer);
+        } else {
+            renderer.draw_text(&format!("FPS: {:.3}", self.fps_counter.get_fps())[..], Vector2::new(0.0, 0.0), 2.0, Color::BLACK);
         }
     }
 }
 
-fn is_key_down(button_id: i32) -> bool {
-    unsafe {
-        (button_states << button_id) & (1 << 31) != 0
-    }
+fn main() {
+    // Physics are updated at a rate of 1 millisecond
+    let mut app = App::new("Rusty Beagle! :D", 1024, 768)
+        .with_tick_rate(1000);
+
+    let mut sandbox = Sandbox::new(app.get_renderer());
+    app.run(&mut sandbox);
 }
 
-// TODO: Find a good pattern for dispencing key press events to the rest of a game engine
-extern "C" fn glfw_key_callback(window: *mut glfw::GLFWwindow, key: i32, scancode: i32, action: i32, mods: i32) {
-    unsafe {
-        let mut button_id = -1;
+// Moves entities with a CameraController around with the arrow keys.
+fn camera_controller(world: &mut World) {
+    let dt = world.get_resource::<DeltaTime>().map_or(0.0, |delta_time| delta_time.seconds);
+    let input = match world.get_resource::<InputState>() {
+        Some(input) => input,
+        None => return
+    };
 
-        if key == glfw::GLFW_KEY_RIGHT as i32 {
-            button_id = 0;
-        }
+    world.for_each::<(&mut Transform, &mut CameraController), _>(|_, (transform, controller)| {
+        controller.previous_position = transform.position;
+
+        let distance = controller.speed * dt;
 
-        if key == glfw::GLFW_KEY_LEFT as i32 {
-            button_id = 1;
+        if input.is_key_down(Key::Right) {
+            transform.translate(distance, 0.0);
         }
 
-        if key == glfw::GLFW_KEY_UP as i32 {
-            button_id = 2;
+        if input.is_key_down(Key::Left) {
+            transform.translate(-distance, 0.0);
         }
 
-        if key == glfw::GLFW_KEY_DOWN as i32 {
-            button_id = 3;
+        if input.is_key_down(Key::Up) {
+            transform.translate(0.0, -distance);
         }
 
-        if button_id >= 0 {
-            if action == glfw::GLFW_PRESS as i32 {
-                button_states = button_states | (1 << (31 - button_id));
-            }
-    
-            if action == glfw::GLFW_RELEASE as i32 {
-                button_states = button_states ^ (1 << (31 - button_id));
-            }
+        if input.is_key_down(Key::Down) {
+            transform.translate(0.0, distance);
         }
-    }   
+    });
 }
\ No newline at end of file
@@ -0,0 +1,10 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="props" tilewidth="16" tileheight="32" tilecount="4" columns="4">
+ <image source="images/props.png" width="64" height="32"/>
+ <tile id="1" type="Chest">
+  <properties>
+   <property name="loot" value="gold"/>
+   <property name="amount" type="int" value="25"/>
+  </properties>
+ </tile>
+</tileset>
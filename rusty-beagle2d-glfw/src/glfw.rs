@@ -79,6 +79,33 @@ pub fn set_key_callback(window: *mut GLFWwindow, callback: GLFWkeyfun) {
     }
 }
 
+// Called with the unicode characters typed, after keyboard layout and modifiers are applied.
+// Use this rather than the key callback for text input.
+pub fn set_char_callback(window: *mut GLFWwindow, callback: GLFWcharfun) {
+    unsafe {
+        glfwSetCharCallback(window, callback);
+    }
+}
+
+pub fn set_mouse_button_callback(window: *mut GLFWwindow, callback: GLFWmousebuttonfun) {
+    unsafe {
+        glfwSetMouseButtonCallback(window, callback);
+    }
+}
+
+// Called with the cursor position in screen coordinates, relative to the top left corner of the window's content area.
+pub fn set_cursor_pos_callback(window: *mut GLFWwindow, callback: GLFWcursorposfun) {
+    unsafe {
+        glfwSetCursorPosCallback(window, callback);
+    }
+}
+
+pub fn set_scroll_callback(window: *mut GLFWwindow, callback: GLFWscrollfun) {
+    unsafe {
+        glfwSetScrollCallback(window, callback);
+    }
+}
+
 pub fn create_window(width: i32, 
                     height: i32, 
                     title: String, 
@@ -18,6 +18,36 @@ use ffi::CString;
 // type c_char = i8
 // type c_uchar = u8
 
+// LEARN: Newtype pattern
+// OpenGL refers to every object (textures, buffers, shaders...) by a plain GLuint name.
+// Wrapping each kind of name in its own single-field tuple struct means the compiler
+// Will refuse to pass a texture where a shader program is expected.
+// #[repr(transparent)] guarantees the wrapper has exactly the same memory layout as the u32 inside,
+// So the handles cost nothing at runtime.
+macro_rules! gl_object_handle {
+    ($name:ident) => {
+        #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+        #[repr(transparent)]
+        pub struct $name(u32);
+
+        impl $name {
+            // The zero name, which OpenGL uses to mean "no object" when binding.
+            pub const NONE: $name = $name(0);
+
+            pub fn as_raw(&self) -> u32 {
+                self.0
+            }
+        }
+    };
+}
+
+gl_object_handle!(TextureId);
+gl_object_handle!(BufferId);
+gl_object_handle!(VertexArrayId);
+gl_object_handle!(ProgramId);
+gl_object_handle!(ShaderId);
+gl_object_handle!(FramebufferId);
+
 #[repr(u32)]
 pub enum ClearMask {
     ColorBufferBit = gl::COLOR_BUFFER_BIT
@@ -29,6 +59,13 @@ pub enum BufferTarget {
     ElementArrayBuffer = gl::ELEMENT_ARRAY_BUFFER
 }
 
+#[repr(u32)]
+pub enum FramebufferTarget {
+    Framebuffer = gl::FRAMEBUFFER,
+    ReadFramebuffer = gl::READ_FRAMEBUFFER,
+    DrawFramebuffer = gl::DRAW_FRAMEBUFFER
+}
+
 #[repr(u32)]
 pub enum Name {
     Version = gl::VERSION,
@@ -41,9 +78,11 @@ pub enum Capability {
 }
 
 // TODO: Refactor to be convertable from u32 like my newest enum pattern
+#[derive(Copy, Clone, PartialEq)]
 pub enum Usage {
     DynamicDraw,
-    StaticDraw
+    StaticDraw,
+    StreamDraw
 }
 
 #[repr(u32)]
@@ -130,8 +169,16 @@ pub enum TextureFormat {
 
 #[repr(u32)]
 pub enum BlendFactor {
+    Zero = gl::ZERO,
+    One = gl::ONE,
+    SrcColor = gl::SRC_COLOR,
+    OneMinusSrcColor = gl::ONE_MINUS_SRC_COLOR,
+    DstColor = gl::DST_COLOR,
+    OneMinusDstColor = gl::ONE_MINUS_DST_COLOR,
     SrcAlpha = gl::SRC_ALPHA,
-    OneMinusSrcAlpha = gl::ONE_MINUS_SRC_ALPHA
+    OneMinusSrcAlpha = gl::ONE_MINUS_SRC_ALPHA,
+    DstAlpha = gl::DST_ALPHA,
+    OneMinusDstAlpha = gl::ONE_MINUS_DST_ALPHA
 }
 
 #[repr(u32)]
@@ -143,7 +190,8 @@ pub enum AlignmentParameter {
 // TODO: Give better name
 #[repr(u32)]
 pub enum Cap {
-    Blend = gl::BLEND
+    Blend = gl::BLEND,
+    ScissorTest = gl::SCISSOR_TEST
 }
 
 pub fn init() {
@@ -187,6 +235,31 @@ pub fn enable(capability: Cap) {
     }
 }
 
+pub fn disable(capability: Cap) {
+    unsafe {
+        gl::Disable(capability as u32);
+    }
+}
+
+// Only pixels inside the rectangle are drawn to while Cap::ScissorTest is enabled.
+// Window pixels, from the bottom left corner.
+pub fn scissor(x: i32, y: i32, width: i32, height: i32) {
+    unsafe {
+        gl::Scissor(x, y, width, height);
+    }
+}
+
+// x, y, width and height of the part of the window drawn to, in pixels from the bottom left corner.
+pub fn get_viewport() -> [i32; 4] {
+    let mut viewport = [0; 4];
+
+    unsafe {
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+    }
+
+    viewport
+}
+
 pub fn blend_func(sfactor: BlendFactor, dfactor: BlendFactor) {
     unsafe {
         gl::BlendFunc(sfactor as u32, dfactor as u32);
@@ -199,9 +272,9 @@ pub fn uniform_matrix_4fv(location: i32, count: i32, transpose: bool, value: *co
     }
 }
 
-pub fn get_uniform_location(program: u32, name: &str) -> i32 {
+pub fn get_uniform_location(program: ProgramId, name: &str) -> i32 {
     unsafe {
-        gl::GetUniformLocation(program, CString::new(name).unwrap().as_ptr())
+        gl::GetUniformLocation(program.0, CString::new(name).unwrap().as_ptr())
     }
 }
 
@@ -249,18 +322,24 @@ pub fn tex_image_2d_from_raw(texture_target: TextureTarget, level: i32, internal
     }
 }
 
-pub fn gen_texture() -> u32 {
+pub fn gen_texture() -> TextureId {
     let mut texture_object: u32 = 0;
 
     unsafe {
         gl::GenTextures(1, &mut texture_object);
-        texture_object
+        TextureId(texture_object)
+    }
+}
+
+pub fn bind_texture(texture_target: TextureTarget, texture: TextureId) {
+    unsafe {
+        gl::BindTexture(texture_target as u32, texture.0);
     }
 }
 
-pub fn bind_texture(texture_target: TextureTarget, texture: u32) {
+pub fn delete_texture(texture: TextureId) {
     unsafe {
-        gl::BindTexture(texture_target as u32, texture);
+        gl::DeleteTextures(1, &texture.0);
     }
 }
 
@@ -273,23 +352,39 @@ pub fn draw_elements(draw_mode: DrawMode, count: i32, data_type: ElementsDataTyp
     }
 }
 
+// "offset" is the byte offset of the first index to read from the bound element buffer.
+pub fn draw_elements_with_offset(draw_mode: DrawMode, count: i32, data_type: ElementsDataType, offset: usize) {
+    unsafe {
+        gl::DrawElements(draw_mode as u32,
+        count,
+        data_type as u32,
+        offset as *const c_void);
+    }
+}
+
 pub fn draw_arrays(draw_mode: DrawMode, first: i32, count: i32) {
     unsafe {
         gl::DrawArrays(draw_mode as u32, first, count)
     }
 }
 
-pub fn bind_vertex_array(vao: u32) {
+pub fn bind_vertex_array(vao: VertexArrayId) {
     unsafe {
-        gl::BindVertexArray(vao);
+        gl::BindVertexArray(vao.0);
     }
 }
 
-pub fn gen_vertex_array() -> u32 {
+pub fn gen_vertex_array() -> VertexArrayId {
     unsafe {
         let mut vertex_array_object = 0;
         gl::GenVertexArrays(1, &mut vertex_array_object as *mut u32);
-        vertex_array_object
+        VertexArrayId(vertex_array_object)
+    }
+}
+
+pub fn delete_vertex_array(vao: VertexArrayId) {
+    unsafe {
+        gl::DeleteVertexArrays(1, &vao.0);
     }
 }
 
@@ -306,60 +401,73 @@ pub fn vertex_attrib_pointer(index: u32, size: i32, data_type: DataType, normali
     }
 }
 
-pub fn delete_shader(shader: u32) {
+// Like vertex_attrib_pointer, but integers reach the shader as integers (ivec, uvec) instead of being turned into floats.
+pub fn vertex_attrib_i_pointer(index: u32, size: i32, data_type: DataType, stride: i32, offset: u32) {
     unsafe {
-        gl::DeleteShader(shader);
+        gl::VertexAttribIPointer(index, size, data_type as u32, stride, offset as *const c_void);
     }
 }
 
-pub fn use_program(program: u32) {
+pub fn delete_shader(shader: ShaderId) {
     unsafe {
-        gl::UseProgram(program);
+        gl::DeleteShader(shader.0);
     }
 }
 
-pub fn get_program_info_log(program: u32) -> String {
+pub fn use_program(program: ProgramId) {
+    unsafe {
+        gl::UseProgram(program.0);
+    }
+}
+
+pub fn delete_program(program: ProgramId) {
+    unsafe {
+        gl::DeleteProgram(program.0);
+    }
+}
+
+pub fn get_program_info_log(program: ProgramId) -> String {
     unsafe {
         let raw_string_ptr = ffi::CString::from_vec_unchecked(vec![0; 512]).into_raw();
-        gl::GetProgramInfoLog(program, 512, ptr::null_mut(), raw_string_ptr);
+        gl::GetProgramInfoLog(program.0, 512, ptr::null_mut(), raw_string_ptr);
         ffi::CString::from_raw(raw_string_ptr).into_string().unwrap()
     }
 }
 
-pub fn get_programiv(program: u32, pname: ProgramParameter) -> i32 {
+pub fn get_programiv(program: ProgramId, pname: ProgramParameter) -> i32 {
     unsafe {
         let mut return_value: i32 = 0;
-        gl::GetProgramiv(program, pname as u32, &mut return_value as *mut i32);
+        gl::GetProgramiv(program.0, pname as u32, &mut return_value as *mut i32);
         return_value
     }
 }
 
-pub fn link_program(program: u32) {
+pub fn link_program(program: ProgramId) {
     unsafe {
-        gl::LinkProgram(program);
+        gl::LinkProgram(program.0);
     }
 }
 
-pub fn attach_shader(program: u32, shader: u32) {
+pub fn attach_shader(program: ProgramId, shader: ShaderId) {
     unsafe {
-        gl::AttachShader(program, shader);
+        gl::AttachShader(program.0, shader.0);
     }
 }
 
-pub fn create_program() -> u32 {
+pub fn create_program() -> ProgramId {
     unsafe {
-        gl::CreateProgram()
+        ProgramId(gl::CreateProgram())
     }
 }
 
-pub fn get_shader_info_log(shader: u32) -> String {
+pub fn get_shader_info_log(shader: ShaderId) -> String {
     unsafe {
         // I convert the created CString into a raw mutable pointer, and transforms ownership.
         // TODO: Not sure if that's actually needed? Could I just take the pointer of the vec directly?
         let raw_pointer = ffi::CString::from_vec_unchecked(vec![0; 512]).into_raw();
 
         // Retrieve the shader log, returned in the CString previously created.
-        gl::GetShaderInfoLog(shader, 512, ptr::null_mut() as *mut i32, raw_pointer);
+        gl::GetShaderInfoLog(shader.0, 512, ptr::null_mut() as *mut i32, raw_pointer);
 
         // Here I retake ownership of the CString previously transferred to C via into_raw.
         // This has to be called after a call to "into_raw". Failure to do so will result in a memory leak.
@@ -367,28 +475,28 @@ pub fn get_shader_info_log(shader: u32) -> String {
     }
 }
 
-pub fn get_shader(shader: u32, parameter_name: Parameter) -> i32 {
+pub fn get_shader(shader: ShaderId, parameter_name: Parameter) -> i32 {
     let mut parameter_value = 0;
 
     unsafe {
-        gl::GetShaderiv(shader, parameter_name as u32, &mut parameter_value as *mut i32);
+        gl::GetShaderiv(shader.0, parameter_name as u32, &mut parameter_value as *mut i32);
         parameter_value
     }
 }
 
-pub fn compile_shader(shader: u32) {
+pub fn compile_shader(shader: ShaderId) {
     unsafe {
-        gl::CompileShader(shader);
+        gl::CompileShader(shader.0);
     }
 }
 
-pub fn create_shader(shader_type: ShaderType) -> u32 {
+pub fn create_shader(shader_type: ShaderType) -> ShaderId {
     unsafe {
-        gl::CreateShader(shader_type as u32)
+        ShaderId(gl::CreateShader(shader_type as u32))
     }
 }
 
-pub fn shader_source(vertex_shader: u32, count: i32, strings: &Vec<&String>) {
+pub fn shader_source(vertex_shader: ShaderId, count: i32, strings: &Vec<&String>) {
     // TODO: Not sure how to do this more efficiently / more elegant
     // Question is: How do you convert &Vec<&String> most consicely to SAFE C Strings of *const *const i8?
     let mut safe_c_strings: Vec<ffi::CString> = Vec::new();
@@ -406,7 +514,7 @@ pub fn shader_source(vertex_shader: u32, count: i32, strings: &Vec<&String>) {
         // Setting it to null means that it is assumed that each string element is null-terminated.
         // NOTE ON LIFETIME: OpenGL will copy the source code strings, so it's not necessary for me
         // To keep them alive after this function call has returned.
-        gl::ShaderSource(vertex_shader, count, pointers_to_safe_c_strings.as_ptr(), ptr::null());
+        gl::ShaderSource(vertex_shader.0, count, pointers_to_safe_c_strings.as_ptr(), ptr::null());
     }
 }
 
@@ -422,38 +530,88 @@ pub fn clear_color(red: f32, green: f32, blue: f32, alpha: f32) {
     }
 }
 
-pub fn gl_gen_buffer() -> u32 {
+pub fn gl_gen_buffer() -> BufferId {
     let mut buffer = 0;
 
     unsafe {
         gl::GenBuffers(1, &mut buffer);
-        buffer
+        BufferId(buffer)
+    }
+}
+
+pub fn gl_bind_buffer(buffer_target: BufferTarget, buffer: BufferId) {
+    unsafe {
+        gl::BindBuffer(buffer_target as u32, buffer.0);
+    }
+}
+
+pub fn delete_buffer(buffer: BufferId) {
+    unsafe {
+        gl::DeleteBuffers(1, &buffer.0);
+    }
+}
+
+pub fn gen_framebuffer() -> FramebufferId {
+    let mut framebuffer = 0;
+
+    unsafe {
+        gl::GenFramebuffers(1, &mut framebuffer);
+        FramebufferId(framebuffer)
     }
 }
 
-pub fn gl_bind_buffer(buffer_target: BufferTarget, buffer: u32) {
+pub fn bind_framebuffer(framebuffer_target: FramebufferTarget, framebuffer: FramebufferId) {
     unsafe {
-        gl::BindBuffer(buffer_target as u32, buffer);
+        gl::BindFramebuffer(framebuffer_target as u32, framebuffer.0);
     }
 }
 
-pub fn buffer_data<T>(buffer_target: BufferTarget, data: &Vec<T>, usage: Usage) {
+pub fn delete_framebuffer(framebuffer: FramebufferId) {
+    unsafe {
+        gl::DeleteFramebuffers(1, &framebuffer.0);
+    }
+}
+
+pub fn buffer_data<T>(buffer_target: BufferTarget, data: &[T], usage: Usage) {
     unsafe {
         gl::BufferData(
-            match buffer_target {
-                BufferTarget::ArrayBuffer => gl::ARRAY_BUFFER,
-                BufferTarget::ElementArrayBuffer => gl::ELEMENT_ARRAY_BUFFER
-            },
+            buffer_target as u32,
             (mem::size_of::<T>() * data.len()) as isize,
             data.as_ptr() as *const c_void,
-            match usage {
-                Usage::StaticDraw => gl::STATIC_DRAW,
-                Usage::DynamicDraw => gl::DYNAMIC_DRAW
-            }
+            usage_to_gl(usage)
+        )
+    }
+}
+
+// Allocates "size" bytes of storage for the bound buffer without uploading anything.
+// Calling this on a buffer that is already in use is the classic "orphaning" trick:
+// The driver hands us fresh storage, while draw calls still in flight keep reading the old one.
+pub fn buffer_data_uninitialized(buffer_target: BufferTarget, size: isize, usage: Usage) {
+    unsafe {
+        gl::BufferData(buffer_target as u32, size, ptr::null(), usage_to_gl(usage))
+    }
+}
+
+// "offset" is given in bytes from the start of the buffer.
+pub fn buffer_sub_data<T>(buffer_target: BufferTarget, offset: isize, data: &[T]) {
+    unsafe {
+        gl::BufferSubData(
+            buffer_target as u32,
+            offset,
+            (mem::size_of::<T>() * data.len()) as isize,
+            data.as_ptr() as *const c_void
         )
     }
 }
 
+fn usage_to_gl(usage: Usage) -> u32 {
+    match usage {
+        Usage::StaticDraw => gl::STATIC_DRAW,
+        Usage::DynamicDraw => gl::DYNAMIC_DRAW,
+        Usage::StreamDraw => gl::STREAM_DRAW
+    }
+}
+
 pub fn gl_get_string(name: Name) -> String {
     unsafe {
         ffi::CStr::from_ptr(gl::GetString(name as u32) as *const i8).to_string_lossy().into_owned()
@@ -0,0 +1,204 @@
+use crate::max_rects::Rect;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// An 8 bit RGBA image, stored row by row from the top left corner.
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>
+}
+
+impl Image {
+    // Creates a fully transparent image
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize]
+        }
+    }
+
+    pub fn load_png(path: &Path) -> Result<Image, String> {
+        let file = File::open(path).map_err(|error| format!("Failed to open {}: {}", path.display(), error))?;
+
+        // Expands palettes and low bit depths, and strips 16 bit channels down to 8 bits
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info().map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|error| format!("Failed to decode {}: {}", path.display(), error))?;
+        let data = &buffer[..info.buffer_size()];
+
+        let pixels: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => data.to_vec(),
+            png::ColorType::Rgb => data.chunks(3).flat_map(|p| vec![p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => data.chunks(2).flat_map(|p| vec![p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => data.iter().flat_map(|&p| vec![p, p, p, 255]).collect(),
+            png::ColorType::Indexed => return Err(format!("{} still has a palette after decoding", path.display()))
+        };
+
+        Ok(Image { width: info.width, height: info.height, pixels })
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|error| format!("Failed to create {}: {}", path.display(), error))?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(|error| format!("Failed to write {}: {}", path.display(), error))?;
+        writer.write_image_data(&self.pixels).map_err(|error| format!("Failed to write {}: {}", path.display(), error))
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * self.width + x) * 4) as usize;
+        [self.pixels[index], self.pixels[index + 1], self.pixels[index + 2], self.pixels[index + 3]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        let index = ((y * self.width + x) * 4) as usize;
+        self.pixels[index..index + 4].copy_from_slice(&pixel);
+    }
+
+    // The smallest rectangle holding every pixel that isn't fully transparent.
+    // A fully transparent image keeps a single pixel, so it still gets a region in the atlas.
+    pub fn get_opaque_bounds(&self) -> Rect {
+        let mut left = self.width;
+        let mut top = self.height;
+        let mut right = 0;
+        let mut bottom = 0;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get_pixel(x, y)[3] != 0 {
+                    left = left.min(x);
+                    top = top.min(y);
+                    right = right.max(x + 1);
+                    bottom = bottom.max(y + 1);
+                }
+            }
+        }
+
+        if right == 0 {
+            return Rect::new(0, 0, 1, 1);
+        }
+
+        Rect::new(left, top, right - left, bottom - top)
+    }
+
+    // Copies "source_rect" of the source image to (x, y) of this image.
+    // With extrusion, the edge pixels of the copied area are repeated "extrude" pixels outwards,
+    // So texture filtering at the edges of a region doesn't bleed in pixels from its neighbours.
+    pub fn blit(&mut self, source: &Image, source_rect: Rect, x: u32, y: u32, extrude: u32) {
+        let extrude = extrude as i64;
+
+        for offset_y in -extrude..source_rect.height as i64 + extrude {
+            for offset_x in -extrude..source_rect.width as i64 + extrude {
+                let source_x = source_rect.x + offset_x.max(0).min(source_rect.width as i64 - 1) as u32;
+                let source_y = source_rect.y + offset_y.max(0).min(source_rect.height as i64 - 1) as u32;
+
+                let target_x = x as i64 + offset_x;
+                let target_y = y as i64 + offset_y;
+
+                if target_x < 0 || target_y < 0 || target_x >= self.width as i64 || target_y >= self.height as i64 {
+                    continue;
+                }
+
+                self.set_pixel(target_x as u32, target_y as u32, source.get_pixel(source_x, source_y));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn opaque_bounds() {
+        let mut image = Image::new(10, 8);
+        assert_eq!(image.get_opaque_bounds(), Rect::new(0, 0, 1, 1));
+
+        image.set_pixel(2, 3, RED);
+        image.set_pixel(6, 5, [0, 0, 0, 1]);
+        assert_eq!(image.get_opaque_bounds(), Rect::new(2, 3, 5, 3));
+    }
+
+    #[test]
+    fn blit_without_extrusion() {
+        let mut source = Image::new(4, 4);
+        source.set_pixel(1, 1, RED);
+        source.set_pixel(2, 2, GREEN);
+
+        let mut target = Image::new(6, 6);
+        target.blit(&source, Rect::new(1, 1, 2, 2), 3, 2, 0);
+
+        assert_eq!(target.get_pixel(3, 2), RED);
+        assert_eq!(target.get_pixel(4, 3), GREEN);
+        assert_eq!(target.get_pixel(2, 2), CLEAR);
+        assert_eq!(target.get_pixel(5, 4), CLEAR);
+    }
+
+    #[test]
+    fn blit_extrudes_the_edge_pixels() {
+        let mut source = Image::new(2, 1);
+        source.set_pixel(0, 0, RED);
+        source.set_pixel(1, 0, GREEN);
+
+        let mut target = Image::new(8, 5);
+        target.blit(&source, Rect::new(0, 0, 2, 1), 3, 2, 2);
+
+        for y in 0..5 {
+            assert_eq!(target.get_pixel(0, y), CLEAR);
+            assert_eq!(target.get_pixel(1, y), RED);
+            assert_eq!(target.get_pixel(2, y), RED);
+            assert_eq!(target.get_pixel(3, y), RED);
+            assert_eq!(target.get_pixel(4, y), GREEN);
+            assert_eq!(target.get_pixel(5, y), GREEN);
+            assert_eq!(target.get_pixel(6, y), GREEN);
+            assert_eq!(target.get_pixel(7, y), CLEAR);
+        }
+    }
+
+    #[test]
+    fn blit_clips_at_the_target_edges() {
+        let mut source = Image::new(2, 2);
+        source.set_pixel(0, 0, RED);
+
+        let mut target = Image::new(2, 2);
+        target.blit(&source, Rect::new(0, 0, 2, 2), 0, 0, 3);
+        assert_eq!(target.get_pixel(0, 0), RED);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut image = Image::new(3, 2);
+        image.set_pixel(0, 0, RED);
+        image.set_pixel(2, 1, [10, 20, 30, 40]);
+
+        let path = std::env::temp_dir().join(format!("beagle-pack-image-{}.png", std::process::id()));
+        image.save_png(&path).unwrap();
+        let loaded = Image::load_png(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((loaded.get_width(), loaded.get_height()), (3, 2));
+        assert_eq!(loaded.pixels, image.pixels);
+    }
+}
@@ -0,0 +1,63 @@
+// beagle-pack
+// Packs a directory of loose PNGs into atlas pages, plus a JSON description of each page the engine can load.
+// Everything runs on the CPU and the output only depends on the input files and options,
+// So the same input always produces byte identical atlases.
+
+mod image;
+mod max_rects;
+mod metadata;
+mod options;
+mod packer;
+
+use options::Options;
+
+use std::process;
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1).collect()) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", options::USAGE);
+            return;
+        },
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, options::USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(error) = run(&options) {
+        eprintln!("beagle-pack: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let paths = packer::find_images(&options.input_directory)?;
+
+    if paths.is_empty() {
+        return Err(format!("No PNG images found in {}", options.input_directory.display()));
+    }
+
+    let source_images = packer::load_source_images(&options.input_directory, &paths, options.trim)?;
+    let pages = packer::pack(&source_images, options)?;
+
+    std::fs::create_dir_all(&options.output_directory)
+        .map_err(|error| format!("Failed to create {}: {}", options.output_directory.display(), error))?;
+
+    for (page_index, page) in pages.iter().enumerate() {
+        let image_path = options.output_directory.join(packer::get_page_file_name(&options.name, page_index, "png"));
+        let metadata_path = options.output_directory.join(packer::get_page_file_name(&options.name, page_index, "json"));
+
+        page.image.save_png(&image_path)?;
+
+        let json = serde_json::to_string_pretty(&page.metadata).map_err(|error| error.to_string())?;
+        std::fs::write(&metadata_path, json)
+            .map_err(|error| format!("Failed to write {}: {}", metadata_path.display(), error))?;
+
+        println!("{}: {}x{}, {} images",
+            image_path.display(), page.image.get_width(), page.image.get_height(), page.metadata.frames.len());
+    }
+
+    Ok(())
+}
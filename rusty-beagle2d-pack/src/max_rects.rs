@@ -0,0 +1,249 @@
+// MaxRects bin packing, using the "best short side fit" heuristic.
+// Based on Jukka Jylänki's "A Thousand Ways to Pack the Bin".
+// LEARN: MaxRects
+// The packer keeps a list of all maximal free rectangles of the bin. These may overlap each other.
+// A new rectangle goes into the free rectangle that leaves the smallest leftover on its shortest side.
+// Afterwards, every free rectangle that overlaps the placed one is split into the (up to four) parts around it,
+// And free rectangles fully contained in other free rectangles are removed again.
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    pub fn get_right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    pub fn get_bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.get_right() && other.x < self.get_right() &&
+        self.y < other.get_bottom() && other.y < self.get_bottom()
+    }
+
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x && other.y >= self.y &&
+        other.get_right() <= self.get_right() && other.get_bottom() <= self.get_bottom()
+    }
+}
+
+pub struct MaxRects {
+    width: u32,
+    height: u32,
+    free_rects: Vec<Rect>,
+    used_rects: Vec<Rect>
+}
+
+impl MaxRects {
+    pub fn new(width: u32, height: u32) -> MaxRects {
+        MaxRects {
+            width,
+            height,
+            free_rects: vec![Rect::new(0, 0, width, height)],
+            used_rects: Vec::new()
+        }
+    }
+
+    // Places a rectangle of the given size, or returns None if the bin has no room for it.
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<Rect> {
+        if width == 0 || height == 0 || width > self.width || height > self.height {
+            return None;
+        }
+
+        let placed = self.find_position(width, height)?;
+
+        let mut new_free_rects = Vec::with_capacity(self.free_rects.len() + 4);
+
+        for free_rect in &self.free_rects {
+            if free_rect.intersects(&placed) {
+                split_free_rect(free_rect, &placed, &mut new_free_rects);
+            } else {
+                new_free_rects.push(*free_rect);
+            }
+        }
+
+        self.free_rects = prune_free_rects(new_free_rects);
+        self.used_rects.push(placed);
+
+        Some(placed)
+    }
+
+    // The smallest size that covers every placed rectangle.
+    pub fn get_used_size(&self) -> (u32, u32) {
+        self.used_rects.iter().fold((0, 0), |(width, height), rect| {
+            (width.max(rect.get_right()), height.max(rect.get_bottom()))
+        })
+    }
+
+    fn find_position(&self, width: u32, height: u32) -> Option<Rect> {
+        let mut best: Option<(Rect, u32, u32)> = None;
+
+        for free_rect in &self.free_rects {
+            if free_rect.width < width || free_rect.height < height {
+                continue;
+            }
+
+            let leftover_horizontal = free_rect.width - width;
+            let leftover_vertical = free_rect.height - height;
+            let short_side = leftover_horizontal.min(leftover_vertical);
+            let long_side = leftover_horizontal.max(leftover_vertical);
+
+            // On ties the first free rectangle wins, which keeps the result deterministic
+            let is_better = match best {
+                Some((_, best_short_side, best_long_side)) => {
+                    short_side < best_short_side || (short_side == best_short_side && long_side < best_long_side)
+                },
+                None => true
+            };
+
+            if is_better {
+                best = Some((Rect::new(free_rect.x, free_rect.y, width, height), short_side, long_side));
+            }
+        }
+
+        best.map(|(rect, _, _)| rect)
+    }
+}
+
+fn split_free_rect(free_rect: &Rect, placed: &Rect, output: &mut Vec<Rect>) {
+    // Left of the placed rectangle
+    if placed.x > free_rect.x {
+        output.push(Rect::new(free_rect.x, free_rect.y, placed.x - free_rect.x, free_rect.height));
+    }
+
+    // Right of it
+    if placed.get_right() < free_rect.get_right() {
+        output.push(Rect::new(placed.get_right(), free_rect.y, free_rect.get_right() - placed.get_right(), free_rect.height));
+    }
+
+    // Above it
+    if placed.y > free_rect.y {
+        output.push(Rect::new(free_rect.x, free_rect.y, free_rect.width, placed.y - free_rect.y));
+    }
+
+    // Below it
+    if placed.get_bottom() < free_rect.get_bottom() {
+        output.push(Rect::new(free_rect.x, placed.get_bottom(), free_rect.width, free_rect.get_bottom() - placed.get_bottom()));
+    }
+}
+
+// Removes free rectangles that lie within another free rectangle. Of two identical rectangles, the first one is kept.
+fn prune_free_rects(free_rects: Vec<Rect>) -> Vec<Rect> {
+    free_rects.iter()
+        .enumerate()
+        .filter(|(index, rect)| {
+            !free_rects.iter().enumerate().any(|(other_index, other)| {
+                other_index != *index && other.contains(rect) && (other != *rect || other_index < *index)
+            })
+        })
+        .map(|(_, rect)| *rect)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small deterministic random numbers, so failures can be reproduced
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self, max: u32) -> u32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % max as u64) as u32
+        }
+    }
+
+    // Whether a rectangle of the given size fits anywhere in the bin without overlapping the used rectangles.
+    fn has_room(width: u32, height: u32, bin_width: u32, bin_height: u32, used: &[Rect]) -> bool {
+        (0..=bin_height.saturating_sub(height)).any(|y| {
+            (0..=bin_width.saturating_sub(width)).any(|x| {
+                let candidate = Rect::new(x, y, width, height);
+                used.iter().all(|rect| !rect.intersects(&candidate))
+            })
+        })
+    }
+
+    #[test]
+    fn placed_rects_never_overlap_and_stay_in_the_bin() {
+        for seed in 0..20 {
+            let mut random = Random(seed);
+            let mut packer = MaxRects::new(64, 48);
+            let bin = Rect::new(0, 0, 64, 48);
+            let mut used = Vec::new();
+
+            for _ in 0..60 {
+                let width = 1 + random.next(20);
+                let height = 1 + random.next(20);
+
+                match packer.insert(width, height) {
+                    Some(rect) => {
+                        assert_eq!((rect.width, rect.height), (width, height));
+                        assert!(bin.contains(&rect), "{:?} is outside the bin", rect);
+                        assert!(used.iter().all(|other: &Rect| !other.intersects(&rect)), "{:?} overlaps a placed rect", rect);
+                        used.push(rect);
+                    },
+                    // The free list holds every maximal free rectangle, so a rejection means there really is no room
+                    None => assert!(!has_room(width, height, 64, 48, &used), "{}x{} was rejected but fits (seed {})", width, height, seed)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fills_the_bin_exactly() {
+        let mut packer = MaxRects::new(32, 32);
+
+        for _ in 0..16 {
+            assert!(packer.insert(8, 8).is_some());
+        }
+
+        assert_eq!(packer.insert(1, 1), None);
+        assert_eq!(packer.get_used_size(), (32, 32));
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_rects() {
+        let mut packer = MaxRects::new(16, 16);
+
+        assert_eq!(packer.insert(0, 4), None);
+        assert_eq!(packer.insert(4, 0), None);
+        assert_eq!(packer.insert(17, 1), None);
+        assert_eq!(packer.insert(16, 16), Some(Rect::new(0, 0, 16, 16)));
+    }
+
+    #[test]
+    fn used_size() {
+        let mut packer = MaxRects::new(64, 64);
+        assert_eq!(packer.get_used_size(), (0, 0));
+
+        packer.insert(10, 20);
+        packer.insert(30, 5);
+        let (width, height) = packer.get_used_size();
+        assert!(width >= 30 && height >= 20);
+        assert!(width <= 40 && height <= 25);
+    }
+
+    #[test]
+    fn same_input_same_placements() {
+        let sizes = [(12, 7), (3, 30), (16, 16), (9, 9), (25, 4), (1, 1), (7, 12)];
+
+        let run = || {
+            let mut packer = MaxRects::new(48, 48);
+            sizes.iter().map(|&(width, height)| packer.insert(width, height)).collect::<Vec<Option<Rect>>>()
+        };
+
+        assert_eq!(run(), run());
+    }
+}
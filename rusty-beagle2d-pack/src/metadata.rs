@@ -0,0 +1,58 @@
+// The atlas description, written in TexturePacker's "JSON (Array)" format.
+// The engine loads it with core::importers::texture_packer::load_texture_packer_atlas.
+
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct JsonRect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32
+}
+
+#[derive(Serialize)]
+pub struct JsonSize {
+    pub w: u32,
+    pub h: u32
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonFrame {
+    pub filename: String,
+    pub frame: JsonRect,
+    pub rotated: bool,
+    pub trimmed: bool,
+    pub sprite_source_size: JsonRect,
+    pub source_size: JsonSize
+}
+
+#[derive(Serialize)]
+pub struct JsonMeta {
+    pub app: String,
+    pub version: String,
+    pub image: String,
+    pub format: String,
+    pub size: JsonSize,
+    pub scale: String
+}
+
+#[derive(Serialize)]
+pub struct JsonAtlas {
+    pub frames: Vec<JsonFrame>,
+    pub meta: JsonMeta
+}
+
+impl JsonMeta {
+    pub fn new(image: String, width: u32, height: u32) -> JsonMeta {
+        JsonMeta {
+            app: String::from("beagle-pack"),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            image,
+            format: String::from("RGBA8888"),
+            size: JsonSize { w: width, h: height },
+            scale: String::from("1")
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: beagle-pack <input directory> <output directory> [options]
+
+Packs every PNG in the input directory (and its sub directories) into atlas pages.
+Writes <name>-<page>.png and <name>-<page>.json for each page.
+
+Options:
+  --name <name>        Base name of the output files (default: atlas)
+  --max-size <pixels>  Maximum width and height of a page (default: 2048)
+  --padding <pixels>   Empty pixels between images (default: 2)
+  --extrude <pixels>   Repeat the edge pixels of each image outwards (default: 1)
+  --trim               Cut away fully transparent borders
+  --pot                Round the page sizes up to powers of two
+  --help               Show this text";
+
+pub struct Options {
+    pub input_directory: PathBuf,
+    pub output_directory: PathBuf,
+    pub name: String,
+    pub max_size: u32,
+    pub padding: u32,
+    pub extrude: u32,
+    pub trim: bool,
+    pub power_of_two: bool
+}
+
+impl Options {
+    // Parses the command line arguments, without the program name.
+    // Returns Ok(None) if the user asked for help.
+    pub fn parse(arguments: Vec<String>) -> Result<Option<Options>, String> {
+        let mut positional = Vec::new();
+        let mut name = String::from("atlas");
+        let mut max_size = 2048;
+        let mut padding = 2;
+        let mut extrude = 1;
+        let mut trim = false;
+        let mut power_of_two = false;
+
+        let mut arguments = arguments.into_iter();
+
+        while let Some(argument) = arguments.next() {
+            match argument.as_str() {
+                "--help" | "-h" => return Ok(None),
+                "--name" => name = next_value(&mut arguments, &argument)?,
+                "--max-size" => max_size = parse_number(&next_value(&mut arguments, &argument)?, &argument)?,
+                "--padding" => padding = parse_number(&next_value(&mut arguments, &argument)?, &argument)?,
+                "--extrude" => extrude = parse_number(&next_value(&mut arguments, &argument)?, &argument)?,
+                "--trim" => trim = true,
+                "--pot" => power_of_two = true,
+                _ if argument.starts_with("--") => return Err(format!("Unknown option {}", argument)),
+                _ => positional.push(argument)
+            }
+        }
+
+        if positional.len() != 2 {
+            return Err(String::from("Expected an input and an output directory"));
+        }
+
+        if max_size == 0 {
+            return Err(String::from("--max-size must be greater than 0"));
+        }
+
+        if power_of_two && !max_size.is_power_of_two() {
+            return Err(format!("--max-size must be a power of two when using --pot, but was {}", max_size));
+        }
+
+        let output_directory = PathBuf::from(positional.pop().unwrap());
+        let input_directory = PathBuf::from(positional.pop().unwrap());
+
+        Ok(Some(Options {
+            input_directory,
+            output_directory,
+            name,
+            max_size,
+            padding,
+            extrude,
+            trim,
+            power_of_two
+        }))
+    }
+}
+
+fn next_value(arguments: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    arguments.next().ok_or_else(|| format!("{} expects a value", option))
+}
+
+fn parse_number(value: &str, option: &str) -> Result<u32, String> {
+    value.parse::<u32>().map_err(|_| format!("{} expects a whole number, but got '{}'", option, value))
+}
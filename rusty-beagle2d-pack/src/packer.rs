@@ -0,0 +1,429 @@
+use crate::image::Image;
+use crate::max_rects::{MaxRects, Rect};
+use crate::metadata::{JsonAtlas, JsonFrame, JsonMeta, JsonRect, JsonSize};
+use crate::options::Options;
+
+use std::path::{Path, PathBuf};
+
+// A loose image to pack, and the part of it that ends up in the atlas.
+pub struct SourceImage {
+    // Path relative to the input directory, without extension and with "/" separators
+    pub name: String,
+    pub image: Image,
+    pub kept_rect: Rect
+}
+
+// Where a source image landed. x and y point at the image itself, inside its extrusion border.
+struct Placement {
+    source_index: usize,
+    x: u32,
+    y: u32
+}
+
+pub struct Page {
+    pub image: Image,
+    pub metadata: JsonAtlas
+}
+
+// Finds every PNG below the directory, sorted by path so the output doesn't depend on the file system.
+pub fn find_images(directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    collect_images(directory, &mut paths)?;
+    paths.sort();
+    Ok(paths)
+}
+
+fn collect_images(directory: &Path, paths: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = std::fs::read_dir(directory)
+        .map_err(|error| format!("Failed to read directory {}: {}", directory.display(), error))?;
+
+    for entry in entries {
+        let path = entry.map_err(|error| format!("Failed to read directory {}: {}", directory.display(), error))?.path();
+
+        if path.is_dir() {
+            collect_images(&path, paths)?;
+        } else if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn load_source_images(input_directory: &Path, paths: &[PathBuf], trim: bool) -> Result<Vec<SourceImage>, String> {
+    let mut source_images = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let image = Image::load_png(path)?;
+
+        let kept_rect = if trim {
+            image.get_opaque_bounds()
+        } else {
+            Rect::new(0, 0, image.get_width(), image.get_height())
+        };
+
+        source_images.push(SourceImage {
+            name: get_image_name(input_directory, path),
+            image,
+            kept_rect
+        });
+    }
+
+    Ok(source_images)
+}
+
+fn get_image_name(input_directory: &Path, path: &Path) -> String {
+    let relative_path = path.strip_prefix(input_directory).unwrap_or(path).with_extension("");
+
+    relative_path.components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+// Packs the images into as many pages as needed.
+pub fn pack(source_images: &[SourceImage], options: &Options) -> Result<Vec<Page>, String> {
+    let border = options.extrude * 2;
+
+    // LEARN: Packing order
+    // MaxRects packs tightest when the big images go first, so sort by longest side, then area.
+    // The name breaks ties, which makes the result the same on every run.
+    let mut order: Vec<usize> = (0..source_images.len()).collect();
+    order.sort_by(|&a, &b| {
+        let rect_a = source_images[a].kept_rect;
+        let rect_b = source_images[b].kept_rect;
+
+        rect_b.width.max(rect_b.height).cmp(&rect_a.width.max(rect_a.height))
+            .then((rect_b.width * rect_b.height).cmp(&(rect_a.width * rect_a.height)))
+            .then(source_images[a].name.cmp(&source_images[b].name))
+    });
+
+    // Each image reserves room for its padding on the right and bottom.
+    // The bin is grown by the padding, so an image touching the page edge doesn't need padding it will never use.
+    let bin_size = options.max_size + options.padding;
+    let mut bins: Vec<(MaxRects, Vec<Placement>)> = Vec::new();
+
+    for source_index in order {
+        let source_image = &source_images[source_index];
+        let width = source_image.kept_rect.width + border + options.padding;
+        let height = source_image.kept_rect.height + border + options.padding;
+
+        if width > bin_size || height > bin_size {
+            return Err(format!("{} ({}x{} with extrusion) doesn't fit on a {}x{} page",
+                source_image.name, width - options.padding, height - options.padding, options.max_size, options.max_size));
+        }
+
+        let mut placed = false;
+
+        for (packer, placements) in bins.iter_mut() {
+            if let Some(rect) = packer.insert(width, height) {
+                placements.push(Placement { source_index, x: rect.x + options.extrude, y: rect.y + options.extrude });
+                placed = true;
+                break;
+            }
+        }
+
+        if !placed {
+            let mut packer = MaxRects::new(bin_size, bin_size);
+            let rect = packer.insert(width, height).expect("An image that fits the page size must fit an empty page");
+            bins.push((packer, vec![Placement { source_index, x: rect.x + options.extrude, y: rect.y + options.extrude }]));
+        }
+    }
+
+    let mut pages = Vec::with_capacity(bins.len());
+
+    for (page_index, (packer, mut placements)) in bins.into_iter().enumerate() {
+        let (used_width, used_height) = packer.get_used_size();
+        let mut width = used_width.saturating_sub(options.padding).min(options.max_size);
+        let mut height = used_height.saturating_sub(options.padding).min(options.max_size);
+
+        if options.power_of_two {
+            width = width.next_power_of_two();
+            height = height.next_power_of_two();
+        }
+
+        // Frames are listed by name, no matter where they were packed
+        placements.sort_by(|a, b| source_images[a.source_index].name.cmp(&source_images[b.source_index].name));
+
+        let mut image = Image::new(width, height);
+        let mut frames = Vec::with_capacity(placements.len());
+
+        for placement in &placements {
+            let source_image = &source_images[placement.source_index];
+            let kept_rect = source_image.kept_rect;
+
+            image.blit(&source_image.image, kept_rect, placement.x, placement.y, options.extrude);
+
+            frames.push(JsonFrame {
+                filename: source_image.name.clone(),
+                frame: JsonRect { x: placement.x, y: placement.y, w: kept_rect.width, h: kept_rect.height },
+                rotated: false,
+                trimmed: kept_rect.width != source_image.image.get_width() || kept_rect.height != source_image.image.get_height(),
+                sprite_source_size: JsonRect { x: kept_rect.x, y: kept_rect.y, w: kept_rect.width, h: kept_rect.height },
+                source_size: JsonSize { w: source_image.image.get_width(), h: source_image.image.get_height() }
+            });
+        }
+
+        pages.push(Page {
+            image,
+            metadata: JsonAtlas {
+                frames,
+                meta: JsonMeta::new(get_page_file_name(&options.name, page_index, "png"), width, height)
+            }
+        });
+    }
+
+    Ok(pages)
+}
+
+pub fn get_page_file_name(name: &str, page_index: usize, extension: &str) -> String {
+    format!("{}-{}.{}", name, page_index, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(max_size: u32, padding: u32, extrude: u32) -> Options {
+        Options {
+            input_directory: PathBuf::from("input"),
+            output_directory: PathBuf::from("output"),
+            name: String::from("atlas"),
+            max_size,
+            padding,
+            extrude,
+            trim: false,
+            power_of_two: false
+        }
+    }
+
+    // An image filled with a color made from "seed", with a darker top left pixel to catch flipped copies.
+    fn solid_image(width: u32, height: u32, seed: u32) -> Image {
+        let mut image = Image::new(width, height);
+        let color = [(seed * 40 % 256) as u8, (seed * 90 % 256) as u8, (seed * 150 % 256) as u8, 255];
+
+        for y in 0..height {
+            for x in 0..width {
+                image.set_pixel(x, y, color);
+            }
+        }
+
+        image.set_pixel(0, 0, [color[0] / 2, color[1] / 2, color[2] / 2, 255]);
+        image
+    }
+
+    fn source_image(name: &str, image: Image, trim: bool) -> SourceImage {
+        let kept_rect = if trim { image.get_opaque_bounds() } else { Rect::new(0, 0, image.get_width(), image.get_height()) };
+        SourceImage { name: String::from(name), image, kept_rect }
+    }
+
+    fn random_images(count: u32) -> Vec<SourceImage> {
+        let mut state: u32 = 12345;
+        let mut next = |max: u32| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            1 + (state >> 16) % max
+        };
+
+        (0..count)
+            .map(|index| {
+                let (width, height) = (next(24), next(24));
+                source_image(&format!("image_{:02}", index), solid_image(width, height, index), false)
+            })
+            .collect()
+    }
+
+    fn find_frame<'a>(pages: &'a [Page], name: &str) -> (&'a Page, &'a JsonFrame) {
+        pages.iter()
+            .find_map(|page| page.metadata.frames.iter().find(|frame| frame.filename == name).map(|frame| (page, frame)))
+            .unwrap()
+    }
+
+    #[test]
+    fn every_image_is_placed_once_without_overlaps() {
+        let source_images = random_images(40);
+        let options = options(64, 2, 1);
+        let pages = pack(&source_images, &options).unwrap();
+        assert!(pages.len() > 1);
+
+        let mut names: Vec<&str> = pages.iter().flat_map(|page| page.metadata.frames.iter().map(|frame| frame.filename.as_str())).collect();
+        names.sort();
+        let expected: Vec<&str> = source_images.iter().map(|source_image| source_image.name.as_str()).collect();
+        assert_eq!(names, expected);
+
+        for page in &pages {
+            assert!(page.image.get_width() <= 64 && page.image.get_height() <= 64);
+
+            // Each frame with its extrusion, plus the padding on the right and bottom
+            let reserved: Vec<Rect> = page.metadata.frames.iter()
+                .map(|frame| Rect::new(frame.frame.x - 1, frame.frame.y - 1, frame.frame.w + 2 + 2, frame.frame.h + 2 + 2))
+                .collect();
+
+            for (index, rect) in reserved.iter().enumerate() {
+                assert!(rect.x + rect.width - 2 <= page.image.get_width() && rect.y + rect.height - 2 <= page.image.get_height());
+                assert!(reserved[index + 1..].iter().all(|other| !other.intersects(rect)), "{:?} overlaps another frame", rect);
+            }
+        }
+
+        for source_image in &source_images {
+            let (page, frame) = find_frame(&pages, &source_image.name);
+            assert_eq!((frame.frame.w, frame.frame.h), (source_image.image.get_width(), source_image.image.get_height()));
+
+            for y in 0..frame.frame.h {
+                for x in 0..frame.frame.w {
+                    assert_eq!(page.image.get_pixel(frame.frame.x + x, frame.frame.y + y), source_image.image.get_pixel(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn images_bigger_than_a_page_are_reported() {
+        let source_images = vec![source_image("small", solid_image(4, 4, 1), false), source_image("huge", solid_image(40, 10, 2), false)];
+
+        let error = pack(&source_images, &options(32, 2, 1)).err().unwrap();
+        assert!(error.contains("huge"), "{}", error);
+
+        // Extrusion counts towards the size, padding at the page edge doesn't
+        assert!(pack(&[source_image("exact", solid_image(30, 30, 3), false)], &options(32, 5, 1)).is_ok());
+        assert!(pack(&[source_image("too_big", solid_image(31, 30, 3), false)], &options(32, 0, 1)).is_err());
+    }
+
+    #[test]
+    fn padding_separates_images() {
+        let source_images = vec![source_image("a", solid_image(10, 10, 1), false), source_image("b", solid_image(10, 10, 2), false)];
+        let pages = pack(&source_images, &options(64, 3, 0)).unwrap();
+        assert_eq!(pages.len(), 1);
+
+        // Same size, so the name decides: "a" goes first, "b" right next to it plus the padding
+        let (_, a) = find_frame(&pages, "a");
+        let (_, b) = find_frame(&pages, "b");
+        assert_eq!((a.frame.x, a.frame.y), (0, 0));
+        assert!((b.frame.x, b.frame.y) == (13, 0) || (b.frame.x, b.frame.y) == (0, 13), "b is at {}, {}", b.frame.x, b.frame.y);
+
+        // The page ends at the last image, without trailing padding
+        let page = &pages[0];
+        assert_eq!(page.image.get_width().max(page.image.get_height()), 23);
+        assert_eq!(page.image.get_width().min(page.image.get_height()), 10);
+    }
+
+    #[test]
+    fn extrusion_repeats_the_edges() {
+        let mut image = solid_image(3, 2, 4);
+        image.set_pixel(2, 1, [1, 2, 3, 255]);
+        let pages = pack(&[source_image("a", image, false)], &options(64, 0, 2)).unwrap();
+
+        let page = &pages[0];
+        let (_, frame) = find_frame(&pages, "a");
+        assert_eq!((frame.frame.x, frame.frame.y), (2, 2));
+        assert_eq!((page.image.get_width(), page.image.get_height()), (7, 6));
+
+        let top_left = page.image.get_pixel(2, 2);
+        assert_eq!(page.image.get_pixel(0, 0), top_left);
+        assert_eq!(page.image.get_pixel(1, 2), top_left);
+        assert_eq!(page.image.get_pixel(6, 5), [1, 2, 3, 255]);
+        assert_eq!(page.image.get_pixel(4, 5), [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn trimming() {
+        let mut image = Image::new(8, 8);
+        for y in 2..5 {
+            for x in 3..5 {
+                image.set_pixel(x, y, [9, 9, 9, 255]);
+            }
+        }
+
+        let source_images = vec![source_image("trimmed", image, true), source_image("solid", solid_image(4, 4, 1), true)];
+        let pages = pack(&source_images, &options(64, 0, 0)).unwrap();
+
+        let (page, trimmed) = find_frame(&pages, "trimmed");
+        assert!(trimmed.trimmed);
+        assert_eq!((trimmed.frame.w, trimmed.frame.h), (2, 3));
+        assert_eq!((trimmed.sprite_source_size.x, trimmed.sprite_source_size.y, trimmed.sprite_source_size.w, trimmed.sprite_source_size.h), (3, 2, 2, 3));
+        assert_eq!((trimmed.source_size.w, trimmed.source_size.h), (8, 8));
+        assert_eq!(page.image.get_pixel(trimmed.frame.x, trimmed.frame.y), [9, 9, 9, 255]);
+
+        let (_, solid) = find_frame(&pages, "solid");
+        assert!(!solid.trimmed);
+        assert_eq!((solid.frame.w, solid.frame.h), (4, 4));
+    }
+
+    #[test]
+    fn power_of_two_sizes() {
+        let source_images = random_images(12);
+
+        let mut pot_options = options(128, 2, 1);
+        pot_options.power_of_two = true;
+
+        let tight = pack(&source_images, &options(128, 2, 1)).unwrap();
+        let pot = pack(&source_images, &pot_options).unwrap();
+        assert_eq!(tight.len(), pot.len());
+
+        for (tight_page, pot_page) in tight.iter().zip(&pot) {
+            let (width, height) = (pot_page.image.get_width(), pot_page.image.get_height());
+            assert!(width.is_power_of_two() && height.is_power_of_two());
+            assert_eq!(width, tight_page.image.get_width().next_power_of_two());
+            assert_eq!(height, tight_page.image.get_height().next_power_of_two());
+            assert_eq!((pot_page.metadata.meta.size.w, pot_page.metadata.meta.size.h), (width, height));
+        }
+    }
+
+    // Writes the images as PNGs, runs the whole pipeline and returns the bytes of every output file.
+    fn pack_directory(directory: &Path, options: &Options) -> Vec<Vec<u8>> {
+        let paths = find_images(directory).unwrap();
+        let source_images = load_source_images(directory, &paths, options.trim).unwrap();
+        let pages = pack(&source_images, options).unwrap();
+
+        let mut output = Vec::new();
+        for (page_index, page) in pages.iter().enumerate() {
+            let image_path = directory.join(get_page_file_name("output", page_index, "png.out"));
+            page.image.save_png(&image_path).unwrap();
+            output.push(std::fs::read(&image_path).unwrap());
+            std::fs::remove_file(&image_path).unwrap();
+
+            output.push(serde_json::to_vec_pretty(&page.metadata).unwrap());
+        }
+
+        output
+    }
+
+    #[test]
+    fn same_input_gives_byte_identical_output() {
+        let directory = std::env::temp_dir().join(format!("beagle-pack-test-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("sub")).unwrap();
+
+        for (index, source_image) in random_images(20).iter().enumerate() {
+            let sub_directory = if index % 3 == 0 { "sub/" } else { "" };
+            source_image.image.save_png(&directory.join(format!("{}{}.png", sub_directory, source_image.name))).unwrap();
+        }
+
+        let mut options = options(64, 2, 1);
+        options.trim = true;
+
+        let names: Vec<String> = find_images(&directory).unwrap().iter().map(|path| get_image_name(&directory, path)).collect();
+        assert!(names.contains(&String::from("sub/image_00")));
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let first = pack_directory(&directory, &options);
+        let second = pack_directory(&directory, &options);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(first.len() >= 2);
+        assert!(first == second, "Two runs on the same input produced different files");
+    }
+
+    #[test]
+    fn input_order_does_not_matter() {
+        let mut source_images = random_images(15);
+        let options = options(64, 1, 1);
+        let first = pack(&source_images, &options).unwrap();
+
+        source_images.reverse();
+        let second = pack(&source_images, &options).unwrap();
+
+        assert_eq!(first.len(), second.len());
+        for (first_page, second_page) in first.iter().zip(&second) {
+            assert_eq!(serde_json::to_string(&first_page.metadata).unwrap(), serde_json::to_string(&second_page.metadata).unwrap());
+        }
+    }
+}